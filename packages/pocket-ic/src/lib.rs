@@ -2,8 +2,14 @@ use std::path::PathBuf;
 use std::process::{Child, Command};
 use std::time::Duration;
 
+const BACKEND_URL: &str = "http://0.0.0.0:3000";
+
+/// Identifies a `StateMachine` instance in the backend.
+pub type InstanceId = u64;
+
 pub struct PocketIC {
     process: Child,
+    client: reqwest::blocking::Client,
 }
 
 impl PocketIC {
@@ -14,21 +20,85 @@ impl PocketIC {
         let process = command
             .spawn()
             .expect("Failed to launch pocketIC backend process.");
-        retry(|| reqwest::blocking::get("http://0.0.0.0:3000/"), 10, 500);
+        retry(|| reqwest::blocking::get(BACKEND_URL), 10, 500);
         println!(
             "Launched pocketIC backend process with pid {}",
             process.id()
         );
-        Self { process }
+        Self {
+            process,
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// Creates a new, isolated `StateMachine` instance in the backend and
+    /// returns its ID.
+    pub fn create_instance(&self) -> InstanceId {
+        self.client
+            .post(self.url("instances"))
+            .json(&serde_json::json!({}))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .expect("Failed to create instance.")
+            .json()
+            .expect("Failed to parse instance id.")
+    }
+
+    /// Creates a new instance from the checkpoint with the given name and
+    /// returns its ID.
+    pub fn create_instance_from_checkpoint(&self, name: &str) -> InstanceId {
+        self.client
+            .post(self.url("instances"))
+            .json(&serde_json::json!({ "from_checkpoint": name }))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .expect("Failed to create instance from checkpoint.")
+            .json()
+            .expect("Failed to parse instance id.")
+    }
+
+    /// Saves the state of the given instance as a checkpoint with the given
+    /// name.
+    pub fn save_checkpoint(&self, instance_id: InstanceId, name: &str) {
+        self.client
+            .post(self.url(&format!("instances/{}/checkpoint", instance_id)))
+            .json(&serde_json::json!({ "name": name }))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .expect("Failed to save checkpoint.");
+    }
+
+    /// Returns the IDs of all instances in the backend.
+    pub fn list_instances(&self) -> Vec<InstanceId> {
+        self.client
+            .get(self.url("instances"))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .expect("Failed to list instances.")
+            .json()
+            .expect("Failed to parse instance ids.")
+    }
+
+    /// Deletes the instance with the given ID.
+    pub fn delete_instance(&self, instance_id: InstanceId) {
+        self.client
+            .delete(self.url(&format!("instances/{}", instance_id)))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .expect("Failed to delete instance.");
+    }
+
+    /// Executes a round on the given instance.
+    pub fn tick(&self, instance_id: InstanceId) {
+        self.client
+            .post(self.url(&format!("instances/{}/tick", instance_id)))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .expect("Failed to tick instance.");
     }
 
-    pub fn test_call(&self) {
-        let url = "http://0.0.0.0:3000/test?first=2&second=3";
-        let response = reqwest::blocking::get(url)
-            .expect("Failed to get response.")
-            .text()
-            .expect("Failed to get text");
-        println!("Response:\n{}", response);
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", BACKEND_URL, path)
     }
 }
 
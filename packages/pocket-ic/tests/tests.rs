@@ -4,6 +4,14 @@ use pocket_ic::PocketIC;
 fn calltest() {
     println!("test start");
     let pic = PocketIC::new("../../target/debug/pocket-ic-backend");
-    pic.test_call();
+    let instance_id = pic.create_instance();
+    assert_eq!(pic.list_instances(), vec![instance_id]);
+    pic.tick(instance_id);
+    pic.save_checkpoint(instance_id, "ticked");
+    let forked_id = pic.create_instance_from_checkpoint("ticked");
+    assert_eq!(pic.list_instances(), vec![instance_id, forked_id]);
+    pic.delete_instance(instance_id);
+    pic.delete_instance(forked_id);
+    assert!(pic.list_instances().is_empty());
     println!("test end");
 }
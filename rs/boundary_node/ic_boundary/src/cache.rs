@@ -0,0 +1,174 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header::CONTENT_TYPE, HeaderValue, Response, StatusCode},
+};
+use candid::Principal;
+use ic_types::messages::HttpQueryResponse;
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::{
+    metrics::CacheMetrics,
+    routes::{RequestContext, RequestType},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub canister_id: Principal,
+    pub method_name: String,
+    pub arg_hash: [u8; 32],
+}
+
+impl CacheKey {
+    // Only anonymous queries are cacheable, since their responses do not depend on the caller
+    pub fn from_request(ctx: &RequestContext) -> Option<Self> {
+        if ctx.request_type != RequestType::Query || ctx.sender != Principal::anonymous() {
+            return None;
+        }
+
+        Some(Self {
+            canister_id: ctx.canister_id,
+            method_name: ctx.method_name.clone()?,
+            arg_hash: Sha256::digest(ctx.arg.as_ref()?).into(),
+        })
+    }
+}
+
+struct CacheEntry {
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+    expires_at: Instant,
+}
+
+impl CacheEntry {
+    fn to_response(&self) -> Response<Body> {
+        let mut builder = Response::builder().status(StatusCode::OK);
+        if let Some(content_type) = &self.content_type {
+            builder = builder.header(CONTENT_TYPE, content_type);
+        }
+
+        builder.body(Body::from(self.body.clone())).unwrap()
+    }
+}
+
+struct CacheState {
+    entries: LruCache<CacheKey, CacheEntry>,
+    size: usize,
+}
+
+impl CacheState {
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.pop(key) {
+            self.size -= entry.body.len();
+        }
+    }
+}
+
+// Bounded cache of query responses
+// The total size of cached bodies is kept below `max_size`, evicting the least recently used entries
+pub struct Cache {
+    state: Mutex<CacheState>,
+    ttl: Duration,
+    max_size: usize,
+    max_item_size: usize,
+    metrics: CacheMetrics,
+}
+
+impl Cache {
+    pub fn new(
+        ttl: Duration,
+        max_size: usize,
+        max_item_size: usize,
+        metrics: CacheMetrics,
+    ) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                entries: LruCache::unbounded(),
+                size: 0,
+            }),
+            ttl,
+            max_size,
+            max_item_size: max_item_size.min(max_size),
+            metrics,
+        }
+    }
+
+    pub fn get(&self, key: &CacheKey, now: Instant) -> Option<Response<Body>> {
+        let mut state = self.state.lock().unwrap();
+
+        let response = match state.entries.get(key) {
+            Some(entry) if entry.expires_at > now => Some(entry.to_response()),
+
+            // Drop expired entries right away
+            Some(_) => {
+                state.remove(key);
+                None
+            }
+
+            None => None,
+        };
+
+        self.metrics.record_lookup(response.is_some());
+        response
+    }
+
+    // Store the response if it is a successful reply and return it back
+    pub async fn insert(
+        &self,
+        key: CacheKey,
+        response: Response<Body>,
+        now: Instant,
+    ) -> Response<Body> {
+        if response.status() != StatusCode::OK {
+            return response;
+        }
+
+        let (parts, body) = response.into_parts();
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(error = %e, "failed to read response body");
+                return Response::from_parts(parts, Body::empty());
+            }
+        };
+
+        // Do not cache rejects, they might be transient
+        let is_reply = matches!(
+            serde_cbor::from_slice(&body),
+            Ok(HttpQueryResponse::Replied { .. })
+        );
+
+        if is_reply && body.len() <= self.max_item_size {
+            let mut state = self.state.lock().unwrap();
+
+            state.remove(&key);
+            while state.size + body.len() > self.max_size {
+                match state.entries.pop_lru() {
+                    Some((_, entry)) => state.size -= entry.body.len(),
+                    None => break,
+                }
+            }
+
+            state.size += body.len();
+            state.entries.put(
+                key,
+                CacheEntry {
+                    content_type: parts.headers.get(CONTENT_TYPE).cloned(),
+                    body: body.clone(),
+                    expires_at: now + self.ttl,
+                },
+            );
+        }
+
+        Response::from_parts(parts, Body::from(body))
+    }
+}
+
+#[cfg(test)]
+mod test;
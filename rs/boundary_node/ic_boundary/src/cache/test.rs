@@ -0,0 +1,198 @@
+use anyhow::Error;
+use ic_types::messages::{Blob, HttpQueryResponseReply};
+use prometheus::Registry;
+
+use super::*;
+
+impl Cache {
+    // Total size of cached bodies and number of entries
+    // Used only in tests
+    pub fn usage(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+        (state.size, state.entries.len())
+    }
+}
+
+fn ctx(request_type: RequestType, sender: Principal, arg: &[u8]) -> RequestContext {
+    RequestContext {
+        request_type,
+        canister_id: Principal::from_text("qoctq-giaaa-aaaaa-aaaea-cai").unwrap(),
+        sender,
+        method_name: Some("greet".to_string()),
+        arg: Some(arg.to_vec()),
+    }
+}
+
+fn key(arg: &[u8]) -> CacheKey {
+    CacheKey::from_request(&ctx(RequestType::Query, Principal::anonymous(), arg)).unwrap()
+}
+
+fn reply(arg: Vec<u8>) -> Response<Body> {
+    let body = serde_cbor::to_vec(&HttpQueryResponse::Replied {
+        reply: HttpQueryResponseReply { arg: Blob(arg) },
+    })
+    .unwrap();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/cbor")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn reject() -> Response<Body> {
+    let body = serde_cbor::to_vec(&HttpQueryResponse::Rejected {
+        error_code: "IC0503".to_string(),
+        reject_code: 5,
+        reject_message: "trapped".to_string(),
+    })
+    .unwrap();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+async fn body(response: Response<Body>) -> Bytes {
+    hyper::body::to_bytes(response.into_body()).await.unwrap()
+}
+
+#[test]
+fn test_cache_key() -> Result<(), Error> {
+    let sender = Principal::from_slice(&[1, 2, 3]);
+
+    assert!(
+        CacheKey::from_request(&ctx(RequestType::Query, Principal::anonymous(), b"")).is_some()
+    );
+    assert!(CacheKey::from_request(&ctx(RequestType::Query, sender, b"")).is_none());
+    assert!(CacheKey::from_request(&ctx(RequestType::Call, Principal::anonymous(), b"")).is_none());
+
+    assert_eq!(key(b"foo"), key(b"foo"));
+    assert_ne!(key(b"foo"), key(b"bar"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_hit_miss() -> Result<(), Error> {
+    let registry = Registry::new();
+    let cache = Cache::new(
+        Duration::from_secs(10),
+        1024 * 1024,
+        1024,
+        CacheMetrics::new(&registry)?,
+    );
+
+    let now = Instant::now();
+
+    assert!(cache.get(&key(b"foo"), now).is_none());
+
+    // The response is passed through unchanged
+    let response = cache.insert(key(b"foo"), reply(vec![1, 2, 3]), now).await;
+    let expected = body(reply(vec![1, 2, 3])).await;
+    assert_eq!(body(response).await, expected);
+
+    let response = cache.get(&key(b"foo"), now).unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    assert_eq!(body(response).await, expected);
+
+    assert!(cache.get(&key(b"bar"), now).is_none());
+
+    // Entries expire after the TTL
+    assert!(cache
+        .get(&key(b"foo"), now + Duration::from_secs(10))
+        .is_none());
+    assert_eq!(cache.usage(), (0, 0));
+
+    // Lookups are counted
+    let metrics = registry.gather();
+    let counters = metrics
+        .iter()
+        .find(|x| x.get_name() == "cache_lookups_total")
+        .unwrap()
+        .get_metric();
+
+    for counter in counters {
+        let status = counter.get_label()[0].get_value();
+        let expected = if status == "hit" { 1.0 } else { 3.0 };
+        assert_eq!(counter.get_counter().get_value(), expected);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_only_replies() -> Result<(), Error> {
+    let cache = Cache::new(
+        Duration::from_secs(10),
+        1024 * 1024,
+        1024,
+        CacheMetrics::new(&Registry::new())?,
+    );
+
+    let now = Instant::now();
+
+    // Rejects are not cached
+    cache.insert(key(b"foo"), reject(), now).await;
+    assert!(cache.get(&key(b"foo"), now).is_none());
+
+    // Errors are not cached
+    let response = Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .body(Body::empty())
+        .unwrap();
+    let response = cache.insert(key(b"foo"), response, now).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(cache.get(&key(b"foo"), now).is_none());
+
+    // Responses larger than the item size limit are not cached
+    cache.insert(key(b"foo"), reply(vec![0; 2048]), now).await;
+    assert!(cache.get(&key(b"foo"), now).is_none());
+
+    assert_eq!(cache.usage(), (0, 0));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_eviction() -> Result<(), Error> {
+    let item_size = body(reply(vec![0; 100])).await.len();
+
+    // Room for exactly 3 items
+    let cache = Cache::new(
+        Duration::from_secs(10),
+        3 * item_size,
+        1024,
+        CacheMetrics::new(&Registry::new())?,
+    );
+
+    let now = Instant::now();
+
+    for arg in [b"1", b"2", b"3"] {
+        cache.insert(key(arg), reply(vec![0; 100]), now).await;
+    }
+    assert_eq!(cache.usage(), (3 * item_size, 3));
+
+    // Make 1 the most recently used one
+    assert!(cache.get(&key(b"1"), now).is_some());
+
+    // 2 is the least recently used one and gets evicted
+    cache.insert(key(b"4"), reply(vec![0; 100]), now).await;
+    assert_eq!(cache.usage(), (3 * item_size, 3));
+
+    assert!(cache.get(&key(b"1"), now).is_some());
+    assert!(cache.get(&key(b"2"), now).is_none());
+    assert!(cache.get(&key(b"3"), now).is_some());
+    assert!(cache.get(&key(b"4"), now).is_some());
+
+    // Replacing an entry does not count twice
+    cache.insert(key(b"4"), reply(vec![0; 100]), now).await;
+    assert_eq!(cache.usage(), (3 * item_size, 3));
+
+    Ok(())
+}
@@ -87,8 +87,12 @@ pub struct Runner<'a, P: Persist, C: Check> {
     checker: C,
 }
 
-// Sort given node check results by score and emit nodes
-fn nodes_sort_by_score(mut nodes: Vec<NodeCheckResult>) -> Vec<Node> {
+// Sort given node check results by height lag and score and emit nodes
+// Nodes with the lowest lag behind the highest node in the subnet come first,
+// nodes with the same lag are ordered by their score
+fn nodes_sort_by_lag_and_score(mut nodes: Vec<NodeCheckResult>) -> Vec<Node> {
+    let height_max = nodes.iter().map(|x| x.height).max().unwrap_or(0);
+
     // Calculate min/max latencies
     let latencies = nodes.iter().map(|x| x.average_latency).collect::<Vec<_>>();
     let latency_min = latencies
@@ -105,8 +109,13 @@ fn nodes_sort_by_score(mut nodes: Vec<NodeCheckResult>) -> Vec<Node> {
 
     // Calculate the score as latency/success_rate - the lower the latency and higher success rate - the lower (better) is score
     // Score is mapped to 0..1000 range
-    // Sort nodes by score in ascending order
-    nodes.sort_unstable_by_key(|x| ((x.average_latency / x.success_rate) * 1000.0) as u32);
+    // Sort nodes by lag and then by score in ascending order
+    nodes.sort_unstable_by_key(|x| {
+        (
+            height_max - x.height,
+            ((x.average_latency / x.success_rate) * 1000.0) as u32,
+        )
+    });
     nodes.into_iter().map(|x| x.node).collect()
 }
 
@@ -258,7 +267,7 @@ impl<'a, P: Persist, C: Check> Runner<'a, P, C> {
             .filter(|x| x.ok_count >= self.min_ok_count) // Filter below min_ok_count
             .collect::<Vec<_>>();
 
-        let nodes = nodes_sort_by_score(nodes);
+        let nodes = nodes_sort_by_lag_and_score(nodes);
         Subnet { nodes, ..subnet }
     }
 }
@@ -307,3 +307,41 @@ async fn test_check_node_upgrade() -> Result<(), Error> {
 
     Ok(())
 }
+
+// Ensure that nodes with the lowest height lag come first in the resulting lookup table
+#[tokio::test(flavor = "multi_thread")]
+async fn test_check_nodes_sorted_by_lag() -> Result<(), Error> {
+    let routes: ArcSwapOption<Routes> = ArcSwapOption::const_empty();
+    let persist = Persister::new(&routes);
+    let routing_table = ArcSwapOption::from_pointee(generate_custom_routing_table(1, 3, 0));
+
+    let mut check = MockCheck::new();
+
+    check
+        .expect_check()
+        .withf(|x: &Node| x.id == node_id(0))
+        .times(1)
+        .returning(|_| Ok(check_result(1000, 0)));
+
+    check
+        .expect_check()
+        .withf(|x: &Node| x.id == node_id(1))
+        .times(1)
+        .returning(|_| Ok(check_result(1005, 10)));
+
+    check
+        .expect_check()
+        .withf(|x: &Node| x.id == node_id(2))
+        .times(1)
+        .returning(|_| Ok(check_result(1002, 0)));
+
+    let mut check_runner = Runner::new(&routing_table, 1, 10, persist, check);
+    check_runner.run().await.expect("run should succeed");
+
+    let rt = routes.load_full().unwrap();
+    let node_ids = rt.subnets[0].nodes.iter().map(|x| x.id).collect::<Vec<_>>();
+
+    assert_eq!(node_ids, vec![node_id(1), node_id(2), node_id(0)]);
+
+    Ok(())
+}
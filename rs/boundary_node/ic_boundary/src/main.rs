@@ -36,21 +36,26 @@ use url::Url;
 
 use crate::{
     acme::Acme,
+    cache::Cache,
     check::{Checker, Runner as CheckRunner},
     configuration::{Configurator, FirewallConfigurator, TlsConfigurator, WithDeduplication},
-    metrics::{MetricParams, WithMetrics},
+    metrics::{CacheMetrics, MetricParams, RateLimitMetrics, WithMetrics},
     nns::Loader,
+    rate_limit::{RateLimiter, Runner as RateLimitRunner},
+    routes::{ProxyRouter, RequestRouter},
     snapshot::{DnsResolver, Runner as SnapshotRunner, TlsVerifier},
     tls::{CustomAcceptor, Provisioner, TokenSetter, WithLoad, WithStore},
 };
 
 mod acme;
+mod cache;
 mod check;
 mod configuration;
 mod firewall;
 mod metrics;
 mod nns;
 mod persist;
+mod rate_limit;
 mod routes;
 mod snapshot;
 mod tls;
@@ -63,6 +68,7 @@ const DAY: Duration = Duration::from_secs(24 * 3600);
 
 static ROUTES: ArcSwapOption<persist::Routes> = ArcSwapOption::const_empty();
 static ROUTING_TABLE: ArcSwapOption<snapshot::RoutingTable> = ArcSwapOption::const_empty();
+static RATE_LIMITS: ArcSwapOption<rate_limit::RateLimits> = ArcSwapOption::const_empty();
 
 #[derive(Parser)]
 #[clap(name = SERVICE_NAME)]
@@ -137,6 +143,32 @@ struct Cli {
     #[clap(long, default_value = "pkey.pem")]
     tls_pkey_path: PathBuf,
 
+    /// How many times to retry a failed request on a different node
+    #[clap(long, default_value = "2")]
+    proxy_retries: usize,
+
+    /// The path to the rate limits file, which is reloaded periodically.
+    /// No rate limiting is done if not provided
+    #[clap(long)]
+    rate_limits_path: Option<PathBuf>,
+
+    /// How frequently to reload the rate limits file in seconds
+    #[clap(long, default_value = "10")]
+    rate_limits_reload_interval: u64,
+
+    /// Maximum total size of cached query responses in bytes.
+    /// Anonymous queries are not cached if not provided
+    #[clap(long)]
+    cache_size_bytes: Option<usize>,
+
+    /// Maximum size of a single cached query response in bytes
+    #[clap(long, default_value = "131072")]
+    cache_max_item_size_bytes: usize,
+
+    /// How long to keep query responses in the cache in seconds
+    #[clap(long, default_value = "1")]
+    cache_ttl: u64,
+
     /// The socket used to export metrics.
     #[clap(long, default_value = "127.0.0.1:9090")]
     metrics_addr: SocketAddr,
@@ -326,6 +358,30 @@ async fn main() -> Result<(), Error> {
     let acme_challenge = routes::acme_challenge;
     let acme_challenge = acme_challenge.layer(Extension(token));
 
+    let proxy_router = ProxyRouter::new(http_client.clone());
+    let proxy_router = WithMetrics(proxy_router, MetricParams::new(SERVICE_NAME, "proxy"));
+    let request_router = RequestRouter::new(
+        &ROUTES,                // published_routes
+        Arc::new(proxy_router), // proxy
+        cli.proxy_retries,      // retries
+    );
+
+    // Rate Limiting
+    let rate_limit_metrics =
+        RateLimitMetrics::new(metrics).context("failed to register rate limit metrics")?;
+    let rate_limiter = RateLimiter::new(&RATE_LIMITS, rate_limit_metrics);
+
+    // Caching
+    let cache = match cli.cache_size_bytes {
+        Some(cache_size_bytes) => Some(Arc::new(Cache::new(
+            Duration::from_secs(cli.cache_ttl),
+            cache_size_bytes,
+            cli.cache_max_item_size_bytes,
+            CacheMetrics::new(metrics).context("failed to register cache metrics")?,
+        ))),
+        None => None,
+    };
+
     let routers = (
         Router::new()
             .route("/.well-known/acme-challenge/:token", get(acme_challenge))
@@ -334,7 +390,10 @@ async fn main() -> Result<(), Error> {
             .route("/api/v2/status", get(routes::status))
             .route("/api/v2/canister/:id/query", post(routes::query))
             .route("/api/v2/canister/:id/call", post(routes::call))
-            .route("/api/v2/canister/:id/read_state", post(routes::read_state)),
+            .route("/api/v2/canister/:id/read_state", post(routes::read_state))
+            .layer(Extension(request_router))
+            .layer(Extension(rate_limiter))
+            .layer(Extension(cache)),
     );
 
     // HTTP
@@ -352,7 +411,12 @@ async fn main() -> Result<(), Error> {
         .map(|ip| {
             Server::bind(SocketAddr::new(ip, 443))
                 .acceptor(tls_acceptor.clone())
-                .serve(routers.1.clone().into_make_service())
+                .serve(
+                    routers
+                        .1
+                        .clone()
+                        .into_make_service_with_connect_info::<SocketAddr>(),
+                )
         });
 
     // Snapshots
@@ -392,14 +456,31 @@ async fn main() -> Result<(), Error> {
     );
     let mut check_runner = check_runner;
 
+    // Rate Limits
+    let rate_limit_runner = cli.rate_limits_path.map(|path| {
+        let rate_limit_runner = RateLimitRunner::new(&RATE_LIMITS, path);
+        let rate_limit_runner = WithMetrics(
+            rate_limit_runner,
+            MetricParams::new(SERVICE_NAME, "run_rate_limits"),
+        );
+        WithThrottle(
+            rate_limit_runner,
+            ThrottleParams::new(Duration::from_secs(cli.rate_limits_reload_interval)),
+        )
+    });
+
     // Runners
-    let runners: Vec<Box<dyn Run>> = vec![
+    let mut runners: Vec<Box<dyn Run>> = vec![
         // TODO FIXME Causes tokio stack overflow currently, fix & re-enable
         //Box::new(configuration_runner),
         Box::new(snapshot_runner),
         Box::new(check_runner),
     ];
 
+    if let Some(rate_limit_runner) = rate_limit_runner {
+        runners.push(Box::new(rate_limit_runner));
+    }
+
     TokioScope::scope_and_block(|s| {
         let metrics_handler = || metrics::handler(metrics);
         let metrics_router = Router::new().route("/metrics", get(metrics_handler));
@@ -3,16 +3,19 @@ use std::time::Instant;
 use anyhow::{anyhow, Error};
 use async_trait::async_trait;
 use axum::{
-    body::Body,
+    body::{Body, Bytes},
     http::{Response, StatusCode},
 };
+use candid::Principal;
 use opentelemetry::{baggage::BaggageExt, trace::FutureExt, Context, KeyValue};
-use prometheus::{Encoder, Registry, TextEncoder};
+use prometheus::{Encoder, IntCounterVec, Opts, Registry, TextEncoder};
 use tracing::{error, info, warn};
 
 use crate::{
     check::{Check, CheckError, CheckResult},
     persist::{Persist, PersistResults, PersistStatus},
+    rate_limit::RateLimitCause,
+    routes::{ErrorCause, Proxy, RequestType},
     snapshot::{Node, RoutingTable},
 };
 
@@ -49,6 +52,55 @@ impl MetricParams {
     }
 }
 
+#[derive(Clone)]
+pub struct RateLimitMetrics {
+    rejected: IntCounterVec,
+}
+
+impl RateLimitMetrics {
+    pub fn new(registry: &Registry) -> Result<Self, Error> {
+        let rejected = IntCounterVec::new(
+            Opts::new(
+                "rate_limited_requests_total",
+                "Number of requests rejected by the rate limiter",
+            ),
+            &["limit"],
+        )?;
+        registry.register(Box::new(rejected.clone()))?;
+
+        Ok(Self { rejected })
+    }
+
+    pub fn record_rejected(&self, cause: &RateLimitCause) {
+        self.rejected.with_label_values(&[cause.short()]).inc();
+    }
+}
+
+#[derive(Clone)]
+pub struct CacheMetrics {
+    lookups: IntCounterVec,
+}
+
+impl CacheMetrics {
+    pub fn new(registry: &Registry) -> Result<Self, Error> {
+        let lookups = IntCounterVec::new(
+            Opts::new(
+                "cache_lookups_total",
+                "Number of response cache lookups by result",
+            ),
+            &["status"],
+        )?;
+        registry.register(Box::new(lookups.clone()))?;
+
+        Ok(Self { lookups })
+    }
+
+    pub fn record_lookup(&self, hit: bool) {
+        let status = if hit { "hit" } else { "miss" };
+        self.lookups.with_label_values(&[status]).inc();
+    }
+}
+
 #[async_trait]
 impl<T: Persist> Persist for WithMetrics<T> {
     async fn persist(&self, rt: RoutingTable) -> Result<PersistStatus, Error> {
@@ -106,3 +158,36 @@ impl<T: Check> Check for WithMetrics<T> {
         out
     }
 }
+
+#[async_trait]
+impl<T: Proxy> Proxy for WithMetrics<T> {
+    async fn proxy(
+        &self,
+        request_type: RequestType,
+        canister_id: Principal,
+        node: &Node,
+        body: Bytes,
+    ) -> Result<Response<Body>, ErrorCause> {
+        let start_time = Instant::now();
+        let out = self.0.proxy(request_type, canister_id, node, body).await;
+        let duration = start_time.elapsed().as_secs_f32();
+
+        let status = match &out {
+            Ok(r) => r.status().as_u16(),
+            Err(_) => 0,
+        };
+
+        info!(
+            action = self.1.action,
+            request_type = %request_type,
+            canister_id = %canister_id,
+            subnet_id = %node.subnet_id,
+            node_id = %node.id,
+            status,
+            duration,
+            error = ?out.as_ref().err(),
+        );
+
+        out
+    }
+}
@@ -0,0 +1,267 @@
+use std::{fmt, hash::Hash, net::IpAddr, path::PathBuf, sync::Arc, time::Instant};
+
+use anyhow::{Context, Error};
+use arc_swap::ArcSwapOption;
+use async_trait::async_trait;
+use candid::Principal;
+use dashmap::DashMap;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{metrics::RateLimitMetrics, Run};
+
+// Token bucket parameters
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Limit {
+    // How many tokens are added to the bucket per second
+    pub requests_per_second: f64,
+    // The capacity of the bucket, i.e. how many requests can be made in a burst
+    pub burst_size: u32,
+}
+
+// Limits as loaded from the rate limits file, a missing limit means no limiting
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    pub per_ip: Option<Limit>,
+    #[serde(default)]
+    pub per_sender: Option<Limit>,
+    #[serde(default)]
+    pub per_subnet: Option<Limit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitCause {
+    Ip,
+    Sender,
+    Subnet,
+}
+
+impl RateLimitCause {
+    pub fn short(&self) -> &str {
+        match self {
+            Self::Ip => "ip",
+            Self::Sender => "sender",
+            Self::Subnet => "subnet",
+        }
+    }
+}
+
+impl fmt::Display for RateLimitCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ip => write!(f, "Too many requests from this IP address"),
+            Self::Sender => write!(f, "Too many requests from this sender"),
+            Self::Subnet => write!(f, "Too many requests to this subnet"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &Limit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst_size as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * limit.requests_per_second)
+            .min(limit.burst_size as f64);
+        self.last_refill = now;
+    }
+
+    fn try_acquire(&mut self, limit: &Limit, now: Instant) -> bool {
+        self.refill(limit, now);
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+        true
+    }
+
+    fn is_full(&self, limit: &Limit) -> bool {
+        self.tokens >= limit.burst_size as f64
+    }
+}
+
+fn acquire<K: Hash + Eq>(
+    buckets: &DashMap<K, TokenBucket>,
+    key: K,
+    limit: &Option<Limit>,
+    now: Instant,
+) -> bool {
+    let limit = match limit {
+        Some(v) => v,
+        None => return true,
+    };
+
+    buckets
+        .entry(key)
+        .or_insert_with(|| TokenBucket::new(limit, now))
+        .try_acquire(limit, now)
+}
+
+// Drop the buckets which are full - they behave the same as new ones
+fn prune<K: Hash + Eq>(buckets: &DashMap<K, TokenBucket>, limit: &Option<Limit>, now: Instant) {
+    match limit {
+        None => buckets.clear(),
+        Some(limit) => buckets.retain(|_, b| {
+            b.refill(limit, now);
+            !b.is_full(limit)
+        }),
+    }
+}
+
+// The currently enforced limits together with the state of all buckets
+// A new instance (with empty buckets) is published whenever the config changes
+pub struct RateLimits {
+    pub config: RateLimitConfig,
+    per_ip: DashMap<IpAddr, TokenBucket>,
+    per_sender: DashMap<Principal, TokenBucket>,
+    per_subnet: DashMap<String, TokenBucket>,
+}
+
+impl RateLimits {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            per_ip: DashMap::new(),
+            per_sender: DashMap::new(),
+            per_subnet: DashMap::new(),
+        }
+    }
+
+    pub fn acquire_ip(&self, ip: IpAddr, now: Instant) -> Result<(), RateLimitCause> {
+        acquire(&self.per_ip, ip, &self.config.per_ip, now)
+            .then_some(())
+            .ok_or(RateLimitCause::Ip)
+    }
+
+    pub fn acquire_sender(&self, sender: Principal, now: Instant) -> Result<(), RateLimitCause> {
+        acquire(&self.per_sender, sender, &self.config.per_sender, now)
+            .then_some(())
+            .ok_or(RateLimitCause::Sender)
+    }
+
+    pub fn acquire_subnet(&self, subnet_id: &str, now: Instant) -> Result<(), RateLimitCause> {
+        acquire(
+            &self.per_subnet,
+            subnet_id.to_string(),
+            &self.config.per_subnet,
+            now,
+        )
+        .then_some(())
+        .ok_or(RateLimitCause::Subnet)
+    }
+
+    pub fn prune(&self, now: Instant) {
+        prune(&self.per_ip, &self.config.per_ip, now);
+        prune(&self.per_sender, &self.config.per_sender, now);
+        prune(&self.per_subnet, &self.config.per_subnet, now);
+    }
+}
+
+// Enforces the published rate limits and counts rejected requests
+#[derive(Clone)]
+pub struct RateLimiter {
+    published_limits: &'static ArcSwapOption<RateLimits>,
+    metrics: RateLimitMetrics,
+}
+
+impl RateLimiter {
+    pub fn new(
+        published_limits: &'static ArcSwapOption<RateLimits>,
+        metrics: RateLimitMetrics,
+    ) -> Self {
+        Self {
+            published_limits,
+            metrics,
+        }
+    }
+
+    fn check(
+        &self,
+        f: impl FnOnce(&RateLimits, Instant) -> Result<(), RateLimitCause>,
+    ) -> Result<(), RateLimitCause> {
+        // No limits published yet
+        let limits = match self.published_limits.load_full() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        let out = f(&limits, Instant::now());
+        if let Err(cause) = &out {
+            self.metrics.record_rejected(cause);
+        }
+
+        out
+    }
+
+    // Check the limits that are known before the request is routed
+    pub fn check_caller(&self, ip: IpAddr, sender: Principal) -> Result<(), RateLimitCause> {
+        self.check(|limits, now| {
+            limits.acquire_ip(ip, now)?;
+            limits.acquire_sender(sender, now)
+        })
+    }
+
+    pub fn check_subnet(&self, subnet_id: &str) -> Result<(), RateLimitCause> {
+        self.check(|limits, now| limits.acquire_subnet(subnet_id, now))
+    }
+}
+
+// Periodically reloads the rate limits file and publishes the limits if they have changed
+pub struct Runner<'a> {
+    published_limits: &'a ArcSwapOption<RateLimits>,
+    path: PathBuf,
+}
+
+impl<'a> Runner<'a> {
+    pub fn new(published_limits: &'a ArcSwapOption<RateLimits>, path: PathBuf) -> Self {
+        Self {
+            published_limits,
+            path,
+        }
+    }
+
+    fn load(&self) -> Result<RateLimitConfig, Error> {
+        let data = std::fs::read(&self.path).context("failed to read rate limits file")?;
+        serde_json::from_slice(&data).context("failed to parse rate limits file")
+    }
+}
+
+#[async_trait]
+impl<'a> Run for Runner<'a> {
+    async fn run(&mut self) -> Result<(), Error> {
+        let config = self.load()?;
+
+        match self.published_limits.load_full() {
+            // Keep the current buckets if nothing has changed
+            Some(limits) if limits.config == config => {
+                limits.prune(Instant::now());
+            }
+
+            _ => {
+                info!(msg = "Rate limits published", config = ?config);
+                self.published_limits
+                    .store(Some(Arc::new(RateLimits::new(config))));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,218 @@
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use prometheus::Registry;
+use tempfile::NamedTempFile;
+
+use super::*;
+
+impl RateLimits {
+    // Number of tracked buckets
+    // Used only in tests
+    pub fn bucket_count(&self) -> usize {
+        self.per_ip.len() + self.per_sender.len() + self.per_subnet.len()
+    }
+}
+
+const LIMIT: Limit = Limit {
+    requests_per_second: 2.0,
+    burst_size: 3,
+};
+
+fn sender(i: u8) -> Principal {
+    Principal::from_slice(&[i, 1])
+}
+
+fn write_config(f: &NamedTempFile, config: &str) -> Result<(), Error> {
+    std::fs::write(f.path(), config)?;
+    Ok(())
+}
+
+#[test]
+fn test_token_bucket() -> Result<(), Error> {
+    let limits = RateLimits::new(RateLimitConfig {
+        per_ip: Some(LIMIT),
+        per_sender: None,
+        per_subnet: None,
+    });
+
+    let now = Instant::now();
+    let ip1 = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+    let ip2 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+    // The whole burst is allowed
+    for _ in 0..3 {
+        assert_eq!(limits.acquire_ip(ip1, now), Ok(()));
+    }
+    assert_eq!(limits.acquire_ip(ip1, now), Err(RateLimitCause::Ip));
+
+    // Other IPs are not affected
+    assert_eq!(limits.acquire_ip(ip2, now), Ok(()));
+
+    // Tokens are refilled over time
+    let now = now + Duration::from_millis(500);
+    assert_eq!(limits.acquire_ip(ip1, now), Ok(()));
+    assert_eq!(limits.acquire_ip(ip1, now), Err(RateLimitCause::Ip));
+
+    // Refill does not exceed the burst size
+    let now = now + Duration::from_secs(60);
+    for _ in 0..3 {
+        assert_eq!(limits.acquire_ip(ip1, now), Ok(()));
+    }
+    assert_eq!(limits.acquire_ip(ip1, now), Err(RateLimitCause::Ip));
+
+    // No limits on senders and subnets
+    for _ in 0..10 {
+        assert_eq!(limits.acquire_sender(sender(1), now), Ok(()));
+        assert_eq!(limits.acquire_subnet("foo", now), Ok(()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_prune() -> Result<(), Error> {
+    let limits = RateLimits::new(RateLimitConfig {
+        per_ip: None,
+        per_sender: Some(LIMIT),
+        per_subnet: Some(LIMIT),
+    });
+
+    let now = Instant::now();
+
+    assert_eq!(limits.acquire_sender(sender(1), now), Ok(()));
+    for _ in 0..3 {
+        assert_eq!(limits.acquire_sender(sender(2), now), Ok(()));
+    }
+    assert_eq!(limits.acquire_subnet("foo", now), Ok(()));
+    assert_eq!(limits.bucket_count(), 3);
+
+    // Half a second later only the bucket of sender 2 is still not full
+    limits.prune(now + Duration::from_millis(500));
+    assert_eq!(limits.bucket_count(), 1);
+    assert_eq!(
+        limits.acquire_sender(sender(2), now + Duration::from_millis(500)),
+        Ok(())
+    );
+    assert_eq!(
+        limits.acquire_sender(sender(2), now + Duration::from_millis(500)),
+        Err(RateLimitCause::Sender)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_rate_limiter() -> Result<(), Error> {
+    let published_limits: &'static ArcSwapOption<RateLimits> =
+        Box::leak(Box::new(ArcSwapOption::const_empty()));
+
+    let registry = Registry::new();
+    let rate_limiter = RateLimiter::new(published_limits, RateLimitMetrics::new(&registry)?);
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+
+    // Nothing is limited until the limits are published
+    for _ in 0..10 {
+        assert_eq!(rate_limiter.check_caller(ip, sender(1)), Ok(()));
+    }
+
+    published_limits.store(Some(Arc::new(RateLimits::new(RateLimitConfig {
+        per_ip: None,
+        per_sender: Some(Limit {
+            requests_per_second: 0.0,
+            burst_size: 1,
+        }),
+        per_subnet: Some(Limit {
+            requests_per_second: 0.0,
+            burst_size: 0,
+        }),
+    }))));
+
+    assert_eq!(rate_limiter.check_caller(ip, sender(1)), Ok(()));
+    assert_eq!(
+        rate_limiter.check_caller(ip, sender(1)),
+        Err(RateLimitCause::Sender)
+    );
+    assert_eq!(rate_limiter.check_caller(ip, sender(2)), Ok(()));
+    assert_eq!(
+        rate_limiter.check_subnet("foo"),
+        Err(RateLimitCause::Subnet)
+    );
+
+    // Rejected requests are counted
+    let metrics = registry.gather();
+    let counters = metrics
+        .iter()
+        .find(|x| x.get_name() == "rate_limited_requests_total")
+        .unwrap()
+        .get_metric();
+    assert_eq!(counters.len(), 2);
+    for counter in counters {
+        assert_eq!(counter.get_counter().get_value(), 1.0);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_runner_reload() -> Result<(), Error> {
+    let published_limits = ArcSwapOption::const_empty();
+    let f = NamedTempFile::new()?;
+    let mut runner = Runner::new(&published_limits, f.path().to_path_buf());
+
+    // Invalid file
+    write_config(&f, r#"{"per_foo": {}}"#)?;
+    assert!(runner.run().await.is_err());
+    assert!(published_limits.load_full().is_none());
+
+    // Valid file
+    write_config(
+        &f,
+        r#"{"per_ip": {"requests_per_second": 2.0, "burst_size": 3}}"#,
+    )?;
+    runner.run().await?;
+
+    let limits = published_limits.load_full().unwrap();
+    assert_eq!(
+        limits.config,
+        RateLimitConfig {
+            per_ip: Some(LIMIT),
+            per_sender: None,
+            per_subnet: None,
+        }
+    );
+
+    // Buckets are kept when the file has not changed
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+    assert_eq!(limits.acquire_ip(ip, Instant::now()), Ok(()));
+    runner.run().await?;
+    assert!(Arc::ptr_eq(&limits, &published_limits.load_full().unwrap()));
+    assert_eq!(limits.bucket_count(), 1);
+
+    // New limits are published when the file changes
+    write_config(
+        &f,
+        r#"{"per_subnet": {"requests_per_second": 100.0, "burst_size": 100}}"#,
+    )?;
+    runner.run().await?;
+
+    let limits = published_limits.load_full().unwrap();
+    assert_eq!(limits.config.per_ip, None);
+    assert_eq!(
+        limits.config.per_subnet,
+        Some(Limit {
+            requests_per_second: 100.0,
+            burst_size: 100,
+        })
+    );
+    assert_eq!(limits.bucket_count(), 0);
+
+    // A broken file keeps the previous limits in place
+    write_config(&f, "foobar")?;
+    assert!(runner.run().await.is_err());
+    assert!(Arc::ptr_eq(&limits, &published_limits.load_full().unwrap()));
+
+    Ok(())
+}
@@ -1,13 +1,307 @@
-use std::sync::Arc;
+use std::{fmt, net::SocketAddr, sync::Arc, time::Instant};
 
+use arc_swap::ArcSwapOption;
+use async_trait::async_trait;
 use axum::{
-    body::Body,
-    extract::{Host, OriginalUri},
-    http::{uri::PathAndQuery, Response, Uri},
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Host, OriginalUri, Path},
+    http::{header::CONTENT_TYPE, uri::PathAndQuery, Response, StatusCode, Uri},
     response::{IntoResponse, Redirect},
     Extension,
 };
+use candid::Principal;
+use ic_types::messages::{
+    Blob, HttpCallContent, HttpQueryContent, HttpReadStateContent, HttpRequestEnvelope,
+};
+use mockall::automock;
+use serde::de::DeserializeOwned;
 use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::{
+    cache::{Cache, CacheKey},
+    persist::{RouteSubnet, Routes},
+    rate_limit::{RateLimitCause, RateLimiter},
+    snapshot::Node,
+};
+
+const CONTENT_TYPE_CBOR: &str = "application/cbor";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestType {
+    Query,
+    Call,
+    ReadState,
+}
+
+impl fmt::Display for RequestType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Query => write!(f, "query"),
+            Self::Call => write!(f, "call"),
+            Self::ReadState => write!(f, "read_state"),
+        }
+    }
+}
+
+// Fields of the request envelope that are relevant for routing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    pub request_type: RequestType,
+    pub canister_id: Principal,
+    pub sender: Principal,
+    pub method_name: Option<String>,
+    pub arg: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorCause {
+    MalformedRequest(String),    // Cannot parse the canister id or the envelope
+    CanisterNotFound,            // No subnet is responsible for the canister
+    NoRoutingTable,              // The routing table has not been published yet
+    NoHealthyNodes,              // The subnet has no healthy nodes
+    RateLimited(RateLimitCause), // The caller or the subnet is over its limit
+    ReplicaUnreachable(String),  // Unable to get a response from any node
+}
+
+impl ErrorCause {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::MalformedRequest(_) => StatusCode::BAD_REQUEST,
+            Self::CanisterNotFound => StatusCode::NOT_FOUND,
+            Self::NoRoutingTable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::NoHealthyNodes => StatusCode::SERVICE_UNAVAILABLE,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::ReplicaUnreachable(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MalformedRequest(e) => write!(f, "Malformed request: {e}"),
+            Self::CanisterNotFound => write!(f, "Canister not found"),
+            Self::NoRoutingTable => write!(f, "Routing table not available"),
+            Self::NoHealthyNodes => write!(f, "No healthy nodes available"),
+            Self::RateLimited(cause) => write!(f, "Rate limited: {cause}"),
+            Self::ReplicaUnreachable(e) => write!(f, "Replica unreachable: {e}"),
+        }
+    }
+}
+
+impl IntoResponse for ErrorCause {
+    fn into_response(self) -> axum::response::Response {
+        (self.status_code(), self.to_string()).into_response()
+    }
+}
+
+// Decodes the CBOR envelope of the request and extracts the routing-relevant fields
+pub fn decode_request(
+    request_type: RequestType,
+    canister_id: Principal,
+    body: &[u8],
+) -> Result<RequestContext, ErrorCause> {
+    fn decode<C: DeserializeOwned>(body: &[u8]) -> Result<C, ErrorCause> {
+        serde_cbor::from_slice::<HttpRequestEnvelope<C>>(body)
+            .map(|envelope| envelope.content)
+            .map_err(|e| ErrorCause::MalformedRequest(format!("unable to decode CBOR: {e}")))
+    }
+
+    fn principal(blob: &Blob, field: &str) -> Result<Principal, ErrorCause> {
+        Principal::try_from_slice(&blob.0)
+            .map_err(|e| ErrorCause::MalformedRequest(format!("invalid {field}: {e}")))
+    }
+
+    let (sender, target, method_name, arg) = match request_type {
+        RequestType::Query => {
+            let HttpQueryContent::Query { query } = decode(body)?;
+            (
+                query.sender,
+                Some(query.canister_id),
+                Some(query.method_name),
+                Some(query.arg.0),
+            )
+        }
+
+        RequestType::Call => {
+            let HttpCallContent::Call { update } = decode(body)?;
+            (
+                update.sender,
+                Some(update.canister_id),
+                Some(update.method_name),
+                Some(update.arg.0),
+            )
+        }
+
+        RequestType::ReadState => {
+            let HttpReadStateContent::ReadState { read_state } = decode(body)?;
+            (read_state.sender, None, None, None)
+        }
+    };
+
+    // The canister in the envelope must be the one the request is routed by
+    if let Some(target) = target {
+        if principal(&target, "canister_id")? != canister_id {
+            return Err(ErrorCause::MalformedRequest(
+                "canister_id in the envelope does not match the URL".into(),
+            ));
+        }
+    }
+
+    Ok(RequestContext {
+        request_type,
+        canister_id,
+        sender: principal(&sender, "sender")?,
+        method_name,
+        arg,
+    })
+}
+
+#[automock]
+#[async_trait]
+pub trait Proxy: Send + Sync {
+    async fn proxy(
+        &self,
+        request_type: RequestType,
+        canister_id: Principal,
+        node: &Node,
+        body: Bytes,
+    ) -> Result<Response<Body>, ErrorCause>;
+}
+
+pub struct ProxyRouter {
+    http_client: reqwest::Client,
+}
+
+impl ProxyRouter {
+    pub fn new(http_client: reqwest::Client) -> Self {
+        Self { http_client }
+    }
+}
+
+#[async_trait]
+impl Proxy for ProxyRouter {
+    async fn proxy(
+        &self,
+        request_type: RequestType,
+        canister_id: Principal,
+        node: &Node,
+        body: Bytes,
+    ) -> Result<Response<Body>, ErrorCause> {
+        // The node id is resolved to its address by the DNS resolver
+        // and its certificate is verified against the registry by the TLS verifier
+        let url = format!(
+            "https://{}:{}/api/v2/canister/{canister_id}/{request_type}",
+            node.id, node.port
+        );
+
+        let response = self
+            .http_client
+            .post(url)
+            .header(CONTENT_TYPE, CONTENT_TYPE_CBOR)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ErrorCause::ReplicaUnreachable(e.to_string()))?;
+
+        let mut builder = Response::builder().status(response.status());
+        if let Some(content_type) = response.headers().get(CONTENT_TYPE) {
+            builder = builder.header(CONTENT_TYPE, content_type);
+        }
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| ErrorCause::ReplicaUnreachable(e.to_string()))?;
+
+        Ok(builder.body(Body::from(body)).unwrap())
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestRouter {
+    published_routes: &'static ArcSwapOption<Routes>,
+    proxy: Arc<dyn Proxy>,
+    retries: usize,
+}
+
+impl RequestRouter {
+    pub fn new(
+        published_routes: &'static ArcSwapOption<Routes>,
+        proxy: Arc<dyn Proxy>,
+        retries: usize,
+    ) -> Self {
+        Self {
+            published_routes,
+            proxy,
+            retries,
+        }
+    }
+
+    // Look up the subnet responsible for the canister
+    pub fn lookup(&self, canister_id: &Principal) -> Result<Arc<RouteSubnet>, ErrorCause> {
+        let routes = self
+            .published_routes
+            .load_full()
+            .ok_or(ErrorCause::NoRoutingTable)?;
+
+        routes
+            .lookup(&canister_id.to_string())
+            .map_err(|_| ErrorCause::CanisterNotFound)
+    }
+
+    // Forward the request to the subnet responsible for the canister
+    pub async fn route(
+        &self,
+        ctx: &RequestContext,
+        body: Bytes,
+    ) -> Result<Response<Body>, ErrorCause> {
+        let subnet = self.lookup(&ctx.canister_id)?;
+        self.forward(ctx, &subnet, body).await
+    }
+
+    // Forward the request to the given subnet
+    // Nodes are tried in the order established by the health checks (lowest height lag first)
+    // and a failed attempt is retried on the next node
+    pub async fn forward(
+        &self,
+        ctx: &RequestContext,
+        subnet: &RouteSubnet,
+        body: Bytes,
+    ) -> Result<Response<Body>, ErrorCause> {
+        if subnet.nodes.is_empty() {
+            return Err(ErrorCause::NoHealthyNodes);
+        }
+
+        let mut out = Err(ErrorCause::NoHealthyNodes);
+        for node in subnet.nodes.iter().take(self.retries + 1) {
+            out = self
+                .proxy
+                .proxy(ctx.request_type, ctx.canister_id, node, body.clone())
+                .await;
+
+            // Retry only on network errors and server-side errors
+            match &out {
+                Ok(r) if !r.status().is_server_error() => return out,
+                Ok(r) => warn!(
+                    node_id = %node.id,
+                    subnet_id = %node.subnet_id,
+                    status = r.status().as_u16(),
+                    "replica returned a server error"
+                ),
+                Err(e) => warn!(
+                    node_id = %node.id,
+                    subnet_id = %node.subnet_id,
+                    error = %e,
+                    "failed to forward request"
+                ),
+            }
+        }
+
+        out
+    }
+}
 
 pub async fn acme_challenge(
     Extension(token): Extension<Arc<RwLock<Option<String>>>>,
@@ -37,14 +331,105 @@ pub async fn status() -> impl IntoResponse {
     "Hello, World!"
 }
 
-pub async fn query() -> impl IntoResponse {
-    "Hello, World!"
+async fn handle(
+    request_type: RequestType,
+    router: RequestRouter,
+    rate_limiter: RateLimiter,
+    cache: Option<Arc<Cache>>,
+    addr: SocketAddr,
+    canister_id: String,
+    body: Bytes,
+) -> Result<Response<Body>, ErrorCause> {
+    let canister_id = Principal::from_text(&canister_id)
+        .map_err(|e| ErrorCause::MalformedRequest(format!("invalid canister_id: {e}")))?;
+
+    let ctx = decode_request(request_type, canister_id, &body)?;
+
+    rate_limiter
+        .check_caller(addr.ip(), ctx.sender)
+        .map_err(ErrorCause::RateLimited)?;
+
+    // Serve cacheable requests from the cache if possible
+    let cache = cache.and_then(|cache| CacheKey::from_request(&ctx).map(|key| (cache, key)));
+    if let Some((cache, key)) = &cache {
+        if let Some(response) = cache.get(key, Instant::now()) {
+            return Ok(response);
+        }
+    }
+
+    let subnet = router.lookup(&ctx.canister_id)?;
+
+    rate_limiter
+        .check_subnet(&subnet.id)
+        .map_err(ErrorCause::RateLimited)?;
+
+    let response = router.forward(&ctx, &subnet, body).await?;
+
+    match cache {
+        Some((cache, key)) => Ok(cache.insert(key, response, Instant::now()).await),
+        None => Ok(response),
+    }
 }
 
-pub async fn call() -> impl IntoResponse {
-    "Hello, World!"
+pub async fn query(
+    Extension(router): Extension<RequestRouter>,
+    Extension(rate_limiter): Extension<RateLimiter>,
+    Extension(cache): Extension<Option<Arc<Cache>>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(canister_id): Path<String>,
+    body: Bytes,
+) -> Result<Response<Body>, ErrorCause> {
+    handle(
+        RequestType::Query,
+        router,
+        rate_limiter,
+        cache,
+        addr,
+        canister_id,
+        body,
+    )
+    .await
 }
 
-pub async fn read_state() -> impl IntoResponse {
-    "Hello, World!"
+pub async fn call(
+    Extension(router): Extension<RequestRouter>,
+    Extension(rate_limiter): Extension<RateLimiter>,
+    Extension(cache): Extension<Option<Arc<Cache>>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(canister_id): Path<String>,
+    body: Bytes,
+) -> Result<Response<Body>, ErrorCause> {
+    handle(
+        RequestType::Call,
+        router,
+        rate_limiter,
+        cache,
+        addr,
+        canister_id,
+        body,
+    )
+    .await
 }
+
+pub async fn read_state(
+    Extension(router): Extension<RequestRouter>,
+    Extension(rate_limiter): Extension<RateLimiter>,
+    Extension(cache): Extension<Option<Arc<Cache>>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(canister_id): Path<String>,
+    body: Bytes,
+) -> Result<Response<Body>, ErrorCause> {
+    handle(
+        RequestType::ReadState,
+        router,
+        rate_limiter,
+        cache,
+        addr,
+        canister_id,
+        body,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,238 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::Arc,
+};
+
+use anyhow::Error;
+use arc_swap::ArcSwapOption;
+use ethnum::u256;
+use ic_crypto_test_utils_keys::public_keys::valid_tls_certificate;
+use ic_test_utilities::types::ids::{node_test_id, subnet_test_id};
+use ic_types::messages::{HttpReadState, HttpUserQuery};
+use mockall::Sequence;
+
+use super::*;
+use crate::persist::RouteSubnet;
+
+fn node(i: u64, subnet_id: Principal) -> Node {
+    Node {
+        id: node_test_id(1001 + i).get().0,
+        subnet_id,
+        addr: IpAddr::V4(Ipv4Addr::new(192, 168, 0, i as u8)),
+        port: 8080,
+        tls_certificate: valid_tls_certificate().certificate_der,
+        replica_version: "7742d96ddd30aa6b607c9d2d4093a7b714f5b25b".to_string(),
+    }
+}
+
+// Generate routes with a single subnet covering the given range
+fn generate_routes(node_count: u64, range_start: u256, range_end: u256) -> Routes {
+    let subnet_id = subnet_test_id(1).get().0;
+
+    Routes {
+        node_count: node_count as u32,
+        subnets: vec![Arc::new(RouteSubnet {
+            id: subnet_id.to_string(),
+            range_start,
+            range_end,
+            nodes: (0..node_count).map(|i| node(i, subnet_id)).collect(),
+        })],
+    }
+}
+
+// RequestRouter requires the routes to live as long as the program, like the global ones
+fn publish(routes: Option<Routes>) -> &'static ArcSwapOption<Routes> {
+    Box::leak(Box::new(ArcSwapOption::from(routes.map(Arc::new))))
+}
+
+fn canister_id() -> Principal {
+    Principal::from_text("qoctq-giaaa-aaaaa-aaaea-cai").unwrap()
+}
+
+fn query_envelope(canister_id: Principal, sender: Principal) -> Vec<u8> {
+    let envelope = HttpRequestEnvelope {
+        content: HttpQueryContent::Query {
+            query: HttpUserQuery {
+                canister_id: Blob(canister_id.as_slice().to_vec()),
+                method_name: "greet".to_string(),
+                arg: Blob(vec![1, 2, 3]),
+                sender: Blob(sender.as_slice().to_vec()),
+                ingress_expiry: 0,
+                nonce: None,
+            },
+        },
+        sender_pubkey: None,
+        sender_sig: None,
+        sender_delegation: None,
+    };
+
+    serde_cbor::to_vec(&envelope).unwrap()
+}
+
+fn ctx(request_type: RequestType) -> RequestContext {
+    RequestContext {
+        request_type,
+        canister_id: canister_id(),
+        sender: Principal::anonymous(),
+        method_name: None,
+        arg: None,
+    }
+}
+
+fn response(status: u16) -> Result<Response<Body>, ErrorCause> {
+    Ok(Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap())
+}
+
+#[test]
+fn test_decode_request() -> Result<(), Error> {
+    let sender = Principal::from_text("2vxsx-fae").unwrap();
+
+    // Query
+    let body = query_envelope(canister_id(), sender);
+    let ctx = decode_request(RequestType::Query, canister_id(), &body).unwrap();
+    assert_eq!(
+        ctx,
+        RequestContext {
+            request_type: RequestType::Query,
+            canister_id: canister_id(),
+            sender,
+            method_name: Some("greet".to_string()),
+            arg: Some(vec![1, 2, 3]),
+        }
+    );
+
+    // Read state
+    let envelope = HttpRequestEnvelope {
+        content: HttpReadStateContent::ReadState {
+            read_state: HttpReadState {
+                sender: Blob(sender.as_slice().to_vec()),
+                paths: vec![],
+                nonce: None,
+                ingress_expiry: 0,
+            },
+        },
+        sender_pubkey: None,
+        sender_sig: None,
+        sender_delegation: None,
+    };
+    let body = serde_cbor::to_vec(&envelope)?;
+    let ctx = decode_request(RequestType::ReadState, canister_id(), &body).unwrap();
+    assert_eq!(ctx.sender, sender);
+    assert_eq!(ctx.method_name, None);
+
+    // Canister id mismatch
+    let other = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+    let body = query_envelope(other, sender);
+    assert!(matches!(
+        decode_request(RequestType::Query, canister_id(), &body),
+        Err(ErrorCause::MalformedRequest(_))
+    ));
+
+    // Wrong request type
+    let body = query_envelope(canister_id(), sender);
+    assert!(matches!(
+        decode_request(RequestType::Call, canister_id(), &body),
+        Err(ErrorCause::MalformedRequest(_))
+    ));
+
+    // Garbage
+    assert!(matches!(
+        decode_request(RequestType::Query, canister_id(), b"foobar"),
+        Err(ErrorCause::MalformedRequest(_))
+    ));
+
+    Ok(())
+}
+
+// Ensure that a failed request is retried on the next node
+#[tokio::test]
+async fn test_route_retry() -> Result<(), Error> {
+    let routes = publish(Some(generate_routes(3, u256::MIN, u256::MAX)));
+    let mut proxy = MockProxy::new();
+    let mut seq = Sequence::new();
+
+    proxy
+        .expect_proxy()
+        .withf(|_, _, x: &Node, _| x.id == node_test_id(1001).get().0)
+        .times(1)
+        .returning(|_, _, _, _| Err(ErrorCause::ReplicaUnreachable("timeout".into())))
+        .in_sequence(&mut seq);
+
+    proxy
+        .expect_proxy()
+        .withf(|_, _, x: &Node, _| x.id == node_test_id(1002).get().0)
+        .times(1)
+        .returning(|_, _, _, _| response(503))
+        .in_sequence(&mut seq);
+
+    proxy
+        .expect_proxy()
+        .withf(|t: &RequestType, c: &Principal, x: &Node, _| {
+            *t == RequestType::Call && *c == canister_id() && x.id == node_test_id(1003).get().0
+        })
+        .times(1)
+        .returning(|_, _, _, _| response(202))
+        .in_sequence(&mut seq);
+
+    let router = RequestRouter::new(routes, Arc::new(proxy), 2);
+    let out = router.route(&ctx(RequestType::Call), Bytes::new()).await;
+
+    assert_eq!(out.unwrap().status(), StatusCode::ACCEPTED);
+
+    Ok(())
+}
+
+// Ensure that client errors are not retried and that retries are limited
+#[tokio::test]
+async fn test_route_no_retry() -> Result<(), Error> {
+    let routes = publish(Some(generate_routes(3, u256::MIN, u256::MAX)));
+
+    // Client errors are passed through as is
+    let mut proxy = MockProxy::new();
+    proxy
+        .expect_proxy()
+        .times(1)
+        .returning(|_, _, _, _| response(400));
+
+    let router = RequestRouter::new(routes, Arc::new(proxy), 2);
+    let out = router.route(&ctx(RequestType::Query), Bytes::new()).await;
+    assert_eq!(out.unwrap().status(), StatusCode::BAD_REQUEST);
+
+    // Only retries + 1 nodes are tried
+    let mut proxy = MockProxy::new();
+    proxy
+        .expect_proxy()
+        .times(2)
+        .returning(|_, _, _, _| Err(ErrorCause::ReplicaUnreachable("timeout".into())));
+
+    let router = RequestRouter::new(routes, Arc::new(proxy), 1);
+    let out = router.route(&ctx(RequestType::Query), Bytes::new()).await;
+    assert!(matches!(out, Err(ErrorCause::ReplicaUnreachable(_))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_route_errors() -> Result<(), Error> {
+    // No routing table
+    let router = RequestRouter::new(publish(None), Arc::new(MockProxy::new()), 2);
+    let out = router.route(&ctx(RequestType::Query), Bytes::new()).await;
+    assert_eq!(out.unwrap_err(), ErrorCause::NoRoutingTable);
+
+    // Canister outside of the known ranges
+    let routes = publish(Some(generate_routes(3, u256::MIN, u256::MIN)));
+    let router = RequestRouter::new(routes, Arc::new(MockProxy::new()), 2);
+    let out = router.route(&ctx(RequestType::Query), Bytes::new()).await;
+    assert_eq!(out.unwrap_err(), ErrorCause::CanisterNotFound);
+
+    // Subnet without healthy nodes
+    let routes = publish(Some(generate_routes(0, u256::MIN, u256::MAX)));
+    let router = RequestRouter::new(routes, Arc::new(MockProxy::new()), 2);
+    let out = router.route(&ctx(RequestType::Query), Bytes::new()).await;
+    assert_eq!(out.unwrap_err(), ErrorCause::NoHealthyNodes);
+
+    Ok(())
+}
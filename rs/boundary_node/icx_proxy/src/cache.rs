@@ -0,0 +1,296 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use candid::Principal;
+use clap::Args;
+use hyper::{Body, Response, StatusCode};
+use lru::LruCache;
+use opentelemetry::{metrics::Meter, KeyValue};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    http::{request::HttpRequest, response::HttpResponse},
+    metrics::MetricParams,
+};
+
+/// The options for the response cache
+#[derive(Args)]
+pub struct CacheOpts {
+    /// Maximum total size of cached responses in bytes. Responses are not cached if not set.
+    #[clap(long)]
+    cache_size_bytes: Option<usize>,
+
+    /// Maximum size of a single cached response in bytes.
+    #[clap(long, default_value = "131072")]
+    cache_max_item_size_bytes: usize,
+
+    /// How long responses are kept in the cache, in seconds.
+    #[clap(long, default_value = "1")]
+    cache_ttl: u64,
+}
+
+/// Identifies a query by the canister, the method and the hash of its argument.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    canister_id: Principal,
+    method_name: &'static str,
+    arg_hash: [u8; 32],
+}
+
+impl CacheKey {
+    /// The key of the `http_request` query made for the given request. All parts of the
+    /// request end up in the argument of the query, so all of them are hashed.
+    pub fn new(canister_id: Principal, request: &HttpRequest) -> Self {
+        fn update(hasher: &mut Sha256, data: &[u8]) {
+            hasher.update((data.len() as u64).to_le_bytes());
+            hasher.update(data);
+        }
+
+        let mut hasher = Sha256::new();
+        update(&mut hasher, request.method.as_bytes());
+        update(&mut hasher, request.uri.to_string().as_bytes());
+        for (name, value) in &request.headers {
+            update(&mut hasher, name.as_bytes());
+            update(&mut hasher, value.as_bytes());
+        }
+        update(&mut hasher, &request.body);
+
+        Self {
+            canister_id,
+            method_name: "http_request",
+            arg_hash: hasher.finalize().into(),
+        }
+    }
+}
+
+/// A response served from the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    pub fn to_response(&self) -> Result<Response<Body>, anyhow::Error> {
+        let mut response_builder =
+            Response::builder().status(StatusCode::from_u16(self.status_code)?);
+        for (name, value) in &self.headers {
+            response_builder = response_builder.header(name, value);
+        }
+
+        Ok(response_builder.body(Body::from(self.body.clone()))?)
+    }
+}
+
+struct CacheEntry {
+    response: CachedResponse,
+    expires_at: Instant,
+}
+
+struct CacheState {
+    entries: LruCache<CacheKey, CacheEntry>,
+    size: usize,
+}
+
+impl CacheState {
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.pop(key) {
+            self.size -= entry.response.body.len();
+        }
+    }
+}
+
+/// A bounded in-memory cache of query responses. The total size of the cached bodies is
+/// kept below the configured limit by evicting the least recently used entries.
+pub struct Cache {
+    state: Mutex<CacheState>,
+    ttl: Duration,
+    max_size: usize,
+    max_item_size: usize,
+    metrics: MetricParams,
+}
+
+impl Cache {
+    pub fn new(
+        ttl: Duration,
+        max_size: usize,
+        max_item_size: usize,
+        metrics: MetricParams,
+    ) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                entries: LruCache::unbounded(),
+                size: 0,
+            }),
+            ttl,
+            max_size,
+            max_item_size: max_item_size.min(max_size),
+            metrics,
+        }
+    }
+
+    pub fn get(&self, key: &CacheKey, now: Instant) -> Option<CachedResponse> {
+        let mut state = self.state.lock().unwrap();
+
+        let response = match state.entries.get(key) {
+            Some(entry) if entry.expires_at > now => Some(entry.response.clone()),
+            // Drop expired entries right away
+            Some(_) => {
+                state.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        let status = if response.is_some() { "hit" } else { "miss" };
+        self.metrics
+            .counter
+            .add(1, &[KeyValue::new("status", status)]);
+
+        response
+    }
+
+    /// Stores the response. The caller is responsible for only passing responses that
+    /// are certified or that were obtained by an anonymous query.
+    pub fn insert(&self, key: CacheKey, response: &HttpResponse, now: Instant) {
+        if response.has_streaming_body || response.body.len() > self.max_item_size {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        state.remove(&key);
+        while state.size + response.body.len() > self.max_size {
+            match state.entries.pop_lru() {
+                Some((_, entry)) => state.size -= entry.response.body.len(),
+                None => break,
+            }
+        }
+
+        state.size += response.body.len();
+        state.entries.put(
+            key,
+            CacheEntry {
+                response: CachedResponse {
+                    status_code: response.status_code,
+                    headers: response.headers.clone(),
+                    body: response.body.clone(),
+                },
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+}
+
+pub fn setup(opts: CacheOpts, meter: &Meter) -> Option<Cache> {
+    opts.cache_size_bytes.map(|cache_size_bytes| {
+        Cache::new(
+            Duration::from_secs(opts.cache_ttl),
+            cache_size_bytes,
+            opts.cache_max_item_size_bytes,
+            MetricParams::new(meter, "cache"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::Uri;
+    use opentelemetry::global;
+
+    fn request(uri: &'static str) -> HttpRequest {
+        HttpRequest {
+            uri: Uri::from_static(uri),
+            method: "GET".to_string(),
+            headers: vec![("accept".to_string(), "*/*".to_string())],
+            body: Vec::new(),
+        }
+    }
+
+    fn response(body: Vec<u8>) -> HttpResponse {
+        HttpResponse {
+            status_code: 200,
+            headers: vec![("content-type".to_string(), "text/plain".to_string())],
+            body,
+            streaming_body: None,
+            has_streaming_body: false,
+        }
+    }
+
+    fn key(uri: &'static str) -> CacheKey {
+        CacheKey::new(Principal::management_canister(), &request(uri))
+    }
+
+    fn cache(max_size: usize) -> Cache {
+        Cache::new(
+            Duration::from_secs(10),
+            max_size,
+            1024,
+            MetricParams::new(&global::meter("test"), "cache"),
+        )
+    }
+
+    #[test]
+    fn cache_key() {
+        assert_eq!(key("http://localhost/a"), key("http://localhost/a"));
+        assert_ne!(key("http://localhost/a"), key("http://localhost/b"));
+
+        let mut other = request("http://localhost/a");
+        other
+            .headers
+            .push(("cookie".to_string(), "foo".to_string()));
+        assert_ne!(
+            key("http://localhost/a"),
+            CacheKey::new(Principal::management_canister(), &other)
+        );
+    }
+
+    #[test]
+    fn cache_hit_and_expiry() {
+        let cache = cache(1024 * 1024);
+        let now = Instant::now();
+
+        assert_eq!(cache.get(&key("http://localhost/a"), now), None);
+
+        cache.insert(key("http://localhost/a"), &response(vec![1, 2, 3]), now);
+        assert_eq!(
+            cache.get(&key("http://localhost/a"), now),
+            Some(CachedResponse {
+                status_code: 200,
+                headers: vec![("content-type".to_string(), "text/plain".to_string())],
+                body: vec![1, 2, 3],
+            })
+        );
+        assert_eq!(cache.get(&key("http://localhost/b"), now), None);
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(cache.get(&key("http://localhost/a"), later), None);
+    }
+
+    #[test]
+    fn cache_size_limits() {
+        let cache = cache(300);
+        let now = Instant::now();
+
+        // Too large to be cached
+        cache.insert(key("http://localhost/big"), &response(vec![0; 2048]), now);
+        assert_eq!(cache.get(&key("http://localhost/big"), now), None);
+
+        cache.insert(key("http://localhost/1"), &response(vec![0; 100]), now);
+        cache.insert(key("http://localhost/2"), &response(vec![0; 100]), now);
+        cache.insert(key("http://localhost/3"), &response(vec![0; 100]), now);
+
+        // Make 1 the most recently used entry, so that 2 gets evicted
+        assert!(cache.get(&key("http://localhost/1"), now).is_some());
+        cache.insert(key("http://localhost/4"), &response(vec![0; 100]), now);
+
+        assert!(cache.get(&key("http://localhost/1"), now).is_some());
+        assert!(cache.get(&key("http://localhost/2"), now).is_none());
+        assert!(cache.get(&key("http://localhost/3"), now).is_some());
+        assert!(cache.get(&key("http://localhost/4"), now).is_some());
+    }
+}
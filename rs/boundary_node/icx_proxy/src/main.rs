@@ -9,6 +9,7 @@ use clap::{builder::ValueParser, Parser};
 use futures::try_join;
 use tracing::{error, Instrument};
 
+mod cache;
 mod canister_alias;
 mod canister_id;
 mod config;
@@ -100,6 +101,10 @@ struct Opts {
     /// The options for metrics
     #[clap(flatten)]
     metrics: metrics::MetricsOpts,
+
+    /// The options for the response cache
+    #[clap(flatten)]
+    cache: cache::CacheOpts,
 }
 
 fn main() -> Result<(), anyhow::Error> {
@@ -115,6 +120,7 @@ fn main() -> Result<(), anyhow::Error> {
         log,
         metrics,
         root_key,
+        cache,
     } = Opts::parse();
 
     let _span = logging::setup(log);
@@ -139,11 +145,15 @@ fn main() -> Result<(), anyhow::Error> {
     let validator = Validator::new();
     let validator = WithMetrics(validator, MetricParams::new(&meter, "validator"));
 
+    // Setup Cache
+    let cache = cache::setup(cache, &meter);
+
     let proxy = proxy::setup(
         proxy::SetupArgs {
             resolver,
             validator,
             client,
+            cache,
         },
         proxy::ProxyOpts {
             address,
@@ -6,6 +6,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::Instant,
 };
 
 use anyhow::bail;
@@ -28,6 +29,7 @@ use ic_utils::{
 };
 use tracing::{enabled, error, info, instrument, trace, Level};
 
+use crate::cache::{Cache, CacheKey};
 use crate::error::ErrorFactory;
 use crate::http;
 use crate::http::request::HttpRequest;
@@ -46,6 +48,7 @@ pub struct Args<V, C> {
     replica_uri: Arc<Uri>,
     validator: V,
     client: C,
+    cache: Option<Arc<Cache>>,
     debug: bool,
 }
 
@@ -75,6 +78,7 @@ impl<V: Clone, C: Clone> FromRef<AppState<V, C>> for Args<V, C> {
             replica_uri,
             validator: state.validator().clone(),
             client: state.client().clone(),
+            cache: state.cache().cloned(),
             debug: state.debug(),
         }
     }
@@ -191,6 +195,7 @@ pub async fn handler<V: Validate, C: HyperService<Body>>(
             &args.replica_uri,
             &args.validator,
             &mut args.client,
+            args.cache.as_deref(),
             uri_canister_id
                 .or(host_canister_id)
                 .or(query_param_canister_id)
@@ -223,6 +228,7 @@ async fn process_request_inner(
     replica_uri: &Uri,
     validator: &impl Validate,
     client: &mut impl HyperService<Body>,
+    cache: Option<&Cache>,
     canister_id: Option<Principal>,
 ) -> Result<Response<Body>, anyhow::Error> {
     let canister_id = match canister_id {
@@ -281,6 +287,16 @@ async fn process_request_inner(
         );
     }
 
+    // The agent has no identity, so all queries are anonymous and their responses can be
+    // served to any client
+    let cache = cache.map(|cache| (cache, CacheKey::new(canister_id, &http_request)));
+    if let Some((cache, key)) = &cache {
+        if let Some(cached_response) = cache.get(key, Instant::now()) {
+            trace!(">> served from cache");
+            return cached_response.to_response();
+        }
+    }
+
     let canister = HttpRequestCanister::create(agent, canister_id);
     let header_fields = http_request
         .headers
@@ -345,6 +361,11 @@ async fn process_request_inner(
                 .body(validation.unwrap_err().into())
                 .unwrap());
         }
+
+        // Only responses that passed validation are cached
+        if let Some((cache, key)) = cache {
+            cache.insert(key, &http_response, Instant::now());
+        }
     }
 
     let response = response_builder.body(match http_response.streaming_body {
@@ -7,6 +7,7 @@ use ic_agent::{agent::http_transport::HyperReplicaV2Transport, Agent};
 use tracing::{error, info};
 
 use crate::{
+    cache::Cache,
     canister_id::ResolverState,
     http_client::{Body, HyperService},
     logging::add_trace_layer,
@@ -76,6 +77,7 @@ pub struct SetupArgs<V, C> {
     pub validator: V,
     pub resolver: ResolverState,
     pub client: C,
+    pub cache: Option<Cache>,
 }
 
 pub fn setup<C: HyperService<Body> + 'static>(
@@ -125,6 +127,7 @@ pub fn setup<C: HyperService<Body> + 'static>(
         resolver: args.resolver,
         debug: opts.debug,
         client,
+        cache: args.cache.map(Arc::new),
     })));
 
     Ok(Runner {
@@ -142,6 +145,7 @@ struct AppStateInner<V, C> {
     resolver: ResolverState,
     validator: V,
     client: C,
+    cache: Option<Arc<Cache>>,
     debug: bool,
 }
 
@@ -158,6 +162,9 @@ impl<V, C> AppState<V, C> {
     pub fn client(&self) -> &C {
         &self.0.client
     }
+    pub fn cache(&self) -> Option<&Arc<Cache>> {
+        self.0.cache.as_ref()
+    }
     pub fn debug(&self) -> bool {
         self.0.debug
     }
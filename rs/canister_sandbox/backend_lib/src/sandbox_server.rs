@@ -161,7 +161,7 @@ mod tests {
     use ic_test_utilities::types::ids::{canister_test_id, subnet_test_id, user_test_id};
     use ic_types::{
         ingress::WasmResult,
-        messages::CallContextId,
+        messages::{CallContextId, NO_DEADLINE},
         methods::{FuncRef, WasmMethod},
         time::Time,
         CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumBytes, NumInstructions,
@@ -217,6 +217,11 @@ mod tests {
             CanisterTimer::Inactive,
             0,
             BTreeSet::from([controller]),
+            None,
+            Cycles::zero(),
+            None,
+            BTreeMap::new(),
+            vec![],
         )
     }
 
@@ -249,6 +254,7 @@ mod tests {
                 Cycles::zero(),
                 PrincipalId::try_from([0].as_ref()).unwrap(),
                 CallContextId::from(0),
+                NO_DEADLINE,
             ),
             globals,
             canister_current_memory_usage: NumBytes::from(0),
@@ -12,7 +12,7 @@
 
 use crate::CertificationVersion;
 use ic_protobuf::proxy::ProxyDecodeError;
-use ic_replicated_state::metadata_state::SystemMetadata;
+use ic_replicated_state::metadata_state::{SubnetMetrics, SystemMetadata};
 use ic_types::{messages::RequestOrResponse, xnet::StreamHeader, PrincipalId};
 use serde::Serialize;
 use std::collections::BTreeSet;
@@ -100,6 +100,15 @@ pub fn encode_metadata(
     types::SystemMetadata::proxy_encode((metadata, certification_version)).unwrap()
 }
 
+/// Encodes the `SubnetMetrics` of the own subnet into canonical CBOR
+/// representation.
+pub fn encode_subnet_metrics(
+    metrics: &SubnetMetrics,
+    certification_version: CertificationVersion,
+) -> Vec<u8> {
+    types::SubnetMetrics::proxy_encode((metrics, certification_version)).unwrap()
+}
+
 /// Encodes the list of canister ID ranges assigned to a subnet according to
 /// the interface specification.
 ///
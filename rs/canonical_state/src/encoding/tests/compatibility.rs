@@ -22,6 +22,7 @@ use ic_test_utilities::types::{
 use ic_types::{
     crypto::CryptoHash,
     messages::{CallbackId, Payload, RejectContext, Request, RequestOrResponse, Response},
+    time::CoarseTime,
     xnet::StreamHeader,
     CryptoHashOfPartialState, Cycles, Funds,
 };
@@ -426,6 +427,162 @@ fn canonical_encoding_reject_response() {
     );
 }
 
+/// Canonical CBOR encoding (with certification versions 13 and up) of:
+///
+/// ```no_run
+/// RequestOrResponse::Request(
+///     Request {
+///         receiver: canister_test_id(1),
+///         sender: canister_test_id(2),
+///         sender_reply_callback: CallbackId::from(3),
+///         payment: Cycles::new(4),
+///         method_name: "test".to_string(),
+///         method_payload: vec![6],
+///         deadline: CoarseTime::from_secs_since_unix_epoch(7),
+///     }
+/// )
+/// ```
+///
+/// Expected:
+///
+/// ```text
+/// A1                            # map(1)
+///    00                         # field_index(RequestOrResponse::request)
+///    A7                         # map(7)
+///       [...]                   # fields 0 to 5, as in `canonical_encoding_request()`
+///       07                      # field_index(Request::deadline)
+///       07                      # unsigned(7)
+/// ```
+///
+/// Before certification version 13 the deadline is not encoded.
+#[test]
+fn canonical_encoding_best_effort_request() {
+    for certification_version in all_supported_versions() {
+        let request: RequestOrResponse = RequestBuilder::new()
+            .receiver(canister_test_id(1))
+            .sender(canister_test_id(2))
+            .sender_reply_callback(CallbackId::from(3))
+            .payment(Cycles::new(4))
+            .method_name("test".to_string())
+            .method_payload(vec![6])
+            .deadline(CoarseTime::from_secs_since_unix_epoch(7))
+            .build()
+            .into();
+
+        if certification_version >= CertificationVersion::V13 {
+            assert_eq!(
+                "A1 00 A7 00 4A 00 00 00 00 00 00 00 01 01 01 01 4A 00 00 00 00 00 00 00 02 01 01 02 03 03 A1 00 A1 00 04 04 64 74 65 73 74 05 41 06 07 07",
+                as_hex(&encode_message(&request, certification_version))
+            );
+        } else {
+            assert_eq!(
+                "A1 00 A6 00 4A 00 00 00 00 00 00 00 01 01 01 01 4A 00 00 00 00 00 00 00 02 01 01 02 03 03 A1 00 A1 00 04 04 64 74 65 73 74 05 41 06",
+                as_hex(&encode_message(&request, certification_version))
+            );
+        }
+    }
+}
+
+/// Canonical CBOR encoding (with certification versions 13 and up) of:
+///
+/// ```no_run
+/// RequestOrResponse::Response(
+///     Response {
+///         originator: canister_test_id(5),
+///         respondent: canister_test_id(4),
+///         originator_reply_callback: CallbackId::from(3),
+///         refund: Cycles::new(2),
+///         response_payload: Payload::Data(vec![1]),
+///         deadline: CoarseTime::from_secs_since_unix_epoch(7),
+///     }
+/// )
+/// ```
+///
+/// Expected:
+///
+/// ```text
+/// A1                            # map(1)
+///    01                         # field_index(RequestOrResponse::response)
+///    A6                         # map(6)
+///       [...]                   # fields 0 to 4, as in `canonical_encoding_response()`
+///       06                      # field_index(Response::deadline)
+///       07                      # unsigned(7)
+/// ```
+///
+/// Before certification version 13 the deadline is not encoded.
+#[test]
+fn canonical_encoding_best_effort_response() {
+    for certification_version in all_supported_versions() {
+        let response: RequestOrResponse = ResponseBuilder::new()
+            .originator(canister_test_id(5))
+            .respondent(canister_test_id(4))
+            .originator_reply_callback(CallbackId::from(3))
+            .refund(Cycles::new(2))
+            .response_payload(Payload::Data(vec![1]))
+            .deadline(CoarseTime::from_secs_since_unix_epoch(7))
+            .build()
+            .into();
+
+        if certification_version >= CertificationVersion::V13 {
+            assert_eq!(
+                "A1 01 A6 00 4A 00 00 00 00 00 00 00 05 01 01 01 4A 00 00 00 00 00 00 00 04 01 01 02 03 03 A1 00 A1 00 02 04 A1 00 41 01 06 07",
+                as_hex(&encode_message(&response, certification_version))
+            );
+        } else {
+            assert_eq!(
+                "A1 01 A5 00 4A 00 00 00 00 00 00 00 05 01 01 01 4A 00 00 00 00 00 00 00 04 01 01 02 03 03 A1 00 A1 00 02 04 A1 00 41 01",
+                as_hex(&encode_message(&response, certification_version))
+            );
+        }
+    }
+}
+
+/// Canonical CBOR encoding (with certification versions 13 and up) of a
+/// `SYS_UNKNOWN` reject response, otherwise identical to the one in
+/// `canonical_encoding_reject_response()`:
+///
+/// ```text
+///       04                      # field_index(Response::response_payload)
+///       A1                      # map(1)
+///          01                   # field_index(Payload::reject)
+///          A2                   # map(2)
+///             00                # field_index(RejectContext::code)
+///             06                # unsigned(6)
+///             [...]
+/// ```
+///
+/// Before certification version 13, `SYS_UNKNOWN` is encoded as `SYS_TRANSIENT`
+/// (2).
+#[test]
+fn canonical_encoding_sys_unknown_reject_response() {
+    for certification_version in all_supported_versions() {
+        let reject_response: RequestOrResponse = ResponseBuilder::new()
+            .originator(canister_test_id(6))
+            .respondent(canister_test_id(5))
+            .originator_reply_callback(CallbackId::from(4))
+            .refund(Cycles::new(3))
+            .response_payload(Payload::Reject(RejectContext {
+                code: RejectCode::SysUnknown,
+                message: "Oops".into(),
+            }))
+            .build()
+            .into();
+
+        let expected_code = if certification_version >= CertificationVersion::V13 {
+            "06"
+        } else {
+            "02"
+        };
+        assert_eq!(
+            format!(
+                "A1 01 A5 00 4A 00 00 00 00 00 00 00 06 01 01 01 4A 00 00 00 00 00 00 00 05 01 01 02 04 03 A1 00 A1 00 03 04 A1 01 A2 00 {} 01 64 4F 6F 70 73",
+                expected_code
+            ),
+            as_hex(&encode_message(&reject_response, certification_version))
+        );
+    }
+}
+
 /// Canonical CBOR encoding of:
 ///
 /// ```no_run
@@ -1,5 +1,5 @@
 use super::test_fixtures::*;
-use crate::{all_supported_versions, encoding::*};
+use crate::{all_supported_versions, encoding::*, CertificationVersion};
 
 #[test]
 fn roundtrip_encoding_stream_header() {
@@ -48,3 +48,31 @@ fn roundtrip_encoding_reject_response() {
         );
     }
 }
+
+#[test]
+fn roundtrip_encoding_best_effort_request() {
+    let request = best_effort_request();
+
+    for certification_version in
+        all_supported_versions().filter(|v| v >= &CertificationVersion::V13)
+    {
+        assert_eq!(
+            request,
+            decode_message(&encode_message(&request, certification_version)).unwrap()
+        );
+    }
+}
+
+#[test]
+fn roundtrip_encoding_best_effort_response() {
+    let response = best_effort_response();
+
+    for certification_version in
+        all_supported_versions().filter(|v| v >= &CertificationVersion::V13)
+    {
+        assert_eq!(
+            response,
+            decode_message(&encode_message(&response, certification_version)).unwrap()
+        );
+    }
+}
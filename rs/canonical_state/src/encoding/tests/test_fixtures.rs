@@ -6,6 +6,7 @@ use ic_test_utilities::types::{
 };
 use ic_types::{
     messages::{CallbackId, Payload, RejectContext, RequestOrResponse},
+    time::CoarseTime,
     xnet::StreamHeader,
     Cycles,
 };
@@ -60,3 +61,31 @@ pub fn reject_response() -> RequestOrResponse {
         .build()
         .into()
 }
+
+pub fn best_effort_request() -> RequestOrResponse {
+    RequestBuilder::new()
+        .receiver(canister_test_id(1))
+        .sender(canister_test_id(2))
+        .sender_reply_callback(CallbackId::from(3))
+        .payment(Cycles::new(4))
+        .method_name("test".to_string())
+        .method_payload(vec![6])
+        .deadline(CoarseTime::from_secs_since_unix_epoch(7))
+        .build()
+        .into()
+}
+
+pub fn best_effort_response() -> RequestOrResponse {
+    ResponseBuilder::new()
+        .originator(canister_test_id(6))
+        .respondent(canister_test_id(5))
+        .originator_reply_callback(CallbackId::from(4))
+        .refund(Cycles::new(3))
+        .response_payload(Payload::Reject(RejectContext {
+            code: RejectCode::SysUnknown,
+            message: "Oops".into(),
+        }))
+        .deadline(CoarseTime::from_secs_since_unix_epoch(7))
+        .build()
+        .into()
+}
@@ -11,7 +11,7 @@
 //! `CanisterIds` are represented as byte vectors.
 
 use crate::CertificationVersion;
-use ic_error_types::TryFromError;
+use ic_error_types::{RejectCode, TryFromError};
 use ic_protobuf::proxy::ProxyDecodeError;
 use ic_types::xnet::StreamIndex;
 use serde::{Deserialize, Serialize};
@@ -65,6 +65,11 @@ pub struct Request {
     pub method_payload: Bytes,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cycles_payment: Option<Cycles>,
+    /// Only encoded for best-effort requests and only starting with
+    /// certification version 13, so that the encoding of guaranteed response
+    /// requests is unchanged.
+    #[serde(default, skip_serializing_if = "is_zero_u32")]
+    pub deadline: u32,
 }
 
 /// Canonical representation of `ic_types::messages::Response`.
@@ -80,6 +85,10 @@ pub struct Response {
     pub response_payload: Payload,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cycles_refund: Option<Cycles>,
+    /// Only encoded for best-effort responses and only starting with
+    /// certification version 13.
+    #[serde(default, skip_serializing_if = "is_zero_u32")]
+    pub deadline: u32,
 }
 
 /// Canonical representation of `ic_types::funds::Cycles`.
@@ -105,6 +114,10 @@ pub fn is_zero(v: &u64) -> bool {
     *v == 0
 }
 
+pub fn is_zero_u32(v: &u32) -> bool {
+    *v == 0
+}
+
 /// Canonical representation of `ic_types::messages::Payload`.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -133,6 +146,16 @@ pub struct SystemMetadata {
     pub prev_state_hash: Option<Vec<u8>>,
 }
 
+/// Canonical representation of `ic_replicated_state::metadata_state::SubnetMetrics`
+/// as exposed under `/subnet/<subnet_id>/metrics`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubnetMetrics {
+    pub num_canisters: u64,
+    pub canister_state_bytes: u64,
+    pub consumed_cycles_total: Cycles,
+}
+
 impl From<(&ic_types::xnet::StreamHeader, CertificationVersion)> for StreamHeader {
     fn from(
         (header, certification_version): (&ic_types::xnet::StreamHeader, CertificationVersion),
@@ -247,6 +270,11 @@ impl From<(&ic_types::messages::Request, CertificationVersion)> for Request {
             method_name: request.method_name.clone(),
             method_payload: request.method_payload.clone(),
             cycles_payment: None,
+            deadline: if certification_version >= CertificationVersion::V13 {
+                request.deadline.as_secs_since_unix_epoch()
+            } else {
+                0
+            },
         }
     }
 }
@@ -268,6 +296,7 @@ impl TryFrom<Request> for ic_types::messages::Request {
             payment,
             method_name: request.method_name,
             method_payload: request.method_payload,
+            deadline: ic_types::time::CoarseTime::from_secs_since_unix_epoch(request.deadline),
         })
     }
 }
@@ -287,6 +316,11 @@ impl From<(&ic_types::messages::Response, CertificationVersion)> for Response {
             refund: funds,
             response_payload: (&response.response_payload, certification_version).into(),
             cycles_refund: None,
+            deadline: if certification_version >= CertificationVersion::V13 {
+                response.deadline.as_secs_since_unix_epoch()
+            } else {
+                0
+            },
         }
     }
 }
@@ -307,6 +341,7 @@ impl TryFrom<Response> for ic_types::messages::Response {
             originator_reply_callback: response.originator_reply_callback.into(),
             refund,
             response_payload: response.response_payload.try_into()?,
+            deadline: ic_types::time::CoarseTime::from_secs_since_unix_epoch(response.deadline),
         })
     }
 }
@@ -398,13 +433,21 @@ impl TryFrom<Payload> for ic_types::messages::Payload {
 
 impl From<(&ic_types::messages::RejectContext, CertificationVersion)> for RejectContext {
     fn from(
-        (context, _certification_version): (
+        (context, certification_version): (
             &ic_types::messages::RejectContext,
             CertificationVersion,
         ),
     ) -> Self {
+        // `SYS_UNKNOWN` is only produced starting with certification version 13.
+        // Before that, it is encoded as the closest preexisting reject code.
+        let code = match context.code {
+            RejectCode::SysUnknown if certification_version < CertificationVersion::V13 => {
+                RejectCode::SysTransient
+            }
+            code => code,
+        };
         Self {
-            code: context.code as u8,
+            code: code as u8,
             message: context.message.clone(),
         }
     }
@@ -451,3 +494,27 @@ impl
         }
     }
 }
+
+impl
+    From<(
+        &ic_replicated_state::metadata_state::SubnetMetrics,
+        CertificationVersion,
+    )> for SubnetMetrics
+{
+    fn from(
+        (metrics, certification_version): (
+            &ic_replicated_state::metadata_state::SubnetMetrics,
+            CertificationVersion,
+        ),
+    ) -> Self {
+        Self {
+            num_canisters: metrics.num_canisters,
+            canister_state_bytes: metrics.canister_state_bytes.get(),
+            consumed_cycles_total: (
+                &ic_types::Cycles::from(metrics.consumed_cycles_total.get()),
+                certification_version,
+            )
+                .into(),
+        }
+    }
+}
@@ -4,7 +4,7 @@ use super::{blob, fork, num, string, Lazy, LazyFork, LazyTree};
 use crate::{
     encoding::{
         encode_controllers, encode_message, encode_metadata, encode_stream_header,
-        encode_subnet_canister_ranges,
+        encode_subnet_canister_ranges, encode_subnet_metrics,
     },
     CertificationVersion, MAX_SUPPORTED_CERTIFICATION_VERSION,
 };
@@ -14,7 +14,9 @@ use ic_error_types::RejectCode;
 use ic_registry_routing_table::RoutingTable;
 use ic_replicated_state::{
     canister_state::CanisterState,
-    metadata_state::{IngressHistoryState, StreamMap, SubnetTopology, SystemMetadata},
+    metadata_state::{
+        IngressHistoryState, StreamMap, SubnetMetrics, SubnetTopology, SystemMetadata,
+    },
     replicated_state::ReplicatedStateMessageRouting,
     ExecutionState, ReplicatedState,
 };
@@ -22,7 +24,7 @@ use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{MessageId, EXPECTED_MESSAGE_ID_LENGTH},
     xnet::{StreamHeader, StreamIndex, StreamIndexedQueue},
-    CanisterId, PrincipalId, SubnetId,
+    CanisterId, NodeId, PrincipalId, SubnetId,
 };
 use std::collections::BTreeMap;
 use std::convert::{AsRef, TryFrom, TryInto};
@@ -285,6 +287,9 @@ fn state_as_tree(state: &ReplicatedState) -> LazyTree<'_> {
                 ));
                 subnets_as_tree(
                     &state.metadata.network_topology.subnets,
+                    state.metadata.own_subnet_id,
+                    &state.metadata.subnet_metrics,
+                    &state.metadata.node_public_keys,
                     inverted_routing_table,
                     certification_version,
                 )
@@ -622,15 +627,25 @@ fn canisters_as_tree(
     })
 }
 
-fn subnets_as_tree(
-    subnets: &BTreeMap<SubnetId, SubnetTopology>,
+/// Converts the subnets of the network topology into a tree.
+///
+/// Starting with certification version 12, the subtree of `own_subnet_id`
+/// additionally contains the subnet metrics and the public keys of the nodes
+/// of the subnet.
+fn subnets_as_tree<'a>(
+    subnets: &'a BTreeMap<SubnetId, SubnetTopology>,
+    own_subnet_id: SubnetId,
+    own_subnet_metrics: &'a SubnetMetrics,
+    own_subnet_node_public_keys: &'a BTreeMap<NodeId, Vec<u8>>,
     inverted_routing_table: Arc<BTreeMap<SubnetId, Vec<(PrincipalId, PrincipalId)>>>,
     certification_version: CertificationVersion,
-) -> LazyTree<'_> {
+) -> LazyTree<'a> {
     fork(MapTransformFork {
         map: subnets,
         certification_version,
         mk_tree: move |subnet_id, subnet_topology, certification_version| {
+            let is_own_subnet_since_v12 =
+                subnet_id == own_subnet_id && certification_version >= CertificationVersion::V12;
             fork(
                 FiniteMap::default()
                     .with_tree("public_key", Blob(&subnet_topology.public_key[..], None))
@@ -645,12 +660,37 @@ fn subnets_as_tree(
                                 )
                             }
                         }),
+                    )
+                    .with_tree_if(
+                        is_own_subnet_since_v12,
+                        "metrics",
+                        blob(move || {
+                            encode_subnet_metrics(own_subnet_metrics, certification_version)
+                        }),
+                    )
+                    .with_tree_if(
+                        is_own_subnet_since_v12,
+                        "node",
+                        nodes_as_tree(own_subnet_node_public_keys, certification_version),
                     ),
             )
         },
     })
 }
 
+fn nodes_as_tree(
+    node_public_keys: &BTreeMap<NodeId, Vec<u8>>,
+    certification_version: CertificationVersion,
+) -> LazyTree<'_> {
+    fork(MapTransformFork {
+        map: node_public_keys,
+        certification_version,
+        mk_tree: |_node_id, public_key, _certification_version| {
+            fork(FiniteMap::default().with_tree("public_key", Blob(&public_key[..], None)))
+        },
+    })
+}
+
 fn canister_metadata_as_tree(
     execution_state: &ExecutionState,
     certification_version: CertificationVersion,
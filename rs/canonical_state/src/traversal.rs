@@ -67,9 +67,9 @@ mod tests {
     use ic_test_utilities::{
         mock_time,
         state::new_canister_state,
-        types::ids::{canister_test_id, subnet_test_id, user_test_id},
+        types::ids::{canister_test_id, node_test_id, subnet_test_id, user_test_id},
     };
-    use ic_types::{CanisterId, Cycles, ExecutionRound};
+    use ic_types::{nominal_cycles::NominalCycles, CanisterId, Cycles, ExecutionRound};
     use ic_wasm_types::CanisterModule;
     use maplit::{btreemap, btreeset};
     use std::collections::{BTreeSet, VecDeque};
@@ -735,5 +735,63 @@ mod tests {
             ],
             traverse(&state, visitor).0
         );
+
+        // Starting with V12, the own subnet also has metrics and node public keys.
+        state.metadata.subnet_metrics.num_canisters = 3;
+        state.metadata.subnet_metrics.canister_state_bytes = NumBytes::new(1024);
+        state.metadata.subnet_metrics.consumed_cycles_total = NominalCycles::from(100);
+        state.metadata.node_public_keys = btreemap! {
+            node_test_id(2) => vec![9, 10],
+            node_test_id(3) => vec![11, 12],
+        };
+        let pattern = Pattern::match_only(
+            "subnet",
+            Pattern::match_only(subnet_test_id(1).get().into_vec(), Pattern::all()),
+        );
+        let visitor = SubtreeVisitor::new(&pattern, TracingVisitor::new(NoopVisitor));
+        state.metadata.certification_version = CertificationVersion::V12;
+        assert_eq!(
+            vec![
+                E::StartSubtree,
+                edge("subnet"),
+                E::StartSubtree,
+                E::EnterEdge(subnet_test_id(1).get().into_vec()),
+                E::StartSubtree,
+                edge("canister_ranges"),
+                E::VisitBlob(
+                    hex::decode("d9d9f781824a000000000000000b01014a00000000000000140101").unwrap()
+                ),
+                edge("metrics"),
+                // A3          # map(3)
+                //    00       # field_index(SubnetMetrics::num_canisters)
+                //    03       # unsigned(3)
+                //    01       # field_index(SubnetMetrics::canister_state_bytes)
+                //    19 0400  # unsigned(1024)
+                //    02       # field_index(SubnetMetrics::consumed_cycles_total)
+                //    A1       # map(1)
+                //       00    # field_index(Cycles::low)
+                //       18 64 # unsigned(100)
+                E::VisitBlob(hex::decode("a300030119040002a1001864").unwrap()),
+                edge("node"),
+                E::StartSubtree,
+                E::EnterEdge(node_test_id(2).get().into_vec()),
+                E::StartSubtree,
+                edge("public_key"),
+                E::VisitBlob(vec![9, 10]),
+                E::EndSubtree, // node
+                E::EnterEdge(node_test_id(3).get().into_vec()),
+                E::StartSubtree,
+                edge("public_key"),
+                E::VisitBlob(vec![11, 12]),
+                E::EndSubtree, // node
+                E::EndSubtree, // nodes
+                edge("public_key"),
+                E::VisitBlob(vec![5, 6, 7, 8]),
+                E::EndSubtree, // subnet
+                E::EndSubtree, // subnets
+                E::EndSubtree, // global
+            ],
+            traverse(&state, visitor).0
+        );
     }
 }
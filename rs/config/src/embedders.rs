@@ -68,6 +68,8 @@ pub struct FeatureFlags {
     /// Track dirty pages with a write barrier instead of the signal handler.
     pub write_barrier: FlagStatus,
     pub wasm_native_stable_memory: FlagStatus,
+    /// Allow canisters to use a 64-bit Wasm memory (the memory64 proposal).
+    pub wasm64: FlagStatus,
 }
 
 impl FeatureFlags {
@@ -76,6 +78,7 @@ impl FeatureFlags {
             rate_limiting_of_debug_prints: FlagStatus::Enabled,
             write_barrier: FlagStatus::Disabled,
             wasm_native_stable_memory: FlagStatus::Enabled,
+            wasm64: FlagStatus::Disabled,
         }
     }
 }
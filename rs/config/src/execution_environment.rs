@@ -6,7 +6,7 @@ use crate::{
 use ic_base_types::{CanisterId, NumSeconds};
 use ic_types::{
     Cycles, NumBytes, NumInstructions, NumPages, MAX_STABLE_MEMORY_IN_BYTES,
-    MAX_WASM_MEMORY_IN_BYTES,
+    MAX_WASM64_MEMORY_IN_BYTES, MAX_WASM_MEMORY_IN_BYTES,
 };
 use serde::{Deserialize, Serialize};
 use std::{str::FromStr, time::Duration};
@@ -38,6 +38,12 @@ const SUBNET_MEMORY_CAPACITY: NumBytes = NumBytes::new(450 * GIB);
 /// responses; plus the maximum allowed response size per queue reservation.
 const SUBNET_MESSAGE_MEMORY_CAPACITY: NumBytes = NumBytes::new(25 * GIB);
 
+/// This is the upper limit on how much memory can be used by all best-effort
+/// requests on a given subnet. Above it, message routing sheds best-effort
+/// requests, largest first, from the canisters with the most best-effort
+/// request bytes.
+const BEST_EFFORT_MESSAGE_MEMORY_CAPACITY: NumBytes = NumBytes::new(5 * GIB);
+
 /// This is the upper limit on how much memory can be used by the ingress
 /// history on a given subnet. It is lower than the subnet messsage memory
 /// capacity because here we count actual memory consumption as opposed to
@@ -101,6 +107,10 @@ const QUERY_SCHEDULING_TIME_SLICE_PER_CANISTER: Duration = Duration::from_millis
 /// executions and user errors.
 const QUERY_CACHE_CAPACITY: NumBytes = NumBytes::new(100 * MIB);
 
+/// The number of blocks over which query statistics are collected before they
+/// are reported through consensus.
+const QUERY_STATS_EPOCH_LENGTH: u64 = 2000;
+
 // The ID of the Bitcoin testnet canister.
 pub const BITCOIN_TESTNET_CANISTER_ID: &str = "g4xu7-jiaaa-aaaan-aaaaq-cai";
 
@@ -114,6 +124,11 @@ pub const BITCOIN_MAINNET_CANISTER_ID: &str = "ghsi2-tqaaa-aaaan-aaaca-cai";
 // TODO(EXC-1298): Uninstall this canister once the bitcoin mainnet canister is live.
 const BITCOIN_MAINNET_SOFT_LAUNCH_CANISTER_ID: &str = "gsvzx-syaaa-aaaan-aaabq-cai";
 
+/// The maximum amount of memory that can be utilized by a single canister if
+/// canisters may use a 64-bit Wasm memory.
+pub const MAX_WASM64_CANISTER_MEMORY_SIZE: NumBytes =
+    NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES + MAX_WASM64_MEMORY_IN_BYTES);
+
 /// The capacity of the Wasm compilation cache.
 pub const MAX_COMPILATION_CACHE_SIZE: NumBytes = NumBytes::new(10 * GIB);
 
@@ -141,6 +156,10 @@ pub struct Config {
     /// across the whole subnet.
     pub subnet_message_memory_capacity: NumBytes,
 
+    /// The maximum amount of logical storage available to best-effort canister
+    /// requests across the whole subnet.
+    pub best_effort_message_memory_capacity: NumBytes,
+
     /// The maximum amount of logical storage available to the ingress history
     /// across the whole subnet.
     pub ingress_history_memory_capacity: NumBytes,
@@ -231,6 +250,13 @@ pub struct Config {
     /// Query cache capacity in bytes
     pub query_cache_capacity: NumBytes,
 
+    /// Indicates whether statistics about the executed queries are collected
+    /// and aggregated through consensus.
+    pub query_stats_aggregation: FlagStatus,
+
+    /// The length of a query statistics epoch in blocks.
+    pub query_stats_epoch_length: u64,
+
     /// Sandbox process eviction does not activate if the number of sandbox
     /// processes is below this threshold.
     pub min_sandbox_count: usize,
@@ -253,6 +279,11 @@ pub struct Config {
     /// If this flag is enabled, then execution of a slice will produce a log
     /// entry with the number of executed instructions and the duration.
     pub trace_execution: FlagStatus,
+
+    /// If this flag is enabled, then canisters may use a 64-bit Wasm memory
+    /// and `max_canister_memory_size` is raised to at least
+    /// [`MAX_WASM64_CANISTER_MEMORY_SIZE`].
+    pub wasm64: FlagStatus,
 }
 
 impl Default for Config {
@@ -273,6 +304,7 @@ impl Default for Config {
             subnet_memory_threshold: SUBNET_MEMORY_THRESHOLD,
             subnet_memory_capacity: SUBNET_MEMORY_CAPACITY,
             subnet_message_memory_capacity: SUBNET_MESSAGE_MEMORY_CAPACITY,
+            best_effort_message_memory_capacity: BEST_EFFORT_MESSAGE_MEMORY_CAPACITY,
             ingress_history_memory_capacity: INGRESS_HISTORY_MEMORY_CAPACITY,
             subnet_wasm_custom_sections_memory_capacity:
                 SUBNET_WASM_CUSTOM_SECTIONS_MEMORY_CAPACITY,
@@ -318,6 +350,8 @@ impl Default for Config {
             composite_queries: FlagStatus::Enabled,
             query_caching: FlagStatus::Enabled,
             query_cache_capacity: QUERY_CACHE_CAPACITY,
+            query_stats_aggregation: FlagStatus::Disabled,
+            query_stats_epoch_length: QUERY_STATS_EPOCH_LENGTH,
             min_sandbox_count: embedders::DEFAULT_MIN_SANDBOX_COUNT,
             max_sandbox_count: embedders::DEFAULT_MAX_SANDBOX_COUNT,
             max_sandbox_idle_time: embedders::DEFAULT_MAX_SANDBOX_IDLE_TIME,
@@ -326,6 +360,7 @@ impl Default for Config {
             ),
             max_compilation_cache_size: MAX_COMPILATION_CACHE_SIZE,
             trace_execution: FlagStatus::Disabled,
+            wasm64: FlagStatus::Disabled,
         }
     }
 }
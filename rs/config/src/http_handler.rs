@@ -59,11 +59,20 @@ pub struct Config {
     /// Serving at most `max_call_concurrent_requests` requests concurrently for endpoint `/api/v2/call`.
     pub max_call_concurrent_requests: usize,
 
+    /// Serving at most `max_call_v3_concurrent_requests` requests concurrently for endpoint `/api/v3/call`.
+    pub max_call_v3_concurrent_requests: usize,
+
     /// Serving at most `max_call_concurrent_requests` requests concurrently for endpoint `/api/v2/query`.
     pub max_query_concurrent_requests: usize,
 
     /// Serving at most `max_pprof_concurrent_requests` requessts concurrently for all endpoints under `/_/pprof`.
     pub max_pprof_concurrent_requests: usize,
+
+    /// Requests to the synchronous endpoint `/api/v3/call` wait at most
+    /// `ingress_message_certificate_timeout_seconds` for the message to be executed.
+    /// If the timeout is reached, [`202 Accepted`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/202)
+    /// is returned and the user has to poll `/api/v2/read_state` for the status.
+    pub ingress_message_certificate_timeout_seconds: u64,
 }
 
 impl Default for Config {
@@ -85,8 +94,10 @@ impl Default for Config {
             max_dashboard_concurrent_requests: 100,
             max_status_concurrent_requests: 100,
             max_call_concurrent_requests: 50,
+            max_call_v3_concurrent_requests: 50,
             max_query_concurrent_requests: QUERY_EXECUTION_THREADS_TOTAL * 100,
             max_pprof_concurrent_requests: 5,
+            ingress_message_certificate_timeout_seconds: 10,
         }
     }
 }
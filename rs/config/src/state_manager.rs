@@ -8,6 +8,11 @@ pub struct Config {
     /// A feature flag that enables/disables the file backed memory allocator.
     #[serde(default = "file_backed_memory_allocator_default")]
     pub file_backed_memory_allocator: FlagStatus,
+    /// A feature flag that enables/disables the log-structured storage of
+    /// canister memories, which persists modified pages as overlay files
+    /// instead of applying them to the full memory files.
+    #[serde(default = "lsmt_storage_default")]
+    pub lsmt_storage: FlagStatus,
 }
 
 impl Config {
@@ -15,6 +20,7 @@ impl Config {
         Self {
             state_root,
             file_backed_memory_allocator: file_backed_memory_allocator_default(),
+            lsmt_storage: lsmt_storage_default(),
         }
     }
 
@@ -32,3 +38,7 @@ impl Config {
 fn file_backed_memory_allocator_default() -> FlagStatus {
     FlagStatus::Enabled
 }
+
+fn lsmt_storage_default() -> FlagStatus {
+    FlagStatus::Disabled
+}
@@ -387,6 +387,11 @@ pub struct CyclesAccountManagerConfig {
 
     /// Fee per byte for networking and consensus work done for an HTTP response per node.
     pub http_response_per_byte_fee: Cycles,
+
+    /// The upper bound on the storage reservation period. When the subnet
+    /// memory usage is at capacity, every newly allocated byte reserves
+    /// cycles for storing it during this period.
+    pub max_storage_reservation_period: Duration,
 }
 
 impl CyclesAccountManagerConfig {
@@ -417,6 +422,7 @@ impl CyclesAccountManagerConfig {
             http_request_quadratic_baseline_fee: Cycles::new(60_000),
             http_request_per_byte_fee: Cycles::new(400),
             http_response_per_byte_fee: Cycles::new(800),
+            max_storage_reservation_period: Duration::from_secs(300_000_000),
         }
     }
 
@@ -447,6 +453,7 @@ impl CyclesAccountManagerConfig {
             http_request_quadratic_baseline_fee: Cycles::new(0),
             http_request_per_byte_fee: Cycles::new(0),
             http_response_per_byte_fee: Cycles::new(0),
+            max_storage_reservation_period: Duration::from_secs(0),
         }
     }
 }
@@ -34,7 +34,7 @@ use ic_types::{
         canister_threshold_sig::MasterEcdsaPublicKey,
         threshold_sig::ni_dkg::{NiDkgId, NiDkgTag, NiDkgTranscript},
     },
-    messages::{CallbackId, Payload, RejectContext, Response},
+    messages::{CallbackId, Payload, RejectContext, Response, NO_DEADLINE},
     CanisterId, Cycles, Height, PrincipalId, Randomness, ReplicaVersion, SubnetId,
 };
 use std::collections::BTreeMap;
@@ -356,6 +356,7 @@ pub fn generate_responses_to_setup_initial_dkg_calls(
                 originator_reply_callback: callback_id,
                 refund: Cycles::zero(),
                 response_payload,
+                deadline: NO_DEADLINE,
             });
         }
     }
@@ -159,6 +159,7 @@ impl<'a> ConsensusRunner<'a> {
             deps.xnet_payload_builder.clone(),
             deps.self_validating_payload_builder.clone(),
             deps.canister_http_payload_builder.clone(),
+            deps.query_stats_payload_builder.clone(),
             deps.dkg_pool.clone(),
             deps.ecdsa_pool.clone(),
             dkg_key_manager.clone(),
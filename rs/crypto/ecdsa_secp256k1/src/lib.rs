@@ -481,4 +481,25 @@ impl PublicKey {
             self.key.verify_prehash(digest, &signature).is_ok()
         }
     }
+
+    /// Determine the y-parity of the point R for a (message digest,signature) pair
+    ///
+    /// Some protocols, for example Ethereum, require a signature to carry a
+    /// recovery identifier in addition to (r,s) so that the public key can be
+    /// recovered from the signature. The recovery identifier is the parity of
+    /// the y coordinate of R.
+    ///
+    /// Returns None if the signature is not valid for this key and digest.
+    pub fn recover_y_parity_prehashed(&self, digest: &[u8], signature: &[u8]) -> Option<bool> {
+        use k256::ecdsa::{RecoveryId, VerifyingKey};
+
+        let signature = k256::ecdsa::Signature::try_from(signature).ok()?;
+
+        [false, true].into_iter().find(|&y_is_odd| {
+            let recovery_id = RecoveryId::new(y_is_odd, false);
+            VerifyingKey::recover_from_prehash(digest, &signature, recovery_id)
+                .map(|recovered_key| recovered_key == self.key)
+                .unwrap_or(false)
+        })
+    }
 }
@@ -43,6 +43,32 @@ fn test_sign_prehash_works_with_any_size_input_gte_16() {
     }
 }
 
+#[test]
+fn should_recover_y_parity_of_signatures() {
+    let mut rng = reproducible_rng();
+
+    let sk = PrivateKey::generate_using_rng(&mut rng);
+    let pk = sk.public_key();
+    let other_pk = PrivateKey::generate_using_rng(&mut rng).public_key();
+
+    let mut seen_parities = std::collections::BTreeSet::new();
+
+    for i in 0..64u8 {
+        let digest = [i; 32];
+        let sig = sk.sign_digest(&digest).unwrap();
+
+        let y_parity = pk.recover_y_parity_prehashed(&digest, &sig);
+        assert!(y_parity.is_some());
+        seen_parities.insert(y_parity.unwrap());
+
+        assert_eq!(other_pk.recover_y_parity_prehashed(&digest, &sig), None);
+        assert_eq!(pk.recover_y_parity_prehashed(&[i ^ 1; 32], &sig), None);
+    }
+
+    // Both parities occur with overwhelming probability
+    assert_eq!(seen_parities.len(), 2);
+}
+
 #[test]
 fn should_use_rfc6979_nonces_for_ecdsa_signature_generation() {
     // Unfortunately RFC 6979 does not include tests for secp256k1. This
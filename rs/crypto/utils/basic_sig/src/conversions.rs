@@ -230,12 +230,26 @@ pub enum InvalidNodePublicKey {
 /// # Returns
 /// * The NodeId associated to the key
 pub fn derive_node_id(node_signing_pk: &PublicKeyProto) -> Result<NodeId, InvalidNodePublicKey> {
+    let der_pk = node_signing_public_key_to_der(node_signing_pk)?;
+    Ok(NodeId::from(PrincipalId::new_self_authenticating(&der_pk)))
+}
+
+/// Encodes the given (Protobuf-serialized) node signing public key as DER
+///
+/// # Errors
+/// * `InvalidNodePublicKey::MalformedRawBytes` if the provided key is not a
+///   proper Ed25519 public key
+///
+/// # Returns
+/// * The DER-encoded Ed25519 public key
+pub fn node_signing_public_key_to_der(
+    node_signing_pk: &PublicKeyProto,
+) -> Result<Vec<u8>, InvalidNodePublicKey> {
     let raw_key = &node_signing_pk.key_value;
     let pk_bytes = internal_types::PublicKey::try_from(&raw_key[..]).map_err(|e| {
         InvalidNodePublicKey::MalformedRawBytes {
             internal_error: format!("{:?}", e),
         }
     })?;
-    let der_pk = pk_bytes.to_der();
-    Ok(NodeId::from(PrincipalId::new_self_authenticating(&der_pk)))
+    Ok(pk_bytes.to_der())
 }
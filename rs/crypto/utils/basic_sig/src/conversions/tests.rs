@@ -175,3 +175,21 @@ fn should_convert_pubkey_nodeid_known_result() {
 }
 
 // TODO(CRP-695): add more tests
+
+#[test]
+fn should_convert_node_signing_pubkey_to_der_matching_test_data() {
+    let proto_key = PublicKeyProto {
+        version: 1,
+        algorithm: 0,
+        key_value: hex::decode(test_data::ED25519_PK_1_HEX).expect("Invalid hex in test data"),
+        proof_data: None,
+        timestamp: None,
+    };
+
+    let der = node_signing_public_key_to_der(&proto_key).expect("Conversion to DER failed");
+
+    assert_eq!(
+        der,
+        hex::decode(test_data::ED25519_PK_1_DER_HEX).expect("Invalid hex in test data")
+    );
+}
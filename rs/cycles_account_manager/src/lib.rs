@@ -61,6 +61,83 @@ impl std::fmt::Display for CyclesAccountManagerError {
     }
 }
 
+/// Describes how much of a subnet resource, such as memory, is in use.
+///
+/// Below `threshold` the resource is cheap to allocate. Between `threshold`
+/// and `capacity` every newly allocated unit reserves cycles, and the amount
+/// grows linearly with the usage above the threshold.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceSaturation {
+    usage: u64,
+    threshold: u64,
+    capacity: u64,
+}
+
+impl ResourceSaturation {
+    /// Scale of the fixed-point fractions returned by `reservation_factor`.
+    const SCALE: u128 = 1_000_000_000;
+
+    pub fn new(usage: u64, threshold: u64, capacity: u64) -> Self {
+        let threshold = threshold.min(capacity);
+        let usage = usage.min(capacity);
+        Self {
+            usage,
+            threshold,
+            capacity,
+        }
+    }
+
+    /// Creates a saturation from the amount of the resource that is still
+    /// available rather than from the usage.
+    pub fn new_from_available(available: u64, threshold: u64, capacity: u64) -> Self {
+        Self::new(capacity.saturating_sub(available), threshold, capacity)
+    }
+
+    pub fn usage(&self) -> u64 {
+        self.usage
+    }
+
+    pub fn threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Returns the saturation after `allocated` more units are in use.
+    pub fn add(&self, allocated: u64) -> Self {
+        Self::new(
+            self.usage.saturating_add(allocated),
+            self.threshold,
+            self.capacity,
+        )
+    }
+
+    /// Returns the fraction of the storage reservation to charge for
+    /// allocating `allocated` units, scaled by `Self::SCALE`.
+    ///
+    /// The fraction is the average over the allocated range of a function
+    /// that is zero at the threshold and one at the capacity.
+    fn reservation_factor(&self, allocated: u64) -> u128 {
+        if allocated == 0 || self.threshold >= self.capacity {
+            return 0;
+        }
+        let new_usage = self.usage.saturating_add(allocated);
+        let above_threshold =
+            |usage: u64| usage.min(self.capacity).saturating_sub(self.threshold) as u128;
+        let before = above_threshold(self.usage);
+        let after = above_threshold(new_usage);
+        let range = (self.capacity - self.threshold) as u128;
+        // The integral of `(x - threshold) / range` over the allocated units
+        // between the threshold and the capacity. Units beyond the capacity
+        // count fully.
+        let below_capacity = (after * after - before * before) * Self::SCALE / 2 / range;
+        let above_capacity = new_usage.saturating_sub(self.capacity) as u128 * Self::SCALE;
+        (below_capacity + above_capacity) / allocated as u128
+    }
+}
+
 /// Handles any operation related to cycles accounting, such as charging (due to
 /// using system resources) or refunding unused cycles.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -492,6 +569,13 @@ impl CyclesAccountManager {
         self.scale_cost(self.config.ecdsa_signature_fee, subnet_size)
     }
 
+    /// Amount to charge for taking or loading a canister snapshot of the
+    /// given size. Copying the state is charged like executing one
+    /// instruction per byte.
+    pub fn canister_snapshot_fee(&self, snapshot_size: NumBytes, subnet_size: usize) -> Cycles {
+        self.execution_cost(NumInstructions::from(snapshot_size.get()), subnet_size)
+    }
+
     ////////////////////////////////////////////////////////////////////////////
     //
     // Storage
@@ -527,6 +611,31 @@ impl CyclesAccountManager {
         )
     }
 
+    /// Returns the number of cycles to move to the reserved balance of a
+    /// canister that allocates `allocated_bytes` of memory on a subnet with the
+    /// given memory saturation.
+    ///
+    /// Nothing is reserved while the subnet memory usage stays below the
+    /// threshold. Above it, the reservation grows linearly up to the cost of
+    /// storing the bytes for `max_storage_reservation_period` at capacity.
+    pub fn storage_reservation_cycles(
+        &self,
+        allocated_bytes: NumBytes,
+        subnet_memory_saturation: &ResourceSaturation,
+        subnet_size: usize,
+    ) -> Cycles {
+        let factor = subnet_memory_saturation.reservation_factor(allocated_bytes.get());
+        if factor == 0 {
+            return Cycles::zero();
+        }
+        let max_reservation = self.memory_cost(
+            allocated_bytes,
+            self.config.max_storage_reservation_period,
+            subnet_size,
+        );
+        Cycles::from(max_reservation.get() * factor / ResourceSaturation::SCALE)
+    }
+
     /// The cost of using `bytes` worth of memory.
     #[doc(hidden)] // pub for usage in tests
     pub fn memory_cost(&self, bytes: NumBytes, duration: Duration, subnet_size: usize) -> Cycles {
@@ -707,14 +816,28 @@ impl CyclesAccountManager {
         threshold: Cycles,
         use_case: CyclesUseCase,
     ) -> Result<(), CanisterOutOfCyclesError> {
+        // Resource allocation fees are paid from the reserved balance first.
+        let pays_from_reserved_balance = matches!(
+            use_case,
+            CyclesUseCase::Memory | CyclesUseCase::ComputeAllocation
+        );
+        let effective_balance = if pays_from_reserved_balance {
+            system_state.balance() + system_state.reserved_balance()
+        } else {
+            system_state.balance()
+        };
         self.verify_cycles_balance_with_treshold(
             system_state.canister_id,
-            system_state.balance(),
+            effective_balance,
             cycles,
             threshold,
         )?;
 
-        system_state.remove_cycles(cycles, use_case);
+        if pays_from_reserved_balance {
+            system_state.remove_charge_from_cycles_balance_and_reserved_balance(cycles, use_case);
+        } else {
+            system_state.remove_cycles(cycles, use_case);
+        }
         system_state.observe_consumed_cycles(cycles);
         Ok(())
     }
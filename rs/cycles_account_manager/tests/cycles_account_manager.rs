@@ -1,7 +1,7 @@
 use ic_base_types::NumSeconds;
 use ic_config::subnet_config::SubnetConfig;
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
-use ic_cycles_account_manager::IngressInductionCost;
+use ic_cycles_account_manager::{IngressInductionCost, ResourceSaturation};
 use ic_ic00_types::{CanisterIdRecord, Payload, IC_00};
 use ic_interfaces::execution_environment::CanisterOutOfCyclesError;
 use ic_logger::replica_logger::no_op_logger;
@@ -91,7 +91,7 @@ fn withdraw_cycles_with_not_enough_balance_returns_error() {
     let amount = Cycles::new(200);
     {
         let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
-        let mut system_state = SystemState::new_running(
+        let mut system_state = SystemState::new_running_for_testing(
             canister_test_id(1),
             canister_test_id(2).get(),
             initial_cycles,
@@ -124,7 +124,7 @@ fn withdraw_cycles_with_not_enough_balance_returns_error() {
 
     {
         let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
-        let mut system_state = SystemState::new_running(
+        let mut system_state = SystemState::new_running_for_testing(
             canister_test_id(1),
             canister_test_id(2).get(),
             initial_cycles,
@@ -157,7 +157,7 @@ fn withdraw_cycles_with_not_enough_balance_returns_error() {
 
     {
         let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
-        let mut system_state = SystemState::new_running(
+        let mut system_state = SystemState::new_running_for_testing(
             canister_test_id(1),
             canister_test_id(2).get(),
             initial_cycles,
@@ -192,7 +192,7 @@ fn withdraw_cycles_with_not_enough_balance_returns_error() {
 
     {
         let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
-        let system_state = SystemState::new_running(
+        let system_state = SystemState::new_running_for_testing(
             canister_test_id(1),
             canister_test_id(2).get(),
             initial_cycles,
@@ -559,7 +559,7 @@ fn cycles_withdraw_for_execution() {
     let initial_cycles = Cycles::from(initial_amount);
     let freeze_threshold = NumSeconds::from(10);
     let canister_id = canister_test_id(1);
-    let mut system_state = SystemState::new_running(
+    let mut system_state = SystemState::new_running_for_testing(
         canister_id,
         canister_test_id(2).get(),
         initial_cycles,
@@ -763,3 +763,86 @@ fn consume_cycles_updates_consumed_cycles() {
         NominalCycles::from(1_000_000)
     );
 }
+
+#[test]
+fn storage_reservation_is_zero_below_threshold() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new()
+        .with_subnet_type(SubnetType::Application)
+        .build();
+    let saturation = ResourceSaturation::new(100, 1_000, 2_000);
+    assert_eq!(
+        cycles_account_manager.storage_reservation_cycles(
+            NumBytes::from(900),
+            &saturation,
+            SMALL_APP_SUBNET_MAX_SIZE
+        ),
+        Cycles::zero()
+    );
+    // Without headroom between threshold and capacity nothing is reserved.
+    let saturation = ResourceSaturation::new(1_000, 2_000, 2_000);
+    assert_eq!(
+        cycles_account_manager.storage_reservation_cycles(
+            NumBytes::from(1 << 30),
+            &saturation,
+            SMALL_APP_SUBNET_MAX_SIZE
+        ),
+        Cycles::zero()
+    );
+}
+
+#[test]
+fn storage_reservation_grows_with_subnet_memory_usage() {
+    let subnet_size = SMALL_APP_SUBNET_MAX_SIZE;
+    let config = SubnetConfig::new(SubnetType::Application).cycles_account_manager_config;
+    let cycles_account_manager = CyclesAccountManagerBuilder::new()
+        .with_subnet_type(SubnetType::Application)
+        .build();
+    let gib = 1 << 30;
+    let bytes = NumBytes::from(gib);
+    let max_reservation = cycles_account_manager.memory_cost(
+        bytes,
+        config.max_storage_reservation_period,
+        subnet_size,
+    );
+    let reservation = |usage: u64| {
+        cycles_account_manager.storage_reservation_cycles(
+            bytes,
+            &ResourceSaturation::new(usage, 10 * gib, 20 * gib),
+            subnet_size,
+        )
+    };
+
+    // Allocating the first GiB above the threshold reserves on average 5% of
+    // the maximum, and the reservation keeps growing towards the capacity.
+    assert_eq!(reservation(10 * gib), max_reservation * 5_u64 / 100_u64);
+    assert!(reservation(15 * gib) > reservation(10 * gib));
+    assert_eq!(reservation(20 * gib), max_reservation);
+}
+
+#[test]
+fn charge_for_memory_uses_reserved_balance_first() {
+    let subnet_size = SMALL_APP_SUBNET_MAX_SIZE;
+    let mut system_state = SystemStateBuilder::new()
+        .initial_cycles(Cycles::new(1_000_000_000))
+        .build();
+    let cycles_account_manager = CyclesAccountManagerBuilder::new()
+        .with_subnet_type(SubnetType::Application)
+        .build();
+    let duration = Duration::from_secs(1);
+    let bytes = NumBytes::from(1 << 30);
+    let fee = cycles_account_manager.memory_cost(bytes, duration, subnet_size);
+    system_state.reserve_cycles(fee * 3_u64 / 2_u64).unwrap();
+    let balance_before = system_state.balance();
+
+    cycles_account_manager
+        .charge_for_memory(&mut system_state, bytes, duration, subnet_size)
+        .unwrap();
+    assert_eq!(system_state.balance(), balance_before);
+    assert_eq!(system_state.reserved_balance(), fee / 2_u64);
+
+    cycles_account_manager
+        .charge_for_memory(&mut system_state, bytes, duration, subnet_size)
+        .unwrap();
+    assert_eq!(system_state.reserved_balance(), Cycles::zero());
+    assert_eq!(system_state.balance(), balance_before - fee / 2_u64);
+}
@@ -466,6 +466,7 @@ pub fn wasm_execution_error(
             allocated_bytes: NumBytes::from(0),
             allocated_message_bytes: NumBytes::from(0),
             instance_stats: InstanceStats::default(),
+            canister_log: Default::default(),
         },
         None,
     )
@@ -627,6 +628,7 @@ pub fn process(
                     allocated_bytes: NumBytes::from(0),
                     allocated_message_bytes: NumBytes::from(0),
                     instance_stats: InstanceStats::default(),
+                    canister_log: Default::default(),
                 },
                 None,
                 Err(system_api),
@@ -667,6 +669,7 @@ pub fn process(
         .store_data_mut()
         .system_api
         .take_execution_result(run_result.as_ref().err());
+    let canister_log = instance.store_data_mut().system_api.take_canister_log();
 
     let wasm_heap_size_after = instance.heap_size(CanisterMemoryType::Heap);
     let wasm_heap_limit =
@@ -748,6 +751,7 @@ pub fn process(
             allocated_bytes,
             allocated_message_bytes,
             instance_stats,
+            canister_log,
         },
         wasm_state_changes,
         Ok(instance),
@@ -31,6 +31,8 @@
 //! (import "__" "stable_read_first_access" (func ((param i64) (param i64) (param i64))))
 //! ```
 //! Where the last three will only be inserted if Wasm-native stable memory is enabled.
+//! In Wasm64 modules, `update_available_memory` takes and returns the 64-bit
+//! values of `memory.grow` instead: `(param i64 i64 i32) (result i64)`.
 //!
 //! It then inserts (and exports) a global mutable counter:
 //! ```wasm
@@ -91,7 +93,9 @@
 //!
 //! Before every bulk memory operation, a call is made to the function which
 //! will decrement the instruction counter by the "size" argument of the bulk
-//! memory instruction.
+//! memory instruction. The `i32` size is first extended to an `i64`, except
+//! for `memory.fill` and `memory.copy` in Wasm64 modules, whose size already
+//! is an `i64`.
 //!
 //! Note that we omit checking for the counter overflow at the non-reentrant
 //! blocks to optimize for performance. The maximal overflow in that case is
@@ -117,7 +121,7 @@ use ic_config::flag_status::FlagStatus;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::NumWasmPages;
 use ic_sys::PAGE_SIZE;
-use ic_types::{methods::WasmMethod, MAX_WASM_MEMORY_IN_BYTES};
+use ic_types::{methods::WasmMethod, MAX_WASM64_MEMORY_IN_BYTES, MAX_WASM_MEMORY_IN_BYTES};
 use ic_types::{NumInstructions, MAX_STABLE_MEMORY_IN_BYTES};
 use ic_wasm_types::{BinaryEncodedWasm, WasmError, WasmInstrumentationError};
 use wasmtime_environ::WASM_PAGE_SIZE;
@@ -185,6 +189,10 @@ const CANISTER_START_STR: &str = "canister_start";
 const BYTEMAP_SIZE_IN_WASM_PAGES: u64 =
     MAX_WASM_MEMORY_IN_BYTES / (PAGE_SIZE as u64) / (WASM_PAGE_SIZE as u64);
 
+const MAX_WASM64_MEMORY_IN_WASM_PAGES: u64 = MAX_WASM64_MEMORY_IN_BYTES / (WASM_PAGE_SIZE as u64);
+/// There is one byte for each OS page in the heap of a Wasm64 module.
+const WASM64_BYTEMAP_SIZE_IN_WASM_PAGES: u64 = MAX_WASM64_MEMORY_IN_WASM_PAGES / (PAGE_SIZE as u64);
+
 const MAX_STABLE_MEMORY_IN_WASM_PAGES: u64 = MAX_STABLE_MEMORY_IN_BYTES / (WASM_PAGE_SIZE as u64);
 /// There is one byte for each OS page in the stable memory.
 const STABLE_BYTEMAP_SIZE_IN_WASM_PAGES: u64 = MAX_STABLE_MEMORY_IN_WASM_PAGES / (PAGE_SIZE as u64);
@@ -269,13 +277,24 @@ fn mutate_function_indices(module: &mut Module, f: impl Fn(u32) -> u32) {
 /// added as the last imports, we'd need to increment only non imported
 /// functions, since imported functions precede all others in the function index
 /// space, but this would be error-prone).
-fn inject_helper_functions(mut module: Module, wasm_native_stable_memory: FlagStatus) -> Module {
+fn inject_helper_functions(
+    mut module: Module,
+    wasm_native_stable_memory: FlagStatus,
+    is_wasm64: bool,
+) -> Module {
     // insert types
     let ooi_type = Type::Func(FuncType::new([], []));
-    let uam_type = Type::Func(FuncType::new(
-        [ValType::I32, ValType::I32, ValType::I32],
-        [ValType::I32],
-    ));
+    let uam_type = if is_wasm64 {
+        Type::Func(FuncType::new(
+            [ValType::I64, ValType::I64, ValType::I32],
+            [ValType::I64],
+        ))
+    } else {
+        Type::Func(FuncType::new(
+            [ValType::I32, ValType::I32, ValType::I32],
+            [ValType::I32],
+        ))
+    };
 
     let ooi_type_idx = add_type(&mut module, ooi_type);
     let uam_type_idx = add_type(&mut module, uam_type);
@@ -391,10 +410,14 @@ pub(super) fn instrument(
     dirty_page_overhead: NumInstructions,
 ) -> Result<InstrumentationOutput, WasmInstrumentationError> {
     let stable_memory_index;
-    let mut module = inject_helper_functions(module, wasm_native_stable_memory);
+    let is_wasm64 = module
+        .memories
+        .first()
+        .map_or(false, |memory| memory.memory64);
+    let mut module = inject_helper_functions(module, wasm_native_stable_memory, is_wasm64);
     module = export_table(module);
     (module, stable_memory_index) =
-        update_memories(module, write_barrier, wasm_native_stable_memory);
+        update_memories(module, write_barrier, wasm_native_stable_memory, is_wasm64);
 
     let mut extra_strs: Vec<String> = Vec::new();
     module = export_mutable_globals(module, &mut extra_strs);
@@ -448,7 +471,7 @@ pub(super) fn instrument(
 
     // inject instructions counter decrementation
     for func_body in &mut module.code_sections {
-        inject_metering(&mut func_body.instructions, &special_indices);
+        inject_metering(&mut func_body.instructions, &special_indices, is_wasm64);
     }
 
     // Collect all the function types of the locally defined functions inside the
@@ -468,9 +491,9 @@ pub(super) fn instrument(
     if !func_types.is_empty() {
         let func_bodies = &mut module.code_sections;
         for (func_ix, func_type) in func_types.into_iter().enumerate() {
-            inject_update_available_memory(&mut func_bodies[func_ix], &func_type);
+            inject_update_available_memory(&mut func_bodies[func_ix], &func_type, is_wasm64);
             if write_barrier == FlagStatus::Enabled {
-                inject_mem_barrier(&mut func_bodies[func_ix], &func_type);
+                inject_mem_barrier(&mut func_bodies[func_ix], &func_type, is_wasm64);
             }
         }
     }
@@ -483,6 +506,7 @@ pub(super) fn instrument(
             special_indices,
             subnet_type,
             dirty_page_overhead,
+            is_wasm64,
         )
     }
 
@@ -561,6 +585,7 @@ fn replace_system_api_functions(
     special_indices: SpecialIndices,
     subnet_type: SubnetType,
     dirty_page_overhead: NumInstructions,
+    is_wasm64: bool,
 ) {
     let api_indexes = calculate_api_indexes(module);
     let number_of_func_imports = module
@@ -573,7 +598,7 @@ fn replace_system_api_functions(
     // replaced.
     let mut func_index_replacements = BTreeMap::new();
     for (api, (ty, body)) in
-        replacement_functions(special_indices, subnet_type, dirty_page_overhead)
+        replacement_functions(special_indices, subnet_type, dirty_page_overhead, is_wasm64)
     {
         if let Some(old_index) = api_indexes.get(&api) {
             let type_idx = add_type(module, ty);
@@ -830,7 +855,8 @@ enum Scope {
 // Describes how to calculate the instruction cost at this injection point.
 // `StaticCost` injection points contain information about the cost of the
 // following basic block. `DynamicCost` injection points assume there is an i32
-// on the stack which should be decremented from the instruction counter.
+// (or an i64 for bulk memory instructions in Wasm64 modules) on the stack which
+// should be decremented from the instruction counter.
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum InjectionPointCostDetail {
     StaticCost { scope: Scope, cost: u64 },
@@ -880,7 +906,7 @@ impl InjectionPoint {
 // - we insert a function call before each dynamic cost instruction which
 //   performs an overflow check and then decrements the counter by the value at
 //   the top of the stack.
-fn inject_metering(code: &mut Vec<Operator>, export_data_module: &SpecialIndices) {
+fn inject_metering(code: &mut Vec<Operator>, export_data_module: &SpecialIndices, is_wasm64: bool) {
     let points = injections(code);
     let points = points.iter().filter(|point| match point.cost_detail {
         InjectionPointCostDetail::StaticCost {
@@ -928,16 +954,27 @@ fn inject_metering(code: &mut Vec<Operator>, export_data_module: &SpecialIndices
                 }
             }
             InjectionPointCostDetail::DynamicCost => {
-                elems.extend_from_slice(&[
-                    I64ExtendI32U,
-                    Call {
+                let has_i64_size = is_wasm64
+                    && matches!(
+                        orig_elems[point.position],
+                        MemoryFill { .. } | MemoryCopy { .. }
+                    );
+                if has_i64_size {
+                    elems.push(Call {
                         function_index: export_data_module.decr_instruction_counter_fn,
-                    },
-                    // decr_instruction_counter returns it's argument unchanged,
-                    // so we can convert back to I32 without worrying about
-                    // overflows.
-                    I32WrapI64,
-                ]);
+                    });
+                } else {
+                    elems.extend_from_slice(&[
+                        I64ExtendI32U,
+                        Call {
+                            function_index: export_data_module.decr_instruction_counter_fn,
+                        },
+                        // decr_instruction_counter returns it's argument unchanged,
+                        // so we can convert back to I32 without worrying about
+                        // overflows.
+                        I32WrapI64,
+                    ]);
+                }
             }
         }
         last_injection_position = point.position;
@@ -952,76 +989,78 @@ fn write_barrier_instructions<'a>(
     offset: u64,
     val_arg_idx: u32,
     addr_arg_idx: u32,
+    is_wasm64: bool,
 ) -> Vec<Operator<'a>> {
     use Operator::*;
     let page_size_shift = PAGE_SIZE.trailing_zeros() as i32;
     let tracking_mem_idx = 1;
-    if offset % PAGE_SIZE as u64 == 0 {
-        vec![
-            LocalSet {
-                local_index: val_arg_idx,
-            }, // value
-            LocalTee {
-                local_index: addr_arg_idx,
-            }, // address
-            I32Const {
-                value: page_size_shift,
-            },
-            I32ShrU,
-            I32Const { value: 1 },
-            I32Store8 {
-                memarg: wasmparser::MemArg {
-                    align: 0,
-                    max_align: 0,
-                    offset: offset >> page_size_shift,
-                    memory: tracking_mem_idx,
+    let mut instructions = vec![
+        LocalSet {
+            local_index: val_arg_idx,
+        }, // value
+        LocalTee {
+            local_index: addr_arg_idx,
+        }, // address
+    ];
+    let bytemap_offset = if offset % PAGE_SIZE as u64 == 0 {
+        offset >> page_size_shift
+    } else {
+        if is_wasm64 {
+            instructions.extend_from_slice(&[
+                I64Const {
+                    value: offset as i64,
                 },
+                I64Add,
+            ]);
+        } else {
+            instructions.extend_from_slice(&[
+                I32Const {
+                    value: offset as i32,
+                },
+                I32Add,
+            ]);
+        }
+        0
+    };
+    // The bytemap is a 32-bit memory even if the heap is a 64-bit memory.
+    if is_wasm64 {
+        instructions.extend_from_slice(&[
+            I64Const {
+                value: page_size_shift as i64,
             },
-            // Put original params on the stack
-            LocalGet {
-                local_index: addr_arg_idx,
-            },
-            LocalGet {
-                local_index: val_arg_idx,
-            },
-        ]
+            I64ShrU,
+            I32WrapI64,
+        ]);
     } else {
-        vec![
-            LocalSet {
-                local_index: val_arg_idx,
-            }, // value
-            LocalTee {
-                local_index: addr_arg_idx,
-            }, // address
-            I32Const {
-                value: offset as i32,
-            },
-            I32Add,
+        instructions.extend_from_slice(&[
             I32Const {
                 value: page_size_shift,
             },
             I32ShrU,
-            I32Const { value: 1 },
-            I32Store8 {
-                memarg: wasmparser::MemArg {
-                    align: 0,
-                    max_align: 0,
-                    offset: 0,
-                    memory: tracking_mem_idx,
-                },
-            },
-            // Put original params on the stack
-            LocalGet {
-                local_index: addr_arg_idx,
-            },
-            LocalGet {
-                local_index: val_arg_idx,
-            },
-        ]
+        ]);
     }
+    instructions.extend_from_slice(&[
+        I32Const { value: 1 },
+        I32Store8 {
+            memarg: wasmparser::MemArg {
+                align: 0,
+                max_align: 0,
+                offset: bytemap_offset,
+                memory: tracking_mem_idx,
+            },
+        },
+        // Put original params on the stack
+        LocalGet {
+            local_index: addr_arg_idx,
+        },
+        LocalGet {
+            local_index: val_arg_idx,
+        },
+    ]);
+    instructions
 }
 
-fn inject_mem_barrier(func_body: &mut wasm_transform::Body, func_type: &FuncType) {
+fn inject_mem_barrier(func_body: &mut wasm_transform::Body, func_type: &FuncType, is_wasm64: bool) {
     use Operator::*;
     let mut val_i32_needed = false;
     let mut val_i64_needed = false;
@@ -1060,7 +1099,7 @@ fn inject_mem_barrier(func_body: &mut wasm_transform::Body, func_type: &FuncType
         // the total number of locals.
         let n_locals: u32 = func_body.locals.iter().map(|x| x.0).sum();
         let mut next_local = func_type.params().len() as u32 + n_locals;
-        let arg_i32_addr_idx = next_local;
+        let arg_addr_idx = next_local;
         next_local += 1;
 
         // conditionally add following locals
@@ -1069,13 +1108,18 @@ fn inject_mem_barrier(func_body: &mut wasm_transform::Body, func_type: &FuncType
         let arg_f32_val_idx;
         let arg_f64_val_idx;
 
+        if is_wasm64 {
+            func_body.locals.push((1, ValType::I64)); // addr local
+        } else {
+            func_body.locals.push((1, ValType::I32)); // addr local
+        }
+
         if val_i32_needed {
             arg_i32_val_idx = next_local;
             next_local += 1;
-            func_body.locals.push((2, ValType::I32)); // addr and val locals
+            func_body.locals.push((1, ValType::I32));
         } else {
             arg_i32_val_idx = u32::MAX; // not used
-            func_body.locals.push((1, ValType::I32)); // only addr local
         }
 
         if val_i64_needed {
@@ -1114,7 +1158,8 @@ fn inject_mem_barrier(func_body: &mut wasm_transform::Body, func_type: &FuncType
                     elems.extend_from_slice(&write_barrier_instructions(
                         memarg.offset,
                         arg_i32_val_idx,
-                        arg_i32_addr_idx,
+                        arg_addr_idx,
+                        is_wasm64,
                     ));
                 }
                 I64Store { memarg }
@@ -1124,21 +1169,24 @@ fn inject_mem_barrier(func_body: &mut wasm_transform::Body, func_type: &FuncType
                     elems.extend_from_slice(&write_barrier_instructions(
                         memarg.offset,
                         arg_i64_val_idx,
-                        arg_i32_addr_idx,
+                        arg_addr_idx,
+                        is_wasm64,
                     ));
                 }
                 F32Store { memarg } => {
                     elems.extend_from_slice(&write_barrier_instructions(
                         memarg.offset,
                         arg_f32_val_idx,
-                        arg_i32_addr_idx,
+                        arg_addr_idx,
+                        is_wasm64,
                     ));
                 }
                 F64Store { memarg } => {
                     elems.extend_from_slice(&write_barrier_instructions(
                         memarg.offset,
                         arg_f64_val_idx,
-                        arg_i32_addr_idx,
+                        arg_addr_idx,
+                        is_wasm64,
                     ));
                 }
                 _ => {}
@@ -1157,7 +1205,11 @@ fn inject_mem_barrier(func_body: &mut wasm_transform::Body, func_type: &FuncType
 // `table.grow` instruction to make sure that there's enough available memory
 // left to support the requested extra memory. If no `memory.grow` or
 // `table.grow` instructions are present then the code remains unchanged.
-fn inject_update_available_memory(func_body: &mut wasm_transform::Body, func_type: &FuncType) {
+fn inject_update_available_memory(
+    func_body: &mut wasm_transform::Body,
+    func_type: &FuncType,
+    is_wasm64: bool,
+) {
     // This is an overestimation of table element size computed based on the
     // existing canister limits.
     const TABLE_ELEMENT_SIZE: u32 = 1024;
@@ -1181,7 +1233,17 @@ fn inject_update_available_memory(func_body: &mut wasm_transform::Body, func_typ
         // the total number of locals.
         let n_locals: u32 = func_body.locals.iter().map(|x| x.0).sum();
         let memory_local_ix = func_type.params().len() as u32 + n_locals;
-        func_body.locals.push((1, ValType::I32));
+        // In Wasm64 modules `memory.grow` takes and returns an `i64` while
+        // `table.grow` still uses `i32`, so the latter needs its own local and
+        // its values are converted for the call to `update_available_memory`.
+        let table_local_ix = if is_wasm64 {
+            func_body.locals.push((1, ValType::I64));
+            func_body.locals.push((1, ValType::I32));
+            memory_local_ix + 1
+        } else {
+            func_body.locals.push((1, ValType::I32));
+            memory_local_ix
+        };
 
         let orig_elems = &func_body.instructions;
         let mut elems: Vec<Operator> = Vec::new();
@@ -1192,21 +1254,42 @@ fn inject_update_available_memory(func_body: &mut wasm_transform::Body, func_typ
             // At this point we have a memory.grow so the argument to it will be on top of
             // the stack, which we just assign to `memory_local_ix` with a local.tee
             // instruction.
-            elems.extend_from_slice(&[
-                LocalTee {
-                    local_index: memory_local_ix,
-                },
-                update_available_memory_instr,
-                LocalGet {
-                    local_index: memory_local_ix,
-                },
-                I32Const {
-                    value: element_size as i32,
-                },
-                Call {
-                    function_index: InjectedImports::UpdateAvailableMemory as u32,
-                },
-            ]);
+            if is_wasm64 && matches!(update_available_memory_instr, TableGrow { .. }) {
+                elems.extend_from_slice(&[
+                    LocalTee {
+                        local_index: table_local_ix,
+                    },
+                    update_available_memory_instr,
+                    I64ExtendI32S,
+                    LocalGet {
+                        local_index: table_local_ix,
+                    },
+                    I64ExtendI32U,
+                    I32Const {
+                        value: element_size as i32,
+                    },
+                    Call {
+                        function_index: InjectedImports::UpdateAvailableMemory as u32,
+                    },
+                    I32WrapI64,
+                ]);
+            } else {
+                elems.extend_from_slice(&[
+                    LocalTee {
+                        local_index: memory_local_ix,
+                    },
+                    update_available_memory_instr,
+                    LocalGet {
+                        local_index: memory_local_ix,
+                    },
+                    I32Const {
+                        value: element_size as i32,
+                    },
+                    Call {
+                        function_index: InjectedImports::UpdateAvailableMemory as u32,
+                    },
+                ]);
+            }
             last_injection_position = point + 1;
         }
         elems.extend_from_slice(&orig_elems[last_injection_position..]);
@@ -1284,7 +1367,8 @@ fn get_data(
                     memory_index: _,
                     offset_expr,
                 } => match offset_expr {
-                    Operator::I32Const { value } => *value as usize,
+                    Operator::I32Const { value } => *value as u32 as usize,
+                    Operator::I64Const { value } => *value as u64 as usize,
                     _ => return Err(WasmInstrumentationError::WasmDeserializeError(WasmError::new(
                         "complex initialization expressions for data segments are not supported!".into()
                     ))),
@@ -1331,9 +1415,21 @@ fn update_memories(
     mut module: Module,
     write_barrier: FlagStatus,
     wasm_native_stable_memory: FlagStatus,
+    is_wasm64: bool,
 ) -> (Module, u32) {
     let mut stable_index = 0;
 
+    // The heap of a Wasm64 module cannot grow beyond the maximum Wasm64
+    // memory size.
+    if is_wasm64 {
+        let heap = &mut module.memories[0];
+        heap.maximum = Some(
+            heap.maximum
+                .unwrap_or(MAX_WASM64_MEMORY_IN_WASM_PAGES)
+                .min(MAX_WASM64_MEMORY_IN_WASM_PAGES),
+        );
+    }
+
     let mut memory_already_exported = false;
     for export in &mut module.exports {
         if let ExternalKind::Memory = export.kind {
@@ -1352,11 +1448,16 @@ fn update_memories(
     }
 
     if write_barrier == FlagStatus::Enabled && !module.memories.is_empty() {
+        let bytemap_size_in_wasm_pages = if is_wasm64 {
+            WASM64_BYTEMAP_SIZE_IN_WASM_PAGES
+        } else {
+            BYTEMAP_SIZE_IN_WASM_PAGES
+        };
         module.memories.push(MemoryType {
             memory64: false,
             shared: false,
-            initial: BYTEMAP_SIZE_IN_WASM_PAGES,
-            maximum: Some(BYTEMAP_SIZE_IN_WASM_PAGES),
+            initial: bytemap_size_in_wasm_pages,
+            maximum: Some(bytemap_size_in_wasm_pages),
         });
 
         module.exports.push(Export {
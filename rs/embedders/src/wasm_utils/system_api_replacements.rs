@@ -30,6 +30,7 @@ pub(super) fn replacement_functions(
     special_indices: SpecialIndices,
     subnet_type: SubnetType,
     dirty_page_overhead: NumInstructions,
+    is_wasm64: bool,
 ) -> Vec<(SystemApiFunc, (Type, Body<'static>))> {
     let count_clean_pages_fn_index = special_indices.count_clean_pages_fn.unwrap();
     let dirty_pages_counter_index = special_indices.dirty_pages_counter_ix.unwrap();
@@ -40,6 +41,16 @@ pub(super) fn replacement_functions(
     use Operator::*;
     let page_size_shift = PAGE_SIZE.trailing_zeros() as i32;
     let stable_memory_bytemap_index = stable_memory_index + 1;
+    // Heap addresses and lengths are `i64` in Wasm64 modules and `i32`
+    // otherwise, so the conversions around copies to and from the heap depend
+    // on the type of the heap.
+    let max_heap_address = if is_wasm64 {
+        u64::MAX as i64
+    } else {
+        u32::MAX as i64
+    };
+    let from_i64_heap_address = if is_wasm64 { Nop } else { I32WrapI64 };
+    let from_i32_heap_address = if is_wasm64 { I64ExtendI32U } else { Nop };
     vec![
         (
             SystemApiFunc::StableSize,
@@ -458,11 +469,13 @@ pub(super) fn replacement_functions(
                             },
                             Else,
                             LocalGet { local_index: DST },
+                            from_i32_heap_address.clone(),
                             LocalGet { local_index: SRC },
                             I64ExtendI32U,
                             LocalGet {
                                 local_index: LENGTH,
                             },
+                            from_i32_heap_address.clone(),
                             MemoryCopy {
                                 dst_mem: 0,
                                 src_mem: stable_memory_index,
@@ -580,11 +593,11 @@ pub(super) fn replacement_functions(
                                 function_index: InjectedImports::InternalTrap as u32,
                             },
                             End,
-                            // check if these i64 hold valid i32 heap addresses
+                            // check if these i64 hold valid heap addresses
                             // check dst
                             LocalGet { local_index: DST },
                             I64Const {
-                                value: u32::MAX as i64,
+                                value: max_heap_address,
                             },
                             I64GtU,
                             If {
@@ -602,7 +615,7 @@ pub(super) fn replacement_functions(
                                 local_index: LENGTH,
                             },
                             I64Const {
-                                value: u32::MAX as i64,
+                                value: max_heap_address,
                             },
                             I64GtU,
                             If {
@@ -762,12 +775,12 @@ pub(super) fn replacement_functions(
                             },
                             Else,
                             LocalGet { local_index: DST },
-                            I32WrapI64,
+                            from_i64_heap_address.clone(),
                             LocalGet { local_index: SRC },
                             LocalGet {
                                 local_index: LENGTH,
                             },
-                            I32WrapI64,
+                            from_i64_heap_address.clone(),
                             MemoryCopy {
                                 dst_mem: 0,
                                 src_mem: stable_memory_index,
@@ -965,7 +978,9 @@ pub(super) fn replacement_functions(
                         LocalGet { local_index: 0 },
                         I64ExtendI32U,
                         LocalGet { local_index: 1 },
+                        from_i32_heap_address.clone(),
                         LocalGet { local_index: 2 },
+                        from_i32_heap_address,
                         MemoryCopy {
                             dst_mem: stable_memory_index,
                             src_mem: 0,
@@ -1071,11 +1086,11 @@ pub(super) fn replacement_functions(
                             function_index: InjectedImports::InternalTrap as u32,
                         },
                         End,
-                        // check if these i64 hold valid i32 heap addresses
+                        // check if these i64 hold valid heap addresses
                         // check src
                         LocalGet { local_index: 1 },
                         I64Const {
-                            value: u32::MAX as i64,
+                            value: max_heap_address,
                         },
                         I64GtU,
                         If {
@@ -1091,7 +1106,7 @@ pub(super) fn replacement_functions(
                         // check len
                         LocalGet { local_index: 2 },
                         I64Const {
-                            value: u32::MAX as i64,
+                            value: max_heap_address,
                         },
                         I64GtU,
                         If {
@@ -1193,9 +1208,9 @@ pub(super) fn replacement_functions(
                         // copy memory contents
                         LocalGet { local_index: 0 },
                         LocalGet { local_index: 1 },
-                        I32WrapI64,
+                        from_i64_heap_address.clone(),
                         LocalGet { local_index: 2 },
-                        I32WrapI64,
+                        from_i64_heap_address,
                         MemoryCopy {
                             dst_mem: stable_memory_index,
                             src_mem: 0,
@@ -13,7 +13,8 @@ use std::{
 
 use ic_system_api::ModificationTracking;
 use wasmtime::{
-    unix::StoreExt, Engine, Instance, Memory, Module, Mutability, OptLevel, Store, Val, ValType,
+    unix::StoreExt, Engine, ExternType, Instance, Memory, Module, Mutability, OptLevel, Store, Val,
+    ValType,
 };
 
 pub use host_memory::WasmtimeMemoryCreator;
@@ -133,6 +134,15 @@ fn get_exported_globals<T>(
         .collect()
 }
 
+/// Returns true if the heap memory exported by the instrumented module is a
+/// 64-bit memory, i.e. the canister is a Wasm64 canister.
+fn has_64_bit_heap(module: &Module) -> bool {
+    matches!(
+        module.get_export(WASM_HEAP_MEMORY_NAME),
+        Some(ExternType::Memory(memory_type)) if memory_type.is_64()
+    )
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum CanisterMemoryType {
     Heap,
@@ -191,7 +201,12 @@ impl WasmtimeEmbedder {
         {
             config.wasm_multi_memory(true);
         }
-        if embedder_config.feature_flags.wasm_native_stable_memory == FlagStatus::Enabled {
+        // Wasm64 canisters have a 64-bit heap. Native stable memory needs
+        // memory64 as well because the injected stable memory is always 64-bit,
+        // but `compile` rejects 64-bit heaps unless Wasm64 is enabled.
+        if embedder_config.feature_flags.wasm64 == FlagStatus::Enabled
+            || embedder_config.feature_flags.wasm_native_stable_memory == FlagStatus::Enabled
+        {
             config.wasm_memory64(true);
         }
         config
@@ -233,6 +248,13 @@ impl WasmtimeEmbedder {
                     format!("{:?}", e),
                 ))
             })?;
+        if self.config.feature_flags.wasm64 == FlagStatus::Disabled && has_64_bit_heap(&module) {
+            return Err(HypervisorError::WasmEngineError(
+                WasmEngineError::FailedToInstantiateModule(
+                    "64-bit Wasm memory is only supported if Wasm64 is enabled".to_string(),
+                ),
+            ));
+        }
         // Note that a wasmtime::Module object is cheaply clonable (just doing
         // a bit of reference counting, i.e. it is a "shallow copy"). This is
         // important because EmbedderCache is cloned frequently, and that must
@@ -327,14 +349,25 @@ impl WasmtimeEmbedder {
             },
         );
 
-        let linker = system_api::syscalls(
-            self.log.clone(),
-            canister_id,
-            &store,
-            self.config.feature_flags,
-            self.config.stable_memory_dirty_page_limit,
-            self.config.stable_memory_accessed_page_limit,
-        );
+        let linker = if has_64_bit_heap(module) {
+            system_api::syscalls::<S, u64>(
+                self.log.clone(),
+                canister_id,
+                &store,
+                self.config.feature_flags,
+                self.config.stable_memory_dirty_page_limit,
+                self.config.stable_memory_accessed_page_limit,
+            )
+        } else {
+            system_api::syscalls::<S, u32>(
+                self.log.clone(),
+                canister_id,
+                &store,
+                self.config.feature_flags,
+                self.config.stable_memory_dirty_page_limit,
+                self.config.stable_memory_accessed_page_limit,
+            )
+        };
 
         let instance = match linker.instantiate(&mut store, module) {
             Ok(instance) => instance,
@@ -443,6 +476,7 @@ impl WasmtimeEmbedder {
             write_barrier: self.config.feature_flags.write_barrier,
             wasm_native_stable_memory: self.config.feature_flags.wasm_native_stable_memory,
             modification_tracking,
+            is_wasm64,
             #[cfg(debug_assertions)]
             stable_memory_dirty_page_limit: self.config.stable_memory_dirty_page_limit,
         })
@@ -659,6 +693,9 @@ pub struct WasmtimeInstance<S: SystemApi> {
     write_barrier: FlagStatus,
     wasm_native_stable_memory: FlagStatus,
     modification_tracking: ModificationTracking,
+    /// Whether the module has a 64-bit heap, in which case callbacks take
+    /// their environment as an `i64`.
+    is_wasm64: bool,
     #[cfg(debug_assertions)]
     stable_memory_dirty_page_limit: ic_types::NumPages,
 }
@@ -792,7 +829,15 @@ impl<S: SystemApi> WasmtimeInstance<S> {
                         "unexpected null function reference".to_string(),
                     )
                 })?
-                .call(&mut self.store, &[Val::I32(closure.env as i32)], &mut [])
+                .call(
+                    &mut self.store,
+                    &[if self.is_wasm64 {
+                        Val::I64(closure.env as i64)
+                    } else {
+                        Val::I32(closure.env as i32)
+                    }],
+                    &mut [],
+                )
                 .map_err(wasmtime_error_to_hypervisor_error),
         }
         .map_err(|e| {
@@ -63,8 +63,9 @@ unsafe impl wasmtime::MemoryCreator for WasmtimeMemoryCreator {
     ) -> Result<Box<dyn wasmtime::LinearMemory>, String> {
         // We don't use the `reserved_size_in_bytes` because the size of the
         // memory allocation is determined based on the memory type: 64-bit
-        // memories have size at most the maximum stable memory size and 32-bit
-        // memories have size at most 4GiB. So we always allocate that amount
+        // memories (the stable memory and the heap of Wasm64 modules) have
+        // size at most the maximum stable memory size and 32-bit memories have
+        // size at most 4GiB. So we always allocate that amount
         // (unless the module explicitly lists a smaller maximum).
         //
         // If we get a `reserved_size_in_bytes` that exceeds the max stable
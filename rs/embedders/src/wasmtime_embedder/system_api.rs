@@ -12,10 +12,10 @@ use ic_registry_subnet_type::SubnetType;
 use ic_sys::PAGE_SIZE;
 use ic_types::{CanisterId, Cycles, NumBytes, NumInstructions, NumPages, Time};
 
-use wasmtime::{AsContextMut, Caller, Global, Linker, Store, Val};
+use wasmtime::{AsContextMut, Caller, Global, Linker, Store, Val, WasmTy};
 
 use crate::InternalErrorCode;
-use std::convert::TryFrom;
+use std::{convert::TryFrom, num::TryFromIntError};
 
 fn process_err<S: SystemApi>(
     store: &mut impl AsContextMut<Data = StoreData<S>>,
@@ -153,7 +153,7 @@ fn charge_for_system_api_call<S: SystemApi>(
     canister_id: CanisterId,
     caller: &mut Caller<'_, StoreData<S>>,
     system_api_overhead: NumInstructions,
-    num_bytes: u32,
+    num_bytes: usize,
     complexity: ExecutionComplexity,
     dirty_page_cost: NumInstructions,
     stable_memory_dirty_page_limit: NumPages,
@@ -360,7 +360,33 @@ fn ic0_performance_counter_helper<S: SystemApi>(
     }
 }
 
-pub(crate) fn syscalls<S: SystemApi>(
+/// The integer type of heap addresses and lengths passed to the System API:
+/// `u32` for Wasm32 modules and `u64` for Wasm64 modules.
+pub(crate) trait WasmAddress:
+    WasmTy + TryFrom<usize, Error = TryFromIntError> + Into<u64> + Copy + Send + Sync + 'static
+{
+    const IS_WASM64: bool;
+
+    fn to_usize(self) -> usize;
+}
+
+impl WasmAddress for u32 {
+    const IS_WASM64: bool = false;
+
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl WasmAddress for u64 {
+    const IS_WASM64: bool = true;
+
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+pub(crate) fn syscalls<S: SystemApi, I: WasmAddress>(
     log: ReplicaLogger,
     canister_id: CanisterId,
     store: &Store<StoreData<S>>,
@@ -406,7 +432,7 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "msg_caller_copy", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, dst: i32, offset: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I, offset: I, size: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -418,10 +444,15 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_msg_caller_copy(dst as u32, offset as u32, size as u32, memory)
+                    system_api.ic0_msg_caller_copy(
+                        dst.to_usize(),
+                        offset.to_usize(),
+                        size.to_usize(),
+                        memory,
+                    )
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as u32 as usize, size as u32 as usize)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), size.to_usize())
                 } else {
                     Ok(())
                 }
@@ -435,7 +466,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                 with_system_api(&mut caller, |s| s.ic0_msg_caller_size())
                     .map_err(|e| process_err(&mut caller, e))
                     .and_then(|s| {
-                        i32::try_from(s).map_err(|e| {
+                        I::try_from(s).map_err(|e| {
                             anyhow::Error::msg(format!("ic0::msg_caller_size failed: {}", e))
                         })
                     })
@@ -449,7 +480,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                 with_system_api(&mut caller, |s| s.ic0_msg_arg_data_size())
                     .map_err(|e| process_err(&mut caller, e))
                     .and_then(|s| {
-                        i32::try_from(s).map_err(|e| {
+                        I::try_from(s).map_err(|e| {
                             anyhow::Error::msg(format!("ic0::msg_arg_data_size failed: {}", e))
                         })
                     })
@@ -460,13 +491,13 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "msg_arg_data_copy", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, dst: i32, offset: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I, offset: I, size: I| {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::MSG_ARG_DATA_COPY,
-                    size as u32,
+                    size.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::MSG_ARG_DATA_COPY,
                         ..Default::default()
@@ -475,10 +506,15 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, mem| {
-                    system_api.ic0_msg_arg_data_copy(dst as u32, offset as u32, size as u32, mem)
+                    system_api.ic0_msg_arg_data_copy(
+                        dst.to_usize(),
+                        offset.to_usize(),
+                        size.to_usize(),
+                        mem,
+                    )
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as u32 as usize, size as u32 as usize)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), size.to_usize())
                 } else {
                     Ok(())
                 }
@@ -492,7 +528,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                 with_system_api(&mut caller, |s| s.ic0_msg_method_name_size())
                     .map_err(|e| process_err(&mut caller, e))
                     .and_then(|s| {
-                        i32::try_from(s).map_err(|e| {
+                        I::try_from(s).map_err(|e| {
                             anyhow::Error::msg(format!("ic0::msg_metohd_name_size failed: {}", e))
                         })
                     })
@@ -503,13 +539,13 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "msg_method_name_copy", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, dst: i32, offset: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I, offset: I, size: I| {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::MSG_METHOD_NAME_COPY,
-                    size as u32,
+                    size.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::MSG_METHOD_NAME_COPY,
                         ..Default::default()
@@ -519,14 +555,14 @@ pub(crate) fn syscalls<S: SystemApi>(
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
                     system_api.ic0_msg_method_name_copy(
-                        dst as u32,
-                        offset as u32,
-                        size as u32,
+                        dst.to_usize(),
+                        offset.to_usize(),
+                        size.to_usize(),
                         memory,
                     )
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as u32 as usize, size as u32 as usize)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), size.to_usize())
                 } else {
                     Ok(())
                 }
@@ -546,13 +582,13 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "msg_reply_data_append", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, src: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, src: I, size: I| {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::MSG_REPLY_DATA_APPEND,
-                    size as u32,
+                    size.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::MSG_REPLY_DATA_APPEND,
                         ..Default::default()
@@ -561,7 +597,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_msg_reply_data_append(src as u32, size as u32, memory)
+                    system_api.ic0_msg_reply_data_append(src.to_usize(), size.to_usize(), memory)
                 })
             }
         })
@@ -585,16 +621,25 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "msg_deadline", {
+            move |mut caller: Caller<'_, StoreData<S>>| {
+                with_system_api(&mut caller, |s| s.ic0_msg_deadline())
+                    .map_err(|e| process_err(&mut caller, e))
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "msg_reject", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, src: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, src: I, size: I| {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::MSG_REJECT,
-                    size as u32,
+                    size.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::MSG_REJECT,
                         ..Default::default()
@@ -603,7 +648,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_msg_reject(src as u32, size as u32, memory)
+                    system_api.ic0_msg_reject(src.to_usize(), size.to_usize(), memory)
                 })
             }
         })
@@ -615,7 +660,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                 with_system_api(&mut caller, |s| s.ic0_msg_reject_msg_size())
                     .map_err(|e| process_err(&mut caller, e))
                     .and_then(|s| {
-                        i32::try_from(s).map_err(|e| {
+                        I::try_from(s).map_err(|e| {
                             anyhow::Error::msg(format!("ic0_msg_reject_msg_size failed: {}", e))
                         })
                     })
@@ -626,13 +671,13 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "msg_reject_msg_copy", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, dst: i32, offset: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I, offset: I, size: I| {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::MSG_REJECT_MSG_COPY,
-                    size as u32,
+                    size.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::MSG_REJECT_MSG_COPY,
                         ..Default::default()
@@ -642,14 +687,14 @@ pub(crate) fn syscalls<S: SystemApi>(
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
                     system_api.ic0_msg_reject_msg_copy(
-                        dst as u32,
-                        offset as u32,
-                        size as u32,
+                        dst.to_usize(),
+                        offset.to_usize(),
+                        size.to_usize(),
                         memory,
                     )
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as u32 as usize, size as u32 as usize)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), size.to_usize())
                 } else {
                     Ok(())
                 }
@@ -663,7 +708,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                 with_system_api(&mut caller, |s| s.ic0_canister_self_size())
                     .map_err(|e| process_err(&mut caller, e))
                     .and_then(|s| {
-                        i32::try_from(s).map_err(|e| {
+                        I::try_from(s).map_err(|e| {
                             anyhow::Error::msg(format!("ic0_canister_self_size failed: {}", e))
                         })
                     })
@@ -674,7 +719,7 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "canister_self_copy", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, dst: i32, offset: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I, offset: I, size: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -687,14 +732,14 @@ pub(crate) fn syscalls<S: SystemApi>(
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
                     system_api.ic0_canister_self_copy(
-                        dst as u32,
-                        offset as u32,
-                        size as u32,
+                        dst.to_usize(),
+                        offset.to_usize(),
+                        size.to_usize(),
                         memory,
                     )
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as u32 as usize, size as u32 as usize)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), size.to_usize())
                 } else {
                     Ok(())
                 }
@@ -702,16 +747,115 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "subnet_self_size", {
+            move |mut caller: Caller<'_, StoreData<S>>| {
+                with_system_api(&mut caller, |s| s.ic0_subnet_self_size())
+                    .map_err(|e| process_err(&mut caller, e))
+                    .and_then(|s| {
+                        I::try_from(s).map_err(|e| {
+                            anyhow::Error::msg(format!("ic0_subnet_self_size failed: {}", e))
+                        })
+                    })
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "subnet_self_copy", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I, offset: I, size: I| {
+                observe_execution_complexity(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::SUBNET_SELF_COPY,
+                        ..Default::default()
+                    },
+                    stable_memory_dirty_page_limit,
+                )?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_subnet_self_copy(
+                        dst.to_usize(),
+                        offset.to_usize(),
+                        size.to_usize(),
+                        memory,
+                    )
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), size.to_usize())
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "root_key_size", {
+            move |mut caller: Caller<'_, StoreData<S>>| {
+                with_system_api(&mut caller, |s| s.ic0_root_key_size())
+                    .map_err(|e| process_err(&mut caller, e))
+                    .and_then(|s| {
+                        I::try_from(s).map_err(|e| {
+                            anyhow::Error::msg(format!("ic0_root_key_size failed: {}", e))
+                        })
+                    })
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "root_key_copy", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I, offset: I, size: I| {
+                observe_execution_complexity(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::ROOT_KEY_COPY,
+                        ..Default::default()
+                    },
+                    stable_memory_dirty_page_limit,
+                )?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_root_key_copy(
+                        dst.to_usize(),
+                        offset.to_usize(),
+                        size.to_usize(),
+                        memory,
+                    )
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), size.to_usize())
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "in_replicated_execution", {
+            move |mut caller: Caller<'_, StoreData<S>>| {
+                with_system_api(&mut caller, |s| s.ic0_in_replicated_execution())
+                    .map_err(|e| process_err(&mut caller, e))
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "debug_print", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, offset: i32, length: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, offset: I, length: I| {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::DEBUG_PRINT,
-                    length as u32,
+                    length.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::DEBUG_PRINT,
                         ..Default::default()
@@ -719,6 +863,12 @@ pub(crate) fn syscalls<S: SystemApi>(
                     NumInstructions::from(0),
                     stable_memory_dirty_page_limit,
                 )?;
+                // The message is always recorded in the canister log,
+                // regardless of rate limiting.
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.save_log_message(offset.to_usize(), length.to_usize(), memory);
+                    Ok(())
+                })?;
                 match (
                     caller.data().system_api.subnet_type(),
                     feature_flags.rate_limiting_of_debug_prints,
@@ -730,7 +880,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     // debug print produces output.
                     (_, FlagStatus::Disabled) | (SubnetType::System, FlagStatus::Enabled) => {
                         with_memory_and_system_api(&mut caller, |system_api, memory| {
-                            system_api.ic0_debug_print(offset as u32, length as u32, memory)
+                            system_api.ic0_debug_print(offset.to_usize(), length.to_usize(), memory)
                         })
                     }
                 }
@@ -741,13 +891,13 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "trap", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, offset: i32, length: i32| -> Result<(), _> {
+            move |mut caller: Caller<'_, StoreData<S>>, offset: I, length: I| -> Result<(), _> {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::TRAP,
-                    length as u32,
+                    length.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::TRAP,
                         ..Default::default()
@@ -756,7 +906,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_trap(offset as u32, length as u32, memory)
+                    system_api.ic0_trap(offset.to_usize(), length.to_usize(), memory)
                 })
             }
         })
@@ -766,14 +916,14 @@ pub(crate) fn syscalls<S: SystemApi>(
         .func_wrap("ic0", "call_new", {
             let log = log.clone();
             move |mut caller: Caller<'_, StoreData<S>>,
-                  callee_src: i32,
-                  callee_size: i32,
-                  name_src: i32,
-                  name_len: i32,
+                  callee_src: I,
+                  callee_size: I,
+                  name_src: I,
+                  name_len: I,
                   reply_fun: i32,
-                  reply_env: i32,
+                  reply_env: I,
                   reject_fun: i32,
-                  reject_env: i32| {
+                  reject_env: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -786,14 +936,14 @@ pub(crate) fn syscalls<S: SystemApi>(
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
                     system_api.ic0_call_new(
-                        callee_src as u32,
-                        callee_size as u32,
-                        name_src as u32,
-                        name_len as u32,
+                        callee_src.to_usize(),
+                        callee_size.to_usize(),
+                        name_src.to_usize(),
+                        name_len.to_usize(),
                         reply_fun as u32,
-                        reply_env as u32,
+                        reply_env.into(),
                         reject_fun as u32,
-                        reject_env as u32,
+                        reject_env.into(),
                         memory,
                     )
                 })
@@ -804,13 +954,13 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "call_data_append", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, src: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, src: I, size: I| {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::CALL_DATA_APPEND,
-                    size as u32,
+                    size.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::CALL_DATA_APPEND,
                         ..Default::default()
@@ -819,7 +969,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_call_data_append(src as u32, size as u32, memory)
+                    system_api.ic0_call_data_append(src.to_usize(), size.to_usize(), memory)
                 })
             }
         })
@@ -827,9 +977,20 @@ pub(crate) fn syscalls<S: SystemApi>(
 
     linker
         .func_wrap("ic0", "call_on_cleanup", {
-            move |mut caller: Caller<'_, StoreData<S>>, fun: i32, env: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, fun: i32, env: I| {
                 with_system_api(&mut caller, |s| {
-                    s.ic0_call_on_cleanup(fun as u32, env as u32)
+                    s.ic0_call_on_cleanup(fun as u32, env.into())
+                })
+                .map_err(|e| process_err(&mut caller, e))
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "call_with_best_effort_response", {
+            move |mut caller: Caller<'_, StoreData<S>>, timeout_seconds: i32| {
+                with_system_api(&mut caller, |s| {
+                    s.ic0_call_with_best_effort_response(timeout_seconds as u32)
                 })
                 .map_err(|e| process_err(&mut caller, e))
             }
@@ -922,7 +1083,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::STABLE_READ,
-                    size as u32,
+                    size as u32 as usize,
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::STABLE_READ,
                         ..Default::default()
@@ -956,7 +1117,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::STABLE_WRITE,
-                    size,
+                    size as usize,
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::STABLE_WRITE,
                         stable_dirty_pages,
@@ -1019,7 +1180,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     )
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as u32 as usize, size as u32 as usize)
+                    mark_writes_on_bytemap(&mut caller, dst as u64 as usize, size as u64 as usize)
                 } else {
                     Ok(())
                 }
@@ -1036,7 +1197,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::STABLE64_READ,
-                    size as u32,
+                    size as u64 as usize,
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::STABLE64_READ,
                         ..Default::default()
@@ -1048,7 +1209,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     system_api.ic0_stable64_read(dst as u64, offset as u64, size as u64, memory)
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as u32 as usize, size as u32 as usize)
+                    mark_writes_on_bytemap(&mut caller, dst as u64 as usize, size as u64 as usize)
                 } else {
                     Ok(())
                 }
@@ -1070,7 +1231,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::STABLE64_WRITE,
-                    size as u32,
+                    size as usize,
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::STABLE64_WRITE,
                         stable_dirty_pages,
@@ -1155,7 +1316,7 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "canister_cycle_balance128", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, dst: u32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -1167,10 +1328,10 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_canister_cycle_balance128(dst, memory)
+                    system_api.ic0_canister_cycle_balance128(dst.to_usize(), memory)
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as usize, 16)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), 16)
                 } else {
                     Ok(())
                 }
@@ -1195,7 +1356,7 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "msg_cycles_available128", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, dst: u32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -1207,10 +1368,10 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_msg_cycles_available128(dst, memory)
+                    system_api.ic0_msg_cycles_available128(dst.to_usize(), memory)
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as usize, 16)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), 16)
                 } else {
                     Ok(())
                 }
@@ -1235,7 +1396,7 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "msg_cycles_refunded128", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, dst: u32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -1247,10 +1408,10 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_msg_cycles_refunded128(dst, memory)
+                    system_api.ic0_msg_cycles_refunded128(dst.to_usize(), memory)
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as usize, 16)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), 16)
                 } else {
                     Ok(())
                 }
@@ -1273,7 +1434,7 @@ pub(crate) fn syscalls<S: SystemApi>(
             move |mut caller: Caller<'_, StoreData<S>>,
                   amount_high: i64,
                   amount_low: i64,
-                  dst: u32| {
+                  dst: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -1287,12 +1448,12 @@ pub(crate) fn syscalls<S: SystemApi>(
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
                     system_api.ic0_msg_cycles_accept128(
                         Cycles::from_parts(amount_high as u64, amount_low as u64),
-                        dst,
+                        dst.to_usize(),
                         memory,
                     )
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as usize, 16)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), 16)
                 } else {
                     Ok(())
                 }
@@ -1314,24 +1475,47 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
-    linker
-        .func_wrap("__", "update_available_memory", {
-            move |mut caller: Caller<'_, StoreData<S>>,
-                  native_memory_grow_res: i32,
-                  additional_elements: i32,
-                  element_size: i32| {
-                with_system_api(&mut caller, |s| {
-                    s.update_available_memory(
-                        native_memory_grow_res as i64,
-                        additional_elements as u32 as u64,
-                        element_size as u32 as u64,
-                    )
-                })
-                .map(|()| native_memory_grow_res)
-                .map_err(|e| process_err(&mut caller, e))
-            }
-        })
-        .unwrap();
+    // The result of `memory.grow` and the number of additional pages are
+    // 64-bit values in Wasm64 modules.
+    if I::IS_WASM64 {
+        linker
+            .func_wrap("__", "update_available_memory", {
+                move |mut caller: Caller<'_, StoreData<S>>,
+                      native_memory_grow_res: i64,
+                      additional_elements: i64,
+                      element_size: i32| {
+                    with_system_api(&mut caller, |s| {
+                        s.update_available_memory(
+                            native_memory_grow_res,
+                            additional_elements as u64,
+                            element_size as u32 as u64,
+                        )
+                    })
+                    .map(|()| native_memory_grow_res)
+                    .map_err(|e| process_err(&mut caller, e))
+                }
+            })
+            .unwrap();
+    } else {
+        linker
+            .func_wrap("__", "update_available_memory", {
+                move |mut caller: Caller<'_, StoreData<S>>,
+                      native_memory_grow_res: i32,
+                      additional_elements: i32,
+                      element_size: i32| {
+                    with_system_api(&mut caller, |s| {
+                        s.update_available_memory(
+                            native_memory_grow_res as i64,
+                            additional_elements as u32 as u64,
+                            element_size as u32 as u64,
+                        )
+                    })
+                    .map(|()| native_memory_grow_res)
+                    .map_err(|e| process_err(&mut caller, e))
+                }
+            })
+            .unwrap();
+    }
 
     linker
         .func_wrap("__", "try_grow_stable_memory", {
@@ -1368,7 +1552,7 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "certified_data_set", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, src: u32, size: u32| {
+            move |mut caller: Caller<'_, StoreData<S>>, src: I, size: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -1380,7 +1564,7 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_certified_data_set(src, size, memory)
+                    system_api.ic0_certified_data_set(src.to_usize(), size.to_usize(), memory)
                 })
             }
         })
@@ -1407,13 +1591,13 @@ pub(crate) fn syscalls<S: SystemApi>(
     linker
         .func_wrap("ic0", "is_controller", {
             let log = log.clone();
-            move |mut caller: Caller<'_, StoreData<S>>, src: i32, size: i32| {
+            move |mut caller: Caller<'_, StoreData<S>>, src: I, size: I| {
                 charge_for_system_api_call(
                     &log,
                     canister_id,
                     &mut caller,
                     system_api_complexity::overhead::IS_CONTROLLER,
-                    size as u32,
+                    size.to_usize(),
                     ExecutionComplexity {
                         cpu: system_api_complexity::cpu::IS_CONTROLLER,
                         ..Default::default()
@@ -1422,15 +1606,144 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_is_controller(src as u32, size as u32, memory)
+                    system_api.ic0_is_controller(src.to_usize(), size.to_usize(), memory)
                 })
             }
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "cost_call", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>,
+                  method_name_size: i64,
+                  payload_size: i64,
+                  dst: I| {
+                observe_execution_complexity(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::COST_CALL,
+                        ..Default::default()
+                    },
+                    stable_memory_dirty_page_limit,
+                )?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_cost_call(
+                        method_name_size as u64,
+                        payload_size as u64,
+                        dst.to_usize(),
+                        memory,
+                    )
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), 16)
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "cost_create_canister", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I| {
+                observe_execution_complexity(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::COST_CREATE_CANISTER,
+                        ..Default::default()
+                    },
+                    stable_memory_dirty_page_limit,
+                )?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_cost_create_canister(dst.to_usize(), memory)
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), 16)
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "cost_http_request", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>,
+                  request_size: i64,
+                  max_res_bytes: i64,
+                  dst: I| {
+                observe_execution_complexity(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::COST_HTTP_REQUEST,
+                        ..Default::default()
+                    },
+                    stable_memory_dirty_page_limit,
+                )?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_cost_http_request(
+                        request_size as u64,
+                        max_res_bytes as u64,
+                        dst.to_usize(),
+                        memory,
+                    )
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), 16)
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "cost_sign_with_ecdsa", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>,
+                  src: I,
+                  size: I,
+                  ecdsa_curve: i32,
+                  dst: I| {
+                observe_execution_complexity(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::COST_SIGN_WITH_ECDSA,
+                        ..Default::default()
+                    },
+                    stable_memory_dirty_page_limit,
+                )?;
+                let result = with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_cost_sign_with_ecdsa(
+                        src.to_usize(),
+                        size.to_usize(),
+                        ecdsa_curve as u32,
+                        dst.to_usize(),
+                        memory,
+                    )
+                })?;
+                if result == 0 && feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), 16)?;
+                }
+                Ok(result)
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "data_certificate_copy", {
-            move |mut caller: Caller<'_, StoreData<S>>, dst: u32, offset: u32, size: u32| {
+            move |mut caller: Caller<'_, StoreData<S>>, dst: I, offset: I, size: I| {
                 observe_execution_complexity(
                     &log,
                     canister_id,
@@ -1442,10 +1755,15 @@ pub(crate) fn syscalls<S: SystemApi>(
                     stable_memory_dirty_page_limit,
                 )?;
                 with_memory_and_system_api(&mut caller, |system_api, memory| {
-                    system_api.ic0_data_certificate_copy(dst, offset, size, memory)
+                    system_api.ic0_data_certificate_copy(
+                        dst.to_usize(),
+                        offset.to_usize(),
+                        size.to_usize(),
+                        memory,
+                    )
                 })?;
                 if feature_flags.write_barrier == FlagStatus::Enabled {
-                    mark_writes_on_bytemap(&mut caller, dst as usize, size as usize)
+                    mark_writes_on_bytemap(&mut caller, dst.to_usize(), size.to_usize())
                 } else {
                     Ok(())
                 }
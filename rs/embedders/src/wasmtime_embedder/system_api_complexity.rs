@@ -84,6 +84,8 @@ pub mod cpu {
     pub const MSG_REPLY_DATA_APPEND: CpuComplexity = from_nanos(70);
     pub const MSG_REJECT: CpuComplexity = from_nanos(20);
     pub const CANISTER_SELF_COPY: CpuComplexity = from_nanos(60);
+    pub const SUBNET_SELF_COPY: CpuComplexity = from_nanos(60);
+    pub const ROOT_KEY_COPY: CpuComplexity = from_nanos(60);
     pub const CONTROLLER_COPY: CpuComplexity = from_nanos(60);
     pub const DEBUG_PRINT: CpuComplexity = from_nanos(30);
     pub const TRAP: CpuComplexity = from_nanos(1_000);
@@ -106,4 +108,8 @@ pub mod cpu {
     pub const CERTIFIED_DATA_SET: CpuComplexity = from_nanos(70);
     pub const PERFORMANCE_COUNTER: CpuComplexity = from_nanos(50);
     pub const IS_CONTROLLER: CpuComplexity = from_nanos(200);
+    pub const COST_CALL: CpuComplexity = from_nanos(50);
+    pub const COST_CREATE_CANISTER: CpuComplexity = from_nanos(50);
+    pub const COST_HTTP_REQUEST: CpuComplexity = from_nanos(50);
+    pub const COST_SIGN_WITH_ECDSA: CpuComplexity = from_nanos(200);
 }
@@ -4,10 +4,12 @@ use super::{system_api, StoreData, INSTRUCTIONS_COUNTER_GLOBAL_NAME};
 use crate::{wasm_utils::validate_and_instrument_for_testing, WasmtimeEmbedder};
 use ic_config::flag_status::FlagStatus;
 use ic_config::{embedders::Config as EmbeddersConfig, subnet_config::SchedulerConfig};
-use ic_interfaces::execution_environment::{ExecutionMode, SubnetAvailableMemory};
+use ic_interfaces::execution_environment::{ExecutionMode, HypervisorError, SubnetAvailableMemory};
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::{Memory, NetworkTopology, SystemState};
+use ic_replicated_state::{
+    page_map::TestPageAllocatorFileDescriptorImpl, Memory, NetworkTopology, SystemState,
+};
 use ic_system_api::{
     sandbox_safe_system_state::SandboxSafeSystemState, ApiType, DefaultOutOfInstructionsHandler,
     ExecutionParameters, InstructionLimits, SystemApiImpl,
@@ -16,7 +18,7 @@ use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder, mock_time, types::ids::canister_test_id,
 };
 use ic_types::{ComputeAllocation, MemoryAllocation, NumBytes, NumInstructions};
-use ic_wasm_types::BinaryEncodedWasm;
+use ic_wasm_types::{BinaryEncodedWasm, WasmEngineError};
 
 use lazy_static::lazy_static;
 use wasmtime::{Engine, Module, Store, Val};
@@ -39,7 +41,10 @@ fn test_wasmtime_system_api() {
     ))
     .expect("Failed to initialize Wasmtime engine");
     let canister_id = canister_test_id(53);
-    let system_state = SystemState::new_for_start(canister_id);
+    let system_state = SystemState::new_for_start(
+        canister_id,
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+    );
     let sandbox_safe_system_state = SandboxSafeSystemState::new(
         &system_state,
         CyclesAccountManagerBuilder::new().build(),
@@ -135,3 +140,35 @@ fn test_wasmtime_system_api() {
         .call(&mut store, &[], &mut [])
         .expect("call failed");
 }
+
+#[test]
+fn test_64_bit_heap_requires_wasm64() {
+    let wasm = BinaryEncodedWasm::new(
+        wat::parse_str(
+            r#"
+            (module
+                (memory (export "memory") i64 1)
+            )"#,
+        )
+        .unwrap(),
+    );
+
+    let config = EmbeddersConfig::default();
+    assert_eq!(config.feature_flags.wasm64, FlagStatus::Disabled);
+    assert_eq!(
+        WasmtimeEmbedder::new(config, no_op_logger())
+            .compile(&wasm)
+            .err(),
+        Some(HypervisorError::WasmEngineError(
+            WasmEngineError::FailedToInstantiateModule(
+                "64-bit Wasm memory is only supported if Wasm64 is enabled".to_string()
+            )
+        ))
+    );
+
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm64 = FlagStatus::Enabled;
+    assert!(WasmtimeEmbedder::new(config, no_op_logger())
+        .compile(&wasm)
+        .is_ok());
+}
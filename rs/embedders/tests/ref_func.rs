@@ -18,6 +18,7 @@ fn run_go_export(wat: &str) {
             Cycles::from(0_u128),
             PrincipalId::new_user_test_id(0),
             0.into(),
+            ic_types::messages::NO_DEADLINE,
         ))
         .with_num_instructions(LARGE_INSTRUCTION_LIMIT.into())
         .build();
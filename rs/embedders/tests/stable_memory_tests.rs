@@ -39,6 +39,7 @@ fn run_test(wat: &str) -> HypervisorResult<Option<WasmResult>> {
             Cycles::zero(),
             PrincipalId::new_user_test_id(0),
             0.into(),
+            ic_types::messages::NO_DEADLINE,
         ))
         .with_wat(wat)
         .build();
@@ -1,5 +1,5 @@
 use assert_matches::assert_matches;
-use ic_config::embedders::Config as EmbeddersConfig;
+use ic_config::{embedders::Config as EmbeddersConfig, flag_status::FlagStatus};
 use ic_embedders::{
     wasm_utils::{
         validate_and_instrument_for_testing,
@@ -925,6 +925,56 @@ fn can_validate_performance_counter_import() {
     );
 }
 
+#[test]
+fn wasm64_module_is_rejected_if_feature_is_disabled() {
+    let wasm = wat2wasm(
+        r#"(module
+        (import "ic0" "msg_reply_data_append" (func $msg_reply_data_append (param i64 i64)))
+        (memory i64 1)
+    )"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::WasmtimeValidation(_))
+    );
+}
+
+#[test]
+fn can_validate_wasm64_module_imports() {
+    let mut config = EmbeddersConfig::default();
+    config.feature_flags.wasm64 = FlagStatus::Enabled;
+    let wasm = wat2wasm(
+        r#"(module
+        (import "ic0" "msg_arg_data_size" (func $msg_arg_data_size (result i64)))
+        (import "ic0" "msg_arg_data_copy" (func $msg_arg_data_copy (param i64 i64 i64)))
+        (import "ic0" "msg_reply_data_append" (func $msg_reply_data_append (param i64 i64)))
+        (import "ic0" "msg_cycles_accept128" (func $msg_cycles_accept128 (param i64 i64 i64)))
+        (import "ic0" "stable64_read" (func $stable64_read (param i64 i64 i64)))
+        (memory i64 1)
+        (data (i64.const 0) "hello")
+    )"#,
+    )
+    .unwrap();
+    assert_eq!(
+        validate_wasm_binary(&wasm, &config),
+        Ok(WasmValidationDetails::default())
+    );
+
+    // Heap addresses of Wasm64 modules are `i64`.
+    let wasm = wat2wasm(
+        r#"(module
+        (import "ic0" "msg_reply_data_append" (func $msg_reply_data_append (param i32 i32)))
+        (memory i64 1)
+    )"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &config),
+        Err(WasmValidationError::InvalidFunctionSignature(_))
+    );
+}
+
 /// The spec doesn't allow exported functions to have results.
 #[test]
 fn function_with_result_is_invalid() {
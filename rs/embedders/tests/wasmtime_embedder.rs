@@ -12,9 +12,11 @@ use ic_types::{
 
 #[cfg(test)]
 mod test {
+    use ic_embedders::wasmtime_embedder::CanisterMemoryType;
     use ic_interfaces::execution_environment::{HypervisorError, TrapCode};
     use ic_registry_subnet_type::SubnetType;
-    use ic_replicated_state::canister_state::WASM_PAGE_SIZE_IN_BYTES;
+    use ic_replicated_state::{canister_state::WASM_PAGE_SIZE_IN_BYTES, NumWasmPages};
+    use ic_sys::{PageIndex, PAGE_SIZE};
     use ic_test_utilities::wasmtime_instance::DEFAULT_NUM_INSTRUCTIONS;
     use ic_types::{methods::WasmClosure, NumBytes, PrincipalId};
 
@@ -286,6 +288,7 @@ mod test {
                 Cycles::zero(),
                 PrincipalId::new_user_test_id(0),
                 0.into(),
+                ic_types::messages::NO_DEADLINE,
             ))
             .build();
 
@@ -330,6 +333,7 @@ mod test {
                 Cycles::zero(),
                 PrincipalId::new_user_test_id(0),
                 0.into(),
+                ic_types::messages::NO_DEADLINE,
             ))
             .with_num_instructions((expected_cpu_complexity as u64 - 1).into())
             .with_subnet_type(subnet_type)
@@ -360,6 +364,7 @@ mod test {
                 Cycles::zero(),
                 PrincipalId::new_user_test_id(0),
                 0.into(),
+                ic_types::messages::NO_DEADLINE,
             ))
             .with_num_instructions((expected_cpu_complexity as u64 - 1).into())
             .with_subnet_type(subnet_type)
@@ -691,6 +696,7 @@ mod test {
                 Cycles::zero(),
                 PrincipalId::new_user_test_id(0),
                 0.into(),
+                ic_types::messages::NO_DEADLINE,
             ))
             .build();
         instance
@@ -719,6 +725,7 @@ mod test {
                 Cycles::zero(),
                 PrincipalId::new_user_test_id(0),
                 0.into(),
+                ic_types::messages::NO_DEADLINE,
             ))
             .build();
         instance
@@ -1605,4 +1612,231 @@ mod test {
             .build();
         instance.run(func_ref("write_to_last_page")).unwrap();
     }
+
+    fn wasm64_config() -> ic_config::embedders::Config {
+        let mut config = ic_config::embedders::Config::default();
+        config.feature_flags.wasm64 = ic_config::flag_status::FlagStatus::Enabled;
+        config
+    }
+
+    /// Test that system API calls of Wasm64 modules take `i64` heap addresses
+    /// and can access the heap beyond the 32-bit range.
+    #[test]
+    fn wasm64_system_api_calls_access_heap_beyond_32_bit_range() {
+        let wat = r#"
+            (module
+                (import "ic0" "msg_arg_data_copy"
+                    (func $msg_arg_data_copy (param i64 i64 i64)))
+                (import "ic0" "trap" (func $trap (param i64 i64)))
+                (func (export "canister_update test")
+                    (call $msg_arg_data_copy (i64.const 4294967296) (i64.const 0) (i64.const 5))
+                    (call $trap (i64.const 4294967296) (i64.const 5))
+                )
+                (memory i64 65537)
+            )"#;
+        let mut instance = WasmtimeInstanceBuilder::new()
+            .with_config(wasm64_config())
+            .with_wat(wat)
+            .with_api_type(ic_system_api::ApiType::init(
+                mock_time(),
+                b"Hello".to_vec(),
+                user_test_id(24).get(),
+            ))
+            .with_canister_memory_limit(NumBytes::from(8 << 30))
+            .build();
+        let err = instance
+            .run(FuncRef::Method(WasmMethod::Update("test".to_string())))
+            .unwrap_err();
+        assert_eq!(err, HypervisorError::CalledTrap("Hello".to_string()));
+    }
+
+    #[test]
+    fn wasm64_memory_grows_beyond_4_gib() {
+        let wat = r#"
+            (module
+                (func (export "canister_update grow")
+                    (if (i64.ne (memory.grow (i64.const 65536)) (i64.const 1))
+                        (then unreachable)
+                    )
+                    (i64.store (i64.const 4294967296) (i64.const 42))
+                )
+                (memory i64 1)
+            )"#;
+        let mut instance = WasmtimeInstanceBuilder::new()
+            .with_config(wasm64_config())
+            .with_wat(wat)
+            .with_canister_memory_limit(NumBytes::from(8 << 30))
+            .build();
+        instance
+            .run(FuncRef::Method(WasmMethod::Update("grow".to_string())))
+            .unwrap();
+        assert_eq!(
+            instance.heap_size(CanisterMemoryType::Heap),
+            NumWasmPages::from(65537)
+        );
+    }
+
+    #[test]
+    fn wasm64_memory_grow_fails_above_canister_memory_limit() {
+        let wat = r#"
+            (module
+                (func (export "canister_update grow")
+                    (if (i64.ne (memory.grow (i64.const 65536)) (i64.const -1))
+                        (then unreachable)
+                    )
+                )
+                (memory i64 1)
+            )"#;
+        let mut instance = WasmtimeInstanceBuilder::new()
+            .with_config(wasm64_config())
+            .with_wat(wat)
+            .with_canister_memory_limit(NumBytes::from(4 << 30))
+            .build();
+        instance
+            .run(FuncRef::Method(WasmMethod::Update("grow".to_string())))
+            .unwrap();
+        assert_eq!(
+            instance.heap_size(CanisterMemoryType::Heap),
+            NumWasmPages::from(1)
+        );
+    }
+
+    /// Test that writes beyond the 32-bit range of a Wasm64 heap are tracked,
+    /// both with the write barrier and with the signal handler.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn wasm64_dirty_pages_are_tracked_beyond_32_bit_range() {
+        let wat = r#"
+            (module
+                (func (export "canister_update write")
+                    (i64.store (i64.const 0) (i64.const 1))
+                    (i64.store (i64.const 4294967296) (i64.const 2))
+                )
+                (memory i64 65537)
+            )"#;
+        for write_barrier in [
+            ic_config::flag_status::FlagStatus::Disabled,
+            ic_config::flag_status::FlagStatus::Enabled,
+        ] {
+            let mut config = wasm64_config();
+            config.feature_flags.write_barrier = write_barrier;
+            let mut instance = WasmtimeInstanceBuilder::new()
+                .with_config(config)
+                .with_wat(wat)
+                .with_canister_memory_limit(NumBytes::from(8 << 30))
+                .build();
+            let mut dirty_pages = instance
+                .run(FuncRef::Method(WasmMethod::Update("write".to_string())))
+                .unwrap()
+                .dirty_pages;
+            dirty_pages.sort();
+            assert_eq!(
+                dirty_pages,
+                vec![
+                    PageIndex::new(0),
+                    PageIndex::new((4 << 30) / PAGE_SIZE as u64)
+                ],
+                "write barrier: {:?}",
+                write_barrier
+            );
+        }
+    }
+
+    #[test]
+    fn wasm64_correctly_count_instructions() {
+        let data_size = 1024;
+        let mut instance = WasmtimeInstanceBuilder::new()
+            .with_config(wasm64_config())
+            .with_wat(
+                format!(
+                    r#"
+                    (module
+                        (import "ic0" "msg_arg_data_copy"
+                            (func $ic0_msg_arg_data_copy (param i64 i64 i64)))
+                        (memory i64 1)
+                        (func (export "canister_update test_msg_arg_data_copy")
+                            (call $ic0_msg_arg_data_copy
+                                (i64.const 0) (i64.const 0) (i64.const {DATA_SIZE}))
+                        )
+                    )
+                    "#,
+                    DATA_SIZE = data_size
+                )
+                .as_str(),
+            )
+            .with_api_type(ic_system_api::ApiType::init(
+                mock_time(),
+                vec![0; 1024],
+                user_test_id(24).get(),
+            ))
+            .build();
+
+        instance
+            .run(FuncRef::Method(WasmMethod::Update(
+                "test_msg_arg_data_copy".to_string(),
+            )))
+            .unwrap();
+
+        let instruction_counter = instance.instruction_counter();
+        let system_api = &instance.store_data().system_api;
+        let instructions_used = system_api.slice_instructions_executed(instruction_counter);
+
+        let call_msg_arg_data_copy_with_3_const = 4;
+        let expected_instructions = call_msg_arg_data_copy_with_3_const
+            + data_size
+            + system_api_complexity::overhead::MSG_ARG_DATA_COPY.get();
+        assert_eq!(instructions_used.get(), expected_instructions);
+    }
+
+    /// Test that bulk memory operations with `i64` sizes are metered the same
+    /// way as their 32-bit counterparts.
+    #[test]
+    fn wasm64_bulk_memory_is_metered_like_wasm32() {
+        fn instructions_used(wat: &str, config: ic_config::embedders::Config) -> u64 {
+            let mut instance = WasmtimeInstanceBuilder::new()
+                .with_config(config)
+                .with_wat(wat)
+                .build();
+            instance
+                .run(FuncRef::Method(WasmMethod::Update("fill".to_string())))
+                .unwrap();
+            let instruction_counter = instance.instruction_counter();
+            instance
+                .store_data()
+                .system_api
+                .slice_instructions_executed(instruction_counter)
+                .get()
+        }
+
+        let mut previous = 0;
+        for size in [1_000, 2_000] {
+            let wasm32 = instructions_used(
+                &format!(
+                    r#"
+                    (module
+                        (func (export "canister_update fill")
+                            (memory.fill (i32.const 0) (i32.const 7) (i32.const {size}))
+                        )
+                        (memory 1)
+                    )"#
+                ),
+                ic_config::embedders::Config::default(),
+            );
+            let wasm64 = instructions_used(
+                &format!(
+                    r#"
+                    (module
+                        (func (export "canister_update fill")
+                            (memory.fill (i64.const 0) (i32.const 7) (i64.const {size}))
+                        )
+                        (memory i64 1)
+                    )"#
+                ),
+                wasm64_config(),
+            );
+            assert_eq!(wasm32, wasm64);
+            assert!(wasm64 > previous);
+            previous = wasm64;
+        }
+    }
 }
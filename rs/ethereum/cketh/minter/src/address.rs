@@ -23,6 +23,12 @@ impl Address {
     }
 }
 
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 impl FromStr for Address {
     type Err = String;
 
@@ -64,7 +70,7 @@ impl fmt::Display for Address {
     }
 }
 
-fn keccak(bytes: &[u8]) -> [u8; 32] {
+pub(crate) fn keccak(bytes: &[u8]) -> [u8; 32] {
     use tiny_keccak::Hasher;
     let mut hash = tiny_keccak::Keccak::v256();
     hash.update(bytes.as_ref());
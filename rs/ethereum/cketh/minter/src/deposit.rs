@@ -0,0 +1,269 @@
+//! The deposit flow: scraping `ReceivedEth` events emitted by the helper smart contract and
+//! minting ckETH for them.
+
+use crate::eth_logs::{
+    EventSource, ReceivedEthEvent, ReceivedEthEventError, RECEIVED_ETH_EVENT_TOPIC,
+};
+use crate::eth_rpc::{BlockSpec, BlockTag, Data, GetLogsParam, Quantity};
+use crate::eth_rpc_client::EthRpcClient;
+use crate::guard::{TaskType, TimerGuard};
+use crate::state::{audit::process_event, event::Event, mutate_state, read_state};
+use candid::Nat;
+use ic_icrc1_client_cdk::{CdkRuntime, ICRC1Client};
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::{Memo, TransferArg, TransferError};
+use num_traits::ToPrimitive;
+use scopeguard::{guard, ScopeGuard};
+use std::future::Future;
+use std::time::Duration;
+
+/// How often the minter looks for new deposits.
+pub const SCRAPING_ETH_LOGS_INTERVAL: Duration = Duration::from_secs(3 * 60);
+
+/// The maximum number of blocks queried by a single `eth_getLogs` call.
+const MAX_BLOCK_SPREAD: u32 = 1024;
+
+/// Fetches new `ReceivedEth` events up to the last finalized block and mints ckETH for them.
+pub async fn scrap_eth_logs() {
+    let _guard = match TimerGuard::new(TaskType::ScrapEthLogs) {
+        Ok(guard) => guard,
+        Err(_) => return,
+    };
+
+    let client = EthRpcClient::from_state();
+    let last_finalized_block_number = match client
+        .eth_get_block_by_number(BlockSpec::Tag(BlockTag::Finalized))
+        .await
+    {
+        Ok(block) => block.number,
+        Err(e) => {
+            ic_cdk::println!("Failed to get the last finalized block number: {e}");
+            return;
+        }
+    };
+
+    let mut last_scraped_block_number = read_state(|s| s.last_scraped_block_number);
+    while last_scraped_block_number < last_finalized_block_number {
+        let from = last_scraped_block_number + Quantity::ONE;
+        let to = (last_scraped_block_number + Quantity::from(MAX_BLOCK_SPREAD))
+            .min(last_finalized_block_number);
+        if let Err(e) = scrap_eth_logs_range(&client, from, to).await {
+            ic_cdk::println!("Failed to scrap logs in blocks [{from}, {to}]: {e}");
+            break;
+        }
+        last_scraped_block_number = to;
+    }
+
+    mint_cketh().await;
+}
+
+/// Processes all `ReceivedEth` events in the given (inclusive) block range and records that
+/// the minter is synced up to the last block of the range.
+async fn scrap_eth_logs_range(
+    client: &EthRpcClient,
+    from: Quantity,
+    to: Quantity,
+) -> Result<(), String> {
+    let contract_address = read_state(|s| s.ethereum_contract_address);
+    let entries = client
+        .eth_get_logs(GetLogsParam {
+            from_block: BlockSpec::Number(from),
+            to_block: BlockSpec::Number(to),
+            address: vec![contract_address],
+            topics: vec![Data(RECEIVED_ETH_EVENT_TOPIC.to_vec())],
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Blocks up to `to` are finalized, so all returned events must be final.
+    let mut events = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match ReceivedEthEvent::try_from(entry) {
+            Ok(event) => events.push(Ok(event)),
+            Err(ReceivedEthEventError::PendingOrRemoved) => {
+                return Err("received a pending or removed log entry".to_string());
+            }
+            Err(ReceivedEthEventError::InvalidEventSource { source, error })
+            | Err(ReceivedEthEventError::InvalidPrincipal { source, error }) => {
+                events.push(Err((source, error)));
+            }
+        }
+    }
+
+    mutate_state(|s| {
+        for event in events {
+            let source = match &event {
+                Ok(event) => event.source(),
+                Err((source, _)) => *source,
+            };
+            if s.is_processed(&source) {
+                ic_cdk::println!("Skipping already processed event {source}");
+                continue;
+            }
+            match event {
+                Ok(event) => {
+                    ic_cdk::println!(
+                        "Received deposit of {} Wei from {} for {} ({source})",
+                        event.value,
+                        event.from_address,
+                        event.principal
+                    );
+                    process_event(s, Event::AcceptedDeposit(event));
+                }
+                Err((event_source, reason)) => {
+                    ic_cdk::println!("Ignoring invalid deposit {event_source}: {reason}");
+                    process_event(
+                        s,
+                        Event::InvalidDeposit {
+                            event_source,
+                            reason,
+                        },
+                    );
+                }
+            }
+        }
+        process_event(s, Event::SyncedToBlock { block_number: to });
+    });
+
+    Ok(())
+}
+
+/// How many times a mint transfer is resubmitted with a fresh `created_at_time` after the
+/// ledger rejected it as too old.
+const MAX_TOO_OLD_RESUBMISSIONS: usize = 3;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MintError {
+    /// The ledger rejected the transfer.
+    Ledger(TransferError),
+    /// The call to the ledger failed.
+    Call { code: i32, message: String },
+}
+
+impl std::fmt::Display for MintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MintError::Ledger(e) => write!(f, "the ledger rejected the transfer: {e:?}"),
+            MintError::Call { code, message } => write!(
+                f,
+                "failed to send a message to the ledger: {message} (reject code = {code})"
+            ),
+        }
+    }
+}
+
+/// Mints ckETH for all accepted deposits.
+///
+/// The minter state guards against minting a deposit twice: only one mint task runs at a
+/// time, a deposit is removed from the pending deposits as soon as its mint succeeds, and a
+/// deposit whose mint panics after contacting the ledger is quarantined. The mint transfer
+/// of a deposit always has the same memo, so every mint can be traced back to its deposit.
+pub async fn mint_cketh() {
+    let _guard = match TimerGuard::new(TaskType::MintCkEth) {
+        Ok(guard) => guard,
+        Err(_) => return,
+    };
+
+    let (ledger_canister_id, events) = read_state(|s| {
+        (
+            s.ledger_id,
+            s.events_to_mint.values().cloned().collect::<Vec<_>>(),
+        )
+    });
+    let client = ICRC1Client {
+        runtime: CdkRuntime,
+        ledger_canister_id,
+    };
+
+    for event in events {
+        // If the callback panics after the ledger minted the tokens, the state changes below
+        // are rolled back. Quarantine the deposit in that case so that it is not minted again.
+        let prevent_double_minting_guard = guard(event.source(), |event_source| {
+            mutate_state(|s| process_event(s, Event::QuarantinedDeposit { event_source }))
+        });
+        let result = mint_deposit(&event, ic_cdk::api::time, |arg| client.transfer(arg)).await;
+        ScopeGuard::into_inner(prevent_double_minting_guard);
+
+        let block_index = match result {
+            Ok(block_index) => block_index,
+            Err(e) => {
+                ic_cdk::println!("Failed to mint ckETH for {}: {e}", event.source());
+                continue;
+            }
+        };
+        ic_cdk::println!(
+            "Minted {} ckWei to {} in block {block_index} for deposit {}",
+            event.value,
+            event.principal,
+            event.source()
+        );
+        mutate_state(|s| {
+            process_event(
+                s,
+                Event::MintedCkEth {
+                    event_source: event.source(),
+                    mint_block_index: block_index,
+                },
+            )
+        });
+    }
+}
+
+/// Mints ckETH for the deposit `event` with the given `transfer` function.
+///
+/// The transfer is created at the current time, as returned by `now`, so a deposit that is
+/// minted long after it was accepted is not rejected by the ledger. If the ledger still
+/// rejects the transfer as too old, it is resubmitted with a fresh `created_at_time`.
+pub async fn mint_deposit<F, Fut>(
+    event: &ReceivedEthEvent,
+    now: impl Fn() -> u64,
+    mut transfer: F,
+) -> Result<u64, MintError>
+where
+    F: FnMut(TransferArg) -> Fut,
+    Fut: Future<Output = Result<Result<u64, TransferError>, (i32, String)>>,
+{
+    // Values that do not fit into u128 are rejected when parsing the event.
+    let amount = event.value.as_u128();
+    let mut resubmissions = 0;
+    loop {
+        let result = transfer(TransferArg {
+            from_subaccount: None,
+            to: Account {
+                owner: event.principal,
+                subaccount: None,
+            },
+            fee: None,
+            created_at_time: Some(now()),
+            memo: Some(mint_memo(&event.source())),
+            amount: Nat::from(amount),
+        })
+        .await;
+        match result {
+            Ok(Ok(block_index)) => return Ok(block_index),
+            // The memo identifies the deposit, so an identical transfer minted this deposit.
+            Ok(Err(TransferError::Duplicate { duplicate_of })) => {
+                return duplicate_of
+                    .0
+                    .to_u64()
+                    .ok_or(MintError::Ledger(TransferError::Duplicate { duplicate_of }))
+            }
+            Ok(Err(TransferError::TooOld)) if resubmissions < MAX_TOO_OLD_RESUBMISSIONS => {
+                resubmissions += 1;
+            }
+            Ok(Err(e)) => return Err(MintError::Ledger(e)),
+            Err((code, message)) => return Err(MintError::Call { code, message }),
+        }
+    }
+}
+
+/// Returns the memo of the mint transfer for the deposit emitted by `source`.
+///
+/// Ledger memos are at most 32 bytes long, so the memo consists of the first 24 bytes of the
+/// transaction hash followed by the big-endian log index, which tells apart deposits made in
+/// the same transaction.
+pub fn mint_memo(source: &EventSource) -> Memo {
+    let mut memo = source.transaction_hash.0[..24].to_vec();
+    memo.extend_from_slice(&source.log_index.as_u64().to_be_bytes());
+    Memo::from(memo)
+}
@@ -1,8 +1,39 @@
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
+/// The Ethereum network the minter operates on.
+#[derive(CandidType, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EthereumNetwork {
+    Mainnet,
+    Sepolia,
+}
+
+impl EthereumNetwork {
+    /// The chain ID, see https://chainlist.org.
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            EthereumNetwork::Mainnet => 1,
+            EthereumNetwork::Sepolia => 11155111,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct InitArg {
+    pub ethereum_network: EthereumNetwork,
     pub ecdsa_key_name: String,
+    /// The address of the helper smart contract emitting `ReceivedEth` events.
+    pub ethereum_contract_address: String,
+    /// The ledger minting ckETH; the minter must be its minting account.
+    pub ledger_id: Principal,
+    /// The minter only considers events in blocks after this one.
+    pub last_scraped_block_number: u64,
+    /// The nonce of the first transaction sent by the minter.
+    pub next_transaction_nonce: u64,
+    /// The URLs of the JSON-RPC providers queried for every read.
+    pub rpc_providers: Vec<String>,
+    /// How many providers must return the same result for the minter to accept it.
+    pub rpc_consensus_threshold: u8,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -20,7 +51,56 @@ pub struct DisplayLogsRequest {
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct ReceivedEthEvent {
+    pub transaction_hash: String,
+    pub block_number: candid::Nat,
+    pub log_index: candid::Nat,
     pub from_address: String,
     pub value: candid::Nat,
     pub principal: candid::Principal,
 }
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct WithdrawalArg {
+    /// The amount of ckWei to burn, including the transaction fees.
+    pub amount: candid::Nat,
+    /// The Ethereum address receiving ETH.
+    pub recipient: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RetrieveEthRequest {
+    /// The index of the burn transaction on the ckETH ledger.
+    pub block_index: candid::Nat,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum WithdrawalError {
+    AmountTooLow { min_withdrawal_amount: candid::Nat },
+    InvalidDestination(String),
+    InsufficientFunds { balance: candid::Nat },
+    InsufficientAllowance { allowance: candid::Nat },
+    TemporarilyUnavailable(String),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EthTransaction {
+    pub transaction_hash: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TxFinalizedStatus {
+    Success(EthTransaction),
+    /// The transaction was included in a block but reverted.
+    Failure(EthTransaction),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RetrieveEthStatus {
+    NotFound,
+    /// The withdrawal is waiting for a transaction to be created.
+    Pending,
+    /// The transaction was sent and is waiting to be finalized. Resubmitted transactions
+    /// replace the previous transaction hash.
+    TxSent(EthTransaction),
+    TxFinalized(TxFinalizedStatus),
+}
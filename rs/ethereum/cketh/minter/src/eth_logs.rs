@@ -0,0 +1,157 @@
+//! Parsing of the events emitted by the helper smart contract (see `minter.sol`).
+
+use crate::address::Address;
+use crate::eth_rpc::{Data, Hash, LogEntry, Quantity};
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The topic of the `ReceivedEth(address,uint256,bytes32)` event, i.e., the Keccak-256 hash of
+/// the event signature.
+pub const RECEIVED_ETH_EVENT_TOPIC: [u8; 32] = [
+    0x25, 0x7e, 0x05, 0x7b, 0xb6, 0x19, 0x20, 0xd8, 0xd0, 0xed, 0x2c, 0xb7, 0xb7, 0x20, 0xac, 0x7f,
+    0x9c, 0x51, 0x3c, 0xd1, 0x11, 0x0b, 0xc9, 0xfa, 0x54, 0x30, 0x79, 0x15, 0x4f, 0x45, 0xf4, 0x35,
+];
+
+/// Uniquely identifies an event on the Ethereum blockchain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EventSource {
+    pub transaction_hash: Hash,
+    pub log_index: Quantity,
+}
+
+impl fmt::Display for EventSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.transaction_hash, self.log_index)
+    }
+}
+
+/// A deposit of ETH to the helper smart contract.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceivedEthEvent {
+    pub transaction_hash: Hash,
+    pub block_number: Quantity,
+    pub log_index: Quantity,
+    pub from_address: Address,
+    /// The deposited amount in Wei.
+    pub value: Quantity,
+    /// The owner of the account receiving ckETH.
+    pub principal: Principal,
+}
+
+impl ReceivedEthEvent {
+    pub fn source(&self) -> EventSource {
+        EventSource {
+            transaction_hash: self.transaction_hash,
+            log_index: self.log_index,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReceivedEthEventError {
+    /// The log entry is pending or was removed by a chain reorganization.
+    PendingOrRemoved,
+    /// The log entry is not a well-formed `ReceivedEth` event.
+    InvalidEventSource { source: EventSource, error: String },
+    /// The event is well-formed but its recipient cannot be decoded. Such deposits can never
+    /// be minted.
+    InvalidPrincipal { source: EventSource, error: String },
+}
+
+impl TryFrom<LogEntry> for ReceivedEthEvent {
+    type Error = ReceivedEthEventError;
+
+    fn try_from(entry: LogEntry) -> Result<Self, Self::Error> {
+        let (transaction_hash, block_number, log_index) =
+            match (entry.transaction_hash, entry.block_number, entry.log_index) {
+                (Some(tx_hash), Some(block_number), Some(log_index)) if !entry.removed => {
+                    (tx_hash, block_number, log_index)
+                }
+                _ => return Err(ReceivedEthEventError::PendingOrRemoved),
+            };
+        let source = EventSource {
+            transaction_hash,
+            log_index,
+        };
+        let invalid = |error: String| ReceivedEthEventError::InvalidEventSource { source, error };
+
+        // We expect exactly 3 topics:
+        // 1. the event signature,
+        // 2. the sender address (indexed),
+        // 3. the principal (indexed).
+        if entry.topics.len() != 3 {
+            return Err(invalid(format!(
+                "expected exactly 3 topics, got {}",
+                entry.topics.len()
+            )));
+        }
+        if entry.topics[0].as_ref() != RECEIVED_ETH_EVENT_TOPIC {
+            return Err(invalid(format!(
+                "unexpected event topic {}",
+                hex::encode(&entry.topics[0])
+            )));
+        }
+        let from_address = parse_address(&entry.topics[1]).map_err(invalid)?;
+        let value = parse_word(&entry.data).map_err(invalid)?;
+        if value > Quantity::from(u128::MAX) {
+            return Err(invalid(format!("value {value} does not fit into u128")));
+        }
+
+        let principal = parse_principal(&entry.topics[2])
+            .map_err(|error| ReceivedEthEventError::InvalidPrincipal { source, error })?;
+
+        Ok(ReceivedEthEvent {
+            transaction_hash,
+            block_number,
+            log_index,
+            from_address,
+            value,
+            principal,
+        })
+    }
+}
+
+fn parse_word(data: &Data) -> Result<Quantity, String> {
+    let bytes: [u8; 32] = data
+        .as_ref()
+        .try_into()
+        .map_err(|_| format!("expected 32 bytes of data, got {}", data.as_ref().len()))?;
+    Ok(Quantity::from_be_bytes(bytes))
+}
+
+fn parse_address(topic: &Data) -> Result<Address, String> {
+    let word = parse_word(topic)?.to_be_bytes();
+    if word[..12].iter().any(|b| *b != 0) {
+        return Err(format!(
+            "address {} has non-zero padding",
+            hex::encode(word)
+        ));
+    }
+    Ok(Address::new(
+        word[12..]
+            .try_into()
+            .expect("BUG: an address is 20 bytes long"),
+    ))
+}
+
+/// Decodes a principal from a 32-byte word: the first byte is the length of the principal,
+/// followed by the principal bytes, right-padded with zeros.
+pub fn parse_principal(topic: &Data) -> Result<Principal, String> {
+    let word = parse_word(topic)?.to_be_bytes();
+    let len = word[0] as usize;
+    if len == 0 || len > Principal::MAX_LENGTH_IN_BYTES {
+        return Err(format!("invalid principal length {len}"));
+    }
+    if word[1 + len..].iter().any(|b| *b != 0) {
+        return Err(format!(
+            "principal {} has non-zero padding",
+            hex::encode(word)
+        ));
+    }
+    let principal = Principal::try_from_slice(&word[1..1 + len]).map_err(|e| e.to_string())?;
+    if principal == Principal::anonymous() {
+        return Err(format!("principal {principal} cannot receive ckETH"));
+    }
+    Ok(principal)
+}
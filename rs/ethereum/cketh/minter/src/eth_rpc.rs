@@ -4,13 +4,14 @@
 use crate::address::Address;
 use candid::candid_method;
 use ethnum::u256;
-use ic_cdk::api::call::{call_with_payment128, CallResult};
+use ic_cdk::api::call::{call_with_payment128, RejectionCode};
 use ic_cdk::api::management_canister::http_request::{
     CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
     TransformContext,
 };
 use ic_cdk_macros::query;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
 
 pub type Quantity = u256;
 
@@ -24,11 +25,17 @@ impl AsRef<[u8]> for Data {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(transparent)]
 pub struct Hash(#[serde(with = "crate::serde_data")] pub [u8; 32]);
 
-#[derive(Debug, Clone, Deserialize)]
+impl std::fmt::Display for Hash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BlockResponse {
     pub number: Quantity,
     pub hash: Data,
@@ -167,7 +174,7 @@ pub struct GetLogsParam {
 //    "removed": false
 //  }
 // ```
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct LogEntry {
     /// The address from which this log originated.
@@ -208,10 +215,111 @@ struct JsonRpcRequest<T> {
     params: T,
 }
 
+/// Parameters of the [`eth_feeHistory`](https://ethereum.github.io/execution-apis/api-documentation/) call.
+#[derive(Debug, Clone, Serialize)]
+#[serde(into = "(Quantity, BlockSpec, Vec<u8>)")]
+pub struct FeeHistoryParams {
+    /// Number of blocks in the requested range.
+    pub block_count: Quantity,
+    /// Highest block of the requested range.
+    pub highest_block: BlockSpec,
+    /// A monotonically increasing list of percentile values between 0 and 100.
+    /// For each block in the requested range, the transactions will be sorted in ascending order
+    /// by effective tip per gas and the corresponding effective tip for the percentile
+    /// will be determined, accounting for gas consumed.
+    pub reward_percentiles: Vec<u8>,
+}
+
+impl From<FeeHistoryParams> for (Quantity, BlockSpec, Vec<u8>) {
+    fn from(value: FeeHistoryParams) -> Self {
+        (
+            value.block_count,
+            value.highest_block,
+            value.reward_percentiles,
+        )
+    }
+}
+
+/// The reply of the `eth_feeHistory` call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    /// Lowest number block of the returned range.
+    pub oldest_block: Quantity,
+    /// An array of block base fees per gas.
+    /// This includes the next block after the newest of the returned range,
+    /// because this value can be derived from the newest block.
+    pub base_fee_per_gas: Vec<Quantity>,
+    /// A two-dimensional array of effective priority fees per gas at the requested block
+    /// percentiles.
+    pub reward: Vec<Vec<Quantity>>,
+}
+
+/// The reply of the `eth_getTransactionReceipt` call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    /// The hash of the block containing the transaction.
+    pub block_hash: Hash,
+    /// The number of the block containing the transaction.
+    pub block_number: Quantity,
+    /// The total base charge plus tip paid for each unit of gas.
+    pub effective_gas_price: Quantity,
+    /// The amount of gas used by this specific transaction alone.
+    pub gas_used: Quantity,
+    /// Either 1 (success) or 0 (failure).
+    pub status: Quantity,
+    /// The hash of the transaction.
+    pub transaction_hash: Hash,
+}
+
 /// An envelope for all JSON-RPC replies.
 #[derive(Deserialize)]
-struct JsonRpcReply<T> {
-    result: T,
+struct JsonRpcReply {
+    /// Some methods (e.g., `eth_getTransactionReceipt`) reply with a null result,
+    /// so the result is decoded only if there is no error.
+    #[serde(default)]
+    result: serde_json::Value,
+    #[serde(default)]
+    error: Option<JsonRpcError>,
+}
+
+/// The error object of a JSON-RPC reply.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// An error of a JSON-RPC call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcError {
+    /// The HTTPS outcall failed.
+    HttpOutcallError {
+        code: RejectionCode,
+        message: String,
+    },
+    /// The JSON-RPC provider replied with an error.
+    JsonRpcError(JsonRpcError),
+    /// The reply could not be decoded.
+    InvalidReply(String),
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::HttpOutcallError { code, message } => {
+                write!(
+                    f,
+                    "HTTPS outcall failed: {message} (reject code = {code:?})"
+                )
+            }
+            RpcError::JsonRpcError(JsonRpcError { code, message }) => {
+                write!(f, "JSON-RPC error {code}: {message}")
+            }
+            RpcError::InvalidReply(message) => write!(f, "invalid reply: {message}"),
+        }
+    }
 }
 
 #[query]
@@ -223,10 +331,10 @@ fn cleanup_response(mut args: TransformArgs) -> HttpResponse {
 
 /// Calls a JSON-RPC method on an Ethereum node at the specified URL.
 pub async fn call<I: Serialize, O: DeserializeOwned>(
-    url: &'static str,
+    url: &str,
     method: impl Into<String>,
     params: I,
-) -> CallResult<O> {
+) -> Result<O, RpcError> {
     const KIB: u64 = 1024;
     let payload = serde_json::to_string(&JsonRpcRequest {
         jsonrpc: "2.0",
@@ -263,17 +371,103 @@ pub async fn call<I: Serialize, O: DeserializeOwned>(
         (request,),
         cycles,
     )
-    .await?;
+    .await
+    .map_err(|(code, message)| RpcError::HttpOutcallError { code, message })?;
 
     ic_cdk::println!("RESPONSE: {}", String::from_utf8_lossy(&response.body));
 
-    let reply: JsonRpcReply<O> = serde_json::from_slice(&response.body).unwrap_or_else(|e| {
-        panic!(
+    let reply: JsonRpcReply = serde_json::from_slice(&response.body).map_err(|e| {
+        RpcError::InvalidReply(format!(
             "failed to decode response {}: {}",
             String::from_utf8_lossy(&response.body),
             e
-        )
-    });
+        ))
+    })?;
+
+    if let Some(error) = reply.error {
+        return Err(RpcError::JsonRpcError(error));
+    }
+    serde_json::from_value(reply.result)
+        .map_err(|e| RpcError::InvalidReply(format!("failed to decode result: {e}")))
+}
 
-    Ok(reply.result)
+/// The reason why the results of several JSON-RPC providers were rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsensusError {
+    /// Fewer than `threshold` providers returned a result.
+    TooFewReplies {
+        threshold: usize,
+        errors: Vec<(String, RpcError)>,
+    },
+    /// Enough providers returned a result, but no `threshold` of them agree. Each result is
+    /// identified by its canonical hash.
+    InconsistentResults {
+        threshold: usize,
+        results: Vec<(String, Hash)>,
+    },
+}
+
+impl fmt::Display for ConsensusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsensusError::TooFewReplies { threshold, errors } => {
+                write!(f, "fewer than {threshold} providers replied:")?;
+                for (provider, error) in errors {
+                    write!(f, " [{provider}: {error}]")?;
+                }
+                Ok(())
+            }
+            ConsensusError::InconsistentResults { threshold, results } => {
+                write!(f, "fewer than {threshold} providers agree:")?;
+                for (provider, hash) in results {
+                    write!(f, " [{provider}: {hash}]")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The Keccak-256 hash of the JSON encoding of a value. Two results are considered equal if
+/// their canonical hashes are equal.
+pub fn canonical_hash<T: Serialize>(value: &T) -> Hash {
+    let bytes = serde_json::to_vec(value).expect("BUG: failed to serialize a JSON-RPC result");
+    Hash(crate::address::keccak(&bytes))
+}
+
+/// Reduces the results returned by several providers to a single one, accepted only if at
+/// least `threshold` providers returned it and no other result reached the threshold.
+pub fn reduce_with_threshold<T: Serialize>(
+    results: Vec<(String, Result<T, RpcError>)>,
+    threshold: usize,
+) -> Result<T, ConsensusError> {
+    let mut errors = Vec::new();
+    let mut hashes = Vec::new();
+    let mut values: Vec<(Hash, usize, T)> = Vec::new();
+    for (provider, result) in results {
+        match result {
+            Ok(value) => {
+                let hash = canonical_hash(&value);
+                hashes.push((provider, hash));
+                match values.iter_mut().find(|(h, _, _)| *h == hash) {
+                    Some((_, count, _)) => *count += 1,
+                    None => values.push((hash, 1, value)),
+                }
+            }
+            Err(error) => errors.push((provider, error)),
+        }
+    }
+    if hashes.len() < threshold {
+        return Err(ConsensusError::TooFewReplies { threshold, errors });
+    }
+    let mut agreed = values
+        .into_iter()
+        .filter(|(_, count, _)| *count >= threshold);
+    match (agreed.next(), agreed.next()) {
+        (Some((_, _, value)), None) => Ok(value),
+        _ => Err(ConsensusError::InconsistentResults {
+            threshold,
+            results: hashes,
+        }),
+    }
 }
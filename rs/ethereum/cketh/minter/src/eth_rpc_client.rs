@@ -0,0 +1,137 @@
+//! A JSON-RPC client querying several Ethereum providers and accepting a result only if
+//! enough of them agree on it.
+
+use crate::address::Address;
+use crate::eth_rpc::{
+    self, reduce_with_threshold, BlockResponse, BlockSpec, ConsensusError, FeeHistory,
+    FeeHistoryParams, GetLogsParam, Hash, LogEntry, Quantity, RpcError, TransactionReceipt,
+};
+use crate::metrics::{observe_consensus_result, observe_provider_error};
+use crate::state::read_state;
+use futures::future::join_all;
+use serde::{de::DeserializeOwned, Serialize};
+
+pub struct EthRpcClient {
+    providers: Vec<String>,
+    threshold: usize,
+}
+
+impl EthRpcClient {
+    pub fn from_state() -> Self {
+        read_state(|s| Self {
+            providers: s.rpc_providers.clone(),
+            threshold: s.rpc_consensus_threshold,
+        })
+    }
+
+    /// Sends the same request to all providers concurrently.
+    async fn call_all<I: Serialize + Clone, O: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        params: I,
+    ) -> Vec<(String, Result<O, RpcError>)> {
+        let replies = join_all(
+            self.providers
+                .iter()
+                .map(|url| eth_rpc::call(url, method, params.clone())),
+        )
+        .await;
+        self.providers
+            .iter()
+            .cloned()
+            .zip(replies)
+            .inspect(|(provider, reply)| {
+                if let Err(e) = reply {
+                    ic_cdk::println!("[{method}]: provider {provider} failed: {e}");
+                    observe_provider_error(provider, method);
+                }
+            })
+            .collect()
+    }
+
+    /// Sends the same request to all providers and returns the result on which at least
+    /// `threshold` of them agree.
+    async fn call_with_consensus<I: Serialize + Clone, O: Serialize + DeserializeOwned>(
+        &self,
+        method: &'static str,
+        params: I,
+    ) -> Result<O, ConsensusError> {
+        let result = reduce_with_threshold(self.call_all(method, params).await, self.threshold);
+        observe_consensus_result(
+            method,
+            match &result {
+                Ok(_) => "consistent",
+                Err(ConsensusError::InconsistentResults { .. }) => "inconsistent",
+                Err(ConsensusError::TooFewReplies { .. }) => "too_few_replies",
+            },
+        );
+        result
+    }
+
+    pub async fn eth_get_logs(
+        &self,
+        params: GetLogsParam,
+    ) -> Result<Vec<LogEntry>, ConsensusError> {
+        self.call_with_consensus("eth_getLogs", vec![params]).await
+    }
+
+    pub async fn eth_get_block_by_number(
+        &self,
+        block: BlockSpec,
+    ) -> Result<BlockResponse, ConsensusError> {
+        self.call_with_consensus("eth_getBlockByNumber", (block, false))
+            .await
+    }
+
+    pub async fn eth_get_transaction_count(
+        &self,
+        address: Address,
+        block: BlockSpec,
+    ) -> Result<Quantity, ConsensusError> {
+        self.call_with_consensus("eth_getTransactionCount", (address, block))
+            .await
+    }
+
+    pub async fn eth_get_transaction_receipt(
+        &self,
+        hash: Hash,
+    ) -> Result<Option<TransactionReceipt>, ConsensusError> {
+        self.call_with_consensus("eth_getTransactionReceipt", vec![hash])
+            .await
+    }
+
+    /// Providers sample different blocks, so their fee histories are not expected to agree.
+    /// Returns the fee history of the first provider that replies.
+    pub async fn eth_fee_history(&self, params: FeeHistoryParams) -> Result<FeeHistory, RpcError> {
+        let mut last_error = None;
+        for provider in &self.providers {
+            match eth_rpc::call(provider, "eth_feeHistory", params.clone()).await {
+                Ok(fee_history) => return Ok(fee_history),
+                Err(e) => {
+                    ic_cdk::println!("[eth_feeHistory]: provider {provider} failed: {e}");
+                    observe_provider_error(provider, "eth_feeHistory");
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.expect("BUG: there is at least one provider"))
+    }
+
+    /// Broadcasts a transaction to all providers. Succeeds if at least one of them accepted it.
+    pub async fn eth_send_raw_transaction(
+        &self,
+        raw_transaction_hex: String,
+    ) -> Result<Hash, RpcError> {
+        let mut last_error = None;
+        for (_provider, result) in self
+            .call_all::<_, Hash>("eth_sendRawTransaction", vec![raw_transaction_hex])
+            .await
+        {
+            match result {
+                Ok(hash) => return Ok(hash),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.expect("BUG: there is at least one provider"))
+    }
+}
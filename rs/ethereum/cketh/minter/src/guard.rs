@@ -0,0 +1,38 @@
+use crate::state::mutate_state;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TaskType {
+    MintCkEth,
+    RetrieveEth,
+    ScrapEthLogs,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TimerGuardError {
+    AlreadyProcessing,
+}
+
+/// Prevents a periodic task from running concurrently with itself, e.g., when a timer fires
+/// while the previous run is still waiting for a response.
+#[must_use]
+#[derive(Debug, PartialEq, Eq)]
+pub struct TimerGuard {
+    task: TaskType,
+}
+
+impl TimerGuard {
+    pub fn new(task: TaskType) -> Result<Self, TimerGuardError> {
+        mutate_state(|s| {
+            if !s.active_tasks.insert(task) {
+                return Err(TimerGuardError::AlreadyProcessing);
+            }
+            Ok(Self { task })
+        })
+    }
+}
+
+impl Drop for TimerGuard {
+    fn drop(&mut self) {
+        mutate_state(|s| s.active_tasks.remove(&self.task));
+    }
+}
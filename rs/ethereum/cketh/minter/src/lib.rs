@@ -1,7 +1,17 @@
 pub mod address;
+pub mod deposit;
 pub mod endpoints;
+pub mod eth_logs;
 pub mod eth_rpc;
+pub mod eth_rpc_client;
+pub mod guard;
+pub mod management;
+pub mod metrics;
 mod serde_data;
+pub mod state;
+pub mod storage;
+pub mod tx;
+pub mod withdraw;
 
 #[cfg(test)]
 mod tests;
@@ -1,27 +1,32 @@
 use candid::candid_method;
-use ic_cdk_macros::{init, update};
-use ic_cketh_minter::address::Address;
-use ic_cketh_minter::endpoints::{DisplayLogsRequest, InitArg, MinterArg, ReceivedEthEvent};
+use ic_canisters_http_types::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use ic_cdk_macros::{init, post_upgrade, query, update};
+use ic_cketh_minter::deposit::{scrap_eth_logs, SCRAPING_ETH_LOGS_INTERVAL};
+use ic_cketh_minter::endpoints::{
+    DisplayLogsRequest, MinterArg, ReceivedEthEvent, RetrieveEthRequest, RetrieveEthStatus,
+    WithdrawalArg, WithdrawalError,
+};
+use ic_cketh_minter::eth_logs;
 use ic_cketh_minter::eth_rpc;
-use ic_crypto_ecdsa_secp256k1::PublicKey;
-use std::cell::RefCell;
+use ic_cketh_minter::eth_rpc_client::EthRpcClient;
+use ic_cketh_minter::metrics::encode_metrics;
+use ic_cketh_minter::state::event::{replay, Event};
+use ic_cketh_minter::state::{replace_state, State};
+use ic_cketh_minter::storage;
+use ic_cketh_minter::withdraw::{
+    self, process_retrieve_eth_requests, PROCESS_ETH_RETRIEVE_TRANSACTIONS_INTERVAL,
+};
+use std::time::Duration;
 
-thread_local! {
-    static STATE: RefCell<Option<State>> = RefCell::default();
-}
-
-struct State {
-    ecdsa_key_name: String,
-}
-
-impl From<InitArg> for State {
-    fn from(InitArg { ecdsa_key_name }: InitArg) -> Self {
-        Self { ecdsa_key_name }
-    }
-}
-
-fn read_state<R>(f: impl FnOnce(&State) -> R) -> R {
-    STATE.with(|s| f(s.borrow().as_ref().expect("BUG: state is not initialized")))
+fn setup_timers() {
+    // Start scraping logs immediately after the install, then repeat with the interval.
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || ic_cdk::spawn(scrap_eth_logs()));
+    ic_cdk_timers::set_timer_interval(SCRAPING_ETH_LOGS_INTERVAL, || {
+        ic_cdk::spawn(scrap_eth_logs())
+    });
+    ic_cdk_timers::set_timer_interval(PROCESS_ETH_RETRIEVE_TRANSACTIONS_INTERVAL, || {
+        ic_cdk::spawn(process_retrieve_eth_requests())
+    });
 }
 
 #[init]
@@ -29,77 +34,118 @@ fn read_state<R>(f: impl FnOnce(&State) -> R) -> R {
 fn init(arg: MinterArg) {
     match arg {
         MinterArg::InitArg(init_arg) => {
-            STATE.with(|cell| *cell.borrow_mut() = Some(State::from(init_arg)));
+            let state = State::try_from(init_arg.clone())
+                .unwrap_or_else(|e| ic_cdk::trap(&format!("invalid init arg: {e}")));
+            storage::record_event(&Event::Init(init_arg));
+            replace_state(state);
         }
         MinterArg::UpgradeArg => {
             ic_cdk::trap("cannot init canister state with upgrade args");
         }
     }
+    setup_timers();
 }
 
-#[update]
-#[candid_method(update)]
-async fn minter_address() -> String {
-    use ic_cdk::api::management_canister::ecdsa::{
-        ecdsa_public_key, EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyArgument,
-    };
-    let key_name = read_state(|s| s.ecdsa_key_name.clone());
-    let (response,) = ecdsa_public_key(EcdsaPublicKeyArgument {
-        canister_id: None,
-        derivation_path: vec![],
-        key_id: EcdsaKeyId {
-            curve: EcdsaCurve::Secp256k1,
-            name: key_name,
-        },
-    })
-    .await
-    .unwrap_or_else(|(error_code, message)| {
+#[post_upgrade]
+fn post_upgrade(minter_arg: Option<MinterArg>) {
+    if let Some(MinterArg::InitArg(_)) = minter_arg {
+        ic_cdk::trap("cannot upgrade canister state with init args");
+    }
+
+    let start = ic_cdk::api::instruction_counter();
+
+    let state = replay(storage::events()).unwrap_or_else(|e| {
         ic_cdk::trap(&format!(
-            "failed to get minter's public key: {} (error code = {:?})",
-            message, error_code,
+            "[upgrade]: failed to replay the event log: {:?}",
+            e
         ))
     });
-    let pubkey = PublicKey::deserialize_sec1(&response.public_key).unwrap_or_else(|e| {
-        ic_cdk::trap(&format!("failed to decode minter's public key: {:?}", e))
-    });
-    Address::from_pubkey(&pubkey).to_string()
+    replace_state(state);
+
+    let end = ic_cdk::api::instruction_counter();
+    ic_cdk::println!(
+        "[upgrade]: replaying {} events consumed {} instructions",
+        storage::count_events(),
+        end - start
+    );
+
+    setup_timers();
+}
+
+#[update]
+#[candid_method(update)]
+async fn minter_address() -> String {
+    ic_cketh_minter::management::minter_address()
+        .await
+        .unwrap_or_else(|e| ic_cdk::trap(&e))
+        .to_string()
+}
+
+#[update]
+#[candid_method(update)]
+async fn withdraw_eth(
+    WithdrawalArg { amount, recipient }: WithdrawalArg,
+) -> Result<RetrieveEthRequest, WithdrawalError> {
+    withdraw::withdraw_eth(ic_cdk::caller(), amount, recipient).await
+}
+
+#[query]
+#[candid_method(query)]
+fn retrieve_eth_status(block_index: u64) -> RetrieveEthStatus {
+    withdraw::retrieve_eth_status(block_index)
 }
 
 #[update]
 async fn display_logs(req: DisplayLogsRequest) -> Vec<ReceivedEthEvent> {
     use candid::Nat;
-    use eth_rpc::{Data, GetLogsParam, LogEntry};
-    use ethabi::param_type::ParamType;
+    use eth_rpc::{Data, GetLogsParam};
 
-    let result: Vec<LogEntry> = eth_rpc::call(
-        "https://rpc.sepolia.org",
-        "eth_getLogs",
-        vec![GetLogsParam {
+    let result = EthRpcClient::from_state()
+        .eth_get_logs(GetLogsParam {
             from_block: req.from.parse().expect("failed to parse 'from' block"),
             to_block: req.to.parse().expect("failed to parse 'to' block"),
             address: vec![req.address.parse().expect("failed to parse 'address'")],
-            topics: vec![],
-        }],
-    )
-    .await
-    .expect("HTTP call failed");
+            topics: vec![Data(eth_logs::RECEIVED_ETH_EVENT_TOPIC.to_vec())],
+        })
+        .await
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("failed to get logs: {e}")));
     result
         .into_iter()
         .map(|entry| {
-            let Data(data) = entry.data;
-            let args = ethabi::decode(
-                &[ParamType::Address, ParamType::Uint(256), ParamType::String],
-                &data,
-            )
-            .expect("failed to parse event payload");
-            assert_eq!(args.len(), 3);
+            let event = eth_logs::ReceivedEthEvent::try_from(entry)
+                .unwrap_or_else(|e| panic!("failed to parse event: {:?}", e));
             ReceivedEthEvent {
-                from_address: Address::new(args[0].clone().into_address().unwrap().0).to_string(),
-                value: Nat::from(args[1].clone().into_uint().unwrap().as_u128()),
-                principal: args[2].clone().into_string().unwrap().parse().unwrap(),
+                transaction_hash: event.transaction_hash.to_string(),
+                block_number: Nat::from(event.block_number.as_u128()),
+                log_index: Nat::from(event.log_index.as_u128()),
+                from_address: event.from_address.to_string(),
+                value: Nat::from(event.value.as_u128()),
+                principal: event.principal,
             }
         })
         .collect()
 }
 
+#[candid_method(query)]
+#[query]
+fn http_request(req: HttpRequest) -> HttpResponse {
+    if req.path() == "/metrics" {
+        let mut writer =
+            ic_metrics_encoder::MetricsEncoder::new(vec![], ic_cdk::api::time() as i64 / 1_000_000);
+
+        match encode_metrics(&mut writer) {
+            Ok(()) => HttpResponseBuilder::ok()
+                .header("Content-Type", "text/plain; version=0.0.4")
+                .with_body_and_content_length(writer.into_inner())
+                .build(),
+            Err(err) => {
+                HttpResponseBuilder::server_error(format!("Failed to encode metrics: {}", err))
+                    .build()
+            }
+        }
+    } else {
+        HttpResponseBuilder::not_found().build()
+    }
+}
+
 fn main() {}
@@ -0,0 +1,65 @@
+//! Calls to the management canister for threshold ECDSA.
+
+use crate::address::Address;
+use crate::state::{mutate_state, read_state};
+use ic_cdk::api::management_canister::ecdsa::{
+    ecdsa_public_key, sign_with_ecdsa, EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyArgument,
+    SignWithEcdsaArgument,
+};
+use ic_crypto_ecdsa_secp256k1::PublicKey;
+
+fn key_id() -> EcdsaKeyId {
+    EcdsaKeyId {
+        curve: EcdsaCurve::Secp256k1,
+        name: read_state(|s| s.ecdsa_key_name.clone()),
+    }
+}
+
+/// Returns the minter's public key, fetching it from the management canister on the first
+/// call.
+pub async fn lazy_call_ecdsa_public_key() -> Result<PublicKey, String> {
+    if let Some(public_key) = read_state(|s| s.ecdsa_public_key.clone()) {
+        return Ok(public_key);
+    }
+    let (response,) = ecdsa_public_key(EcdsaPublicKeyArgument {
+        canister_id: None,
+        derivation_path: vec![],
+        key_id: key_id(),
+    })
+    .await
+    .map_err(|(error_code, message)| {
+        format!(
+            "failed to get minter's public key: {} (error code = {:?})",
+            message, error_code,
+        )
+    })?;
+    let public_key = PublicKey::deserialize_sec1(&response.public_key)
+        .map_err(|e| format!("failed to decode minter's public key: {:?}", e))?;
+    mutate_state(|s| s.ecdsa_public_key = Some(public_key.clone()));
+    Ok(public_key)
+}
+
+/// The Ethereum address controlled by the minter.
+pub async fn minter_address() -> Result<Address, String> {
+    Ok(Address::from_pubkey(&lazy_call_ecdsa_public_key().await?))
+}
+
+/// Signs the message hash with the minter's key and returns the signature as `r || s`.
+pub async fn sign_with_minter_key(message_hash: [u8; 32]) -> Result<[u8; 64], String> {
+    let (response,) = sign_with_ecdsa(SignWithEcdsaArgument {
+        message_hash: message_hash.to_vec(),
+        derivation_path: vec![],
+        key_id: key_id(),
+    })
+    .await
+    .map_err(|(error_code, message)| {
+        format!(
+            "failed to sign with the minter's key: {} (error code = {:?})",
+            message, error_code,
+        )
+    })?;
+    response
+        .signature
+        .try_into()
+        .map_err(|signature: Vec<u8>| format!("invalid signature length {}", signature.len()))
+}
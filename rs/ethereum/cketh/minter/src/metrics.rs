@@ -0,0 +1,79 @@
+use crate::state::read_state;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+thread_local! {
+    /// The number of multi-provider JSON-RPC calls, by method and consensus outcome.
+    static RPC_CONSENSUS_RESULTS: RefCell<BTreeMap<(&'static str, &'static str), u64>> = RefCell::default();
+    /// The number of failed JSON-RPC calls, by provider host and method.
+    static RPC_PROVIDER_ERRORS: RefCell<BTreeMap<(String, &'static str), u64>> = RefCell::default();
+}
+
+pub fn observe_consensus_result(method: &'static str, outcome: &'static str) {
+    RPC_CONSENSUS_RESULTS.with(|c| *c.borrow_mut().entry((method, outcome)).or_default() += 1);
+}
+
+pub fn observe_provider_error(provider: &str, method: &'static str) {
+    // Only keep the host: the rest of the URL may contain API keys.
+    let host = provider
+        .trim_start_matches("https://")
+        .split(['/', '?'])
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    RPC_PROVIDER_ERRORS.with(|c| *c.borrow_mut().entry((host, method)).or_default() += 1);
+}
+
+pub fn encode_metrics(
+    metrics: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>,
+) -> std::io::Result<()> {
+    const WASM_PAGE_SIZE_IN_BYTES: f64 = 65536.0;
+
+    metrics.encode_gauge(
+        "cketh_minter_stable_memory_bytes",
+        ic_cdk::api::stable::stable_size() as f64 * WASM_PAGE_SIZE_IN_BYTES,
+        "Size of the stable memory allocated by this canister.",
+    )?;
+
+    metrics.encode_gauge(
+        "cketh_minter_cycle_balance",
+        ic_cdk::api::canister_balance128() as f64,
+        "Cycle balance on this canister.",
+    )?;
+
+    metrics.encode_gauge(
+        "cketh_minter_last_scraped_block_number",
+        read_state(|s| s.last_scraped_block_number.as_u64() as f64),
+        "The last Ethereum block scraped for deposits.",
+    )?;
+
+    RPC_CONSENSUS_RESULTS.with(|results| {
+        let results = results.borrow();
+        let mut counter = metrics.counter_vec(
+            "cketh_minter_rpc_consensus_results",
+            "Number of multi-provider JSON-RPC calls, by method and outcome.",
+        )?;
+        for ((method, outcome), count) in results.iter() {
+            counter =
+                counter.value(&[("method", *method), ("outcome", *outcome)], *count as f64)?;
+        }
+        Ok::<_, std::io::Error>(())
+    })?;
+
+    RPC_PROVIDER_ERRORS.with(|errors| {
+        let errors = errors.borrow();
+        let mut counter = metrics.counter_vec(
+            "cketh_minter_rpc_provider_errors",
+            "Number of failed JSON-RPC calls, by provider host and method.",
+        )?;
+        for ((provider, method), count) in errors.iter() {
+            counter = counter.value(
+                &[("provider", provider.as_str()), ("method", *method)],
+                *count as f64,
+            )?;
+        }
+        Ok::<_, std::io::Error>(())
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use crate::address::Address;
+use crate::endpoints::{EthereumNetwork, InitArg};
+use crate::eth_logs::{EventSource, ReceivedEthEvent};
+use crate::eth_rpc::Quantity;
+use crate::guard::TaskType;
+use crate::state::transactions::EthTransactions;
+use candid::Principal;
+use ic_crypto_ecdsa_secp256k1::PublicKey;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub mod audit;
+pub mod event;
+pub mod transactions;
+
+thread_local! {
+    static STATE: RefCell<Option<State>> = RefCell::default();
+}
+
+/// A deposit for which the minter minted ckETH.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MintedEvent {
+    pub deposit_event: ReceivedEthEvent,
+    pub mint_block_index: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    pub ethereum_network: EthereumNetwork,
+    pub ecdsa_key_name: String,
+    pub ethereum_contract_address: Address,
+    pub ledger_id: Principal,
+    /// The JSON-RPC providers, see [crate::eth_rpc_client::EthRpcClient].
+    pub rpc_providers: Vec<String>,
+    pub rpc_consensus_threshold: usize,
+    /// All events in blocks up to and including this one were processed.
+    pub last_scraped_block_number: Quantity,
+    /// Accepted deposits waiting to be minted.
+    pub events_to_mint: BTreeMap<EventSource, ReceivedEthEvent>,
+    pub minted_events: BTreeMap<EventSource, MintedEvent>,
+    /// Events that the minter will never mint, e.g., because of an invalid principal.
+    pub invalid_events: BTreeSet<EventSource>,
+    /// Deposits for which the minter may or may not have minted ckETH, because the mint task
+    /// panicked after contacting the ledger. They are not minted again automatically.
+    pub quarantined_deposits: BTreeSet<EventSource>,
+    pub eth_transactions: EthTransactions,
+
+    /// The minter's ECDSA public key, fetched lazily from the management canister.
+    pub ecdsa_public_key: Option<PublicKey>,
+    /// Tasks that are currently running, see [crate::guard::TimerGuard].
+    pub active_tasks: BTreeSet<TaskType>,
+}
+
+impl TryFrom<InitArg> for State {
+    type Error = String;
+
+    fn try_from(
+        InitArg {
+            ethereum_network,
+            ecdsa_key_name,
+            ethereum_contract_address,
+            ledger_id,
+            last_scraped_block_number,
+            next_transaction_nonce,
+            rpc_providers,
+            rpc_consensus_threshold,
+        }: InitArg,
+    ) -> Result<Self, Self::Error> {
+        if let Some(url) = rpc_providers
+            .iter()
+            .find(|url| !url.starts_with("https://"))
+        {
+            return Err(format!("RPC provider URL {url} does not use HTTPS"));
+        }
+        let rpc_consensus_threshold = rpc_consensus_threshold as usize;
+        if rpc_consensus_threshold == 0 || rpc_consensus_threshold > rpc_providers.len() {
+            return Err(format!(
+                "rpc_consensus_threshold must be between 1 and the number of RPC providers ({}), got {rpc_consensus_threshold}",
+                rpc_providers.len()
+            ));
+        }
+        let ethereum_contract_address = ethereum_contract_address
+            .parse()
+            .map_err(|e| format!("invalid ethereum_contract_address: {e}"))?;
+        Ok(Self {
+            ethereum_network,
+            ecdsa_key_name,
+            ethereum_contract_address,
+            ledger_id,
+            rpc_providers,
+            rpc_consensus_threshold,
+            last_scraped_block_number: Quantity::from(last_scraped_block_number),
+            events_to_mint: Default::default(),
+            minted_events: Default::default(),
+            invalid_events: Default::default(),
+            quarantined_deposits: Default::default(),
+            eth_transactions: EthTransactions::new(Quantity::from(next_transaction_nonce)),
+            ecdsa_public_key: None,
+            active_tasks: Default::default(),
+        })
+    }
+}
+
+impl State {
+    /// Returns true if the minter has already seen the event.
+    pub fn is_processed(&self, source: &EventSource) -> bool {
+        self.events_to_mint.contains_key(source)
+            || self.minted_events.contains_key(source)
+            || self.invalid_events.contains(source)
+            || self.quarantined_deposits.contains(source)
+    }
+
+    fn record_event_to_mint(&mut self, event: ReceivedEthEvent) {
+        let source = event.source();
+        assert!(
+            !self.is_processed(&source),
+            "BUG: event {source} was already processed"
+        );
+        self.events_to_mint.insert(source, event);
+    }
+
+    fn record_invalid_event(&mut self, source: EventSource) {
+        assert!(
+            !self.is_processed(&source),
+            "BUG: event {source} was already processed"
+        );
+        self.invalid_events.insert(source);
+    }
+
+    fn record_successful_mint(&mut self, source: EventSource, mint_block_index: u64) {
+        let deposit_event = self
+            .events_to_mint
+            .remove(&source)
+            .unwrap_or_else(|| panic!("BUG: minted event {source} is not pending"));
+        self.minted_events.insert(
+            source,
+            MintedEvent {
+                deposit_event,
+                mint_block_index,
+            },
+        );
+    }
+
+    fn record_quarantined_deposit(&mut self, source: EventSource) {
+        self.events_to_mint
+            .remove(&source)
+            .unwrap_or_else(|| panic!("BUG: quarantined event {source} is not pending"));
+        self.quarantined_deposits.insert(source);
+    }
+}
+
+/// Mutates the state, see [audit::process_event] for modifications that must be
+/// recorded in the event log.
+pub fn mutate_state<R>(f: impl FnOnce(&mut State) -> R) -> R {
+    STATE.with(|s| {
+        f(s.borrow_mut()
+            .as_mut()
+            .expect("BUG: state is not initialized"))
+    })
+}
+
+pub fn read_state<R>(f: impl FnOnce(&State) -> R) -> R {
+    STATE.with(|s| f(s.borrow().as_ref().expect("BUG: state is not initialized")))
+}
+
+/// Replaces the current state.
+pub fn replace_state(state: State) {
+    STATE.with(|s| {
+        *s.borrow_mut() = Some(state);
+    });
+}
@@ -0,0 +1,62 @@
+//! State modifications that should end up in the event log.
+
+use super::{event::Event, State};
+use crate::storage::record_event;
+
+/// Records the event in the event log and applies it to the state.
+pub fn process_event(state: &mut State, event: Event) {
+    record_event(&event);
+    apply_state_transition(state, &event);
+}
+
+/// Updates the state to reflect the given event. Used both when processing new events and
+/// when replaying the event log.
+pub fn apply_state_transition(state: &mut State, event: &Event) {
+    match event {
+        Event::Init(_) => {
+            panic!("BUG: the Init event can only be the first event in the log")
+        }
+        Event::AcceptedDeposit(deposit) => {
+            state.record_event_to_mint(deposit.clone());
+        }
+        Event::InvalidDeposit { event_source, .. } => {
+            state.record_invalid_event(*event_source);
+        }
+        Event::MintedCkEth {
+            event_source,
+            mint_block_index,
+        } => {
+            state.record_successful_mint(*event_source, *mint_block_index);
+        }
+        Event::QuarantinedDeposit { event_source } => {
+            state.record_quarantined_deposit(*event_source);
+        }
+        Event::SyncedToBlock { block_number } => {
+            state.last_scraped_block_number = *block_number;
+        }
+        Event::AcceptedEthWithdrawalRequest(request) => {
+            state
+                .eth_transactions
+                .record_withdrawal_request(request.clone());
+        }
+        Event::SignedTransaction {
+            withdrawal_id,
+            transaction,
+        } => {
+            state
+                .eth_transactions
+                .record_signed_transaction(*withdrawal_id, transaction.clone());
+        }
+        Event::FinalizedTransaction {
+            withdrawal_id,
+            transaction_hash,
+            transaction_receipt,
+        } => {
+            state.eth_transactions.record_finalized_transaction(
+                *withdrawal_id,
+                *transaction_hash,
+                transaction_receipt.clone(),
+            );
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::endpoints::InitArg;
+use crate::eth_logs::{EventSource, ReceivedEthEvent};
+use crate::eth_rpc::{Hash, Quantity, TransactionReceipt};
+use crate::state::transactions::EthWithdrawalRequest;
+use crate::state::State;
+use crate::tx::SignedEip1559TransactionRequest;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event {
+    /// The minter initialization with the specified arguments. Must be the first event in the
+    /// event log.
+    #[serde(rename = "init")]
+    Init(InitArg),
+    /// The minter discovered a valid deposit and will mint ckETH for it.
+    #[serde(rename = "accepted_deposit")]
+    AcceptedDeposit(ReceivedEthEvent),
+    /// The minter discovered a deposit it cannot mint ckETH for.
+    #[serde(rename = "invalid_deposit")]
+    InvalidDeposit {
+        event_source: EventSource,
+        reason: String,
+    },
+    /// The minter minted ckETH for an accepted deposit.
+    #[serde(rename = "minted_cketh")]
+    MintedCkEth {
+        event_source: EventSource,
+        mint_block_index: u64,
+    },
+    /// The mint task panicked after contacting the ledger for an accepted deposit, so the
+    /// minter does not know whether it minted ckETH for it and will not try again.
+    #[serde(rename = "quarantined_deposit")]
+    QuarantinedDeposit { event_source: EventSource },
+    /// The minter processed all events in blocks up to and including the specified one.
+    #[serde(rename = "synced_to_block")]
+    SyncedToBlock { block_number: Quantity },
+    /// The minter burned ckETH and will send ETH for the withdrawal request.
+    #[serde(rename = "accepted_eth_withdrawal_request")]
+    AcceptedEthWithdrawalRequest(EthWithdrawalRequest),
+    /// The minter signed a (possibly resubmitted) transaction for a withdrawal request.
+    #[serde(rename = "signed_transaction")]
+    SignedTransaction {
+        withdrawal_id: u64,
+        transaction: SignedEip1559TransactionRequest,
+    },
+    /// A transaction for a withdrawal request was included in a finalized block.
+    #[serde(rename = "finalized_transaction")]
+    FinalizedTransaction {
+        withdrawal_id: u64,
+        transaction_hash: Hash,
+        transaction_receipt: TransactionReceipt,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplayLogError {
+    /// There are no events in the event log.
+    EmptyLog,
+    /// The event log is inconsistent.
+    InconsistentLog(String),
+}
+
+/// Reconstructs the minter state from the event log.
+pub fn replay(mut events: impl Iterator<Item = Event>) -> Result<State, ReplayLogError> {
+    let mut state = match events.next() {
+        Some(Event::Init(init_arg)) => {
+            State::try_from(init_arg).map_err(ReplayLogError::InconsistentLog)?
+        }
+        Some(event) => {
+            return Err(ReplayLogError::InconsistentLog(format!(
+                "The first event is not Init: {:?}",
+                event
+            )))
+        }
+        None => return Err(ReplayLogError::EmptyLog),
+    };
+
+    for event in events {
+        if let Event::Init(_) = event {
+            return Err(ReplayLogError::InconsistentLog(
+                "Unexpected Init event in the middle of the log".to_string(),
+            ));
+        }
+        super::audit::apply_state_transition(&mut state, &event);
+    }
+
+    Ok(state)
+}
@@ -0,0 +1,176 @@
+use crate::address::Address;
+use crate::eth_rpc::{Hash, Quantity, TransactionReceipt};
+use crate::tx::SignedEip1559TransactionRequest;
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// A request to withdraw ETH for which the minter burnt ckETH.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EthWithdrawalRequest {
+    /// The burnt amount of ckWei, including the transaction fees.
+    pub withdrawal_amount: Quantity,
+    pub destination: Address,
+    /// The index of the burn transaction on the ledger, which identifies the withdrawal.
+    pub ledger_burn_index: u64,
+    pub from: Principal,
+    /// The IC time at which the request was accepted.
+    pub created_at: u64,
+}
+
+/// A withdrawal for which the minter signed at least one transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SentWithdrawal {
+    pub request: EthWithdrawalRequest,
+    /// All signed versions of the transaction, which share the same nonce, in the order of
+    /// creation. The last one is the most recent attempt.
+    pub transactions: Vec<SignedEip1559TransactionRequest>,
+}
+
+impl SentWithdrawal {
+    pub fn nonce(&self) -> Quantity {
+        self.latest_transaction().nonce()
+    }
+
+    pub fn latest_transaction(&self) -> &SignedEip1559TransactionRequest {
+        self.transactions
+            .last()
+            .expect("BUG: a sent withdrawal has at least one transaction")
+    }
+}
+
+/// A withdrawal whose transaction was included in a finalized block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FinalizedWithdrawal {
+    pub request: EthWithdrawalRequest,
+    pub transaction: SignedEip1559TransactionRequest,
+    pub receipt: TransactionReceipt,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WithdrawalStatus<'a> {
+    Pending(&'a EthWithdrawalRequest),
+    Sent(&'a SentWithdrawal),
+    Finalized(&'a FinalizedWithdrawal),
+}
+
+/// Tracks withdrawals from the moment ckETH is burnt until the ETH transfer is finalized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EthTransactions {
+    pub pending_withdrawal_requests: VecDeque<EthWithdrawalRequest>,
+    /// Withdrawals with signed transactions, indexed by the ledger burn index.
+    pub sent_withdrawals: BTreeMap<u64, SentWithdrawal>,
+    pub finalized_withdrawals: BTreeMap<u64, FinalizedWithdrawal>,
+    /// The nonce of the next transaction created by the minter.
+    pub next_nonce: Quantity,
+}
+
+impl EthTransactions {
+    pub fn new(next_nonce: Quantity) -> Self {
+        Self {
+            pending_withdrawal_requests: VecDeque::new(),
+            sent_withdrawals: BTreeMap::new(),
+            finalized_withdrawals: BTreeMap::new(),
+            next_nonce,
+        }
+    }
+
+    /// Returns true if there are no withdrawals waiting to be finalized.
+    pub fn is_idle(&self) -> bool {
+        self.pending_withdrawal_requests.is_empty() && self.sent_withdrawals.is_empty()
+    }
+
+    pub fn withdrawal_status(&self, ledger_burn_index: u64) -> Option<WithdrawalStatus> {
+        if let Some(request) = self
+            .pending_withdrawal_requests
+            .iter()
+            .find(|r| r.ledger_burn_index == ledger_burn_index)
+        {
+            return Some(WithdrawalStatus::Pending(request));
+        }
+        if let Some(sent) = self.sent_withdrawals.get(&ledger_burn_index) {
+            return Some(WithdrawalStatus::Sent(sent));
+        }
+        self.finalized_withdrawals
+            .get(&ledger_burn_index)
+            .map(WithdrawalStatus::Finalized)
+    }
+
+    pub fn record_withdrawal_request(&mut self, request: EthWithdrawalRequest) {
+        assert!(
+            self.withdrawal_status(request.ledger_burn_index).is_none(),
+            "BUG: duplicate withdrawal request {}",
+            request.ledger_burn_index
+        );
+        self.pending_withdrawal_requests.push_back(request);
+    }
+
+    /// Records a new signed transaction for a withdrawal. The first transaction of a pending
+    /// withdrawal consumes the next nonce, later ones replace the previous transaction and
+    /// must reuse its nonce.
+    pub fn record_signed_transaction(
+        &mut self,
+        ledger_burn_index: u64,
+        transaction: SignedEip1559TransactionRequest,
+    ) {
+        if let Some(sent) = self.sent_withdrawals.get_mut(&ledger_burn_index) {
+            assert_eq!(
+                sent.nonce(),
+                transaction.nonce(),
+                "BUG: a resubmitted transaction must reuse the nonce"
+            );
+            sent.transactions.push(transaction);
+            return;
+        }
+
+        let position = self
+            .pending_withdrawal_requests
+            .iter()
+            .position(|r| r.ledger_burn_index == ledger_burn_index)
+            .unwrap_or_else(|| panic!("BUG: unknown withdrawal request {ledger_burn_index}"));
+        assert_eq!(
+            transaction.nonce(),
+            self.next_nonce,
+            "BUG: the transaction must use the next nonce"
+        );
+        let request = self
+            .pending_withdrawal_requests
+            .remove(position)
+            .expect("BUG: the position is valid");
+        self.next_nonce = self.next_nonce + Quantity::ONE;
+        self.sent_withdrawals.insert(
+            ledger_burn_index,
+            SentWithdrawal {
+                request,
+                transactions: vec![transaction],
+            },
+        );
+    }
+
+    pub fn record_finalized_transaction(
+        &mut self,
+        ledger_burn_index: u64,
+        transaction_hash: Hash,
+        receipt: TransactionReceipt,
+    ) {
+        let sent = self
+            .sent_withdrawals
+            .remove(&ledger_burn_index)
+            .unwrap_or_else(|| panic!("BUG: withdrawal {ledger_burn_index} was not sent"));
+        let transaction = sent
+            .transactions
+            .into_iter()
+            .find(|tx| tx.hash() == transaction_hash)
+            .unwrap_or_else(|| {
+                panic!("BUG: unknown transaction {transaction_hash} for withdrawal {ledger_burn_index}")
+            });
+        self.finalized_withdrawals.insert(
+            ledger_burn_index,
+            FinalizedWithdrawal {
+                request: sent.request,
+                transaction,
+                receipt,
+            },
+        );
+    }
+}
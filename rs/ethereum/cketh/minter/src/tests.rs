@@ -108,3 +108,692 @@ fn address_display() {
         assert_eq!(&addr.to_string(), example);
     }
 }
+
+mod received_eth_event {
+    use crate::address::Address;
+    use crate::eth_logs::{
+        parse_principal, ReceivedEthEvent, ReceivedEthEventError, RECEIVED_ETH_EVENT_TOPIC,
+    };
+    use crate::eth_rpc::{Data, Hash, LogEntry, Quantity};
+    use candid::Principal;
+    use std::str::FromStr;
+
+    const CONTRACT_ADDRESS: &str = "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34";
+    const FROM_ADDRESS: &str = "0xdd2851Cdd40aE6536831558DD46db62fAc7A844d";
+
+    fn principal_word(principal: &Principal) -> Data {
+        let bytes = principal.as_slice();
+        let mut word = vec![0u8; 32];
+        word[0] = bytes.len() as u8;
+        word[1..1 + bytes.len()].copy_from_slice(bytes);
+        Data(word)
+    }
+
+    fn address_word(address: &str) -> Data {
+        let mut word = vec![0u8; 12];
+        word.extend_from_slice(&hex::decode(&address[2..]).unwrap());
+        Data(word)
+    }
+
+    fn log_entry(principal_topic: Data) -> LogEntry {
+        LogEntry {
+            address: Address::from_str(CONTRACT_ADDRESS).unwrap(),
+            topics: vec![
+                Data(RECEIVED_ETH_EVENT_TOPIC.to_vec()),
+                address_word(FROM_ADDRESS),
+                principal_topic,
+            ],
+            data: Data(Quantity::new(10_000_000_000_000_000).to_be_bytes().to_vec()),
+            block_number: Some(Quantity::new(3_960_623)),
+            transaction_hash: Some(Hash([0x11; 32])),
+            transaction_index: Some(Quantity::new(3)),
+            block_hash: Some(Hash([0x22; 32])),
+            log_index: Some(Quantity::new(39)),
+            removed: false,
+        }
+    }
+
+    #[test]
+    fn should_parse_received_eth_event() {
+        let principal = Principal::from_slice(&[4, 5, 6]);
+
+        let event = ReceivedEthEvent::try_from(log_entry(principal_word(&principal))).unwrap();
+
+        assert_eq!(
+            event,
+            ReceivedEthEvent {
+                transaction_hash: Hash([0x11; 32]),
+                block_number: Quantity::new(3_960_623),
+                log_index: Quantity::new(39),
+                from_address: Address::from_str(FROM_ADDRESS).unwrap(),
+                value: Quantity::new(10_000_000_000_000_000),
+                principal,
+            }
+        );
+    }
+
+    #[test]
+    fn should_reject_pending_or_removed_entries() {
+        let principal = Principal::management_canister();
+        let mut pending = log_entry(principal_word(&principal));
+        pending.block_number = None;
+        assert_eq!(
+            ReceivedEthEvent::try_from(pending),
+            Err(ReceivedEthEventError::PendingOrRemoved)
+        );
+
+        let mut removed = log_entry(principal_word(&principal));
+        removed.removed = true;
+        assert_eq!(
+            ReceivedEthEvent::try_from(removed),
+            Err(ReceivedEthEventError::PendingOrRemoved)
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_principal() {
+        let mut word = principal_word(&Principal::from_slice(&[1, 2, 3]));
+        word.0[31] = 1;
+
+        assert!(matches!(
+            ReceivedEthEvent::try_from(log_entry(word)),
+            Err(ReceivedEthEventError::InvalidPrincipal { .. })
+        ));
+    }
+
+    #[test]
+    fn should_parse_principal() {
+        let principal = Principal::from_slice(&[1, 2, 3]);
+        assert_eq!(parse_principal(&principal_word(&principal)), Ok(principal));
+
+        // Empty principal
+        assert!(parse_principal(&Data(vec![0; 32])).is_err());
+        // Too long
+        let mut word = vec![0u8; 32];
+        word[0] = 30;
+        assert!(parse_principal(&Data(word)).is_err());
+        // Anonymous
+        assert!(parse_principal(&principal_word(&Principal::anonymous())).is_err());
+        // Not a word
+        assert!(parse_principal(&Data(vec![3, 1, 2, 3])).is_err());
+    }
+}
+
+mod state {
+    use crate::address::Address;
+    use crate::endpoints::{EthereumNetwork, InitArg};
+    use crate::eth_logs::ReceivedEthEvent;
+    use crate::eth_rpc::{Hash, Quantity};
+    use crate::state::event::{replay, Event, ReplayLogError};
+    use crate::storage::{decode_event, encode_event};
+    use candid::Principal;
+    use std::str::FromStr;
+
+    fn init_arg() -> InitArg {
+        InitArg {
+            ethereum_network: EthereumNetwork::Sepolia,
+            ecdsa_key_name: "test_key_1".to_string(),
+            ethereum_contract_address: "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34".to_string(),
+            ledger_id: Principal::from_slice(&[1, 2, 3]),
+            last_scraped_block_number: 3_956_206,
+            next_transaction_nonce: 0,
+            rpc_providers: vec![
+                "https://rpc.sepolia.org".to_string(),
+                "https://ethereum-sepolia.publicnode.com".to_string(),
+            ],
+            rpc_consensus_threshold: 2,
+        }
+    }
+
+    fn deposit(log_index: u64) -> ReceivedEthEvent {
+        ReceivedEthEvent {
+            transaction_hash: Hash([0x11; 32]),
+            block_number: Quantity::new(3_960_623),
+            log_index: Quantity::from(log_index),
+            from_address: Address::from_str("0xdd2851Cdd40aE6536831558DD46db62fAc7A844d").unwrap(),
+            value: Quantity::new(10_000_000_000_000_000),
+            principal: Principal::from_slice(&[4, 5, 6]),
+        }
+    }
+
+    #[test]
+    fn should_encode_and_decode_events() {
+        let events = vec![
+            Event::Init(init_arg()),
+            Event::AcceptedDeposit(deposit(0)),
+            Event::InvalidDeposit {
+                event_source: deposit(1).source(),
+                reason: "invalid principal".to_string(),
+            },
+            Event::MintedCkEth {
+                event_source: deposit(0).source(),
+                mint_block_index: 7,
+            },
+            Event::QuarantinedDeposit {
+                event_source: deposit(2).source(),
+            },
+            Event::SyncedToBlock {
+                block_number: Quantity::new(3_960_623),
+            },
+        ];
+        for event in events {
+            assert_eq!(decode_event(&encode_event(&event)), event);
+        }
+    }
+
+    #[test]
+    fn should_replay_events() {
+        let state = replay(
+            vec![
+                Event::Init(init_arg()),
+                Event::AcceptedDeposit(deposit(0)),
+                Event::AcceptedDeposit(deposit(1)),
+                Event::AcceptedDeposit(deposit(3)),
+                Event::InvalidDeposit {
+                    event_source: deposit(2).source(),
+                    reason: "invalid principal".to_string(),
+                },
+                Event::MintedCkEth {
+                    event_source: deposit(0).source(),
+                    mint_block_index: 7,
+                },
+                Event::QuarantinedDeposit {
+                    event_source: deposit(3).source(),
+                },
+                Event::SyncedToBlock {
+                    block_number: Quantity::new(3_960_623),
+                },
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+
+        assert_eq!(state.last_scraped_block_number, Quantity::new(3_960_623));
+        assert_eq!(
+            state.events_to_mint.keys().collect::<Vec<_>>(),
+            vec![&deposit(1).source()]
+        );
+        assert_eq!(
+            state.minted_events[&deposit(0).source()].mint_block_index,
+            7
+        );
+        assert!(state.invalid_events.contains(&deposit(2).source()));
+        assert!(state.quarantined_deposits.contains(&deposit(3).source()));
+        for i in 0..4 {
+            assert!(state.is_processed(&deposit(i).source()));
+        }
+        assert!(!state.is_processed(&deposit(4).source()));
+    }
+
+    #[test]
+    fn should_derive_distinct_mint_memos_for_deposits_in_the_same_transaction() {
+        use crate::deposit::mint_memo;
+
+        let memo = |log_index| mint_memo(&deposit(log_index).source()).0.into_vec();
+        assert_eq!(memo(0).len(), 32);
+        assert_eq!(&memo(0)[..24], &[0x11; 24]);
+        assert_eq!(&memo(1)[24..], &1u64.to_be_bytes());
+        assert_ne!(memo(0), memo(1));
+        assert_eq!(memo(1), memo(1));
+    }
+
+    #[test]
+    fn should_mint_deposit_accepted_outside_of_the_deduplication_window() {
+        use crate::deposit::mint_deposit;
+        use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
+        use std::cell::{Cell, RefCell};
+
+        // The deposit was accepted long before the ledger's deduplication window.
+        let clock = Cell::new(1_693_000_000_000_000_000_u64);
+        let now = || {
+            clock.set(clock.get() + 86_400_000_000_000);
+            clock.get()
+        };
+        let transfers = RefCell::new(Vec::<TransferArg>::new());
+        let transfer = |arg: TransferArg| {
+            transfers.borrow_mut().push(arg);
+            let result = if transfers.borrow().len() == 1 {
+                Err(TransferError::TooOld)
+            } else {
+                Ok(42)
+            };
+            futures::future::ready(Ok(result))
+        };
+
+        let block_index =
+            futures::executor::block_on(mint_deposit(&deposit(0), now, transfer)).unwrap();
+
+        assert_eq!(block_index, 42);
+        let transfers = transfers.into_inner();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(
+            transfers[0].created_at_time,
+            Some(1_693_086_400_000_000_000)
+        );
+        assert_eq!(
+            transfers[1].created_at_time,
+            Some(1_693_172_800_000_000_000)
+        );
+        assert_eq!(transfers[0].memo, transfers[1].memo);
+        assert_eq!(transfers[1].to.owner, deposit(0).principal);
+    }
+
+    #[test]
+    fn should_stop_resubmitting_mint_rejected_as_too_old() {
+        use crate::deposit::{mint_deposit, MintError};
+        use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
+        use std::cell::Cell;
+
+        let attempts = Cell::new(0);
+        let transfer = |_: TransferArg| {
+            attempts.set(attempts.get() + 1);
+            futures::future::ready(Ok(Err(TransferError::TooOld)))
+        };
+
+        assert_eq!(
+            futures::executor::block_on(mint_deposit(&deposit(0), || 0, transfer)),
+            Err(MintError::Ledger(TransferError::TooOld))
+        );
+        assert_eq!(attempts.get(), 4);
+    }
+
+    #[test]
+    fn should_validate_rpc_providers() {
+        use crate::state::State;
+
+        assert!(State::try_from(init_arg()).is_ok());
+        assert!(State::try_from(InitArg {
+            rpc_consensus_threshold: 0,
+            ..init_arg()
+        })
+        .is_err());
+        assert!(State::try_from(InitArg {
+            rpc_consensus_threshold: 3,
+            ..init_arg()
+        })
+        .is_err());
+        assert!(State::try_from(InitArg {
+            rpc_providers: vec!["http://rpc.sepolia.org".to_string()],
+            rpc_consensus_threshold: 1,
+            ..init_arg()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn should_fail_to_replay_log_without_init() {
+        assert_eq!(replay(vec![].into_iter()), Err(ReplayLogError::EmptyLog));
+        assert!(matches!(
+            replay(vec![Event::AcceptedDeposit(deposit(0))].into_iter()),
+            Err(ReplayLogError::InconsistentLog(_))
+        ));
+    }
+}
+
+mod consensus {
+    use crate::eth_rpc::{
+        canonical_hash, reduce_with_threshold, ConsensusError, JsonRpcError, Quantity, RpcError,
+    };
+
+    fn provider(i: u8) -> String {
+        format!("https://provider{i}.example")
+    }
+
+    fn error() -> RpcError {
+        RpcError::JsonRpcError(JsonRpcError {
+            code: -32000,
+            message: "header not found".to_string(),
+        })
+    }
+
+    #[test]
+    fn should_accept_result_reaching_threshold() {
+        let results = vec![
+            (provider(1), Ok(Quantity::new(1))),
+            (provider(2), Err(error())),
+            (provider(3), Ok(Quantity::new(1))),
+        ];
+        assert_eq!(reduce_with_threshold(results, 2), Ok(Quantity::new(1)));
+    }
+
+    #[test]
+    fn should_accept_majority_result() {
+        let results = vec![
+            (provider(1), Ok(Quantity::new(1))),
+            (provider(2), Ok(Quantity::new(2))),
+            (provider(3), Ok(Quantity::new(1))),
+        ];
+        assert_eq!(reduce_with_threshold(results, 2), Ok(Quantity::new(1)));
+    }
+
+    #[test]
+    fn should_reject_too_few_replies() {
+        let results = vec![
+            (provider(1), Ok(Quantity::new(1))),
+            (provider(2), Err(error())),
+            (provider(3), Err(error())),
+        ];
+        assert_eq!(
+            reduce_with_threshold(results, 2),
+            Err(ConsensusError::TooFewReplies {
+                threshold: 2,
+                errors: vec![(provider(2), error()), (provider(3), error())],
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_inconsistent_results() {
+        let results = vec![
+            (provider(1), Ok(Quantity::new(1))),
+            (provider(2), Ok(Quantity::new(2))),
+            (provider(3), Err(error())),
+        ];
+        assert_eq!(
+            reduce_with_threshold(results, 2),
+            Err(ConsensusError::InconsistentResults {
+                threshold: 2,
+                results: vec![
+                    (provider(1), canonical_hash(&Quantity::new(1))),
+                    (provider(2), canonical_hash(&Quantity::new(2))),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_ambiguous_results() {
+        let results = vec![
+            (provider(1), Ok(Quantity::new(1))),
+            (provider(2), Ok(Quantity::new(2))),
+        ];
+        assert!(matches!(
+            reduce_with_threshold(results, 1),
+            Err(ConsensusError::InconsistentResults { .. })
+        ));
+    }
+}
+
+mod tx {
+    use crate::address::Address;
+    use crate::eth_rpc::{FeeHistory, Quantity};
+    use crate::tx::{
+        estimate_transaction_price, rlp, Eip1559Signature, Eip1559TransactionRequest,
+        SignedEip1559TransactionRequest, TransactionPrice,
+    };
+    use std::str::FromStr;
+
+    pub fn transaction(nonce: u64, max_fee_per_gas: u64) -> SignedEip1559TransactionRequest {
+        SignedEip1559TransactionRequest {
+            transaction: Eip1559TransactionRequest {
+                chain_id: 11155111,
+                nonce: Quantity::from(nonce),
+                max_priority_fee_per_gas: Quantity::new(1_500_000_000),
+                max_fee_per_gas: Quantity::from(max_fee_per_gas),
+                gas_limit: Quantity::new(21_000),
+                destination: Address::from_str("0xdd2851Cdd40aE6536831558DD46db62fAc7A844d")
+                    .unwrap(),
+                amount: Quantity::new(9_000_000_000_000_000),
+                data: vec![],
+            },
+            signature: Eip1559Signature {
+                signature_y_parity: true,
+                r: Quantity::from_str_hex(
+                    "0x7d097b81dc8bf5ad313f8d6656146d4723d0e6bb3fb35f1a709e6a3d4426c0f3",
+                )
+                .unwrap(),
+                s: Quantity::from_str_hex(
+                    "0x4f8a618d959e7d96e19156f0f5f2ed321b34e2004a0c8fdb7f02bc7d08b74441",
+                )
+                .unwrap(),
+            },
+        }
+    }
+
+    // See https://ethereum.org/en/developers/docs/data-structures-and-encoding/rlp/#examples
+    #[test]
+    fn rlp_encoding() {
+        fn bytes(b: &[u8]) -> Vec<u8> {
+            let mut out = vec![];
+            rlp::encode_bytes(b, &mut out);
+            out
+        }
+        fn quantity(q: u64) -> Vec<u8> {
+            let mut out = vec![];
+            rlp::encode_quantity(Quantity::from(q), &mut out);
+            out
+        }
+
+        assert_eq!(bytes(b"dog"), vec![0x83, b'd', b'o', b'g']);
+        assert_eq!(bytes(b""), vec![0x80]);
+        assert_eq!(bytes(&[0x0f]), vec![0x0f]);
+        assert_eq!(bytes(&[0x80]), vec![0x81, 0x80]);
+        assert_eq!(quantity(0), vec![0x80]);
+        assert_eq!(quantity(15), vec![0x0f]);
+        assert_eq!(quantity(1024), vec![0x82, 0x04, 0x00]);
+
+        let mut list = vec![];
+        let mut items = bytes(b"cat");
+        items.extend(bytes(b"dog"));
+        rlp::encode_list(&items, &mut list);
+        assert_eq!(
+            list,
+            vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']
+        );
+
+        let mut empty_list = vec![];
+        rlp::encode_list(&[], &mut empty_list);
+        assert_eq!(empty_list, vec![0xc0]);
+
+        let lorem = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        let mut expected = vec![0xb8, 0x38];
+        expected.extend_from_slice(lorem);
+        assert_eq!(bytes(lorem), expected);
+    }
+
+    #[test]
+    fn should_encode_signed_transaction() {
+        let tx = transaction(0, 50_000_000_000);
+        let raw = tx.raw_transaction();
+
+        // EIP-2718 envelope of an EIP-1559 transaction followed by a long list.
+        assert_eq!(raw[0], 0x02);
+        assert_eq!(raw[1], 0xf8);
+        assert_eq!(raw[2] as usize, raw.len() - 3);
+        assert!(tx.raw_transaction_hex().starts_with("0x02f8"));
+
+        // The signature is not part of the signed hash.
+        let mut other_signature = tx.clone();
+        other_signature.signature.signature_y_parity = false;
+        assert_eq!(tx.transaction.hash(), other_signature.transaction.hash());
+        assert_ne!(tx.hash(), other_signature.hash());
+        assert_ne!(
+            tx.transaction.hash(),
+            transaction(1, 50_000_000_000).transaction.hash()
+        );
+    }
+
+    #[test]
+    fn should_estimate_transaction_price() {
+        let fee_history = FeeHistory {
+            oldest_block: Quantity::new(0x10b0d8c),
+            base_fee_per_gas: vec![
+                Quantity::new(20_000_000_000),
+                Quantity::new(21_000_000_000),
+                Quantity::new(22_000_000_000),
+            ],
+            reward: vec![
+                vec![Quantity::new(3_000_000_000)],
+                vec![Quantity::new(1_000_000_000)],
+                vec![Quantity::new(2_000_000_000)],
+            ],
+        };
+
+        assert_eq!(
+            estimate_transaction_price(&fee_history),
+            Ok(TransactionPrice {
+                gas_limit: Quantity::new(21_000),
+                max_fee_per_gas: Quantity::new(2 * 22_000_000_000 + 2_000_000_000),
+                max_priority_fee_per_gas: Quantity::new(2_000_000_000),
+            })
+        );
+
+        assert!(estimate_transaction_price(&FeeHistory {
+            oldest_block: Quantity::ZERO,
+            base_fee_per_gas: vec![],
+            reward: vec![],
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn should_increase_fees_by_at_least_ten_percent_on_resubmission() {
+        let price = TransactionPrice {
+            gas_limit: Quantity::new(21_000),
+            max_fee_per_gas: Quantity::new(100),
+            max_priority_fee_per_gas: Quantity::new(10),
+        };
+
+        let lower_estimate = TransactionPrice {
+            gas_limit: Quantity::new(21_000),
+            max_fee_per_gas: Quantity::new(50),
+            max_priority_fee_per_gas: Quantity::new(1),
+        };
+        assert_eq!(
+            price.resubmission_price(&lower_estimate),
+            TransactionPrice {
+                gas_limit: Quantity::new(21_000),
+                max_fee_per_gas: Quantity::new(111),
+                max_priority_fee_per_gas: Quantity::new(12),
+            }
+        );
+
+        let higher_estimate = TransactionPrice {
+            gas_limit: Quantity::new(21_000),
+            max_fee_per_gas: Quantity::new(200),
+            max_priority_fee_per_gas: Quantity::new(20),
+        };
+        assert_eq!(price.resubmission_price(&higher_estimate), higher_estimate);
+    }
+}
+
+mod withdrawals {
+    use super::tx::transaction;
+    use crate::address::Address;
+    use crate::eth_rpc::{Hash, Quantity, TransactionReceipt};
+    use crate::state::event::Event;
+    use crate::state::transactions::{EthTransactions, EthWithdrawalRequest, WithdrawalStatus};
+    use crate::storage::{decode_event, encode_event};
+    use candid::Principal;
+    use std::str::FromStr;
+
+    fn request(ledger_burn_index: u64) -> EthWithdrawalRequest {
+        EthWithdrawalRequest {
+            withdrawal_amount: Quantity::new(10_000_000_000_000_000),
+            destination: Address::from_str("0xdd2851Cdd40aE6536831558DD46db62fAc7A844d").unwrap(),
+            ledger_burn_index,
+            from: Principal::from_slice(&[4, 5, 6]),
+            created_at: 1_690_000_000_000_000_000,
+        }
+    }
+
+    fn receipt(transaction_hash: Hash) -> TransactionReceipt {
+        TransactionReceipt {
+            block_hash: Hash([0x22; 32]),
+            block_number: Quantity::new(4_190_269),
+            effective_gas_price: Quantity::new(40_000_000_000),
+            gas_used: Quantity::new(21_000),
+            status: Quantity::ONE,
+            transaction_hash,
+        }
+    }
+
+    #[test]
+    fn should_reject_duplicate_burn() {
+        use crate::endpoints::WithdrawalError;
+        use crate::withdraw::burn_block_index;
+        use candid::Nat;
+        use icrc_ledger_types::icrc2::transfer_from::TransferFromError;
+
+        assert_eq!(burn_block_index(Ok(Nat::from(15_u64))), Ok(15));
+        assert!(matches!(
+            burn_block_index(Err(TransferFromError::Duplicate {
+                duplicate_of: Nat::from(15_u64)
+            })),
+            Err(WithdrawalError::TemporarilyUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn should_track_withdrawal_until_finalized() {
+        let mut transactions = EthTransactions::new(Quantity::new(7));
+        assert!(transactions.is_idle());
+
+        transactions.record_withdrawal_request(request(15));
+        transactions.record_withdrawal_request(request(16));
+        assert!(matches!(
+            transactions.withdrawal_status(15),
+            Some(WithdrawalStatus::Pending(_))
+        ));
+        assert_eq!(transactions.withdrawal_status(17), None);
+
+        let first = transaction(7, 50_000_000_000);
+        transactions.record_signed_transaction(15, first.clone());
+        assert_eq!(transactions.next_nonce, Quantity::new(8));
+        assert_eq!(transactions.pending_withdrawal_requests.len(), 1);
+
+        // A resubmission reuses the nonce
+        let resubmitted = transaction(7, 60_000_000_000);
+        transactions.record_signed_transaction(15, resubmitted.clone());
+        assert_eq!(transactions.next_nonce, Quantity::new(8));
+        match transactions.withdrawal_status(15) {
+            Some(WithdrawalStatus::Sent(sent)) => {
+                assert_eq!(sent.transactions, vec![first, resubmitted.clone()]);
+                assert_eq!(sent.latest_transaction(), &resubmitted);
+            }
+            status => panic!("unexpected status {:?}", status),
+        }
+
+        transactions.record_finalized_transaction(
+            15,
+            resubmitted.hash(),
+            receipt(resubmitted.hash()),
+        );
+        match transactions.withdrawal_status(15) {
+            Some(WithdrawalStatus::Finalized(finalized)) => {
+                assert_eq!(finalized.transaction, resubmitted);
+                assert_eq!(finalized.request, request(15));
+            }
+            status => panic!("unexpected status {:?}", status),
+        }
+        assert!(!transactions.is_idle());
+    }
+
+    #[test]
+    #[should_panic(expected = "the transaction must use the next nonce")]
+    fn should_panic_on_unexpected_nonce() {
+        let mut transactions = EthTransactions::new(Quantity::new(7));
+        transactions.record_withdrawal_request(request(15));
+        transactions.record_signed_transaction(15, transaction(8, 50_000_000_000));
+    }
+
+    #[test]
+    fn should_encode_and_decode_withdrawal_events() {
+        let tx = transaction(7, 50_000_000_000);
+        let events = vec![
+            Event::AcceptedEthWithdrawalRequest(request(15)),
+            Event::SignedTransaction {
+                withdrawal_id: 15,
+                transaction: tx.clone(),
+            },
+            Event::FinalizedTransaction {
+                withdrawal_id: 15,
+                transaction_hash: tx.hash(),
+                transaction_receipt: receipt(tx.hash()),
+            },
+        ];
+        for event in events {
+            assert_eq!(decode_event(&encode_event(&event)), event);
+        }
+    }
+}
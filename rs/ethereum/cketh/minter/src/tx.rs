@@ -0,0 +1,219 @@
+//! EIP-1559 transactions, see https://eips.ethereum.org/EIPS/eip-1559.
+
+use crate::address::{keccak, Address};
+use crate::eth_rpc::{FeeHistory, Hash, Quantity};
+use crate::management::{lazy_call_ecdsa_public_key, sign_with_minter_key};
+use serde::{Deserialize, Serialize};
+
+/// The type of EIP-1559 transactions, see https://eips.ethereum.org/EIPS/eip-2718.
+const EIP1559_TX_ID: u8 = 2;
+
+/// The gas needed by a plain ETH transfer to an externally owned account.
+pub const DEFAULT_GAS_LIMIT: u64 = 21_000;
+
+/// Minimal recursive length prefix encoding, see
+/// https://ethereum.org/en/developers/docs/data-structures-and-encoding/rlp/.
+pub mod rlp {
+    use crate::eth_rpc::Quantity;
+
+    fn encode_length(len: usize, offset: u8, out: &mut Vec<u8>) {
+        if len <= 55 {
+            out.push(offset + len as u8);
+        } else {
+            let len_bytes = len.to_be_bytes();
+            let len_bytes = strip_leading_zeros(&len_bytes);
+            out.push(offset + 55 + len_bytes.len() as u8);
+            out.extend_from_slice(len_bytes);
+        }
+    }
+
+    fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+        let first_non_zero = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+        &bytes[first_non_zero..]
+    }
+
+    /// Encodes a byte string.
+    pub fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+        if bytes.len() == 1 && bytes[0] < 0x80 {
+            out.push(bytes[0]);
+        } else {
+            encode_length(bytes.len(), 0x80, out);
+            out.extend_from_slice(bytes);
+        }
+    }
+
+    /// Encodes an integer as a big-endian byte string without leading zeros.
+    pub fn encode_quantity(value: Quantity, out: &mut Vec<u8>) {
+        encode_bytes(strip_leading_zeros(&value.to_be_bytes()), out);
+    }
+
+    /// Encodes a list whose items are already encoded.
+    pub fn encode_list(encoded_items: &[u8], out: &mut Vec<u8>) {
+        encode_length(encoded_items.len(), 0xc0, out);
+        out.extend_from_slice(encoded_items);
+    }
+}
+
+/// The fees of a transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionPrice {
+    pub gas_limit: Quantity,
+    pub max_fee_per_gas: Quantity,
+    pub max_priority_fee_per_gas: Quantity,
+}
+
+impl TransactionPrice {
+    /// The maximum amount of Wei the transaction can cost.
+    pub fn max_transaction_fee(&self) -> Quantity {
+        self.max_fee_per_gas * self.gas_limit
+    }
+
+    /// Returns a price suitable to replace a transaction with this price, given the current
+    /// price estimate. Nodes only accept replacements that increase both fees by at least 10%.
+    pub fn resubmission_price(&self, estimate: &TransactionPrice) -> TransactionPrice {
+        let bump = |fee: Quantity| fee + fee / Quantity::new(10) + Quantity::ONE;
+        TransactionPrice {
+            gas_limit: self.gas_limit,
+            max_fee_per_gas: bump(self.max_fee_per_gas).max(estimate.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(self.max_priority_fee_per_gas)
+                .max(estimate.max_priority_fee_per_gas),
+        }
+    }
+}
+
+/// Estimates the price of a transaction included in one of the next blocks from the fee
+/// history of the recent blocks.
+pub fn estimate_transaction_price(fee_history: &FeeHistory) -> Result<TransactionPrice, String> {
+    // The last base fee is the one of the next block.
+    let base_fee_of_next_block = *fee_history
+        .base_fee_per_gas
+        .last()
+        .ok_or_else(|| "fee history has no base fees".to_string())?;
+    let mut rewards: Vec<Quantity> = fee_history
+        .reward
+        .iter()
+        .filter_map(|block_rewards| block_rewards.first().copied())
+        .collect();
+    if rewards.is_empty() {
+        return Err("fee history has no rewards".to_string());
+    }
+    rewards.sort_unstable();
+    let max_priority_fee_per_gas = rewards[rewards.len() / 2];
+    // Leave room for the base fee doubling before the transaction is included, see
+    // https://www.blocknative.com/blog/eip-1559-fees.
+    let max_fee_per_gas = base_fee_of_next_block * Quantity::new(2) + max_priority_fee_per_gas;
+
+    Ok(TransactionPrice {
+        gas_limit: Quantity::from(DEFAULT_GAS_LIMIT),
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    })
+}
+
+/// An unsigned EIP-1559 transaction transferring ETH, with an empty access list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eip1559TransactionRequest {
+    pub chain_id: u64,
+    pub nonce: Quantity,
+    pub max_priority_fee_per_gas: Quantity,
+    pub max_fee_per_gas: Quantity,
+    pub gas_limit: Quantity,
+    pub destination: Address,
+    pub amount: Quantity,
+    pub data: Vec<u8>,
+}
+
+impl Eip1559TransactionRequest {
+    pub fn transaction_price(&self) -> TransactionPrice {
+        TransactionPrice {
+            gas_limit: self.gas_limit,
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+        }
+    }
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        rlp::encode_quantity(Quantity::from(self.chain_id), out);
+        rlp::encode_quantity(self.nonce, out);
+        rlp::encode_quantity(self.max_priority_fee_per_gas, out);
+        rlp::encode_quantity(self.max_fee_per_gas, out);
+        rlp::encode_quantity(self.gas_limit, out);
+        rlp::encode_bytes(self.destination.as_ref(), out);
+        rlp::encode_quantity(self.amount, out);
+        rlp::encode_bytes(&self.data, out);
+        // Empty access list
+        rlp::encode_list(&[], out);
+    }
+
+    /// The hash to sign: `keccak256(0x02 || rlp([chain_id, nonce, max_priority_fee_per_gas,
+    /// max_fee_per_gas, gas_limit, destination, amount, data, access_list]))`.
+    pub fn hash(&self) -> Hash {
+        let mut fields = Vec::new();
+        self.encode_fields(&mut fields);
+        let mut bytes = vec![EIP1559_TX_ID];
+        rlp::encode_list(&fields, &mut bytes);
+        Hash(keccak(&bytes))
+    }
+
+    /// Signs the transaction with the minter's threshold ECDSA key.
+    pub async fn sign(self) -> Result<SignedEip1559TransactionRequest, String> {
+        let hash = self.hash();
+        let signature = sign_with_minter_key(hash.0).await?;
+        let public_key = lazy_call_ecdsa_public_key().await?;
+        let signature_y_parity = public_key
+            .recover_y_parity_prehashed(&hash.0, &signature)
+            .ok_or_else(|| "BUG: the signature does not match the minter's key".to_string())?;
+        let (r, s) = signature.split_at(32);
+        Ok(SignedEip1559TransactionRequest {
+            transaction: self,
+            signature: Eip1559Signature {
+                signature_y_parity,
+                r: Quantity::from_be_bytes(r.try_into().expect("BUG: r is 32 bytes long")),
+                s: Quantity::from_be_bytes(s.try_into().expect("BUG: s is 32 bytes long")),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eip1559Signature {
+    pub signature_y_parity: bool,
+    pub r: Quantity,
+    pub s: Quantity,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedEip1559TransactionRequest {
+    pub transaction: Eip1559TransactionRequest,
+    pub signature: Eip1559Signature,
+}
+
+impl SignedEip1559TransactionRequest {
+    /// The signed transaction as expected by `eth_sendRawTransaction`.
+    pub fn raw_transaction(&self) -> Vec<u8> {
+        let mut fields = Vec::new();
+        self.transaction.encode_fields(&mut fields);
+        rlp::encode_quantity(
+            Quantity::from(self.signature.signature_y_parity as u8),
+            &mut fields,
+        );
+        rlp::encode_quantity(self.signature.r, &mut fields);
+        rlp::encode_quantity(self.signature.s, &mut fields);
+        let mut bytes = vec![EIP1559_TX_ID];
+        rlp::encode_list(&fields, &mut bytes);
+        bytes
+    }
+
+    pub fn raw_transaction_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.raw_transaction()))
+    }
+
+    /// The hash identifying the transaction on the Ethereum network.
+    pub fn hash(&self) -> Hash {
+        Hash(keccak(&self.raw_transaction()))
+    }
+
+    pub fn nonce(&self) -> Quantity {
+        self.transaction.nonce
+    }
+}
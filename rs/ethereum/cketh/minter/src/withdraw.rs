@@ -0,0 +1,386 @@
+//! The withdrawal flow: burning ckETH and sending ETH with EIP-1559 transactions signed by the
+//! minter's threshold ECDSA key.
+
+use crate::address::Address;
+use crate::endpoints::{
+    EthTransaction, RetrieveEthRequest, RetrieveEthStatus, TxFinalizedStatus, WithdrawalError,
+};
+use crate::eth_rpc::{BlockSpec, BlockTag, FeeHistoryParams, Quantity};
+use crate::eth_rpc_client::EthRpcClient;
+use crate::guard::{TaskType, TimerGuard};
+use crate::management::minter_address;
+use crate::state::transactions::{EthWithdrawalRequest, WithdrawalStatus};
+use crate::state::{audit::process_event, event::Event, mutate_state, read_state};
+use crate::tx::{estimate_transaction_price, Eip1559TransactionRequest, TransactionPrice};
+use candid::{Nat, Principal};
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
+use num_traits::ToPrimitive;
+use std::time::Duration;
+
+/// How often the minter creates, resubmits and finalizes withdrawal transactions.
+pub const PROCESS_ETH_RETRIEVE_TRANSACTIONS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The minimum amount of ckWei that can be withdrawn, 0.01 ETH.
+pub const MINIMUM_WITHDRAWAL_AMOUNT: u128 = 10_000_000_000_000_000;
+
+/// The maximum number of new transactions created in a single run.
+const TRANSACTIONS_TO_SIGN_BATCH_SIZE: usize = 5;
+
+/// Burns the caller's ckETH with an ICRC-2 `transfer_from` to the minting account and
+/// queues a transaction sending the corresponding amount of ETH to the recipient.
+pub async fn withdraw_eth(
+    caller: Principal,
+    amount: Nat,
+    recipient: String,
+) -> Result<RetrieveEthRequest, WithdrawalError> {
+    let destination: Address = recipient
+        .parse()
+        .map_err(WithdrawalError::InvalidDestination)?;
+    let withdrawal_amount = match amount.0.to_u128() {
+        Some(amount) if amount >= MINIMUM_WITHDRAWAL_AMOUNT => amount,
+        _ => {
+            return Err(WithdrawalError::AmountTooLow {
+                min_withdrawal_amount: Nat::from(MINIMUM_WITHDRAWAL_AMOUNT),
+            })
+        }
+    };
+
+    let created_at = ic_cdk::api::time();
+    let ledger_burn_index = burn(caller, withdrawal_amount, &destination, created_at).await?;
+
+    let request = EthWithdrawalRequest {
+        withdrawal_amount: Quantity::from(withdrawal_amount),
+        destination,
+        ledger_burn_index,
+        from: caller,
+        created_at,
+    };
+    ic_cdk::println!("Accepted withdrawal request {request:?}");
+    mutate_state(|s| process_event(s, Event::AcceptedEthWithdrawalRequest(request)));
+
+    Ok(RetrieveEthRequest {
+        block_index: Nat::from(ledger_burn_index),
+    })
+}
+
+/// Burns `amount` ckWei of `from`. The burn has the destination address as memo and
+/// `created_at` as `created_at_time`.
+async fn burn(
+    from: Principal,
+    amount: u128,
+    destination: &Address,
+    created_at: u64,
+) -> Result<u64, WithdrawalError> {
+    let ledger_id = read_state(|s| s.ledger_id);
+    let args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account {
+            owner: from,
+            subaccount: None,
+        },
+        // Transfers to the minting account are burns.
+        to: Account {
+            owner: ic_cdk::id(),
+            subaccount: None,
+        },
+        amount: Nat::from(amount),
+        fee: None,
+        memo: Some(destination.as_ref().to_vec().into()),
+        created_at_time: Some(created_at),
+    };
+    let (result,): (Result<Nat, TransferFromError>,) =
+        ic_cdk::call(ledger_id, "icrc2_transfer_from", (args,))
+            .await
+            .map_err(|(code, msg)| {
+                WithdrawalError::TemporarilyUnavailable(format!(
+                    "cannot burn ckETH: {msg} (reject code = {code:?})"
+                ))
+            })?;
+    burn_block_index(result)
+}
+
+/// Returns the ledger block index of a successful burn.
+///
+/// The `created_at_time` of a burn is the time of its withdrawal request, so a duplicate
+/// burn belongs to another, concurrent withdrawal request with the same amount and
+/// destination. It did not burn anything for this request, so it is an error.
+pub fn burn_block_index(result: Result<Nat, TransferFromError>) -> Result<u64, WithdrawalError> {
+    match result {
+        Ok(block_index) => block_index.0.to_u64().ok_or_else(|| {
+            WithdrawalError::TemporarilyUnavailable(format!(
+                "burn block index {block_index} does not fit into u64"
+            ))
+        }),
+        Err(TransferFromError::InsufficientFunds { balance }) => {
+            Err(WithdrawalError::InsufficientFunds { balance })
+        }
+        Err(TransferFromError::InsufficientAllowance { allowance }) => {
+            Err(WithdrawalError::InsufficientAllowance { allowance })
+        }
+        Err(TransferFromError::Duplicate { duplicate_of }) => {
+            Err(WithdrawalError::TemporarilyUnavailable(format!(
+                "cannot burn ckETH: an identical burn was already made in block {duplicate_of}"
+            )))
+        }
+        Err(e) => Err(WithdrawalError::TemporarilyUnavailable(format!(
+            "cannot burn ckETH: {e:?}"
+        ))),
+    }
+}
+
+pub fn retrieve_eth_status(ledger_burn_index: u64) -> RetrieveEthStatus {
+    read_state(
+        |s| match s.eth_transactions.withdrawal_status(ledger_burn_index) {
+            None => RetrieveEthStatus::NotFound,
+            Some(WithdrawalStatus::Pending(_)) => RetrieveEthStatus::Pending,
+            Some(WithdrawalStatus::Sent(sent)) => RetrieveEthStatus::TxSent(EthTransaction {
+                transaction_hash: sent.latest_transaction().hash().to_string(),
+            }),
+            Some(WithdrawalStatus::Finalized(finalized)) => {
+                let tx = EthTransaction {
+                    transaction_hash: finalized.transaction.hash().to_string(),
+                };
+                RetrieveEthStatus::TxFinalized(if finalized.receipt.status == Quantity::ONE {
+                    TxFinalizedStatus::Success(tx)
+                } else {
+                    TxFinalizedStatus::Failure(tx)
+                })
+            }
+        },
+    )
+}
+
+/// Creates transactions for pending withdrawals, resubmits stuck transactions and records
+/// finalized ones.
+pub async fn process_retrieve_eth_requests() {
+    let _guard = match TimerGuard::new(TaskType::RetrieveEth) {
+        Ok(guard) => guard,
+        Err(_) => return,
+    };
+    if read_state(|s| s.eth_transactions.is_idle()) {
+        return;
+    }
+
+    let client = EthRpcClient::from_state();
+    let price = match client
+        .eth_fee_history(FeeHistoryParams {
+            block_count: Quantity::new(5),
+            highest_block: BlockSpec::Tag(BlockTag::Latest),
+            reward_percentiles: vec![20],
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|fee_history| estimate_transaction_price(&fee_history))
+    {
+        Ok(price) => price,
+        Err(e) => {
+            ic_cdk::println!("Failed to estimate the transaction price: {e}");
+            return;
+        }
+    };
+
+    create_transactions(&price).await;
+    if let Err(e) = send_transactions(&client, &price).await {
+        ic_cdk::println!("Failed to send transactions: {e}");
+    }
+    if let Err(e) = finalize_transactions(&client).await {
+        ic_cdk::println!("Failed to finalize transactions: {e}");
+    }
+}
+
+/// Signs the first transaction of pending withdrawals, in the order of the requests since
+/// nonces are assigned sequentially.
+async fn create_transactions(price: &TransactionPrice) {
+    let (chain_id, requests) = read_state(|s| {
+        (
+            s.ethereum_network.chain_id(),
+            s.eth_transactions
+                .pending_withdrawal_requests
+                .iter()
+                .take(TRANSACTIONS_TO_SIGN_BATCH_SIZE)
+                .cloned()
+                .collect::<Vec<_>>(),
+        )
+    });
+
+    for request in requests {
+        let max_transaction_fee = price.max_transaction_fee();
+        if request.withdrawal_amount <= max_transaction_fee {
+            ic_cdk::println!(
+                "Withdrawal {} of {} Wei does not cover the transaction fee of {} Wei, waiting for lower fees",
+                request.ledger_burn_index,
+                request.withdrawal_amount,
+                max_transaction_fee
+            );
+            return;
+        }
+        let transaction = Eip1559TransactionRequest {
+            chain_id,
+            nonce: read_state(|s| s.eth_transactions.next_nonce),
+            max_priority_fee_per_gas: price.max_priority_fee_per_gas,
+            max_fee_per_gas: price.max_fee_per_gas,
+            gas_limit: price.gas_limit,
+            destination: request.destination,
+            amount: request.withdrawal_amount - max_transaction_fee,
+            data: vec![],
+        };
+        match transaction.sign().await {
+            Ok(signed) => mutate_state(|s| {
+                process_event(
+                    s,
+                    Event::SignedTransaction {
+                        withdrawal_id: request.ledger_burn_index,
+                        transaction: signed,
+                    },
+                )
+            }),
+            Err(e) => {
+                ic_cdk::println!(
+                    "Failed to sign the transaction of withdrawal {}: {e}",
+                    request.ledger_burn_index
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Sends the latest transaction of every withdrawal that is not included in a block yet.
+/// Transactions priced below the current estimate are first replaced by a transaction with
+/// higher fees.
+async fn send_transactions(client: &EthRpcClient, price: &TransactionPrice) -> Result<(), String> {
+    let minter_address = minter_address().await?;
+    let latest_tx_count = client
+        .eth_get_transaction_count(minter_address, BlockSpec::Tag(BlockTag::Latest))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let sent_withdrawals = read_state(|s| {
+        s.eth_transactions
+            .sent_withdrawals
+            .iter()
+            .filter(|(_, sent)| sent.nonce() >= latest_tx_count)
+            .map(|(id, sent)| (*id, sent.clone()))
+            .collect::<Vec<_>>()
+    });
+
+    for (withdrawal_id, sent) in sent_withdrawals {
+        let mut transaction = sent.latest_transaction().clone();
+        let current_price = transaction.transaction.transaction_price();
+        if current_price.max_fee_per_gas < price.max_fee_per_gas
+            || current_price.max_priority_fee_per_gas < price.max_priority_fee_per_gas
+        {
+            let new_price = current_price.resubmission_price(price);
+            let max_transaction_fee = new_price.max_transaction_fee();
+            if sent.request.withdrawal_amount <= max_transaction_fee {
+                ic_cdk::println!(
+                    "Cannot resubmit the transaction of withdrawal {withdrawal_id}: the fee of {max_transaction_fee} Wei exceeds the withdrawn amount",
+                );
+            } else {
+                let replacement = Eip1559TransactionRequest {
+                    max_priority_fee_per_gas: new_price.max_priority_fee_per_gas,
+                    max_fee_per_gas: new_price.max_fee_per_gas,
+                    gas_limit: new_price.gas_limit,
+                    amount: sent.request.withdrawal_amount - max_transaction_fee,
+                    ..transaction.transaction.clone()
+                };
+                match replacement.sign().await {
+                    Ok(signed) => {
+                        ic_cdk::println!(
+                            "Resubmitting withdrawal {withdrawal_id} with {} instead of {}",
+                            signed.hash(),
+                            transaction.hash()
+                        );
+                        mutate_state(|s| {
+                            process_event(
+                                s,
+                                Event::SignedTransaction {
+                                    withdrawal_id,
+                                    transaction: signed.clone(),
+                                },
+                            )
+                        });
+                        transaction = signed;
+                    }
+                    Err(e) => ic_cdk::println!(
+                        "Failed to sign the replacement transaction of withdrawal {withdrawal_id}: {e}"
+                    ),
+                }
+            }
+        }
+
+        // Sending a transaction is idempotent, nodes reply with an error if they already
+        // know the transaction. The transaction is broadcast to all providers.
+        match client
+            .eth_send_raw_transaction(transaction.raw_transaction_hex())
+            .await
+        {
+            Ok(hash) => ic_cdk::println!("Sent transaction {hash} of withdrawal {withdrawal_id}"),
+            Err(e) => ic_cdk::println!(
+                "Failed to send transaction {} of withdrawal {withdrawal_id}: {e}",
+                transaction.hash()
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Records the receipts of the transactions whose nonce is below the transaction count of
+/// the minter's address at the last finalized block.
+async fn finalize_transactions(client: &EthRpcClient) -> Result<(), String> {
+    let minter_address = minter_address().await?;
+    let finalized_tx_count = client
+        .eth_get_transaction_count(minter_address, BlockSpec::Tag(BlockTag::Finalized))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let finalized_withdrawals = read_state(|s| {
+        s.eth_transactions
+            .sent_withdrawals
+            .iter()
+            .filter(|(_, sent)| sent.nonce() < finalized_tx_count)
+            .map(|(id, sent)| (*id, sent.clone()))
+            .collect::<Vec<_>>()
+    });
+
+    for (withdrawal_id, sent) in finalized_withdrawals {
+        // Exactly one of the transactions sharing the nonce was included in a block,
+        // most likely the latest one.
+        let mut found = false;
+        for transaction in sent.transactions.iter().rev() {
+            let hash = transaction.hash();
+            match client.eth_get_transaction_receipt(hash).await {
+                Ok(Some(receipt)) => {
+                    ic_cdk::println!(
+                        "Transaction {hash} of withdrawal {withdrawal_id} was finalized in block {}",
+                        receipt.block_number
+                    );
+                    mutate_state(|s| {
+                        process_event(
+                            s,
+                            Event::FinalizedTransaction {
+                                withdrawal_id,
+                                transaction_hash: hash,
+                                transaction_receipt: receipt,
+                            },
+                        )
+                    });
+                    found = true;
+                    break;
+                }
+                Ok(None) => continue,
+                Err(e) => return Err(format!("failed to get the receipt of {hash}: {e}")),
+            }
+        }
+        if !found {
+            ic_cdk::println!(
+                "No transaction of withdrawal {withdrawal_id} with nonce {} was found in a finalized block",
+                sent.nonce()
+            );
+        }
+    }
+
+    Ok(())
+}
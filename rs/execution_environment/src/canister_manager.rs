@@ -13,8 +13,10 @@ use ic_config::flag_status::FlagStatus;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode, CanisterStatusResultV2,
-    CanisterStatusType, InstallCodeArgs, Method as Ic00Method,
+    CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode, CanisterSnapshotResponse,
+    CanisterStatusResultV2, CanisterStatusType, ChunkHash, InstallCodeArgs,
+    ListCanisterSnapshotsResponse, Method as Ic00Method, QueryStatsResponse, SnapshotId,
+    StoredChunksReply, UploadChunkReply,
 };
 use ic_interfaces::execution_environment::{
     CanisterOutOfCyclesError, HypervisorError, IngressHistoryWriter, SubnetAvailableMemory,
@@ -23,10 +25,12 @@ use ic_interfaces::messages::CanisterCall;
 use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::canister_state::system_state::CyclesUseCase;
+use ic_replicated_state::canister_state::system_state::{
+    CyclesUseCase, WasmChunkStore, CHUNK_SIZE, DEFAULT_MAX_SIZE as MAX_WASM_CHUNKS,
+};
 use ic_replicated_state::{
-    CallOrigin, CanisterState, CanisterStatus, NetworkTopology, ReplicatedState, SchedulerState,
-    SystemState,
+    page_map::PageAllocatorFileDescriptor, CallOrigin, CanisterSnapshot, CanisterState,
+    CanisterStatus, Memory, NetworkTopology, ReplicatedState, SchedulerState, SystemState,
 };
 use ic_system_api::ExecutionParameters;
 use ic_types::messages::{MessageId, SignedIngressContent};
@@ -46,6 +50,9 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::{collections::BTreeSet, convert::TryFrom, str::FromStr, sync::Arc};
 
+/// The maximum number of snapshots a canister can have at any time.
+pub(crate) const MAX_NUMBER_OF_SNAPSHOTS_PER_CANISTER: usize = 1;
+
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) struct InstallCodeResult {
     pub heap_delta: NumBytes,
@@ -213,11 +220,15 @@ impl From<InvalidMemoryAllocationError> for InstallCodeContextError {
     }
 }
 
-impl TryFrom<(CanisterChangeOrigin, InstallCodeArgs)> for InstallCodeContext {
+/// Converts the given install code arguments, where the memory allocation can
+/// be at most the given maximum memory allocation of the subnet.
+impl TryFrom<(CanisterChangeOrigin, InstallCodeArgs, NumBytes)> for InstallCodeContext {
     type Error = InstallCodeContextError;
 
-    fn try_from(input: (CanisterChangeOrigin, InstallCodeArgs)) -> Result<Self, Self::Error> {
-        let (origin, args) = input;
+    fn try_from(
+        input: (CanisterChangeOrigin, InstallCodeArgs, NumBytes),
+    ) -> Result<Self, Self::Error> {
+        let (origin, args, max_memory_allocation) = input;
         let canister_id = CanisterId::new(args.canister_id).map_err(|err| {
             InstallCodeContextError::InvalidCanisterId(format!(
                 "Converting canister id {} failed with {}",
@@ -235,11 +246,15 @@ impl TryFrom<(CanisterChangeOrigin, InstallCodeArgs)> for InstallCodeContext {
             None => None,
         };
         let memory_allocation = match args.memory_allocation {
-            Some(ma) => Some(MemoryAllocation::try_from(NumBytes::from(
-                ma.0.to_u64().ok_or_else(|| {
-                    InstallCodeContextError::MemoryAllocation(InvalidMemoryAllocationError::new(ma))
-                })?,
-            ))?),
+            Some(ma) => Some(MemoryAllocation::try_from_bytes(
+                NumBytes::from(ma.0.to_u64().ok_or_else(|| {
+                    InstallCodeContextError::MemoryAllocation(InvalidMemoryAllocationError::new(
+                        ma,
+                        max_memory_allocation,
+                    ))
+                })?),
+                max_memory_allocation,
+            )?),
             None => None,
         };
 
@@ -331,6 +346,15 @@ impl CanisterManager {
                 format!("Only canisters can call ic00 method {}", method_name),
             )),
 
+            // Canister logs are only served through non-replicated queries.
+            Ok(Ic00Method::FetchCanisterLogs) => Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "{} API is only accessible in non-replicated mode",
+                    Ic00Method::FetchCanisterLogs
+                ),
+            )),
+
 
             // These methods are only valid if they are sent by the controller
             // of the canister. We assume that the canister always wants to
@@ -342,7 +366,15 @@ impl CanisterManager {
             | Ok(Ic00Method::DeleteCanister) |
             Ok(Ic00Method::UpdateSettings)|
             Ok(Ic00Method::InstallCode) |
-            Ok(Ic00Method::SetController) => {
+            Ok(Ic00Method::SetController) |
+            Ok(Ic00Method::TakeCanisterSnapshot) |
+            Ok(Ic00Method::LoadCanisterSnapshot) |
+            Ok(Ic00Method::ListCanisterSnapshots) |
+            Ok(Ic00Method::DeleteCanisterSnapshot) |
+            Ok(Ic00Method::UploadChunk) |
+            Ok(Ic00Method::ClearChunkStore) |
+            Ok(Ic00Method::StoredChunks) |
+            Ok(Ic00Method::InstallChunkedCode) => {
                 match effective_canister_id {
                     Some(canister_id) => {
                         let canister = state.canister_state(&canister_id).ok_or_else(|| UserError::new(
@@ -401,6 +433,7 @@ impl CanisterManager {
             self.config.max_controllers,
             self.config.default_freeze_threshold,
             canister_cycles_balance,
+            Cycles::zero(),
             &self.cycles_account_manager,
             subnet_size,
         )
@@ -451,6 +484,22 @@ impl CanisterManager {
         if let Some(freezing_threshold) = settings.freezing_threshold() {
             canister.system_state.freeze_threshold = freezing_threshold;
         }
+        if let Some(log_visibility) = settings.log_visibility() {
+            canister.system_state.log_visibility = log_visibility;
+        }
+        if let Some(wasm_memory_limit) = settings.wasm_memory_limit() {
+            // A limit of zero means that there is no limit.
+            canister.system_state.wasm_memory_limit =
+                Some(wasm_memory_limit).filter(|limit| limit.get() > 0);
+        }
+        if let Some(wasm_memory_threshold) = settings.wasm_memory_threshold() {
+            canister.system_state.wasm_memory_threshold = wasm_memory_threshold;
+        }
+        if let Some(reserved_cycles_limit) = settings.reserved_cycles_limit() {
+            canister
+                .system_state
+                .set_reserved_balance_limit(Some(reserved_cycles_limit));
+        }
     }
 
     /// Tries to apply the requested settings on the canister identified by
@@ -479,6 +528,7 @@ impl CanisterManager {
             self.config.max_controllers,
             canister.system_state.freeze_threshold,
             canister.system_state.balance(),
+            canister.system_state.reserved_balance(),
             &self.cycles_account_manager,
             subnet_size,
         )?;
@@ -803,6 +853,7 @@ impl CanisterManager {
             canister,
             time,
             AddCanisterChangeToHistory::Yes(origin),
+            self.hypervisor.fd_factory(),
         );
         crate::util::process_responses(
             rejects,
@@ -948,6 +999,7 @@ impl CanisterManager {
         let compute_allocation = canister.scheduler_state.compute_allocation;
         let memory_allocation = canister.memory_allocation();
         let freeze_threshold = canister.system_state.freeze_threshold;
+        let query_stats = &canister.system_state.total_query_stats;
 
         Ok(CanisterStatusResultV2::new(
             canister.status(),
@@ -962,6 +1014,11 @@ impl CanisterManager {
             compute_allocation.as_percent(),
             Some(memory_allocation.bytes().get()),
             freeze_threshold.get(),
+            canister
+                .system_state
+                .wasm_memory_limit
+                .map(|limit| limit.get()),
+            canister.system_state.wasm_memory_threshold.get(),
             self.cycles_account_manager
                 .idle_cycles_burned_rate(
                     memory_allocation,
@@ -970,6 +1027,17 @@ impl CanisterManager {
                     subnet_size,
                 )
                 .get(),
+            canister.system_state.reserved_balance().get(),
+            canister
+                .system_state
+                .reserved_balance_limit()
+                .map(|limit| limit.get()),
+            QueryStatsResponse::new(
+                query_stats.num_calls,
+                query_stats.num_instructions,
+                query_stats.ingress_payload_size,
+                query_stats.egress_payload_size,
+            ),
         ))
     }
 
@@ -1045,6 +1113,10 @@ impl CanisterManager {
 
         // Take out the canister from `ReplicatedState`.
         let canister_to_delete = state.take_canister_state(&canister_id_to_delete).unwrap();
+        // Its snapshots are deleted together with it.
+        state
+            .canister_snapshots
+            .delete_snapshots(canister_id_to_delete);
         // Leftover cycles in the balance are considered `consumed`.
         let leftover_cycles = NominalCycles::from(canister_to_delete.system_state.balance());
         let consumed_cycles_by_canister_to_delete = leftover_cycles
@@ -1083,6 +1155,336 @@ impl CanisterManager {
         Ok(())
     }
 
+    /// Takes a snapshot of the canister's current state and stores it in
+    /// `ReplicatedState`.
+    ///
+    /// Only the controllers of the canister can take snapshots. If
+    /// `replace_snapshot` is given, the referenced snapshot of the same
+    /// canister is deleted before the new one is taken. The caller is charged
+    /// a fee proportional to the size of the snapshot. Snapshots count towards
+    /// the memory usage of the canister, so the new snapshot must fit into the
+    /// canister's memory allocation or, for best-effort canisters, into the
+    /// available subnet memory.
+    pub(crate) fn take_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        replace_snapshot: Option<SnapshotId>,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+        subnet_size: usize,
+    ) -> Result<CanisterSnapshotResponse, CanisterManagerError> {
+        let time = state.time();
+        let canister = state
+            .canister_state(&canister_id)
+            .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
+        validate_controller(canister, &sender)?;
+
+        let snapshot = CanisterSnapshot::from_canister(canister, time).ok_or(
+            CanisterManagerError::CanisterSnapshotEmptyCanister(canister_id),
+        )?;
+
+        let replaced_size = match replace_snapshot {
+            Some(replace_snapshot) => self
+                .validate_snapshot_ownership(state, canister_id, &replace_snapshot)?
+                .size(),
+            None => NumBytes::from(0),
+        };
+        let existing_snapshots = state.canister_snapshots.count_snapshots(canister_id)
+            - replace_snapshot.is_some() as usize;
+        if existing_snapshots >= MAX_NUMBER_OF_SNAPSHOTS_PER_CANISTER {
+            return Err(CanisterManagerError::CanisterSnapshotLimitExceeded {
+                canister_id,
+                limit: MAX_NUMBER_OF_SNAPSHOTS_PER_CANISTER,
+            });
+        }
+
+        let snapshot_size = snapshot.size();
+        let canister = state.canister_state_mut(&canister_id).unwrap();
+        let new_memory = NumBytes::from(snapshot_size.get().saturating_sub(replaced_size.get()));
+        reserve_memory_for_canister(canister, new_memory, round_limits)?;
+        if let Err(err) = self.charge_for_canister_snapshot(canister, snapshot_size, subnet_size) {
+            release_memory_of_canister(canister, new_memory, round_limits);
+            return Err(err);
+        }
+        let freed_memory = NumBytes::from(replaced_size.get().saturating_sub(snapshot_size.get()));
+        release_memory_of_canister(canister, freed_memory, round_limits);
+        let snapshot_id =
+            SnapshotId::new(canister_id, canister.system_state.new_local_snapshot_id());
+
+        if let Some(replace_snapshot) = replace_snapshot {
+            state.canister_snapshots.remove(&replace_snapshot);
+        }
+        state
+            .canister_snapshots
+            .push(snapshot_id, Arc::new(snapshot));
+        state.update_snapshots_memory_usage(&canister_id);
+
+        Ok(CanisterSnapshotResponse::new(
+            &snapshot_id,
+            time.as_nanos_since_unix_epoch(),
+            snapshot_size,
+        ))
+    }
+
+    /// Replaces the execution state and the certified data of the canister
+    /// with the ones saved in the given snapshot.
+    ///
+    /// Only the controllers of the canister can load snapshots and only
+    /// snapshots that belong to the same canister can be loaded. The Wasm
+    /// module of the snapshot is recompiled, which is charged against the
+    /// round instruction limit, and the caller is charged a fee proportional
+    /// to the size of the snapshot.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn load_canister_snapshot(
+        &self,
+        origin: CanisterChangeOrigin,
+        canister_id: CanisterId,
+        snapshot_id: SnapshotId,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+        compilation_cost_handling: CompilationCostHandling,
+        subnet_size: usize,
+    ) -> Result<(), CanisterManagerError> {
+        let sender = origin.origin();
+        let time = state.time();
+        let canister = state
+            .canister_state(&canister_id)
+            .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
+        validate_controller(canister, &sender)?;
+        let snapshot = self.validate_snapshot_ownership(state, canister_id, &snapshot_id)?;
+
+        let (_instructions, result) = self.hypervisor.create_execution_state(
+            snapshot.binary().clone(),
+            "NOT_USED".into(),
+            canister_id,
+            round_limits,
+            compilation_cost_handling,
+        );
+        let mut execution_state = result.map_err(|err| (canister_id, err))?;
+        execution_state.wasm_memory = Memory::new(
+            snapshot.wasm_memory().page_map.clone(),
+            snapshot.wasm_memory().size,
+        );
+        execution_state.stable_memory = Memory::new(
+            snapshot.stable_memory().page_map.clone(),
+            snapshot.stable_memory().size,
+        );
+        execution_state.exported_globals = snapshot.exported_globals().clone();
+
+        let canister = state.canister_state_mut(&canister_id).unwrap();
+        let old_memory_usage = canister
+            .execution_state
+            .as_ref()
+            .map_or(NumBytes::from(0), |execution_state| {
+                execution_state.memory_usage()
+            });
+        let new_memory_usage = execution_state.memory_usage();
+        let memory_growth = NumBytes::from(
+            new_memory_usage
+                .get()
+                .saturating_sub(old_memory_usage.get()),
+        );
+        reserve_memory_for_canister(canister, memory_growth, round_limits)?;
+        if let Err(err) = self.charge_for_canister_snapshot(canister, snapshot.size(), subnet_size)
+        {
+            release_memory_of_canister(canister, memory_growth, round_limits);
+            return Err(err);
+        }
+        let freed_memory = NumBytes::from(
+            old_memory_usage
+                .get()
+                .saturating_sub(new_memory_usage.get()),
+        );
+        release_memory_of_canister(canister, freed_memory, round_limits);
+
+        let module_hash = snapshot.binary().module_hash();
+        canister.execution_state = Some(execution_state);
+        canister.system_state.certified_data = snapshot.certified_data().clone();
+        canister.system_state.canister_version += 1;
+        canister.system_state.add_canister_change(
+            time,
+            origin,
+            CanisterChangeDetails::code_deployment(CanisterInstallMode::Reinstall, module_hash),
+        );
+        Ok(())
+    }
+
+    /// Lists the snapshots of the canister.
+    ///
+    /// Only the controllers of the canister can list its snapshots.
+    pub(crate) fn list_canister_snapshots(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &ReplicatedState,
+    ) -> Result<ListCanisterSnapshotsResponse, CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        validate_controller(canister, &sender)?;
+
+        Ok(ListCanisterSnapshotsResponse(
+            state
+                .canister_snapshots
+                .list_snapshots(canister_id)
+                .into_iter()
+                .map(|(snapshot_id, snapshot)| {
+                    CanisterSnapshotResponse::new(
+                        &snapshot_id,
+                        snapshot.taken_at_timestamp().as_nanos_since_unix_epoch(),
+                        snapshot.size(),
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    /// Deletes a snapshot of the canister.
+    ///
+    /// Only the controllers of the canister can delete its snapshots. The
+    /// memory used by the snapshot is freed.
+    pub(crate) fn delete_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        snapshot_id: SnapshotId,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<(), CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        validate_controller(canister, &sender)?;
+        let snapshot = self.validate_snapshot_ownership(state, canister_id, &snapshot_id)?;
+        release_memory_of_canister(canister, snapshot.size(), round_limits);
+
+        state.canister_snapshots.remove(&snapshot_id);
+        state.update_snapshots_memory_usage(&canister_id);
+        Ok(())
+    }
+
+    /// Returns the snapshot with the given id if it exists and belongs to
+    /// the given canister.
+    fn validate_snapshot_ownership(
+        &self,
+        state: &ReplicatedState,
+        canister_id: CanisterId,
+        snapshot_id: &SnapshotId,
+    ) -> Result<Arc<CanisterSnapshot>, CanisterManagerError> {
+        if snapshot_id.get_canister_id() != canister_id {
+            return Err(CanisterManagerError::CanisterSnapshotInvalidOwnership {
+                canister_id,
+                snapshot_id: *snapshot_id,
+            });
+        }
+        state.canister_snapshots.get(snapshot_id).cloned().ok_or(
+            CanisterManagerError::CanisterSnapshotNotFound {
+                canister_id,
+                snapshot_id: *snapshot_id,
+            },
+        )
+    }
+
+    /// Charges the canister the fee for taking or loading a snapshot of the
+    /// given size.
+    fn charge_for_canister_snapshot(
+        &self,
+        canister: &mut CanisterState,
+        snapshot_size: NumBytes,
+        subnet_size: usize,
+    ) -> Result<(), CanisterManagerError> {
+        let fee = self
+            .cycles_account_manager
+            .canister_snapshot_fee(snapshot_size, subnet_size);
+        let memory_usage = canister.memory_usage();
+        let compute_allocation = canister.scheduler_state.compute_allocation;
+        self.cycles_account_manager
+            .consume_cycles(
+                &mut canister.system_state,
+                memory_usage,
+                compute_allocation,
+                fee,
+                subnet_size,
+                CyclesUseCase::Instructions,
+            )
+            .map_err(CanisterManagerError::CanisterSnapshotNotEnoughCycles)
+    }
+
+    /// Adds a chunk to the Wasm chunk store of the canister and returns its
+    /// hash.
+    ///
+    /// Only the controllers of the canister can upload chunks. A new chunk
+    /// counts towards the memory usage of the canister, so it must fit into
+    /// the canister's memory allocation or, for best-effort canisters, into
+    /// the available subnet memory.
+    pub(crate) fn upload_chunk(
+        &self,
+        sender: PrincipalId,
+        canister: &mut CanisterState,
+        chunk: Vec<u8>,
+        round_limits: &mut RoundLimits,
+    ) -> Result<UploadChunkReply, CanisterManagerError> {
+        validate_controller(canister, &sender)?;
+
+        let canister_id = canister.canister_id();
+        canister
+            .system_state
+            .wasm_chunk_store
+            .can_insert_chunk(MAX_WASM_CHUNKS, &chunk)
+            .map_err(|message| CanisterManagerError::WasmChunkStoreError {
+                canister_id,
+                message,
+            })?;
+
+        let hash = WasmChunkStore::chunk_hash(&chunk);
+        if !canister.system_state.wasm_chunk_store.contains(&hash) {
+            // Every chunk takes up a full slot in the store.
+            reserve_memory_for_canister(canister, NumBytes::from(CHUNK_SIZE), round_limits)?;
+        }
+
+        let hash = canister.system_state.wasm_chunk_store.insert_chunk(&chunk);
+        Ok(UploadChunkReply {
+            hash: hash.to_vec(),
+        })
+    }
+
+    /// Removes all chunks from the Wasm chunk store of the canister.
+    pub(crate) fn clear_chunk_store(
+        &self,
+        sender: PrincipalId,
+        canister: &mut CanisterState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<(), CanisterManagerError> {
+        validate_controller(canister, &sender)?;
+        release_memory_of_canister(
+            canister,
+            canister.wasm_chunk_store_memory_usage(),
+            round_limits,
+        );
+        canister
+            .system_state
+            .wasm_chunk_store
+            .clear(self.hypervisor.fd_factory());
+        Ok(())
+    }
+
+    /// Returns the hashes of the chunks in the Wasm chunk store of the
+    /// canister.
+    pub(crate) fn stored_chunks(
+        &self,
+        sender: PrincipalId,
+        canister: &CanisterState,
+    ) -> Result<StoredChunksReply, CanisterManagerError> {
+        validate_controller(canister, &sender)?;
+        Ok(StoredChunksReply(
+            canister
+                .system_state
+                .wasm_chunk_store
+                .keys()
+                .map(|hash| ChunkHash {
+                    hash: hash.to_vec(),
+                })
+                .collect(),
+        ))
+    }
+
     /// Creates a new canister with the cycles amount specified and inserts it
     /// into `ReplicatedState`.
     ///
@@ -1204,6 +1606,7 @@ impl CanisterManager {
             sender,
             cycles,
             self.config.default_freeze_threshold,
+            self.hypervisor.fd_factory(),
         );
 
         system_state.remove_cycles(creation_fee, CyclesUseCase::CanisterCreation);
@@ -1393,6 +1796,33 @@ pub(crate) enum CanisterManagerError {
         available: Cycles,
         threshold: Cycles,
     },
+    CanisterSnapshotEmptyCanister(CanisterId),
+    CanisterSnapshotNotFound {
+        canister_id: CanisterId,
+        snapshot_id: SnapshotId,
+    },
+    CanisterSnapshotInvalidOwnership {
+        canister_id: CanisterId,
+        snapshot_id: SnapshotId,
+    },
+    CanisterSnapshotLimitExceeded {
+        canister_id: CanisterId,
+        limit: usize,
+    },
+    CanisterSnapshotNotEnoughCycles(CanisterOutOfCyclesError),
+    WasmChunkStoreError {
+        canister_id: CanisterId,
+        message: String,
+    },
+    ReservedCyclesLimitExceededInMemoryGrow {
+        bytes: NumBytes,
+        requested: Cycles,
+        limit: Cycles,
+    },
+    ReservedCyclesLimitIsTooLow {
+        cycles: Cycles,
+        limit: Cycles,
+    },
 }
 
 impl From<CanisterManagerError> for UserError {
@@ -1592,6 +2022,62 @@ impl From<CanisterManagerError> for UserError {
                          threshold - available)
                 )
             }
+            CanisterSnapshotEmptyCanister(canister_id) => {
+                Self::new(
+                    ErrorCode::CanisterWasmModuleNotFound,
+                    format!("Cannot take a snapshot of canister {} because it has no Wasm module installed.", canister_id),
+                )
+            }
+            CanisterSnapshotNotFound { canister_id, snapshot_id } => {
+                Self::new(
+                    ErrorCode::CanisterSnapshotNotFound,
+                    format!("Could not find the snapshot ID {} for canister {}.", snapshot_id, canister_id),
+                )
+            }
+            CanisterSnapshotInvalidOwnership { canister_id, snapshot_id } => {
+                Self::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!("The snapshot {} does not belong to canister {}.", snapshot_id, canister_id),
+                )
+            }
+            CanisterSnapshotLimitExceeded { canister_id, limit } => {
+                Self::new(
+                    ErrorCode::CanisterSnapshotLimitExceeded,
+                    format!("Canister {} has reached the maximum number of {} snapshots. Delete or replace an existing snapshot to take a new one.", canister_id, limit),
+                )
+            }
+            CanisterSnapshotNotEnoughCycles(err) => {
+                Self::new(
+                    ErrorCode::CanisterOutOfCycles,
+                    format!("Canister snapshot operation failed with `{}`", err),
+                )
+            }
+            WasmChunkStoreError { canister_id, message } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!("Error with the Wasm chunk store of canister {}: {}", canister_id, message),
+                )
+            }
+            ReservedCyclesLimitExceededInMemoryGrow { bytes, requested, limit } => {
+                Self::new(
+                    ErrorCode::ReservedCyclesLimitExceededInMemoryGrow,
+                    format!(
+                        "Canister cannot grow memory by {} bytes due to its reserved cycles limit. \
+                         The current limit ({}) would be exceeded by {}.",
+                        bytes, limit, requested - limit,
+                    ),
+                )
+            }
+            ReservedCyclesLimitIsTooLow { cycles, limit } => {
+                Self::new(
+                    ErrorCode::ReservedCyclesLimitIsTooLow,
+                    format!(
+                        "Cannot set the reserved cycles limit {} below the reserved cycles balance of \
+                         the canister {}.",
+                        limit, cycles,
+                    ),
+                )
+            }
         }
     }
 }
@@ -1609,6 +2095,58 @@ impl From<CanisterManagerError> for RejectContext {
     }
 }
 
+/// Checks that `bytes` of additional memory fit into the memory allocation of
+/// the canister or, for best-effort canisters, reserves them from the available
+/// subnet memory.
+fn reserve_memory_for_canister(
+    canister: &CanisterState,
+    bytes: NumBytes,
+    round_limits: &mut RoundLimits,
+) -> Result<(), CanisterManagerError> {
+    match canister.memory_allocation() {
+        MemoryAllocation::Reserved(reserved_bytes) => {
+            let memory_usage_needed = canister.memory_usage() + bytes;
+            if memory_usage_needed > reserved_bytes {
+                return Err(CanisterManagerError::NotEnoughMemoryAllocationGiven {
+                    memory_allocation_given: canister.memory_allocation(),
+                    memory_usage_needed,
+                });
+            }
+        }
+        MemoryAllocation::BestEffort => {
+            round_limits
+                .subnet_available_memory
+                .try_decrement(bytes, NumBytes::from(0), NumBytes::from(0))
+                .map_err(
+                    |_| CanisterManagerError::SubnetMemoryCapacityOverSubscribed {
+                        requested: bytes,
+                        available: NumBytes::from(
+                            round_limits
+                                .subnet_available_memory
+                                .get_execution_memory()
+                                .max(0) as u64,
+                        ),
+                    },
+                )?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns `bytes` of memory no longer used by a best-effort canister to the
+/// available subnet memory.
+fn release_memory_of_canister(
+    canister: &CanisterState,
+    bytes: NumBytes,
+    round_limits: &mut RoundLimits,
+) {
+    if canister.memory_allocation() == MemoryAllocation::BestEffort {
+        round_limits
+            .subnet_available_memory
+            .increment(bytes, NumBytes::from(0), NumBytes::from(0));
+    }
+}
+
 /// Uninstalls a canister.
 ///
 /// See https://sdk.dfinity.org/docs/interface-spec/index.html#ic-uninstall_code
@@ -1620,6 +2158,7 @@ pub fn uninstall_canister(
     canister: &mut CanisterState,
     time: Time,
     add_canister_change: AddCanisterChangeToHistory,
+    fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
 ) -> Vec<Response> {
     // Drop the canister's execution state.
     canister.execution_state = None;
@@ -1627,6 +2166,9 @@ pub fn uninstall_canister(
     // Drop its certified data.
     canister.system_state.certified_data = Vec::new();
 
+    // Drop the chunks it uploaded.
+    canister.system_state.wasm_chunk_store.clear(fd_factory);
+
     // Deactivate global timer.
     canister.system_state.global_timer = CanisterTimer::Inactive;
     // Increment canister version.
@@ -1673,7 +2215,7 @@ pub fn uninstall_canister(
                         },
                     }));
                 }
-                CallOrigin::CanisterUpdate(caller_canister_id, callback_id) => {
+                CallOrigin::CanisterUpdate(caller_canister_id, callback_id, deadline) => {
                     rejects.push(Response::Canister(CanisterResponse {
                         originator: *caller_canister_id,
                         respondent: canister_id,
@@ -1683,6 +2225,7 @@ pub fn uninstall_canister(
                             code: RejectCode::CanisterReject,
                             message: String::from("Canister has been uninstalled."),
                         }),
+                        deadline: *deadline,
                     }));
                 }
                 CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => fatal!(
@@ -67,7 +67,7 @@ use ic_types::{
     messages::{CallbackId, StopCanisterContext},
     nominal_cycles::NominalCycles,
     CanisterId, CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumBytes,
-    NumInstructions, QueryAllocation, SubnetId, Time, UserId,
+    NumInstructions, QueryAllocation, SubnetId, Time, UserId, MAX_MEMORY_ALLOCATION,
 };
 use ic_wasm_types::{CanisterModule, WasmValidationError};
 use lazy_static::lazy_static;
@@ -2329,7 +2329,7 @@ fn delete_canister_consumed_cycles_observed() {
         let initial_cycles = Cycles::new(5_000_000_000_000);
 
         let mut canister = CanisterState {
-            system_state: SystemState::new_stopped(
+            system_state: SystemState::new_stopped_for_testing(
                 canister_id,
                 controller_id.get(),
                 initial_cycles,
@@ -2919,6 +2919,7 @@ fn uninstall_canister_doesnt_respond_to_responded_call_contexts() {
                 .build(),
             mock_time(),
             AddCanisterChangeToHistory::No,
+            Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
         ),
         Vec::new()
     );
@@ -2943,6 +2944,7 @@ fn uninstall_canister_responds_to_unresponded_call_contexts() {
                 .build(),
             mock_time(),
             AddCanisterChangeToHistory::No,
+            Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
         )[0],
         Response::Ingress(IngressResponse {
             message_id: message_test_id(456),
@@ -4318,6 +4320,7 @@ fn install_code_context_conversion_u128() {
             None
         ),
         install_args,
+        MAX_MEMORY_ALLOCATION,
     ))
     .is_err());
 }
@@ -1,7 +1,7 @@
 use ic_base_types::{NumBytes, NumSeconds};
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, UserError};
-use ic_ic00_types::CanisterSettingsArgs;
+use ic_ic00_types::{CanisterSettingsArgs, LogVisibility};
 use ic_interfaces::execution_environment::SubnetAvailableMemory;
 use ic_types::{
     ComputeAllocation, Cycles, InvalidComputeAllocationError, InvalidMemoryAllocationError,
@@ -20,6 +20,10 @@ pub(crate) struct CanisterSettings {
     pub(crate) compute_allocation: Option<ComputeAllocation>,
     pub(crate) memory_allocation: Option<MemoryAllocation>,
     pub(crate) freezing_threshold: Option<NumSeconds>,
+    pub(crate) log_visibility: Option<LogVisibility>,
+    pub(crate) wasm_memory_limit: Option<NumBytes>,
+    pub(crate) wasm_memory_threshold: Option<NumBytes>,
+    pub(crate) reserved_cycles_limit: Option<Cycles>,
 }
 
 impl CanisterSettings {
@@ -29,6 +33,10 @@ impl CanisterSettings {
         compute_allocation: Option<ComputeAllocation>,
         memory_allocation: Option<MemoryAllocation>,
         freezing_threshold: Option<NumSeconds>,
+        log_visibility: Option<LogVisibility>,
+        wasm_memory_limit: Option<NumBytes>,
+        wasm_memory_threshold: Option<NumBytes>,
+        reserved_cycles_limit: Option<Cycles>,
     ) -> Self {
         Self {
             controller,
@@ -36,6 +44,10 @@ impl CanisterSettings {
             compute_allocation,
             memory_allocation,
             freezing_threshold,
+            log_visibility,
+            wasm_memory_limit,
+            wasm_memory_threshold,
+            reserved_cycles_limit,
         }
     }
 
@@ -58,12 +70,32 @@ impl CanisterSettings {
     pub fn freezing_threshold(&self) -> Option<NumSeconds> {
         self.freezing_threshold
     }
+
+    pub fn log_visibility(&self) -> Option<LogVisibility> {
+        self.log_visibility
+    }
+
+    pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
+        self.wasm_memory_limit
+    }
+
+    pub fn wasm_memory_threshold(&self) -> Option<NumBytes> {
+        self.wasm_memory_threshold
+    }
+
+    pub fn reserved_cycles_limit(&self) -> Option<Cycles> {
+        self.reserved_cycles_limit
+    }
 }
 
-impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
+/// Converts the given settings, where the memory allocation can be at most the
+/// given maximum memory allocation of the subnet.
+impl TryFrom<(CanisterSettingsArgs, NumBytes)> for CanisterSettings {
     type Error = UpdateSettingsError;
 
-    fn try_from(input: CanisterSettingsArgs) -> Result<Self, Self::Error> {
+    fn try_from(
+        (input, max_memory_allocation): (CanisterSettingsArgs, NumBytes),
+    ) -> Result<Self, Self::Error> {
         let controller = input.get_controller();
         let compute_allocation = match input.compute_allocation {
             Some(ca) => Some(ComputeAllocation::try_from(ca.0.to_u64().ok_or_else(
@@ -73,11 +105,15 @@ impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
         };
 
         let memory_allocation = match input.memory_allocation {
-            Some(ma) => Some(MemoryAllocation::try_from(NumBytes::from(
-                ma.0.to_u64().ok_or_else(|| {
-                    UpdateSettingsError::MemoryAllocation(InvalidMemoryAllocationError::new(ma))
-                })?,
-            ))?),
+            Some(ma) => Some(MemoryAllocation::try_from_bytes(
+                NumBytes::from(ma.0.to_u64().ok_or_else(|| {
+                    UpdateSettingsError::MemoryAllocation(InvalidMemoryAllocationError::new(
+                        ma,
+                        max_memory_allocation,
+                    ))
+                })?),
+                max_memory_allocation,
+            )?),
             None => None,
         };
 
@@ -88,22 +124,51 @@ impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
             None => None,
         };
 
+        let wasm_memory_limit = match input.wasm_memory_limit {
+            Some(limit) => Some(NumBytes::from(limit.0.to_u64().ok_or(
+                UpdateSettingsError::WasmMemoryLimitOutOfRange { provided: limit },
+            )?)),
+            None => None,
+        };
+
+        let wasm_memory_threshold = match input.wasm_memory_threshold {
+            Some(threshold) => Some(NumBytes::from(threshold.0.to_u64().ok_or(
+                UpdateSettingsError::WasmMemoryThresholdOutOfRange {
+                    provided: threshold,
+                },
+            )?)),
+            None => None,
+        };
+
+        let reserved_cycles_limit = match input.reserved_cycles_limit {
+            Some(limit) => Some(Cycles::from(limit.0.to_u128().ok_or(
+                UpdateSettingsError::ReservedCyclesLimitOutOfRange { provided: limit },
+            )?)),
+            None => None,
+        };
+
         Ok(CanisterSettings::new(
             controller,
             input.controllers,
             compute_allocation,
             memory_allocation,
             freezing_threshold,
+            input.log_visibility,
+            wasm_memory_limit,
+            wasm_memory_threshold,
+            reserved_cycles_limit,
         ))
     }
 }
 
-impl TryFrom<Option<CanisterSettingsArgs>> for CanisterSettings {
+impl TryFrom<(Option<CanisterSettingsArgs>, NumBytes)> for CanisterSettings {
     type Error = UpdateSettingsError;
 
-    fn try_from(input: Option<CanisterSettingsArgs>) -> Result<Self, Self::Error> {
+    fn try_from(
+        (input, max_memory_allocation): (Option<CanisterSettingsArgs>, NumBytes),
+    ) -> Result<Self, Self::Error> {
         match input {
-            Some(settings) => CanisterSettings::try_from(settings),
+            Some(settings) => CanisterSettings::try_from((settings, max_memory_allocation)),
             None => Ok(CanisterSettings::default()),
         }
     }
@@ -115,6 +180,10 @@ pub(crate) struct CanisterSettingsBuilder {
     compute_allocation: Option<ComputeAllocation>,
     memory_allocation: Option<MemoryAllocation>,
     freezing_threshold: Option<NumSeconds>,
+    log_visibility: Option<LogVisibility>,
+    wasm_memory_limit: Option<NumBytes>,
+    wasm_memory_threshold: Option<NumBytes>,
+    reserved_cycles_limit: Option<Cycles>,
 }
 
 #[allow(dead_code)]
@@ -126,6 +195,10 @@ impl CanisterSettingsBuilder {
             compute_allocation: None,
             memory_allocation: None,
             freezing_threshold: None,
+            log_visibility: None,
+            wasm_memory_limit: None,
+            wasm_memory_threshold: None,
+            reserved_cycles_limit: None,
         }
     }
 
@@ -136,6 +209,10 @@ impl CanisterSettingsBuilder {
             compute_allocation: self.compute_allocation,
             memory_allocation: self.memory_allocation,
             freezing_threshold: self.freezing_threshold,
+            log_visibility: self.log_visibility,
+            wasm_memory_limit: self.wasm_memory_limit,
+            wasm_memory_threshold: self.wasm_memory_threshold,
+            reserved_cycles_limit: self.reserved_cycles_limit,
         }
     }
 
@@ -173,12 +250,43 @@ impl CanisterSettingsBuilder {
             ..self
         }
     }
+
+    pub fn with_log_visibility(self, log_visibility: LogVisibility) -> Self {
+        Self {
+            log_visibility: Some(log_visibility),
+            ..self
+        }
+    }
+
+    pub fn with_wasm_memory_limit(self, wasm_memory_limit: NumBytes) -> Self {
+        Self {
+            wasm_memory_limit: Some(wasm_memory_limit),
+            ..self
+        }
+    }
+
+    pub fn with_wasm_memory_threshold(self, wasm_memory_threshold: NumBytes) -> Self {
+        Self {
+            wasm_memory_threshold: Some(wasm_memory_threshold),
+            ..self
+        }
+    }
+
+    pub fn with_reserved_cycles_limit(self, reserved_cycles_limit: Cycles) -> Self {
+        Self {
+            reserved_cycles_limit: Some(reserved_cycles_limit),
+            ..self
+        }
+    }
 }
 
 pub enum UpdateSettingsError {
     ComputeAllocation(InvalidComputeAllocationError),
     MemoryAllocation(InvalidMemoryAllocationError),
     FreezingThresholdOutOfRange { provided: candid::Nat },
+    WasmMemoryLimitOutOfRange { provided: candid::Nat },
+    WasmMemoryThresholdOutOfRange { provided: candid::Nat },
+    ReservedCyclesLimitOutOfRange { provided: candid::Nat },
 }
 
 impl From<UpdateSettingsError> for UserError {
@@ -207,6 +315,27 @@ impl From<UpdateSettingsError> for UserError {
                     provided
                 ),
             ),
+            UpdateSettingsError::WasmMemoryLimitOutOfRange { provided } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "Wasm memory limit expected to be in the range of [0..2^64-1], got {}",
+                    provided
+                ),
+            ),
+            UpdateSettingsError::WasmMemoryThresholdOutOfRange { provided } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "Wasm memory threshold expected to be in the range of [0..2^64-1], got {}",
+                    provided
+                ),
+            ),
+            UpdateSettingsError::ReservedCyclesLimitOutOfRange { provided } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "Reserved cycles limit expected to be in the range of [0..2^128-1], got {}",
+                    provided
+                ),
+            ),
         }
     }
 }
@@ -229,6 +358,10 @@ pub(crate) struct ValidatedCanisterSettings {
     compute_allocation: Option<ComputeAllocation>,
     memory_allocation: Option<MemoryAllocation>,
     freezing_threshold: Option<NumSeconds>,
+    log_visibility: Option<LogVisibility>,
+    wasm_memory_limit: Option<NumBytes>,
+    wasm_memory_threshold: Option<NumBytes>,
+    reserved_cycles_limit: Option<Cycles>,
 }
 
 impl ValidatedCanisterSettings {
@@ -251,6 +384,22 @@ impl ValidatedCanisterSettings {
     pub fn freezing_threshold(&self) -> Option<NumSeconds> {
         self.freezing_threshold
     }
+
+    pub fn log_visibility(&self) -> Option<LogVisibility> {
+        self.log_visibility
+    }
+
+    pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
+        self.wasm_memory_limit
+    }
+
+    pub fn wasm_memory_threshold(&self) -> Option<NumBytes> {
+        self.wasm_memory_threshold
+    }
+
+    pub fn reserved_cycles_limit(&self) -> Option<Cycles> {
+        self.reserved_cycles_limit
+    }
 }
 
 /// Validates the new canisters settings:
@@ -263,6 +412,8 @@ impl ValidatedCanisterSettings {
 ///     - there must be enough cycles to avoid freezing the canister.
 /// - controllers:
 ///     - the number of controllers cannot exceed the given maximum.
+/// - reserved cycles limit:
+///     - it cannot be lower than the current reserved balance.
 pub(crate) fn validate_canister_settings(
     settings: CanisterSettings,
     canister_memory_usage: NumBytes,
@@ -274,6 +425,7 @@ pub(crate) fn validate_canister_settings(
     max_controllers: usize,
     canister_freezing_threshold: NumSeconds,
     canister_cycles_balance: Cycles,
+    canister_reserved_balance: Cycles,
     cycles_account_manager: &CyclesAccountManager,
     subnet_size: usize,
 ) -> Result<ValidatedCanisterSettings, CanisterManagerError> {
@@ -347,6 +499,15 @@ pub(crate) fn validate_canister_settings(
         None => {}
     }
 
+    if let Some(reserved_cycles_limit) = settings.reserved_cycles_limit() {
+        if canister_reserved_balance > reserved_cycles_limit {
+            return Err(CanisterManagerError::ReservedCyclesLimitIsTooLow {
+                cycles: canister_reserved_balance,
+                limit: reserved_cycles_limit,
+            });
+        }
+    }
+
     let new_memory_allocation = settings
         .memory_allocation
         .unwrap_or(canister_memory_allocation);
@@ -398,5 +559,9 @@ pub(crate) fn validate_canister_settings(
         compute_allocation: settings.compute_allocation(),
         memory_allocation: settings.memory_allocation(),
         freezing_threshold: settings.freezing_threshold(),
+        log_visibility: settings.log_visibility(),
+        wasm_memory_limit: settings.wasm_memory_limit(),
+        wasm_memory_threshold: settings.wasm_memory_threshold(),
+        reserved_cycles_limit: settings.reserved_cycles_limit(),
     })
 }
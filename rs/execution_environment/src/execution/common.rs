@@ -22,7 +22,7 @@ use ic_system_api::sandbox_safe_system_state::SystemStateChanges;
 use ic_types::ingress::{IngressState, IngressStatus, WasmResult};
 use ic_types::messages::{CallContextId, CallbackId, MessageId, Payload, RejectContext, Response};
 use ic_types::methods::{Callback, WasmMethod};
-use ic_types::{Cycles, MemoryAllocation, NumInstructions, Time, UserId};
+use ic_types::{time::CoarseTime, Cycles, MemoryAllocation, NumInstructions, Time, UserId};
 
 use crate::execution_environment::ExecutionResponse;
 use crate::{as_round_instructions, ExecuteMessageResult, RoundLimits};
@@ -68,8 +68,8 @@ pub(crate) fn action_to_response(
             time,
             log,
         ),
-        CallOrigin::CanisterUpdate(caller_canister_id, callback_id) => {
-            action_to_request_response(canister, action, caller_canister_id, callback_id)
+        CallOrigin::CanisterUpdate(caller_canister_id, callback_id, deadline) => {
+            action_to_request_response(canister, action, caller_canister_id, callback_id, deadline)
         }
         CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => fatal!(
             log,
@@ -90,6 +90,7 @@ pub(crate) fn action_to_request_response(
     action: CallContextAction,
     originator: CanisterId,
     reply_callback_id: CallbackId,
+    deadline: CoarseTime,
 ) -> ExecutionResponse {
     let response_payload_and_refund = match action {
         CallContextAction::NotYetResponded | CallContextAction::AlreadyResponded => None,
@@ -130,6 +131,7 @@ pub(crate) fn action_to_request_response(
             originator_reply_callback: reply_callback_id,
             refund,
             response_payload,
+            deadline,
         })
     } else {
         ExecutionResponse::Empty
@@ -240,13 +242,14 @@ pub(crate) fn wasm_result_to_query_response(
         CallOrigin::Ingress(user_id, message_id) => {
             wasm_result_to_ingress_response(result, canister, user_id, message_id, time)
         }
-        CallOrigin::CanisterUpdate(caller_canister_id, callback_id) => {
+        CallOrigin::CanisterUpdate(caller_canister_id, callback_id, deadline) => {
             let response = Response {
                 originator: caller_canister_id,
                 respondent: canister.canister_id(),
                 originator_reply_callback: callback_id,
                 refund,
                 response_payload: Payload::from(result),
+                deadline,
             };
             ExecutionResponse::Request(response)
         }
@@ -502,6 +505,8 @@ pub fn apply_canister_state_changes(
             }
         }
     }
+    // Log records are kept even if the execution failed.
+    system_state.canister_log.append(&mut output.canister_log);
 }
 
 pub(crate) fn finish_call_with_error(
@@ -521,6 +526,7 @@ pub(crate) fn finish_call_with_error(
                 originator_reply_callback: request.sender_reply_callback,
                 refund: request.payment,
                 response_payload: Payload::from(Err(user_error)),
+                deadline: request.deadline,
             };
             ExecutionResponse::Request(response)
         }
@@ -572,14 +578,14 @@ mod test {
     use ic_logger::LoggerImpl;
     use ic_logger::ReplicaLogger;
     use ic_replicated_state::{CanisterState, SchedulerState, SystemState};
-    use ic_types::messages::CallbackId;
+    use ic_types::messages::{CallbackId, NO_DEADLINE};
     use ic_types::Cycles;
     use ic_types::Time;
 
     #[test]
     fn test_wasm_result_to_query_response_refunds_correclty() {
         let scheduler_state = SchedulerState::default();
-        let system_state = SystemState::new_running(
+        let system_state = SystemState::new_running_for_testing(
             CanisterId::from_u64(42),
             CanisterId::from(100u64).into(),
             Cycles::new(1 << 36),
@@ -599,6 +605,7 @@ mod test {
             ic_replicated_state::CallOrigin::CanisterUpdate(
                 CanisterId::from(123u64),
                 CallbackId::new(2),
+                NO_DEADLINE,
             ),
             &log,
             Cycles::from(1000u128),
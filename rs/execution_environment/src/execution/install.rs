@@ -129,7 +129,7 @@ pub(crate) fn execute_install(
         let wasm_execution_result = round.hypervisor.execute_dts(
             ApiType::start(original.time),
             execution_state,
-            &SystemState::new_for_start(canister_id),
+            &SystemState::new_for_start(canister_id, round.hypervisor.fd_factory()),
             helper.canister_memory_usage(),
             helper.execution_parameters().clone(),
             FuncRef::Method(method),
@@ -185,7 +185,7 @@ fn install_stage_2a_process_start_result(
     output: WasmExecutionOutput,
     context_sender: PrincipalId,
     context_arg: Vec<u8>,
-    clean_canister: CanisterState,
+    mut clean_canister: CanisterState,
     mut helper: InstallCodeHelper,
     original: OriginalContext,
     round: RoundContext,
@@ -203,6 +203,7 @@ fn install_stage_2a_process_start_result(
 
     if let Err(err) = result {
         let instructions_left = helper.instructions_left();
+        helper.keep_canister_log(&mut clean_canister);
         return finish_err(clean_canister, instructions_left, original, round, err);
     }
 
@@ -287,7 +288,7 @@ fn install_stage_2b_continue_install_after_start(
 #[allow(clippy::too_many_arguments)]
 fn install_stage_3_process_init_result(
     canister_state_changes: Option<CanisterStateChanges>,
-    clean_canister: CanisterState,
+    mut clean_canister: CanisterState,
     mut helper: InstallCodeHelper,
     output: WasmExecutionOutput,
     original: OriginalContext,
@@ -304,6 +305,7 @@ fn install_stage_3_process_init_result(
     );
     if let Err(err) = result {
         let instructions_left = helper.instructions_left();
+        helper.keep_canister_log(&mut clean_canister);
         return finish_err(clean_canister, instructions_left, original, round, err);
     }
     helper.finish(clean_canister, original, round, round_limits)
@@ -5,8 +5,12 @@ use std::path::{Path, PathBuf};
 
 use ic_base_types::{CanisterId, NumBytes, PrincipalId};
 use ic_config::flag_status::FlagStatus;
+use ic_cycles_account_manager::ResourceSaturation;
 use ic_embedders::wasm_executor::CanisterStateChanges;
-use ic_ic00_types::{CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode};
+use ic_ic00_types::{
+    CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode, InstallChunkedCodeArgs,
+    InstallCodeArgs,
+};
 use ic_interfaces::{
     execution_environment::{
         HypervisorError, HypervisorResult, SubnetAvailableMemoryError, WasmExecutionOutput,
@@ -14,13 +18,16 @@ use ic_interfaces::{
     messages::CanisterCall,
 };
 use ic_logger::{error, fatal, info, warn};
-use ic_replicated_state::{CanisterState, ExecutionState};
+use ic_replicated_state::{
+    canister_state::system_state::{ReservationError, WasmChunkStore},
+    CanisterState, ExecutionState, ReplicatedState,
+};
 use ic_state_layout::{CanisterLayout, CheckpointLayout, ReadOnly};
 use ic_sys::PAGE_SIZE;
 use ic_system_api::ExecutionParameters;
 use ic_types::{
-    funds::Cycles, CanisterTimer, ComputeAllocation, Height, MemoryAllocation, NumInstructions,
-    Time,
+    canister_log::CanisterLog, funds::Cycles, CanisterTimer, ComputeAllocation, Height,
+    MemoryAllocation, NumInstructions, Time,
 };
 use ic_wasm_types::WasmHash;
 
@@ -88,6 +95,9 @@ pub(crate) struct InstallCodeHelper {
     deallocated_wasm_custom_sections_bytes: NumBytes,
     // The total heap delta of all steps.
     total_heap_delta: NumBytes,
+    // The log records produced by the steps. They are kept even if the
+    // installation fails and the canister state is rolled back.
+    canister_log: CanisterLog,
 }
 
 impl InstallCodeHelper {
@@ -103,6 +113,7 @@ impl InstallCodeHelper {
             deallocated_bytes: NumBytes::from(0),
             deallocated_wasm_custom_sections_bytes: NumBytes::from(0),
             total_heap_delta: NumBytes::from(0),
+            canister_log: CanisterLog::default(),
         }
     }
 
@@ -110,6 +121,15 @@ impl InstallCodeHelper {
         &self.canister
     }
 
+    /// Moves the log records produced by the steps to the given clean canister
+    /// state, so that they survive a rollback of the installation.
+    pub fn keep_canister_log(&mut self, clean_canister: &mut CanisterState) {
+        clean_canister
+            .system_state
+            .canister_log
+            .append(&mut self.canister_log);
+    }
+
     pub fn clear_certified_data(&mut self) {
         self.canister.system_state.certified_data = Vec::new();
     }
@@ -188,11 +208,15 @@ impl InstallCodeHelper {
     /// execution to fail with errors.
     pub fn finish(
         mut self,
-        clean_canister: CanisterState,
+        mut clean_canister: CanisterState,
         original: OriginalContext,
         round: RoundContext,
         round_limits: &mut RoundLimits,
     ) -> DtsInstallCodeResult {
+        // The clean canister state is returned if any of the checks below
+        // fails, so it must keep the log records of the executed steps.
+        self.keep_canister_log(&mut clean_canister);
+
         let message_instruction_limit = original.execution_parameters.instruction_limits.message();
         let instructions_left = self.instructions_left();
 
@@ -241,6 +265,53 @@ impl InstallCodeHelper {
                     err,
                 );
             }
+
+            // Reserve cycles for the newly allocated memory if the subnet
+            // memory usage is high.
+            let bytes = self.allocated_bytes - self.deallocated_bytes;
+            let subnet_memory_saturation = ResourceSaturation::new_from_available(
+                round_limits
+                    .subnet_available_memory
+                    .get_execution_memory()
+                    .max(0) as u64,
+                original.execution_parameters.subnet_memory_threshold.get(),
+                original.execution_parameters.subnet_memory_capacity.get(),
+            );
+            let reservation_cycles = round.cycles_account_manager.storage_reservation_cycles(
+                bytes,
+                &subnet_memory_saturation,
+                original.subnet_size,
+            );
+            if let Err(err) = self
+                .canister
+                .system_state
+                .reserve_cycles(reservation_cycles)
+            {
+                let err = match err {
+                    ReservationError::InsufficientCycles {
+                        requested,
+                        available,
+                    } => CanisterManagerError::InsufficientCyclesInMemoryGrow {
+                        bytes,
+                        available,
+                        threshold: requested,
+                    },
+                    ReservationError::ReservedLimitExceed { requested, limit } => {
+                        CanisterManagerError::ReservedCyclesLimitExceededInMemoryGrow {
+                            bytes,
+                            requested,
+                            limit,
+                        }
+                    }
+                };
+                return finish_err(
+                    clean_canister,
+                    self.instructions_left(),
+                    original,
+                    round,
+                    err,
+                );
+            }
         }
 
         let mut subnet_available_memory = round_limits.subnet_available_memory;
@@ -363,7 +434,7 @@ impl InstallCodeHelper {
                 controllers: None,
                 compute_allocation: original.requested_compute_allocation,
                 memory_allocation: original.requested_memory_allocation,
-                freezing_threshold: None,
+                ..CanisterSettings::default()
             },
             self.canister.memory_usage(),
             self.canister.memory_allocation(),
@@ -374,6 +445,7 @@ impl InstallCodeHelper {
             original.config.max_controllers,
             self.canister.system_state.freeze_threshold,
             self.canister.system_state.balance(),
+            self.canister.system_state.reserved_balance(),
             round.cycles_account_manager,
             original.subnet_size,
         )?;
@@ -519,7 +591,7 @@ impl InstallCodeHelper {
     pub fn handle_wasm_execution(
         &mut self,
         canister_state_changes: Option<CanisterStateChanges>,
-        output: WasmExecutionOutput,
+        mut output: WasmExecutionOutput,
         original: &OriginalContext,
         round: &RoundContext,
     ) -> Result<(), CanisterManagerError> {
@@ -528,6 +600,12 @@ impl InstallCodeHelper {
             output: output.clone(),
         });
 
+        self.canister_log.append(&mut output.canister_log.clone());
+        self.canister
+            .system_state
+            .canister_log
+            .append(&mut output.canister_log);
+
         self.execution_parameters
             .instruction_limits
             .update(output.num_instructions_left);
@@ -671,6 +749,70 @@ pub(crate) fn validate_controller(
     Ok(())
 }
 
+/// Converts the arguments of `install_chunked_code` into the arguments of a
+/// regular `install_code` call by putting the Wasm module together from the
+/// chunks in the store canister.
+///
+/// The store canister must be on the same subnet as the target canister and
+/// the sender must be one of its controllers (or the store canister itself).
+/// The assembled module must match the given `wasm_module_hash`.
+pub(crate) fn install_code_args_from_chunks(
+    sender: PrincipalId,
+    args: InstallChunkedCodeArgs,
+    state: &ReplicatedState,
+) -> Result<InstallCodeArgs, CanisterManagerError> {
+    let store_canister_id = args.store_canister_id();
+    let store_canister = state.canister_state(&store_canister_id).ok_or_else(|| {
+        CanisterManagerError::WasmChunkStoreError {
+            canister_id: store_canister_id,
+            message: "the store canister must be on the same subnet as the target canister"
+                .to_string(),
+        }
+    })?;
+    if sender != store_canister_id.get() {
+        validate_controller(store_canister, &sender)?;
+    }
+
+    let store = &store_canister.system_state.wasm_chunk_store;
+    let mut wasm_module = Vec::new();
+    for chunk_hash in args.chunk_hashes_list.iter() {
+        let chunk = <[u8; 32]>::try_from(chunk_hash.hash.as_slice())
+            .ok()
+            .and_then(|hash| store.get_chunk(&hash))
+            .ok_or_else(|| CanisterManagerError::WasmChunkStoreError {
+                canister_id: store_canister_id,
+                message: format!(
+                    "chunk {} is not in the store",
+                    hex::encode(&chunk_hash.hash)
+                ),
+            })?;
+        wasm_module.extend_from_slice(&chunk);
+    }
+
+    let module_hash = WasmChunkStore::chunk_hash(&wasm_module);
+    if module_hash.as_slice() != args.wasm_module_hash.as_slice() {
+        return Err(CanisterManagerError::WasmChunkStoreError {
+            canister_id: store_canister_id,
+            message: format!(
+                "the hash of the assembled module {} does not match the expected hash {}",
+                hex::encode(module_hash),
+                hex::encode(&args.wasm_module_hash)
+            ),
+        });
+    }
+
+    Ok(InstallCodeArgs {
+        mode: args.mode,
+        canister_id: args.target_canister,
+        wasm_module,
+        arg: args.arg,
+        compute_allocation: None,
+        memory_allocation: None,
+        query_allocation: None,
+        sender_canister_version: args.sender_canister_version,
+    })
+}
+
 pub(crate) fn get_wasm_hash(canister: &CanisterState) -> Option<[u8; 32]> {
     canister
         .execution_state
@@ -13,7 +13,10 @@ use ic_test_utilities_execution_environment::{
 };
 use ic_test_utilities_metrics::fetch_int_counter;
 use ic_types::messages::MessageId;
-use ic_types::{ingress::WasmResult, MAX_STABLE_MEMORY_IN_BYTES, MAX_WASM_MEMORY_IN_BYTES};
+use ic_types::{
+    ingress::WasmResult, MAX_STABLE_MEMORY_IN_BYTES, MAX_WASM64_MEMORY_IN_BYTES,
+    MAX_WASM_MEMORY_IN_BYTES,
+};
 use ic_types_test_utils::ids::user_test_id;
 use std::mem::size_of;
 
@@ -84,6 +87,36 @@ fn install_code_fails_on_invalid_memory_allocation() {
     );
 }
 
+#[test]
+fn install_code_accepts_wasm64_memory_allocation_only_if_wasm64_is_enabled() {
+    let memory_allocation = MAX_STABLE_MEMORY_IN_BYTES + MAX_WASM64_MEMORY_IN_BYTES;
+    let binary = wat::parse_str("(module)").unwrap();
+
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister = test.create_canister(Cycles::new(1_000_000_000_000_000_000));
+    let err = test
+        .install_canister_with_allocation(canister, binary.clone(), None, Some(memory_allocation))
+        .unwrap_err();
+    assert_eq!(ErrorCode::CanisterContractViolation, err.code());
+    assert_eq!(
+        format!(
+            "MemoryAllocation expected to be in the range [0..{}], got {}",
+            candid::Nat((MAX_STABLE_MEMORY_IN_BYTES + MAX_WASM_MEMORY_IN_BYTES).into()),
+            candid::Nat(memory_allocation.into())
+        ),
+        err.description()
+    );
+
+    let mut test = ExecutionTestBuilder::new().with_wasm64().build();
+    let canister = test.create_canister(Cycles::new(1_000_000_000_000_000_000));
+    test.install_canister_with_allocation(canister, binary, None, Some(memory_allocation))
+        .unwrap();
+    assert_eq!(
+        test.canister_state(canister).system_state.memory_allocation,
+        MemoryAllocation::Reserved(NumBytes::from(memory_allocation))
+    );
+}
+
 #[test]
 fn dts_resume_works_in_install_code() {
     const INSTRUCTION_LIMIT: u64 = 3_000_000;
@@ -112,6 +112,7 @@ pub fn execute_update(
             msg.cycles(),
             *msg.sender(),
             helper.call_context_id(),
+            msg.deadline(),
         ),
         CanisterCallOrTask::Task(CanisterTask::Heartbeat) => ApiType::system_task(
             SystemMethod::CanisterHeartbeat,
@@ -123,6 +124,11 @@ pub fn execute_update(
             time,
             helper.call_context_id(),
         ),
+        CanisterCallOrTask::Task(CanisterTask::OnLowWasmMemory) => ApiType::system_task(
+            SystemMethod::CanisterOnLowWasmMemory,
+            time,
+            helper.call_context_id(),
+        ),
     };
 
     let memory_usage = helper.canister().memory_usage();
@@ -280,6 +286,10 @@ impl UpdateHelper {
                 // The global timer is one-off.
                 canister.system_state.global_timer = CanisterTimer::Inactive;
             }
+            CanisterCallOrTask::Task(CanisterTask::OnLowWasmMemory) => {
+                // The hook runs once until its condition stops holding.
+                canister.system_state.on_low_wasm_memory_hook_executed();
+            }
         }
 
         Ok(Self {
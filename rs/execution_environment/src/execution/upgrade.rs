@@ -186,7 +186,7 @@ fn upgrade_stage_1_process_pre_upgrade_result(
     canister_state_changes: Option<CanisterStateChanges>,
     output: WasmExecutionOutput,
     context: InstallCodeContext,
-    clean_canister: CanisterState,
+    mut clean_canister: CanisterState,
     mut helper: InstallCodeHelper,
     original: OriginalContext,
     round: RoundContext,
@@ -204,6 +204,7 @@ fn upgrade_stage_1_process_pre_upgrade_result(
 
     if let Err(err) = result {
         let instructions_left = helper.instructions_left();
+        helper.keep_canister_log(&mut clean_canister);
         return finish_err(clean_canister, instructions_left, original, round, err);
     }
 
@@ -276,7 +277,7 @@ fn upgrade_stage_2_and_3a_create_execution_state_and_call_start(
         let wasm_execution_result = round.hypervisor.execute_dts(
             ApiType::start(original.time),
             execution_state,
-            &SystemState::new_for_start(canister_id),
+            &SystemState::new_for_start(canister_id, round.hypervisor.fd_factory()),
             helper.canister_memory_usage(),
             helper.execution_parameters().clone(),
             FuncRef::Method(method),
@@ -332,7 +333,7 @@ fn upgrade_stage_3b_process_start_result(
     output: WasmExecutionOutput,
     context_sender: PrincipalId,
     context_arg: Vec<u8>,
-    clean_canister: CanisterState,
+    mut clean_canister: CanisterState,
     mut helper: InstallCodeHelper,
     original: OriginalContext,
     round: RoundContext,
@@ -350,6 +351,7 @@ fn upgrade_stage_3b_process_start_result(
 
     if let Err(err) = result {
         let instructions_left = helper.instructions_left();
+        helper.keep_canister_log(&mut clean_canister);
         return finish_err(clean_canister, instructions_left, original, round, err);
     }
 
@@ -438,7 +440,7 @@ fn upgrade_stage_4a_call_post_upgrade(
 fn upgrade_stage_4b_process_post_upgrade_result(
     canister_state_changes: Option<CanisterStateChanges>,
     output: WasmExecutionOutput,
-    clean_canister: CanisterState,
+    mut clean_canister: CanisterState,
     mut helper: InstallCodeHelper,
     original: OriginalContext,
     round: RoundContext,
@@ -454,6 +456,7 @@ fn upgrade_stage_4b_process_post_upgrade_result(
     );
     if let Err(err) = result {
         let instructions_left = helper.instructions_left();
+        helper.keep_canister_log(&mut clean_canister);
         return finish_err(clean_canister, instructions_left, original, round, err);
     }
     helper.finish(clean_canister, original, round, round_limits)
@@ -5,7 +5,8 @@ use crate::{
     },
     canister_settings::CanisterSettings,
     execution::{
-        inspect_message, nonreplicated_query::execute_non_replicated_query,
+        inspect_message, install_code::install_code_args_from_chunks,
+        nonreplicated_query::execute_non_replicated_query,
         replicated_query::execute_replicated_query, response::execute_response,
         update::execute_update,
     },
@@ -26,11 +27,13 @@ use ic_cycles_account_manager::{CyclesAccountManager, IngressInductionCost};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
     CanisterChangeOrigin, CanisterHttpRequestArgs, CanisterIdRecord, CanisterInfoRequest,
-    CanisterInfoResponse, CanisterSettingsArgs, ComputeInitialEcdsaDealingsArgs,
-    CreateCanisterArgs, ECDSAPublicKeyArgs, ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob,
-    InstallCodeArgs, Method as Ic00Method, Payload as Ic00Payload,
-    ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs, SetControllerArgs,
-    SetupInitialDKGArgs, SignWithECDSAArgs, UninstallCodeArgs, UpdateSettingsArgs, IC_00,
+    CanisterInfoResponse, CanisterSettingsArgs, ClearChunkStoreArgs,
+    ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, DeleteCanisterSnapshotArgs,
+    ECDSAPublicKeyArgs, ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, InstallChunkedCodeArgs,
+    InstallCodeArgs, ListCanisterSnapshotArgs, LoadCanisterSnapshotArgs, Method as Ic00Method,
+    Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
+    SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs, StoredChunksArgs,
+    TakeCanisterSnapshotArgs, UninstallCodeArgs, UpdateSettingsArgs, UploadChunkArgs, IC_00,
 };
 use ic_interfaces::{
     execution_environment::{
@@ -46,6 +49,7 @@ use ic_metrics::{MetricsRegistry, Timer};
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::canister_state::{system_state::CyclesUseCase, NextExecution};
+use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 use ic_replicated_state::ExecutionTask;
 use ic_replicated_state::{
     canister_state::system_state::PausedExecutionId,
@@ -65,12 +69,12 @@ use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{
         extract_effective_canister_id, AnonymousQuery, Payload, RejectContext, Request, Response,
-        SignedIngressContent, StopCanisterContext,
+        SignedIngressContent, StopCanisterContext, NO_DEADLINE,
     },
     methods::SystemMethod,
     nominal_cycles::NominalCycles,
     CanisterId, CpuComplexity, Cycles, LongExecutionMode, NumBytes, NumInstructions, SubnetId,
-    Time,
+    Time, MAX_MEMORY_ALLOCATION, MAX_WASM64_MEMORY_ALLOCATION,
 };
 use ic_types::{messages::MessageId, methods::WasmMethod};
 use ic_wasm_types::WasmHash;
@@ -328,6 +332,11 @@ impl ExecutionEnvironment {
         }
     }
 
+    /// Returns the factory for the files backing the pages of new page maps.
+    pub(crate) fn fd_factory(&self) -> Arc<dyn PageAllocatorFileDescriptor> {
+        self.hypervisor.fd_factory()
+    }
+
     /// Look up the current amount of memory available on the subnet.
     pub fn subnet_available_memory(&self, state: &ReplicatedState) -> SubnetAvailableMemory {
         let memory_taken = state.memory_taken();
@@ -395,6 +404,7 @@ impl ExecutionEnvironment {
                                 originator_reply_callback: request.sender_reply_callback,
                                 refund: request.payment,
                                 response_payload: response.response_payload.clone(),
+                                deadline: request.deadline,
                             }
                             .into(),
                         );
@@ -422,7 +432,7 @@ impl ExecutionEnvironment {
         }
 
         let result = match method {
-            Ok(Ic00Method::InstallCode) => {
+            Ok(Ic00Method::InstallCode) | Ok(Ic00Method::InstallChunkedCode) => {
                 // Tail call is needed for deterministic time slicing here to
                 // properly handle the case of a paused execution.
                 return self.execute_install_code(
@@ -458,6 +468,7 @@ impl ExecutionEnvironment {
                                         message: reject_message,
                                     },
                                 ),
+                                deadline: request.deadline,
                             }
                             .into(),
                         );
@@ -516,7 +527,10 @@ impl ExecutionEnvironment {
                                     None => CanisterSettingsArgs::default(),
                                     Some(settings) => settings,
                                 };
-                                let result = match CanisterSettings::try_from(settings) {
+                                let result = match CanisterSettings::try_from((
+                                    settings,
+                                    self.max_memory_allocation(),
+                                )) {
                                     Err(err) => Some((Err(err.into()), cycles)),
                                     Ok(settings) => Some(self.create_canister(
                                         msg.canister_change_origin(sender_canister_version),
@@ -567,7 +581,10 @@ impl ExecutionEnvironment {
 
                         let canister_id = args.get_canister_id();
                         let sender_canister_version = args.get_sender_canister_version();
-                        let result = match CanisterSettings::try_from(args.settings) {
+                        let result = match CanisterSettings::try_from((
+                            args.settings,
+                            self.max_memory_allocation(),
+                        )) {
                             Err(err) => Err(err.into()),
                             Ok(settings) => self.update_settings(
                                 timestamp_nanos,
@@ -874,13 +891,94 @@ impl ExecutionEnvironment {
                 }
             }
 
+            Ok(Ic00Method::TakeCanisterSnapshot) => {
+                let res = match TakeCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => match args.replace_snapshot() {
+                        Err(err) => Err(err),
+                        Ok(replace_snapshot) => self
+                            .canister_manager
+                            .take_canister_snapshot(
+                                *msg.sender(),
+                                args.get_canister_id(),
+                                replace_snapshot,
+                                &mut state,
+                                round_limits,
+                                registry_settings.subnet_size,
+                            )
+                            .map(|response| response.encode())
+                            .map_err(|err| err.into()),
+                    },
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::LoadCanisterSnapshot) => {
+                let res = match LoadCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => match args.snapshot_id() {
+                        Err(err) => Err(err),
+                        Ok(snapshot_id) => self
+                            .canister_manager
+                            .load_canister_snapshot(
+                                msg.canister_change_origin(args.get_sender_canister_version()),
+                                args.get_canister_id(),
+                                snapshot_id,
+                                &mut state,
+                                round_limits,
+                                CompilationCostHandling::CountFullAmount,
+                                registry_settings.subnet_size,
+                            )
+                            .map(|()| EmptyBlob.encode())
+                            .map_err(|err| err.into()),
+                    },
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::ListCanisterSnapshots) => {
+                let res = match ListCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => self
+                        .canister_manager
+                        .list_canister_snapshots(*msg.sender(), args.get_canister_id(), &state)
+                        .map(|response| response.encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::DeleteCanisterSnapshot) => {
+                let res = match DeleteCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => match args.snapshot_id() {
+                        Err(err) => Err(err),
+                        Ok(snapshot_id) => self
+                            .canister_manager
+                            .delete_canister_snapshot(
+                                *msg.sender(),
+                                args.get_canister_id(),
+                                snapshot_id,
+                                &mut state,
+                                round_limits,
+                            )
+                            .map(|()| EmptyBlob.encode())
+                            .map_err(|err| err.into()),
+                    },
+                };
+                Some((res, msg.take_cycles()))
+            }
+
             Ok(Ic00Method::ProvisionalCreateCanisterWithCycles) => {
                 let res = match ProvisionalCreateCanisterWithCyclesArgs::decode(payload) {
                     Err(err) => Err(err),
                     Ok(args) => {
                         let cycles_amount = args.to_u128();
                         let sender_canister_version = args.get_sender_canister_version();
-                        match CanisterSettings::try_from(args.settings) {
+                        match CanisterSettings::try_from((
+                            args.settings,
+                            self.max_memory_allocation(),
+                        )) {
                             Ok(settings) => self
                                 .canister_manager
                                 .create_canister_with_cycles(
@@ -953,6 +1051,46 @@ impl ExecutionEnvironment {
             }
             .map(|payload| (payload, msg.take_cycles())),
 
+            Ok(Ic00Method::UploadChunk) => {
+                let res = match UploadChunkArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => self.upload_chunk(*msg.sender(), args, &mut state, round_limits),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::ClearChunkStore) => {
+                let res = match ClearChunkStoreArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => self.clear_chunk_store(
+                        *msg.sender(),
+                        args.get_canister_id(),
+                        &mut state,
+                        round_limits,
+                    ),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::StoredChunks) => {
+                let res = match StoredChunksArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => self.stored_chunks(*msg.sender(), args.get_canister_id(), &state),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::FetchCanisterLogs) => Some((
+                Err(UserError::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "{} API is only accessible in non-replicated mode",
+                        Ic00Method::FetchCanisterLogs
+                    ),
+                )),
+                msg.take_cycles(),
+            )),
+
             Ok(Ic00Method::BitcoinGetBalance)
             | Ok(Ic00Method::BitcoinGetUtxos)
             | Ok(Ic00Method::BitcoinSendTransaction)
@@ -1195,6 +1333,15 @@ impl ExecutionEnvironment {
         self.config.max_canister_memory_size
     }
 
+    /// Returns the maximum memory allocation a canister can reserve, which
+    /// depends on whether canisters may use a 64-bit Wasm memory.
+    fn max_memory_allocation(&self) -> NumBytes {
+        match self.config.wasm64 {
+            FlagStatus::Enabled => MAX_WASM64_MEMORY_ALLOCATION,
+            FlagStatus::Disabled => MAX_MEMORY_ALLOCATION,
+        }
+    }
+
     /// Returns the subnet memory capacity.
     pub fn subnet_memory_capacity(&self) -> NumBytes {
         self.config.subnet_memory_capacity
@@ -1331,6 +1478,52 @@ impl ExecutionEnvironment {
         }
     }
 
+    fn upload_chunk(
+        &self,
+        sender: PrincipalId,
+        args: UploadChunkArgs,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<Vec<u8>, UserError> {
+        let canister = get_canister_mut(args.get_canister_id(), state)?;
+        self.canister_manager
+            .upload_chunk(sender, canister, args.chunk, round_limits)
+            .map(|reply| reply.encode())
+            .map_err(|err| err.into())
+    }
+
+    fn clear_chunk_store(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<Vec<u8>, UserError> {
+        let canister = get_canister_mut(canister_id, state)?;
+        self.canister_manager
+            .clear_chunk_store(sender, canister, round_limits)
+            .map(|()| EmptyBlob.encode())
+            .map_err(|err| err.into())
+    }
+
+    fn stored_chunks(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &ReplicatedState,
+    ) -> Result<Vec<u8>, UserError> {
+        let canister = state.canister_state(&canister_id).ok_or_else(|| {
+            UserError::new(
+                ErrorCode::CanisterNotFound,
+                format!("Canister {} not found.", canister_id),
+            )
+        })?;
+        self.canister_manager
+            .stored_chunks(sender, canister)
+            .map(|reply| reply.encode())
+            .map_err(|err| err.into())
+    }
+
     fn get_canister_status(
         &self,
         sender: PrincipalId,
@@ -1621,6 +1814,7 @@ impl ExecutionEnvironment {
                     originator_reply_callback: req.sender_reply_callback,
                     refund,
                     response_payload: payload,
+                    deadline: req.deadline,
                 };
 
                 state.push_subnet_output_response(response.into());
@@ -1702,6 +1896,7 @@ impl ExecutionEnvironment {
                             code: RejectCode::CanisterReject,
                             message: format!("Canister {}'s stop request cancelled", canister_id),
                         }),
+                        deadline: NO_DEADLINE,
                     };
                     state.push_subnet_output_response(response.into());
                 }
@@ -1888,12 +2083,20 @@ impl ExecutionEnvironment {
         fn decode_input_and_take_canister(
             msg: &CanisterCall,
             state: &mut ReplicatedState,
+            max_memory_allocation: NumBytes,
         ) -> Result<(InstallCodeContext, CanisterState), UserError> {
             let payload = msg.method_payload();
-            let args = InstallCodeArgs::decode(payload)?;
+            let args = match Ic00Method::from_str(msg.method_name()) {
+                Ok(Ic00Method::InstallChunkedCode) => {
+                    let args = InstallChunkedCodeArgs::decode(payload)?;
+                    install_code_args_from_chunks(*msg.sender(), args, state)?
+                }
+                _ => InstallCodeArgs::decode(payload)?,
+            };
             let install_context = InstallCodeContext::try_from((
                 msg.canister_change_origin(args.get_sender_canister_version()),
                 args,
+                max_memory_allocation,
             ))?;
             let canister = state
                 .take_canister_state(&install_context.canister_id)
@@ -1906,16 +2109,16 @@ impl ExecutionEnvironment {
         // Start logging execution time for `install_code`.
         let timer = Timer::start();
 
-        let (install_context, old_canister) = match decode_input_and_take_canister(&msg, &mut state)
-        {
-            Ok(result) => result,
-            Err(err) => {
-                let refund = msg.take_cycles();
-                let state =
-                    self.finish_subnet_message_execution(state, msg, Err(err), refund, timer);
-                return (state, Some(NumInstructions::from(0)));
-            }
-        };
+        let (install_context, old_canister) =
+            match decode_input_and_take_canister(&msg, &mut state, self.max_memory_allocation()) {
+                Ok(result) => result,
+                Err(err) => {
+                    let refund = msg.take_cycles();
+                    let state =
+                        self.finish_subnet_message_execution(state, msg, Err(err), refund, timer);
+                    return (state, Some(NumInstructions::from(0)));
+                }
+            };
 
         // Check the precondition.
         match old_canister.next_execution() {
@@ -2082,6 +2285,7 @@ impl ExecutionEnvironment {
         match task {
             ExecutionTask::Heartbeat
             | ExecutionTask::GlobalTimer
+            | ExecutionTask::OnLowWasmMemory
             | ExecutionTask::PausedExecution(_)
             | ExecutionTask::AbortedExecution { .. } => {
                 panic!(
@@ -2168,7 +2372,8 @@ impl ExecutionEnvironment {
                     ExecutionTask::AbortedExecution { .. }
                     | ExecutionTask::AbortedInstallCode { .. }
                     | ExecutionTask::Heartbeat
-                    | ExecutionTask::GlobalTimer => task,
+                    | ExecutionTask::GlobalTimer
+                    | ExecutionTask::OnLowWasmMemory => task,
                     ExecutionTask::PausedExecution(id) => {
                         let paused = self.take_paused_execution(id).unwrap();
                         let (input, prepaid_execution_cycles) = paused.abort(log);
@@ -2475,6 +2680,10 @@ pub fn execute_canister(
                 let task = CanisterMessageOrTask::Task(CanisterTask::GlobalTimer);
                 (task, None)
             }
+            ExecutionTask::OnLowWasmMemory => {
+                let task = CanisterMessageOrTask::Task(CanisterTask::OnLowWasmMemory);
+                (task, None)
+            }
             ExecutionTask::AbortedExecution {
                 input,
                 prepaid_execution_cycles,
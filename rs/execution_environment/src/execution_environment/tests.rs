@@ -30,6 +30,7 @@ use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{
         CallbackId, Payload, RejectContext, RequestOrResponse, Response, MAX_RESPONSE_COUNT_BYTES,
+        NO_DEADLINE,
     },
     CanisterId, Cycles, PrincipalId, RegistryVersion,
 };
@@ -37,6 +38,10 @@ use ic_types_test_utils::ids::{canister_test_id, node_test_id, subnet_test_id, u
 use ic_universal_canister::{call_args, wasm};
 use std::mem::size_of;
 
+#[cfg(test)]
+mod canister_logging;
+#[cfg(test)]
+mod canister_snapshots;
 #[cfg(test)]
 mod canister_task;
 
@@ -44,6 +49,12 @@ mod canister_task;
 mod compilation;
 #[cfg(test)]
 mod orthogonal_persistence;
+#[cfg(test)]
+mod reserved_cycles;
+#[cfg(test)]
+mod wasm_chunk_store;
+#[cfg(test)]
+mod wasm_memory_limit;
 
 const BALANCE_EPSILON: Cycles = Cycles::new(10_000_000);
 const ONE_GIB: i64 = 1 << 30;
@@ -1078,7 +1089,8 @@ fn setup_initial_dkg_sender_not_on_nns() {
                     ic00::Method::SetupInitialDKG,
                     other_canister,
                 )
-            })
+            }),
+            deadline: NO_DEADLINE,
         }
         .into()
     );
@@ -0,0 +1,180 @@
+use candid::Decode;
+use ic_error_types::ErrorCode;
+use ic_ic00_types::{
+    CanisterSettingsArgsBuilder, FetchCanisterLogsRequest, FetchCanisterLogsResponse,
+    LogVisibility, Method, Payload as Ic00Payload, UpdateSettingsArgs, IC_00,
+};
+use ic_test_utilities_execution_environment::{ExecutionTest, ExecutionTestBuilder};
+use ic_types::{ingress::WasmResult, messages::UserQuery, CanisterId, Cycles, UserId};
+use ic_types_test_utils::ids::user_test_id;
+use ic_universal_canister::wasm;
+use std::sync::Arc;
+
+fn fetch_canister_logs(
+    test: &ExecutionTest,
+    sender: UserId,
+    canister_id: CanisterId,
+) -> Result<FetchCanisterLogsResponse, ic_error_types::UserError> {
+    let result = test.query(
+        UserQuery {
+            source: sender,
+            receiver: IC_00,
+            method_name: Method::FetchCanisterLogs.to_string(),
+            method_payload: FetchCanisterLogsRequest::new(canister_id).encode(),
+            ingress_expiry: 0,
+            nonce: None,
+        },
+        Arc::new(test.state().clone()),
+        vec![],
+    )?;
+    match result {
+        WasmResult::Reply(data) => Ok(Decode!(&data, FetchCanisterLogsResponse).unwrap()),
+        WasmResult::Reject(msg) => panic!("Unexpected reject: {}", msg),
+    }
+}
+
+fn log_contents(response: FetchCanisterLogsResponse) -> Vec<String> {
+    response
+        .canister_log_records
+        .into_iter()
+        .map(|record| String::from_utf8(record.content).unwrap())
+        .collect()
+}
+
+#[test]
+fn debug_print_is_recorded_in_canister_log() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.ingress(
+        canister_id,
+        "update",
+        wasm()
+            .debug_print(b"first")
+            .debug_print(b"second")
+            .reply()
+            .build(),
+    )
+    .unwrap();
+
+    let response = fetch_canister_logs(&test, test.user_id(), canister_id).unwrap();
+    let records = &response.canister_log_records;
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].idx, records[0].idx + 1);
+    assert_eq!(
+        records[0].timestamp_nanos,
+        test.time().as_nanos_since_unix_epoch()
+    );
+    assert_eq!(log_contents(response), vec!["first", "second"]);
+}
+
+#[test]
+fn trap_message_is_recorded_in_canister_log() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let err = test
+        .ingress(
+            canister_id,
+            "update",
+            wasm()
+                .debug_print(b"before trap")
+                .trap_with_blob(b"boom")
+                .build(),
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterCalledTrap);
+
+    let contents = log_contents(fetch_canister_logs(&test, test.user_id(), canister_id).unwrap());
+    assert_eq!(contents.len(), 2);
+    assert_eq!(contents[0], "before trap");
+    assert!(contents[1].starts_with("[TRAP]"));
+    assert!(contents[1].contains("boom"));
+}
+
+#[test]
+fn queries_do_not_write_to_canister_log() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.non_replicated_query(
+        canister_id,
+        "query",
+        wasm().debug_print(b"query").reply().build(),
+    )
+    .unwrap();
+
+    let response = fetch_canister_logs(&test, test.user_id(), canister_id).unwrap();
+    assert!(response.canister_log_records.is_empty());
+}
+
+#[test]
+fn fetch_canister_logs_respects_log_visibility() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.ingress(
+        canister_id,
+        "update",
+        wasm().debug_print(b"hello").reply().build(),
+    )
+    .unwrap();
+
+    let other_user = user_test_id(42);
+    assert_ne!(other_user, test.user_id());
+    let err = fetch_canister_logs(&test, other_user, canister_id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+
+    let settings = CanisterSettingsArgsBuilder::new()
+        .with_log_visibility(LogVisibility::Public)
+        .build();
+    test.subnet_message(
+        Method::UpdateSettings,
+        UpdateSettingsArgs::new(canister_id, settings).encode(),
+    )
+    .unwrap();
+    assert_eq!(
+        test.canister_state(canister_id).system_state.log_visibility,
+        LogVisibility::Public
+    );
+
+    let contents = log_contents(fetch_canister_logs(&test, other_user, canister_id).unwrap());
+    assert_eq!(contents, vec!["hello"]);
+}
+
+#[test]
+fn fetch_canister_logs_is_rejected_in_replicated_mode() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let err = test
+        .subnet_message(
+            Method::FetchCanisterLogs,
+            FetchCanisterLogsRequest::new(canister_id).encode(),
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+}
+
+#[test]
+fn canister_log_is_kept_when_install_code_fails() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    let wat = r#"
+        (module
+            (import "ic0" "debug_print" (func $debug_print (param i32 i32)))
+            (import "ic0" "trap" (func $trap (param i32 i32)))
+            (func (export "canister_init")
+                (call $debug_print (i32.const 0) (i32.const 4))
+                (call $trap (i32.const 4) (i32.const 4))
+            )
+            (memory 1)
+            (data (i32.const 0) "initboom")
+        )"#;
+    let err = test
+        .install_canister(canister_id, wat::parse_str(wat).unwrap())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterCalledTrap);
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+
+    let contents = log_contents(fetch_canister_logs(&test, test.user_id(), canister_id).unwrap());
+    assert_eq!(contents.len(), 2);
+    assert_eq!(contents[0], "init");
+    assert!(contents[1].starts_with("[TRAP]"));
+    assert!(contents[1].contains("boom"));
+}
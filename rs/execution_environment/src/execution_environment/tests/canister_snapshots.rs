@@ -0,0 +1,275 @@
+use candid::Decode;
+use ic_error_types::ErrorCode;
+use ic_ic00_types::{
+    CanisterIdRecord, CanisterSnapshotResponse, DeleteCanisterSnapshotArgs,
+    ListCanisterSnapshotArgs, ListCanisterSnapshotsResponse, LoadCanisterSnapshotArgs, Method,
+    Payload as Ic00Payload, SnapshotId, TakeCanisterSnapshotArgs,
+};
+use ic_interfaces::execution_environment::SubnetAvailableMemory;
+use ic_test_utilities_execution_environment::{get_reply, ExecutionTest, ExecutionTestBuilder};
+use ic_types::{ingress::WasmResult, CanisterId, NumBytes, PrincipalId};
+use ic_universal_canister::{wasm, UNIVERSAL_CANISTER_WASM};
+
+fn take_snapshot(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    replace_snapshot: Option<SnapshotId>,
+) -> SnapshotId {
+    let args = TakeCanisterSnapshotArgs::new(canister_id, replace_snapshot);
+    let result = test.subnet_message(Method::TakeCanisterSnapshot, args.encode());
+    Decode!(&get_reply(result), CanisterSnapshotResponse)
+        .unwrap()
+        .snapshot_id()
+        .unwrap()
+}
+
+fn write_stable_memory(test: &mut ExecutionTest, canister_id: CanisterId, data: &[u8]) {
+    test.ingress(
+        canister_id,
+        "update",
+        wasm().stable_write(0, data).reply().build(),
+    )
+    .unwrap();
+}
+
+fn read_stable_memory(test: &mut ExecutionTest, canister_id: CanisterId, len: u32) -> Vec<u8> {
+    let result = test
+        .ingress(
+            canister_id,
+            "update",
+            wasm().stable_read(0, len).append_and_reply().build(),
+        )
+        .unwrap();
+    match result {
+        WasmResult::Reply(data) => data,
+        WasmResult::Reject(msg) => panic!("Unexpected reject: {}", msg),
+    }
+}
+
+#[test]
+fn load_canister_snapshot_restores_state() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.ingress(
+        canister_id,
+        "update",
+        wasm()
+            .stable_grow(1)
+            .certified_data_set(b"certified")
+            .reply()
+            .build(),
+    )
+    .unwrap();
+    write_stable_memory(&mut test, canister_id, b"before");
+    let certified_data = test
+        .canister_state(canister_id)
+        .system_state
+        .certified_data
+        .clone();
+
+    let snapshot_id = take_snapshot(&mut test, canister_id, None);
+    assert_eq!(snapshot_id.get_canister_id(), canister_id);
+
+    write_stable_memory(&mut test, canister_id, b"after!");
+    assert_eq!(read_stable_memory(&mut test, canister_id, 6), b"after!");
+
+    let version_before_load = test
+        .canister_state(canister_id)
+        .system_state
+        .canister_version;
+    let args = LoadCanisterSnapshotArgs::new(canister_id, snapshot_id, None);
+    test.subnet_message(Method::LoadCanisterSnapshot, args.encode())
+        .unwrap();
+
+    assert_eq!(read_stable_memory(&mut test, canister_id, 6), b"before");
+    let canister = test.canister_state(canister_id);
+    assert_eq!(canister.system_state.certified_data, certified_data);
+    assert_eq!(certified_data, b"certified".to_vec());
+    assert!(canister.system_state.canister_version > version_before_load);
+    assert_eq!(
+        canister
+            .execution_state
+            .as_ref()
+            .unwrap()
+            .wasm_binary
+            .binary
+            .as_slice(),
+        UNIVERSAL_CANISTER_WASM
+    );
+}
+
+#[test]
+fn list_and_delete_canister_snapshots() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let snapshot_id = take_snapshot(&mut test, canister_id, None);
+
+    let args = ListCanisterSnapshotArgs::new(canister_id);
+    let result = test.subnet_message(Method::ListCanisterSnapshots, args.encode());
+    let snapshots = Decode!(&get_reply(result), ListCanisterSnapshotsResponse)
+        .unwrap()
+        .0;
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].snapshot_id().unwrap(), snapshot_id);
+    assert_eq!(
+        snapshots[0].taken_at_timestamp,
+        test.time().as_nanos_since_unix_epoch()
+    );
+
+    let args = DeleteCanisterSnapshotArgs::new(canister_id, snapshot_id);
+    test.subnet_message(Method::DeleteCanisterSnapshot, args.encode())
+        .unwrap();
+    assert!(test.state().canister_snapshots.is_empty());
+
+    // Deleting it again fails.
+    let err = test
+        .subnet_message(Method::DeleteCanisterSnapshot, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotNotFound);
+}
+
+#[test]
+fn take_canister_snapshot_respects_limit_and_replaces() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let first = take_snapshot(&mut test, canister_id, None);
+
+    let args = TakeCanisterSnapshotArgs::new(canister_id, None);
+    let err = test
+        .subnet_message(Method::TakeCanisterSnapshot, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotLimitExceeded);
+
+    let second = take_snapshot(&mut test, canister_id, Some(first));
+    assert_ne!(first, second);
+    let snapshots = test.state().canister_snapshots.list_snapshots(canister_id);
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].0, second);
+}
+
+#[test]
+fn take_canister_snapshot_charges_cycles() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let balance_before = test.canister_state(canister_id).system_state.balance();
+    take_snapshot(&mut test, canister_id, None);
+    let balance_after = test.canister_state(canister_id).system_state.balance();
+    assert!(balance_after < balance_before);
+}
+
+#[test]
+fn canister_snapshots_require_controller() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.set_controller(canister_id, PrincipalId::new_anonymous())
+        .unwrap();
+
+    let args = TakeCanisterSnapshotArgs::new(canister_id, None);
+    let err = test
+        .subnet_message(Method::TakeCanisterSnapshot, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+    assert!(test.state().canister_snapshots.is_empty());
+}
+
+#[test]
+fn cannot_load_snapshot_of_another_canister() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let other_canister_id = test.universal_canister().unwrap();
+    let snapshot_id = take_snapshot(&mut test, other_canister_id, None);
+
+    let args = LoadCanisterSnapshotArgs::new(canister_id, snapshot_id, None);
+    let err = test
+        .subnet_message(Method::LoadCanisterSnapshot, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+}
+
+#[test]
+fn deleting_canister_deletes_its_snapshots() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    take_snapshot(&mut test, canister_id, None);
+    test.stop_canister(canister_id);
+    test.process_stopping_canisters();
+    test.subnet_message(
+        Method::DeleteCanister,
+        CanisterIdRecord::from(canister_id).encode(),
+    )
+    .unwrap();
+    assert!(test.state().canister_snapshots.is_empty());
+}
+
+#[test]
+fn canister_snapshots_count_towards_memory_usage() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let memory_usage_before = test.canister_state(canister_id).memory_usage();
+    let subnet_memory_before = test.subnet_available_memory().get_execution_memory();
+
+    let snapshot_id = take_snapshot(&mut test, canister_id, None);
+    let snapshot_size = test
+        .state()
+        .canister_snapshots
+        .get(&snapshot_id)
+        .unwrap()
+        .size();
+    let canister = test.canister_state(canister_id);
+    assert_eq!(canister.snapshots_memory_usage(), snapshot_size);
+    assert_eq!(canister.memory_usage(), memory_usage_before + snapshot_size);
+    assert_eq!(
+        test.subnet_available_memory().get_execution_memory(),
+        subnet_memory_before - snapshot_size.get() as i64
+    );
+
+    let args = DeleteCanisterSnapshotArgs::new(canister_id, snapshot_id);
+    test.subnet_message(Method::DeleteCanisterSnapshot, args.encode())
+        .unwrap();
+    let canister = test.canister_state(canister_id);
+    assert_eq!(canister.snapshots_memory_usage(), NumBytes::from(0));
+    assert_eq!(canister.memory_usage(), memory_usage_before);
+    assert_eq!(
+        test.subnet_available_memory().get_execution_memory(),
+        subnet_memory_before
+    );
+}
+
+#[test]
+fn take_canister_snapshot_fails_when_memory_allocation_is_too_small() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let memory_usage = test.canister_state(canister_id).memory_usage();
+    test.canister_update_allocations_settings(canister_id, None, Some(memory_usage.get()))
+        .unwrap();
+
+    let args = TakeCanisterSnapshotArgs::new(canister_id, None);
+    let err = test
+        .subnet_message(Method::TakeCanisterSnapshot, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InsufficientMemoryAllocation);
+    assert!(test.state().canister_snapshots.is_empty());
+    assert_eq!(
+        test.canister_state(canister_id).snapshots_memory_usage(),
+        NumBytes::from(0)
+    );
+}
+
+#[test]
+fn take_canister_snapshot_fails_when_subnet_memory_is_exhausted() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let subnet_available_memory = test.subnet_available_memory();
+    test.set_subnet_available_memory(SubnetAvailableMemory::new(
+        0,
+        subnet_available_memory.get_message_memory(),
+        subnet_available_memory.get_wasm_custom_sections_memory(),
+    ));
+
+    let args = TakeCanisterSnapshotArgs::new(canister_id, None);
+    let err = test
+        .subnet_message(Method::TakeCanisterSnapshot, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::SubnetOversubscribed);
+    assert!(test.state().canister_snapshots.is_empty());
+}
@@ -0,0 +1,126 @@
+use ic_error_types::ErrorCode;
+use ic_ic00_types::{
+    CanisterSettingsArgsBuilder, CanisterStatusResultV2, Method, Payload as Ic00Payload,
+    UpdateSettingsArgs,
+};
+use ic_replicated_state::NumWasmPages;
+use ic_test_utilities_execution_environment::{get_reply, ExecutionTest, ExecutionTestBuilder};
+use ic_types::{CanisterId, Cycles};
+
+// A canister that grows its Wasm memory by ten pages in each update call.
+const GROW_WAT: &str = r#"(module
+    (import "ic0" "msg_reply" (func $msg_reply))
+    (func (export "canister_update grow")
+        (drop (memory.grow (i32.const 10)))
+        (call $msg_reply)
+    )
+    (memory 1)
+)"#;
+
+fn set_reserved_cycles_limit(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    limit: Cycles,
+) -> Result<(), ic_error_types::UserError> {
+    let settings = CanisterSettingsArgsBuilder::new()
+        .with_reserved_cycles_limit(limit.get())
+        .build();
+    test.subnet_message(
+        Method::UpdateSettings,
+        UpdateSettingsArgs::new(canister_id, settings).encode(),
+    )
+    .map(|_| ())
+}
+
+fn reserved_balance(test: &ExecutionTest, canister_id: CanisterId) -> Cycles {
+    test.canister_state(canister_id)
+        .system_state
+        .reserved_balance()
+}
+
+#[test]
+fn memory_grow_does_not_reserve_cycles_below_threshold() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+    assert_eq!(reserved_balance(&test, canister_id), Cycles::zero());
+}
+
+#[test]
+fn memory_grow_reserves_cycles_above_threshold() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    let reserved_before = reserved_balance(&test, canister_id);
+    let balance_before = test.canister_state(canister_id).system_state.balance();
+
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+
+    let reserved_after = reserved_balance(&test, canister_id);
+    assert!(reserved_after > reserved_before);
+    // The reserved cycles come out of the main balance.
+    let balance_after = test.canister_state(canister_id).system_state.balance();
+    assert!(balance_before - balance_after >= reserved_after - reserved_before);
+}
+
+#[test]
+fn memory_grow_fails_above_reserved_cycles_limit() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    let reserved = reserved_balance(&test, canister_id);
+    set_reserved_cycles_limit(&mut test, canister_id, reserved).unwrap();
+
+    let err = test.ingress(canister_id, "grow", vec![]).unwrap_err();
+    assert_eq!(
+        err.code(),
+        ErrorCode::ReservedCyclesLimitExceededInMemoryGrow
+    );
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::new(1)
+    );
+    assert_eq!(reserved_balance(&test, canister_id), reserved);
+}
+
+#[test]
+fn reserved_cycles_limit_cannot_be_below_reserved_balance() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+    let reserved = reserved_balance(&test, canister_id);
+    assert!(reserved > Cycles::zero());
+
+    let err =
+        set_reserved_cycles_limit(&mut test, canister_id, reserved - Cycles::new(1)).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::ReservedCyclesLimitIsTooLow);
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .reserved_balance_limit(),
+        None
+    );
+}
+
+#[test]
+fn canister_status_reports_reserved_cycles() {
+    let mut test = ExecutionTestBuilder::new()
+        .with_subnet_memory_threshold(0)
+        .build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+    let limit = Cycles::new(1_000_000_000_000);
+    set_reserved_cycles_limit(&mut test, canister_id, limit).unwrap();
+
+    let result = test.canister_status(canister_id);
+    let status = CanisterStatusResultV2::decode(&get_reply(result)).unwrap();
+    assert_eq!(
+        status.reserved_cycles(),
+        reserved_balance(&test, canister_id).get()
+    );
+    assert_eq!(status.reserved_cycles_limit(), Some(limit.get()));
+}
@@ -0,0 +1,225 @@
+use candid::Decode;
+use ic_crypto_sha::Sha256;
+use ic_error_types::ErrorCode;
+use ic_ic00_types::{
+    CanisterInstallMode, ClearChunkStoreArgs, InstallChunkedCodeArgs, Method,
+    Payload as Ic00Payload, StoredChunksArgs, StoredChunksReply, UploadChunkArgs, UploadChunkReply,
+};
+use ic_replicated_state::canister_state::system_state::CHUNK_SIZE;
+use ic_test_utilities_execution_environment::{get_reply, ExecutionTest, ExecutionTestBuilder};
+use ic_types::{CanisterId, Cycles, PrincipalId};
+use ic_universal_canister::UNIVERSAL_CANISTER_WASM;
+
+const CYCLES: Cycles = Cycles::new(1_000_000_000_000_000);
+
+fn upload_chunk(test: &mut ExecutionTest, canister_id: CanisterId, chunk: &[u8]) -> Vec<u8> {
+    let args = UploadChunkArgs::new(canister_id, chunk.to_vec());
+    let result = test.subnet_message(Method::UploadChunk, args.encode());
+    Decode!(&get_reply(result), UploadChunkReply).unwrap().hash
+}
+
+fn stored_chunks(test: &mut ExecutionTest, canister_id: CanisterId) -> Vec<Vec<u8>> {
+    let args = StoredChunksArgs::new(canister_id);
+    let result = test.subnet_message(Method::StoredChunks, args.encode());
+    Decode!(&get_reply(result), StoredChunksReply)
+        .unwrap()
+        .0
+        .into_iter()
+        .map(|chunk_hash| chunk_hash.hash)
+        .collect()
+}
+
+/// Uploads the universal canister in two chunks to `store_canister` and
+/// returns the hashes of the chunks.
+fn upload_universal_canister(test: &mut ExecutionTest, store_canister: CanisterId) -> Vec<Vec<u8>> {
+    let (first, second) = UNIVERSAL_CANISTER_WASM.split_at(UNIVERSAL_CANISTER_WASM.len() / 2);
+    vec![
+        upload_chunk(test, store_canister, first),
+        upload_chunk(test, store_canister, second),
+    ]
+}
+
+#[test]
+fn upload_chunk_stores_chunk_and_returns_hash() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(CYCLES);
+    let memory_before = test.canister_state(canister_id).memory_usage();
+
+    let hash = upload_chunk(&mut test, canister_id, b"chunk");
+    assert_eq!(hash, Sha256::hash(b"chunk").to_vec());
+    // Uploading the same chunk again does not store it twice.
+    assert_eq!(upload_chunk(&mut test, canister_id, b"chunk"), hash);
+
+    assert_eq!(stored_chunks(&mut test, canister_id), vec![hash]);
+    // The chunk takes up a full slot in the store.
+    assert_eq!(
+        test.canister_state(canister_id).memory_usage(),
+        memory_before + CHUNK_SIZE.into()
+    );
+}
+
+#[test]
+fn chunk_store_counts_towards_cycle_charges() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(CYCLES);
+    let freezing_threshold_before = test.freezing_threshold(canister_id);
+
+    upload_chunk(&mut test, canister_id, b"chunk");
+    assert!(test.freezing_threshold(canister_id) > freezing_threshold_before);
+}
+
+#[test]
+fn upload_chunk_rejects_oversized_chunk() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(CYCLES);
+    let args = UploadChunkArgs::new(canister_id, vec![0; CHUNK_SIZE as usize + 1]);
+    let err = test
+        .subnet_message(Method::UploadChunk, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(test
+        .canister_state(canister_id)
+        .system_state
+        .wasm_chunk_store
+        .is_empty());
+}
+
+#[test]
+fn clear_chunk_store_removes_all_chunks() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(CYCLES);
+    let memory_before = test.canister_state(canister_id).memory_usage();
+    upload_chunk(&mut test, canister_id, b"first");
+    upload_chunk(&mut test, canister_id, b"second");
+    assert_eq!(stored_chunks(&mut test, canister_id).len(), 2);
+
+    let args = ClearChunkStoreArgs::new(canister_id);
+    test.subnet_message(Method::ClearChunkStore, args.encode())
+        .unwrap();
+    assert!(stored_chunks(&mut test, canister_id).is_empty());
+    assert_eq!(
+        test.canister_state(canister_id).memory_usage(),
+        memory_before
+    );
+}
+
+#[test]
+fn chunk_store_methods_require_controller() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(CYCLES);
+    test.set_controller(canister_id, PrincipalId::new_anonymous())
+        .unwrap();
+
+    let err = test
+        .subnet_message(
+            Method::UploadChunk,
+            UploadChunkArgs::new(canister_id, b"chunk".to_vec()).encode(),
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+
+    let err = test
+        .subnet_message(
+            Method::StoredChunks,
+            StoredChunksArgs::new(canister_id).encode(),
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+}
+
+#[test]
+fn install_chunked_code_installs_module() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(CYCLES);
+    let chunk_hashes = upload_universal_canister(&mut test, canister_id);
+
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        None,
+        chunk_hashes,
+        Sha256::hash(UNIVERSAL_CANISTER_WASM).to_vec(),
+        vec![],
+    );
+    test.subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap();
+
+    let canister = test.canister_state(canister_id);
+    assert_eq!(
+        canister
+            .execution_state
+            .as_ref()
+            .unwrap()
+            .wasm_binary
+            .binary
+            .as_slice(),
+        UNIVERSAL_CANISTER_WASM
+    );
+    // The chunks are kept after installation.
+    assert_eq!(canister.system_state.wasm_chunk_store.len(), 2);
+}
+
+#[test]
+fn install_chunked_code_from_another_store_canister() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let store_canister = test.create_canister(CYCLES);
+    let target_canister = test.create_canister(CYCLES);
+    let chunk_hashes = upload_universal_canister(&mut test, store_canister);
+
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        target_canister,
+        Some(store_canister),
+        chunk_hashes,
+        Sha256::hash(UNIVERSAL_CANISTER_WASM).to_vec(),
+        vec![],
+    );
+    test.subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap();
+    assert!(test
+        .canister_state(target_canister)
+        .execution_state
+        .is_some());
+}
+
+#[test]
+fn install_chunked_code_fails_on_hash_mismatch() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(CYCLES);
+    let mut chunk_hashes = upload_universal_canister(&mut test, canister_id);
+    // Assembling the chunks in the wrong order yields a different module.
+    chunk_hashes.reverse();
+
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        None,
+        chunk_hashes,
+        Sha256::hash(UNIVERSAL_CANISTER_WASM).to_vec(),
+        vec![],
+    );
+    let err = test
+        .subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+}
+
+#[test]
+fn install_chunked_code_fails_on_missing_chunk() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(CYCLES);
+
+    let args = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        None,
+        vec![Sha256::hash(b"missing").to_vec()],
+        Sha256::hash(b"missing").to_vec(),
+        vec![],
+    );
+    let err = test
+        .subnet_message(Method::InstallChunkedCode, args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+}
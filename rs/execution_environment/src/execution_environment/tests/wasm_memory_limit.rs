@@ -0,0 +1,153 @@
+use ic_error_types::ErrorCode;
+use ic_ic00_types::{
+    CanisterSettingsArgsBuilder, CanisterStatusResultV2, Method, Payload as Ic00Payload,
+    UpdateSettingsArgs,
+};
+use ic_replicated_state::{
+    canister_state::{system_state::OnLowWasmMemoryHookStatus, WASM_PAGE_SIZE_IN_BYTES},
+    NumWasmPages,
+};
+use ic_state_machine_tests::StateMachine;
+use ic_test_utilities_execution_environment::{get_reply, ExecutionTest, ExecutionTestBuilder};
+use ic_types::{CanisterId, Cycles, NumBytes};
+
+const PAGE_SIZE: u64 = WASM_PAGE_SIZE_IN_BYTES as u64;
+
+// A canister that grows its Wasm memory by three pages in update and query
+// calls. It starts with a single page.
+const GROW_WAT: &str = r#"(module
+    (import "ic0" "msg_reply" (func $msg_reply))
+    (func (export "canister_update grow")
+        (drop (memory.grow (i32.const 3)))
+        (call $msg_reply)
+    )
+    (func (export "canister_query grow_query")
+        (drop (memory.grow (i32.const 3)))
+        (call $msg_reply)
+    )
+    (memory 1)
+)"#;
+
+fn set_wasm_memory_limit(test: &mut ExecutionTest, canister_id: CanisterId, limit: u64) {
+    let settings = CanisterSettingsArgsBuilder::new()
+        .with_wasm_memory_limit(limit)
+        .build();
+    test.subnet_message(
+        Method::UpdateSettings,
+        UpdateSettingsArgs::new(canister_id, settings).encode(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn memory_grow_fails_above_wasm_memory_limit_in_update() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    set_wasm_memory_limit(&mut test, canister_id, 2 * PAGE_SIZE);
+
+    let err = test.ingress(canister_id, "grow", vec![]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterWasmMemoryLimitExceeded);
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::new(1)
+    );
+
+    // A limit of zero removes the limit.
+    set_wasm_memory_limit(&mut test, canister_id, 0);
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .wasm_memory_limit,
+        None
+    );
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::new(4)
+    );
+}
+
+#[test]
+fn wasm_memory_limit_is_not_enforced_in_queries() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    set_wasm_memory_limit(&mut test, canister_id, 2 * PAGE_SIZE);
+
+    test.non_replicated_query(canister_id, "grow_query", vec![])
+        .unwrap();
+}
+
+#[test]
+fn canister_status_reports_wasm_memory_settings() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+
+    let result = test.canister_status(canister_id);
+    let status = CanisterStatusResultV2::decode(&get_reply(result)).unwrap();
+    assert_eq!(status.wasm_memory_limit(), 0);
+    assert_eq!(status.wasm_memory_threshold(), 0);
+
+    let settings = CanisterSettingsArgsBuilder::new()
+        .with_wasm_memory_limit(10 * PAGE_SIZE)
+        .with_wasm_memory_threshold(PAGE_SIZE)
+        .build();
+    test.subnet_message(
+        Method::UpdateSettings,
+        UpdateSettingsArgs::new(canister_id, settings).encode(),
+    )
+    .unwrap();
+
+    let result = test.canister_status(canister_id);
+    let status = CanisterStatusResultV2::decode(&get_reply(result)).unwrap();
+    assert_eq!(status.wasm_memory_limit(), 10 * PAGE_SIZE);
+    assert_eq!(status.wasm_memory_threshold(), PAGE_SIZE);
+}
+
+#[test]
+fn on_low_wasm_memory_hook_runs_once() {
+    let env = StateMachine::new();
+    let wat = r#"(module
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (func (export "canister_update grow")
+            (drop (memory.grow (i32.const 6)))
+            (call $msg_reply)
+        )
+        (func (export "canister_on_low_wasm_memory")
+            (drop (memory.grow (i32.const 1)))
+        )
+        (memory 1)
+    )"#;
+    let settings = CanisterSettingsArgsBuilder::new()
+        .with_wasm_memory_limit(10 * PAGE_SIZE)
+        .with_wasm_memory_threshold(5 * PAGE_SIZE)
+        .build();
+    let canister_id = env.install_canister_wat(wat, vec![], Some(settings));
+    let wasm_memory_size = |env: &StateMachine| {
+        env.get_latest_state()
+            .canister_state(&canister_id)
+            .unwrap()
+            .wasm_memory_usage()
+    };
+
+    // Nine pages of headroom are left, which is above the threshold.
+    env.tick();
+    assert_eq!(wasm_memory_size(&env), NumBytes::from(PAGE_SIZE));
+
+    // Three pages of headroom are left, so the hook runs in the next round.
+    env.execute_ingress(canister_id, "grow", vec![]).unwrap();
+    env.tick();
+    assert_eq!(wasm_memory_size(&env), NumBytes::from(8 * PAGE_SIZE));
+
+    // The hook does not run again while the condition keeps holding.
+    env.tick();
+    env.tick();
+    assert_eq!(wasm_memory_size(&env), NumBytes::from(8 * PAGE_SIZE));
+    assert_eq!(
+        env.get_latest_state()
+            .canister_state(&canister_id)
+            .unwrap()
+            .system_state
+            .on_low_wasm_memory_hook_status(),
+        OnLowWasmMemoryHookStatus::Executed
+    );
+}
@@ -301,5 +301,10 @@ fn dashboard_label_value_from(code: ErrorCode) -> &'static str {
         InsufficientCyclesInComputeAllocation => "Canister does not have enough cycles to increase its compute allocation",
         InsufficientCyclesInMemoryAllocation => "Canister does not have enough cycles to increase its memory allocation",
         InsufficientCyclesInMemoryGrow => "Canister does not have enough cycles to grow memory",
+        CanisterSnapshotNotFound => "Canister snapshot not found",
+        CanisterSnapshotLimitExceeded => "Canister exceeded the limit for the number of snapshots",
+        CanisterWasmMemoryLimitExceeded => "Canister exceeded its Wasm memory limit",
+        ReservedCyclesLimitExceededInMemoryGrow => "Canister cannot grow memory due to its reserved cycles limit",
+        ReservedCyclesLimitIsTooLow => "Canister reserved cycles limit is below its reserved cycles",
     }
 }
@@ -23,7 +23,9 @@ use std::{path::PathBuf, sync::Arc};
 
 use crate::execution::common::{apply_canister_state_changes, update_round_limits};
 use crate::execution_environment::{as_round_instructions, CompilationCostHandling, RoundLimits};
-use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
+use ic_replicated_state::page_map::{
+    PageAllocatorFileDescriptor, TestPageAllocatorFileDescriptorImpl,
+};
 
 #[cfg(test)]
 mod tests;
@@ -126,6 +128,7 @@ pub struct Hypervisor {
     deterministic_time_slicing: FlagStatus,
     cost_to_compile_wasm_instruction: NumInstructions,
     dirty_page_overhead: NumInstructions,
+    fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
 }
 
 impl Hypervisor {
@@ -137,6 +140,11 @@ impl Hypervisor {
         self.own_subnet_type
     }
 
+    /// Returns the factory for the files backing the pages of new page maps.
+    pub(crate) fn fd_factory(&self) -> Arc<dyn PageAllocatorFileDescriptor> {
+        Arc::clone(&self.fd_factory)
+    }
+
     pub fn create_execution_state(
         &self,
         canister_module: CanisterModule,
@@ -202,6 +210,7 @@ impl Hypervisor {
             config.query_execution_threads_per_canister;
         embedder_config.feature_flags.rate_limiting_of_debug_prints =
             config.rate_limiting_of_debug_prints;
+        embedder_config.feature_flags.wasm64 = config.wasm64;
         embedder_config.cost_to_compile_wasm_instruction = config.cost_to_compile_wasm_instruction;
         embedder_config.max_sandbox_count = config.max_sandbox_count;
         embedder_config.max_sandbox_idle_time = config.max_sandbox_idle_time;
@@ -243,6 +252,7 @@ impl Hypervisor {
             deterministic_time_slicing: config.deterministic_time_slicing,
             cost_to_compile_wasm_instruction: config.cost_to_compile_wasm_instruction,
             dirty_page_overhead,
+            fd_factory,
         }
     }
 
@@ -269,6 +279,7 @@ impl Hypervisor {
             deterministic_time_slicing,
             cost_to_compile_wasm_instruction,
             dirty_page_overhead,
+            fd_factory: Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
         }
     }
 
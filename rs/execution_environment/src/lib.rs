@@ -23,7 +23,11 @@ pub use execution_environment::{
 pub use history::{IngressHistoryReaderImpl, IngressHistoryWriterImpl};
 pub use hypervisor::{Hypervisor, HypervisorMetrics};
 use ic_base_types::PrincipalId;
-use ic_config::{execution_environment::Config, subnet_config::SchedulerConfig};
+use ic_config::{
+    execution_environment::{Config, MAX_WASM64_CANISTER_MEMORY_SIZE},
+    flag_status::FlagStatus,
+    subnet_config::SchedulerConfig,
+};
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_interfaces::execution_environment::AnonymousQueryService;
 use ic_interfaces::execution_environment::{
@@ -33,6 +37,7 @@ use ic_interfaces::execution_environment::{
 use ic_interfaces_state_manager::StateReader;
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
+use ic_query_stats::{init_query_stats, QueryStatsPayloadBuilderParams};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 use ic_replicated_state::{CallOrigin, NetworkTopology, ReplicatedState};
@@ -84,6 +89,7 @@ pub struct ExecutionServices {
     pub async_query_handler: QueryExecutionService,
     pub anonymous_query_handler: AnonymousQueryService,
     pub scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
+    pub query_stats_payload_builder: QueryStatsPayloadBuilderParams,
 }
 
 impl ExecutionServices {
@@ -96,11 +102,19 @@ impl ExecutionServices {
         own_subnet_id: SubnetId,
         own_subnet_type: SubnetType,
         scheduler_config: SchedulerConfig,
-        config: Config,
+        mut config: Config,
         cycles_account_manager: Arc<CyclesAccountManager>,
         state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
         fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
     ) -> ExecutionServices {
+        // Canisters with a 64-bit Wasm memory may grow beyond the default
+        // canister memory limit.
+        if config.wasm64 == FlagStatus::Enabled {
+            config.max_canister_memory_size = config
+                .max_canister_memory_size
+                .max(MAX_WASM64_CANISTER_MEMORY_SIZE);
+        }
+
         let hypervisor = Arc::new(Hypervisor::new(
             config.clone(),
             metrics_registry,
@@ -131,6 +145,10 @@ impl ExecutionServices {
             config.clone(),
             Arc::clone(&cycles_account_manager),
         ));
+        let (query_stats_collector, query_stats_payload_builder) =
+            init_query_stats(logger.clone(), &config);
+        let query_stats_collector = Arc::new(query_stats_collector);
+
         let sync_query_handler = Arc::new(InternalHttpQueryHandler::new(
             logger.clone(),
             hypervisor,
@@ -139,6 +157,7 @@ impl ExecutionServices {
             metrics_registry,
             scheduler_config.max_instructions_per_message_without_dts,
             Arc::clone(&cycles_account_manager),
+            Arc::clone(&query_stats_collector),
         ));
 
         // If this is not a system or verified subnet we can double the
@@ -165,6 +184,7 @@ impl ExecutionServices {
             Arc::clone(&sync_query_handler) as Arc<_>,
             query_scheduler.clone(),
             Arc::clone(&state_reader),
+            query_stats_collector,
         );
         let ingress_filter = IngressFilter::new_service(
             query_scheduler.clone(),
@@ -199,6 +219,7 @@ impl ExecutionServices {
             async_query_handler,
             anonymous_query_handler,
             scheduler,
+            query_stats_payload_builder,
         }
     }
 
@@ -213,6 +234,7 @@ impl ExecutionServices {
         QueryExecutionService,
         AnonymousQueryService,
         Box<dyn Scheduler<State = ReplicatedState>>,
+        QueryStatsPayloadBuilderParams,
     ) {
         (
             self.ingress_filter,
@@ -222,6 +244,7 @@ impl ExecutionServices {
             self.async_query_handler,
             self.anonymous_query_handler,
             self.scheduler,
+            self.query_stats_payload_builder,
         )
     }
 }
@@ -3,7 +3,8 @@ use ic_metrics::{
     MetricsRegistry,
 };
 use ic_types::{
-    NumInstructions, NumMessages, NumSlices, MAX_STABLE_MEMORY_IN_BYTES, MAX_WASM_MEMORY_IN_BYTES,
+    NumInstructions, NumMessages, NumSlices, MAX_STABLE_MEMORY_IN_BYTES,
+    MAX_WASM64_MEMORY_IN_BYTES, MAX_WASM_MEMORY_IN_BYTES,
 };
 use prometheus::{Histogram, IntCounter};
 use std::{cell::RefCell, rc::Rc, time::Instant};
@@ -311,7 +312,14 @@ fn memory_buckets() -> Vec<f64> {
         8 * G,
     ]
     .iter()
-    .chain([MAX_STABLE_MEMORY_IN_BYTES, MAX_WASM_MEMORY_IN_BYTES].iter())
+    .chain(
+        [
+            MAX_STABLE_MEMORY_IN_BYTES,
+            MAX_WASM_MEMORY_IN_BYTES,
+            MAX_WASM64_MEMORY_IN_BYTES,
+        ]
+        .iter(),
+    )
     .cloned()
     .collect();
     // Ensure that all buckets are unique
@@ -635,5 +643,6 @@ mod tests {
         assert!(buckets.contains(&0));
         assert!(buckets.contains(&MAX_STABLE_MEMORY_IN_BYTES));
         assert!(buckets.contains(&MAX_WASM_MEMORY_IN_BYTES));
+        assert!(buckets.contains(&MAX_WASM64_MEMORY_IN_BYTES));
     }
 }
@@ -18,10 +18,15 @@ use ic_config::flag_status::FlagStatus;
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, LabeledTree::SubTree};
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, RejectCode, UserError};
+use ic_ic00_types::{
+    FetchCanisterLogsRequest, FetchCanisterLogsResponse, LogVisibility, Method as Ic00Method,
+    Payload as Ic00Payload, IC_00,
+};
 use ic_interfaces::execution_environment::{QueryExecutionService, QueryHandler};
 use ic_interfaces_state_manager::StateReader;
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
+use ic_query_stats::QueryStatsCollector;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::ReplicatedState;
 use ic_types::{
@@ -30,13 +35,14 @@ use ic_types::{
         Blob, Certificate, CertificateDelegation, HttpQueryResponse, HttpQueryResponseReply,
         UserQuery,
     },
-    CanisterId, NumInstructions,
+    CanisterId, Height, NumInstructions, PrincipalId,
 };
 use serde::Serialize;
 use std::{
     convert::Infallible,
     future::Future,
     pin::Pin,
+    str::FromStr,
     sync::Arc,
     task::{Context, Poll},
 };
@@ -57,7 +63,7 @@ fn get_latest_certified_state_and_data_certificate(
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
     certificate_delegation: Option<CertificateDelegation>,
     canister_id: CanisterId,
-) -> Option<(Arc<ReplicatedState>, Vec<u8>)> {
+) -> Option<(Arc<ReplicatedState>, Vec<u8>, Height)> {
     // The path to fetch the data certificate for the canister.
     let path = SubTree(flatmap! {
         label("canister") => SubTree(
@@ -82,6 +88,7 @@ fn get_latest_certified_state_and_data_certificate(
                     signature: Blob(cert.signed.signature.signature.get().0),
                     delegation: certificate_delegation,
                 }),
+                cert.height,
             )
         })
 }
@@ -90,6 +97,50 @@ fn label<T: Into<Label>>(t: T) -> Label {
     t.into()
 }
 
+/// Returns the log records of the requested canister. The logs are readable by
+/// anyone if the canister's log visibility is public, otherwise only by its
+/// controllers.
+fn fetch_canister_logs(
+    sender: PrincipalId,
+    state: &ReplicatedState,
+    args: FetchCanisterLogsRequest,
+) -> Result<WasmResult, UserError> {
+    let canister_id = args.get_canister_id();
+    let canister = state.canister_state(&canister_id).ok_or_else(|| {
+        UserError::new(
+            ErrorCode::CanisterNotFound,
+            format!("Canister {} not found", canister_id),
+        )
+    })?;
+
+    match canister.system_state.log_visibility {
+        LogVisibility::Public => {}
+        LogVisibility::Controllers => {
+            if !canister.controllers().contains(&sender) {
+                return Err(UserError::new(
+                    ErrorCode::CanisterInvalidController,
+                    format!(
+                        "Caller {} is not allowed to query ic00 method {}",
+                        sender,
+                        Ic00Method::FetchCanisterLogs
+                    ),
+                ));
+            }
+        }
+    }
+
+    let response = FetchCanisterLogsResponse {
+        canister_log_records: canister
+            .system_state
+            .canister_log
+            .records()
+            .iter()
+            .cloned()
+            .collect(),
+    };
+    Ok(WasmResult::Reply(response.encode()))
+}
+
 pub struct InternalHttpQueryHandler {
     log: ReplicaLogger,
     hypervisor: Arc<Hypervisor>,
@@ -99,6 +150,7 @@ pub struct InternalHttpQueryHandler {
     max_instructions_per_query: NumInstructions,
     cycles_account_manager: Arc<CyclesAccountManager>,
     query_cache: query_cache::QueryCache,
+    local_query_execution_stats: Arc<QueryStatsCollector>,
 }
 
 #[derive(Clone)]
@@ -107,6 +159,7 @@ pub(crate) struct HttpQueryHandler {
     internal: Arc<dyn QueryHandler<State = ReplicatedState>>,
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
     query_scheduler: QueryScheduler,
+    local_query_execution_stats: Arc<QueryStatsCollector>,
 }
 
 impl InternalHttpQueryHandler {
@@ -118,6 +171,7 @@ impl InternalHttpQueryHandler {
         metrics_registry: &MetricsRegistry,
         max_instructions_per_query: NumInstructions,
         cycles_account_manager: Arc<CyclesAccountManager>,
+        local_query_execution_stats: Arc<QueryStatsCollector>,
     ) -> Self {
         let query_cache_capacity = config.query_cache_capacity;
         Self {
@@ -129,6 +183,7 @@ impl InternalHttpQueryHandler {
             max_instructions_per_query,
             cycles_account_manager,
             query_cache: query_cache::QueryCache::new(metrics_registry, query_cache_capacity),
+            local_query_execution_stats,
         }
     }
 }
@@ -144,6 +199,23 @@ impl QueryHandler for InternalHttpQueryHandler {
     ) -> Result<WasmResult, UserError> {
         let measurement_scope = MeasurementScope::root(&self.metrics.query);
 
+        // The management canister only serves `fetch_canister_logs` as a query.
+        if query.receiver == IC_00 {
+            return match Ic00Method::from_str(&query.method_name) {
+                Ok(Ic00Method::FetchCanisterLogs) => {
+                    let args = FetchCanisterLogsRequest::decode(&query.method_payload)?;
+                    fetch_canister_logs(query.source.get(), state.as_ref(), args)
+                }
+                _ => Err(UserError::new(
+                    ErrorCode::CanisterMethodNotFound,
+                    format!(
+                        "Query method {} not found in the management canister",
+                        query.method_name
+                    ),
+                )),
+            };
+        }
+
         // Check the query cache first (if the query caching is enabled).
         // If a valid cache entry found, the result will be immediately returned.
         // Otherwise, the key and the env will be kept for the `insert` below.
@@ -182,6 +254,8 @@ impl QueryHandler for InternalHttpQueryHandler {
             self.config.composite_queries,
             query.receiver,
             &self.metrics.query_critical_error,
+            (self.config.query_stats_aggregation == FlagStatus::Enabled)
+                .then_some(self.local_query_execution_stats.as_ref()),
         );
         let result = context.run(
             query,
@@ -206,11 +280,13 @@ impl HttpQueryHandler {
         internal: Arc<dyn QueryHandler<State = ReplicatedState>>,
         query_scheduler: QueryScheduler,
         state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        local_query_execution_stats: Arc<QueryStatsCollector>,
     ) -> QueryExecutionService {
         BoxCloneService::new(Self {
             internal,
             state_reader,
             query_scheduler,
+            local_query_execution_stats,
         })
     }
 }
@@ -244,6 +320,7 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
     ) -> Self::Future {
         let internal = Arc::clone(&self.internal);
         let state_reader = Arc::clone(&self.state_reader);
+        let local_query_execution_stats = Arc::clone(&self.local_query_execution_stats);
         let (tx, rx) = oneshot::channel();
         let canister_id = query.receiver;
         self.query_scheduler.push(canister_id, move || {
@@ -257,7 +334,12 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
                     certificate_delegation,
                     query.receiver,
                 ) {
-                    Some((state, cert)) => internal.query(query, state, cert),
+                    Some((state, cert, height)) => {
+                        // Queries are attributed to the epoch of the certified
+                        // state they are executed against.
+                        local_query_execution_stats.set_epoch_from_height(height);
+                        internal.query(query, state, cert)
+                    }
                     None => Err(UserError::new(
                         ErrorCode::CertifiedStateUnavailable,
                         "Certified state is not available yet. Please try again...",
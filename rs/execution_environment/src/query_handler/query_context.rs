@@ -15,14 +15,18 @@ use ic_interfaces::execution_environment::{
     ExecutionComplexity, ExecutionMode, HypervisorError, SubnetAvailableMemory,
 };
 use ic_logger::{error, ReplicaLogger};
+use ic_query_stats::QueryStatsCollector;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
     CallContextAction, CallOrigin, CanisterState, NetworkTopology, ReplicatedState,
 };
 use ic_system_api::{ApiType, ExecutionParameters, InstructionLimits};
 use ic_types::{
+    batch::QueryStats,
     ingress::WasmResult,
-    messages::{Payload, RejectContext, Request, RequestOrResponse, Response, UserQuery},
+    messages::{
+        Payload, RejectContext, Request, RequestOrResponse, Response, UserQuery, NO_DEADLINE,
+    },
     methods::WasmMethod,
     CanisterId, Cycles, NumInstructions, NumMessages, Time,
 };
@@ -95,6 +99,8 @@ pub(super) struct QueryContext<'a> {
     query_context_time_limit: Duration,
     query_critical_error: &'a IntCounter,
     subnet_memory_capacity: NumBytes,
+    // Collects the statistics of the executed queries, if enabled.
+    local_query_execution_stats: Option<&'a QueryStatsCollector>,
 }
 
 impl<'a> QueryContext<'a> {
@@ -116,6 +122,7 @@ impl<'a> QueryContext<'a> {
         composite_queries: FlagStatus,
         canister_id: CanisterId,
         query_critical_error: &'a IntCounter,
+        local_query_execution_stats: Option<&'a QueryStatsCollector>,
     ) -> Self {
         let network_topology = Arc::new(state.metadata.network_topology.clone());
         let round_limits = RoundLimits {
@@ -144,6 +151,7 @@ impl<'a> QueryContext<'a> {
             query_context_time_limit: max_query_call_walltime,
             query_critical_error,
             subnet_memory_capacity,
+            local_query_execution_stats,
         }
     }
 
@@ -399,9 +407,30 @@ impl<'a> QueryContext<'a> {
             NumSlices::from(1),
             NumMessages::from(1),
         );
+        let egress_payload_size = match &result {
+            Ok(Some(WasmResult::Reply(reply))) => reply.len(),
+            Ok(Some(WasmResult::Reject(reject))) => reject.len(),
+            Ok(None) | Err(_) => 0,
+        };
+        self.add_query_stats(
+            canister.canister_id(),
+            QueryStats {
+                num_calls: 1,
+                num_instructions: instructions_executed.get(),
+                ingress_payload_size: method_payload.len() as u64,
+                egress_payload_size: egress_payload_size as u64,
+            },
+        );
         (canister, result)
     }
 
+    /// Records the statistics of an execution on the given canister.
+    fn add_query_stats(&self, canister_id: CanisterId, stats: QueryStats) {
+        if let Some(collector) = self.local_query_execution_stats {
+            collector.register_query_statistics(canister_id, &stats);
+        }
+    }
+
     fn execute_callback(
         &mut self,
         mut canister: CanisterState,
@@ -451,7 +480,7 @@ impl<'a> QueryContext<'a> {
         };
         let func_ref = match call_origin {
             CallOrigin::Ingress(_, _)
-            | CallOrigin::CanisterUpdate(_, _)
+            | CallOrigin::CanisterUpdate(_, _, _)
             | CallOrigin::SystemTask => unreachable!("Unreachable in the QueryContext."),
             CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => {
                 FuncRef::QueryClosure(closure)
@@ -474,6 +503,7 @@ impl<'a> QueryContext<'a> {
                 payload.to_vec(),
                 incoming_cycles,
                 call_context_id,
+                callback.deadline,
                 call_responded,
                 execution_parameters.execution_mode.clone(),
             ),
@@ -482,6 +512,7 @@ impl<'a> QueryContext<'a> {
                 context,
                 incoming_cycles,
                 call_context_id,
+                callback.deadline,
                 call_responded,
                 execution_parameters.execution_mode.clone(),
             ),
@@ -546,6 +577,13 @@ impl<'a> QueryContext<'a> {
             NumSlices::from(1),
             NumMessages::from(1),
         );
+        self.add_query_stats(
+            canister_id,
+            QueryStats {
+                num_instructions: instructions_executed.get(),
+                ..QueryStats::default()
+            },
+        );
         Ok((canister, call_origin, action))
     }
 
@@ -567,7 +605,7 @@ impl<'a> QueryContext<'a> {
     ) -> (NumInstructions, Result<Option<WasmResult>, HypervisorError>) {
         let func_ref = match call_origin {
             CallOrigin::Ingress(_, _)
-            | CallOrigin::CanisterUpdate(_, _)
+            | CallOrigin::CanisterUpdate(_, _, _)
             | CallOrigin::SystemTask => unreachable!("Unreachable in the QueryContext."),
             CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => {
                 FuncRef::QueryClosure(cleanup_closure)
@@ -628,6 +666,7 @@ impl<'a> QueryContext<'a> {
                 originator_reply_callback: request.sender_reply_callback,
                 response_payload: payload,
                 refund: Cycles::zero(),
+                deadline: request.deadline,
             })
         };
 
@@ -780,7 +819,7 @@ impl<'a> QueryContext<'a> {
             };
 
         match call_origin {
-            CallOrigin::CanisterUpdate(_, _)
+            CallOrigin::CanisterUpdate(_, _, _)
             | CallOrigin::Ingress(_, _)
             | CallOrigin::SystemTask => {
                 error!(
@@ -815,6 +854,7 @@ impl<'a> QueryContext<'a> {
                         originator_reply_callback: callback_id,
                         refund: Cycles::zero(),
                         response_payload: payload,
+                        deadline: NO_DEADLINE,
                     };
                     QueryResponse::CanisterResponse(response)
                 };
@@ -865,7 +905,7 @@ impl<'a> QueryContext<'a> {
         );
         match call_origin {
             CallOrigin::Ingress(_, _)
-            | CallOrigin::CanisterUpdate(_, _)
+            | CallOrigin::CanisterUpdate(_, _, _)
             | CallOrigin::SystemTask => {
                 unreachable!("Expected a query call context");
             }
@@ -877,6 +917,7 @@ impl<'a> QueryContext<'a> {
                     originator_reply_callback: callback_id,
                     refund: Cycles::zero(),
                     response_payload: Payload::Reject(RejectContext::from(error)),
+                    deadline: NO_DEADLINE,
                 };
                 QueryResponse::CanisterResponse(response)
             }
@@ -24,7 +24,9 @@ use ic_logger::{debug, error, fatal, info, new_logger, warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_replicated_state::{
     canister_state::{
-        execution_state::NextScheduledMethod, system_state::CyclesUseCase, NextExecution,
+        execution_state::NextScheduledMethod,
+        system_state::{CyclesUseCase, OnLowWasmMemoryHookStatus},
+        NextExecution,
     },
     testing::ReplicatedStateTesting,
     CanisterState, CanisterStatus, ExecutionTask, InputQueueType, NetworkTopology, ReplicatedState,
@@ -579,6 +581,20 @@ impl SchedulerImpl {
                                 break;
                             }
                         }
+
+                        // Add `OnLowWasmMemory` if the Wasm memory of the
+                        // canister got close to its `wasm_memory_limit`.
+                        canister.update_on_low_wasm_memory_hook_condition();
+                        if canister.system_state.on_low_wasm_memory_hook_status()
+                            == OnLowWasmMemoryHookStatus::Ready
+                            && canister.exports_on_low_wasm_memory_method()
+                        {
+                            canister
+                                .system_state
+                                .task_queue
+                                .push_front(ExecutionTask::OnLowWasmMemory);
+                            heartbeat_and_timer_canister_ids.insert(canister.canister_id());
+                        }
                     }
                 }
             }
@@ -682,12 +698,15 @@ impl SchedulerImpl {
                 .metrics
                 .round_inner_heartbeat_overhead_duration
                 .start_timer();
-            // Remove all remaining `Heartbeat` and `GlobalTimer` tasks
-            // because they will be added again in the next round.
+            // Remove all remaining `Heartbeat`, `GlobalTimer` and
+            // `OnLowWasmMemory` tasks because they will be added again in the
+            // next round.
             for canister_id in &heartbeat_and_timer_canister_ids {
                 let canister = state.canister_state_mut(canister_id).unwrap();
                 canister.system_state.task_queue.retain(|task| match task {
-                    ExecutionTask::Heartbeat | ExecutionTask::GlobalTimer => false,
+                    ExecutionTask::Heartbeat
+                    | ExecutionTask::GlobalTimer
+                    | ExecutionTask::OnLowWasmMemory => false,
                     ExecutionTask::PausedExecution(..)
                     | ExecutionTask::PausedInstallCode(..)
                     | ExecutionTask::AbortedExecution { .. }
@@ -1009,6 +1028,7 @@ impl SchedulerImpl {
                         canister,
                         state_time,
                         AddCanisterChangeToHistory::No,
+                        self.exec_env.fd_factory(),
                     ));
                     canister.scheduler_state.compute_allocation = ComputeAllocation::zero();
                     canister.system_state.memory_allocation = MemoryAllocation::BestEffort;
@@ -1224,6 +1244,7 @@ impl SchedulerImpl {
                 }
             }
         }
+        update_subnet_metrics(state);
         self.check_dts_invariants(state, current_round_type);
     }
 
@@ -1238,8 +1259,8 @@ impl SchedulerImpl {
             .iter()
             .filter(|(_, canister)| !canister.system_state.task_queue.is_empty());
 
-        // 1. Heartbeat and GlobalTimer tasks exist only during the round
-        //    and must not exist after the round.
+        // 1. Heartbeat, GlobalTimer and OnLowWasmMemory tasks exist only
+        //    during the round and must not exist after the round.
         // 2. Paused executions can exist only in ordinary rounds (not checkpoint rounds).
         // 3. If deterministic time slicing is disabled, then there are no paused tasks.
         //    Aborted tasks may still exist if DTS was disabled in recent checkpoints.
@@ -1260,6 +1281,12 @@ impl SchedulerImpl {
                             id
                         );
                     }
+                    ExecutionTask::OnLowWasmMemory => {
+                        panic!(
+                            "Unexpected on low Wasm memory task after a round in canister {:?}",
+                            id
+                        );
+                    }
                     ExecutionTask::PausedExecution(_) | ExecutionTask::PausedInstallCode(_) => {
                         assert_eq!(
                             self.deterministic_time_slicing,
@@ -1855,6 +1882,30 @@ fn execute_canisters_on_thread(
 
 /// Updates end-of-round replicated state metrics (canisters, queues, cycles,
 /// etc.).
+/// Updates the subnet metrics that are certified as part of the subnet
+/// subtree of the state tree.
+fn update_subnet_metrics(state: &mut ReplicatedState) {
+    let mut canister_state_bytes = NumBytes::from(0);
+    let mut consumed_cycles_total = NominalCycles::new(0);
+    for canister in state.canisters_iter() {
+        canister_state_bytes += canister.memory_usage();
+        consumed_cycles_total += canister
+            .system_state
+            .canister_metrics
+            .consumed_cycles_since_replica_started;
+    }
+    let num_canisters = state.canister_states.len() as u64;
+
+    let subnet_metrics = &mut state.metadata.subnet_metrics;
+    consumed_cycles_total += subnet_metrics.consumed_cycles_by_deleted_canisters;
+    consumed_cycles_total += subnet_metrics.consumed_cycles_ecdsa_outcalls;
+    consumed_cycles_total += subnet_metrics.consumed_cycles_http_outcalls;
+
+    subnet_metrics.num_canisters = num_canisters;
+    subnet_metrics.canister_state_bytes = canister_state_bytes;
+    subnet_metrics.consumed_cycles_total = consumed_cycles_total;
+}
+
 fn observe_replicated_state_metrics(
     own_subnet_id: SubnetId,
     state: &ReplicatedState,
@@ -1906,7 +1957,10 @@ fn observe_replicated_state_metrics(
             Some(&ExecutionTask::AbortedInstallCode { .. }) => {
                 num_aborted_install += 1;
             }
-            Some(&ExecutionTask::Heartbeat) | Some(&ExecutionTask::GlobalTimer) | None => {}
+            Some(&ExecutionTask::Heartbeat)
+            | Some(&ExecutionTask::GlobalTimer)
+            | Some(&ExecutionTask::OnLowWasmMemory)
+            | None => {}
         }
         consumed_cycles_total += canister
             .system_state
@@ -2081,7 +2135,9 @@ fn can_execute_msg(
         };
 
         // Only one install code message allowed at a time.
-        if let Some(Ic00Method::InstallCode) = maybe_instal_code_method {
+        if let Some(Ic00Method::InstallCode) | Some(Ic00Method::InstallChunkedCode) =
+            maybe_instal_code_method
+        {
             return false;
         }
     }
@@ -2131,6 +2187,14 @@ fn get_instructions_limits_for_subnet_message(
             | StopCanister
             | UninstallCode
             | UpdateSettings
+            | TakeCanisterSnapshot
+            | LoadCanisterSnapshot
+            | ListCanisterSnapshots
+            | DeleteCanisterSnapshot
+            | FetchCanisterLogs
+            | UploadChunk
+            | ClearChunkStore
+            | StoredChunks
             | BitcoinGetBalance
             | BitcoinGetUtxos
             | BitcoinSendTransaction
@@ -2139,7 +2203,7 @@ fn get_instructions_limits_for_subnet_message(
             | BitcoinGetSuccessors
             | ProvisionalCreateCanisterWithCycles
             | ProvisionalTopUpCanister => default_limits,
-            InstallCode => InstructionLimits::new(
+            InstallCode | InstallChunkedCode => InstructionLimits::new(
                 dts,
                 config.max_instructions_per_install_code,
                 config.max_instructions_per_install_code_slice,
@@ -2213,7 +2277,8 @@ fn should_add_task(
         ExecutionTask::GlobalTimer => {
             global_timer_has_reached_deadline && canister.exports_global_timer_method()
         }
-        ExecutionTask::AbortedExecution { .. }
+        ExecutionTask::OnLowWasmMemory
+        | ExecutionTask::AbortedExecution { .. }
         | ExecutionTask::AbortedInstallCode { .. }
         | ExecutionTask::PausedExecution(..)
         | ExecutionTask::PausedInstallCode(..) => unreachable!("Unexpected ExecutionTask variant."),
@@ -2224,7 +2289,8 @@ fn get_other_task(task: ExecutionTask) -> ExecutionTask {
     match task {
         ExecutionTask::Heartbeat => ExecutionTask::GlobalTimer,
         ExecutionTask::GlobalTimer => ExecutionTask::Heartbeat,
-        ExecutionTask::AbortedExecution { .. }
+        ExecutionTask::OnLowWasmMemory
+        | ExecutionTask::AbortedExecution { .. }
         | ExecutionTask::AbortedInstallCode { .. }
         | ExecutionTask::PausedExecution(..)
         | ExecutionTask::PausedInstallCode(..) => unreachable!("Unexpected ExecutionTask variant."),
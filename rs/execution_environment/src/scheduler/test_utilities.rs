@@ -43,7 +43,7 @@ use ic_test_utilities::{
     state::CanisterStateBuilder,
     types::{
         ids::{canister_test_id, subnet_test_id, user_test_id},
-        messages::{RequestBuilder, SignedIngressBuilder},
+        messages::{RequestBuilder, SignedIngressBuilder, NO_DEADLINE},
     },
 };
 use ic_test_utilities_execution_environment::{generate_subnets, test_registry_settings};
@@ -1072,6 +1072,7 @@ impl TestWasmExecutorCore {
                 allocated_bytes: NumBytes::from(0),
                 allocated_message_bytes: NumBytes::from(0),
                 instance_stats: InstanceStats::default(),
+                canister_log: Default::default(),
             };
             self.schedule
                 .push((self.round, canister_id, instructions_to_execute));
@@ -1112,6 +1113,7 @@ impl TestWasmExecutorCore {
             allocated_message_bytes: NumBytes::from(0),
             num_instructions_left: instructions_left,
             instance_stats,
+            canister_log: Default::default(),
         };
         self.schedule
             .push((self.round, canister_id, instructions_to_execute));
@@ -1186,7 +1188,7 @@ impl TestWasmExecutorCore {
         let response_message_id = self.next_message_id();
         let closure = WasmClosure {
             func_idx: 0,
-            env: response_message_id,
+            env: response_message_id as u64,
         };
         let prepayment_for_response_execution = self
             .cycles_account_manager
@@ -1205,6 +1207,7 @@ impl TestWasmExecutorCore {
                 on_reply: closure.clone(),
                 on_reject: closure,
                 on_cleanup: None,
+                deadline: NO_DEADLINE,
             })
             .map_err(|err| err.to_string())?;
         let request = Request {
@@ -1214,6 +1217,7 @@ impl TestWasmExecutorCore {
             payment: Cycles::zero(),
             method_name: "update".into(),
             method_payload: encode_message_id_as_payload(call_message_id),
+            deadline: NO_DEADLINE,
         };
         if let Err(req) = system_state.push_output_request(
             canister_current_memory_usage,
@@ -1253,7 +1257,9 @@ impl TestWasmExecutorCore {
             } => {
                 let message_id = match &input.func_ref {
                     FuncRef::Method(_) => unreachable!("A callback requires a closure"),
-                    FuncRef::UpdateClosure(closure) | FuncRef::QueryClosure(closure) => closure.env,
+                    FuncRef::UpdateClosure(closure) | FuncRef::QueryClosure(closure) => {
+                        closure.env as u32
+                    }
                 };
                 let message = self.messages.remove(&message_id).unwrap();
                 (message_id, message, Some(*call_context_id))
@@ -42,7 +42,9 @@ use ic_test_utilities::{
 use ic_test_utilities_metrics::{
     fetch_counter, fetch_gauge, fetch_gauge_vec, fetch_int_gauge, fetch_int_gauge_vec, metric_vec,
 };
-use ic_types::messages::{CallbackId, Payload, RejectContext, Response, MAX_RESPONSE_COUNT_BYTES};
+use ic_types::messages::{
+    CallbackId, Payload, RejectContext, Response, MAX_RESPONSE_COUNT_BYTES, NO_DEADLINE,
+};
 use ic_types::methods::SystemMethod;
 use ic_types::methods::WasmMethod;
 use ic_types::time::expiry_time_from_now;
@@ -2863,6 +2865,7 @@ fn ecdsa_signature_agreements_metric_is_updated() {
             code: RejectCode::SysFatal,
             message: "".into(),
         }),
+        deadline: NO_DEADLINE,
     };
 
     test.state_mut().consensus_queue.push(response);
@@ -2911,6 +2914,7 @@ fn ecdsa_signature_agreements_metric_is_updated() {
             }
             .encode(),
         ),
+        deadline: NO_DEADLINE,
     };
 
     test.state_mut().consensus_queue.push(response);
@@ -2950,6 +2954,36 @@ fn ecdsa_signature_agreements_metric_is_updated() {
     assert!(sign_with_ecdsa_contexts.is_empty());
 }
 
+#[test]
+fn subnet_metrics_are_updated_at_the_end_of_the_round() {
+    let mut test = SchedulerTestBuilder::new().build();
+    let canister_1 = test.create_canister();
+    let canister_2 = test.create_canister();
+    test.canister_state_mut(canister_1)
+        .system_state
+        .canister_metrics
+        .consumed_cycles_since_replica_started = NominalCycles::from(1_000_u128);
+    test.state_mut()
+        .metadata
+        .subnet_metrics
+        .consumed_cycles_by_deleted_canisters = NominalCycles::from(20_u128);
+
+    test.execute_round(ExecutionRoundType::OrdinaryRound);
+
+    let expected_canister_state_bytes = test.canister_state(canister_1).memory_usage()
+        + test.canister_state(canister_2).memory_usage();
+    let subnet_metrics = &test.state().metadata.subnet_metrics;
+    assert_eq!(subnet_metrics.num_canisters, 2);
+    assert_eq!(
+        subnet_metrics.canister_state_bytes,
+        expected_canister_state_bytes
+    );
+    assert_eq!(
+        subnet_metrics.consumed_cycles_total,
+        NominalCycles::from(1_020_u128)
+    );
+}
+
 #[test]
 fn consumed_cycles_ecdsa_outcalls_are_added_to_consumed_cycles_total() {
     let ecdsa_key = EcdsaKeyId {
@@ -4,8 +4,10 @@ use ic_config::{
 };
 use ic_ic00_types::CanisterSettingsArgsBuilder;
 use ic_registry_subnet_type::SubnetType;
-use ic_state_machine_tests::{ErrorCode, StateMachine, StateMachineConfig, UserError};
-use ic_types::{ingress::WasmResult, Cycles, NumBytes};
+use ic_state_machine_tests::{
+    ErrorCode, MultiSubnetStateMachineBuilder, StateMachine, StateMachineConfig, UserError,
+};
+use ic_types::{ingress::WasmResult, Cycles, NumBytes, PrincipalId, SubnetId};
 use ic_universal_canister::{call_args, wasm, UNIVERSAL_CANISTER_WASM};
 use std::{convert::TryInto, time::Duration};
 
@@ -247,6 +249,91 @@ fn test_canister_delete_restart() {
     );
 }
 
+/// Tests that state machines started from a snapshot have the state at the
+/// time of the snapshot and evolve independently of each other and of the
+/// original state machine.
+#[test]
+fn test_state_machine_snapshot() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TEST_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    env.execute_ingress(canister_id, "grow_page", vec![]).unwrap();
+    env.execute_ingress(canister_id, "persist", vec![]).unwrap();
+
+    let snapshot = env.snapshot();
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+
+    let fork_1 = snapshot.start();
+    let fork_2 = snapshot.start();
+    fork_1.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    fork_1.execute_ingress(canister_id, "inc", vec![]).unwrap();
+
+    let read = |env: &StateMachine| {
+        to_int(env.query(canister_id, "read", vec![]).unwrap().bytes())
+    };
+    assert_eq!(read(&env), 2);
+    assert_eq!(read(&fork_1), 3);
+    assert_eq!(read(&fork_2), 1);
+
+    // Stable memory is part of the snapshot.
+    fork_2.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    fork_2.execute_ingress(canister_id, "load", vec![]).unwrap();
+    assert_eq!(read(&fork_2), 1);
+    assert_eq!(fork_2.time(), env.time());
+
+    // The snapshot stays valid after the original state machine is dropped.
+    drop(env);
+    let fork_3 = snapshot.start();
+    assert_eq!(read(&fork_3), 1);
+}
+
+/// Tests that calls between canisters on different subnets are delivered, also
+/// after the callee is migrated to the subnet of the caller.
+#[test]
+fn test_multi_subnet_calls_and_canister_migration() {
+    let subnet_1 = SubnetId::from(PrincipalId::new_subnet_test_id(1));
+    let subnet_2 = SubnetId::from(PrincipalId::new_subnet_test_id(2));
+    let mut env = MultiSubnetStateMachineBuilder::new()
+        .with_subnet(subnet_1, SubnetType::System)
+        .with_subnet(subnet_2, SubnetType::Application)
+        .build();
+
+    let install_uc = |subnet_id| {
+        env.subnet(subnet_id)
+            .install_canister_with_cycles(
+                UNIVERSAL_CANISTER_WASM.into(),
+                vec![],
+                None,
+                INITIAL_CYCLES_BALANCE,
+            )
+            .unwrap()
+    };
+    let caller = install_uc(subnet_1);
+    let callee = install_uc(subnet_2);
+    assert_eq!(env.route(callee).unwrap().get_subnet_id(), subnet_2);
+
+    let call = wasm()
+        .inter_update(
+            callee.get(),
+            call_args().other_side(wasm().reply_data(b"pong").build()),
+        )
+        .build();
+    assert_eq!(
+        env.execute_ingress(caller, "update", call.clone()).unwrap(),
+        WasmResult::Reply(b"pong".to_vec())
+    );
+    env.run_until_completion(100);
+
+    env.migrate_canister(callee, subnet_1).unwrap();
+    assert_eq!(env.route(callee).unwrap().get_subnet_id(), subnet_1);
+    assert!(!env.subnet(subnet_2).canister_exists(callee));
+    assert_eq!(
+        env.execute_ingress(caller, "update", call).unwrap(),
+        WasmResult::Reply(b"pong".to_vec())
+    );
+}
+
 /// The test checks that the canister stable memory is discarded on code
 /// re-install, and that the stable memory stays discarded after a checkpoint
 /// recovery. It's a common bug in execution to reset a page map in memory, but
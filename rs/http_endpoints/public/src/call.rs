@@ -26,7 +26,7 @@ use ic_registry_client_helpers::{
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_types::messages::SignedIngressContent;
 use ic_types::{
-    messages::{SignedIngress, SignedRequestBytes},
+    messages::{MessageId, SignedIngress, SignedRequestBytes},
     CanisterId, CountBytes, NodeId, RegistryVersion, SubnetId,
 };
 use std::convert::{Infallible, TryInto};
@@ -54,8 +54,7 @@ pub(crate) struct CallService {
 
 impl CallService {
     #[allow(clippy::too_many_arguments)]
-    pub(crate) fn new_service(
-        config: Config,
+    pub(crate) fn new(
         log: ReplicaLogger,
         metrics: HttpHandlerMetrics,
         node_id: NodeId,
@@ -66,24 +65,28 @@ impl CallService {
         ingress_filter: IngressFilterService,
         ingress_throttler: Arc<RwLock<dyn IngressPoolThrottler + Send + Sync>>,
         ingress_tx: Sender<UnvalidatedArtifact<SignedIngress>>,
-    ) -> EndpointService {
+    ) -> Self {
+        Self {
+            log,
+            metrics,
+            node_id,
+            subnet_id,
+            time_source,
+            registry_client,
+            validator_executor,
+            ingress_filter,
+            ingress_throttler,
+            ingress_tx,
+        }
+    }
+
+    pub(crate) fn new_service(config: Config, call_service: Self) -> EndpointService {
         let base_service = BoxCloneService::new(
             ServiceBuilder::new()
                 .layer(GlobalConcurrencyLimitLayer::new(
                     config.max_call_concurrent_requests,
                 ))
-                .service(Self {
-                    log,
-                    metrics,
-                    subnet_id,
-                    registry_client,
-                    validator_executor,
-                    ingress_throttler,
-                    ingress_tx,
-                    time_source,
-                    ingress_filter,
-                    node_id,
-                }),
+                .service(call_service),
         );
 
         BoxCloneService::new(
@@ -92,74 +95,22 @@ impl CallService {
                 .service(base_service),
         )
     }
-}
-
-fn get_registry_data(
-    log: &ReplicaLogger,
-    subnet_id: SubnetId,
-    registry_version: RegistryVersion,
-    registry_client: &dyn RegistryClient,
-) -> Result<(IngressMessageSettings, ProvisionalWhitelist), HttpError> {
-    let settings = match registry_client.get_ingress_message_settings(subnet_id, registry_version) {
-        Ok(Some(settings)) => settings,
-        Ok(None) => {
-            let message = format!(
-                "No subnet record found for registry_version={:?} and subnet_id={:?}",
-                registry_version, subnet_id
-            );
-            warn!(log, "{}", message);
-            return Err(HttpError {
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message,
-            });
-        }
-        Err(err) => {
-            let message = format!(
-                "max_ingress_bytes_per_message not found for registry_version={:?} and subnet_id={:?}. {:?}",
-                registry_version, subnet_id, err
-            );
-            error!(log, "{}", message);
-            return Err(HttpError {
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message,
-            });
-        }
-    };
 
-    let provisional_whitelist = match registry_client.get_provisional_whitelist(registry_version) {
-        Ok(Some(list)) => list,
-        Ok(None) => {
-            error!(log, "At registry version {}, get_provisional_whitelist() returned Ok(None). Using empty list.",
-                       registry_version);
-            ProvisionalWhitelist::new_empty()
-        }
-        Err(err) => {
-            error!(log, "At registry version {}, get_provisional_whitelist() failed with {}.  Using empty list.",
-                       registry_version, err);
-            ProvisionalWhitelist::new_empty()
-        }
-    };
-    Ok((settings, provisional_whitelist))
-}
-
-/// Handles a call to /api/v2/canister/../call
-impl Service<Request<Vec<u8>>> for CallService {
-    type Response = Response<Body>;
-    type Error = Infallible;
+    /// Validates the call request and submits the contained message to the
+    /// ingress pool.
+    ///
+    /// Returns the id of the submitted message, or the response to send back
+    /// to the user if the message could not be submitted.
     #[allow(clippy::type_complexity)]
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
-
-    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        Poll::Ready(Ok(()))
-    }
-
-    fn call(&mut self, request: Request<Vec<u8>>) -> Self::Future {
-        // Actual parsing.
+    pub(crate) fn submit(
+        &self,
+        request: Request<Vec<u8>>,
+        api_req_type: ApiReqType,
+    ) -> Pin<Box<dyn Future<Output = Result<MessageId, Response<Body>>> + Send>> {
         self.metrics
             .request_body_size_bytes
-            .with_label_values(&[ApiReqType::Call.into(), LABEL_UNKNOWN])
+            .with_label_values(&[api_req_type.into(), LABEL_UNKNOWN])
             .observe(request.body().len() as f64);
-
         let (mut parts, body) = request.into_parts();
         let msg: SignedIngress = match SignedRequestBytes::from(body).try_into() {
             Ok(msg) => msg,
@@ -168,7 +119,7 @@ impl Service<Request<Vec<u8>>> for CallService {
                     StatusCode::BAD_REQUEST,
                     format!("Could not parse body as call message: {}", e),
                 );
-                return Box::pin(async move { Ok(res) });
+                return Box::pin(async move { Err(res) });
             }
         };
 
@@ -179,7 +130,7 @@ impl Service<Request<Vec<u8>>> for CallService {
                     self.log,
                     "Effective canister ID is not attached to call request. This is a bug."
                 );
-                return Box::pin(async move { Ok(res) });
+                return Box::pin(async move { Err(res) });
             }
         };
 
@@ -197,7 +148,7 @@ impl Service<Request<Vec<u8>>> for CallService {
                     effective_canister_id
                 ),
             );
-            return Box::pin(async move { Ok(res) });
+            return Box::pin(async move { Err(res) });
         }
 
         let message_id = msg.id();
@@ -210,7 +161,7 @@ impl Service<Request<Vec<u8>>> for CallService {
         ) {
             Ok((s, p)) => (s, p),
             Err(HttpError { status, message }) => {
-                return Box::pin(async move { Ok(make_plaintext_response(status, message)) });
+                return Box::pin(async move { Err(make_plaintext_response(status, message)) });
             }
         };
         if msg.count_bytes() > ingress_registry_settings.max_ingress_bytes_per_message {
@@ -223,7 +174,7 @@ impl Service<Request<Vec<u8>>> for CallService {
                     ingress_registry_settings.max_ingress_bytes_per_message
                 ),
             );
-            return Box::pin(async move { Ok(res) });
+            return Box::pin(async move { Err(res) });
         }
 
         let ingress_tx = self.ingress_tx.clone();
@@ -238,7 +189,7 @@ impl Service<Request<Vec<u8>>> for CallService {
                 validator_executor.validate_request(msg.as_ref().clone(), registry_version);
             if let Err(http_err) = validate_signed_ingress_fut.await {
                 let res = make_plaintext_response(http_err.status, http_err.message);
-                return Ok(res);
+                return Err(res);
             }
 
             match ingress_filter
@@ -250,7 +201,7 @@ impl Service<Request<Vec<u8>>> for CallService {
             {
                 Err(_) => panic!("Can't panic on Infallible"),
                 Ok(Err(err)) => {
-                    return Ok(make_response(err));
+                    return Err(make_response(err));
                 }
                 Ok(Ok(())) => (),
             }
@@ -266,28 +217,96 @@ impl Service<Request<Vec<u8>>> for CallService {
                     })
                     .is_err();
 
-            let response = if is_overloaded {
-                make_plaintext_response(
+            if is_overloaded {
+                Err(make_plaintext_response(
                     StatusCode::TOO_MANY_REQUESTS,
                     "Service is overloaded, try again later.".to_string(),
-                )
+                ))
             } else {
-                // We're pretty much done, just need to send the message to ingress and
-                // make_response to the client
+                // We're pretty much done, the message was sent to ingress.
                 info_sample!(
                     "message_id" => &message_id,
                     log,
                     "ingress_message_submit";
                     ingress_message => ingress_log_entry
                 );
-                make_accepted_response()
-            };
-            Ok(response)
+                Ok(message_id)
+            }
+        })
+    }
+}
+
+fn get_registry_data(
+    log: &ReplicaLogger,
+    subnet_id: SubnetId,
+    registry_version: RegistryVersion,
+    registry_client: &dyn RegistryClient,
+) -> Result<(IngressMessageSettings, ProvisionalWhitelist), HttpError> {
+    let settings = match registry_client.get_ingress_message_settings(subnet_id, registry_version) {
+        Ok(Some(settings)) => settings,
+        Ok(None) => {
+            let message = format!(
+                "No subnet record found for registry_version={:?} and subnet_id={:?}",
+                registry_version, subnet_id
+            );
+            warn!(log, "{}", message);
+            return Err(HttpError {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                message,
+            });
+        }
+        Err(err) => {
+            let message = format!(
+                "max_ingress_bytes_per_message not found for registry_version={:?} and subnet_id={:?}. {:?}",
+                registry_version, subnet_id, err
+            );
+            error!(log, "{}", message);
+            return Err(HttpError {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                message,
+            });
+        }
+    };
+
+    let provisional_whitelist = match registry_client.get_provisional_whitelist(registry_version) {
+        Ok(Some(list)) => list,
+        Ok(None) => {
+            error!(log, "At registry version {}, get_provisional_whitelist() returned Ok(None). Using empty list.",
+                       registry_version);
+            ProvisionalWhitelist::new_empty()
+        }
+        Err(err) => {
+            error!(log, "At registry version {}, get_provisional_whitelist() failed with {}.  Using empty list.",
+                       registry_version, err);
+            ProvisionalWhitelist::new_empty()
+        }
+    };
+    Ok((settings, provisional_whitelist))
+}
+
+/// Handles a call to /api/v2/canister/../call
+impl Service<Request<Vec<u8>>> for CallService {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Vec<u8>>) -> Self::Future {
+        let submission = self.submit(request, ApiReqType::Call);
+        Box::pin(async move {
+            Ok(match submission.await {
+                Ok(_message_id) => make_accepted_response(),
+                Err(res) => res,
+            })
         })
     }
 }
 
-fn make_accepted_response() -> Response<Body> {
+pub(crate) fn make_accepted_response() -> Response<Body> {
     let mut response = Response::new(Body::from(""));
     *response.status_mut() = StatusCode::ACCEPTED;
     *response.headers_mut() = get_cors_headers();
@@ -0,0 +1,204 @@
+//! Module that deals with requests to /api/v3/canister/.../call
+//!
+//! The message is submitted the same way as for /api/v2/canister/.../call,
+//! but the connection is kept open until the message has been executed and
+//! its status is part of the certified state. The response then contains a
+//! certificate with the `request_status` of the message. If the message is not
+//! certified as replied or rejected within the configured timeout, or its reply
+//! was already pruned, `202 Accepted` is returned and the user has to poll
+//! /api/v2/canister/.../read_state instead.
+
+use crate::{
+    body::BodyReceiverLayer,
+    call::{make_accepted_response, CallService},
+    common::{cbor_response, into_cbor},
+    state_reader_executor::StateReaderExecutor,
+    types::ApiReqType,
+    EndpointService, HttpHandlerMetrics,
+};
+use http::Request;
+use hyper::{Body, Response};
+use ic_config::http_handler::Config;
+use ic_crypto_tree_hash::{sparse_labeled_tree_from_paths, Label, MixedHashTree, Path};
+use ic_logger::{warn, ReplicaLogger};
+use ic_types::{
+    ingress::{IngressState, IngressStatus},
+    messages::{Blob, Certificate, CertificateDelegation, HttpCallV3Response, MessageId},
+    Height,
+};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::{sync::watch, time::Instant};
+use tower::{limit::GlobalConcurrencyLimitLayer, util::BoxCloneService, Service, ServiceBuilder};
+
+#[derive(Clone)]
+pub(crate) struct CallV3Service {
+    log: ReplicaLogger,
+    metrics: HttpHandlerMetrics,
+    call_service: CallService,
+    delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    state_reader_executor: StateReaderExecutor,
+    certified_height_watcher: watch::Receiver<Height>,
+    ingress_message_certificate_timeout: Duration,
+}
+
+impl CallV3Service {
+    pub(crate) fn new_service(
+        config: Config,
+        log: ReplicaLogger,
+        metrics: HttpHandlerMetrics,
+        call_service: CallService,
+        delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+        state_reader_executor: StateReaderExecutor,
+        certified_height_watcher: watch::Receiver<Height>,
+    ) -> EndpointService {
+        let base_service = Self {
+            log,
+            metrics,
+            call_service,
+            delegation_from_nns,
+            state_reader_executor,
+            certified_height_watcher,
+            ingress_message_certificate_timeout: Duration::from_secs(
+                config.ingress_message_certificate_timeout_seconds,
+            ),
+        };
+        let base_service = BoxCloneService::new(
+            ServiceBuilder::new()
+                .layer(GlobalConcurrencyLimitLayer::new(
+                    config.max_call_v3_concurrent_requests,
+                ))
+                .service(base_service),
+        );
+        BoxCloneService::new(
+            ServiceBuilder::new()
+                .layer(BodyReceiverLayer::new(&config))
+                .service(base_service),
+        )
+    }
+}
+
+/// Handles a call to /api/v3/canister/../call
+impl Service<Request<Vec<u8>>> for CallV3Service {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Vec<u8>>) -> Self::Future {
+        let submission = self.call_service.submit(request, ApiReqType::SyncCall);
+        let log = self.log.clone();
+        let metrics = self.metrics.clone();
+        let delegation_from_nns = Arc::clone(&self.delegation_from_nns);
+        let state_reader_executor = self.state_reader_executor.clone();
+        let certified_height_watcher = self.certified_height_watcher.clone();
+        let deadline = Instant::now() + self.ingress_message_certificate_timeout;
+        Box::pin(async move {
+            let message_id = match submission.await {
+                Ok(message_id) => message_id,
+                Err(res) => return Ok(res),
+            };
+
+            let certificate = match wait_for_certified_status(
+                &log,
+                &state_reader_executor,
+                certified_height_watcher,
+                &message_id,
+                deadline,
+            )
+            .await
+            {
+                Some((tree, signature)) => Certificate {
+                    tree,
+                    signature: Blob(signature),
+                    delegation: delegation_from_nns.read().unwrap().clone(),
+                },
+                None => return Ok(make_accepted_response()),
+            };
+
+            let (resp, body_size) = cbor_response(&HttpCallV3Response::Replied {
+                certificate: Blob(into_cbor(&certificate)),
+            });
+            metrics
+                .response_body_size_bytes
+                .with_label_values(&[ApiReqType::SyncCall.into()])
+                .observe(body_size as f64);
+            Ok(resp)
+        })
+    }
+}
+
+/// Waits until the message with `message_id` is replied or rejected in the
+/// latest certified state, or until `deadline` is reached. The certified state
+/// is only read again once `certified_height_watcher` reports a new height.
+///
+/// Returns the certified `request_status` and `time` subtree together with
+/// the signature of the certification, or `None` if the deadline was reached
+/// or the status of the message is `done`.
+async fn wait_for_certified_status(
+    log: &ReplicaLogger,
+    state_reader_executor: &StateReaderExecutor,
+    mut certified_height_watcher: watch::Receiver<Height>,
+    message_id: &MessageId,
+    deadline: Instant,
+) -> Option<(MixedHashTree, Vec<u8>)> {
+    let paths = vec![
+        Path::new(vec![
+            Label::from("request_status"),
+            Label::from(message_id.as_bytes()),
+        ]),
+        Path::from(Label::from("time")),
+    ];
+    let labeled_tree =
+        sparse_labeled_tree_from_paths(&paths).expect("Path of length 2 is never too long");
+
+    loop {
+        // Mark the current height as seen, so that `changed()` below only
+        // returns once a newer state has been certified.
+        certified_height_watcher.borrow_and_update();
+        match state_reader_executor
+            .read_certified_state(&labeled_tree)
+            .await
+        {
+            Ok(Some((replicated_state, tree, certification))) => {
+                if let IngressStatus::Known { state, .. } =
+                    replicated_state.get_ingress_status(message_id)
+                {
+                    match state {
+                        IngressState::Completed(_) | IngressState::Failed(_) => {
+                            return Some((tree, certification.signed.signature.signature.get().0));
+                        }
+                        // The reply or reject was already pruned, so the
+                        // certificate would not contain it.
+                        IngressState::Done => return None,
+                        IngressState::Received | IngressState::Processing => {}
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!(
+                    log,
+                    "Failed to read the certified state for message {}: {}",
+                    message_id,
+                    err.message
+                );
+            }
+        }
+
+        match tokio::time::timeout_at(deadline, certified_height_watcher.changed()).await {
+            Ok(Ok(())) => {}
+            // The deadline was reached, or the replica is shutting down and
+            // no new heights will be certified.
+            Err(_) | Ok(Err(_)) => return None,
+        }
+    }
+}
@@ -7,6 +7,7 @@
 //! Specification](https://sdk.dfinity.org/docs/interface-spec/index.html)
 mod body;
 mod call;
+mod call_v3;
 mod catch_up_package;
 mod common;
 mod dashboard;
@@ -17,12 +18,14 @@ mod query;
 mod read_state;
 mod state_reader_executor;
 mod status;
+mod subnet_read_state;
 mod threads;
 mod types;
 mod validator_executor;
 
 use crate::{
     call::CallService,
+    call_v3::CallV3Service,
     catch_up_package::CatchUpPackageService,
     common::{
         get_cors_headers, get_root_threshold_public_key, make_plaintext_response,
@@ -36,6 +39,7 @@ use crate::{
     read_state::ReadStateService,
     state_reader_executor::StateReaderExecutor,
     status::StatusService,
+    subnet_read_state::SubnetReadStateService,
     types::*,
     validator_executor::ValidatorExecutor,
 };
@@ -75,7 +79,7 @@ use ic_types::{
         HttpReadStateResponse, HttpRequestEnvelope, ReplicaHealthStatus, SignedIngress,
     },
     time::expiry_time_from_now,
-    CanisterId, NodeId, SubnetId,
+    CanisterId, Height, NodeId, PrincipalId, SubnetId,
 };
 use metrics::{HttpHandlerMetrics, LABEL_UNKNOWN};
 use rand::Rng;
@@ -91,6 +95,7 @@ use std::{
 use tempfile::NamedTempFile;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
+use tokio::sync::watch;
 use tokio::time::{sleep, timeout, Instant};
 use tokio_io_timeout::TimeoutStream;
 use tower::{
@@ -114,11 +119,13 @@ pub(crate) type EndpointService = BoxCloneService<Request<Body>, Response<Body>,
 #[derive(Clone)]
 struct HttpHandler {
     call_service: EndpointService,
+    call_v3_service: EndpointService,
     query_service: EndpointService,
     catchup_service: EndpointService,
     dashboard_service: EndpointService,
     status_service: EndpointService,
     read_state_service: EndpointService,
+    subnet_read_state_service: EndpointService,
     pprof_home_service: EndpointService,
     pprof_profile_service: EndpointService,
     pprof_flamegraph_service: EndpointService,
@@ -241,6 +248,7 @@ pub fn start_server(
     ingress_tx: Sender<UnvalidatedArtifact<SignedIngress>>,
     time_source: Arc<dyn TimeSource>,
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+    certified_height_watcher: watch::Receiver<Height>,
     registry_client: Arc<dyn RegistryClient>,
     tls_handshake: Arc<dyn TlsHandshake + Send + Sync>,
     ingress_verifier: Arc<dyn IngressSigVerifier + Send + Sync>,
@@ -272,8 +280,7 @@ pub fn start_server(
     let delegation_from_nns = Arc::new(RwLock::new(None));
     let health_status = Arc::new(AtomicCell::new(ReplicaHealthStatus::Starting));
     let state_reader_executor = StateReaderExecutor::new(state_reader);
-    let call_service = CallService::new_service(
-        config.clone(),
+    let call_service = CallService::new(
         log.clone(),
         metrics.clone(),
         node_id,
@@ -290,6 +297,16 @@ pub fn start_server(
         ingress_throttler,
         ingress_tx,
     );
+    let call_v3_service = CallV3Service::new_service(
+        config.clone(),
+        log.clone(),
+        metrics.clone(),
+        call_service.clone(),
+        Arc::clone(&delegation_from_nns),
+        state_reader_executor.clone(),
+        certified_height_watcher,
+    );
+    let call_service = CallService::new_service(config.clone(), call_service);
     let query_service = QueryService::new_service(
         config.clone(),
         log.clone(),
@@ -320,6 +337,22 @@ pub fn start_server(
         ),
         Arc::clone(&registry_client),
     );
+    let subnet_read_state_service = SubnetReadStateService::new_service(
+        config.clone(),
+        log.clone(),
+        metrics.clone(),
+        subnet_id,
+        Arc::clone(&health_status),
+        Arc::clone(&delegation_from_nns),
+        state_reader_executor.clone(),
+        ValidatorExecutor::new(
+            Arc::clone(&registry_client),
+            ingress_verifier.clone(),
+            &malicious_flags,
+            log.clone(),
+        ),
+        Arc::clone(&registry_client),
+    );
     let status_service = StatusService::new_service(
         config.clone(),
         log.clone(),
@@ -369,11 +402,13 @@ pub fn start_server(
 
     let http_handler = HttpHandler {
         call_service,
+        call_v3_service,
         query_service,
         status_service,
         catchup_service,
         dashboard_service,
         read_state_service,
+        subnet_read_state_service,
         pprof_home_service,
         pprof_profile_service,
         pprof_flamegraph_service,
@@ -599,11 +634,13 @@ async fn make_router(
     (mut req, mut timer): RequestWithTimer,
 ) -> ResponseWithTimer {
     let call_service = http_handler.call_service.clone();
+    let call_v3_service = http_handler.call_v3_service.clone();
     let query_service = http_handler.query_service.clone();
     let status_service = http_handler.status_service.clone();
     let catch_up_package_service = http_handler.catchup_service.clone();
     let dashboard_service = http_handler.dashboard_service.clone();
     let read_state_service = http_handler.read_state_service.clone();
+    let subnet_read_state_service = http_handler.subnet_read_state_service.clone();
     let pprof_home_service = http_handler.pprof_home_service.clone();
     let pprof_profile_service = http_handler.pprof_profile_service.clone();
     let pprof_flamegraph_service = http_handler.pprof_flamegraph_service.clone();
@@ -633,6 +670,7 @@ async fn make_router(
             }
 
             // Check the path
+            let mut parsed_subnet_id = None;
             let path = req.uri().path();
             let (svc, effective_canister_id) =
                 match *path.split('/').collect::<Vec<&str>>().as_slice() {
@@ -640,6 +678,10 @@ async fn make_router(
                         timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::Call.into());
                         (call_service, Some(effective_canister_id))
                     }
+                    ["", "api", "v3", "canister", effective_canister_id, "call"] => {
+                        timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::SyncCall.into());
+                        (call_v3_service, Some(effective_canister_id))
+                    }
                     ["", "api", "v2", "canister", effective_canister_id, "query"] => {
                         timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::Query.into());
                         (query_service, Some(effective_canister_id))
@@ -648,6 +690,27 @@ async fn make_router(
                         timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::ReadState.into());
                         (read_state_service, Some(effective_canister_id))
                     }
+                    ["", "api", "v2", "subnet", effective_subnet_id, "read_state"] => {
+                        timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::SubnetReadState.into());
+                        match PrincipalId::from_str(effective_subnet_id) {
+                            Ok(subnet_id) => {
+                                parsed_subnet_id = Some(SubnetId::from(subnet_id));
+                            }
+                            Err(e) => {
+                                return (
+                                    make_plaintext_response(
+                                        StatusCode::BAD_REQUEST,
+                                        format!(
+                                            "Malformed request: Invalid effective subnet id {}: {}",
+                                            effective_subnet_id, e
+                                        ),
+                                    ),
+                                    timer,
+                                );
+                            }
+                        }
+                        (subnet_read_state_service, None)
+                    }
                     ["", "_", "catch_up_package"] => {
                         timer.set_label(LABEL_REQUEST_TYPE, ApiReqType::CatchUpPackage.into());
                         (catch_up_package_service, None)
@@ -684,6 +747,11 @@ async fn make_router(
                     }
                 }
             }
+            // If url contains effective subnet id we attach it to the request. The
+            // subnet read state service checks that it is the id of this subnet.
+            if let Some(effective_subnet_id) = parsed_subnet_id {
+                req.extensions_mut().insert(effective_subnet_id);
+            }
             svc
         }
         Method::GET => match req.uri().path() {
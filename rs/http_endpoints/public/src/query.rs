@@ -13,6 +13,7 @@ use futures_util::FutureExt;
 use http::Request;
 use hyper::{Body, Response, StatusCode};
 use ic_config::http_handler::Config;
+use ic_ic00_types::{FetchCanisterLogsRequest, Method as Ic00Method, Payload, IC_00};
 use ic_interfaces::execution_environment::QueryExecutionService;
 use ic_interfaces_registry::RegistryClient;
 use ic_logger::{error, ReplicaLogger};
@@ -138,13 +139,37 @@ impl Service<Request<Vec<u8>>> for QueryService {
             }
         };
 
-        // Reject requests where `canister_id` != `effective_canister_id`. In comparison to update
-        // requests we don't need to check for the mgmt canister since all mgmt canister calls are updated calls.
+        // Reject requests where `canister_id` != `effective_canister_id`. For queries to the
+        // mgmt canister (i.e. `fetch_canister_logs`), the canister id is taken from the payload.
         // This needs to be enforced because boundary nodes block access based on the `effective_canister_id`
         // in the url and the replica processes the request based on the `canister_id`.
         // If this is not enforced, a blocked canisters can still be accessed by specifying
         // a non-blocked `effective_canister_id` and a blocked `canister_id`.
-        let canister_id = request.content().canister_id();
+        let mut canister_id = request.content().canister_id();
+        if canister_id == IC_00 {
+            // `fetch_canister_logs` is the only management canister method
+            // that can be called as a query.
+            if request.content().method_name != Ic00Method::FetchCanisterLogs.to_string() {
+                let res = make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Query method {} of the management canister does not exist",
+                        request.content().method_name
+                    ),
+                );
+                return Box::pin(async move { Ok(res) });
+            }
+            match FetchCanisterLogsRequest::decode(&request.content().method_payload) {
+                Ok(args) => canister_id = args.get_canister_id(),
+                Err(e) => {
+                    let res = make_plaintext_response(
+                        StatusCode::BAD_REQUEST,
+                        format!("Malformed management canister query: {}", e),
+                    );
+                    return Box::pin(async move { Ok(res) });
+                }
+            }
+        }
         if canister_id != effective_canister_id {
             let res = make_plaintext_response(
                 StatusCode::BAD_REQUEST,
@@ -127,27 +127,10 @@ impl Service<Request<Vec<u8>>> for ReadStateService {
 
         let delegation_from_nns = self.delegation_from_nns.read().unwrap().clone();
 
-        let request = match <HttpRequestEnvelope<HttpReadStateContent>>::try_from(
-            &SignedRequestBytes::from(body),
-        ) {
+        let request = match parse_read_state_request(body) {
             Ok(request) => request,
-            Err(e) => {
-                let res = make_plaintext_response(
-                    StatusCode::BAD_REQUEST,
-                    format!("Could not parse body as read request: {}", e),
-                );
-                return Box::pin(async move { Ok(res) });
-            }
-        };
-
-        // Convert the message to a strongly-typed struct.
-        let request = match HttpRequest::<ReadState>::try_from(request) {
-            Ok(request) => request,
-            Err(e) => {
-                let res = make_plaintext_response(
-                    StatusCode::BAD_REQUEST,
-                    format!("Malformed request: {:?}", e),
-                );
+            Err(HttpError { status, message }) => {
+                let res = make_plaintext_response(status, message);
                 return Box::pin(async move { Ok(res) });
             }
         };
@@ -182,56 +165,82 @@ impl Service<Request<Vec<u8>>> for ReadStateService {
                 return Ok(make_plaintext_response(status, message));
             }
 
-            // Create labeled tree. This may be an expensive operation and by
-            // creating the labeled tree after verifying the paths we know that
-            // the depth is max 4.
-            // Always add "time" to the paths even if not explicitly requested.
-            let mut paths: Vec<Path> = read_state.paths;
-            paths.push(Path::from(Label::from("time")));
-            let labeled_tree = match sparse_labeled_tree_from_paths(&paths) {
-                Ok(tree) => tree,
-                Err(TooLongPathError) => {
-                    let res = make_plaintext_response(
-                        StatusCode::BAD_REQUEST,
-                        "Failed to parse requested paths: path is too long.".to_string(),
-                    );
-                    return Ok(res);
-                }
-            };
+            // By verifying the paths first we know that the depth is max 4.
+            Ok(certified_read_state_response(
+                &state_reader_executor,
+                read_state.paths,
+                delegation_from_nns,
+                &metrics,
+                ApiReqType::ReadState,
+            )
+            .await)
+        })
+    }
+}
 
-            let res = match state_reader_executor
-                .read_certified_state(&labeled_tree)
-                .await
-            {
-                Ok(r) => r,
-                Err(e) => return Ok(make_plaintext_response(e.status, e.message)),
-            };
+/// Parses the body of a read_state request into a strongly-typed request.
+pub(crate) fn parse_read_state_request(body: Vec<u8>) -> Result<HttpRequest<ReadState>, HttpError> {
+    let request =
+        <HttpRequestEnvelope<HttpReadStateContent>>::try_from(&SignedRequestBytes::from(body))
+            .map_err(|e| HttpError {
+                status: StatusCode::BAD_REQUEST,
+                message: format!("Could not parse body as read request: {}", e),
+            })?;
+
+    // Convert the message to a strongly-typed struct.
+    HttpRequest::<ReadState>::try_from(request).map_err(|e| HttpError {
+        status: StatusCode::BAD_REQUEST,
+        message: format!("Malformed request: {:?}", e),
+    })
+}
 
-            let res = match res {
-                Some((_state, tree, certification)) => {
-                    let signature = certification.signed.signature.signature.get().0;
-                    let res = HttpReadStateResponse {
-                        certificate: Blob(into_cbor(&Certificate {
-                            tree,
-                            signature: Blob(signature),
-                            delegation: delegation_from_nns,
-                        })),
-                    };
-                    let (resp, body_size) = cbor_response(&res);
-                    metrics
-                        .response_body_size_bytes
-                        .with_label_values(&[ApiReqType::ReadState.into()])
-                        .observe(body_size as f64);
-                    resp
-                }
-                None => make_plaintext_response(
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    "Certified state is not available yet. Please try again...".to_string(),
-                ),
-            };
+/// Reads the requested `paths` from the latest certified state and returns
+/// them in a certificate. The `paths` must have been verified, since creating
+/// the labeled tree may be expensive for deep paths.
+pub(crate) async fn certified_read_state_response(
+    state_reader_executor: &StateReaderExecutor,
+    mut paths: Vec<Path>,
+    delegation_from_nns: Option<CertificateDelegation>,
+    metrics: &HttpHandlerMetrics,
+    api_req_type: ApiReqType,
+) -> Response<Body> {
+    // Always add "time" to the paths even if not explicitly requested.
+    paths.push(Path::from(Label::from("time")));
+    let labeled_tree = match sparse_labeled_tree_from_paths(&paths) {
+        Ok(tree) => tree,
+        Err(TooLongPathError) => {
+            return make_plaintext_response(
+                StatusCode::BAD_REQUEST,
+                "Failed to parse requested paths: path is too long.".to_string(),
+            );
+        }
+    };
 
-            Ok(res)
-        })
+    match state_reader_executor
+        .read_certified_state(&labeled_tree)
+        .await
+    {
+        Ok(Some((_state, tree, certification))) => {
+            let signature = certification.signed.signature.signature.get().0;
+            let res = HttpReadStateResponse {
+                certificate: Blob(into_cbor(&Certificate {
+                    tree,
+                    signature: Blob(signature),
+                    delegation: delegation_from_nns,
+                })),
+            };
+            let (resp, body_size) = cbor_response(&res);
+            metrics
+                .response_body_size_bytes
+                .with_label_values(&[api_req_type.into()])
+                .observe(body_size as f64);
+            resp
+        }
+        Ok(None) => make_plaintext_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Certified state is not available yet. Please try again...".to_string(),
+        ),
+        Err(e) => make_plaintext_response(e.status, e.message),
     }
 }
 
@@ -421,7 +430,7 @@ mod test {
     use ic_interfaces_state_manager_mocks::MockStateManager;
     use ic_metrics::MetricsRegistry;
     use ic_registry_subnet_type::SubnetType;
-    use ic_replicated_state::{CanisterQueues, ReplicatedState, SystemMetadata};
+    use ic_replicated_state::{CanisterQueues, CanisterSnapshots, ReplicatedState, SystemMetadata};
     use ic_test_utilities::{
         mock_time,
         state::insert_dummy_canister,
@@ -557,6 +566,7 @@ mod test {
                         BTreeMap::new(),
                         metadata,
                         CanisterQueues::default(),
+                        CanisterSnapshots::default(),
                     )),
                 )
             });
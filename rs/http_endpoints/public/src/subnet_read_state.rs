@@ -0,0 +1,263 @@
+//! Module that deals with requests to /api/v2/subnet/.../read_state
+
+use crate::{
+    body::BodyReceiverLayer,
+    common::make_plaintext_response,
+    metrics::LABEL_UNKNOWN,
+    read_state::{certified_read_state_response, parse_read_state_request},
+    state_reader_executor::StateReaderExecutor,
+    types::ApiReqType,
+    validator_executor::ValidatorExecutor,
+    EndpointService, HttpError, HttpHandlerMetrics, ReplicaHealthStatus,
+};
+use crossbeam::atomic::AtomicCell;
+use http::Request;
+use hyper::{Body, Response, StatusCode};
+use ic_config::http_handler::Config;
+use ic_crypto_tree_hash::Path;
+use ic_interfaces_registry::RegistryClient;
+use ic_logger::{error, ReplicaLogger};
+use ic_types::{
+    messages::{CertificateDelegation, ReadState},
+    SubnetId,
+};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use tower::{
+    limit::concurrency::GlobalConcurrencyLimitLayer, util::BoxCloneService, Service, ServiceBuilder,
+};
+
+#[derive(Clone)]
+pub(crate) struct SubnetReadStateService {
+    log: ReplicaLogger,
+    metrics: HttpHandlerMetrics,
+    subnet_id: SubnetId,
+    health_status: Arc<AtomicCell<ReplicaHealthStatus>>,
+    delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    state_reader_executor: StateReaderExecutor,
+    validator_executor: ValidatorExecutor<ReadState>,
+    registry_client: Arc<dyn RegistryClient>,
+}
+
+impl SubnetReadStateService {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new_service(
+        config: Config,
+        log: ReplicaLogger,
+        metrics: HttpHandlerMetrics,
+        subnet_id: SubnetId,
+        health_status: Arc<AtomicCell<ReplicaHealthStatus>>,
+        delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+        state_reader_executor: StateReaderExecutor,
+        validator_executor: ValidatorExecutor<ReadState>,
+        registry_client: Arc<dyn RegistryClient>,
+    ) -> EndpointService {
+        let base_service = Self {
+            log,
+            metrics,
+            subnet_id,
+            health_status,
+            delegation_from_nns,
+            state_reader_executor,
+            validator_executor,
+            registry_client,
+        };
+        let base_service = BoxCloneService::new(
+            ServiceBuilder::new()
+                .layer(GlobalConcurrencyLimitLayer::new(
+                    config.max_read_state_concurrent_requests,
+                ))
+                .service(base_service),
+        );
+        BoxCloneService::new(
+            ServiceBuilder::new()
+                .layer(BodyReceiverLayer::new(&config))
+                .service(base_service),
+        )
+    }
+}
+
+impl Service<Request<Vec<u8>>> for SubnetReadStateService {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Vec<u8>>) -> Self::Future {
+        self.metrics
+            .request_body_size_bytes
+            .with_label_values(&[ApiReqType::SubnetReadState.into(), LABEL_UNKNOWN])
+            .observe(request.body().len() as f64);
+
+        if self.health_status.load() != ReplicaHealthStatus::Healthy {
+            let res = make_plaintext_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!(
+                    "Replica is unhealthy: {}. Check the /api/v2/status for more information.",
+                    self.health_status.load(),
+                ),
+            );
+            return Box::pin(async move { Ok(res) });
+        }
+        let (mut parts, body) = request.into_parts();
+        let effective_subnet_id = match parts.extensions.remove::<SubnetId>() {
+            Some(subnet_id) => subnet_id,
+            _ => {
+                error!(
+                    self.log,
+                    "Effective subnet ID is not attached to subnet read state request. This is a bug."
+                );
+                let res = make_plaintext_response(
+                    StatusCode::BAD_REQUEST,
+                    "Malformed request".to_string(),
+                );
+                return Box::pin(async move { Ok(res) });
+            }
+        };
+        // The certificate is signed by this subnet, so it can only answer for itself.
+        if effective_subnet_id != self.subnet_id {
+            let res = make_plaintext_response(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Effective subnet id in URL {} does not match the id of this subnet: {}.",
+                    effective_subnet_id, self.subnet_id
+                ),
+            );
+            return Box::pin(async move { Ok(res) });
+        }
+
+        let delegation_from_nns = self.delegation_from_nns.read().unwrap().clone();
+
+        let request = match parse_read_state_request(body) {
+            Ok(request) => request,
+            Err(HttpError { status, message }) => {
+                let res = make_plaintext_response(status, message);
+                return Box::pin(async move { Ok(res) });
+            }
+        };
+
+        let read_state = request.content().clone();
+        let registry_version = self.registry_client.get_latest_version();
+        let state_reader_executor = self.state_reader_executor.clone();
+        let validator_executor = self.validator_executor.clone();
+        let metrics = self.metrics.clone();
+        Box::pin(async move {
+            // The targets are irrelevant here, since none of the allowed paths
+            // refers to a canister.
+            if let Err(http_err) = validator_executor
+                .validate_request(request, registry_version)
+                .await
+            {
+                return Ok(make_plaintext_response(http_err.status, http_err.message));
+            }
+            if let Err(HttpError { status, message }) = verify_paths(&read_state.paths) {
+                return Ok(make_plaintext_response(status, message));
+            }
+
+            // By verifying the paths first we know that the depth is max 5.
+            Ok(certified_read_state_response(
+                &state_reader_executor,
+                read_state.paths,
+                delegation_from_nns,
+                &metrics,
+                ApiReqType::SubnetReadState,
+            )
+            .await)
+        })
+    }
+}
+
+// Verifies that only subnet related paths are requested. All of them are
+// public, so no authorization is needed.
+fn verify_paths(paths: &[Path]) -> Result<(), HttpError> {
+    for path in paths {
+        let path: Vec<&[u8]> = path.iter().map(|label| label.as_bytes()).collect();
+        match path.as_slice() {
+            [b"time"] => {}
+            [b"subnet"] => {}
+            [b"subnet", _subnet_id]
+            | [b"subnet", _subnet_id, b"public_key" | b"canister_ranges" | b"metrics"] => {}
+            [b"subnet", _subnet_id, b"node"]
+            | [b"subnet", _subnet_id, b"node", _node_id]
+            | [b"subnet", _subnet_id, b"node", _node_id, b"public_key"] => {}
+            _ => {
+                // All other paths are unsupported.
+                return Err(HttpError {
+                    status: StatusCode::NOT_FOUND,
+                    message: "Invalid path requested.".to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ic_crypto_tree_hash::Label;
+
+    #[test]
+    fn verify_subnet_paths() {
+        let subnet_id = Label::from(vec![1, 2, 3]);
+        let node_id = Label::from(vec![4, 5, 6]);
+        let path = |labels: &[&Label]| Path::new(labels.iter().map(|l| (*l).clone()).collect());
+
+        assert_eq!(
+            verify_paths(&[
+                Path::from(Label::from("time")),
+                Path::from(Label::from("subnet")),
+                path(&[&Label::from("subnet"), &subnet_id]),
+                path(&[
+                    &Label::from("subnet"),
+                    &subnet_id,
+                    &Label::from("public_key")
+                ]),
+                path(&[
+                    &Label::from("subnet"),
+                    &subnet_id,
+                    &Label::from("canister_ranges")
+                ]),
+                path(&[&Label::from("subnet"), &subnet_id, &Label::from("metrics")]),
+                path(&[&Label::from("subnet"), &subnet_id, &Label::from("node")]),
+                path(&[
+                    &Label::from("subnet"),
+                    &subnet_id,
+                    &Label::from("node"),
+                    &node_id,
+                    &Label::from("public_key")
+                ]),
+            ]),
+            Ok(())
+        );
+
+        for invalid_path in [
+            Path::from(Label::from("canister")),
+            Path::new(vec![Label::from("request_status"), [0; 32].into()]),
+            path(&[&Label::from("subnet"), &subnet_id, &Label::from("unknown")]),
+            path(&[
+                &Label::from("subnet"),
+                &subnet_id,
+                &Label::from("node"),
+                &node_id,
+                &Label::from("unknown"),
+            ]),
+        ] {
+            assert_eq!(
+                verify_paths(&[invalid_path]),
+                Err(HttpError {
+                    status: StatusCode::NOT_FOUND,
+                    message: "Invalid path requested.".to_string(),
+                })
+            );
+        }
+    }
+}
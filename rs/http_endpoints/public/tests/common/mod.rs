@@ -35,7 +35,9 @@ use ic_registry_keys::{
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::{CanisterMigrations, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::{CanisterQueues, NetworkTopology, ReplicatedState, SystemMetadata};
+use ic_replicated_state::{
+    CanisterQueues, CanisterSnapshots, NetworkTopology, ReplicatedState, SystemMetadata,
+};
 use ic_test_utilities::{
     consensus::MockConsensusCache,
     crypto::temp_crypto_component_with_fake_registry,
@@ -68,6 +70,7 @@ use mockall::{mock, predicate::*};
 use prost::Message;
 use std::{collections::BTreeMap, net::SocketAddr, sync::Arc, sync::RwLock, time::Duration};
 use tokio::net::{TcpSocket, TcpStream};
+use tokio::sync::watch;
 use tower::{util::BoxCloneService, Service, ServiceExt};
 use tower_test::mock::Handle;
 
@@ -174,6 +177,7 @@ pub fn default_get_latest_state() -> Labeled<Arc<ReplicatedState>> {
             BTreeMap::new(),
             metadata,
             CanisterQueues::default(),
+            CanisterSnapshots::default(),
         )),
     )
 }
@@ -362,6 +366,32 @@ pub fn start_http_endpoint(
     IngressFilterHandle,
     Receiver<UnvalidatedArtifact<SignedIngress>>,
     QueryExecutionHandle,
+) {
+    // The certified height never changes in these tests.
+    let (_, certified_height_watcher) = watch::channel(default_latest_certified_height());
+    start_http_endpoint_with_certified_height_watcher(
+        rt,
+        config,
+        state_manager,
+        consensus_cache,
+        registry_client,
+        pprof_collector,
+        certified_height_watcher,
+    )
+}
+
+pub fn start_http_endpoint_with_certified_height_watcher(
+    rt: tokio::runtime::Handle,
+    config: Config,
+    state_manager: Arc<dyn StateReader<State = ReplicatedState>>,
+    consensus_cache: Arc<dyn ConsensusPoolCache>,
+    registry_client: Arc<dyn RegistryClient>,
+    pprof_collector: Arc<dyn PprofCollector>,
+    certified_height_watcher: watch::Receiver<Height>,
+) -> (
+    IngressFilterHandle,
+    Receiver<UnvalidatedArtifact<SignedIngress>>,
+    QueryExecutionHandle,
 ) {
     let metrics = MetricsRegistry::new();
     let (ingress_filter, ingress_filter_handle) = setup_ingress_filter_mock();
@@ -389,6 +419,7 @@ pub fn start_http_endpoint(
         ingress_tx,
         time_source,
         state_manager,
+        certified_height_watcher,
         registry_client,
         tls_handshake,
         sig_verifier,
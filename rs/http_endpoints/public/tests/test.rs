@@ -5,8 +5,9 @@ pub mod common;
 
 use crate::common::{
     basic_consensus_pool_cache, basic_registry_client, basic_state_manager_mock,
-    create_conn_and_send_request, get_free_localhost_socket_addr, start_http_endpoint,
-    wait_for_status_healthy,
+    create_conn_and_send_request, default_get_latest_state, default_latest_certified_height,
+    default_read_certified_state, get_free_localhost_socket_addr, start_http_endpoint,
+    start_http_endpoint_with_certified_height_watcher, wait_for_status_healthy,
 };
 use hyper::{Body, Client, Method, Request, StatusCode};
 use ic_agent::{
@@ -20,31 +21,43 @@ use ic_agent::{
     Agent, AgentError,
 };
 use ic_config::http_handler::Config;
+use ic_crypto_tree_hash::{Label as TreeLabel, Path};
 use ic_error_types::{ErrorCode, UserError};
 use ic_interfaces_registry_mocks::MockRegistryClient;
+use ic_interfaces_state_manager_mocks::MockStateManager;
 use ic_pprof::Pprof;
 use ic_protobuf::registry::crypto::v1::{
     AlgorithmId as AlgorithmIdProto, PublicKey as PublicKeyProto,
 };
 use ic_registry_keys::make_crypto_threshold_signing_pubkey_key;
-use ic_test_utilities::{consensus::MockConsensusCache, mock_time, types::ids::subnet_test_id};
+use ic_test_utilities::{
+    consensus::MockConsensusCache,
+    mock_time,
+    types::ids::{canister_test_id, node_test_id, subnet_test_id, user_test_id},
+};
 use ic_types::{
     batch::{BatchPayload, ValidationContext},
     consensus::{dkg::Dealings, Block, Payload, Rank},
     crypto::{threshold_sig::ThresholdSigPublicKey, CryptoHash, CryptoHashOf},
-    messages::{Blob, HttpQueryResponse, HttpQueryResponseReply},
-    Height, RegistryVersion,
+    ingress::{IngressState, IngressStatus, WasmResult},
+    messages::{
+        Blob, HttpCallContent, HttpCallV3Response, HttpCanisterUpdate, HttpQueryResponse,
+        HttpQueryResponseReply, HttpReadState, HttpReadStateContent, HttpRequestEnvelope,
+    },
+    time::expiry_time_from_now,
+    Height, NumBytes, PrincipalId, RegistryVersion, SubnetId,
 };
 use prost::Message;
 use std::{
     net::TcpStream,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, RwLock,
     },
 };
 use tokio::{
     runtime::Runtime,
+    sync::watch,
     time::{sleep, Duration},
 };
 use tower::ServiceExt;
@@ -278,6 +291,25 @@ fn test_unathorized_query() {
     });
     query_tests.push((query, Err(expected_resp)));
 
+    // Invalid query call to a management canister method other than `fetch_canister_logs`
+    let query = QueryBuilder::new(
+        &agent,
+        Principal::management_canister(),
+        "canister_status".to_string(),
+    )
+    .with_effective_canister_id(canister1)
+    .with_arg(Vec::new())
+    .sign()
+    .unwrap();
+    let expected_resp = AgentError::HttpError(HttpErrorPayload {
+        status: 400,
+        content_type: Some("text/plain".to_string()),
+        content: "Query method canister_status of the management canister does not exist"
+            .as_bytes()
+            .to_vec(),
+    });
+    query_tests.push((query, Err(expected_resp)));
+
     rt.block_on(async {
         wait_for_status_healthy(&agent).await.unwrap();
         for (query, expected_resp) in query_tests {
@@ -748,3 +780,270 @@ fn test_too_long_paths_are_rejected() {
 
     assert_eq!(Err(expected_error_response), actual_response);
 }
+
+/// The subnet read_state endpoint only serves subnet paths of the subnet the node belongs to.
+#[test]
+fn test_subnet_read_state() {
+    let rt = Runtime::new().unwrap();
+    let addr = get_free_localhost_socket_addr();
+    let config = Config {
+        listen_addr: addr,
+        ..Default::default()
+    };
+
+    let mock_state_manager = basic_state_manager_mock();
+    let mock_consensus_cache = basic_consensus_pool_cache();
+    let mock_registry_client = basic_registry_client();
+
+    let _ = start_http_endpoint(
+        rt.handle().clone(),
+        config,
+        Arc::new(mock_state_manager),
+        Arc::new(mock_consensus_cache),
+        Arc::new(mock_registry_client),
+        Arc::new(Pprof::default()),
+    );
+
+    let agent = Agent::builder()
+        .with_transport(ReqwestHttpReplicaV2Transport::create(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    rt.block_on(wait_for_status_healthy(&agent)).unwrap();
+
+    let read_subnet_state = |effective_subnet_id: SubnetId, paths: Vec<Path>| {
+        let envelope = HttpRequestEnvelope {
+            content: HttpReadStateContent::ReadState {
+                read_state: HttpReadState {
+                    sender: Blob(PrincipalId::new_anonymous().to_vec()),
+                    paths,
+                    nonce: None,
+                    ingress_expiry: expiry_time_from_now().as_nanos_since_unix_epoch(),
+                },
+            },
+            sender_pubkey: None,
+            sender_sig: None,
+            sender_delegation: None,
+        };
+        rt.block_on(async {
+            let req = Request::builder()
+                .method(Method::POST)
+                .uri(format!(
+                    "http://{}/api/v2/subnet/{}/read_state",
+                    addr, effective_subnet_id
+                ))
+                .header("Content-Type", "application/cbor")
+                .body(Body::from(serde_cbor::to_vec(&envelope).unwrap()))
+                .expect("request builder");
+            Client::new().request(req).await.unwrap().status()
+        })
+    };
+
+    let subnet_id = subnet_test_id(1);
+    let subnet_path = |labels: &[&str]| {
+        let mut path = vec![
+            TreeLabel::from("subnet"),
+            TreeLabel::from(subnet_id.get_ref().to_vec()),
+        ];
+        path.extend(labels.iter().map(|label| TreeLabel::from(*label)));
+        Path::new(path)
+    };
+
+    // Allowed paths.
+    assert_eq!(
+        read_subnet_state(
+            subnet_id,
+            vec![
+                Path::from(TreeLabel::from("time")),
+                subnet_path(&["public_key"]),
+                subnet_path(&["canister_ranges"]),
+                subnet_path(&["metrics"]),
+                subnet_path(&["node"]),
+            ]
+        ),
+        StatusCode::OK
+    );
+    let mut node_public_key_path = subnet_path(&["node"]);
+    node_public_key_path.push(TreeLabel::from(node_test_id(1).get_ref().to_vec()));
+    node_public_key_path.push(TreeLabel::from("public_key"));
+    assert_eq!(
+        read_subnet_state(subnet_id, vec![node_public_key_path]),
+        StatusCode::OK
+    );
+
+    // Paths that are not subnet paths.
+    for path in [
+        Path::new(vec![
+            TreeLabel::from("canister"),
+            TreeLabel::from(canister_test_id(1).get_ref().to_vec()),
+            TreeLabel::from("controllers"),
+        ]),
+        Path::new(vec![TreeLabel::from("request_status"), [0; 32].into()]),
+        subnet_path(&["unknown"]),
+    ] {
+        assert_eq!(
+            read_subnet_state(subnet_id, vec![path]),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    // A subnet id that is not the id of the node's subnet.
+    assert_eq!(
+        read_subnet_state(subnet_test_id(2), vec![subnet_path(&["metrics"])]),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+/// Sends an update call to /api/v3/canister/.../call while the certified state
+/// reports the status of the message as `certified_state`. If `executed_state`
+/// is given, it is certified at the next height while the call is waiting.
+fn call_v3(
+    certified_state: IngressState,
+    executed_state: Option<IngressState>,
+    ingress_message_certificate_timeout_seconds: u64,
+) -> (StatusCode, Vec<u8>) {
+    let rt = Runtime::new().unwrap();
+    let addr = get_free_localhost_socket_addr();
+    let config = Config {
+        listen_addr: addr,
+        ingress_message_certificate_timeout_seconds,
+        ..Default::default()
+    };
+
+    let canister_id = canister_test_id(1);
+    let update = HttpCanisterUpdate {
+        canister_id: Blob(canister_id.get().to_vec()),
+        method_name: "test".to_string(),
+        arg: Blob(vec![]),
+        sender: Blob(PrincipalId::new_anonymous().to_vec()),
+        ingress_expiry: expiry_time_from_now().as_nanos_since_unix_epoch(),
+        nonce: None,
+    };
+    let message_id = update.id();
+    let envelope = HttpRequestEnvelope {
+        content: HttpCallContent::Call { update },
+        sender_pubkey: None,
+        sender_sig: None,
+        sender_delegation: None,
+    };
+
+    let ingress_state = Arc::new(RwLock::new(certified_state));
+    let mut mock_state_manager = MockStateManager::new();
+    mock_state_manager
+        .expect_get_latest_state()
+        .returning(default_get_latest_state);
+    mock_state_manager
+        .expect_latest_certified_height()
+        .returning(default_latest_certified_height);
+    let state = Arc::clone(&ingress_state);
+    mock_state_manager
+        .expect_read_certified_state()
+        .returning(move |labeled_tree| {
+            let (replicated_state, tree, certification) =
+                default_read_certified_state(labeled_tree)?;
+            let mut replicated_state = (*replicated_state).clone();
+            replicated_state.set_ingress_status(
+                message_id.clone(),
+                IngressStatus::Known {
+                    receiver: canister_id.get(),
+                    user_id: user_test_id(1),
+                    time: mock_time(),
+                    state: state.read().unwrap().clone(),
+                },
+                NumBytes::from(u64::MAX),
+            );
+            Some((Arc::new(replicated_state), tree, certification))
+        });
+    let mock_consensus_cache = basic_consensus_pool_cache();
+    let mock_registry_client = basic_registry_client();
+
+    let (certified_height_sender, certified_height_watcher) =
+        watch::channel(default_latest_certified_height());
+    let (mut ingress_filter, _ingress_rx, _) = start_http_endpoint_with_certified_height_watcher(
+        rt.handle().clone(),
+        config,
+        Arc::new(mock_state_manager),
+        Arc::new(mock_consensus_cache),
+        Arc::new(mock_registry_client),
+        Arc::new(Pprof::default()),
+        certified_height_watcher,
+    );
+
+    let agent = Agent::builder()
+        .with_transport(ReqwestHttpReplicaV2Transport::create(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    rt.block_on(wait_for_status_healthy(&agent)).unwrap();
+
+    // Ingress filter mock that accepts every message.
+    rt.spawn(async move {
+        loop {
+            let (_, resp) = ingress_filter.next_request().await.unwrap();
+            resp.send_response(Ok(()))
+        }
+    });
+
+    rt.block_on(async {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!(
+                "http://{}/api/v3/canister/{}/call",
+                addr, canister_id
+            ))
+            .header("Content-Type", "application/cbor")
+            .body(Body::from(serde_cbor::to_vec(&envelope).unwrap()))
+            .expect("request builder");
+        let response = tokio::spawn(Client::new().request(req));
+
+        if let Some(executed_state) = executed_state {
+            // Give the call time to wait for the next certified height.
+            sleep(Duration::from_millis(500)).await;
+            *ingress_state.write().unwrap() = executed_state;
+            certified_height_sender
+                .send(default_latest_certified_height() + Height::from(1))
+                .unwrap();
+        }
+
+        let response = response.await.unwrap().unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, body.to_vec())
+    })
+}
+
+/// Once the message is replied or rejected in a newly certified state, the
+/// v3 call endpoint responds with a certificate.
+#[test]
+fn test_call_v3_returns_certificate_when_executed() {
+    for executed_state in [
+        IngressState::Completed(WasmResult::Reply(vec![1, 2, 3])),
+        IngressState::Failed(UserError::new(
+            ErrorCode::CanisterRejectedMessage,
+            "rejected",
+        )),
+    ] {
+        let (status, body) = call_v3(IngressState::Processing, Some(executed_state), 60);
+        assert_eq!(status, StatusCode::OK);
+        assert!(matches!(
+            serde_cbor::from_slice::<HttpCallV3Response>(&body),
+            Ok(HttpCallV3Response::Replied { .. })
+        ));
+    }
+}
+
+/// If the message is not executed within the timeout, the v3 call endpoint
+/// falls back to `202 Accepted`.
+#[test]
+fn test_call_v3_returns_accepted_on_timeout() {
+    let (status, _) = call_v3(IngressState::Processing, None, 1);
+    assert_eq!(status, StatusCode::ACCEPTED);
+}
+
+/// If the reply of the message was already pruned, the v3 call endpoint
+/// returns `202 Accepted` right away instead of waiting for the timeout.
+#[test]
+fn test_call_v3_returns_accepted_when_done() {
+    let start = std::time::Instant::now();
+    let (status, _) = call_v3(IngressState::Done, None, 60);
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(start.elapsed() < Duration::from_secs(60));
+}
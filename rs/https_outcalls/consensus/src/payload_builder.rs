@@ -12,7 +12,7 @@ use ic_consensus_utils::{
 };
 use ic_error_types::RejectCode;
 use ic_interfaces::{
-    batch_payload::{BatchPayloadBuilder, IntoMessages, PastPayload},
+    batch_payload::{BatchPayloadBuilder, IntoMessages, PastPayload, ProposalContext},
     canister_http::{
         CanisterHttpPayloadValidationError, CanisterHttpPermanentValidationError, CanisterHttpPool,
         CanisterHttpTransientValidationError,
@@ -36,7 +36,7 @@ use ic_types::{
     },
     consensus::Committee,
     crypto::Signed,
-    messages::{CallbackId, Payload, RejectContext, Response},
+    messages::{CallbackId, Payload, RejectContext, Response, NO_DEADLINE},
     registry::RegistryClientError,
     signature::BasicSignature,
     CanisterId, CountBytes, Cycles, Height, NodeId, NumBytes, RegistryVersion, SubnetId,
@@ -623,7 +623,7 @@ impl BatchPayloadBuilder for CanisterHttpPayloadBuilderImpl {
         height: Height,
         payload: &[u8],
         past_payloads: &[PastPayload],
-        context: &ValidationContext,
+        proposal_context: &ProposalContext,
     ) -> Result<(), PayloadValidationError> {
         if payload.is_empty() {
             return Ok(());
@@ -646,16 +646,21 @@ impl BatchPayloadBuilder for CanisterHttpPayloadBuilderImpl {
                 CanisterHttpPermanentValidationError::DecodeError(e),
             ))
         })?;
-        self.validate_canister_http_payload_impl(height, &payload, context, delivered_ids)
-            .map(|_| ())
-            .map_err(|err| match err {
-                ValidationError::Permanent(err) => ValidationError::Permanent(
-                    PayloadPermanentError::CanisterHttpPayloadValidationError(err),
-                ),
-                ValidationError::Transient(err) => ValidationError::Transient(
-                    PayloadTransientError::CanisterHttpPayloadValidationError(err),
-                ),
-            })
+        self.validate_canister_http_payload_impl(
+            height,
+            &payload,
+            proposal_context.validation_context,
+            delivered_ids,
+        )
+        .map(|_| ())
+        .map_err(|err| match err {
+            ValidationError::Permanent(err) => ValidationError::Permanent(
+                PayloadPermanentError::CanisterHttpPayloadValidationError(err),
+            ),
+            ValidationError::Transient(err) => ValidationError::Transient(
+                PayloadTransientError::CanisterHttpPayloadValidationError(err),
+            ),
+        })
     }
 }
 
@@ -722,6 +727,7 @@ impl IntoMessages<(Vec<Response>, CanisterHttpBatchStats)> for CanisterHttpPaylo
                 originator_reply_callback: id,
                 refund: Cycles::zero(),
                 response_payload: response,
+                deadline: NO_DEADLINE,
             })
             .collect();
 
@@ -1,9 +1,17 @@
 use crate::{consensus::PayloadValidationError, validation::ValidationResult};
 use ic_base_types::NumBytes;
 use ic_types::{
-    batch::ValidationContext, consensus::BlockPayload, crypto::CryptoHashOf, Height, Time,
+    batch::ValidationContext, consensus::BlockPayload, crypto::CryptoHashOf, Height, NodeId, Time,
 };
 
+/// The context of a block proposal, under which its payload is validated.
+pub struct ProposalContext<'a> {
+    /// The node that proposed the block
+    pub proposer: NodeId,
+    /// The [`ValidationContext`] of the block
+    pub validation_context: &'a ValidationContext,
+}
+
 /// A list of [`PastPayload`] will be passed to invocation of
 ///  [`BatchPayloadBuilder::build_payload`].
 ///
@@ -64,7 +72,7 @@ pub trait BatchPayloadBuilder: Send + Sync {
     /// - `payload`: The payload to validate
     /// - `past_payloads`: A collection of past payloads. Allows the payload builder
     ///     to deduplicate messages
-    /// - `context`: [`ValidationContext`] under which to validate the payload
+    /// - `proposal_context`: [`ProposalContext`] of the block that contains the payload
     ///
     /// # Returns
     ///
@@ -75,7 +83,7 @@ pub trait BatchPayloadBuilder: Send + Sync {
         height: Height,
         payload: &[u8],
         past_payloads: &[PastPayload],
-        context: &ValidationContext,
+        proposal_context: &ProposalContext,
     ) -> ValidationResult<PayloadValidationError>;
 }
 
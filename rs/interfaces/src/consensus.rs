@@ -1,5 +1,6 @@
 //! The consensus public interface.
 use crate::{
+    batch_payload::ProposalContext,
     canister_http::{
         CanisterHttpPayloadValidationError, CanisterHttpPermanentValidationError,
         CanisterHttpTransientValidationError,
@@ -8,6 +9,10 @@ use crate::{
         IngressPayloadValidationError, IngressPermanentError, IngressTransientError,
     },
     messaging::{InvalidXNetPayload, XNetPayloadValidationError, XNetTransientValidationError},
+    query_stats::{
+        QueryStatsPayloadValidationError, QueryStatsPermanentValidationError,
+        QueryStatsTransientValidationError,
+    },
     self_validating_payload::{
         InvalidSelfValidatingPayload, SelfValidatingPayloadValidationError,
         SelfValidatingTransientValidationError,
@@ -39,17 +44,17 @@ pub trait PayloadBuilder: Send + Sync {
     ) -> BatchPayload;
 
     /// Checks whether the provided `payload` is valid given `past_payloads` and
-    /// `context`.
+    /// the `proposal_context` of the block that contains it.
     ///
     /// `past_payloads` contains the `Payloads` from all blocks above the
-    /// certified height provided in `context`, in descending block height
-    /// order.
+    /// certified height provided in `proposal_context`, in descending block
+    /// height order.
     fn validate_payload(
         &self,
         height: Height,
         payload: &Payload,
         past_payloads: &[(Height, Time, Payload)],
-        context: &ValidationContext,
+        proposal_context: &ProposalContext,
     ) -> ValidationResult<PayloadValidationError>;
 }
 
@@ -63,6 +68,7 @@ pub enum PayloadPermanentError {
     },
     SelfValidatingPayloadValidationError(InvalidSelfValidatingPayload),
     CanisterHttpPayloadValidationError(CanisterHttpPermanentValidationError),
+    QueryStatsPayloadValidationError(QueryStatsPermanentValidationError),
 }
 
 #[derive(Debug)]
@@ -73,6 +79,7 @@ pub enum PayloadTransientError {
     SubnetNotFound(SubnetId),
     SelfValidatingPayloadValidationError(SelfValidatingTransientValidationError),
     CanisterHttpPayloadValidationError(CanisterHttpTransientValidationError),
+    QueryStatsPayloadValidationError(QueryStatsTransientValidationError),
 }
 
 /// Payload validation error
@@ -113,3 +120,12 @@ impl From<CanisterHttpPayloadValidationError> for PayloadValidationError {
         )
     }
 }
+
+impl From<QueryStatsPayloadValidationError> for PayloadValidationError {
+    fn from(err: QueryStatsPayloadValidationError) -> Self {
+        err.map(
+            PayloadPermanentError::QueryStatsPayloadValidationError,
+            PayloadTransientError::QueryStatsPayloadValidationError,
+        )
+    }
+}
@@ -140,6 +140,19 @@ pub enum HypervisorError {
         available: Cycles,
         threshold: Cycles,
     },
+    /// An attempt was made to grow the canister's Wasm memory above its
+    /// `wasm_memory_limit` setting.
+    WasmMemoryLimitExceeded {
+        bytes: NumBytes,
+        limit: NumBytes,
+    },
+    /// Growing the memory would reserve more cycles than the canister's
+    /// `reserved_cycles_limit` setting allows.
+    ReservedCyclesLimitExceededInMemoryGrow {
+        bytes: NumBytes,
+        requested: Cycles,
+        limit: Cycles,
+    },
 }
 
 impl From<WasmInstrumentationError> for HypervisorError {
@@ -333,6 +346,28 @@ impl HypervisorError {
                      bytes,
                      threshold - available)
             ),
+            Self::WasmMemoryLimitExceeded { bytes, limit } => UserError::new(
+                E::CanisterWasmMemoryLimitExceeded,
+                format!(
+                    "Canister {} exceeded its Wasm memory limit: growing the Wasm memory to {} bytes \
+                     would exceed the limit of {} bytes.",
+                    canister_id, bytes, limit
+                ),
+            ),
+            Self::ReservedCyclesLimitExceededInMemoryGrow {
+                bytes,
+                requested,
+                limit,
+            } => UserError::new(
+                E::ReservedCyclesLimitExceededInMemoryGrow,
+                format!(
+                    "Canister cannot grow memory by {} bytes due to its reserved cycles limit. \
+                     The current limit ({}) would be exceeded by {}.",
+                    bytes,
+                    limit,
+                    requested - limit
+                ),
+            ),
         }
     }
 
@@ -366,6 +401,10 @@ impl HypervisorError {
             HypervisorError::InsufficientCyclesInMemoryGrow { .. } => {
                 "InsufficientCyclesInMemoryGrow"
             }
+            HypervisorError::WasmMemoryLimitExceeded { .. } => "WasmMemoryLimitExceeded",
+            HypervisorError::ReservedCyclesLimitExceededInMemoryGrow { .. } => {
+                "ReservedCyclesLimitExceededInMemoryGrow"
+            }
         }
     }
 }
@@ -1,8 +1,9 @@
 //! Messages used in various components.
 use ic_ic00_types::CanisterChangeOrigin;
 use ic_types::{
-    messages::{Ingress, Request, Response, StopCanisterContext},
+    messages::{Ingress, Request, Response, StopCanisterContext, NO_DEADLINE},
     methods::SystemMethod,
+    time::CoarseTime,
     CanisterId, Cycles, PrincipalId,
 };
 use std::{convert::TryFrom, sync::Arc};
@@ -79,6 +80,15 @@ impl CanisterCall {
         }
     }
 
+    /// Returns the deadline of the call, `NO_DEADLINE` for ingress messages
+    /// and guaranteed response calls.
+    pub fn deadline(&self) -> CoarseTime {
+        match self {
+            CanisterCall::Request(request) => request.deadline,
+            CanisterCall::Ingress(_) => NO_DEADLINE,
+        }
+    }
+
     /// Extracts the cycles received with this message.
     pub fn take_cycles(&mut self) -> Cycles {
         match self {
@@ -127,11 +137,13 @@ impl TryFrom<CanisterMessage> for CanisterCall {
 }
 
 /// A canister task can be thought of as a special system message that the IC
-/// sends to the canister to execute its heartbeat or the global timer method.
+/// sends to the canister to execute its heartbeat, global timer or low Wasm
+/// memory hook method.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CanisterTask {
     Heartbeat,
     GlobalTimer,
+    OnLowWasmMemory,
 }
 
 impl From<CanisterTask> for SystemMethod {
@@ -139,6 +151,7 @@ impl From<CanisterTask> for SystemMethod {
         match task {
             CanisterTask::Heartbeat => SystemMethod::CanisterHeartbeat,
             CanisterTask::GlobalTimer => SystemMethod::CanisterGlobalTimer,
+            CanisterTask::OnLowWasmMemory => SystemMethod::CanisterOnLowWasmMemory,
         }
     }
 }
@@ -148,6 +161,7 @@ impl Display for CanisterTask {
         match self {
             Self::Heartbeat => write!(f, "Heartbeat task"),
             Self::GlobalTimer => write!(f, "Global timer task"),
+            Self::OnLowWasmMemory => write!(f, "On low Wasm memory task"),
         }
     }
 }
@@ -0,0 +1,37 @@
+//! Query statistics related public interfaces.
+use crate::validation::ValidationError;
+use ic_protobuf::proxy::ProxyDecodeError;
+use ic_types::{batch::QueryStatsEpoch, NodeId};
+
+/// A permanent error that can occur during validation of a
+/// [`QueryStatsPayload`](ic_types::batch::QueryStatsPayload).
+#[derive(Debug)]
+pub enum QueryStatsPermanentValidationError {
+    /// The payload could not be deserialized
+    DeserializationFailed(ProxyDecodeError),
+    /// The payload reports statistics for an epoch that is not over yet
+    EpochNotOver {
+        current_epoch: QueryStatsEpoch,
+        received: QueryStatsEpoch,
+    },
+    /// The proposer already reported its statistics for this epoch, or the
+    /// epoch was already aggregated
+    DuplicateReport(QueryStatsEpoch),
+    /// The proposer is not a member of the subnet
+    ProposerNotMember(NodeId),
+    /// The payload reports statistics of a node other than the block proposer
+    InvalidNodeId { expected: NodeId, reported: NodeId },
+}
+
+/// A transient error that can occur during validation of a
+/// [`QueryStatsPayload`](ic_types::batch::QueryStatsPayload).
+#[derive(Debug)]
+pub enum QueryStatsTransientValidationError {
+    /// The state was not available at the time of validation
+    StateUnavailable,
+    /// The feature is not enabled
+    Disabled,
+}
+
+pub type QueryStatsPayloadValidationError =
+    ValidationError<QueryStatsPermanentValidationError, QueryStatsTransientValidationError>;
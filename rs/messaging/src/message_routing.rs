@@ -4,6 +4,7 @@ use crate::{
 };
 use ic_config::execution_environment::{BitcoinConfig, Config as HypervisorConfig};
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
+use ic_crypto_utils_basic_sig::conversions::node_signing_public_key_to_der;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_ic00_types::EcdsaKeyId;
 use ic_interfaces::crypto::ErrorReproducibility;
@@ -31,10 +32,11 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{NetworkTopology, ReplicatedState, SubnetTopology};
 use ic_types::{
     batch::Batch,
+    crypto::KeyPurpose,
     malicious_flags::MaliciousFlags,
     registry::RegistryClientError,
     xnet::{StreamHeader, StreamIndex},
-    Height, NumBytes, PrincipalIdBlobParseError, RegistryVersion, SubnetId,
+    Height, NodeId, NumBytes, PrincipalIdBlobParseError, RegistryVersion, SubnetId,
 };
 use ic_utils::thread::JoinOnDrop;
 #[cfg(test)]
@@ -76,6 +78,8 @@ const PHASE_COMMIT: &str = "commit";
 const METRIC_PROCESS_BATCH_DURATION: &str = "mr_process_batch_duration_seconds";
 const METRIC_PROCESS_BATCH_PHASE_DURATION: &str = "mr_process_batch_phase_duration_seconds";
 const METRIC_TIMED_OUT_REQUESTS_TOTAL: &str = "mr_timed_out_requests_total";
+const METRIC_EXPIRED_CALLBACKS_TOTAL: &str = "mr_expired_callbacks_total";
+const METRIC_SHED_REQUESTS_TOTAL: &str = "mr_shed_requests_total";
 
 const METRIC_WASM_CUSTOM_SECTIONS_MEMORY_USAGE_BYTES: &str =
     "mr_wasm_custom_sections_memory_usage_bytes";
@@ -85,6 +89,8 @@ const METRIC_CANISTER_HISTORY_TOTAL_NUM_CHANGES: &str = "mr_canister_history_tot
 const CRITICAL_ERROR_MISSING_SUBNET_SIZE: &str = "cycles_account_manager_missing_subnet_size_error";
 const CRITICAL_ERROR_NO_CANISTER_ALLOCATION_RANGE: &str = "mr_empty_canister_allocation_range";
 const CRITICAL_ERROR_FAILED_TO_READ_REGISTRY: &str = "mr_failed_to_read_registry_error";
+const CRITICAL_ERROR_MISSING_OR_INVALID_NODE_PUBLIC_KEYS: &str =
+    "mr_missing_or_invalid_node_public_keys";
 
 /// Records the timestamp when all messages before the given index (down to the
 /// previous `MessageTime`) were first added to / learned about in a stream.
@@ -274,8 +280,15 @@ pub(crate) struct MessageRoutingMetrics {
     critical_error_no_canister_allocation_range: IntCounter,
     /// Critical error: reading from the registry failed during processing a batch.
     critical_error_failed_to_read_registry: IntCounter,
+    /// Critical error: the public key of a node of this subnet is missing from
+    /// the registry or could not be DER-encoded.
+    critical_error_missing_or_invalid_node_public_keys: IntCounter,
     /// Number of timed out requests.
     pub timed_out_requests_total: IntCounter,
+    /// Number of expired best-effort callbacks.
+    pub expired_callbacks_total: IntCounter,
+    /// Number of best-effort requests shed due to memory pressure.
+    pub shed_requests_total: IntCounter,
 }
 
 impl MessageRoutingMetrics {
@@ -335,10 +348,20 @@ impl MessageRoutingMetrics {
                 .error_counter(CRITICAL_ERROR_NO_CANISTER_ALLOCATION_RANGE),
             critical_error_failed_to_read_registry: metrics_registry
                 .error_counter(CRITICAL_ERROR_FAILED_TO_READ_REGISTRY),
+            critical_error_missing_or_invalid_node_public_keys: metrics_registry
+                .error_counter(CRITICAL_ERROR_MISSING_OR_INVALID_NODE_PUBLIC_KEYS),
             timed_out_requests_total: metrics_registry.int_counter(
                 METRIC_TIMED_OUT_REQUESTS_TOTAL,
                 "Count of timed out requests.",
             ),
+            expired_callbacks_total: metrics_registry.int_counter(
+                METRIC_EXPIRED_CALLBACKS_TOTAL,
+                "Count of expired best-effort callbacks.",
+            ),
+            shed_requests_total: metrics_registry.int_counter(
+                METRIC_SHED_REQUESTS_TOTAL,
+                "Count of best-effort requests shed due to memory pressure.",
+            ),
         }
     }
 
@@ -385,6 +408,16 @@ struct BatchProcessorImpl {
     malicious_flags: MaliciousFlags,
 }
 
+/// The registry contents required by `BatchProcessorImpl::process_batch()`:
+/// the network topology, the features of this subnet, the execution settings
+/// and the DER-encoded public keys of the nodes of this subnet.
+type RegistryContents = (
+    NetworkTopology,
+    SubnetFeatures,
+    RegistryExecutionSettings,
+    BTreeMap<NodeId, Vec<u8>>,
+);
+
 /// Errors that can occur when reading from the registry.
 #[derive(Debug)]
 enum ReadRegistryError {
@@ -503,7 +536,7 @@ impl BatchProcessorImpl {
         &self,
         registry_version: RegistryVersion,
         own_subnet_id: SubnetId,
-    ) -> (NetworkTopology, SubnetFeatures, RegistryExecutionSettings) {
+    ) -> RegistryContents {
         loop {
             match self.try_to_read_registry(registry_version, own_subnet_id) {
                 Ok(result) => return result,
@@ -524,19 +557,25 @@ impl BatchProcessorImpl {
         }
     }
 
-    /// Loads the `NetworkTopology`, `SubnetFeatures` and execution settings from
-    /// the registry.
+    /// Loads the `NetworkTopology`, `SubnetFeatures`, execution settings and
+    /// node public keys from the registry.
     ///
     /// All of the above are required for deterministic processing, so if any
     /// entry is missing or cannot be decoded; or reading the registry fails; the
-    /// call fails and returns an error.
+    /// call fails and returns an error. The only exception are node public keys,
+    /// which are skipped if missing or not DER-encodable.
     fn try_to_read_registry(
         &self,
         registry_version: RegistryVersion,
         own_subnet_id: SubnetId,
-    ) -> Result<(NetworkTopology, SubnetFeatures, RegistryExecutionSettings), ReadRegistryError>
-    {
+    ) -> Result<RegistryContents, ReadRegistryError> {
         let network_topology = self.try_to_populate_network_topology(registry_version)?;
+        let node_public_keys = match network_topology.subnets.get(&own_subnet_id) {
+            Some(subnet_topology) => {
+                self.try_to_populate_node_public_keys(&subnet_topology.nodes, registry_version)?
+            }
+            None => BTreeMap::new(),
+        };
 
         let provisional_whitelist = self
             .registry
@@ -581,9 +620,63 @@ impl BatchProcessorImpl {
                 max_ecdsa_queue_size,
                 subnet_size,
             },
+            node_public_keys,
         ))
     }
 
+    /// Loads the DER-encoded node signing public keys of `nodes` from the
+    /// registry at a specific version.
+    ///
+    /// Nodes whose public key is missing from the registry or cannot be
+    /// DER-encoded are skipped, so that they cannot prevent the subnet from
+    /// making progress.
+    fn try_to_populate_node_public_keys(
+        &self,
+        nodes: &BTreeSet<NodeId>,
+        registry_version: RegistryVersion,
+    ) -> Result<BTreeMap<NodeId, Vec<u8>>, ReadRegistryError> {
+        let mut node_public_keys = BTreeMap::new();
+        for node_id in nodes {
+            let public_key = self
+                .registry
+                .get_crypto_key_for_node(*node_id, KeyPurpose::NodeSigning, registry_version)
+                .map_err(|err| {
+                    registry_error(&format!("public key of node {}", node_id), None, err)
+                })?;
+            match public_key.map(|public_key| node_signing_public_key_to_der(&public_key)) {
+                Some(Ok(public_key_der)) => {
+                    node_public_keys.insert(*node_id, public_key_der);
+                }
+                Some(Err(err)) => {
+                    self.metrics
+                        .critical_error_missing_or_invalid_node_public_keys
+                        .inc();
+                    warn!(
+                        self.log,
+                        "{}: Invalid public key of node {} @ version {}: {:?}",
+                        CRITICAL_ERROR_MISSING_OR_INVALID_NODE_PUBLIC_KEYS,
+                        node_id,
+                        registry_version,
+                        err
+                    );
+                }
+                None => {
+                    self.metrics
+                        .critical_error_missing_or_invalid_node_public_keys
+                        .inc();
+                    warn!(
+                        self.log,
+                        "{}: Public key of node {} not found @ version {}",
+                        CRITICAL_ERROR_MISSING_OR_INVALID_NODE_PUBLIC_KEYS,
+                        node_id,
+                        registry_version
+                    );
+                }
+            }
+        }
+        Ok(node_public_keys)
+    }
+
     /// Tries to populate a `NetworkTopology` from the registry at a specific version.
     fn try_to_populate_network_topology(
         &self,
@@ -764,8 +857,9 @@ impl BatchProcessor for BatchProcessorImpl {
         // TODO (MR-29) Cache network topology and subnet_features; and populate only
         // if version referenced in batch changes.
         let registry_version = batch.registry_version;
-        let (network_topology, subnet_features, registry_execution_settings) =
+        let (network_topology, subnet_features, registry_execution_settings, node_public_keys) =
             self.read_registry(registry_version, state.metadata.own_subnet_id);
+        state.metadata.node_public_keys = node_public_keys;
 
         let mut state_after_round = self.state_machine.execute_round(
             state,
@@ -979,6 +1073,7 @@ impl MessageRoutingImpl {
             scheduler,
             demux,
             stream_builder,
+            hypervisor_config.best_effort_message_memory_capacity,
             log.clone(),
             Arc::clone(&metrics),
         ));
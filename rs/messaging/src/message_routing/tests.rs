@@ -4,6 +4,9 @@ use ic_ic00_types::{EcdsaCurve, EcdsaKeyId};
 use ic_interfaces_registry::RegistryValue;
 use ic_interfaces_state_manager::StateReader;
 use ic_interfaces_state_manager_mocks::MockStateManager;
+use ic_protobuf::registry::crypto::v1::{
+    AlgorithmId as AlgorithmIdProto, PublicKey as PublicKeyProto,
+};
 use ic_protobuf::registry::subnet::v1::SubnetRecord as SubnetRecordProto;
 use ic_registry_client_fake::FakeRegistryClient;
 use ic_registry_proto_data_provider::{ProtoRegistryDataProvider, ProtoRegistryDataProviderError};
@@ -252,6 +255,20 @@ impl RegistryFixture {
         )
     }
 
+    /// Writes the node signing public key of a node into the registry.
+    fn write_node_public_key(
+        &self,
+        node_id: NodeId,
+        public_key: Integrity<&PublicKeyProto>,
+    ) -> Result<(), ProtoRegistryDataProviderError> {
+        use ic_registry_keys::make_crypto_node_key;
+
+        self.write_record(
+            &make_crypto_node_key(node_id, KeyPurpose::NodeSigning),
+            public_key.map(|pk| pk.clone()),
+        )
+    }
+
     /// Writes the subnet record corresponding to a subnet id into the registry.
     fn write_subnet_record(
         &self,
@@ -467,7 +484,7 @@ fn try_to_read_registry(
     registry: Arc<FakeRegistryClient>,
     log: ReplicaLogger,
     own_subnet_id: SubnetId,
-) -> Result<(NetworkTopology, SubnetFeatures, RegistryExecutionSettings), ReadRegistryError> {
+) -> Result<RegistryContents, ReadRegistryError> {
     let (batch_processor, _, _, _) = make_batch_processor(registry.clone(), log);
     batch_processor.try_to_read_registry(registry.get_latest_version(), own_subnet_id)
 }
@@ -572,18 +589,44 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
                 canister_migrations: Valid(&canister_migrations),
             })
             .unwrap();
+        // Only the first node of `own_subnet_id` has a public key in the registry.
+        let node_public_key = PublicKeyProto {
+            algorithm: AlgorithmIdProto::Ed25519 as i32,
+            key_value: vec![7; 32],
+            ..Default::default()
+        };
+        fixture
+            .write_node_public_key(node_test_id(1), Valid(&node_public_key))
+            .unwrap();
+        fixture.registry.update_to_latest_version();
 
         // Reading from the registry must succeed for fully specified records.
         let (batch_processor, metrics, state_manager, registry_settings) =
             make_batch_processor(fixture.registry.clone(), log);
-        let (network_topology, own_subnet_features, registry_execution_settings) = batch_processor
-            .try_to_read_registry(fixture.registry.get_latest_version(), own_subnet_id)
-            .unwrap();
+        let (network_topology, own_subnet_features, registry_execution_settings, node_public_keys) =
+            batch_processor
+                .try_to_read_registry(fixture.registry.get_latest_version(), own_subnet_id)
+                .unwrap();
 
         // Full specification includes the subnet size of `own_subnet_id`. Check the corresponding
         // critical error counter is untouched.
         assert_eq!(metrics.critical_error_missing_subnet_size.get(), 0);
 
+        // Check node public keys; the missing public key of the second node is reported as a
+        // critical error.
+        assert_eq!(
+            btreemap! {
+                node_test_id(1) => node_signing_public_key_to_der(&node_public_key).unwrap(),
+            },
+            node_public_keys
+        );
+        assert_eq!(
+            metrics
+                .critical_error_missing_or_invalid_node_public_keys
+                .get(),
+            1
+        );
+
         // Check network topology.
         assert_eq!(network_topology.subnets.len(), 2);
         for (subnet_id, subnet_record, transcript) in [
@@ -669,6 +712,7 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
             *registry_settings.lock().unwrap(),
             registry_execution_settings,
         );
+        assert_ne!(node_public_keys, latest_state.metadata.node_public_keys);
         batch_processor.process_batch(Batch {
             batch_number: height.increment().increment(),
             requires_full_state_hash: false,
@@ -689,6 +733,7 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
             *registry_settings.lock().unwrap(),
             registry_execution_settings,
         );
+        assert_eq!(node_public_keys, latest_state.metadata.node_public_keys);
     });
 }
 
@@ -734,7 +779,7 @@ fn try_read_registry_succeeds_with_minimal_registry_records() {
         // critical error for `subnet_size` has incremented.
         assert_eq!(metrics.critical_error_missing_subnet_size.get(), 1);
         // Check the subnet size was set to the maximum for a small app subnet.
-        let (_, _, registry_execution_settings) = result.unwrap();
+        let (_, _, registry_execution_settings, _) = result.unwrap();
         assert_eq!(
             registry_execution_settings.subnet_size,
             SMALL_APP_SUBNET_MAX_SIZE
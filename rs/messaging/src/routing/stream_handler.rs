@@ -77,6 +77,7 @@ const LABEL_VALUE_SUCCESS: &str = "success";
 const LABEL_VALUE_SENDER_SUBNET_MISMATCH: &str = "SenderSubnetMismatch";
 const LABEL_VALUE_RECEIVER_SUBNET_MISMATCH: &str = "ReceiverSubnetMismatch";
 const LABEL_VALUE_CANISTER_MIGRATED: &str = "CanisterMigrated";
+const LABEL_VALUE_DEADLINE_EXPIRED: &str = "DeadlineExpired";
 const LABEL_TYPE: &str = "type";
 const LABEL_VALUE_TYPE_REQUEST: &str = "request";
 const LABEL_VALUE_TYPE_RESPONSE: &str = "response";
@@ -594,7 +595,22 @@ impl StreamHandlerImpl {
                 .route(msg.receiver().get());
 
             let payload_size = msg.payload_size_bytes().get();
+            let has_expired_deadline = matches!(
+                &msg,
+                RequestOrResponse::Request(request) if request.has_expired_deadline(state.time())
+            );
             match receiver_host_subnet {
+                // Matching receiver subnet, but the deadline of the best-effort
+                // request has already passed: reject it without inducting it.
+                Some(host_subnet) if host_subnet == self.subnet_id && has_expired_deadline => {
+                    self.observe_inducted_message_status(msg_type, LABEL_VALUE_DEADLINE_EXPIRED);
+                    stream.push(generate_reject_response(
+                        msg,
+                        RejectCode::SysUnknown,
+                        "Request deadline expired.".to_string(),
+                    ));
+                }
+
                 // Matching receiver subnet, try inducting message.
                 Some(host_subnet) if host_subnet == self.subnet_id => match state
                     .push_input(msg, subnet_available_memory)
@@ -847,6 +863,7 @@ fn generate_reject_response(
                 message,
                 MR_SYNTHETIC_REJECT_MESSAGE_MAX_LEN,
             )),
+            deadline: msg.deadline,
         }
         .into()
     } else {
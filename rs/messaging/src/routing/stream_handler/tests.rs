@@ -30,7 +30,7 @@ use ic_test_utilities_metrics::{
     fetch_int_gauge_vec, metric_vec, nonzero_values, HistogramStats, MetricVec,
 };
 use ic_types::{
-    messages::{CallbackId, Payload, Request, MAX_RESPONSE_COUNT_BYTES},
+    messages::{CallbackId, Payload, Request, MAX_RESPONSE_COUNT_BYTES, NO_DEADLINE},
     xnet::{testing::StreamSliceTesting, StreamIndex, StreamIndexedQueue},
     CanisterId, Cycles,
 };
@@ -1280,6 +1280,7 @@ fn generate_reject_response_queue_full() {
                 RejectCode::SysTransient,
                 err.to_string(),
             )),
+            deadline: NO_DEADLINE,
         }
         .into(),
     );
@@ -1320,6 +1321,7 @@ fn generate_reject_response_canister_not_found() {
                 RejectCode::DestinationInvalid,
                 err.to_string(),
             )),
+            deadline: NO_DEADLINE,
         }
         .into(),
     );
@@ -5,9 +5,10 @@ use ic_interfaces::execution_environment::{
 };
 use ic_logger::{fatal, ReplicaLogger};
 use ic_metrics::Timer;
+use ic_query_stats::deliver_query_stats;
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_replicated_state::{NetworkTopology, ReplicatedState};
-use ic_types::{batch::Batch, ExecutionRound};
+use ic_types::{batch::Batch, ExecutionRound, NumBytes};
 use std::sync::Arc;
 
 #[cfg(test)]
@@ -17,6 +18,7 @@ const PHASE_INDUCTION: &str = "induction";
 const PHASE_EXECUTION: &str = "execution";
 const PHASE_MESSAGE_ROUTING: &str = "message_routing";
 const PHASE_TIME_OUT_REQUESTS: &str = "time_out_requests";
+const PHASE_SHED_BEST_EFFORT_MESSAGES: &str = "shed_best_effort_messages";
 
 pub(crate) trait StateMachine: Send {
     fn execute_round(
@@ -32,6 +34,9 @@ pub(crate) struct StateMachineImpl {
     scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
     demux: Box<dyn Demux>,
     stream_builder: Box<dyn StreamBuilder>,
+    /// Limit on the total byte size of best-effort requests, above which
+    /// best-effort requests are shed.
+    best_effort_message_memory_capacity: NumBytes,
     log: ReplicaLogger,
     metrics: Arc<MessageRoutingMetrics>,
 }
@@ -41,6 +46,7 @@ impl StateMachineImpl {
         scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
         demux: Box<dyn Demux>,
         stream_builder: Box<dyn StreamBuilder>,
+        best_effort_message_memory_capacity: NumBytes,
         log: ReplicaLogger,
         metrics: Arc<MessageRoutingMetrics>,
     ) -> Self {
@@ -48,6 +54,7 @@ impl StateMachineImpl {
             scheduler,
             demux,
             stream_builder,
+            best_effort_message_memory_capacity,
             log,
             metrics,
         }
@@ -90,11 +97,22 @@ impl StateMachine for StateMachineImpl {
             )
         }
 
+        // Aggregate the query statistics reported in the batch, if any.
+        if let Some(query_stats) = &batch.messages.query_stats {
+            deliver_query_stats(query_stats, &mut state, &self.log);
+        }
+
         // Time out requests.
         let timed_out_requests = state.time_out_requests(batch.time);
         self.metrics
             .timed_out_requests_total
             .inc_by(timed_out_requests);
+
+        // Expire best-effort callbacks.
+        let expired_callbacks = state.expire_callbacks(batch.time);
+        self.metrics
+            .expired_callbacks_total
+            .inc_by(expired_callbacks);
         self.observe_phase_duration(PHASE_TIME_OUT_REQUESTS, &phase_timer);
 
         // Preprocess messages and add messages to the induction pool through the Demux.
@@ -116,7 +134,7 @@ impl StateMachine for StateMachineImpl {
 
         let phase_timer = Timer::start();
         // Process messages from the induction pool through the Scheduler.
-        let state_after_execution = self.scheduler.execute_round(
+        let mut state_after_execution = self.scheduler.execute_round(
             state_with_messages,
             batch.randomness,
             batch.ecdsa_subnet_public_keys,
@@ -126,6 +144,13 @@ impl StateMachine for StateMachineImpl {
         );
         self.observe_phase_duration(PHASE_EXECUTION, &phase_timer);
 
+        let phase_timer = Timer::start();
+        // Shed best-effort requests if above the best-effort message memory limit.
+        let shed_requests = state_after_execution
+            .shed_best_effort_messages(self.best_effort_message_memory_capacity.get() as usize);
+        self.metrics.shed_requests_total.inc_by(shed_requests);
+        self.observe_phase_duration(PHASE_SHED_BEST_EFFORT_MESSAGES, &phase_timer);
+
         let phase_timer = Timer::start();
         // Postprocess the state and consolidate the Streams.
         let state_after_stream_builder = self.stream_builder.build_streams(state_after_execution);
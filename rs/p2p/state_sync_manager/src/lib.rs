@@ -7,8 +7,10 @@
 //!    - Adding peers to ongoing state sync if they advertise the same state.
 //!
 //! API:
-//!    - `/chunk` route takes `pb::GossipChunkRequest` and responds with `pb::ArtifactChunk`
-//!      if the chunk was found. It responds with NOT_FOUND if the chunk is not available.
+//!    - `/chunk` route takes `pb::StateSyncChunkRequest` and responds with
+//!      `pb::StateSyncChunkResponse` if the chunk was found. The chunk data is compressed
+//!      if the requester accepts a compression supported by the responder and compression
+//!      makes the chunk smaller. It responds with NO_CONTENT if the chunk is not available.
 //!    - `/advert` accepts `pb::GossipAdvert` and returns nothing.
 //!
 //! GUARANTEES:
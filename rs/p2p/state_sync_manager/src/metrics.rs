@@ -16,6 +16,12 @@ const CHUNK_DOWNLOAD_STATUS_LABEL: &str = "status";
 const CHUNK_DOWNLOAD_STATUS_MORE_NEEDED: &str = "more_needed";
 const CHUNK_DOWNLOAD_STATUS_SUCCESS: &str = "success";
 
+const CHUNK_BYTES_LABEL: &str = "bytes";
+/// Size of the chunk data before compression.
+pub(crate) const CHUNK_BYTES_LOGICAL: &str = "logical";
+/// Size of the chunk as sent over the network.
+pub(crate) const CHUNK_BYTES_WIRE: &str = "wire";
+
 #[derive(Debug, Clone)]
 pub(crate) struct StateSyncManagerMetrics {
     pub state_syncs_total: IntCounter,
@@ -46,6 +52,7 @@ impl StateSyncManagerMetrics {
 #[derive(Debug, Clone)]
 pub struct StateSyncManagerHandlerMetrics {
     pub request_duration: HistogramVec,
+    pub served_chunk_bytes_total: IntCounterVec,
 }
 
 impl StateSyncManagerHandlerMetrics {
@@ -58,6 +65,11 @@ impl StateSyncManagerHandlerMetrics {
                 exponential_buckets(0.001, 10.0, 4).unwrap(),
                 &[HANDLER_LABEL],
             ),
+            served_chunk_bytes_total: metrics_registry.int_counter_vec(
+                "state_sync_manager_served_chunk_bytes_total",
+                "Total size of the chunks served to peers, logical and on the wire.",
+                &[CHUNK_BYTES_LABEL],
+            ),
         }
     }
 }
@@ -70,6 +82,7 @@ pub(crate) struct OngoingStateSyncMetrics {
     pub peers_serving_state: IntGauge,
     pub chunk_download_duration: Histogram,
     pub chunk_download_results_total: IntCounterVec,
+    pub downloaded_chunk_bytes_total: IntCounterVec,
 }
 
 impl OngoingStateSyncMetrics {
@@ -107,6 +120,11 @@ impl OngoingStateSyncMetrics {
                 "Chunk download request results.",
                 &[CHUNK_DOWNLOAD_STATUS_LABEL],
             ),
+            downloaded_chunk_bytes_total: metrics_registry.int_counter_vec(
+                "state_sync_manager_downloaded_chunk_bytes_total",
+                "Total size of the chunks downloaded from peers, logical and on the wire.",
+                &[CHUNK_BYTES_LABEL],
+            ),
         }
     }
 
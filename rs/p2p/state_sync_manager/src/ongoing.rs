@@ -16,7 +16,7 @@ use std::{
     time::Duration,
 };
 
-use crate::metrics::OngoingStateSyncMetrics;
+use crate::metrics::{OngoingStateSyncMetrics, CHUNK_BYTES_LOGICAL, CHUNK_BYTES_WIRE};
 use crate::routes::{build_chunk_handler_request, parse_chunk_handler_response};
 
 use ic_async_utils::JoinMap;
@@ -26,7 +26,7 @@ use ic_quic_transport::Transport;
 use ic_types::{
     artifact::{Artifact, StateSyncArtifactId, StateSyncMessage},
     chunkable::ChunkId,
-    chunkable::{ArtifactErrorCode, Chunkable},
+    chunkable::{ArtifactChunkData, ArtifactErrorCode, Chunkable},
     NodeId,
 };
 use rand::{rngs::SmallRng, Rng, SeedableRng};
@@ -286,7 +286,18 @@ impl OngoingStateSync {
             Err(_) => Err(DownloadChunkError::Timeout),
         }?;
 
+        let wire_size = response.body().len();
         let chunk = parse_chunk_handler_response(response, chunk_id)?;
+        if let ArtifactChunkData::SemiStructuredChunkData(data) = &chunk.artifact_chunk_data {
+            metrics
+                .downloaded_chunk_bytes_total
+                .with_label_values(&[CHUNK_BYTES_LOGICAL])
+                .inc_by(data.len() as u64);
+        }
+        metrics
+            .downloaded_chunk_bytes_total
+            .with_label_values(&[CHUNK_BYTES_WIRE])
+            .inc_by(wire_size as u64);
 
         // TODO: This should be done in a threadpool of size 1.
         let chunk_add_result =
@@ -1,6 +1,11 @@
-use std::sync::Arc;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
 
-use crate::metrics::{StateSyncManagerHandlerMetrics, CHUNK_HANDLER_LABEL};
+use crate::metrics::{
+    StateSyncManagerHandlerMetrics, CHUNK_BYTES_LOGICAL, CHUNK_BYTES_WIRE, CHUNK_HANDLER_LABEL,
+};
 use crate::ongoing::DownloadChunkError;
 use axum::{
     body::Bytes,
@@ -8,18 +13,30 @@ use axum::{
     http::{Request, Response, StatusCode},
 };
 use bytes::BytesMut;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use ic_interfaces::state_sync_client::StateSyncClient;
 use ic_logger::ReplicaLogger;
 use ic_protobuf::p2p::v1 as pb;
 use ic_types::{
     artifact::StateSyncArtifactId,
     chunkable::{ArtifactChunk, ChunkId},
+    state_sync::DEFAULT_CHUNK_SIZE,
     NodeId,
 };
 use prost::Message;
 
 pub const STATE_SYNC_CHUNK_PATH: &str = "/chunk";
 
+/// Compressions this node is able to decompress, in order of preference.
+const ACCEPTED_COMPRESSIONS: [pb::StateSyncChunkCompression; 1] =
+    [pb::StateSyncChunkCompression::Deflate];
+
+/// Upper bound on the size of a decompressed chunk. Chunks produced by the
+/// state manager are at most `DEFAULT_CHUNK_SIZE` large plus some encoding
+/// overhead, so this only rejects malicious responses. Larger chunks are
+/// never compressed.
+const MAX_DECOMPRESSED_CHUNK_SIZE: usize = 4 * DEFAULT_CHUNK_SIZE as usize;
+
 pub(crate) struct StateSyncChunkHandler {
     _log: ReplicaLogger,
     state_sync: Arc<dyn StateSyncClient>,
@@ -51,27 +68,105 @@ pub(crate) async fn state_sync_chunk_handler(
         .start_timer();
 
     // Parse payload
-    let pb::StateSyncChunkRequest { id, chunk_id } =
-        pb::StateSyncChunkRequest::decode(payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let pb::StateSyncChunkRequest {
+        id,
+        chunk_id,
+        accepted_compressions,
+    } = pb::StateSyncChunkRequest::decode(payload).map_err(|_| StatusCode::BAD_REQUEST)?;
     let artifact_id: StateSyncArtifactId = id.map(From::from).ok_or(StatusCode::BAD_REQUEST)?;
     let chunk_id = ChunkId::from(chunk_id);
+    let compression = negotiate_compression(&accepted_compressions);
 
     // TODO: (NET-1442) move this to threadpool
+    let state_c = state.clone();
     let jh = tokio::task::spawn_blocking(move || {
-        state
+        let chunk = state_c
             .state_sync
             .chunk(&artifact_id, chunk_id)
-            .ok_or(StatusCode::NO_CONTENT)
+            .ok_or(StatusCode::NO_CONTENT)?;
+        let pb_chunk: pb::StateSyncChunkResponse = chunk.into();
+        let logical_size = pb_chunk.data.len();
+        Ok((compress_chunk(pb_chunk, compression), logical_size))
     });
-    let chunk = jh.await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)??;
+    let (pb_chunk, logical_size) = jh.await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)??;
 
-    let pb_chunk: pb::StateSyncChunkResponse = chunk.into();
     let mut raw = BytesMut::with_capacity(pb_chunk.encoded_len());
     pb_chunk.encode(&mut raw).expect("Allocated enough memory");
 
+    state
+        .metrics
+        .served_chunk_bytes_total
+        .with_label_values(&[CHUNK_BYTES_LOGICAL])
+        .inc_by(logical_size as u64);
+    state
+        .metrics
+        .served_chunk_bytes_total
+        .with_label_values(&[CHUNK_BYTES_WIRE])
+        .inc_by(raw.len() as u64);
+
     Ok(raw.into())
 }
 
+/// Picks the compression to use for a response: the first compression
+/// accepted by the requester that this node supports, if any.
+fn negotiate_compression(accepted_compressions: &[i32]) -> pb::StateSyncChunkCompression {
+    accepted_compressions
+        .iter()
+        .filter_map(|compression| pb::StateSyncChunkCompression::from_i32(*compression))
+        .find(|compression| ACCEPTED_COMPRESSIONS.contains(compression))
+        .unwrap_or(pb::StateSyncChunkCompression::Unspecified)
+}
+
+/// Compresses the data of `chunk` with `compression`. The chunk is left
+/// uncompressed if compression does not make it smaller.
+fn compress_chunk(
+    chunk: pb::StateSyncChunkResponse,
+    compression: pb::StateSyncChunkCompression,
+) -> pb::StateSyncChunkResponse {
+    if chunk.data.len() > MAX_DECOMPRESSED_CHUNK_SIZE {
+        return chunk;
+    }
+    let compressed = match compression {
+        pb::StateSyncChunkCompression::Unspecified => return chunk,
+        pb::StateSyncChunkCompression::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+            encoder
+                .write_all(&chunk.data)
+                .and_then(|_| encoder.finish())
+                .expect("Writing to a vector cannot fail")
+        }
+    };
+    if compressed.len() >= chunk.data.len() {
+        return chunk;
+    }
+    pb::StateSyncChunkResponse {
+        data: compressed,
+        compression: compression as i32,
+    }
+}
+
+/// Returns the uncompressed data of `chunk`.
+fn decompress_chunk(chunk: pb::StateSyncChunkResponse) -> Result<Vec<u8>, String> {
+    match pb::StateSyncChunkCompression::from_i32(chunk.compression) {
+        Some(pb::StateSyncChunkCompression::Unspecified) => Ok(chunk.data),
+        Some(pb::StateSyncChunkCompression::Deflate) => {
+            let mut data = Vec::new();
+            DeflateDecoder::new(chunk.data.as_slice())
+                .take(MAX_DECOMPRESSED_CHUNK_SIZE as u64 + 1)
+                .read_to_end(&mut data)
+                .map_err(|e| format!("Failed to decompress chunk: {}", e))?;
+            if data.len() > MAX_DECOMPRESSED_CHUNK_SIZE {
+                return Err(format!(
+                    "Decompressed chunk exceeds the maximum size of {} bytes",
+                    MAX_DECOMPRESSED_CHUNK_SIZE
+                ));
+            }
+            Ok(data)
+        }
+        None => Err(format!("Unknown chunk compression {}", chunk.compression)),
+    }
+}
+
 pub(crate) fn build_chunk_handler_request(
     artifact_id: StateSyncArtifactId,
     chunk_id: ChunkId,
@@ -79,6 +174,10 @@ pub(crate) fn build_chunk_handler_request(
     let pb = pb::StateSyncChunkRequest {
         id: Some(artifact_id.into()),
         chunk_id: chunk_id.get(),
+        accepted_compressions: ACCEPTED_COMPRESSIONS
+            .iter()
+            .map(|compression| *compression as i32)
+            .collect(),
     };
 
     let mut raw = BytesMut::with_capacity(pb.encoded_len());
@@ -110,12 +209,17 @@ pub(crate) fn parse_chunk_handler_response(
                     err: e.to_string(),
                 }
             })?;
+            let data = decompress_chunk(pb).map_err(|err| DownloadChunkError::RequestError {
+                peer_id,
+                chunk_id,
+                err,
+            })?;
 
             let chunk = ArtifactChunk {
                 chunk_id,
                 witness: Vec::new(),
                 artifact_chunk_data:
-                    ic_types::chunkable::ArtifactChunkData::SemiStructuredChunkData(pb.data),
+                    ic_types::chunkable::ArtifactChunkData::SemiStructuredChunkData(data),
             };
             Ok(chunk)
         }
@@ -129,3 +233,74 @@ pub(crate) fn parse_chunk_handler_response(
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, RngCore, SeedableRng};
+
+    #[test]
+    fn negotiates_first_supported_compression() {
+        assert_eq!(
+            negotiate_compression(&[42, pb::StateSyncChunkCompression::Deflate as i32]),
+            pb::StateSyncChunkCompression::Deflate
+        );
+        assert_eq!(
+            negotiate_compression(&[42]),
+            pb::StateSyncChunkCompression::Unspecified
+        );
+        assert_eq!(
+            negotiate_compression(&[]),
+            pb::StateSyncChunkCompression::Unspecified
+        );
+    }
+
+    #[test]
+    fn compressed_chunk_round_trips() {
+        let data = vec![7; DEFAULT_CHUNK_SIZE as usize];
+        let chunk = compress_chunk(
+            pb::StateSyncChunkResponse {
+                data: data.clone(),
+                compression: pb::StateSyncChunkCompression::Unspecified as i32,
+            },
+            pb::StateSyncChunkCompression::Deflate,
+        );
+        assert_eq!(
+            chunk.compression,
+            pb::StateSyncChunkCompression::Deflate as i32
+        );
+        assert!(chunk.data.len() < data.len());
+        assert_eq!(decompress_chunk(chunk), Ok(data));
+    }
+
+    #[test]
+    fn incompressible_chunk_is_sent_uncompressed() {
+        let mut data = vec![0; 4096];
+        SmallRng::seed_from_u64(0).fill_bytes(&mut data);
+        let chunk = compress_chunk(
+            pb::StateSyncChunkResponse {
+                data: data.clone(),
+                compression: pb::StateSyncChunkCompression::Unspecified as i32,
+            },
+            pb::StateSyncChunkCompression::Deflate,
+        );
+        assert_eq!(
+            chunk.compression,
+            pb::StateSyncChunkCompression::Unspecified as i32
+        );
+        assert_eq!(chunk.data, data);
+    }
+
+    #[test]
+    fn rejects_chunks_that_decompress_beyond_the_limit() {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+        encoder
+            .write_all(&vec![0; MAX_DECOMPRESSED_CHUNK_SIZE + 1])
+            .unwrap();
+        let chunk = pb::StateSyncChunkResponse {
+            data: encoder.finish().unwrap(),
+            compression: pb::StateSyncChunkCompression::Deflate as i32,
+        };
+        assert!(decompress_chunk(chunk).is_err());
+    }
+}
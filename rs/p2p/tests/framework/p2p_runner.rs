@@ -10,6 +10,7 @@ use ic_interfaces_transport::Transport;
 use ic_logger::{debug, info, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_protobuf::types::v1 as pb;
+use ic_query_stats::test_utils::FakeQueryStatsPayloadBuilder;
 use ic_registry_client::client::RegistryClientImpl;
 use ic_registry_subnet_type::SubnetType;
 use ic_replica_setup_ic_network::{setup_consensus_and_p2p, P2PStateSyncClient};
@@ -125,6 +126,7 @@ fn execute_test(
             no_state_sync_client,
             xnet_payload_builder as Arc<_>,
             self_validating_payload_builder as Arc<_>,
+            Arc::new(FakeQueryStatsPayloadBuilder::new()),
             message_router as Arc<_>,
             Arc::clone(&fake_crypto) as Arc<_>,
             Arc::clone(&fake_crypto) as Arc<_>,
@@ -291,6 +293,7 @@ fn execute_test_chunking_pool(
             state_sync_client,
             xnet_payload_builder,
             self_validating_payload_builder,
+            Arc::new(FakeQueryStatsPayloadBuilder::new()),
             message_router,
             Arc::clone(&fake_crypto) as Arc<_>,
             Arc::clone(&fake_crypto) as Arc<_>,
@@ -0,0 +1,137 @@
+//! Types exchanged with clients of the PocketIC backend over HTTP.
+//!
+//! Principals (sender, canister IDs) are encoded in their textual form and
+//! binary blobs (Wasm modules, arguments, replies) are base64-encoded, so that
+//! clients written in any language can talk to the backend using plain JSON.
+use serde::{Deserialize, Serialize};
+
+/// Identifies a `StateMachine` instance managed by the backend.
+pub type InstanceId = u64;
+
+/// Arguments of the `POST /instances` endpoint. All fields are optional.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CreateInstanceArgs {
+    /// The type of the subnet, one of `application`, `system` or
+    /// `verified_application`. Defaults to `system`. Cannot be combined with
+    /// `from_checkpoint`.
+    #[serde(default)]
+    pub subnet_type: Option<ic_registry_subnet_type::SubnetType>,
+    /// The name of a checkpoint to start the instance from, instead of
+    /// starting from an empty state.
+    #[serde(default)]
+    pub from_checkpoint: Option<String>,
+    /// The time (in nanoseconds since the Unix epoch) the instance starts at.
+    /// Defaults to the genesis time of the `StateMachine`, or to the time of
+    /// the checkpoint.
+    #[serde(default)]
+    pub time_nanos_since_epoch: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawCheckpointName {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawTime {
+    pub nanos_since_epoch: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawDuration {
+    pub nanos: u64,
+}
+
+/// An update or query call to a canister.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawCanisterCall {
+    pub sender: String,
+    pub canister_id: String,
+    pub method: String,
+    #[serde(with = "base64_bytes")]
+    pub arg: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawCreateCanisterArgs {
+    #[serde(default)]
+    pub specified_id: Option<String>,
+    pub cycles: u128,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawInstallCodeArgs {
+    pub canister_id: String,
+    #[serde(default)]
+    pub mode: ic_ic00_types::CanisterInstallMode,
+    #[serde(with = "base64_bytes")]
+    pub wasm_module: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub arg: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawAddCycles {
+    pub canister_id: String,
+    pub amount: u128,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawCanisterId {
+    pub canister_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawBlob {
+    #[serde(with = "base64_bytes")]
+    pub blob: Vec<u8>,
+}
+
+/// The result of executing a canister method, see `WasmResult`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RawWasmResult {
+    Reply(#[serde(with = "base64_bytes")] Vec<u8>),
+    Reject(String),
+}
+
+impl From<ic_state_machine_tests::WasmResult> for RawWasmResult {
+    fn from(result: ic_state_machine_tests::WasmResult) -> Self {
+        use ic_state_machine_tests::WasmResult;
+        match result {
+            WasmResult::Reply(bytes) => RawWasmResult::Reply(bytes),
+            WasmResult::Reject(reason) => RawWasmResult::Reject(reason),
+        }
+    }
+}
+
+/// An error returned by the IC, see `UserError`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawUserError {
+    pub code: u64,
+    pub description: String,
+}
+
+impl From<ic_state_machine_tests::UserError> for RawUserError {
+    fn from(err: ic_state_machine_tests::UserError) -> Self {
+        RawUserError {
+            code: err.code() as u64,
+            description: err.description().to_string(),
+        }
+    }
+}
+
+pub type RawCallResult = Result<RawWasmResult, RawUserError>;
+
+mod base64_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
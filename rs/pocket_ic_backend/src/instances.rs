@@ -0,0 +1,78 @@
+use crate::api::InstanceId;
+use ic_state_machine_tests::{StateMachine, StateMachineSnapshot};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// A `StateMachine` instance shared between request handlers.
+///
+/// A `StateMachine` owns a tokio runtime, so an instance must only be
+/// accessed, and in particular dropped, from a blocking thread (see
+/// `tokio::task::spawn_blocking`), never from an async context.
+pub type Instance = Arc<Mutex<StateMachine>>;
+
+/// Keeps track of the `StateMachine` instances created through the API and of
+/// the named checkpoints saved from them.
+///
+/// Each instance has its own state directory and registry, so instances are
+/// fully isolated from each other. Requests to different instances are served
+/// concurrently, requests to the same instance are serialized.
+#[derive(Default)]
+pub struct InstanceStore {
+    instances: RwLock<BTreeMap<InstanceId, Instance>>,
+    next_id: AtomicU64,
+    checkpoints: RwLock<BTreeMap<String, Arc<StateMachineSnapshot>>>,
+}
+
+impl InstanceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new instance to the store and returns its ID.
+    pub fn insert(&self, state_machine: StateMachine) -> InstanceId {
+        let instance_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.instances
+            .write()
+            .unwrap()
+            .insert(instance_id, Arc::new(Mutex::new(state_machine)));
+        instance_id
+    }
+
+    pub fn get(&self, instance_id: InstanceId) -> Option<Instance> {
+        self.instances.read().unwrap().get(&instance_id).cloned()
+    }
+
+    /// Removes the instance from the store. The returned instance must be
+    /// dropped on a blocking thread.
+    pub fn remove(&self, instance_id: InstanceId) -> Option<Instance> {
+        self.instances.write().unwrap().remove(&instance_id)
+    }
+
+    /// Returns the IDs of all instances in ascending order.
+    pub fn list(&self) -> Vec<InstanceId> {
+        self.instances.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Saves a checkpoint under the given name, replacing any previous
+    /// checkpoint with the same name.
+    pub fn insert_checkpoint(&self, name: String, snapshot: StateMachineSnapshot) {
+        self.checkpoints
+            .write()
+            .unwrap()
+            .insert(name, Arc::new(snapshot));
+    }
+
+    pub fn get_checkpoint(&self, name: &str) -> Option<Arc<StateMachineSnapshot>> {
+        self.checkpoints.read().unwrap().get(name).cloned()
+    }
+
+    pub fn remove_checkpoint(&self, name: &str) -> Option<Arc<StateMachineSnapshot>> {
+        self.checkpoints.write().unwrap().remove(name)
+    }
+
+    /// Returns the names of all checkpoints in ascending order.
+    pub fn list_checkpoints(&self) -> Vec<String> {
+        self.checkpoints.read().unwrap().keys().cloned().collect()
+    }
+}
@@ -1 +1,7 @@
-
+//! The PocketIC backend is a long-lived HTTP server that manages any number of
+//! independent `StateMachine` instances. Test suites in any language can
+//! create isolated replicas through its REST API, drive them and delete them
+//! again, instead of spawning one `ic-test-state-machine` process per test.
+pub mod api;
+pub mod instances;
+pub mod routes;
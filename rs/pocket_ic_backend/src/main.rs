@@ -1,27 +1,28 @@
-use axum::{extract::Query, routing::get, Router, Server};
-use serde::Deserialize;
+use axum::Server;
+use clap::Parser;
+use pocket_ic_backend::{instances::InstanceStore, routes::router};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Command-line options
+#[derive(Parser)]
+#[clap(version = "1.0")]
+struct Opts {
+    /// The IP address the server listens on.
+    #[clap(long, default_value = "0.0.0.0")]
+    ip: IpAddr,
+    /// The port the server listens on.
+    #[clap(long, default_value_t = 3000)]
+    port: u16,
+}
 
 #[tokio::main]
 async fn main() {
-    let app = Router::new()
-        .route("/", get(up))
-        .route("/test", get(handle_tup));
+    let opts = Opts::parse();
+    let app = router(Arc::new(InstanceStore::new()));
 
-    Server::bind(&"0.0.0.0:3000".parse().expect("Failed to parse address"))
+    Server::bind(&SocketAddr::new(opts.ip, opts.port))
         .serve(app.into_make_service())
         .await
         .expect("Failed to run app");
 }
-
-#[derive(Deserialize)]
-struct Tup {
-    first: u32,
-    second: u32,
-}
-
-async fn up() {}
-
-async fn handle_tup(Query(input): Query<Tup>) -> String {
-    println!("handling tup");
-    format!("ok {}", input.first * input.second)
-}
@@ -0,0 +1,449 @@
+//! The REST API of the PocketIC backend.
+//!
+//! Instances are managed under `/instances` and each instance is driven
+//! through the endpoints under `/instances/:id/`, which map one to one to the
+//! corresponding `StateMachine` methods. The state of an instance can be saved
+//! as a named checkpoint with `/instances/:id/checkpoint`, and new instances
+//! can be started from it; checkpoints are managed under `/checkpoints`.
+use crate::api::{
+    CreateInstanceArgs, InstanceId, RawAddCycles, RawBlob, RawCallResult, RawCanisterCall,
+    RawCanisterId, RawCheckpointName, RawCreateCanisterArgs, RawDuration, RawInstallCodeArgs,
+    RawTime, RawUserError, RawWasmResult,
+};
+use crate::instances::InstanceStore;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use ic_config::{execution_environment, subnet_config::SubnetConfig};
+use ic_ic00_types::{self as ic00, CanisterIdRecord, Payload};
+use ic_registry_subnet_type::SubnetType;
+use ic_state_machine_tests::{
+    CanisterId, Cycles, PrincipalId, StateMachine, StateMachineBuilder, StateMachineConfig,
+    WasmResult,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+pub type AppState = Arc<InstanceStore>;
+
+pub fn router(store: AppState) -> Router {
+    Router::new()
+        .route("/", get(status))
+        .route("/instances", get(list_instances).post(create_instance))
+        .route("/instances/:id", delete(delete_instance))
+        .route("/instances/:id/checkpoint", post(checkpoint))
+        .route("/checkpoints", get(list_checkpoints))
+        .route("/checkpoints/:name", delete(delete_checkpoint))
+        .route("/instances/:id/tick", post(tick))
+        .route("/instances/:id/time", get(time))
+        .route("/instances/:id/set_time", post(set_time))
+        .route("/instances/:id/advance_time", post(advance_time))
+        .route("/instances/:id/root_key", get(root_key))
+        .route(
+            "/instances/:id/execute_ingress_as",
+            post(execute_ingress_as),
+        )
+        .route("/instances/:id/query_as", post(query_as))
+        .route(
+            "/instances/:id/create_canister_with_cycles",
+            post(create_canister_with_cycles),
+        )
+        .route("/instances/:id/install_canister", post(install_canister))
+        .route("/instances/:id/add_cycles", post(add_cycles))
+        .route("/instances/:id/stable_memory", post(stable_memory))
+        .with_state(store)
+}
+
+/// Errors returned by the API, mapped to HTTP status codes.
+///
+/// Note that errors produced by the IC itself (e.g. a canister trap) are not
+/// API errors: they are returned as part of a successful response.
+#[derive(Debug)]
+pub enum ApiError {
+    InstanceNotFound(InstanceId),
+    CheckpointNotFound(String),
+    CanisterNotFound(CanisterId),
+    BadRequest(String),
+    Internal(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::InstanceNotFound(id) => {
+                (StatusCode::NOT_FOUND, format!("Instance {} not found", id))
+            }
+            ApiError::CheckpointNotFound(name) => (
+                StatusCode::NOT_FOUND,
+                format!("Checkpoint {} not found", name),
+            ),
+            ApiError::CanisterNotFound(id) => {
+                (StatusCode::NOT_FOUND, format!("Canister {} not found", id))
+            }
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::Internal(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
+        };
+        (status, message).into_response()
+    }
+}
+
+/// Runs `f` on the specified instance on a blocking thread.
+///
+/// `StateMachine` calls block until the corresponding rounds are executed, so
+/// they must not run on the async executor.
+async fn with_instance<T, F>(
+    store: AppState,
+    instance_id: InstanceId,
+    f: F,
+) -> Result<Json<T>, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&StateMachine) -> Result<T, ApiError> + Send + 'static,
+{
+    let instance = store
+        .get(instance_id)
+        .ok_or(ApiError::InstanceNotFound(instance_id))?;
+    tokio::task::spawn_blocking(move || {
+        let state_machine = instance.lock().map_err(|_| {
+            ApiError::Internal(format!(
+                "Instance {} is unusable after a previous failure",
+                instance_id
+            ))
+        })?;
+        f(&state_machine)
+    })
+    .await
+    .map_err(|err| ApiError::Internal(format!("Request failed: {}", err)))?
+    .map(Json)
+}
+
+fn parse_principal(text: &str) -> Result<PrincipalId, ApiError> {
+    PrincipalId::from_str(text)
+        .map_err(|err| ApiError::BadRequest(format!("Invalid principal {}: {}", text, err)))
+}
+
+fn parse_canister_id(text: &str) -> Result<CanisterId, ApiError> {
+    CanisterId::try_from(parse_principal(text)?)
+        .map_err(|err| ApiError::BadRequest(format!("Invalid canister id {}: {}", text, err)))
+}
+
+fn ensure_canister_exists(
+    state_machine: &StateMachine,
+    canister_id: CanisterId,
+) -> Result<(), ApiError> {
+    if state_machine.canister_exists(canister_id) {
+        Ok(())
+    } else {
+        Err(ApiError::CanisterNotFound(canister_id))
+    }
+}
+
+async fn status() -> impl IntoResponse {
+    StatusCode::OK
+}
+
+async fn list_instances(State(store): State<AppState>) -> Json<Vec<InstanceId>> {
+    Json(store.list())
+}
+
+async fn create_instance(
+    State(store): State<AppState>,
+    args: Option<Json<CreateInstanceArgs>>,
+) -> Result<Json<InstanceId>, ApiError> {
+    let args = args.map(|Json(args)| args).unwrap_or_default();
+    let snapshot = match args.from_checkpoint {
+        Some(name) => {
+            if args.subnet_type.is_some() {
+                return Err(ApiError::BadRequest(
+                    "The subnet type of an instance started from a checkpoint cannot be changed"
+                        .to_string(),
+                ));
+            }
+            Some(
+                store
+                    .get_checkpoint(&name)
+                    .ok_or(ApiError::CheckpointNotFound(name))?,
+            )
+        }
+        None => None,
+    };
+    let subnet_type = args.subnet_type.unwrap_or(SubnetType::System);
+    let state_machine = tokio::task::spawn_blocking(move || {
+        let state_machine = match snapshot {
+            Some(snapshot) => snapshot.start(),
+            None => {
+                let hypervisor_config = execution_environment::Config {
+                    default_provisional_cycles_balance: Cycles::new(0),
+                    ..Default::default()
+                };
+                let config =
+                    StateMachineConfig::new(SubnetConfig::new(subnet_type), hypervisor_config);
+                StateMachineBuilder::new()
+                    .with_config(Some(config))
+                    .with_subnet_type(subnet_type)
+                    .build()
+            }
+        };
+        if let Some(nanos) = args.time_nanos_since_epoch {
+            state_machine.set_time(SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos));
+        }
+        state_machine
+    })
+    .await
+    .map_err(|err| ApiError::Internal(format!("Failed to create instance: {}", err)))?;
+    Ok(Json(store.insert(state_machine)))
+}
+
+async fn delete_instance(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+) -> Result<StatusCode, ApiError> {
+    let instance = store
+        .remove(instance_id)
+        .ok_or(ApiError::InstanceNotFound(instance_id))?;
+    // Dropping the instance shuts down its runtime, which is not allowed in an
+    // async context.
+    tokio::task::spawn_blocking(move || drop(instance))
+        .await
+        .map_err(|err| ApiError::Internal(format!("Failed to delete instance: {}", err)))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Saves the latest state of the instance as a checkpoint with the given
+/// name, replacing any previous checkpoint with the same name.
+async fn checkpoint(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(args): Json<RawCheckpointName>,
+) -> Result<Json<()>, ApiError> {
+    let checkpoints = Arc::clone(&store);
+    with_instance(store, instance_id, move |sm| {
+        checkpoints.insert_checkpoint(args.name, sm.snapshot());
+        Ok(())
+    })
+    .await
+}
+
+async fn list_checkpoints(State(store): State<AppState>) -> Json<Vec<String>> {
+    Json(store.list_checkpoints())
+}
+
+/// Deletes the checkpoint. Instances started from it are not affected.
+async fn delete_checkpoint(
+    State(store): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let snapshot = store
+        .remove_checkpoint(&name)
+        .ok_or(ApiError::CheckpointNotFound(name))?;
+    // Removing the checkpoint files may take a while.
+    tokio::task::spawn_blocking(move || drop(snapshot))
+        .await
+        .map_err(|err| ApiError::Internal(format!("Failed to delete checkpoint: {}", err)))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn tick(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+) -> Result<Json<()>, ApiError> {
+    with_instance(store, instance_id, |sm| {
+        sm.tick();
+        Ok(())
+    })
+    .await
+}
+
+async fn time(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+) -> Result<Json<RawTime>, ApiError> {
+    with_instance(store, instance_id, |sm| {
+        let nanos_since_epoch = sm
+            .time()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_err(|err| ApiError::Internal(err.to_string()))?
+            .as_nanos() as u64;
+        Ok(RawTime { nanos_since_epoch })
+    })
+    .await
+}
+
+async fn set_time(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(time): Json<RawTime>,
+) -> Result<Json<()>, ApiError> {
+    with_instance(store, instance_id, move |sm| {
+        sm.set_time(SystemTime::UNIX_EPOCH + Duration::from_nanos(time.nanos_since_epoch));
+        Ok(())
+    })
+    .await
+}
+
+async fn advance_time(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(duration): Json<RawDuration>,
+) -> Result<Json<()>, ApiError> {
+    with_instance(store, instance_id, move |sm| {
+        sm.advance_time(Duration::from_nanos(duration.nanos));
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the DER-encoded root key of the instance.
+async fn root_key(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+) -> Result<Json<RawBlob>, ApiError> {
+    with_instance(store, instance_id, |sm| {
+        let blob =
+            ic_crypto_utils_threshold_sig_der::public_key_to_der(&sm.root_key().into_bytes())
+                .map_err(ApiError::Internal)?;
+        Ok(RawBlob { blob })
+    })
+    .await
+}
+
+async fn execute_ingress_as(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(call): Json<RawCanisterCall>,
+) -> Result<Json<RawCallResult>, ApiError> {
+    let sender = parse_principal(&call.sender)?;
+    let canister_id = parse_canister_id(&call.canister_id)?;
+    with_instance(store, instance_id, move |sm| {
+        let mut method = call.method;
+        // Canisters can only be created through the provisional API in a
+        // `StateMachine`, the same substitution is done by the
+        // `ic-test-state-machine` binary.
+        if canister_id == CanisterId::ic_00() && method == "create_canister" {
+            method = "provisional_create_canister_with_cycles".to_string();
+        }
+        Ok(sm
+            .execute_ingress_as(sender, canister_id, method, call.arg)
+            .map(RawWasmResult::from)
+            .map_err(RawUserError::from))
+    })
+    .await
+}
+
+async fn query_as(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(call): Json<RawCanisterCall>,
+) -> Result<Json<RawCallResult>, ApiError> {
+    let sender = parse_principal(&call.sender)?;
+    let canister_id = parse_canister_id(&call.canister_id)?;
+    with_instance(store, instance_id, move |sm| {
+        Ok(sm
+            .query_as(sender, canister_id, call.method, call.arg)
+            .map(RawWasmResult::from)
+            .map_err(RawUserError::from))
+    })
+    .await
+}
+
+/// Creates a canister and returns its ID, or the error returned by the
+/// management canister.
+async fn create_canister_with_cycles(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(args): Json<RawCreateCanisterArgs>,
+) -> Result<Json<Result<RawCanisterId, RawUserError>>, ApiError> {
+    let specified_id = args
+        .specified_id
+        .as_deref()
+        .map(parse_principal)
+        .transpose()?;
+    with_instance(store, instance_id, move |sm| {
+        // Unlike `StateMachine::create_canister_with_cycles`, this does not
+        // panic if the canister cannot be created.
+        let result = sm.execute_ingress(
+            ic00::IC_00,
+            ic00::Method::ProvisionalCreateCanisterWithCycles,
+            ic00::ProvisionalCreateCanisterWithCyclesArgs::new(Some(args.cycles), specified_id)
+                .encode(),
+        );
+        Ok(match result {
+            Ok(WasmResult::Reply(bytes)) => {
+                let canister_id = CanisterIdRecord::decode(&bytes[..])
+                    .map_err(|err| {
+                        ApiError::Internal(format!("Failed to decode canister id: {}", err))
+                    })?
+                    .get_canister_id();
+                Ok(RawCanisterId {
+                    canister_id: canister_id.to_string(),
+                })
+            }
+            Ok(WasmResult::Reject(reason)) => Err(RawUserError {
+                code: ic_state_machine_tests::ErrorCode::CanisterRejectedMessage as u64,
+                description: reason,
+            }),
+            Err(err) => Err(RawUserError::from(err)),
+        })
+    })
+    .await
+}
+
+async fn install_canister(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(args): Json<RawInstallCodeArgs>,
+) -> Result<Json<Result<(), RawUserError>>, ApiError> {
+    let canister_id = parse_canister_id(&args.canister_id)?;
+    with_instance(store, instance_id, move |sm| {
+        ensure_canister_exists(sm, canister_id)?;
+        Ok(sm
+            .install_wasm_in_mode(canister_id, args.mode, args.wasm_module, args.arg)
+            .map_err(RawUserError::from))
+    })
+    .await
+}
+
+/// Tops up the canister and returns its new cycles balance.
+async fn add_cycles(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(args): Json<RawAddCycles>,
+) -> Result<Json<u128>, ApiError> {
+    let canister_id = parse_canister_id(&args.canister_id)?;
+    with_instance(store, instance_id, move |sm| {
+        ensure_canister_exists(sm, canister_id)?;
+        Ok(sm.add_cycles(canister_id, args.amount))
+    })
+    .await
+}
+
+async fn stable_memory(
+    State(store): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    Json(args): Json<RawCanisterId>,
+) -> Result<Json<RawBlob>, ApiError> {
+    let canister_id = parse_canister_id(&args.canister_id)?;
+    with_instance(store, instance_id, move |sm| {
+        let has_module = sm
+            .get_latest_state()
+            .canister_state(&canister_id)
+            .ok_or(ApiError::CanisterNotFound(canister_id))?
+            .execution_state
+            .is_some();
+        if !has_module {
+            return Err(ApiError::BadRequest(format!(
+                "Canister {} has no module",
+                canister_id
+            )));
+        }
+        Ok(RawBlob {
+            blob: sm.stable_memory(canister_id),
+        })
+    })
+    .await
+}
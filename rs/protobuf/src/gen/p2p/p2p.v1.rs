@@ -157,6 +157,10 @@ pub struct StateSyncChunkRequest {
     pub id: ::core::option::Option<StateSyncId>,
     #[prost(uint32, tag = "2")]
     pub chunk_id: u32,
+    /// Compressions that the requester is able to decompress. The responder may
+    /// use any of them, or none.
+    #[prost(enumeration = "StateSyncChunkCompression", repeated, tag = "3")]
+    pub accepted_compressions: ::prost::alloc::vec::Vec<i32>,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -164,4 +168,28 @@ pub struct StateSyncChunkRequest {
 pub struct StateSyncChunkResponse {
     #[prost(bytes = "vec", tag = "1")]
     pub data: ::prost::alloc::vec::Vec<u8>,
+    /// Compression applied to `data`.
+    #[prost(enumeration = "StateSyncChunkCompression", tag = "2")]
+    pub compression: i32,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum StateSyncChunkCompression {
+    /// The data is not compressed.
+    Unspecified = 0,
+    Deflate = 1,
+}
+impl StateSyncChunkCompression {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            StateSyncChunkCompression::Unspecified => "STATE_SYNC_CHUNK_COMPRESSION_UNSPECIFIED",
+            StateSyncChunkCompression::Deflate => "STATE_SYNC_CHUNK_COMPRESSION_DEFLATE",
+        }
+    }
 }
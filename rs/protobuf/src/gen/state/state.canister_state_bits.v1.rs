@@ -29,6 +29,10 @@ pub mod call_context {
         pub canister_id: ::core::option::Option<super::super::super::super::types::v1::CanisterId>,
         #[prost(uint64, tag = "2")]
         pub callback_id: u64,
+        /// The deadline of the request, in seconds since the Unix epoch. Zero
+        /// for guaranteed response calls.
+        #[prost(uint32, tag = "3")]
+        pub deadline_seconds: u32,
     }
     /// System task is either a Heartbeat or a GlobalTimer.
     #[allow(clippy::derive_partial_eq_without_eq)]
@@ -62,8 +66,8 @@ pub struct CallContextEntry {
 pub struct WasmClosure {
     #[prost(uint32, tag = "1")]
     pub func_idx: u32,
-    #[prost(uint32, tag = "2")]
-    pub env: u32,
+    #[prost(uint64, tag = "2")]
+    pub env: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -87,6 +91,10 @@ pub struct Callback {
     #[prost(message, optional, tag = "9")]
     pub prepayment_for_response_transmission:
         ::core::option::Option<super::super::queues::v1::Cycles>,
+    /// The deadline of the call, in seconds since the Unix epoch. Zero for
+    /// guaranteed response calls.
+    #[prost(uint32, tag = "10")]
+    pub deadline_seconds: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -107,6 +115,9 @@ pub struct CallContextManager {
     pub call_contexts: ::prost::alloc::vec::Vec<CallContextEntry>,
     #[prost(message, repeated, tag = "4")]
     pub callbacks: ::prost::alloc::vec::Vec<CallbackEntry>,
+    /// IDs of best-effort callbacks whose deadline has expired.
+    #[prost(uint64, repeated, tag = "5")]
+    pub expired_callbacks: ::prost::alloc::vec::Vec<u64>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -157,6 +168,7 @@ pub mod wasm_method {
         CanisterHeartbeat = 6,
         Empty = 7,
         CanisterGlobalTimer = 8,
+        CanisterOnLowWasmMemory = 9,
     }
     impl SystemMethod {
         /// String value of the enum field names used in the ProtoBuf definition.
@@ -174,6 +186,9 @@ pub mod wasm_method {
                 SystemMethod::CanisterHeartbeat => "SYSTEM_METHOD_CANISTER_HEARTBEAT",
                 SystemMethod::Empty => "SYSTEM_METHOD_EMPTY",
                 SystemMethod::CanisterGlobalTimer => "SYSTEM_METHOD_CANISTER_GLOBAL_TIMER",
+                SystemMethod::CanisterOnLowWasmMemory => {
+                    "SYSTEM_METHOD_CANISTER_ON_LOW_WASM_MEMORY"
+                }
             }
         }
     }
@@ -342,6 +357,7 @@ pub mod execution_task {
         Unspecified = 0,
         Heartbeat = 1,
         Timer = 2,
+        OnLowWasmMemory = 3,
     }
     impl CanisterTask {
         /// String value of the enum field names used in the ProtoBuf definition.
@@ -353,6 +369,7 @@ pub mod execution_task {
                 CanisterTask::Unspecified => "CANISTER_TASK_UNSPECIFIED",
                 CanisterTask::Heartbeat => "CANISTER_TASK_HEARTBEAT",
                 CanisterTask::Timer => "CANISTER_TASK_TIMER",
+                CanisterTask::OnLowWasmMemory => "CANISTER_TASK_ON_LOW_WASM_MEMORY",
             }
         }
     }
@@ -458,6 +475,16 @@ pub struct CanisterHistory {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterLogRecord {
+    #[prost(uint64, tag = "1")]
+    pub idx: u64,
+    #[prost(uint64, tag = "2")]
+    pub timestamp_nanos: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub content: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CanisterStateBits {
     #[prost(uint64, tag = "2")]
     pub last_full_execution_round: u64,
@@ -526,6 +553,37 @@ pub struct CanisterStateBits {
     /// Resource reservation cycles.
     #[prost(message, optional, tag = "38")]
     pub reserved_balance: ::core::option::Option<super::super::queues::v1::Cycles>,
+    /// The local id to assign to the next snapshot of the canister.
+    #[prost(uint64, tag = "39")]
+    pub next_snapshot_id: u64,
+    /// Who is allowed to fetch the canister's logs.
+    #[prost(enumeration = "LogVisibility", tag = "40")]
+    pub log_visibility: i32,
+    /// The records in the canister's log buffer.
+    #[prost(message, repeated, tag = "41")]
+    pub canister_log_records: ::prost::alloc::vec::Vec<CanisterLogRecord>,
+    /// The index to assign to the next canister log record.
+    #[prost(uint64, tag = "42")]
+    pub next_canister_log_record_idx: u64,
+    /// The upper bound on the canister's Wasm memory in bytes, if any.
+    #[prost(uint64, optional, tag = "44")]
+    pub wasm_memory_limit: ::core::option::Option<u64>,
+    /// The remaining Wasm memory below which `canister_on_low_wasm_memory` runs.
+    #[prost(uint64, tag = "45")]
+    pub wasm_memory_threshold: u64,
+    /// Whether `canister_on_low_wasm_memory` is due or has already run.
+    #[prost(enumeration = "OnLowWasmMemoryHookStatus", tag = "46")]
+    pub on_low_wasm_memory_hook_status: i32,
+    /// The upper bound on the reserved balance, if any.
+    #[prost(message, optional, tag = "47")]
+    pub reserved_balance_limit: ::core::option::Option<super::super::queues::v1::Cycles>,
+    /// The aggregated statistics of the queries executed on the canister.
+    #[prost(message, optional, tag = "48")]
+    pub total_query_stats: ::core::option::Option<TotalQueryStats>,
+    /// The chunks in the canister's Wasm chunk store. Their contents are stored
+    /// in a separate file.
+    #[prost(message, optional, tag = "49")]
+    pub wasm_chunk_store_metadata: ::core::option::Option<WasmChunkStoreMetadata>,
     #[prost(oneof = "canister_state_bits::CanisterStatus", tags = "11, 12, 13")]
     pub canister_status: ::core::option::Option<canister_state_bits::CanisterStatus>,
 }
@@ -542,6 +600,63 @@ pub mod canister_state_bits {
         Stopped(super::CanisterStatusStopped),
     }
 }
+/// Query statistics of a canister, aggregated over all nodes and epochs.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TotalQueryStats {
+    #[prost(message, optional, tag = "1")]
+    pub num_calls: ::core::option::Option<super::super::super::types::v1::NominalCycles>,
+    #[prost(message, optional, tag = "2")]
+    pub num_instructions: ::core::option::Option<super::super::super::types::v1::NominalCycles>,
+    #[prost(message, optional, tag = "3")]
+    pub ingress_payload_size: ::core::option::Option<super::super::super::types::v1::NominalCycles>,
+    #[prost(message, optional, tag = "4")]
+    pub egress_payload_size: ::core::option::Option<super::super::super::types::v1::NominalCycles>,
+}
+/// The position of a chunk in the Wasm chunk store file.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WasmChunkData {
+    /// The SHA-256 hash of the chunk.
+    #[prost(bytes = "vec", tag = "1")]
+    pub hash: ::prost::alloc::vec::Vec<u8>,
+    /// The chunk starts at `index` times the maximum chunk size.
+    #[prost(uint64, tag = "2")]
+    pub index: u64,
+    /// The length of the chunk in bytes.
+    #[prost(uint64, tag = "3")]
+    pub length: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WasmChunkStoreMetadata {
+    #[prost(message, repeated, tag = "1")]
+    pub chunks: ::prost::alloc::vec::Vec<WasmChunkData>,
+}
+/// A snapshot of a canister taken via `take_canister_snapshot`. The Wasm module
+/// and the memories are stored in separate files next to this message.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterSnapshotBits {
+    #[prost(message, optional, tag = "1")]
+    pub canister_id: ::core::option::Option<super::super::super::types::v1::CanisterId>,
+    #[prost(uint64, tag = "2")]
+    pub local_id: u64,
+    #[prost(uint64, tag = "3")]
+    pub taken_at_timestamp: u64,
+    #[prost(uint64, tag = "4")]
+    pub canister_version: u64,
+    #[prost(bytes = "vec", tag = "5")]
+    pub certified_data: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, repeated, tag = "6")]
+    pub exported_globals: ::prost::alloc::vec::Vec<Global>,
+    #[prost(uint64, tag = "7")]
+    pub wasm_memory_size: u64,
+    #[prost(uint64, tag = "8")]
+    pub stable_memory_size: u64,
+    #[prost(bytes = "vec", tag = "9")]
+    pub binary_hash: ::prost::alloc::vec::Vec<u8>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
@@ -627,3 +742,49 @@ impl CyclesUseCase {
         }
     }
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum LogVisibility {
+    Unspecified = 0,
+    Controllers = 1,
+    Public = 2,
+}
+impl LogVisibility {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            LogVisibility::Unspecified => "LOG_VISIBILITY_UNSPECIFIED",
+            LogVisibility::Controllers => "LOG_VISIBILITY_CONTROLLERS",
+            LogVisibility::Public => "LOG_VISIBILITY_PUBLIC",
+        }
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OnLowWasmMemoryHookStatus {
+    Unspecified = 0,
+    ConditionNotSatisfied = 1,
+    Ready = 2,
+    Executed = 3,
+}
+impl OnLowWasmMemoryHookStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            OnLowWasmMemoryHookStatus::Unspecified => "ON_LOW_WASM_MEMORY_HOOK_STATUS_UNSPECIFIED",
+            OnLowWasmMemoryHookStatus::ConditionNotSatisfied => {
+                "ON_LOW_WASM_MEMORY_HOOK_STATUS_CONDITION_NOT_SATISFIED"
+            }
+            OnLowWasmMemoryHookStatus::Ready => "ON_LOW_WASM_MEMORY_HOOK_STATUS_READY",
+            OnLowWasmMemoryHookStatus::Executed => "ON_LOW_WASM_MEMORY_HOOK_STATUS_EXECUTED",
+        }
+    }
+}
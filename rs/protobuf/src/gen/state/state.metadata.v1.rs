@@ -267,6 +267,21 @@ pub struct SubnetMetrics {
     #[prost(message, repeated, tag = "5")]
     pub consumed_cycles_by_use_case:
         ::prost::alloc::vec::Vec<super::super::canister_state_bits::v1::ConsumedCyclesByUseCase>,
+    #[prost(uint64, optional, tag = "6")]
+    pub num_canisters: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "7")]
+    pub canister_state_bytes: ::core::option::Option<u64>,
+    #[prost(message, optional, tag = "8")]
+    pub consumed_cycles_total:
+        ::core::option::Option<super::super::super::types::v1::NominalCycles>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodePublicKeyEntry {
+    #[prost(message, optional, tag = "1")]
+    pub node_id: ::core::option::Option<super::super::super::types::v1::NodeId>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub public_key: ::prost::alloc::vec::Vec<u8>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -322,6 +337,33 @@ pub struct SystemMetadata {
     #[prost(message, repeated, tag = "18")]
     pub bitcoin_get_successors_follow_up_responses:
         ::prost::alloc::vec::Vec<BitcoinGetSuccessorsFollowUpResponses>,
+    #[prost(message, optional, tag = "19")]
+    pub raw_query_stats: ::core::option::Option<RawQueryStats>,
+    /// DER-encoded node signing public keys of the nodes of this subnet.
+    #[prost(message, repeated, tag = "20")]
+    pub node_public_keys: ::prost::alloc::vec::Vec<NodePublicKeyEntry>,
+}
+/// The query statistics that a node reported for an epoch.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryStatsReport {
+    #[prost(uint64, tag = "1")]
+    pub epoch: u64,
+    #[prost(message, optional, tag = "2")]
+    pub proposer: ::core::option::Option<super::super::super::types::v1::NodeId>,
+    #[prost(message, repeated, tag = "3")]
+    pub stats: ::prost::alloc::vec::Vec<super::super::super::types::v1::CanisterQueryStats>,
+}
+/// Query statistics that were reported by nodes but not yet aggregated into
+/// the canisters' totals.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RawQueryStats {
+    /// The highest epoch that was aggregated, if any.
+    #[prost(uint64, optional, tag = "1")]
+    pub highest_aggregated_epoch: ::core::option::Option<u64>,
+    #[prost(message, repeated, tag = "2")]
+    pub reports: ::prost::alloc::vec::Vec<QueryStatsReport>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
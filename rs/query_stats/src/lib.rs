@@ -0,0 +1,188 @@
+//! Collection and aggregation of query statistics.
+//!
+//! Every replica keeps track of the queries it executes on behalf of users and
+//! collects statistics about them per canister and epoch. Once an epoch is
+//! over, the replica includes its statistics in one of the blocks it proposes.
+//! When the block is delivered, the statistics are stored in the replicated
+//! state until they can be aggregated with the reports of the other replicas
+//! into the canisters' `total_query_stats`.
+mod payload_builder;
+mod state_machine;
+pub mod test_utils;
+
+pub use payload_builder::QueryStatsPayloadBuilderImpl;
+pub use state_machine::deliver_query_stats;
+
+use ic_config::{execution_environment::Config, flag_status::FlagStatus};
+use ic_interfaces_state_manager::StateReader;
+use ic_logger::{debug, ReplicaLogger};
+use ic_replicated_state::ReplicatedState;
+use ic_types::{
+    batch::{epoch_from_height, QueryStats, QueryStatsEpoch},
+    CanisterId, Height, NodeId,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// The statistics collected by this replica during an epoch.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LocalQueryStats {
+    epoch: Option<QueryStatsEpoch>,
+    stats: BTreeMap<CanisterId, QueryStats>,
+}
+
+/// Creates the [`QueryStatsCollector`] used by the query handler, and the
+/// parameters needed to create the matching [`QueryStatsPayloadBuilderImpl`].
+pub fn init_query_stats(
+    log: ReplicaLogger,
+    config: &Config,
+) -> (QueryStatsCollector, QueryStatsPayloadBuilderParams) {
+    let previous_epoch = Arc::new(Mutex::new(None));
+    (
+        QueryStatsCollector {
+            log,
+            epoch_length: config.query_stats_epoch_length,
+            current_epoch: Mutex::new(LocalQueryStats::default()),
+            previous_epoch: Arc::clone(&previous_epoch),
+        },
+        QueryStatsPayloadBuilderParams {
+            enabled: config.query_stats_aggregation == FlagStatus::Enabled,
+            epoch_length: config.query_stats_epoch_length,
+            previous_epoch,
+        },
+    )
+}
+
+/// Collects the statistics of the queries executed by this replica.
+pub struct QueryStatsCollector {
+    log: ReplicaLogger,
+    epoch_length: u64,
+    current_epoch: Mutex<LocalQueryStats>,
+    previous_epoch: Arc<Mutex<Option<LocalQueryStats>>>,
+}
+
+impl QueryStatsCollector {
+    /// Sets the current epoch to the one containing `height`, the height of
+    /// the certified state that queries are executed against.
+    ///
+    /// If this starts a new epoch, the statistics collected so far are handed
+    /// over to the payload builder.
+    pub fn set_epoch_from_height(&self, height: Height) {
+        let epoch = epoch_from_height(height, self.epoch_length);
+        let mut current_epoch = self.current_epoch.lock().unwrap();
+        match current_epoch.epoch {
+            Some(current) if current >= epoch => {}
+            Some(current) => {
+                debug!(
+                    self.log,
+                    "Query stats epoch {} is over, starting epoch {}", current, epoch
+                );
+                let finished = std::mem::replace(
+                    &mut *current_epoch,
+                    LocalQueryStats {
+                        epoch: Some(epoch),
+                        stats: BTreeMap::new(),
+                    },
+                );
+                *self.previous_epoch.lock().unwrap() = Some(finished);
+            }
+            None => current_epoch.epoch = Some(epoch),
+        }
+    }
+
+    /// Records the statistics of a query executed on `canister_id`.
+    pub fn register_query_statistics(&self, canister_id: CanisterId, stats: &QueryStats) {
+        self.current_epoch
+            .lock()
+            .unwrap()
+            .stats
+            .entry(canister_id)
+            .or_default()
+            .saturating_accumulate(stats);
+    }
+}
+
+/// The parameters of the [`QueryStatsPayloadBuilderImpl`] that are known when
+/// execution is set up, before the consensus components are created.
+pub struct QueryStatsPayloadBuilderParams {
+    enabled: bool,
+    epoch_length: u64,
+    previous_epoch: Arc<Mutex<Option<LocalQueryStats>>>,
+}
+
+impl QueryStatsPayloadBuilderParams {
+    pub fn into_payload_builder(
+        self,
+        state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        node_id: NodeId,
+        log: ReplicaLogger,
+    ) -> QueryStatsPayloadBuilderImpl {
+        QueryStatsPayloadBuilderImpl {
+            state_reader,
+            node_id,
+            enabled: self.enabled,
+            epoch_length: self.epoch_length,
+            previous_epoch: self.previous_epoch,
+            log,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_test_utilities::types::ids::canister_test_id;
+
+    fn stats(num_instructions: u64) -> QueryStats {
+        QueryStats {
+            num_calls: 1,
+            num_instructions,
+            ingress_payload_size: 10,
+            egress_payload_size: 20,
+        }
+    }
+
+    #[test]
+    fn collector_hands_over_finished_epochs() {
+        let config = Config {
+            query_stats_epoch_length: 10,
+            ..Config::default()
+        };
+        let (collector, params) = init_query_stats(no_op_logger(), &config);
+
+        collector.set_epoch_from_height(Height::from(5));
+        collector.register_query_statistics(canister_test_id(1), &stats(100));
+        collector.register_query_statistics(canister_test_id(1), &stats(200));
+        collector.register_query_statistics(canister_test_id(2), &stats(300));
+        assert!(params.previous_epoch.lock().unwrap().is_none());
+
+        // Same epoch, nothing is handed over.
+        collector.set_epoch_from_height(Height::from(9));
+        assert!(params.previous_epoch.lock().unwrap().is_none());
+
+        collector.set_epoch_from_height(Height::from(12));
+        collector.register_query_statistics(canister_test_id(1), &stats(400));
+
+        let previous = params.previous_epoch.lock().unwrap().clone().unwrap();
+        assert_eq!(previous.epoch, Some(QueryStatsEpoch::from(0)));
+        assert_eq!(previous.stats.len(), 2);
+        assert_eq!(
+            previous.stats[&canister_test_id(1)],
+            QueryStats {
+                num_calls: 2,
+                num_instructions: 300,
+                ingress_payload_size: 20,
+                egress_payload_size: 40,
+            }
+        );
+        assert_eq!(previous.stats[&canister_test_id(2)], stats(300));
+
+        // Going back to an older epoch does not affect the collected stats.
+        collector.set_epoch_from_height(Height::from(3));
+        let previous = params.previous_epoch.lock().unwrap().clone().unwrap();
+        assert_eq!(previous.epoch, Some(QueryStatsEpoch::from(0)));
+    }
+}
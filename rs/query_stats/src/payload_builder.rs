@@ -0,0 +1,202 @@
+use crate::LocalQueryStats;
+use ic_interfaces::{
+    batch_payload::{BatchPayloadBuilder, PastPayload, ProposalContext},
+    consensus::PayloadValidationError,
+    query_stats::{
+        QueryStatsPayloadValidationError, QueryStatsPermanentValidationError,
+        QueryStatsTransientValidationError,
+    },
+    validation::ValidationError,
+};
+use ic_interfaces_state_manager::StateReader;
+use ic_logger::{warn, ReplicaLogger};
+use ic_replicated_state::ReplicatedState;
+use ic_types::{
+    batch::{
+        epoch_from_height, CanisterQueryStats, QueryStatsEpoch, QueryStatsPayload,
+        ValidationContext,
+    },
+    Height, NodeId, NumBytes,
+};
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+mod tests;
+
+/// Implementation of the [`BatchPayloadBuilder`] for query statistics.
+///
+/// Once an epoch is over, the payload builder includes the statistics that
+/// this replica collected during the epoch into a block that it proposes.
+/// Statistics of canisters that do not fit into the block are dropped.
+pub struct QueryStatsPayloadBuilderImpl {
+    pub(crate) state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+    pub(crate) node_id: NodeId,
+    pub(crate) enabled: bool,
+    pub(crate) epoch_length: u64,
+    pub(crate) previous_epoch: Arc<Mutex<Option<LocalQueryStats>>>,
+    pub(crate) log: ReplicaLogger,
+}
+
+impl QueryStatsPayloadBuilderImpl {
+    /// Returns `true` if `proposer` already reported its statistics for
+    /// `epoch`, either in the certified state or in one of the past payloads.
+    fn is_reported(
+        &self,
+        epoch: QueryStatsEpoch,
+        proposer: &NodeId,
+        state: &ReplicatedState,
+        past_payloads: &[PastPayload],
+    ) -> bool {
+        state.metadata.raw_query_stats.has_report(epoch, proposer)
+            || past_payloads.iter().any(|past_payload| {
+                matches!(
+                    QueryStatsPayload::deserialize(past_payload.payload),
+                    Ok(Some(payload)) if payload.epoch == epoch && payload.proposer == *proposer
+                )
+            })
+    }
+
+    fn validate_payload_impl(
+        &self,
+        payload: &[u8],
+        past_payloads: &[PastPayload],
+        proposal_context: &ProposalContext,
+    ) -> Result<(), QueryStatsPayloadValidationError> {
+        if !self.enabled {
+            return Err(ValidationError::Transient(
+                QueryStatsTransientValidationError::Disabled,
+            ));
+        }
+
+        let payload = match QueryStatsPayload::deserialize(payload) {
+            Ok(Some(payload)) => payload,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                return Err(ValidationError::Permanent(
+                    QueryStatsPermanentValidationError::DeserializationFailed(err),
+                ))
+            }
+        };
+
+        // Nodes may only report their own statistics.
+        if payload.proposer != proposal_context.proposer {
+            return Err(ValidationError::Permanent(
+                QueryStatsPermanentValidationError::InvalidNodeId {
+                    expected: proposal_context.proposer,
+                    reported: payload.proposer,
+                },
+            ));
+        }
+
+        let context = proposal_context.validation_context;
+        let current_epoch = epoch_from_height(context.certified_height, self.epoch_length);
+        if payload.epoch >= current_epoch {
+            return Err(ValidationError::Permanent(
+                QueryStatsPermanentValidationError::EpochNotOver {
+                    current_epoch,
+                    received: payload.epoch,
+                },
+            ));
+        }
+
+        let state = self
+            .state_reader
+            .get_state_at(context.certified_height)
+            .map_err(|_| {
+                ValidationError::Transient(QueryStatsTransientValidationError::StateUnavailable)
+            })?;
+        let state = state.get_ref();
+
+        let is_member = state
+            .metadata
+            .network_topology
+            .subnets
+            .get(&state.metadata.own_subnet_id)
+            .map_or(false, |subnet| subnet.nodes.contains(&payload.proposer));
+        if !is_member {
+            return Err(ValidationError::Permanent(
+                QueryStatsPermanentValidationError::ProposerNotMember(payload.proposer),
+            ));
+        }
+
+        if self.is_reported(payload.epoch, &payload.proposer, state, past_payloads) {
+            return Err(ValidationError::Permanent(
+                QueryStatsPermanentValidationError::DuplicateReport(payload.epoch),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl BatchPayloadBuilder for QueryStatsPayloadBuilderImpl {
+    fn build_payload(
+        &self,
+        _height: Height,
+        max_size: NumBytes,
+        past_payloads: &[PastPayload],
+        context: &ValidationContext,
+    ) -> Vec<u8> {
+        if !self.enabled {
+            return vec![];
+        }
+
+        let previous_epoch = self.previous_epoch.lock().unwrap();
+        let (epoch, stats) = match previous_epoch.as_ref() {
+            Some(LocalQueryStats {
+                epoch: Some(epoch),
+                stats,
+            }) => (*epoch, stats),
+            _ => return vec![],
+        };
+
+        // The epoch must also be over from the point of view of the
+        // validation context.
+        if epoch >= epoch_from_height(context.certified_height, self.epoch_length) {
+            return vec![];
+        }
+
+        let state = match self.state_reader.get_state_at(context.certified_height) {
+            Ok(state) => state,
+            Err(err) => {
+                warn!(
+                    self.log,
+                    "QueryStatsPayloadBuilder: state at height {} unavailable: {:?}",
+                    context.certified_height,
+                    err
+                );
+                return vec![];
+            }
+        };
+        if self.is_reported(epoch, &self.node_id, state.get_ref(), past_payloads) {
+            return vec![];
+        }
+
+        QueryStatsPayload {
+            epoch,
+            proposer: self.node_id,
+            stats: stats
+                .iter()
+                .map(|(canister_id, stats)| CanisterQueryStats {
+                    canister_id: *canister_id,
+                    stats: stats.clone(),
+                })
+                .collect(),
+        }
+        .serialize_with_limit(max_size)
+    }
+
+    fn validate_payload(
+        &self,
+        _height: Height,
+        payload: &[u8],
+        past_payloads: &[PastPayload],
+        proposal_context: &ProposalContext,
+    ) -> Result<(), PayloadValidationError> {
+        if payload.is_empty() {
+            return Ok(());
+        }
+        self.validate_payload_impl(payload, past_payloads, proposal_context)
+            .map_err(PayloadValidationError::from)
+    }
+}
@@ -0,0 +1,260 @@
+use super::*;
+use ic_interfaces::consensus::PayloadPermanentError;
+use ic_interfaces_state_manager::Labeled;
+use ic_interfaces_state_manager_mocks::MockStateManager;
+use ic_logger::replica_logger::no_op_logger;
+use ic_replicated_state::SubnetTopology;
+use ic_test_utilities::{
+    mock_time,
+    state::ReplicatedStateBuilder,
+    types::ids::{canister_test_id, node_test_id, subnet_test_id},
+};
+use ic_types::{
+    batch::QueryStats,
+    consensus::BlockPayload,
+    crypto::{CryptoHash, CryptoHashOf},
+    CanisterId, RegistryVersion,
+};
+use std::collections::BTreeMap;
+
+const EPOCH_LENGTH: u64 = 10;
+
+fn test_stats(num_canisters: u64) -> BTreeMap<CanisterId, QueryStats> {
+    (0..num_canisters)
+        .map(|i| {
+            (
+                canister_test_id(i),
+                QueryStats {
+                    num_calls: 1,
+                    num_instructions: 1_000,
+                    ingress_payload_size: 10,
+                    egress_payload_size: 20,
+                },
+            )
+        })
+        .collect()
+}
+
+fn test_payload_builder(
+    node_id: NodeId,
+    state: ReplicatedState,
+    previous_epoch: Option<LocalQueryStats>,
+) -> QueryStatsPayloadBuilderImpl {
+    let state = Arc::new(state);
+    let mut state_manager = MockStateManager::new();
+    state_manager
+        .expect_get_state_at()
+        .returning(move |height| Ok(Labeled::new(height, Arc::clone(&state))));
+    QueryStatsPayloadBuilderImpl {
+        state_reader: Arc::new(state_manager),
+        node_id,
+        enabled: true,
+        epoch_length: EPOCH_LENGTH,
+        previous_epoch: Arc::new(Mutex::new(previous_epoch)),
+        log: no_op_logger(),
+    }
+}
+
+/// Returns a state of a subnet consisting of nodes 1 to 4.
+fn test_state() -> ReplicatedState {
+    let mut state = ReplicatedStateBuilder::new()
+        .with_subnet_id(subnet_test_id(1))
+        .build();
+    state.metadata.network_topology.subnets.insert(
+        subnet_test_id(1),
+        SubnetTopology {
+            nodes: (1..=4).map(node_test_id).collect(),
+            ..SubnetTopology::default()
+        },
+    );
+    state
+}
+
+fn context(certified_height: u64) -> ValidationContext {
+    ValidationContext {
+        registry_version: RegistryVersion::from(1),
+        certified_height: Height::from(certified_height),
+        time: mock_time(),
+    }
+}
+
+fn proposal_context(
+    proposer: NodeId,
+    validation_context: &ValidationContext,
+) -> ProposalContext<'_> {
+    ProposalContext {
+        proposer,
+        validation_context,
+    }
+}
+
+fn past_payload(payload: &[u8]) -> PastPayload {
+    PastPayload {
+        height: Height::from(0),
+        time: mock_time(),
+        block_hash: CryptoHashOf::<BlockPayload>::from(CryptoHash(vec![])),
+        payload,
+    }
+}
+
+fn local_stats(epoch: u64, num_canisters: u64) -> Option<LocalQueryStats> {
+    Some(LocalQueryStats {
+        epoch: Some(QueryStatsEpoch::from(epoch)),
+        stats: test_stats(num_canisters),
+    })
+}
+
+#[test]
+fn builds_and_validates_payload_once_the_epoch_is_over() {
+    let builder = test_payload_builder(node_test_id(1), test_state(), local_stats(0, 5));
+    let max_size = NumBytes::from(1_000_000);
+
+    // Epoch 0 is not over from the point of view of the validation context.
+    assert!(builder
+        .build_payload(Height::from(1), max_size, &[], &context(9))
+        .is_empty());
+
+    let payload = builder.build_payload(Height::from(1), max_size, &[], &context(10));
+    let deserialized = QueryStatsPayload::deserialize(&payload).unwrap().unwrap();
+    assert_eq!(deserialized.epoch, QueryStatsEpoch::from(0));
+    assert_eq!(deserialized.proposer, node_test_id(1));
+    assert_eq!(deserialized.stats.len(), 5);
+    builder
+        .validate_payload(
+            Height::from(1),
+            &payload,
+            &[],
+            &proposal_context(node_test_id(1), &context(10)),
+        )
+        .unwrap();
+
+    // The same epoch is not reported twice.
+    let past_payloads = vec![past_payload(&payload)];
+    assert!(builder
+        .build_payload(Height::from(2), max_size, &past_payloads, &context(10))
+        .is_empty());
+    assert!(matches!(
+        builder.validate_payload(
+            Height::from(2),
+            &payload,
+            &past_payloads,
+            &proposal_context(node_test_id(1), &context(10)),
+        ),
+        Err(ValidationError::Permanent(
+            PayloadPermanentError::QueryStatsPayloadValidationError(
+                QueryStatsPermanentValidationError::DuplicateReport(_)
+            )
+        ))
+    ));
+}
+
+#[test]
+fn does_not_report_epochs_already_in_state() {
+    let mut state = test_state();
+    state.metadata.raw_query_stats.insert(QueryStatsPayload {
+        epoch: QueryStatsEpoch::from(0),
+        proposer: node_test_id(1),
+        stats: vec![],
+    });
+    let builder = test_payload_builder(node_test_id(1), state, local_stats(0, 5));
+    assert!(builder
+        .build_payload(
+            Height::from(1),
+            NumBytes::from(1_000_000),
+            &[],
+            &context(10)
+        )
+        .is_empty());
+}
+
+#[test]
+fn rejects_invalid_payloads() {
+    let builder = test_payload_builder(node_test_id(1), test_state(), None);
+    let serialize = |epoch: u64, proposer: NodeId| {
+        QueryStatsPayload {
+            epoch: QueryStatsEpoch::from(epoch),
+            proposer,
+            stats: vec![],
+        }
+        .serialize_with_limit(NumBytes::from(1_000_000))
+    };
+
+    let validate = |payload: &[u8], proposer: NodeId| {
+        builder.validate_payload(
+            Height::from(1),
+            payload,
+            &[],
+            &proposal_context(proposer, &context(25)),
+        )
+    };
+
+    assert!(validate(&[], node_test_id(2)).is_ok());
+    assert!(validate(&serialize(1, node_test_id(2)), node_test_id(2)).is_ok());
+    assert!(matches!(
+        validate(&[1, 2, 3], node_test_id(2)),
+        Err(ValidationError::Permanent(
+            PayloadPermanentError::QueryStatsPayloadValidationError(
+                QueryStatsPermanentValidationError::DeserializationFailed(_)
+            )
+        ))
+    ));
+    assert!(matches!(
+        validate(&serialize(2, node_test_id(2)), node_test_id(2)),
+        Err(ValidationError::Permanent(
+            PayloadPermanentError::QueryStatsPayloadValidationError(
+                QueryStatsPermanentValidationError::EpochNotOver { .. }
+            )
+        ))
+    ));
+    assert!(matches!(
+        validate(&serialize(1, node_test_id(5)), node_test_id(5)),
+        Err(ValidationError::Permanent(
+            PayloadPermanentError::QueryStatsPayloadValidationError(
+                QueryStatsPermanentValidationError::ProposerNotMember(_)
+            )
+        ))
+    ));
+}
+
+#[test]
+fn rejects_payloads_reporting_for_another_node() {
+    let builder = test_payload_builder(node_test_id(1), test_state(), local_stats(0, 5));
+    let payload = builder.build_payload(
+        Height::from(1),
+        NumBytes::from(1_000_000),
+        &[],
+        &context(10),
+    );
+
+    // Node 2 proposes a block containing the statistics reported by node 1.
+    assert!(matches!(
+        builder.validate_payload(
+            Height::from(1),
+            &payload,
+            &[],
+            &proposal_context(node_test_id(2), &context(10)),
+        ),
+        Err(ValidationError::Permanent(
+            PayloadPermanentError::QueryStatsPayloadValidationError(
+                QueryStatsPermanentValidationError::InvalidNodeId { expected, reported }
+            )
+        )) if expected == node_test_id(2) && reported == node_test_id(1)
+    ));
+}
+
+#[test]
+fn payload_respects_size_limit() {
+    let builder = test_payload_builder(node_test_id(1), test_state(), local_stats(0, 1_000));
+    let max_size = NumBytes::from(1_000);
+    let payload = builder.build_payload(Height::from(1), max_size, &[], &context(10));
+    assert!(!payload.is_empty());
+    assert!(payload.len() as u64 <= max_size.get());
+    builder
+        .validate_payload(
+            Height::from(1),
+            &payload,
+            &[],
+            &proposal_context(node_test_id(1), &context(10)),
+        )
+        .unwrap();
+}
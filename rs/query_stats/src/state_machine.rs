@@ -0,0 +1,305 @@
+use ic_logger::{warn, ReplicaLogger};
+use ic_replicated_state::{canister_state::system_state::TotalQueryStats, ReplicatedState};
+use ic_types::{
+    batch::{CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload},
+    consensus::get_faults_tolerated,
+    CanisterId, NodeId,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The number of epochs after which an epoch is aggregated even if not enough
+/// nodes reported their statistics for it.
+const GRACE_PERIOD_EPOCHS: u64 = 2;
+
+/// Records the statistics reported in `query_stats` in the replicated state
+/// and aggregates all epochs that are complete into the canisters'
+/// `total_query_stats`.
+///
+/// An epoch is complete once
+/// - all nodes of the subnet reported their statistics for it; or
+/// - all but the tolerated number of faulty nodes reported their statistics
+///   for it and a later epoch was reported; or
+/// - an epoch at least [`GRACE_PERIOD_EPOCHS`] later was reported.
+pub fn deliver_query_stats(
+    query_stats: &QueryStatsPayload,
+    state: &mut ReplicatedState,
+    log: &ReplicaLogger,
+) {
+    let raw_query_stats = &mut state.metadata.raw_query_stats;
+    if raw_query_stats.has_report(query_stats.epoch, &query_stats.proposer) {
+        warn!(
+            log,
+            "Ignoring duplicate query stats of node {} for epoch {}",
+            query_stats.proposer,
+            query_stats.epoch
+        );
+        return;
+    }
+    raw_query_stats.insert(query_stats.clone());
+
+    let nodes = state
+        .metadata
+        .network_topology
+        .subnets
+        .get(&state.metadata.own_subnet_id)
+        .map(|subnet| subnet.nodes.clone())
+        .unwrap_or_default();
+
+    let quorum = nodes.len() - get_faults_tolerated(nodes.len());
+    let latest_epoch = *raw_query_stats
+        .reports
+        .keys()
+        .next_back()
+        .expect("The reported epoch was just inserted");
+    let complete_epochs: Vec<QueryStatsEpoch> = raw_query_stats
+        .reports
+        .iter()
+        .filter(|(epoch, reports)| {
+            nodes.iter().all(|node| reports.contains_key(node))
+                || (**epoch < latest_epoch && reports.len() >= quorum)
+                || epoch.get() + GRACE_PERIOD_EPOCHS <= latest_epoch.get()
+        })
+        .map(|(epoch, _)| *epoch)
+        .collect();
+
+    for epoch in complete_epochs {
+        let reports = raw_query_stats
+            .reports
+            .remove(&epoch)
+            .expect("The epoch was taken from the reports");
+        let num_nodes = nodes.len().max(reports.len());
+        for (canister_id, stats) in aggregate_epoch(reports, num_nodes) {
+            if let Some(canister) = state.canister_states.get_mut(&canister_id) {
+                let total = &mut canister.system_state.total_query_stats;
+                total.num_calls += stats.num_calls;
+                total.num_instructions += stats.num_instructions;
+                total.ingress_payload_size += stats.ingress_payload_size;
+                total.egress_payload_size += stats.egress_payload_size;
+            }
+        }
+        raw_query_stats.highest_aggregated_epoch =
+            raw_query_stats.highest_aggregated_epoch.max(Some(epoch));
+    }
+}
+
+/// Aggregates the reports of `num_nodes` nodes for an epoch.
+///
+/// For every canister and field, takes the median of the values reported by
+/// the nodes, where nodes that did not report a value count as zero, and
+/// extrapolates it to the whole subnet. Using the median ensures that a
+/// minority of nodes cannot skew the result arbitrarily.
+fn aggregate_epoch(
+    reports: BTreeMap<NodeId, Vec<CanisterQueryStats>>,
+    num_nodes: usize,
+) -> BTreeMap<CanisterId, TotalQueryStats> {
+    let mut stats_by_canister: BTreeMap<CanisterId, Vec<QueryStats>> = BTreeMap::new();
+    for stats in reports.into_values().flatten() {
+        stats_by_canister
+            .entry(stats.canister_id)
+            .or_default()
+            .push(stats.stats);
+    }
+
+    let median = |stats: &[QueryStats], field: fn(&QueryStats) -> u64| -> u128 {
+        let mut values: Vec<u64> = stats.iter().map(field).collect();
+        values.resize(num_nodes.max(values.len()), 0);
+        values.sort_unstable();
+        values[values.len() / 2] as u128 * values.len() as u128
+    };
+
+    stats_by_canister
+        .into_iter()
+        .map(|(canister_id, stats)| {
+            (
+                canister_id,
+                TotalQueryStats {
+                    num_calls: median(&stats, |stats| stats.num_calls as u64),
+                    num_instructions: median(&stats, |stats| stats.num_instructions),
+                    ingress_payload_size: median(&stats, |stats| stats.ingress_payload_size),
+                    egress_payload_size: median(&stats, |stats| stats.egress_payload_size),
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_replicated_state::SubnetTopology;
+    use ic_test_utilities::{
+        state::{CanisterStateBuilder, ReplicatedStateBuilder},
+        types::ids::{canister_test_id, node_test_id, subnet_test_id},
+    };
+
+    fn test_state() -> ReplicatedState {
+        let mut state = ReplicatedStateBuilder::new()
+            .with_subnet_id(subnet_test_id(1))
+            .with_canister(
+                CanisterStateBuilder::new()
+                    .with_canister_id(canister_test_id(1))
+                    .build(),
+            )
+            .build();
+        state.metadata.network_topology.subnets.insert(
+            subnet_test_id(1),
+            SubnetTopology {
+                nodes: (1..=4).map(node_test_id).collect(),
+                ..SubnetTopology::default()
+            },
+        );
+        state
+    }
+
+    fn report(epoch: u64, node: u64, num_instructions: u64) -> QueryStatsPayload {
+        QueryStatsPayload {
+            epoch: QueryStatsEpoch::from(epoch),
+            proposer: node_test_id(node),
+            stats: vec![CanisterQueryStats {
+                canister_id: canister_test_id(1),
+                stats: QueryStats {
+                    num_calls: 1,
+                    num_instructions,
+                    ingress_payload_size: 10,
+                    egress_payload_size: 20,
+                },
+            }],
+        }
+    }
+
+    fn total_query_stats(state: &ReplicatedState) -> &TotalQueryStats {
+        &state
+            .canister_state(&canister_test_id(1))
+            .unwrap()
+            .system_state
+            .total_query_stats
+    }
+
+    #[test]
+    fn aggregates_epoch_once_all_nodes_reported() {
+        let mut state = test_state();
+        let log = no_op_logger();
+
+        for (node, num_instructions) in [(1, 100), (2, 200), (3, 1_000_000)] {
+            deliver_query_stats(&report(0, node, num_instructions), &mut state, &log);
+            assert_eq!(total_query_stats(&state), &TotalQueryStats::default());
+        }
+        deliver_query_stats(&report(0, 4, 300), &mut state, &log);
+
+        // The median of the four reports is taken and multiplied by the
+        // number of nodes.
+        assert_eq!(
+            total_query_stats(&state),
+            &TotalQueryStats {
+                num_calls: 4,
+                num_instructions: 1_200,
+                ingress_payload_size: 40,
+                egress_payload_size: 80,
+            }
+        );
+        assert!(state.metadata.raw_query_stats.reports.is_empty());
+        assert_eq!(
+            state.metadata.raw_query_stats.highest_aggregated_epoch,
+            Some(QueryStatsEpoch::from(0))
+        );
+
+        // Late reports for an aggregated epoch are ignored.
+        deliver_query_stats(&report(0, 4, 300), &mut state, &log);
+        assert!(state.metadata.raw_query_stats.reports.is_empty());
+    }
+
+    #[test]
+    fn aggregates_epoch_once_a_quorum_reported_and_a_later_epoch_is_reported() {
+        let mut state = test_state();
+        let log = no_op_logger();
+
+        deliver_query_stats(&report(0, 1, 100), &mut state, &log);
+        deliver_query_stats(&report(0, 2, 200), &mut state, &log);
+        deliver_query_stats(&report(0, 3, 300), &mut state, &log);
+        deliver_query_stats(&report(1, 1, 100), &mut state, &log);
+
+        // Node 4 did not report epoch 0, so it counts as a zero.
+        assert_eq!(
+            total_query_stats(&state),
+            &TotalQueryStats {
+                num_calls: 4,
+                num_instructions: 800,
+                ingress_payload_size: 40,
+                egress_payload_size: 80,
+            }
+        );
+        assert_eq!(
+            state.metadata.raw_query_stats.highest_aggregated_epoch,
+            Some(QueryStatsEpoch::from(0))
+        );
+        assert_eq!(state.metadata.raw_query_stats.reports.len(), 1);
+    }
+
+    #[test]
+    fn single_node_cannot_close_epoch_before_grace_period() {
+        let mut state = test_state();
+        let log = no_op_logger();
+
+        deliver_query_stats(&report(0, 1, 100), &mut state, &log);
+        deliver_query_stats(&report(0, 2, 200), &mut state, &log);
+
+        // Node 1 moving on to epoch 1 does not close epoch 0, as only two out
+        // of four nodes reported it.
+        deliver_query_stats(&report(1, 1, 100), &mut state, &log);
+        assert_eq!(total_query_stats(&state), &TotalQueryStats::default());
+        assert_eq!(
+            state.metadata.raw_query_stats.highest_aggregated_epoch,
+            None
+        );
+
+        // A late report for epoch 0 is still accepted.
+        deliver_query_stats(&report(0, 3, 300), &mut state, &log);
+        assert_eq!(
+            state.metadata.raw_query_stats.highest_aggregated_epoch,
+            Some(QueryStatsEpoch::from(0))
+        );
+        assert_eq!(
+            total_query_stats(&state),
+            &TotalQueryStats {
+                num_calls: 4,
+                num_instructions: 800,
+                ingress_payload_size: 40,
+                egress_payload_size: 80,
+            }
+        );
+    }
+
+    #[test]
+    fn aggregates_epoch_after_grace_period() {
+        let mut state = test_state();
+        let log = no_op_logger();
+
+        deliver_query_stats(&report(0, 1, 100), &mut state, &log);
+        deliver_query_stats(&report(0, 2, 200), &mut state, &log);
+        deliver_query_stats(&report(1, 1, 100), &mut state, &log);
+        assert_eq!(
+            state.metadata.raw_query_stats.highest_aggregated_epoch,
+            None
+        );
+
+        deliver_query_stats(&report(GRACE_PERIOD_EPOCHS, 1, 100), &mut state, &log);
+
+        // Epoch 0 is aggregated with the nodes that did not report it
+        // counting as zeros, epoch 1 is still within its grace period.
+        assert_eq!(
+            state.metadata.raw_query_stats.highest_aggregated_epoch,
+            Some(QueryStatsEpoch::from(0))
+        );
+        assert_eq!(
+            total_query_stats(&state),
+            &TotalQueryStats {
+                num_calls: 4,
+                num_instructions: 400,
+                ingress_payload_size: 40,
+                egress_payload_size: 80,
+            }
+        );
+        assert_eq!(state.metadata.raw_query_stats.reports.len(), 2);
+    }
+}
@@ -0,0 +1,49 @@
+use ic_interfaces::{
+    batch_payload::{BatchPayloadBuilder, PastPayload, ProposalContext},
+    consensus::PayloadValidationError,
+};
+use ic_types::{
+    batch::{QueryStatsPayload, ValidationContext},
+    Height, NumBytes,
+};
+
+/// A [`BatchPayloadBuilder`] that includes a fixed query stats payload, if
+/// any, and accepts every payload.
+#[derive(Default)]
+pub struct FakeQueryStatsPayloadBuilder(Option<QueryStatsPayload>);
+
+impl FakeQueryStatsPayloadBuilder {
+    pub fn new() -> Self {
+        Self(None)
+    }
+
+    pub fn with_payload(mut self, payload: QueryStatsPayload) -> Self {
+        self.0 = Some(payload);
+        self
+    }
+}
+
+impl BatchPayloadBuilder for FakeQueryStatsPayloadBuilder {
+    fn build_payload(
+        &self,
+        _height: Height,
+        max_size: NumBytes,
+        _past_payloads: &[PastPayload],
+        _context: &ValidationContext,
+    ) -> Vec<u8> {
+        self.0
+            .as_ref()
+            .map(|payload| payload.serialize_with_limit(max_size))
+            .unwrap_or_default()
+    }
+
+    fn validate_payload(
+        &self,
+        _height: Height,
+        _payload: &[u8],
+        _past_payloads: &[PastPayload],
+        _proposal_context: &ProposalContext,
+    ) -> Result<(), PayloadValidationError> {
+        Ok(())
+    }
+}
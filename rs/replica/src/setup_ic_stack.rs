@@ -239,6 +239,10 @@ pub fn construct_ic_stack(
         registry.clone(),
         log.clone(),
     ));
+    // ---------- QUERY STATS DEPS FOLLOW -----------
+    let query_stats_payload_builder = execution_services
+        .query_stats_payload_builder
+        .into_payload_builder(state_manager.clone(), node_id, log.clone());
     // ---------- HTTPS OUTCALLS DEPS FOLLOW ----------
     let canister_http_adapter_client = setup_canister_http_client(
         rt_handle.clone(),
@@ -273,6 +277,7 @@ pub fn construct_ic_stack(
         P2PStateSyncClient::Client(state_sync),
         xnet_payload_builder,
         self_validating_payload_builder,
+        Arc::new(query_stats_payload_builder),
         message_router,
         // TODO(SCL-213)
         Arc::clone(&crypto) as Arc<_>,
@@ -297,6 +302,7 @@ pub fn construct_ic_stack(
         ingress_tx.clone(),
         time_source,
         Arc::clone(&state_manager) as Arc<_>,
+        state_manager.certified_height_watcher(),
         registry,
         Arc::clone(&crypto) as Arc<_>,
         Arc::clone(&crypto) as Arc<_>,
@@ -6,7 +6,7 @@ use ic_error_types::{ErrorCode, RejectCode};
 use ic_ic00_types::{
     self as ic00, CanisterChange, CanisterIdRecord, CanisterInstallMode,
     CanisterSettingsArgsBuilder, CanisterStatusResultV2, CanisterStatusType, EmptyBlob,
-    InstallCodeArgs, Method, Payload, UpdateSettingsArgs, IC_00,
+    InstallCodeArgs, Method, Payload, QueryStatsResponse, UpdateSettingsArgs, IC_00,
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_replica_tests as utils;
@@ -710,7 +710,12 @@ fn can_get_canister_information() {
                 ComputeAllocation::default().as_percent(),
                 None,
                 2592000,
+                None,
+                0,
+                0u128,
                 0u128,
+                None,
+                QueryStatsResponse::default(),
             )
         );
 
@@ -761,7 +766,12 @@ fn can_get_canister_information() {
                     ComputeAllocation::default().as_percent(),
                     None,
                     259200,
+                    None,
+                    0,
+                    0u128,
                     0u128,
+                    None,
+                    QueryStatsResponse::default(),
                 ),
                 CanisterStatusResultV2::decode(&res).unwrap(),
                 2 * BALANCE_EPSILON,
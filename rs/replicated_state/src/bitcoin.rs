@@ -6,7 +6,7 @@ use ic_btc_types_internal::{
 use ic_error_types::RejectCode;
 use ic_ic00_types::{BitcoinGetSuccessorsResponse, EmptyBlob, Payload as _};
 use ic_types::{
-    messages::{CallbackId, Payload, RejectContext, Response},
+    messages::{CallbackId, Payload, RejectContext, Response, NO_DEADLINE},
     CanisterId,
 };
 use std::cmp::min;
@@ -61,6 +61,7 @@ pub fn push_response(
                 originator_reply_callback: callback_id,
                 refund: context.request.take_cycles(),
                 response_payload,
+                deadline: NO_DEADLINE,
             });
 
             Ok(())
@@ -87,6 +88,7 @@ pub fn push_response(
                 originator_reply_callback: callback_id,
                 refund: context.request.take_cycles(),
                 response_payload,
+                deadline: NO_DEADLINE,
             });
 
             Ok(())
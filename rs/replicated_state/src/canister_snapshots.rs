@@ -0,0 +1,207 @@
+use crate::{canister_state::execution_state::Memory, num_bytes_try_from, CanisterState, Global};
+use ic_ic00_types::SnapshotId;
+use ic_types::{CanisterId, NumBytes, Time};
+use ic_wasm_types::CanisterModule;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// A snapshot of the state of a canister, taken through the management
+/// canister's `take_canister_snapshot` method.
+///
+/// A snapshot contains everything needed to restore the canister's execution
+/// state: the Wasm module, the heap, the stable memory and the exported
+/// globals, along with the certified data. Snapshots are immutable once taken.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanisterSnapshot {
+    /// The canister the snapshot was taken of.
+    canister_id: CanisterId,
+    /// The time at which the snapshot was taken.
+    taken_at_timestamp: Time,
+    /// The version of the canister at the time the snapshot was taken.
+    canister_version: u64,
+    /// The certified data of the canister.
+    certified_data: Vec<u8>,
+    /// The Wasm module of the canister.
+    binary: CanisterModule,
+    /// The exported globals of the canister.
+    exported_globals: Vec<Global>,
+    /// The Wasm heap of the canister.
+    wasm_memory: Memory,
+    /// The stable memory of the canister.
+    stable_memory: Memory,
+}
+
+impl CanisterSnapshot {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        canister_id: CanisterId,
+        taken_at_timestamp: Time,
+        canister_version: u64,
+        certified_data: Vec<u8>,
+        binary: CanisterModule,
+        exported_globals: Vec<Global>,
+        wasm_memory: Memory,
+        stable_memory: Memory,
+    ) -> Self {
+        Self {
+            canister_id,
+            taken_at_timestamp,
+            canister_version,
+            certified_data,
+            binary,
+            exported_globals,
+            wasm_memory,
+            stable_memory,
+        }
+    }
+
+    /// Takes a snapshot of the given canister. Returns `None` if the canister
+    /// has no execution state, i.e. it is empty.
+    ///
+    /// Copying the memories is cheap, since `PageMap`s are persistent data
+    /// structures.
+    pub fn from_canister(canister: &CanisterState, taken_at_timestamp: Time) -> Option<Self> {
+        let execution_state = canister.execution_state.as_ref()?;
+        Some(Self {
+            canister_id: canister.canister_id(),
+            taken_at_timestamp,
+            canister_version: canister.system_state.canister_version,
+            certified_data: canister.system_state.certified_data.clone(),
+            binary: execution_state.wasm_binary.binary.clone(),
+            exported_globals: execution_state.exported_globals.clone(),
+            wasm_memory: Memory::new(
+                execution_state.wasm_memory.page_map.clone(),
+                execution_state.wasm_memory.size,
+            ),
+            stable_memory: Memory::new(
+                execution_state.stable_memory.page_map.clone(),
+                execution_state.stable_memory.size,
+            ),
+        })
+    }
+
+    pub fn canister_id(&self) -> CanisterId {
+        self.canister_id
+    }
+
+    pub fn taken_at_timestamp(&self) -> Time {
+        self.taken_at_timestamp
+    }
+
+    pub fn canister_version(&self) -> u64 {
+        self.canister_version
+    }
+
+    pub fn certified_data(&self) -> &Vec<u8> {
+        &self.certified_data
+    }
+
+    pub fn binary(&self) -> &CanisterModule {
+        &self.binary
+    }
+
+    pub fn exported_globals(&self) -> &Vec<Global> {
+        &self.exported_globals
+    }
+
+    pub fn wasm_memory(&self) -> &Memory {
+        &self.wasm_memory
+    }
+
+    pub fn stable_memory(&self) -> &Memory {
+        &self.stable_memory
+    }
+
+    /// Returns the size of the snapshot in bytes, computed the same way as the
+    /// memory usage of an `ExecutionState`.
+    pub fn size(&self) -> NumBytes {
+        // We use 8 bytes per global.
+        let globals_size_bytes = 8 * self.exported_globals.len() as u64;
+        num_bytes_try_from(self.wasm_memory.size)
+            .expect("could not convert from wasm memory number of pages to bytes")
+            + num_bytes_try_from(self.stable_memory.size)
+                .expect("could not convert from stable memory number of pages to bytes")
+            + NumBytes::from(globals_size_bytes)
+            + NumBytes::from(self.binary.len() as u64)
+            + NumBytes::from(self.certified_data.len() as u64)
+    }
+}
+
+/// The snapshots of all canisters on the subnet, indexed by snapshot id.
+///
+/// Snapshot ids are ordered by canister id first, so the snapshots of a given
+/// canister are always adjacent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CanisterSnapshots {
+    snapshots: BTreeMap<SnapshotId, Arc<CanisterSnapshot>>,
+}
+
+impl CanisterSnapshots {
+    pub fn new(snapshots: BTreeMap<SnapshotId, Arc<CanisterSnapshot>>) -> Self {
+        Self { snapshots }
+    }
+
+    /// Adds a new snapshot, replacing any existing snapshot with the same id.
+    pub fn push(&mut self, snapshot_id: SnapshotId, snapshot: Arc<CanisterSnapshot>) {
+        self.snapshots.insert(snapshot_id, snapshot);
+    }
+
+    /// Returns the snapshot with the given id, if it exists.
+    pub fn get(&self, snapshot_id: &SnapshotId) -> Option<&Arc<CanisterSnapshot>> {
+        self.snapshots.get(snapshot_id)
+    }
+
+    /// Removes the snapshot with the given id and returns it, if it existed.
+    pub fn remove(&mut self, snapshot_id: &SnapshotId) -> Option<Arc<CanisterSnapshot>> {
+        self.snapshots.remove(snapshot_id)
+    }
+
+    /// Returns all snapshots of the given canister, ordered by snapshot id.
+    pub fn list_snapshots(
+        &self,
+        canister_id: CanisterId,
+    ) -> Vec<(SnapshotId, Arc<CanisterSnapshot>)> {
+        self.snapshots
+            .range(SnapshotId::new(canister_id, 0)..=SnapshotId::new(canister_id, u64::MAX))
+            .map(|(snapshot_id, snapshot)| (*snapshot_id, Arc::clone(snapshot)))
+            .collect()
+    }
+
+    /// Returns the number of snapshots of the given canister.
+    pub fn count_snapshots(&self, canister_id: CanisterId) -> usize {
+        self.snapshots
+            .range(SnapshotId::new(canister_id, 0)..=SnapshotId::new(canister_id, u64::MAX))
+            .count()
+    }
+
+    /// Returns the total size of the snapshots of the given canister.
+    pub fn total_size(&self, canister_id: CanisterId) -> NumBytes {
+        self.snapshots
+            .range(SnapshotId::new(canister_id, 0)..=SnapshotId::new(canister_id, u64::MAX))
+            .map(|(_, snapshot)| snapshot.size())
+            .sum()
+    }
+
+    /// Removes all snapshots of the given canister, e.g. when the canister is
+    /// deleted.
+    pub fn delete_snapshots(&mut self, canister_id: CanisterId) {
+        self.snapshots
+            .retain(|snapshot_id, _| snapshot_id.get_canister_id() != canister_id);
+    }
+
+    /// Retains only the snapshots for which the predicate on the canister id
+    /// holds.
+    pub fn retain_canisters<F: Fn(&CanisterId) -> bool>(&mut self, f: F) {
+        self.snapshots
+            .retain(|snapshot_id, _| f(&snapshot_id.get_canister_id()));
+    }
+
+    /// Returns an iterator over all snapshots, ordered by snapshot id.
+    pub fn iter(&self) -> impl Iterator<Item = (&SnapshotId, &Arc<CanisterSnapshot>)> {
+        self.snapshots.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
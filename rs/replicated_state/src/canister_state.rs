@@ -199,6 +199,7 @@ impl CanisterState {
             (None, true) => NextExecution::StartNew,
             (Some(ExecutionTask::Heartbeat), _) => NextExecution::StartNew,
             (Some(ExecutionTask::GlobalTimer), _) => NextExecution::StartNew,
+            (Some(ExecutionTask::OnLowWasmMemory), _) => NextExecution::StartNew,
             (Some(ExecutionTask::AbortedExecution { .. }), _)
             | (Some(ExecutionTask::PausedExecution(..)), _) => NextExecution::ContinueLong,
             (Some(ExecutionTask::AbortedInstallCode { .. }), _)
@@ -218,6 +219,7 @@ impl CanisterState {
             None
             | Some(ExecutionTask::Heartbeat)
             | Some(ExecutionTask::GlobalTimer)
+            | Some(ExecutionTask::OnLowWasmMemory)
             | Some(ExecutionTask::PausedExecution(..))
             | Some(ExecutionTask::PausedInstallCode(..))
             | Some(ExecutionTask::AbortedInstallCode { .. }) => false,
@@ -231,6 +233,7 @@ impl CanisterState {
             None
             | Some(ExecutionTask::Heartbeat)
             | Some(ExecutionTask::GlobalTimer)
+            | Some(ExecutionTask::OnLowWasmMemory)
             | Some(ExecutionTask::PausedInstallCode(..))
             | Some(ExecutionTask::AbortedExecution { .. })
             | Some(ExecutionTask::AbortedInstallCode { .. }) => false,
@@ -244,6 +247,7 @@ impl CanisterState {
             None
             | Some(ExecutionTask::Heartbeat)
             | Some(ExecutionTask::GlobalTimer)
+            | Some(ExecutionTask::OnLowWasmMemory)
             | Some(ExecutionTask::PausedExecution(..))
             | Some(ExecutionTask::AbortedExecution { .. })
             | Some(ExecutionTask::AbortedInstallCode { .. }) => false,
@@ -257,6 +261,7 @@ impl CanisterState {
             None
             | Some(ExecutionTask::Heartbeat)
             | Some(ExecutionTask::GlobalTimer)
+            | Some(ExecutionTask::OnLowWasmMemory)
             | Some(ExecutionTask::PausedExecution(..))
             | Some(ExecutionTask::PausedInstallCode(..))
             | Some(ExecutionTask::AbortedExecution { .. }) => false,
@@ -383,10 +388,13 @@ impl CanisterState {
 
     /// The amount of memory currently being used by the canister.
     ///
-    /// This only includes execution memory (heap, stable, globals, Wasm)
-    /// and canister history memory.
+    /// This only includes execution memory (heap, stable, globals, Wasm),
+    /// canister history memory, the Wasm chunk store and the snapshots.
     pub fn memory_usage(&self) -> NumBytes {
-        self.raw_memory_usage() + self.canister_history_memory_usage()
+        self.raw_memory_usage()
+            + self.canister_history_memory_usage()
+            + self.wasm_chunk_store_memory_usage()
+            + self.snapshots_memory_usage()
     }
 
     /// Returns the amount of raw memory currently used by the canister in bytes.
@@ -398,6 +406,16 @@ impl CanisterState {
             .map_or(NumBytes::from(0), |es| es.memory_usage())
     }
 
+    /// Returns the size of the canister's Wasm memory (heap) in bytes.
+    pub fn wasm_memory_usage(&self) -> NumBytes {
+        self.execution_state
+            .as_ref()
+            .map_or(NumBytes::from(0), |es| {
+                num_bytes_try_from(es.wasm_memory.size)
+                    .expect("could not convert from wasm memory number of pages to bytes")
+            })
+    }
+
     /// Returns the amount of canister message memory used by the canister in bytes.
     pub fn message_memory_usage(&self) -> NumBytes {
         self.system_state.message_memory_usage()
@@ -416,6 +434,23 @@ impl CanisterState {
         self.system_state.canister_history_memory_usage()
     }
 
+    /// Returns the amount of memory used by the Wasm chunk store in bytes.
+    pub fn wasm_chunk_store_memory_usage(&self) -> NumBytes {
+        self.system_state.wasm_chunk_store_memory_usage()
+    }
+
+    /// Returns the amount of memory used by the snapshots of the canister in
+    /// bytes.
+    pub fn snapshots_memory_usage(&self) -> NumBytes {
+        self.system_state.snapshots_memory_usage()
+    }
+
+    /// Sets the (transient) total size in bytes of the snapshots of this
+    /// canister.
+    pub(super) fn set_snapshots_memory_usage(&mut self, size: NumBytes) {
+        self.system_state.set_snapshots_memory_usage(size);
+    }
+
     /// Sets the (transient) size in bytes of responses from this canister
     /// routed into streams and not yet garbage collected.
     pub(super) fn set_stream_responses_size_bytes(&mut self, size_bytes: usize) {
@@ -454,6 +489,20 @@ impl CanisterState {
         self.exports_method(&WasmMethod::System(SystemMethod::CanisterGlobalTimer))
     }
 
+    /// Returns true if the canister exports the `canister_on_low_wasm_memory`
+    /// system method.
+    pub fn exports_on_low_wasm_memory_method(&self) -> bool {
+        self.exports_method(&WasmMethod::System(SystemMethod::CanisterOnLowWasmMemory))
+    }
+
+    /// Re-evaluates the condition of the `canister_on_low_wasm_memory` hook
+    /// against the current Wasm memory usage.
+    pub fn update_on_low_wasm_memory_hook_condition(&mut self) {
+        let wasm_memory_usage = self.wasm_memory_usage();
+        self.system_state
+            .update_on_low_wasm_memory_hook_condition(wasm_memory_usage);
+    }
+
     /// Returns true if the canister exports the given Wasm method.
     pub fn exports_method(&self, method: &WasmMethod) -> bool {
         match &self.execution_state {
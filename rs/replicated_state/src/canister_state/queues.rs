@@ -15,7 +15,7 @@ use ic_protobuf::{
 };
 use ic_types::{
     messages::{
-        Ingress, Payload, RejectContext, Request, RequestOrResponse, Response,
+        CallbackId, Ingress, Payload, RejectContext, Request, RequestOrResponse, Response,
         MAX_RESPONSE_COUNT_BYTES,
     },
     xnet::{QueueId, SessionId},
@@ -561,6 +561,9 @@ impl CanisterQueues {
     /// Pushes a `Request` type message into the relevant output queue. Also
     /// reserves a slot for the eventual response on the matching input queue.
     ///
+    /// The request times out after `REQUEST_LIFETIME` or, for best-effort
+    /// requests, at its deadline, whichever comes first.
+    ///
     /// # Errors
     ///
     /// Returns a `QueueFull` error along with the provided message if either
@@ -583,8 +586,12 @@ impl CanisterQueues {
         let oq_stats_delta =
             OutputQueuesStats::stats_delta(&RequestOrResponse::Request(msg.clone()));
 
+        let mut deadline = time + REQUEST_LIFETIME;
+        if msg.is_best_effort() {
+            deadline = deadline.min(msg.deadline.into());
+        }
         output_queue
-            .push_request(msg, time + REQUEST_LIFETIME)
+            .push_request(msg, deadline)
             .expect("cannot fail due to the checks above");
 
         self.input_queues_stats.reserved_slots += 1;
@@ -628,6 +635,7 @@ impl CanisterQueues {
             originator_reply_callback: request.sender_reply_callback,
             refund: request.payment,
             response_payload: Payload::Reject(reject_context),
+            deadline: request.deadline,
         }));
         self.push_input(response, InputQueueType::LocalSubnet)
             .map_err(|(e, _msg)| e)
@@ -698,19 +706,38 @@ impl CanisterQueues {
         self.push_input(msg, InputQueueType::LocalSubnet)
             .map_err(|_| ())?;
 
-        let msg = self
-            .canister_queues
-            .get_mut(&own_canister_id)
-            .expect("Output queue existed above so should not fail.")
-            .1
-            .pop()
+        self.pop_output(&own_canister_id)
             .expect("Message peeked above so pop should not fail.");
-        let oq_stats_delta = OutputQueuesStats::stats_delta(&msg);
-        self.output_queues_stats -= oq_stats_delta;
+
+        Ok(())
+    }
+
+    /// Pops the message at the head of the output queue to `canister_id`, if
+    /// any.
+    pub(super) fn pop_output(&mut self, canister_id: &CanisterId) -> Option<RequestOrResponse> {
+        let msg = self.canister_queues.get_mut(canister_id)?.1.pop()?;
+
+        self.output_queues_stats -= OutputQueuesStats::stats_delta(&msg);
         self.memory_usage_stats -= MemoryUsageStats::stats_delta(QueueOp::Pop, &msg);
         debug_assert!(self.stats_ok());
 
-        Ok(())
+        Some(msg)
+    }
+
+    /// Returns `true` if a response from `respondent` for the callback with the
+    /// given ID is already enqueued in the respective input queue.
+    ///
+    /// Time complexity: O(num_messages) in the input queue from `respondent`.
+    pub(super) fn has_enqueued_response(
+        &self,
+        respondent: &CanisterId,
+        callback_id: CallbackId,
+    ) -> bool {
+        self.canister_queues
+            .get(respondent)
+            .map_or(false, |(input_queue, _)| {
+                input_queue.has_response_for_callback(callback_id)
+            })
     }
 
     /// Returns the number of enqueued ingress messages.
@@ -785,6 +812,12 @@ impl CanisterQueues {
         self.memory_usage_stats.oversized_requests_extra_bytes
     }
 
+    /// Returns the total byte size of best-effort requests across input and
+    /// output queues.
+    pub fn best_effort_requests_size_bytes(&self) -> usize {
+        self.memory_usage_stats.best_effort_requests_size_bytes
+    }
+
     /// Sets the (transient) size in bytes of responses routed from
     /// `output_queues` into streams and not yet garbage collected.
     pub(super) fn set_stream_responses_size_bytes(&mut self, size_bytes: usize) {
@@ -982,16 +1015,25 @@ impl CanisterQueues {
             }
             RequestOrResponse::Response(_) => 0,
         };
+        // Actual byte size for best-effort requests, 0 for other messages.
+        let best_effort_request_size_bytes = |msg: &RequestOrResponse| match msg {
+            RequestOrResponse::Request(req) if req.is_best_effort() => msg.count_bytes(),
+            _ => 0,
+        };
 
         let mut stats = MemoryUsageStats::default();
         for (iq, oq) in canister_queues.values() {
             stats.responses_size_bytes += iq.calculate_stat_sum(response_size_bytes);
             stats.reserved_slots += iq.reserved_slots() as i64;
             stats.oversized_requests_extra_bytes += iq.calculate_stat_sum(request_overhead_bytes);
+            stats.best_effort_requests_size_bytes +=
+                iq.calculate_stat_sum(best_effort_request_size_bytes);
 
             stats.responses_size_bytes += oq.calculate_stat_sum(response_size_bytes);
             stats.reserved_slots += oq.reserved_slots() as i64;
-            stats.oversized_requests_extra_bytes += oq.calculate_stat_sum(request_overhead_bytes)
+            stats.oversized_requests_extra_bytes += oq.calculate_stat_sum(request_overhead_bytes);
+            stats.best_effort_requests_size_bytes +=
+                oq.calculate_stat_sum(best_effort_request_size_bytes);
         }
         stats
     }
@@ -1054,6 +1096,104 @@ impl CanisterQueues {
         timed_out_requests_count
     }
 
+    /// Sheds the largest best-effort request across input and output queues, in
+    /// order to reduce the memory used by best-effort messages. Returns the
+    /// shed request; or `None` if there are no best-effort requests.
+    ///
+    ///  * A shed output request is rejected with `SYS_UNKNOWN`, by enqueuing a
+    ///    reject response refunding its payment into the matching input queue
+    ///    (same as if it had timed out).
+    ///  * A shed input request is dropped, releasing the output queue slot
+    ///    reserved for its response. Its payment is lost and the originator
+    ///    eventually gets a `SYS_UNKNOWN` reject when its callback expires.
+    ///
+    /// Best-effort responses are never shed, as they occupy slots (and memory)
+    /// already reserved when the respective request was enqueued.
+    ///
+    /// Updating the correct input queues schedule after enqueuing a reject response into a
+    /// previously empty queue also requires the full set of local canisters to decide whether
+    /// the destination canister was local or remote.
+    ///
+    /// Time complexity: O(num_messages).
+    pub fn shed_largest_best_effort_request(
+        &mut self,
+        own_canister_id: &CanisterId,
+        local_canisters: &BTreeMap<CanisterId, CanisterState>,
+    ) -> Option<Arc<Request>> {
+        // Find the largest best-effort request: its size, the canister whose queues
+        // hold it, whether it is an input request and its index in the queue.
+        let mut largest: Option<(usize, CanisterId, bool, usize)> = None;
+        for (canister_id, (input_queue, output_queue)) in self.canister_queues.iter() {
+            let candidates = [
+                input_queue
+                    .largest_best_effort_request()
+                    .map(|(index, size_bytes)| (size_bytes, *canister_id, true, index)),
+                output_queue
+                    .largest_best_effort_request()
+                    .map(|(index, size_bytes)| (size_bytes, *canister_id, false, index)),
+            ];
+            for candidate in candidates.into_iter().flatten() {
+                if largest.map_or(true, |(largest_size, ..)| candidate.0 > largest_size) {
+                    largest = Some(candidate);
+                }
+            }
+        }
+        let (_, canister_id, is_input, index) = largest?;
+        let (input_queue, output_queue) = self.canister_queues.get_mut(&canister_id).unwrap();
+
+        let request = if is_input {
+            let request = input_queue.remove_request(index);
+            output_queue.release_reserved_slot();
+
+            // Request was dropped, update stats.
+            let msg = RequestOrResponse::Request(Arc::clone(&request));
+            self.input_queues_stats -= InputQueuesStats::stats_delta(QueueOp::Pop, &msg);
+            self.memory_usage_stats -= MemoryUsageStats::stats_delta(QueueOp::Pop, &msg);
+            self.memory_usage_stats -= MemoryUsageStats::response_slot_delta();
+
+            // If the input queue is now empty, remove it from the input schedule.
+            if input_queue.num_messages() == 0 {
+                self.local_subnet_input_schedule
+                    .retain(|sender| sender != &canister_id);
+                self.remote_subnet_input_schedule
+                    .retain(|sender| sender != &canister_id);
+            }
+
+            request
+        } else {
+            let request = output_queue.take_request(index);
+            let response = generate_reject_response(&request, "Request was shed.");
+
+            // Request was dropped, update stats.
+            let msg = RequestOrResponse::Request(Arc::clone(&request));
+            self.memory_usage_stats -= MemoryUsageStats::stats_delta(QueueOp::Pop, &msg);
+            self.output_queues_stats -= OutputQueuesStats::stats_delta(&msg);
+
+            // Push response, update stats.
+            let iq_stats_delta = InputQueuesStats::stats_delta(QueueOp::Push, &response);
+            let mu_stats_delta = MemoryUsageStats::stats_delta(QueueOp::Push, &response);
+            input_queue.push(response).unwrap();
+            self.input_queues_stats += iq_stats_delta;
+            self.memory_usage_stats += mu_stats_delta;
+
+            // If this was a previously empty input queue, add it to input queue schedule.
+            if input_queue.num_messages() == 1 {
+                if &canister_id == own_canister_id || local_canisters.contains_key(&canister_id) {
+                    self.local_subnet_input_schedule.push_back(canister_id);
+                } else {
+                    self.remote_subnet_input_schedule.push_back(canister_id);
+                }
+            }
+
+            request
+        };
+
+        debug_assert!(self.stats_ok());
+        debug_assert!(self.schedules_ok(own_canister_id, local_canisters));
+
+        Some(request)
+    }
+
     /// Re-partitions `self.local_subnet_input_schedule` and
     /// `self.remote_subnet_input_schedule` based on the set of all local canisters
     /// plus `own_canister_id` (since Rust's ownership rules would prevent us from
@@ -1099,17 +1239,35 @@ impl CanisterQueues {
 }
 
 /// Generates a timeout reject response from a request, refunding its payment.
+///
+/// Best-effort requests are rejected with `SYS_UNKNOWN`, as the caller cannot
+/// tell whether a best-effort request was delivered before its deadline.
 fn generate_timeout_response(request: &Arc<Request>) -> RequestOrResponse {
+    generate_reject_response(request, "Request timed out.")
+}
+
+/// Generates a reject response with the given message from a request that was
+/// dropped from an output queue, refunding its payment.
+///
+/// Best-effort requests are rejected with `SYS_UNKNOWN`, guaranteed response
+/// requests with `SYS_TRANSIENT`.
+fn generate_reject_response(request: &Arc<Request>, message: &str) -> RequestOrResponse {
+    let reject_code = if request.is_best_effort() {
+        RejectCode::SysUnknown
+    } else {
+        RejectCode::SysTransient
+    };
     RequestOrResponse::Response(Arc::new(Response {
         originator: request.sender,
         respondent: request.receiver,
         originator_reply_callback: request.sender_reply_callback,
         refund: request.payment,
         response_payload: Payload::Reject(RejectContext::new_with_message_length_limit(
-            RejectCode::SysTransient,
-            "Request timed out.".to_string(),
+            reject_code,
+            message.to_string(),
             MR_SYNTHETIC_REJECT_MESSAGE_MAX_LEN,
         )),
+        deadline: request.deadline,
     }))
 }
 
@@ -1366,6 +1524,11 @@ struct MemoryUsageStats {
     /// `MAX_RESPONSE_COUNT_BYTES`.
     oversized_requests_extra_bytes: usize,
 
+    /// Sum total of the byte size of every best-effort request across input
+    /// and output queues. Not part of `memory_usage()`: best-effort requests
+    /// are instead limited by shedding them when over capacity.
+    best_effort_requests_size_bytes: usize,
+
     /// Transient: size in bytes of responses routed from `output_queues` into
     /// streams and not yet garbage collected.
     ///
@@ -1406,6 +1569,11 @@ impl MemoryUsageStats {
             oversized_requests_extra_bytes: req
                 .count_bytes()
                 .saturating_sub(MAX_RESPONSE_COUNT_BYTES),
+            best_effort_requests_size_bytes: if req.is_best_effort() {
+                req.count_bytes()
+            } else {
+                0
+            },
             transient_stream_responses_size_bytes: 0,
         }
     }
@@ -1423,6 +1591,7 @@ impl MemoryUsageStats {
             },
             // No change in requests overhead (as this is a response).
             oversized_requests_extra_bytes: 0,
+            best_effort_requests_size_bytes: 0,
             transient_stream_responses_size_bytes: 0,
         }
     }
@@ -1434,6 +1603,7 @@ impl MemoryUsageStats {
             responses_size_bytes: 0,
             reserved_slots: 1,
             oversized_requests_extra_bytes: 0,
+            best_effort_requests_size_bytes: 0,
             transient_stream_responses_size_bytes: 0,
         }
     }
@@ -1444,6 +1614,7 @@ impl AddAssign<MemoryUsageStats> for MemoryUsageStats {
         self.responses_size_bytes += rhs.responses_size_bytes;
         self.reserved_slots += rhs.reserved_slots;
         self.oversized_requests_extra_bytes += rhs.oversized_requests_extra_bytes;
+        self.best_effort_requests_size_bytes += rhs.best_effort_requests_size_bytes;
         debug_assert!(self.reserved_slots >= 0);
     }
 }
@@ -1453,6 +1624,7 @@ impl SubAssign<MemoryUsageStats> for MemoryUsageStats {
         self.responses_size_bytes -= rhs.responses_size_bytes;
         self.reserved_slots -= rhs.reserved_slots;
         self.oversized_requests_extra_bytes -= rhs.oversized_requests_extra_bytes;
+        self.best_effort_requests_size_bytes -= rhs.best_effort_requests_size_bytes;
         debug_assert!(self.reserved_slots >= 0);
     }
 }
@@ -1463,6 +1635,7 @@ impl PartialEq for MemoryUsageStats {
         self.responses_size_bytes == rhs.responses_size_bytes
             && self.reserved_slots == rhs.reserved_slots
             && self.oversized_requests_extra_bytes == rhs.oversized_requests_extra_bytes
+            && self.best_effort_requests_size_bytes == rhs.best_effort_requests_size_bytes
     }
 }
 
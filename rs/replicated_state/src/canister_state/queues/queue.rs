@@ -4,7 +4,7 @@ mod tests;
 
 use ic_protobuf::proxy::ProxyDecodeError;
 use ic_protobuf::state::{ingress::v1 as pb_ingress, queues::v1 as pb_queues};
-use ic_types::messages::{Ingress, Request, RequestOrResponse, Response};
+use ic_types::messages::{CallbackId, Ingress, Request, RequestOrResponse, Response};
 use ic_types::{CountBytes, Cycles, Time};
 use std::{
     collections::VecDeque,
@@ -183,6 +183,33 @@ impl<T: QueueItem<T> + std::clone::Clone> QueueWithReservation<T> {
         msg
     }
 
+    /// Removes the item at `index` from the queue, releasing its slot. Returns
+    /// `None` if `index` is out of bounds.
+    fn remove(&mut self, index: usize) -> Option<T> {
+        let msg = self.queue.remove(index);
+        if let Some(msg) = &msg {
+            if msg.is_response() {
+                self.num_response_slots = self.num_response_slots.checked_sub(1).unwrap();
+            } else {
+                self.num_request_slots = self.num_request_slots.checked_sub(1).unwrap();
+            }
+        }
+        debug_assert!(self.check_invariants());
+        msg
+    }
+
+    /// Releases a reserved response slot, e.g. because the request that the
+    /// expected response would have answered was dropped.
+    ///
+    /// # Panics
+    ///
+    /// If there are no reserved slots.
+    fn release_reserved_slot(&mut self) {
+        assert!(self.reserved_slots() > 0, "No reserved slot to release");
+        self.num_response_slots -= 1;
+        debug_assert!(self.check_invariants());
+    }
+
     /// Returns a reference to the next item in the queue; or `None` if
     /// the queue is empty.
     fn peek(&self) -> Option<&T> {
@@ -378,6 +405,39 @@ impl InputQueue {
         self.queue.queue.len()
     }
 
+    /// Returns the index and byte size of the largest best-effort request in
+    /// the queue; or `None` if there are no best-effort requests.
+    ///
+    /// Time complexity: O(num_messages).
+    pub(super) fn largest_best_effort_request(&self) -> Option<(usize, usize)> {
+        largest_best_effort_request(self.queue.queue.iter().map(Some))
+    }
+
+    /// Removes the request at `index` from the queue, releasing its slot.
+    ///
+    /// # Panics
+    ///
+    /// If there is no request at `index`.
+    pub(super) fn remove_request(&mut self, index: usize) -> Arc<Request> {
+        match self.queue.remove(index) {
+            Some(RequestOrResponse::Request(request)) => request,
+            _ => panic!("No request at index {} of input queue", index),
+        }
+    }
+
+    /// Returns `true` if the queue holds a response for the callback with the
+    /// given ID.
+    ///
+    /// Time complexity: O(num_messages).
+    pub(super) fn has_response_for_callback(&self, callback_id: CallbackId) -> bool {
+        self.queue.queue.iter().any(|msg| match msg {
+            RequestOrResponse::Response(response) => {
+                response.originator_reply_callback == callback_id
+            }
+            RequestOrResponse::Request(_) => false,
+        })
+    }
+
     /// Returns the number of reserved slots in the queue.
     pub(super) fn reserved_slots(&self) -> usize {
         self.queue.reserved_slots()
@@ -677,6 +737,43 @@ impl OutputQueue {
         self.queue.calculate_stat_sum(stat)
     }
 
+    /// Returns the index (relative to the front of the queue) and byte size of
+    /// the largest best-effort request in the queue; or `None` if there are no
+    /// best-effort requests.
+    ///
+    /// Time complexity: O(num_messages).
+    pub(super) fn largest_best_effort_request(&self) -> Option<(usize, usize)> {
+        largest_best_effort_request(self.queue.queue.iter().map(Option::as_ref))
+    }
+
+    /// Takes the request at `index` (relative to the front of the queue) out of
+    /// the queue, leaving an empty slot in its place, same as timing it out.
+    ///
+    /// # Panics
+    ///
+    /// If there is no request at `index`.
+    pub(super) fn take_request(&mut self, index: usize) -> Arc<Request> {
+        let request = match self.queue.queue.get_mut(index) {
+            Some(item @ Some(RequestOrResponse::Request(_))) => match item.take() {
+                Some(RequestOrResponse::Request(request)) => request,
+                _ => unreachable!(),
+            },
+            _ => panic!("No request at index {} of output queue", index),
+        };
+
+        self.num_messages -= 1;
+        self.advance_to_next_message();
+        debug_assert!(self.check_invariants());
+
+        request
+    }
+
+    /// Releases a slot reserved for a response, because the request it would
+    /// have answered was dropped.
+    pub(super) fn release_reserved_slot(&mut self) {
+        self.queue.release_reserved_slot()
+    }
+
     /// Returns true if there are any expired deadlines at `current_time`, false otherwise.
     pub(super) fn has_expired_deadlines(&self, current_time: Time) -> bool {
         match self.deadline_range_ends.front() {
@@ -702,6 +799,26 @@ impl OutputQueue {
     }
 }
 
+/// Returns the index and byte size of the largest best-effort request among
+/// `items`; or `None` if there are no best-effort requests. On ties, the
+/// earliest such request is returned.
+fn largest_best_effort_request<'a>(
+    items: impl Iterator<Item = Option<&'a RequestOrResponse>>,
+) -> Option<(usize, usize)> {
+    let mut largest: Option<(usize, usize)> = None;
+    for (index, item) in items.enumerate() {
+        if let Some(RequestOrResponse::Request(request)) = item {
+            let size_bytes = request.count_bytes();
+            if request.is_best_effort()
+                && largest.map_or(true, |(_, largest_size)| size_bytes > largest_size)
+            {
+                largest = Some((index, size_bytes));
+            }
+        }
+    }
+    largest
+}
+
 /// Iterator over timed out requests in an OutputQueue.
 ///
 /// This extracts timed out requests by removing them from the queue,
@@ -16,7 +16,10 @@ use ic_test_utilities::{
         messages::{IngressBuilder, RequestBuilder, ResponseBuilder},
     },
 };
-use ic_types::{messages::CallbackId, time::expiry_time_from_now};
+use ic_types::{
+    messages::{CallbackId, NO_DEADLINE},
+    time::{expiry_time_from_now, CoarseTime},
+};
 use maplit::btreemap;
 use proptest::prelude::*;
 use std::convert::TryInto;
@@ -95,7 +98,7 @@ impl CanisterQueuesFixture {
         let local_canisters = maplit::btreemap! {
             self.this => {
                 let scheduler_state = SchedulerState::default();
-                let system_state = SystemState::new_running(
+                let system_state = SystemState::new_running_for_testing(
                     CanisterId::from_u64(42),
                     user_test_id(24).get(),
                     Cycles::new(1 << 36),
@@ -576,7 +579,8 @@ fn test_split_input_schedules() {
     assert_eq!(vec![other_4, other_5], queues.remote_schedule());
 
     // After the split we only have `other_1` (and `this`) on the subnet.
-    let system_state = SystemState::new_running(other_1, other_1.get(), Cycles::zero(), 0.into());
+    let system_state =
+        SystemState::new_running_for_testing(other_1, other_1.get(), Cycles::zero(), 0.into());
     let scheduler_state = SchedulerState::new(mock_time());
     let local_canisters = btreemap! {
         other_1 => CanisterState::new(system_state, None, scheduler_state)
@@ -1051,6 +1055,7 @@ fn test_stats() {
         reserved_slots: -1,
         responses_size_bytes: msg_size[3],
         oversized_requests_extra_bytes: 0,
+        best_effort_requests_size_bytes: 0,
         transient_stream_responses_size_bytes: 0,
     };
     assert_eq!(expected_mu_stats, queues.memory_usage_stats);
@@ -1161,6 +1166,7 @@ fn test_stats() {
         reserved_slots: -1,
         responses_size_bytes: msg_size[5],
         oversized_requests_extra_bytes: 0,
+        best_effort_requests_size_bytes: 0,
         transient_stream_responses_size_bytes: 0,
     };
     assert_eq!(expected_mu_stats, queues.memory_usage_stats);
@@ -1303,6 +1309,7 @@ fn test_stats_induct_message_to_self() {
         reserved_slots: -1,
         responses_size_bytes: response_size,
         oversized_requests_extra_bytes: 0,
+        best_effort_requests_size_bytes: 0,
         transient_stream_responses_size_bytes: 0,
     };
     assert_eq!(expected_mu_stats, queues.memory_usage_stats);
@@ -1809,6 +1816,7 @@ fn time_out_requests_pushes_correct_reject_responses() {
                     payment: Cycles::from(cycles as u64),
                     method_name: "No-Op".to_string(),
                     method_payload: vec![],
+                    deadline: NO_DEADLINE,
                 }),
                 deadline,
             )
@@ -1818,7 +1826,7 @@ fn time_out_requests_pushes_correct_reject_responses() {
     let local_canisters = maplit::btreemap! {
         local_canister_id => {
             let scheduler_state = SchedulerState::default();
-            let system_state = SystemState::new_running(
+            let system_state = SystemState::new_running_for_testing(
                 CanisterId::from_u64(42),
                 user_test_id(24).get(),
                 Cycles::new(1 << 36),
@@ -1864,7 +1872,8 @@ fn time_out_requests_pushes_correct_reject_responses() {
                     RejectCode::SysTransient,
                     "Request timed out.".to_string(),
                     MR_SYNTHETIC_REJECT_MESSAGE_MAX_LEN
-                ))
+                )),
+                deadline: NO_DEADLINE,
             }),
             *reject_response,
         );
@@ -1898,3 +1907,252 @@ fn time_out_requests_pushes_correct_reject_responses() {
         VecDeque::from(vec![remote_canister_id]),
     );
 }
+
+#[test]
+fn best_effort_request_times_out_at_deadline_with_sys_unknown() {
+    let mut canister_queues = CanisterQueues::default();
+    let own_canister_id = canister_test_id(67);
+    let remote_canister_id = canister_test_id(97);
+
+    // A best-effort request whose deadline is well before `REQUEST_LIFETIME`.
+    let time = Time::from_nanos_since_unix_epoch(0);
+    let deadline = CoarseTime::from_secs_since_unix_epoch(10);
+    canister_queues
+        .push_output_request(
+            Arc::new(Request {
+                receiver: remote_canister_id,
+                sender: own_canister_id,
+                sender_reply_callback: CallbackId::from(1),
+                payment: Cycles::from(7_u64),
+                method_name: "No-Op".to_string(),
+                method_payload: vec![],
+                deadline,
+            }),
+            time,
+        )
+        .unwrap();
+
+    // Nothing times out before the deadline.
+    let local_canisters = BTreeMap::new();
+    assert_eq!(
+        0,
+        canister_queues.time_out_requests(
+            time + Duration::from_secs(9),
+            &own_canister_id,
+            &local_canisters
+        ),
+    );
+
+    let current_time = Time::from(deadline) + Duration::from_secs(1);
+    assert_eq!(
+        1,
+        canister_queues.time_out_requests(current_time, &own_canister_id, &local_canisters),
+    );
+    let (input_queue, output_queue) = canister_queues
+        .canister_queues
+        .get(&remote_canister_id)
+        .unwrap();
+    assert_eq!(0, output_queue.num_messages());
+    assert_eq!(
+        Some(&RequestOrResponse::Response(Arc::new(Response {
+            originator: own_canister_id,
+            respondent: remote_canister_id,
+            originator_reply_callback: CallbackId::from(1),
+            refund: Cycles::from(7_u64),
+            response_payload: Payload::Reject(RejectContext::new_with_message_length_limit(
+                RejectCode::SysUnknown,
+                "Request timed out.".to_string(),
+                MR_SYNTHETIC_REJECT_MESSAGE_MAX_LEN
+            )),
+            deadline,
+        }))),
+        input_queue.peek()
+    );
+}
+
+/// Generates a best-effort request of (roughly) `payload_size_bytes` from
+/// `sender` to `receiver`.
+fn best_effort_request(
+    sender: CanisterId,
+    receiver: CanisterId,
+    callback_id: u64,
+    payload_size_bytes: usize,
+) -> Arc<Request> {
+    Arc::new(
+        RequestBuilder::default()
+            .sender(sender)
+            .receiver(receiver)
+            .sender_reply_callback(CallbackId::from(callback_id))
+            .payment(Cycles::new(7))
+            .method_payload(vec![13; payload_size_bytes])
+            .deadline(CoarseTime::from_secs_since_unix_epoch(1000))
+            .build(),
+    )
+}
+
+#[test]
+fn shed_largest_best_effort_request_rejects_output_request() {
+    let mut queues = CanisterQueues::default();
+    let own_canister_id = canister_test_id(13);
+    let other_canister_id = canister_test_id(11);
+    let local_canisters = BTreeMap::new();
+
+    let small_request = best_effort_request(own_canister_id, other_canister_id, 1, 10);
+    let large_request = best_effort_request(own_canister_id, other_canister_id, 2, 1000);
+    queues
+        .push_output_request(Arc::clone(&small_request), mock_time())
+        .unwrap();
+    queues
+        .push_output_request(Arc::clone(&large_request), mock_time())
+        .unwrap();
+    assert_eq!(
+        small_request.count_bytes() + large_request.count_bytes(),
+        queues.best_effort_requests_size_bytes()
+    );
+
+    // The largest request is shed first.
+    assert_eq!(
+        Some(Arc::clone(&large_request)),
+        queues.shed_largest_best_effort_request(&own_canister_id, &local_canisters)
+    );
+    assert_eq!(
+        small_request.count_bytes(),
+        queues.best_effort_requests_size_bytes()
+    );
+    assert_eq!(1, queues.output_queues_message_count());
+
+    // And a `SYS_UNKNOWN` reject refunding its payment is enqueued in its place.
+    assert_eq!(
+        Some(CanisterMessage::Response(Arc::new(Response {
+            originator: own_canister_id,
+            respondent: other_canister_id,
+            originator_reply_callback: CallbackId::from(2),
+            refund: Cycles::new(7),
+            response_payload: Payload::Reject(RejectContext::new_with_message_length_limit(
+                RejectCode::SysUnknown,
+                "Request was shed.".to_string(),
+                MR_SYNTHETIC_REJECT_MESSAGE_MAX_LEN
+            )),
+            deadline: large_request.deadline,
+        }))),
+        queues.pop_input()
+    );
+
+    assert_eq!(
+        Some(small_request),
+        queues.shed_largest_best_effort_request(&own_canister_id, &local_canisters)
+    );
+    assert_eq!(0, queues.best_effort_requests_size_bytes());
+    assert_eq!(0, queues.output_queues_message_count());
+    assert_eq!(
+        None,
+        queues.shed_largest_best_effort_request(&own_canister_id, &local_canisters)
+    );
+}
+
+#[test]
+fn shed_largest_best_effort_request_drops_input_request() {
+    let mut queues = CanisterQueues::default();
+    let own_canister_id = canister_test_id(13);
+    let other_canister_id = canister_test_id(11);
+    let local_canisters = BTreeMap::new();
+
+    let request = best_effort_request(other_canister_id, own_canister_id, 1, 100);
+    queues
+        .push_input(
+            RequestOrResponse::Request(Arc::clone(&request)),
+            RemoteSubnet,
+        )
+        .unwrap();
+    assert_eq!(1, queues.reserved_slots());
+    assert_eq!(
+        request.count_bytes(),
+        queues.best_effort_requests_size_bytes()
+    );
+
+    assert_eq!(
+        Some(request),
+        queues.shed_largest_best_effort_request(&own_canister_id, &local_canisters)
+    );
+
+    // The request is gone, along with the output queue reservation for its
+    // response and its input schedule entry.
+    assert_eq!(0, queues.input_queues_message_count());
+    assert_eq!(0, queues.reserved_slots());
+    assert_eq!(0, queues.best_effort_requests_size_bytes());
+    assert!(queues.remote_subnet_input_schedule.is_empty());
+    assert!(!queues.has_input());
+    queues.garbage_collect();
+    assert_eq!(CanisterQueues::default(), queues);
+}
+
+#[test]
+fn shed_largest_best_effort_request_ignores_guaranteed_response_requests() {
+    let mut queues = CanisterQueues::default();
+    let own_canister_id = canister_test_id(13);
+    let other_canister_id = canister_test_id(11);
+    let local_canisters = BTreeMap::new();
+
+    queues
+        .push_output_request(
+            Arc::new(
+                RequestBuilder::default()
+                    .sender(own_canister_id)
+                    .receiver(other_canister_id)
+                    .method_payload(vec![13; 1000])
+                    .build(),
+            ),
+            mock_time(),
+        )
+        .unwrap();
+    queues
+        .push_input(
+            RequestBuilder::default()
+                .sender(other_canister_id)
+                .receiver(own_canister_id)
+                .method_payload(vec![13; 1000])
+                .build()
+                .into(),
+            RemoteSubnet,
+        )
+        .unwrap();
+
+    assert_eq!(0, queues.best_effort_requests_size_bytes());
+    assert_eq!(
+        None,
+        queues.shed_largest_best_effort_request(&own_canister_id, &local_canisters)
+    );
+    assert_eq!(1, queues.output_queues_message_count());
+    assert_eq!(1, queues.input_queues_message_count());
+}
+
+#[test]
+fn has_enqueued_response_reports_correctly() {
+    let mut queues = CanisterQueues::default();
+    let own_canister_id = canister_test_id(13);
+    let other_canister_id = canister_test_id(11);
+
+    queues
+        .push_output_request(
+            best_effort_request(own_canister_id, other_canister_id, 1, 10),
+            mock_time(),
+        )
+        .unwrap();
+    queues.pop_canister_output(&other_canister_id).unwrap();
+    assert!(!queues.has_enqueued_response(&other_canister_id, CallbackId::from(1)));
+
+    queues
+        .push_input(
+            ResponseBuilder::default()
+                .originator(own_canister_id)
+                .respondent(other_canister_id)
+                .originator_reply_callback(CallbackId::from(1))
+                .build()
+                .into(),
+            RemoteSubnet,
+        )
+        .unwrap();
+    assert!(queues.has_enqueued_response(&other_canister_id, CallbackId::from(1)));
+    assert!(!queues.has_enqueued_response(&other_canister_id, CallbackId::from(2)));
+    assert!(!queues.has_enqueued_response(&own_canister_id, CallbackId::from(1)));
+}
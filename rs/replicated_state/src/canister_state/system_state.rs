@@ -1,12 +1,17 @@
 mod call_context_manager;
+mod wasm_chunk_store;
 
 use super::queues::can_push;
 pub use super::queues::memory_required_to_push_request;
 pub use crate::canister_state::queues::CanisterOutputQueuesIterator;
-use crate::{CanisterQueues, CanisterState, InputQueueType, StateError};
+use crate::{
+    page_map::PageAllocatorFileDescriptor, CanisterQueues, CanisterState, InputQueueType,
+    StateError,
+};
 pub use call_context_manager::{CallContext, CallContextAction, CallContextManager, CallOrigin};
 use ic_base_types::NumSeconds;
-use ic_ic00_types::{CanisterChange, CanisterChangeDetails, CanisterChangeOrigin};
+use ic_error_types::RejectCode;
+use ic_ic00_types::{CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, LogVisibility};
 use ic_interfaces::messages::{CanisterCall, CanisterMessage, CanisterMessageOrTask, CanisterTask};
 use ic_logger::{error, ReplicaLogger};
 use ic_protobuf::{
@@ -15,7 +20,10 @@ use ic_protobuf::{
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
-    messages::{Ingress, RejectContext, Request, RequestOrResponse, Response, StopCanisterContext},
+    canister_log::CanisterLog,
+    messages::{
+        Ingress, Payload, RejectContext, Request, RequestOrResponse, Response, StopCanisterContext,
+    },
     nominal_cycles::NominalCycles,
     CanisterId, CanisterTimer, Cycles, MemoryAllocation, NumBytes, PrincipalId, Time,
 };
@@ -28,6 +36,9 @@ use std::{
 };
 use std::{collections::BTreeSet, sync::Arc};
 use std::{collections::VecDeque, str::FromStr};
+pub use wasm_chunk_store::{
+    WasmChunkHash, WasmChunkStore, WasmChunkStoreMetadata, CHUNK_SIZE, DEFAULT_MAX_SIZE,
+};
 
 lazy_static! {
     static ref DEFAULT_PRINCIPAL_MULTIPLE_CONTROLLERS: PrincipalId =
@@ -298,6 +309,11 @@ pub struct SystemState {
     /// Resource reservation cycles.
     reserved_balance: Cycles,
 
+    /// The user-specified upper bound on `reserved_balance`. A resource
+    /// allocation that would reserve more cycles fails. `None` means that
+    /// there is no limit.
+    reserved_balance_limit: Option<Cycles>,
+
     /// Tasks to execute before processing input messages.
     /// Currently the task queue is empty outside of execution rounds.
     pub task_queue: VecDeque<ExecutionTask>,
@@ -310,6 +326,135 @@ pub struct SystemState {
 
     /// Canister history.
     canister_history: CanisterHistory,
+
+    /// The local id to assign to the next snapshot taken of this canister.
+    /// Never decreases, so that snapshot ids are not reused.
+    next_snapshot_id: u64,
+
+    /// The total size of the canister's snapshots, which are stored in
+    /// `ReplicatedState`. Transient: recomputed from the snapshots when the
+    /// state is loaded from a checkpoint.
+    snapshots_memory_usage: NumBytes,
+
+    /// Who is allowed to fetch the canister's logs.
+    pub log_visibility: LogVisibility,
+
+    /// Log records produced by `ic0.debug_print` and by traps during
+    /// replicated execution.
+    pub canister_log: CanisterLog,
+
+    /// Chunks of Wasm modules uploaded through `upload_chunk`.
+    pub wasm_chunk_store: WasmChunkStore,
+
+    /// The upper bound on the canister's Wasm memory. Growing the Wasm memory
+    /// beyond it fails in update calls. `None` means that there is no limit.
+    pub wasm_memory_limit: Option<NumBytes>,
+
+    /// When the Wasm memory left below `wasm_memory_limit` drops below this
+    /// threshold, `canister_on_low_wasm_memory` is scheduled.
+    pub wasm_memory_threshold: NumBytes,
+
+    /// Tracks whether `canister_on_low_wasm_memory` needs to run.
+    on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus,
+
+    /// Statistics of the queries executed on the canister, aggregated over
+    /// the reports of all nodes of the subnet.
+    pub total_query_stats: TotalQueryStats,
+}
+
+/// The query statistics of a canister since its creation, as aggregated from
+/// the statistics reported by the nodes of the subnet.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotalQueryStats {
+    pub num_calls: u128,
+    pub num_instructions: u128,
+    pub ingress_payload_size: u128,
+    pub egress_payload_size: u128,
+}
+
+impl From<&TotalQueryStats> for pb::TotalQueryStats {
+    fn from(item: &TotalQueryStats) -> Self {
+        Self {
+            num_calls: Some((&NominalCycles::from(item.num_calls)).into()),
+            num_instructions: Some((&NominalCycles::from(item.num_instructions)).into()),
+            ingress_payload_size: Some((&NominalCycles::from(item.ingress_payload_size)).into()),
+            egress_payload_size: Some((&NominalCycles::from(item.egress_payload_size)).into()),
+        }
+    }
+}
+
+impl TryFrom<pb::TotalQueryStats> for TotalQueryStats {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb::TotalQueryStats) -> Result<Self, Self::Error> {
+        let decode = |value: Option<ic_protobuf::types::v1::NominalCycles>| {
+            value
+                .map(NominalCycles::try_from)
+                .transpose()
+                .map(|value| value.unwrap_or_default().get())
+        };
+        Ok(Self {
+            num_calls: decode(item.num_calls)?,
+            num_instructions: decode(item.num_instructions)?,
+            ingress_payload_size: decode(item.ingress_payload_size)?,
+            egress_payload_size: decode(item.egress_payload_size)?,
+        })
+    }
+}
+
+/// The status of the `canister_on_low_wasm_memory` hook.
+///
+/// The hook becomes `Ready` when the Wasm memory left below the Wasm memory
+/// limit drops below the Wasm memory threshold. It runs at most once until the
+/// condition stops holding, e.g. because the limit was raised.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OnLowWasmMemoryHookStatus {
+    ConditionNotSatisfied,
+    Ready,
+    Executed,
+}
+
+impl Default for OnLowWasmMemoryHookStatus {
+    fn default() -> Self {
+        OnLowWasmMemoryHookStatus::ConditionNotSatisfied
+    }
+}
+
+impl From<&OnLowWasmMemoryHookStatus> for pb::OnLowWasmMemoryHookStatus {
+    fn from(item: &OnLowWasmMemoryHookStatus) -> Self {
+        match item {
+            OnLowWasmMemoryHookStatus::ConditionNotSatisfied => {
+                pb::OnLowWasmMemoryHookStatus::ConditionNotSatisfied
+            }
+            OnLowWasmMemoryHookStatus::Ready => pb::OnLowWasmMemoryHookStatus::Ready,
+            OnLowWasmMemoryHookStatus::Executed => pb::OnLowWasmMemoryHookStatus::Executed,
+        }
+    }
+}
+
+impl From<pb::OnLowWasmMemoryHookStatus> for OnLowWasmMemoryHookStatus {
+    fn from(item: pb::OnLowWasmMemoryHookStatus) -> Self {
+        match item {
+            pb::OnLowWasmMemoryHookStatus::Unspecified
+            | pb::OnLowWasmMemoryHookStatus::ConditionNotSatisfied => {
+                OnLowWasmMemoryHookStatus::ConditionNotSatisfied
+            }
+            pb::OnLowWasmMemoryHookStatus::Ready => OnLowWasmMemoryHookStatus::Ready,
+            pb::OnLowWasmMemoryHookStatus::Executed => OnLowWasmMemoryHookStatus::Executed,
+        }
+    }
+}
+
+/// An error returned by `SystemState::reserve_cycles`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReservationError {
+    /// The main balance does not hold the requested amount.
+    InsufficientCycles {
+        requested: Cycles,
+        available: Cycles,
+    },
+    /// The reserved balance would exceed `reserved_balance_limit`.
+    ReservedLimitExceed { requested: Cycles, limit: Cycles },
 }
 
 /// A wrapper around the different canister statuses.
@@ -409,6 +554,10 @@ pub enum ExecutionTask {
     /// The task exists only within an execution round, it never gets serialized.
     GlobalTimer,
 
+    /// Task running `canister_on_low_wasm_memory`.
+    /// The task exists only within an execution round, it never gets serialized.
+    OnLowWasmMemory,
+
     // A paused execution task exists only within an epoch (between
     // checkpoints). It is never serialized, and it turns into `AbortedExecution`
     // before the checkpoint or when there are too many long-running executions.
@@ -447,6 +596,7 @@ impl From<&ExecutionTask> for pb::ExecutionTask {
         match item {
             ExecutionTask::Heartbeat
             | ExecutionTask::GlobalTimer
+            | ExecutionTask::OnLowWasmMemory
             | ExecutionTask::PausedExecution(_)
             | ExecutionTask::PausedInstallCode(_) => {
                 panic!("Attempt to serialize ephemeral task: {:?}.", item);
@@ -474,6 +624,9 @@ impl From<&ExecutionTask> for pb::ExecutionTask {
                     CanisterMessageOrTask::Task(CanisterTask::GlobalTimer) => {
                         PbInput::Task(PbCanisterTask::Timer as i32)
                     }
+                    CanisterMessageOrTask::Task(CanisterTask::OnLowWasmMemory) => {
+                        PbInput::Task(PbCanisterTask::OnLowWasmMemory as i32)
+                    }
                 };
                 Self {
                     task: Some(pb::execution_task::Task::AbortedExecution(
@@ -547,6 +700,7 @@ impl TryFrom<pb::ExecutionTask> for ExecutionTask {
                             }
                             PbCanisterTask::Heartbeat => CanisterTask::Heartbeat,
                             PbCanisterTask::Timer => CanisterTask::GlobalTimer,
+                            PbCanisterTask::OnLowWasmMemory => CanisterTask::OnLowWasmMemory,
                         };
                         CanisterMessageOrTask::Task(task)
                     }
@@ -622,6 +776,23 @@ impl SystemState {
         controller: PrincipalId,
         initial_cycles: Cycles,
         freeze_threshold: NumSeconds,
+        fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
+    ) -> Self {
+        Self::new(
+            canister_id,
+            controller,
+            initial_cycles,
+            freeze_threshold,
+            CanisterStatus::new_running(),
+            WasmChunkStore::new(fd_factory),
+        )
+    }
+
+    pub fn new_running_for_testing(
+        canister_id: CanisterId,
+        controller: PrincipalId,
+        initial_cycles: Cycles,
+        freeze_threshold: NumSeconds,
     ) -> Self {
         Self::new(
             canister_id,
@@ -629,10 +800,11 @@ impl SystemState {
             initial_cycles,
             freeze_threshold,
             CanisterStatus::new_running(),
+            WasmChunkStore::new_for_testing(),
         )
     }
 
-    pub fn new_stopping(
+    pub fn new_stopping_for_testing(
         canister_id: CanisterId,
         controller: PrincipalId,
         initial_cycles: Cycles,
@@ -647,10 +819,11 @@ impl SystemState {
                 call_context_manager: CallContextManager::default(),
                 stop_contexts: Vec::default(),
             },
+            WasmChunkStore::new_for_testing(),
         )
     }
 
-    pub fn new_stopped(
+    pub fn new_stopped_for_testing(
         canister_id: CanisterId,
         controller: PrincipalId,
         initial_cycles: Cycles,
@@ -662,15 +835,17 @@ impl SystemState {
             initial_cycles,
             freeze_threshold,
             CanisterStatus::Stopped,
+            WasmChunkStore::new_for_testing(),
         )
     }
 
-    pub fn new(
+    fn new(
         canister_id: CanisterId,
         controller: PrincipalId,
         initial_cycles: Cycles,
         freeze_threshold: NumSeconds,
         status: CanisterStatus,
+        wasm_chunk_store: WasmChunkStore,
     ) -> Self {
         Self {
             canister_id,
@@ -679,6 +854,7 @@ impl SystemState {
             cycles_balance: initial_cycles,
             ingress_induction_cycles_debit: Cycles::zero(),
             reserved_balance: Cycles::zero(),
+            reserved_balance_limit: None,
             memory_allocation: MemoryAllocation::BestEffort,
             freeze_threshold,
             status,
@@ -688,6 +864,15 @@ impl SystemState {
             global_timer: CanisterTimer::Inactive,
             canister_version: 0,
             canister_history: CanisterHistory::default(),
+            next_snapshot_id: 0,
+            snapshots_memory_usage: NumBytes::from(0),
+            log_visibility: LogVisibility::default(),
+            canister_log: CanisterLog::default(),
+            wasm_chunk_store,
+            wasm_memory_limit: None,
+            wasm_memory_threshold: NumBytes::from(0),
+            on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus::default(),
+            total_query_stats: TotalQueryStats::default(),
         }
     }
 
@@ -696,7 +881,10 @@ impl SystemState {
     /// module is run. There is nothing interesting in the system state
     /// that can be accessed at that point in time, hence this
     /// "slightly" fake system state.
-    pub fn new_for_start(canister_id: CanisterId) -> Self {
+    pub fn new_for_start(
+        canister_id: CanisterId,
+        fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
+    ) -> Self {
         let controller = *canister_id.get_ref();
         Self::new(
             canister_id,
@@ -704,6 +892,7 @@ impl SystemState {
             Cycles::zero(),
             NumSeconds::from(0),
             CanisterStatus::Stopped,
+            WasmChunkStore::new(fd_factory),
         )
     }
 
@@ -720,10 +909,19 @@ impl SystemState {
         cycles_balance: Cycles,
         ingress_induction_cycles_debit: Cycles,
         reserved_balance: Cycles,
+        reserved_balance_limit: Option<Cycles>,
         task_queue: VecDeque<ExecutionTask>,
         global_timer: CanisterTimer,
         canister_version: u64,
         canister_history: CanisterHistory,
+        next_snapshot_id: u64,
+        log_visibility: LogVisibility,
+        canister_log: CanisterLog,
+        wasm_chunk_store: WasmChunkStore,
+        wasm_memory_limit: Option<NumBytes>,
+        wasm_memory_threshold: NumBytes,
+        on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus,
+        total_query_stats: TotalQueryStats,
     ) -> Self {
         Self {
             controllers,
@@ -737,10 +935,20 @@ impl SystemState {
             cycles_balance,
             ingress_induction_cycles_debit,
             reserved_balance,
+            reserved_balance_limit,
             task_queue,
             global_timer,
             canister_version,
             canister_history,
+            next_snapshot_id,
+            snapshots_memory_usage: NumBytes::from(0),
+            log_visibility,
+            canister_log,
+            wasm_chunk_store,
+            wasm_memory_limit,
+            wasm_memory_threshold,
+            on_low_wasm_memory_hook_status,
+            total_query_stats,
         }
     }
 
@@ -770,6 +978,58 @@ impl SystemState {
         self.reserved_balance
     }
 
+    /// Returns the user-specified limit on the reserved balance, if any.
+    pub fn reserved_balance_limit(&self) -> Option<Cycles> {
+        self.reserved_balance_limit
+    }
+
+    /// Sets the limit on the reserved balance. `None` removes the limit.
+    pub fn set_reserved_balance_limit(&mut self, limit: Option<Cycles>) {
+        self.reserved_balance_limit = limit;
+    }
+
+    /// Moves the given amount of cycles from the main balance to the reserved
+    /// balance.
+    ///
+    /// Fails without changing the balances if the main balance is too low or
+    /// if the reserved balance would exceed its limit.
+    pub fn reserve_cycles(&mut self, amount: Cycles) -> Result<(), ReservationError> {
+        if amount == Cycles::zero() {
+            return Ok(());
+        }
+        if let Some(limit) = self.reserved_balance_limit {
+            let requested = self.reserved_balance + amount;
+            if requested > limit {
+                return Err(ReservationError::ReservedLimitExceed { requested, limit });
+            }
+        }
+        if amount > self.cycles_balance {
+            return Err(ReservationError::InsufficientCycles {
+                requested: amount,
+                available: self.cycles_balance,
+            });
+        }
+        self.cycles_balance -= amount;
+        self.reserved_balance += amount;
+        Ok(())
+    }
+
+    /// Removes a resource charge, taking it from the reserved balance first
+    /// and the remainder from the main balance.
+    ///
+    /// Precondition:
+    /// - `charge <= self.reserved_balance() + self.balance()`.
+    pub fn remove_charge_from_cycles_balance_and_reserved_balance(
+        &mut self,
+        charge: Cycles,
+        use_case: CyclesUseCase,
+    ) {
+        let from_reserved = std::cmp::min(charge, self.reserved_balance);
+        self.reserved_balance -= from_reserved;
+        self.observe_consumed_cycles_with_use_case(from_reserved, use_case, ConsumingCycles::Yes);
+        self.remove_cycles(charge - from_reserved, use_case);
+    }
+
     /// Records the given amount as debit that will be charged from the balance
     /// at some point in the future.
     ///
@@ -964,6 +1224,9 @@ impl SystemState {
     ///  * `CanisterStopped` if the canister is stopped.
     ///  * `NonMatchingResponse` if the callback is not found or the respondent
     ///    does not match.
+    ///
+    /// Late best-effort responses (for expired or unknown callbacks) are
+    /// silently dropped, after crediting their refund to the canister.
     pub(crate) fn push_input(
         &mut self,
         msg: RequestOrResponse,
@@ -1005,6 +1268,12 @@ impl SystemState {
                 },
             ) => {
                 if let RequestOrResponse::Response(response) = &msg {
+                    // Late best-effort responses are dropped, crediting their refund.
+                    if call_context_manager.is_late_response(response) {
+                        let refund = response.refund;
+                        self.add_cycles(refund, CyclesUseCase::NonConsumed);
+                        return Ok(());
+                    }
                     call_context_manager
                         .validate_response(response)
                         .map_err(|err| (err, msg.clone()))?;
@@ -1094,6 +1363,60 @@ impl SystemState {
         self.canister_history.get_memory_usage()
     }
 
+    /// Returns the memory currently in use by the `SystemState`
+    /// for the Wasm chunk store.
+    pub fn wasm_chunk_store_memory_usage(&self) -> NumBytes {
+        self.wasm_chunk_store.memory_usage()
+    }
+
+    /// Returns the memory currently in use by the snapshots of the canister.
+    pub fn snapshots_memory_usage(&self) -> NumBytes {
+        self.snapshots_memory_usage
+    }
+
+    /// Sets the (transient) total size of the snapshots of the canister.
+    pub(super) fn set_snapshots_memory_usage(&mut self, size: NumBytes) {
+        self.snapshots_memory_usage = size;
+    }
+
+    /// Returns the status of the `canister_on_low_wasm_memory` hook.
+    pub fn on_low_wasm_memory_hook_status(&self) -> OnLowWasmMemoryHookStatus {
+        self.on_low_wasm_memory_hook_status
+    }
+
+    /// Re-evaluates the condition of the `canister_on_low_wasm_memory` hook:
+    /// the Wasm memory left below `wasm_memory_limit` is less than
+    /// `wasm_memory_threshold`. The hook becomes ready when the condition
+    /// starts holding and is re-armed when it stops holding.
+    pub fn update_on_low_wasm_memory_hook_condition(&mut self, wasm_memory_usage: NumBytes) {
+        let is_condition_satisfied = match self.wasm_memory_limit {
+            Some(wasm_memory_limit) => {
+                NumBytes::from(
+                    wasm_memory_limit
+                        .get()
+                        .saturating_sub(wasm_memory_usage.get()),
+                ) < self.wasm_memory_threshold
+            }
+            None => false,
+        };
+        self.on_low_wasm_memory_hook_status =
+            match (is_condition_satisfied, self.on_low_wasm_memory_hook_status) {
+                (false, _) => OnLowWasmMemoryHookStatus::ConditionNotSatisfied,
+                (true, OnLowWasmMemoryHookStatus::ConditionNotSatisfied) => {
+                    OnLowWasmMemoryHookStatus::Ready
+                }
+                (true, status) => status,
+            };
+    }
+
+    /// Records that `canister_on_low_wasm_memory` has been executed, so that
+    /// it is not scheduled again while its condition keeps holding.
+    pub fn on_low_wasm_memory_hook_executed(&mut self) {
+        if self.on_low_wasm_memory_hook_status == OnLowWasmMemoryHookStatus::Ready {
+            self.on_low_wasm_memory_hook_status = OnLowWasmMemoryHookStatus::Executed;
+        }
+    }
+
     /// Sets the (transient) size in bytes of responses from this canister
     /// routed into streams and not yet garbage collected.
     pub(super) fn set_stream_responses_size_bytes(&mut self, size_bytes: usize) {
@@ -1141,21 +1464,36 @@ impl SystemState {
         let mut memory_usage = self.queues.memory_usage() as i64;
 
         while let Some(msg) = self.queues.peek_output(&self.canister_id) {
-            // Ensure that enough memory is available for inducting `msg`.
-            if own_subnet_type != SubnetType::System
-                && can_push(msg, *subnet_available_memory).is_err()
-            {
-                // Bail out if not enough memory available for message.
-                return;
-            }
+            let late_response_refund = match (msg, self.call_context_manager()) {
+                (RequestOrResponse::Response(response), Some(call_context_manager))
+                    if call_context_manager.is_late_response(response) =>
+                {
+                    Some(response.refund)
+                }
+                _ => None,
+            };
+
+            if let Some(refund) = late_response_refund {
+                // Drop the late best-effort response, crediting its refund.
+                self.queues.pop_output(&self.canister_id);
+                self.add_cycles(refund, CyclesUseCase::NonConsumed);
+            } else {
+                // Ensure that enough memory is available for inducting `msg`.
+                if own_subnet_type != SubnetType::System
+                    && can_push(msg, *subnet_available_memory).is_err()
+                {
+                    // Bail out if not enough memory available for message.
+                    return;
+                }
 
-            // Attempt inducting `msg`. May fail if the input queue is full.
-            if self
-                .queues
-                .induct_message_to_self(self.canister_id)
-                .is_err()
-            {
-                return;
+                // Attempt inducting `msg`. May fail if the input queue is full.
+                if self
+                    .queues
+                    .induct_message_to_self(self.canister_id)
+                    .is_err()
+                {
+                    return;
+                }
             }
 
             // Adjust `subnet_available_memory` by `memory_usage_before - memory_usage_after`.
@@ -1191,6 +1529,100 @@ impl SystemState {
             .time_out_requests(current_time, own_canister_id, local_canisters)
     }
 
+    /// Queries whether any best-effort callbacks are due to expire at
+    /// `current_time`.
+    pub fn has_expired_callbacks(&self, current_time: Time) -> bool {
+        self.call_context_manager()
+            .map_or(false, |call_context_manager| {
+                call_context_manager.has_expired_callbacks(current_time)
+            })
+    }
+
+    /// Expires all best-effort callbacks whose deadline is at or before
+    /// `current_time`. For each expired callback that does not already have a
+    /// response enqueued, enqueues a `SYS_UNKNOWN` reject response (with no
+    /// refund) into the slot reserved for the actual response. Returns the
+    /// number of expired callbacks.
+    ///
+    /// Must not be called while the canister has a paused or aborted execution,
+    /// as that may be the execution of the response for an expired callback.
+    ///
+    /// Any response for an expired callback that arrives later is dropped.
+    pub fn expire_callbacks(
+        &mut self,
+        current_time: Time,
+        own_canister_id: &CanisterId,
+        local_canisters: &BTreeMap<CanisterId, CanisterState>,
+    ) -> u64 {
+        let call_context_manager = match &mut self.status {
+            CanisterStatus::Running {
+                call_context_manager,
+            }
+            | CanisterStatus::Stopping {
+                call_context_manager,
+                ..
+            } => call_context_manager,
+            CanisterStatus::Stopped => return 0,
+        };
+
+        let mut expired_callbacks_count = 0;
+        for callback_id in call_context_manager.expire_callbacks(current_time) {
+            expired_callbacks_count += 1;
+
+            let callback = call_context_manager
+                .callback(&callback_id)
+                .expect("Expired callbacks are registered");
+            let respondent = match callback.respondent {
+                Some(respondent) => respondent,
+                None => continue,
+            };
+            if self.queues.has_enqueued_response(&respondent, callback_id) {
+                continue;
+            }
+
+            let response = RequestOrResponse::Response(Arc::new(Response {
+                originator: self.canister_id,
+                respondent,
+                originator_reply_callback: callback_id,
+                refund: Cycles::zero(),
+                response_payload: Payload::Reject(RejectContext::new(
+                    RejectCode::SysUnknown,
+                    "Call deadline has expired.".to_string(),
+                )),
+                deadline: callback.deadline,
+            }));
+            let input_queue_type =
+                if &respondent == own_canister_id || local_canisters.contains_key(&respondent) {
+                    InputQueueType::LocalSubnet
+                } else {
+                    InputQueueType::RemoteSubnet
+                };
+            // Cannot fail: a slot was reserved for the response when the request
+            // was enqueued; and the response was neither enqueued nor executed.
+            let result = self.queues.push_input(response, input_queue_type);
+            debug_assert!(
+                result.is_ok(),
+                "Failed to enqueue reject response for expired callback: {:?}",
+                result
+            );
+        }
+        expired_callbacks_count
+    }
+
+    /// Sheds the largest best-effort request in `self.queues`. Returns the shed
+    /// request; or `None` if there are no best-effort requests.
+    ///
+    /// See [`CanisterQueues::shed_largest_best_effort_request`] for further
+    /// details.
+    pub fn shed_largest_best_effort_request(
+        &mut self,
+        own_canister_id: &CanisterId,
+        local_canisters: &BTreeMap<CanisterId, CanisterState>,
+    ) -> Option<Arc<Request>> {
+        self.queues
+            .shed_largest_best_effort_request(own_canister_id, local_canisters)
+    }
+
     /// Re-partitions the local and remote input schedules of `self.queues`
     /// following a canister migration, based on the updated set of local canisters.
     ///
@@ -1292,6 +1724,18 @@ impl SystemState {
     pub fn get_canister_history(&self) -> &CanisterHistory {
         &self.canister_history
     }
+
+    /// Returns the local id to assign to the next snapshot of this canister.
+    pub fn next_snapshot_id(&self) -> u64 {
+        self.next_snapshot_id
+    }
+
+    /// Allocates a new local id for a snapshot of this canister.
+    pub fn new_local_snapshot_id(&mut self) -> u64 {
+        let local_id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        local_id
+    }
 }
 
 /// Implements memory limits verification for pushing a canister-to-canister
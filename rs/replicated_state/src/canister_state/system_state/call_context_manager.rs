@@ -8,15 +8,15 @@ use ic_protobuf::proxy::{try_from_option_field, ProxyDecodeError};
 use ic_protobuf::state::canister_state_bits::v1 as pb;
 use ic_protobuf::types::v1 as pb_types;
 use ic_types::messages::Response;
-use ic_types::Time;
 use ic_types::{
     ingress::WasmResult,
     messages::{CallContextId, CallbackId, MessageId},
     methods::Callback,
     user_id_into_protobuf, user_id_try_from_protobuf, CanisterId, Cycles, Funds, UserId,
 };
+use ic_types::{messages::NO_DEADLINE, time::CoarseTime, Time};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::{From, TryFrom, TryInto};
 use std::time::Duration;
 
@@ -213,12 +213,23 @@ pub struct CallContextManager {
     /// Maps call context to its responded status.
     call_contexts: BTreeMap<CallContextId, CallContext>,
     callbacks: BTreeMap<CallbackId, Callback>,
+    /// Deadlines of best-effort callbacks that have not expired yet, ordered by
+    /// deadline.
+    ///
+    /// Derived from `callbacks` and `expired_callbacks`, so it is not persisted.
+    unexpired_callbacks: BTreeSet<(CoarseTime, CallbackId)>,
+    /// Best-effort callbacks whose deadline has expired. Any response for one of
+    /// these callbacks is late and must be dropped, as a `SYS_UNKNOWN` reject
+    /// has already been (or is about to be) delivered in its place.
+    expired_callbacks: BTreeSet<CallbackId>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CallOrigin {
     Ingress(UserId, MessageId),
-    CanisterUpdate(CanisterId, CallbackId),
+    /// A canister request, with the deadline of the request (`NO_DEADLINE`
+    /// for guaranteed response calls).
+    CanisterUpdate(CanisterId, CallbackId, CoarseTime),
     Query(UserId),
     CanisterQuery(CanisterId, CallbackId),
     /// System task is either a `Heartbeat` or a `GlobalTimer`.
@@ -232,10 +243,11 @@ impl From<&CallOrigin> for pb::call_context::CallOrigin {
                 user_id: Some(user_id_into_protobuf(*user_id)),
                 message_id: message_id.as_bytes().to_vec(),
             }),
-            CallOrigin::CanisterUpdate(canister_id, callback_id) => {
+            CallOrigin::CanisterUpdate(canister_id, callback_id, deadline) => {
                 Self::CanisterUpdate(pb::call_context::CanisterUpdateOrQuery {
                     canister_id: Some(pb_types::CanisterId::from(*canister_id)),
                     callback_id: callback_id.get(),
+                    deadline_seconds: deadline.as_secs_since_unix_epoch(),
                 })
             }
             CallOrigin::Query(user_id) => Self::Query(user_id_into_protobuf(*user_id)),
@@ -243,6 +255,7 @@ impl From<&CallOrigin> for pb::call_context::CallOrigin {
                 Self::CanisterQuery(pb::call_context::CanisterUpdateOrQuery {
                     canister_id: Some(pb_types::CanisterId::from(*canister_id)),
                     callback_id: callback_id.get(),
+                    deadline_seconds: NO_DEADLINE.as_secs_since_unix_epoch(),
                 })
             }
             CallOrigin::SystemTask => Self::SystemTask(pb::call_context::SystemTask {}),
@@ -268,10 +281,12 @@ impl TryFrom<pb::call_context::CallOrigin> for CallOrigin {
                 pb::call_context::CanisterUpdateOrQuery {
                     canister_id,
                     callback_id,
+                    deadline_seconds,
                 },
             ) => Self::CanisterUpdate(
                 try_from_option_field(canister_id, "CallOrigin::CanisterUpdate::canister_id")?,
                 callback_id.into(),
+                CoarseTime::from_secs_since_unix_epoch(deadline_seconds),
             ),
             pb::call_context::CallOrigin::Query(user_id) => {
                 Self::Query(user_id_try_from_protobuf(user_id)?)
@@ -280,6 +295,7 @@ impl TryFrom<pb::call_context::CallOrigin> for CallOrigin {
                 pb::call_context::CanisterUpdateOrQuery {
                     canister_id,
                     callback_id,
+                    ..
                 },
             ) => Self::CanisterQuery(
                 try_from_option_field(canister_id, "CallOrigin::CanisterQuery::canister_id")?,
@@ -491,6 +507,10 @@ impl CallContextManager {
     pub fn register_callback(&mut self, callback: Callback) -> CallbackId {
         self.next_callback_id += 1;
         let callback_id = CallbackId::from(self.next_callback_id);
+        if callback.deadline != NO_DEADLINE {
+            self.unexpired_callbacks
+                .insert((callback.deadline, callback_id));
+        }
         self.callbacks.insert(callback_id, callback);
         callback_id
     }
@@ -503,7 +523,50 @@ impl CallContextManager {
     /// If we get a response for one of the outstanding calls, we unregister
     /// the callback and return it.
     pub fn unregister_callback(&mut self, callback_id: CallbackId) -> Option<Callback> {
-        self.callbacks.remove(&callback_id)
+        let callback = self.callbacks.remove(&callback_id)?;
+        self.unexpired_callbacks
+            .remove(&(callback.deadline, callback_id));
+        self.expired_callbacks.remove(&callback_id);
+        Some(callback)
+    }
+
+    /// Returns `true` if the best-effort callback with the given ID has
+    /// expired.
+    pub fn is_callback_expired(&self, callback_id: CallbackId) -> bool {
+        self.expired_callbacks.contains(&callback_id)
+    }
+
+    /// Returns `true` if `response` is a late best-effort response, i.e. one for
+    /// a callback that has expired or is no longer registered. A `SYS_UNKNOWN`
+    /// reject has been (or is about to be) delivered in its place.
+    pub(crate) fn is_late_response(&self, response: &Response) -> bool {
+        let callback_id = response.originator_reply_callback;
+        response.is_best_effort()
+            && (!self.callbacks.contains_key(&callback_id) || self.is_callback_expired(callback_id))
+    }
+
+    /// Returns `true` if at least one best-effort callback is due to expire at
+    /// `current_time`.
+    pub fn has_expired_callbacks(&self, current_time: Time) -> bool {
+        match self.unexpired_callbacks.first() {
+            Some((deadline, _)) => Time::from(*deadline) <= current_time,
+            None => false,
+        }
+    }
+
+    /// Marks all best-effort callbacks whose deadline is at or before
+    /// `current_time` as expired and returns their IDs, in deadline order.
+    pub fn expire_callbacks(&mut self, current_time: Time) -> Vec<CallbackId> {
+        let mut expired = Vec::new();
+        while let Some(&(deadline, callback_id)) = self.unexpired_callbacks.first() {
+            if Time::from(deadline) > current_time {
+                break;
+            }
+            self.unexpired_callbacks.pop_first();
+            self.expired_callbacks.insert(callback_id);
+            expired.push(callback_id);
+        }
+        expired
     }
 
     /// Returns the call origin, which is either the message id of the ingress
@@ -566,9 +629,11 @@ impl CallContextManager {
 impl From<&CanisterCall> for CallOrigin {
     fn from(msg: &CanisterCall) -> Self {
         match msg {
-            CanisterCall::Request(request) => {
-                CallOrigin::CanisterUpdate(request.sender, request.sender_reply_callback)
-            }
+            CanisterCall::Request(request) => CallOrigin::CanisterUpdate(
+                request.sender,
+                request.sender_reply_callback,
+                request.deadline,
+            ),
             CanisterCall::Ingress(ingress) => {
                 CallOrigin::Ingress(ingress.source, ingress.message_id.clone())
             }
@@ -606,6 +671,7 @@ impl From<&CallContextManager> for pb::CallContextManager {
                     callback: Some(callback.into()),
                 })
                 .collect(),
+            expired_callbacks: item.expired_callbacks.iter().map(|id| id.get()).collect(),
         }
     }
 }
@@ -635,12 +701,26 @@ impl TryFrom<pb::CallContextManager> for CallContextManager {
                 try_from_option_field(callback, "CallContextManager::callbacks::V")?,
             );
         }
+        let expired_callbacks: BTreeSet<CallbackId> = value
+            .expired_callbacks
+            .into_iter()
+            .map(CallbackId::from)
+            .collect();
+        let unexpired_callbacks = callbacks
+            .iter()
+            .filter(|(callback_id, callback)| {
+                callback.deadline != NO_DEADLINE && !expired_callbacks.contains(callback_id)
+            })
+            .map(|(callback_id, callback)| (callback.deadline, *callback_id))
+            .collect();
 
         Ok(Self {
             next_call_context_id: value.next_call_context_id,
             next_callback_id: value.next_callback_id,
             call_contexts,
             callbacks,
+            unexpired_callbacks,
+            expired_callbacks,
         })
     }
 }
@@ -8,13 +8,13 @@ fn call_context_origin() {
     let id = canister_test_id(42);
     let cb_id = CallbackId::from(1);
     let cc_id = ccm.new_call_context(
-        CallOrigin::CanisterUpdate(id, cb_id),
+        CallOrigin::CanisterUpdate(id, cb_id, NO_DEADLINE),
         Cycles::new(10),
         Time::from_nanos_since_unix_epoch(0),
     );
     assert_eq!(
         ccm.call_contexts().get(&cc_id).unwrap().call_origin,
-        CallOrigin::CanisterUpdate(id, cb_id)
+        CallOrigin::CanisterUpdate(id, cb_id, NO_DEADLINE)
     );
 }
 
@@ -26,18 +26,18 @@ fn call_context_handling() {
 
     // On two incoming calls
     let call_context_id1 = call_context_manager.new_call_context(
-        CallOrigin::CanisterUpdate(canister_test_id(123), CallbackId::from(1)),
+        CallOrigin::CanisterUpdate(canister_test_id(123), CallbackId::from(1), NO_DEADLINE),
         Cycles::zero(),
         Time::from_nanos_since_unix_epoch(0),
     );
     let call_context_id2 = call_context_manager.new_call_context(
-        CallOrigin::CanisterUpdate(canister_test_id(123), CallbackId::from(2)),
+        CallOrigin::CanisterUpdate(canister_test_id(123), CallbackId::from(2), NO_DEADLINE),
         Cycles::zero(),
         Time::from_nanos_since_unix_epoch(0),
     );
 
     let call_context_id3 = call_context_manager.new_call_context(
-        CallOrigin::CanisterUpdate(canister_test_id(123), CallbackId::from(3)),
+        CallOrigin::CanisterUpdate(canister_test_id(123), CallbackId::from(3), NO_DEADLINE),
         Cycles::zero(),
         Time::from_nanos_since_unix_epoch(0),
     );
@@ -78,6 +78,7 @@ fn call_context_handling() {
         WasmClosure::new(0, 1),
         WasmClosure::new(2, 3),
         None,
+        NO_DEADLINE,
     ));
     let callback_id2 = call_context_manager.register_callback(Callback::new(
         call_context_id1,
@@ -89,6 +90,7 @@ fn call_context_handling() {
         WasmClosure::new(4, 5),
         WasmClosure::new(6, 7),
         None,
+        NO_DEADLINE,
     ));
 
     // There are 2 ougoing calls
@@ -105,6 +107,7 @@ fn call_context_handling() {
         WasmClosure::new(8, 9),
         WasmClosure::new(10, 11),
         None,
+        NO_DEADLINE,
     ));
     // There is 1 outgoing call
     assert_eq!(call_context_manager.outstanding_calls(call_context_id2), 1);
@@ -254,7 +257,7 @@ fn withdraw_cycles_fails_when_not_enough_available_cycles() {
     let id = canister_test_id(42);
     let cb_id = CallbackId::from(1);
     let cc_id = ccm.new_call_context(
-        CallOrigin::CanisterUpdate(id, cb_id),
+        CallOrigin::CanisterUpdate(id, cb_id, NO_DEADLINE),
         Cycles::new(30),
         Time::from_nanos_since_unix_epoch(0),
     );
@@ -276,7 +279,7 @@ fn withdraw_cycles_succeeds_when_enough_available_cycles() {
     let id = canister_test_id(42);
     let cb_id = CallbackId::from(1);
     let cc_id = ccm.new_call_context(
-        CallOrigin::CanisterUpdate(id, cb_id),
+        CallOrigin::CanisterUpdate(id, cb_id, NO_DEADLINE),
         Cycles::new(30),
         Time::from_nanos_since_unix_epoch(0),
     );
@@ -288,3 +291,84 @@ fn withdraw_cycles_succeeds_when_enough_available_cycles() {
         Ok(())
     );
 }
+
+fn best_effort_callback(call_context_id: CallContextId, deadline: CoarseTime) -> Callback {
+    Callback::new(
+        call_context_id,
+        Some(canister_test_id(1)),
+        Some(canister_test_id(2)),
+        Cycles::zero(),
+        Some(Cycles::new(42)),
+        Some(Cycles::new(84)),
+        WasmClosure::new(0, 1),
+        WasmClosure::new(2, 3),
+        None,
+        deadline,
+    )
+}
+
+#[test]
+fn expire_callbacks() {
+    let mut ccm = CallContextManager::default();
+    let cc_id = ccm.new_call_context(
+        CallOrigin::SystemTask,
+        Cycles::zero(),
+        Time::from_nanos_since_unix_epoch(0),
+    );
+    let guaranteed_callback_id = ccm.register_callback(best_effort_callback(cc_id, NO_DEADLINE));
+    let callback_id_1 = ccm.register_callback(best_effort_callback(
+        cc_id,
+        CoarseTime::from_secs_since_unix_epoch(20),
+    ));
+    let callback_id_2 = ccm.register_callback(best_effort_callback(
+        cc_id,
+        CoarseTime::from_secs_since_unix_epoch(10),
+    ));
+
+    let time = |secs| Time::from_nanos_since_unix_epoch(secs * 1_000_000_000);
+
+    assert!(!ccm.has_expired_callbacks(time(9)));
+    assert!(ccm.expire_callbacks(time(9)).is_empty());
+
+    assert!(ccm.has_expired_callbacks(time(10)));
+    assert_eq!(vec![callback_id_2], ccm.expire_callbacks(time(10)));
+    assert!(ccm.is_callback_expired(callback_id_2));
+    assert!(!ccm.is_callback_expired(callback_id_1));
+
+    // Already expired callbacks are not returned again.
+    assert_eq!(vec![callback_id_1], ccm.expire_callbacks(time(100)));
+    assert!(ccm.is_callback_expired(callback_id_1));
+    assert!(!ccm.is_callback_expired(guaranteed_callback_id));
+    assert!(!ccm.has_expired_callbacks(time(100)));
+
+    // Unregistering an expired callback forgets about it.
+    ccm.unregister_callback(callback_id_1);
+    assert!(!ccm.is_callback_expired(callback_id_1));
+    assert!(ccm.is_callback_expired(callback_id_2));
+}
+
+#[test]
+fn expired_callbacks_roundtrip_through_proto() {
+    let mut ccm = CallContextManager::default();
+    let cc_id = ccm.new_call_context(
+        CallOrigin::SystemTask,
+        Cycles::zero(),
+        Time::from_nanos_since_unix_epoch(0),
+    );
+    let expired_callback_id = ccm.register_callback(best_effort_callback(
+        cc_id,
+        CoarseTime::from_secs_since_unix_epoch(10),
+    ));
+    let unexpired_callback_id = ccm.register_callback(best_effort_callback(
+        cc_id,
+        CoarseTime::from_secs_since_unix_epoch(20),
+    ));
+    ccm.expire_callbacks(Time::from_nanos_since_unix_epoch(15_000_000_000));
+
+    let pb_ccm = pb::CallContextManager::from(&ccm);
+    let round_trip = CallContextManager::try_from(pb_ccm).unwrap();
+
+    assert_eq!(ccm, round_trip);
+    assert!(round_trip.is_callback_expired(expired_callback_id));
+    assert!(!round_trip.is_callback_expired(unexpired_callback_id));
+}
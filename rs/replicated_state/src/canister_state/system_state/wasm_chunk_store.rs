@@ -0,0 +1,270 @@
+use crate::page_map::{Buffer, PageAllocatorFileDescriptor, PageMap};
+use ic_crypto_sha::Sha256;
+use ic_protobuf::{
+    proxy::{try_decode_hash, ProxyDecodeError},
+    state::canister_state_bits::v1 as pb,
+};
+use ic_types::NumBytes;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// The maximum size of a single chunk in the store.
+pub const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// The default maximum number of chunks a canister can keep in its store.
+pub const DEFAULT_MAX_SIZE: u64 = 100;
+
+/// The SHA-256 hash of a chunk, which is also its key in the store.
+pub type WasmChunkHash = [u8; 32];
+
+/// The position of a chunk in the `PageMap` of the store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ChunkInfo {
+    /// The chunk occupies the `CHUNK_SIZE` bytes starting at
+    /// `index * CHUNK_SIZE`.
+    index: u64,
+    /// The number of bytes of the chunk.
+    length: u64,
+}
+
+/// Describes which chunks are kept in the `PageMap` of a `WasmChunkStore`
+/// and where. Unlike the chunks themselves, it is persisted as part of the
+/// canister state bits.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasmChunkStoreMetadata {
+    chunks: BTreeMap<WasmChunkHash, ChunkInfo>,
+}
+
+impl From<&WasmChunkStoreMetadata> for pb::WasmChunkStoreMetadata {
+    fn from(item: &WasmChunkStoreMetadata) -> Self {
+        Self {
+            chunks: item
+                .chunks
+                .iter()
+                .map(|(hash, info)| pb::WasmChunkData {
+                    hash: hash.to_vec(),
+                    index: info.index,
+                    length: info.length,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<pb::WasmChunkStoreMetadata> for WasmChunkStoreMetadata {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb::WasmChunkStoreMetadata) -> Result<Self, Self::Error> {
+        let mut chunks = BTreeMap::new();
+        for chunk in item.chunks {
+            chunks.insert(
+                try_decode_hash(chunk.hash)?,
+                ChunkInfo {
+                    index: chunk.index,
+                    length: chunk.length,
+                },
+            );
+        }
+        Ok(Self { chunks })
+    }
+}
+
+/// A content-addressed store of Wasm module chunks, uploaded through the
+/// management canister's `upload_chunk` method and later assembled into a
+/// module by `install_chunked_code`.
+///
+/// The chunks are kept in a `PageMap`, like the memories of the canister, so
+/// that checkpoints only need to write the chunks uploaded since the previous
+/// one. Every chunk takes up a slot of `CHUNK_SIZE` bytes in it, which is what
+/// counts towards the memory usage of the canister. Uploading the same chunk
+/// twice stores it only once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WasmChunkStore {
+    data: PageMap,
+    metadata: WasmChunkStoreMetadata,
+}
+
+impl WasmChunkStore {
+    pub fn new(fd_factory: Arc<dyn PageAllocatorFileDescriptor>) -> Self {
+        Self {
+            data: PageMap::new(fd_factory),
+            metadata: WasmChunkStoreMetadata::default(),
+        }
+    }
+
+    pub fn new_for_testing() -> Self {
+        Self {
+            data: PageMap::new_for_testing(),
+            metadata: WasmChunkStoreMetadata::default(),
+        }
+    }
+
+    /// Restores a store from the `PageMap` and the metadata persisted in a
+    /// checkpoint.
+    pub fn from_checkpoint(data: PageMap, metadata: WasmChunkStoreMetadata) -> Self {
+        Self { data, metadata }
+    }
+
+    pub fn page_map(&self) -> &PageMap {
+        &self.data
+    }
+
+    pub fn page_map_mut(&mut self) -> &mut PageMap {
+        &mut self.data
+    }
+
+    pub fn metadata(&self) -> &WasmChunkStoreMetadata {
+        &self.metadata
+    }
+
+    /// Returns the hash under which `chunk` is stored.
+    pub fn chunk_hash(chunk: &[u8]) -> WasmChunkHash {
+        Sha256::hash(chunk)
+    }
+
+    /// Returns the contents of the chunk with the given hash, if it exists.
+    pub fn get_chunk(&self, hash: &WasmChunkHash) -> Option<Vec<u8>> {
+        let info = self.metadata.chunks.get(hash)?;
+        let mut chunk = vec![0; info.length as usize];
+        Buffer::new(self.data.clone()).read(&mut chunk, (info.index * CHUNK_SIZE) as usize);
+        Some(chunk)
+    }
+
+    /// Returns the hashes of all chunks in the store, in ascending order.
+    pub fn keys(&self) -> impl Iterator<Item = &WasmChunkHash> {
+        self.metadata.chunks.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.metadata.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metadata.chunks.is_empty()
+    }
+
+    /// Returns `true` if the store already contains a chunk with the given
+    /// hash.
+    pub fn contains(&self, hash: &WasmChunkHash) -> bool {
+        self.metadata.chunks.contains_key(hash)
+    }
+
+    /// Returns the number of bytes reserved for the chunks in the store.
+    pub fn memory_usage(&self) -> NumBytes {
+        NumBytes::from(self.len() as u64 * CHUNK_SIZE)
+    }
+
+    /// Checks whether `chunk` can be added to a store that may hold at most
+    /// `max_chunks` chunks. Re-uploading an existing chunk is always allowed.
+    pub fn can_insert_chunk(&self, max_chunks: u64, chunk: &[u8]) -> Result<(), String> {
+        if chunk.len() as u64 > CHUNK_SIZE {
+            return Err(format!(
+                "Wasm chunk size {} exceeds the maximum chunk size of {}",
+                chunk.len(),
+                CHUNK_SIZE
+            ));
+        }
+        if self.len() as u64 >= max_chunks && !self.contains(&Self::chunk_hash(chunk)) {
+            return Err(format!(
+                "Wasm chunk store already contains the maximum number of {} chunks",
+                max_chunks
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds the chunk to the store and returns its hash. Callers are expected
+    /// to check `can_insert_chunk` first.
+    pub fn insert_chunk(&mut self, chunk: &[u8]) -> WasmChunkHash {
+        let hash = Self::chunk_hash(chunk);
+        if !self.contains(&hash) {
+            // Chunks are only ever removed all at once, so the next free slot
+            // is the one after the last chunk.
+            let index = self.len() as u64;
+            let mut buffer = Buffer::new(self.data.clone());
+            buffer.write(chunk, (index * CHUNK_SIZE) as usize);
+            self.data.update(&buffer.dirty_pages().collect::<Vec<_>>());
+            self.metadata.chunks.insert(
+                hash,
+                ChunkInfo {
+                    index,
+                    length: chunk.len() as u64,
+                },
+            );
+        }
+        hash
+    }
+
+    /// Removes all chunks from the store. The chunks are dropped together with
+    /// their `PageMap`, so that the next checkpoint does not keep them on disk.
+    pub fn clear(&mut self, fd_factory: Arc<dyn PageAllocatorFileDescriptor>) {
+        *self = Self::new(fd_factory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page_map::TestPageAllocatorFileDescriptorImpl;
+
+    #[test]
+    fn chunks_are_deduplicated() {
+        let mut store = WasmChunkStore::new_for_testing();
+        let hash = store.insert_chunk(&[1, 2, 3]);
+        assert_eq!(store.insert_chunk(&[1, 2, 3]), hash);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.memory_usage(), NumBytes::from(CHUNK_SIZE));
+        assert_eq!(store.get_chunk(&hash).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn chunks_are_stored_in_separate_slots() {
+        let mut store = WasmChunkStore::new_for_testing();
+        let first = vec![1; CHUNK_SIZE as usize];
+        let second = vec![2; 10];
+        let first_hash = store.insert_chunk(&first);
+        let second_hash = store.insert_chunk(&second);
+        assert_eq!(store.get_chunk(&first_hash).unwrap(), first);
+        assert_eq!(store.get_chunk(&second_hash).unwrap(), second);
+        assert_eq!(store.memory_usage(), NumBytes::from(2 * CHUNK_SIZE));
+    }
+
+    #[test]
+    fn can_insert_chunk_enforces_limits() {
+        let mut store = WasmChunkStore::new_for_testing();
+        assert!(store
+            .can_insert_chunk(1, &vec![0; CHUNK_SIZE as usize + 1])
+            .is_err());
+        store.insert_chunk(&[1]);
+        assert!(store.can_insert_chunk(1, &[2]).is_err());
+        // Re-uploading an existing chunk does not need more space.
+        assert!(store.can_insert_chunk(1, &[1]).is_ok());
+    }
+
+    #[test]
+    fn clear_releases_memory() {
+        let mut store = WasmChunkStore::new_for_testing();
+        store.insert_chunk(&[1]);
+        store.insert_chunk(&[2, 3]);
+        assert_eq!(store.memory_usage(), NumBytes::from(2 * CHUNK_SIZE));
+        store.clear(Arc::new(TestPageAllocatorFileDescriptorImpl::new()));
+        assert!(store.is_empty());
+        assert_eq!(store.memory_usage(), NumBytes::from(0));
+        assert_eq!(store.page_map().num_host_pages(), 0);
+    }
+
+    #[test]
+    fn metadata_roundtrips_through_protobuf() {
+        let mut store = WasmChunkStore::new_for_testing();
+        store.insert_chunk(&[1, 2, 3]);
+        store.insert_chunk(&[4, 5]);
+
+        let pb_metadata = pb::WasmChunkStoreMetadata::from(store.metadata());
+        let metadata = WasmChunkStoreMetadata::try_from(pb_metadata).unwrap();
+        assert_eq!(&metadata, store.metadata());
+
+        let restored = WasmChunkStore::from_checkpoint(store.page_map().clone(), metadata);
+        assert_eq!(restored, store);
+    }
+}
@@ -19,7 +19,7 @@ use ic_test_utilities::types::{
     ids::user_test_id,
     messages::{RequestBuilder, ResponseBuilder},
 };
-use ic_types::messages::CallContextId;
+use ic_types::messages::{CallContextId, NO_DEADLINE};
 use ic_types::{
     messages::CallbackId,
     methods::{Callback, WasmClosure},
@@ -67,7 +67,7 @@ struct CanisterStateFixture {
 impl CanisterStateFixture {
     fn new() -> CanisterStateFixture {
         let scheduler_state = SchedulerState::default();
-        let system_state = SystemState::new_running(
+        let system_state = SystemState::new_running_for_testing(
             CANISTER_ID,
             user_test_id(24).get(),
             Cycles::new(1 << 36),
@@ -86,7 +86,7 @@ impl CanisterStateFixture {
             .call_context_manager_mut()
             .unwrap()
             .new_call_context(
-                CallOrigin::CanisterUpdate(CANISTER_ID, CallbackId::from(1)),
+                CallOrigin::CanisterUpdate(CANISTER_ID, CallbackId::from(1), NO_DEADLINE),
                 Cycles::zero(),
                 Time::from_nanos_since_unix_epoch(0),
             );
@@ -104,6 +104,7 @@ impl CanisterStateFixture {
                 WasmClosure::new(0, 2),
                 WasmClosure::new(0, 2),
                 None,
+                NO_DEADLINE,
             ))
     }
 
@@ -551,6 +552,7 @@ fn canister_state_callback_round_trip() {
         WasmClosure::new(0, 2),
         WasmClosure::new(0, 2),
         None,
+        NO_DEADLINE,
     );
 
     let pb_callback = pb::Callback::from(&callback);
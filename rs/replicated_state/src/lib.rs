@@ -1,4 +1,5 @@
 mod bitcoin;
+pub mod canister_snapshots;
 pub mod canister_state;
 pub(crate) mod hash;
 pub mod metadata_state;
@@ -9,6 +10,7 @@ pub mod testing {
     pub use super::canister_state::testing::CanisterQueuesTesting;
     pub use super::replicated_state::testing::ReplicatedStateTesting;
 }
+pub use canister_snapshots::{CanisterSnapshot, CanisterSnapshots};
 pub use canister_state::{
     execution_state::Memory,
     num_bytes_try_from,
@@ -1,10 +1,13 @@
+pub mod query_stats;
 pub mod subnet_call_context_manager;
 #[cfg(test)]
 mod tests;
 
 use crate::{
     canister_state::system_state::CyclesUseCase,
-    metadata_state::subnet_call_context_manager::SubnetCallContextManager,
+    metadata_state::{
+        query_stats::RawQueryStats, subnet_call_context_manager::SubnetCallContextManager,
+    },
 };
 use ic_base_types::CanisterId;
 use ic_btc_types_internal::BlockBlob;
@@ -153,6 +156,14 @@ pub struct SystemMetadata {
     /// response limit. To work around this limitation, large responses are paginated
     /// and are stored here temporarily until they're fetched by the calling canister.
     pub bitcoin_get_successors_follow_up_responses: BTreeMap<CanisterId, Vec<BlockBlob>>,
+
+    /// Query statistics reported by the nodes of the subnet, waiting to be
+    /// aggregated into the totals of the canisters.
+    pub raw_query_stats: RawQueryStats,
+
+    /// DER-encoded node signing public keys of the nodes of this subnet,
+    /// populated from the registry at the beginning of each round.
+    pub node_public_keys: BTreeMap<NodeId, Vec<u8>>,
 }
 
 /// Full description of the IC network toplogy.
@@ -385,6 +396,14 @@ pub struct SubnetMetrics {
     pub consumed_cycles_ecdsa_outcalls: NominalCycles,
     consumed_cycles_by_use_case: BTreeMap<CyclesUseCase, NominalCycles>,
     pub ecdsa_signature_agreements: u64,
+    /// The number of canisters on the subnet at the end of the last round.
+    pub num_canisters: u64,
+    /// The memory used by all canisters on the subnet at the end of the last
+    /// round.
+    pub canister_state_bytes: NumBytes,
+    /// The cycles consumed by the subnet, including those consumed by deleted
+    /// canisters, as of the end of the last round.
+    pub consumed_cycles_total: NominalCycles,
 }
 
 impl SubnetMetrics {
@@ -422,6 +441,9 @@ impl From<&SubnetMetrics> for pb_metadata::SubnetMetrics {
                     cycles: Some((&entry.1).into()),
                 })
                 .collect(),
+            num_canisters: Some(item.num_canisters),
+            canister_state_bytes: Some(item.canister_state_bytes.get()),
+            consumed_cycles_total: Some((&item.consumed_cycles_total).into()),
         }
     }
 }
@@ -455,6 +477,13 @@ impl TryFrom<pb_metadata::SubnetMetrics> for SubnetMetrics {
                     )
                 })
                 .collect(),
+            num_canisters: item.num_canisters.unwrap_or_default(),
+            canister_state_bytes: NumBytes::from(item.canister_state_bytes.unwrap_or_default()),
+            consumed_cycles_total: try_from_option_field(
+                item.consumed_cycles_total,
+                "SubnetMetrics::consumed_cycles_total",
+            )
+            .unwrap_or_else(|_| NominalCycles::from(0_u128)),
         })
     }
 }
@@ -498,6 +527,15 @@ impl From<&SystemMetadata> for pb_metadata::SystemMetadata {
                     },
                 )
                 .collect(),
+            raw_query_stats: Some((&item.raw_query_stats).into()),
+            node_public_keys: item
+                .node_public_keys
+                .iter()
+                .map(|(node_id, public_key)| pb_metadata::NodePublicKeyEntry {
+                    node_id: Some(node_id_into_protobuf(*node_id)),
+                    public_key: public_key.clone(),
+                })
+                .collect(),
         }
     }
 }
@@ -550,6 +588,11 @@ impl TryFrom<pb_metadata::SystemMetadata> for SystemMetadata {
             bitcoin_get_successors_follow_up_responses.insert(sender, response.payloads);
         }
 
+        let mut node_public_keys = BTreeMap::new();
+        for entry in item.node_public_keys {
+            node_public_keys.insert(node_id_try_from_option(entry.node_id)?, entry.public_key);
+        }
+
         let batch_time = Time::from_nanos_since_unix_epoch(item.batch_time_nanos);
         Ok(Self {
             own_subnet_id: subnet_id_try_from_protobuf(try_from_option_field(
@@ -598,6 +641,11 @@ impl TryFrom<pb_metadata::SystemMetadata> for SystemMetadata {
             },
             expected_compiled_wasms: BTreeSet::new(),
             bitcoin_get_successors_follow_up_responses,
+            raw_query_stats: match item.raw_query_stats {
+                Some(raw_query_stats) => raw_query_stats.try_into()?,
+                None => RawQueryStats::default(),
+            },
+            node_public_keys,
         })
     }
 }
@@ -632,6 +680,8 @@ impl SystemMetadata {
             subnet_metrics: Default::default(),
             expected_compiled_wasms: BTreeSet::new(),
             bitcoin_get_successors_follow_up_responses: BTreeMap::default(),
+            raw_query_stats: RawQueryStats::default(),
+            node_public_keys: BTreeMap::new(),
         }
     }
 
@@ -864,6 +914,9 @@ impl SystemMetadata {
             subnet_metrics,
             expected_compiled_wasms,
             bitcoin_get_successors_follow_up_responses,
+            raw_query_stats,
+            // Overwritten as soon as the round begins, no explicit action needed.
+            node_public_keys,
         } = self;
 
         let split_from = split_from.expect("Not a state resulting from a subnet split");
@@ -899,6 +952,8 @@ impl SystemMetadata {
             subnet_metrics,
             expected_compiled_wasms,
             bitcoin_get_successors_follow_up_responses,
+            raw_query_stats,
+            node_public_keys,
         }
     }
 }
@@ -0,0 +1,85 @@
+use ic_protobuf::{proxy::ProxyDecodeError, state::system_metadata::v1 as pb_metadata};
+use ic_types::{
+    batch::{CanisterQueryStats, QueryStatsEpoch, QueryStatsPayload},
+    node_id_into_protobuf, node_id_try_from_option, NodeId,
+};
+use std::collections::BTreeMap;
+
+/// Query statistics reported by the nodes of the subnet that were not yet
+/// aggregated into the canisters' `total_query_stats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RawQueryStats {
+    /// The highest epoch whose statistics were aggregated, if any. Reports for
+    /// this or earlier epochs are no longer accepted.
+    pub highest_aggregated_epoch: Option<QueryStatsEpoch>,
+
+    /// The statistics reported by each node, by epoch.
+    pub reports: BTreeMap<QueryStatsEpoch, BTreeMap<NodeId, Vec<CanisterQueryStats>>>,
+}
+
+impl RawQueryStats {
+    /// Returns `true` if `proposer` already reported its statistics for
+    /// `epoch`, or if `epoch` was already aggregated.
+    pub fn has_report(&self, epoch: QueryStatsEpoch, proposer: &NodeId) -> bool {
+        self.highest_aggregated_epoch
+            .map_or(false, |highest| epoch <= highest)
+            || self
+                .reports
+                .get(&epoch)
+                .map_or(false, |reports| reports.contains_key(proposer))
+    }
+
+    /// Records the statistics reported in `payload`.
+    pub fn insert(&mut self, payload: QueryStatsPayload) {
+        self.reports
+            .entry(payload.epoch)
+            .or_default()
+            .insert(payload.proposer, payload.stats);
+    }
+}
+
+impl From<&RawQueryStats> for pb_metadata::RawQueryStats {
+    fn from(item: &RawQueryStats) -> Self {
+        Self {
+            highest_aggregated_epoch: item.highest_aggregated_epoch.map(|epoch| epoch.get()),
+            reports: item
+                .reports
+                .iter()
+                .flat_map(|(epoch, reports)| {
+                    reports
+                        .iter()
+                        .map(|(proposer, stats)| pb_metadata::QueryStatsReport {
+                            epoch: epoch.get(),
+                            proposer: Some(node_id_into_protobuf(*proposer)),
+                            stats: stats.iter().map(Into::into).collect(),
+                        })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<pb_metadata::RawQueryStats> for RawQueryStats {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb_metadata::RawQueryStats) -> Result<Self, Self::Error> {
+        let mut reports: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+        for report in item.reports {
+            reports
+                .entry(QueryStatsEpoch::from(report.epoch))
+                .or_default()
+                .insert(
+                    node_id_try_from_option(report.proposer)?,
+                    report
+                        .stats
+                        .into_iter()
+                        .map(CanisterQueryStats::try_from)
+                        .collect::<Result<_, _>>()?,
+                );
+        }
+        Ok(Self {
+            highest_aggregated_epoch: item.highest_aggregated_epoch.map(QueryStatsEpoch::from),
+            reports,
+        })
+    }
+}
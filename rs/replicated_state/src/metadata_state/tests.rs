@@ -8,19 +8,20 @@ use ic_test_utilities::{
     mock_time,
     types::{
         ids::{
-            canister_test_id, message_test_id, subnet_test_id, user_test_id, SUBNET_0, SUBNET_1,
-            SUBNET_2,
+            canister_test_id, message_test_id, node_test_id, subnet_test_id, user_test_id,
+            SUBNET_0, SUBNET_1, SUBNET_2,
         },
         messages::{RequestBuilder, ResponseBuilder},
         xnet::{StreamHeaderBuilder, StreamSliceBuilder},
     },
 };
-use ic_types::{canister_http::Transform, time::current_time};
 use ic_types::{
+    batch::{CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload},
     canister_http::{CanisterHttpMethod, CanisterHttpRequestContext},
     ingress::WasmResult,
     messages::{CallbackId, Payload},
 };
+use ic_types::{canister_http::Transform, time::current_time};
 use lazy_static::lazy_static;
 use maplit::btreemap;
 
@@ -447,6 +448,38 @@ fn roundtrip_encoding() {
     // Set `last_generated_canister_id` to valid, but migrated canister ID.
     system_metadata.last_generated_canister_id = Some(15.into());
     validate_roundtrip_encoding(&system_metadata);
+
+    // Add raw query stats reported by two nodes.
+    system_metadata.raw_query_stats.highest_aggregated_epoch = Some(QueryStatsEpoch::from(2));
+    for node in [node_test_id(1), node_test_id(2)] {
+        system_metadata.raw_query_stats.insert(QueryStatsPayload {
+            epoch: QueryStatsEpoch::from(3),
+            proposer: node,
+            stats: vec![CanisterQueryStats {
+                canister_id: *LOCAL_CANISTER,
+                stats: QueryStats {
+                    num_calls: 1,
+                    num_instructions: 2,
+                    ingress_payload_size: 3,
+                    egress_payload_size: 4,
+                },
+            }],
+        });
+    }
+    validate_roundtrip_encoding(&system_metadata);
+
+    // Set subnet metrics and node public keys.
+    system_metadata.subnet_metrics = SubnetMetrics {
+        num_canisters: 5,
+        canister_state_bytes: NumBytes::from(1 << 20),
+        consumed_cycles_total: NominalCycles::from(12_345_u128),
+        ..Default::default()
+    };
+    system_metadata.node_public_keys = btreemap! {
+        node_test_id(1) => vec![1; 44],
+        node_test_id(2) => vec![2; 44],
+    };
+    validate_roundtrip_encoding(&system_metadata);
 }
 
 #[test]
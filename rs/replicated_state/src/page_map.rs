@@ -1,6 +1,7 @@
 mod checkpoint;
 pub mod int_map;
 mod page_allocator;
+mod storage;
 
 use checkpoint::Checkpoint;
 pub use checkpoint::{CheckpointSerialization, MappingSerialization};
@@ -11,6 +12,8 @@ pub use page_allocator::{
     allocated_pages_count, PageAllocator, PageAllocatorRegistry, PageAllocatorSerialization,
     PageDeltaSerialization, PageSerialization,
 };
+pub use storage::{MergeCandidate, OverlayFileSerialization, StorageLayout, StorageSerialization};
+use storage::{OverlayFile, Storage};
 
 // NOTE: We use a persistent map to make snapshotting of a PageMap a cheap
 // operation. This allows us to simplify canister state management: we can
@@ -199,6 +202,8 @@ pub enum PersistenceError {
     },
     /// (Slice) size is not equal to page size.
     BadPageSize { expected: usize, actual: usize },
+    /// Overlay file is malformed.
+    InvalidOverlay { path: String, message: String },
 }
 
 impl PersistenceError {
@@ -244,6 +249,9 @@ impl std::fmt::Display for PersistenceError {
                 "Bad slice size: expected {}, actual {}",
                 expected, actual
             ),
+            PersistenceError::InvalidOverlay { path, message } => {
+                write!(f, "Invalid overlay file {}: {}", path, message)
+            }
         }
     }
 }
@@ -264,8 +272,9 @@ pub type FileOffset = off_t;
 /// pages share the same backing store. There are three possible cases:
 /// - The page is not in the current `PageMap` and it is zero initialized.
 /// - The page maps to the checkpoint file.
-/// - The page is in the page delta of the current `PageMap`. In this case the
-///   range is a singleton and its contents need to be copied out.
+/// - The page is in the page delta of the current `PageMap` or in one of its
+///   overlay files. In this case the range is a singleton and its contents
+///   need to be copied out.
 #[derive(Debug, PartialEq)]
 pub enum MemoryRegion<'a> {
     Zeros(Range<PageIndex>),
@@ -290,14 +299,14 @@ pub enum MemoryRegion<'a> {
 /// versioned.
 #[derive(Clone)]
 pub struct PageMap {
-    /// The checkpoint that is used for all the pages that can not be found in
-    /// the `page_delta`.
-    checkpoint: Checkpoint,
+    /// The checkpoint file and the overlay files on top of it that are used
+    /// for all the pages that can not be found in the `page_delta`.
+    storage: Storage,
 
     /// The height of the checkpoint that backs the page map.
     pub base_height: Option<Height>,
 
-    /// The map containing pages overriding pages from the `checkpoint`.
+    /// The map containing pages overriding pages from the `storage`.
     /// We need these pages to be able to reconstruct the full heap.
     /// It is reset when `strip_all_deltas()` method is called.
     page_delta: PageDelta,
@@ -322,7 +331,7 @@ impl PageMap {
     /// the page map is instantiated with.
     pub fn new(fd_factory: Arc<dyn PageAllocatorFileDescriptor>) -> Self {
         Self {
-            checkpoint: Default::default(),
+            storage: Default::default(),
             base_height: Default::default(),
             page_delta: Default::default(),
             unflushed_delta: Default::default(),
@@ -334,7 +343,7 @@ impl PageMap {
     /// Creates a new page map for testing purposes.
     pub fn new_for_testing() -> Self {
         Self {
-            checkpoint: Default::default(),
+            storage: Default::default(),
             base_height: Default::default(),
             page_delta: Default::default(),
             unflushed_delta: Default::default(),
@@ -351,9 +360,29 @@ impl PageMap {
         base_height: Height,
         fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
     ) -> Result<Self, PersistenceError> {
-        let checkpoint = Checkpoint::open(heap_file)?;
+        let storage = Storage::from_base(Checkpoint::open(heap_file)?);
+        Ok(Self {
+            storage,
+            base_height: Some(base_height),
+            page_delta: Default::default(),
+            unflushed_delta: Default::default(),
+            has_stripped_unflushed_deltas: false,
+            page_allocator: PageAllocator::new(fd_factory),
+        })
+    }
+
+    /// Creates a page map backed by the base file and all the overlay files
+    /// of the provided layout.
+    ///
+    /// Note that the files are assumed to be read-only.
+    pub fn open_with_overlays(
+        storage_layout: &dyn StorageLayout,
+        base_height: Height,
+        fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
+    ) -> Result<Self, PersistenceError> {
+        let storage = Storage::load(storage_layout)?;
         Ok(Self {
-            checkpoint,
+            storage,
             base_height: Some(base_height),
             page_delta: Default::default(),
             unflushed_delta: Default::default(),
@@ -365,7 +394,7 @@ impl PageMap {
     /// Returns a serialization-friendly representation of the page-map.
     pub fn serialize(&self) -> PageMapSerialization {
         PageMapSerialization {
-            checkpoint: self.checkpoint.serialize(),
+            storage: self.storage.serialize(),
             base_height: self.base_height,
             page_delta: self
                 .page_allocator
@@ -387,14 +416,14 @@ impl PageMap {
         page_map: PageMapSerialization,
         registry: &PageAllocatorRegistry,
     ) -> Result<Self, PersistenceError> {
-        let checkpoint = Checkpoint::deserialize(page_map.checkpoint)?;
+        let storage = Storage::deserialize(page_map.storage)?;
         let page_allocator = PageAllocator::deserialize(page_map.page_allocator, registry);
         let page_delta =
             PageDelta::from(page_allocator.deserialize_page_delta(page_map.page_delta));
         let unflushed_delta =
             PageDelta::from(page_allocator.deserialize_page_delta(page_map.unflushed_delta));
         Ok(Self {
-            checkpoint,
+            storage,
             base_height: page_map.base_height,
             page_delta,
             unflushed_delta,
@@ -441,6 +470,50 @@ impl PageMap {
         self.persist_to_file(&self.unflushed_delta, dst)
     }
 
+    /// Persists the unflushed delta contained in this page map as a new
+    /// overlay file at the specified destination. Unlike
+    /// `persist_unflushed_delta()`, this never modifies existing files.
+    pub fn persist_unflushed_delta_to_overlay(&self, dst: &Path) -> Result<(), PersistenceError> {
+        let pages: Vec<(PageIndex, &PageBytes)> = self
+            .unflushed_delta
+            .iter()
+            .map(|(index, page)| (index, page.contents()))
+            .collect();
+        OverlayFile::write(&pages, dst)
+    }
+
+    /// Persists all pages of this page map, i.e. the checkpoint with the
+    /// overlays and the page delta applied on top, to the specified
+    /// destination. Unlike
+    /// `persist_delta()`, this does not require `dst` to contain the
+    /// checkpoint already.
+    pub fn persist_all(&self, dst: &Path) -> Result<(), PersistenceError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dst)
+            .map_err(|err| PersistenceError::FileSystemError {
+                path: dst.display().to_string(),
+                context: "Failed to open file".to_string(),
+                internal_error: err.to_string(),
+            })?;
+        let num_pages = self.num_host_pages() as u64;
+        let mut start = 0;
+        while start < num_pages {
+            let end = num_pages.min(start + MAXIMUM_GAP);
+            let mut buffer = WriteBuffer {
+                content: (start..end)
+                    .map(|i| &self.get_page(PageIndex::new(i))[..])
+                    .collect(),
+                start_index: PageIndex::new(start),
+            };
+            buffer.apply_to_file(&mut file, dst)?;
+            start = end;
+        }
+        Ok(())
+    }
+
     /// Returns the iterator over host pages managed by this `PageMap`.
     pub fn host_pages_iter(&self) -> impl Iterator<Item = (PageIndex, &PageBytes)> + '_ {
         (0..self.num_host_pages()).map(move |i| {
@@ -453,7 +526,7 @@ impl PageMap {
     pub fn get_page(&self, page_index: PageIndex) -> &PageBytes {
         match self.page_delta.get_page(page_index) {
             Some(page) => page,
-            None => self.checkpoint.get_page(page_index),
+            None => self.storage.get_page(page_index),
         }
     }
 
@@ -482,17 +555,15 @@ impl PageMap {
                 };
                 let range = Range { start, end };
                 assert!(range.contains(&page_index));
-                self.checkpoint.get_memory_region(page_index, range)
+                self.storage.get_memory_region(page_index, range)
             }
         }
     }
 
-    /// Returns the whole checkpoint memory region.
+    /// Returns the whole checkpoint memory region. Note that pages in this
+    /// region may be shadowed by overlays, see `get_memory_region()`.
     pub fn get_checkpoint_memory_region(&self) -> MemoryRegion {
-        let start = PageIndex::new(0);
-        let end = PageIndex::new(u64::MAX);
-        self.checkpoint
-            .get_memory_region(start, Range { start, end })
+        self.storage.get_base_memory_region()
     }
 
     /// Removes the page delta from this page map.
@@ -542,8 +613,8 @@ impl PageMap {
     /// ∀ n . n ≥ self.num_host_pages() ⇒ self.get_page(n) = ZERO_PAGE
     /// ```
     pub fn num_host_pages(&self) -> usize {
-        let pages_in_checkpoint = self.checkpoint.num_pages();
-        pages_in_checkpoint.max(
+        let pages_in_storage = self.storage.num_logical_pages();
+        pages_in_storage.max(
             self.page_delta
                 .max_page_index()
                 .map(|i| i.get() + 1)
@@ -551,10 +622,10 @@ impl PageMap {
         )
     }
 
-    /// Switches the checkpoint file of the current page map to the one provided
+    /// Switches the checkpoint files of the current page map to the ones provided
     /// by the given page map. Page deltas of both page maps must be empty.
     pub fn switch_to_checkpoint(&mut self, checkpointed_page_map: &PageMap) {
-        self.checkpoint = checkpointed_page_map.checkpoint.clone();
+        self.storage = checkpointed_page_map.storage.clone();
         // Also copy the base height to reflect the height of the new checkpoint.
         self.base_height = checkpointed_page_map.base_height;
         assert!(self.page_delta.is_empty());
@@ -789,7 +860,7 @@ impl std::fmt::Debug for PageMap {
 /// need `unflushed_delta`, but the field is kept for consistency here.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PageMapSerialization {
-    pub checkpoint: CheckpointSerialization,
+    pub storage: StorageSerialization,
     pub base_height: Option<Height>,
     pub page_delta: PageDeltaSerialization,
     pub unflushed_delta: PageDeltaSerialization,
@@ -0,0 +1,577 @@
+//! Layered on-disk storage of a `PageMap`.
+//!
+//! The persisted state of a page map consists of a base file, i.e. a full
+//! image of the memory as written by `PageMap::persist_delta()`, and a stack
+//! of overlay files on top of it. An overlay file is immutable and contains
+//! only the pages that were modified while it was produced, together with an
+//! index that maps page indices to their location in the file. A page is
+//! looked up in the overlays from the newest to the oldest one and, if none
+//! of them contains it, in the base file.
+//!
+//! An overlay file has the following layout:
+//!
+//! ```text
+//! ┌──────────────────────┬──────────────────────────┬────────────────┐
+//! │ pages                │ index                    │ trailer        │
+//! │ num_pages × PAGE_SIZE│ num_ranges × 24 bytes    │ 24 bytes       │
+//! └──────────────────────┴──────────────────────────┴────────────────┘
+//! ```
+//!
+//! Every index entry describes a range of consecutive page indices whose
+//! pages are stored consecutively in the `pages` section. It consists of
+//! `start_page: u64`, `end_page: u64` (exclusive) and `start_slot: u64`, the
+//! position of the first page of the range in the `pages` section. Entries
+//! are sorted by `start_page` and do not overlap. The trailer consists of
+//! `num_pages: u64`, `num_ranges: u64` and `version: u64`. All integers are
+//! little-endian.
+
+use crate::page_map::{
+    checkpoint::{Checkpoint, CheckpointSerialization, MappingSerialization},
+    FileDescriptor, FileOffset, MemoryRegion, PageIndex, PersistenceError,
+};
+use ic_sys::{mmap::ScopedMmap, page_bytes_from_ptr, PageBytes, PAGE_SIZE};
+use ic_types::Height;
+use ic_utils::fs::write_all_vectored;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::ops::Range;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The version of the overlay file format written by this replica.
+const OVERLAY_VERSION: u64 = 0;
+
+/// Size of a single index entry in bytes.
+const INDEX_ENTRY_SIZE: usize = 3 * std::mem::size_of::<u64>();
+
+/// Size of the trailer in bytes.
+const TRAILER_SIZE: usize = 3 * std::mem::size_of::<u64>();
+
+/// Maximum number of pages written to an overlay file in a single vectored
+/// write.
+const MAX_PAGES_PER_WRITE: usize = 200;
+
+/// Every overlay must be at least `MERGE_SIZE_RATIO` times as large as all
+/// newer overlays combined. This keeps the number of overlays logarithmic in
+/// the amount of data written, while every page is rewritten by merges only
+/// a logarithmic number of times.
+const MERGE_SIZE_RATIO: u64 = 2;
+
+/// Describes where the files that make up the persisted state of a `PageMap`
+/// are located.
+pub trait StorageLayout {
+    /// Path of the base file.
+    fn base(&self) -> PathBuf;
+
+    /// Path of the overlay file written at the given height.
+    fn overlay(&self, height: Height) -> PathBuf;
+
+    /// Paths of all existing overlay files, ordered from the oldest to the
+    /// newest one.
+    fn existing_overlays(&self) -> Result<Vec<PathBuf>, PersistenceError>;
+}
+
+/// A range of page indices that is stored contiguously in an overlay file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PageIndexRange {
+    start_page: u64,
+    end_page: u64,
+    start_slot: u64,
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+struct OverlayMapping {
+    mmap: ScopedMmap,
+    _file: File, // It is not used but it keeps the `file_descriptor` alive.
+    file_descriptor: FileDescriptor,
+    num_pages: u64,
+    index: Vec<PageIndexRange>,
+}
+
+impl OverlayMapping {
+    fn new(file: File, len: usize, path: Option<&Path>) -> Result<Self, PersistenceError> {
+        let path = match path {
+            Some(path) => path.display().to_string(),
+            None => format!("/proc/self/fd/{}", file.as_raw_fd()),
+        };
+        let invalid_overlay = |message: String| PersistenceError::InvalidOverlay {
+            path: path.clone(),
+            message,
+        };
+        if len < TRAILER_SIZE {
+            return Err(invalid_overlay(format!(
+                "file size {} is smaller than the trailer",
+                len
+            )));
+        }
+        let mmap = ScopedMmap::from_readonly_file(&file, len).map_err(|err| {
+            PersistenceError::MmapError {
+                path: path.clone(),
+                len,
+                internal_error: err.to_string(),
+            }
+        })?;
+
+        let bytes = mmap.as_slice();
+        let trailer_start = len - TRAILER_SIZE;
+        let num_pages = read_u64(bytes, trailer_start);
+        let num_ranges = read_u64(bytes, trailer_start + 8);
+        let version = read_u64(bytes, trailer_start + 16);
+        if version != OVERLAY_VERSION {
+            return Err(invalid_overlay(format!("unsupported version {}", version)));
+        }
+        let expected_len = num_pages as u128 * PAGE_SIZE as u128
+            + num_ranges as u128 * INDEX_ENTRY_SIZE as u128
+            + TRAILER_SIZE as u128;
+        if expected_len != len as u128 {
+            return Err(invalid_overlay(format!(
+                "file size {} does not match {} pages and {} index entries",
+                len, num_pages, num_ranges
+            )));
+        }
+
+        let index_start = num_pages as usize * PAGE_SIZE;
+        let mut index: Vec<PageIndexRange> = Vec::with_capacity(num_ranges as usize);
+        let mut next_slot = 0;
+        for i in 0..num_ranges as usize {
+            let offset = index_start + i * INDEX_ENTRY_SIZE;
+            let range = PageIndexRange {
+                start_page: read_u64(bytes, offset),
+                end_page: read_u64(bytes, offset + 8),
+                start_slot: read_u64(bytes, offset + 16),
+            };
+            let after_previous = index
+                .last()
+                .map_or(true, |previous| previous.end_page <= range.start_page);
+            if range.start_page >= range.end_page
+                || range.start_slot != next_slot
+                || !after_previous
+            {
+                return Err(invalid_overlay(format!(
+                    "invalid index entry #{}: {:?}",
+                    i, range
+                )));
+            }
+            next_slot = next_slot.saturating_add(range.end_page - range.start_page);
+            index.push(range);
+        }
+        if next_slot != num_pages {
+            return Err(invalid_overlay(format!(
+                "the index covers {} pages instead of {}",
+                next_slot, num_pages
+            )));
+        }
+
+        let fd = file.as_raw_fd();
+        Ok(Self {
+            mmap,
+            _file: file,
+            file_descriptor: FileDescriptor { fd },
+            num_pages,
+            index,
+        })
+    }
+
+    fn open(path: &Path) -> Result<Self, PersistenceError> {
+        let file = OpenOptions::new().read(true).open(path).map_err(|err| {
+            PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to open file".to_string(),
+                internal_error: err.to_string(),
+            }
+        })?;
+        let metadata = file
+            .metadata()
+            .map_err(|err| PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to retrieve file metadata".to_string(),
+                internal_error: err.to_string(),
+            })?;
+        Self::new(file, metadata.len() as usize, Some(path))
+    }
+
+    fn page_at_slot(&self, slot: u64) -> &PageBytes {
+        assert!(slot < self.num_pages);
+        let page_start = slot as usize * PAGE_SIZE;
+        // SAFETY: The memory from `page_start` to `page_start + PAGE_SIZE` is mapped
+        // and will remain valid for the lifetime of `self`. The memory is read-only
+        // and does not have any mutable references to it.
+        unsafe { page_bytes_from_ptr(self, self.mmap.addr().add(page_start)) }
+    }
+}
+
+/// An immutable overlay file, see the module documentation for its format.
+#[derive(Clone)]
+pub(crate) struct OverlayFile {
+    mapping: Arc<OverlayMapping>,
+}
+
+impl OverlayFile {
+    /// Opens and validates an existing overlay file.
+    pub fn load(path: &Path) -> Result<Self, PersistenceError> {
+        Ok(Self {
+            mapping: Arc::new(OverlayMapping::open(path)?),
+        })
+    }
+
+    /// Writes the given pages as a new overlay file to `path`, replacing
+    /// any existing file. The pages must be sorted by their index and the
+    /// indices must be unique.
+    pub fn write(pages: &[(PageIndex, &PageBytes)], path: &Path) -> Result<(), PersistenceError> {
+        debug_assert!(pages.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        let mut index: Vec<PageIndexRange> = vec![];
+        for (slot, (page_index, _)) in pages.iter().enumerate() {
+            match index.last_mut() {
+                Some(range) if range.end_page == page_index.get() => range.end_page += 1,
+                _ => index.push(PageIndexRange {
+                    start_page: page_index.get(),
+                    end_page: page_index.get() + 1,
+                    start_slot: slot as u64,
+                }),
+            }
+        }
+
+        let mut metadata = Vec::with_capacity(index.len() * INDEX_ENTRY_SIZE + TRAILER_SIZE);
+        for range in index.iter() {
+            metadata.extend_from_slice(&range.start_page.to_le_bytes());
+            metadata.extend_from_slice(&range.end_page.to_le_bytes());
+            metadata.extend_from_slice(&range.start_slot.to_le_bytes());
+        }
+        metadata.extend_from_slice(&(pages.len() as u64).to_le_bytes());
+        metadata.extend_from_slice(&(index.len() as u64).to_le_bytes());
+        metadata.extend_from_slice(&OVERLAY_VERSION.to_le_bytes());
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|err| PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to open file".to_string(),
+                internal_error: err.to_string(),
+            })?;
+        for chunk in pages.chunks(MAX_PAGES_PER_WRITE) {
+            let content: Vec<&[u8]> = chunk.iter().map(|(_, page)| &page[..]).collect();
+            write_all_vectored(&mut file, &content).map_err(|err| {
+                PersistenceError::FileSystemError {
+                    path: path.display().to_string(),
+                    context: "Failed to write overlay pages".to_string(),
+                    internal_error: err.to_string(),
+                }
+            })?;
+        }
+        write_all_vectored(&mut file, &[&metadata]).map_err(|err| {
+            PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to write overlay index".to_string(),
+                internal_error: err.to_string(),
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Returns the page with the given index if it is contained in this
+    /// overlay.
+    pub fn get_page(&self, page_index: PageIndex) -> Option<&PageBytes> {
+        let index = &self.mapping.index;
+        let position = index.partition_point(|range| range.end_page <= page_index.get());
+        let range = index.get(position)?;
+        if range.start_page > page_index.get() {
+            return None;
+        }
+        Some(
+            self.mapping
+                .page_at_slot(range.start_slot + page_index.get() - range.start_page),
+        )
+    }
+
+    /// Returns the closest pages contained in this overlay below and above
+    /// `page_index`, which must not be contained in this overlay itself.
+    pub fn neighbours(&self, page_index: PageIndex) -> (Option<PageIndex>, Option<PageIndex>) {
+        let index = &self.mapping.index;
+        let position = index.partition_point(|range| range.end_page <= page_index.get());
+        let lower = position
+            .checked_sub(1)
+            .map(|previous| PageIndex::new(index[previous].end_page - 1));
+        let upper = index.get(position).map(|range| {
+            debug_assert!(range.start_page > page_index.get());
+            PageIndex::new(range.start_page)
+        });
+        (lower, upper)
+    }
+
+    /// Enumerates all the pages in this overlay, sorted by their index.
+    pub fn iter(&self) -> impl Iterator<Item = (PageIndex, &PageBytes)> + '_ {
+        self.mapping.index.iter().flat_map(move |range| {
+            (range.start_page..range.end_page).map(move |page| {
+                (
+                    PageIndex::new(page),
+                    self.mapping
+                        .page_at_slot(range.start_slot + page - range.start_page),
+                )
+            })
+        })
+    }
+
+    /// Returns one past the largest page index contained in this overlay.
+    pub fn num_logical_pages(&self) -> usize {
+        self.mapping
+            .index
+            .last()
+            .map_or(0, |range| range.end_page as usize)
+    }
+
+    /// Returns a serialization-friendly representation of the overlay.
+    pub fn serialize(&self) -> OverlayFileSerialization {
+        OverlayFileSerialization {
+            mapping: MappingSerialization {
+                file_descriptor: self.mapping.file_descriptor.clone(),
+                file_len: self.mapping.mmap.len() as FileOffset,
+            },
+        }
+    }
+
+    /// Creates the overlay from the given serialization-friendly
+    /// representation.
+    pub fn deserialize(serialized: OverlayFileSerialization) -> Result<Self, PersistenceError> {
+        // SAFETY: the file descriptor is valid because `serialized` is
+        // guaranteed to be valid as a precondition.
+        let file = unsafe { File::from_raw_fd(serialized.mapping.file_descriptor.fd) };
+        Ok(Self {
+            mapping: Arc::new(OverlayMapping::new(
+                file,
+                serialized.mapping.file_len as usize,
+                None,
+            )?),
+        })
+    }
+}
+
+/// The persisted part of a `PageMap`: a base checkpoint file and a stack of
+/// overlay files on top of it.
+#[derive(Clone, Default)]
+pub(crate) struct Storage {
+    base: Checkpoint,
+    /// Overlays ordered from the oldest to the newest one.
+    overlays: Vec<OverlayFile>,
+}
+
+impl Storage {
+    /// Creates a storage that consists of the given base only.
+    pub fn from_base(base: Checkpoint) -> Self {
+        Self {
+            base,
+            overlays: vec![],
+        }
+    }
+
+    /// Opens the base and all overlay files of the given layout.
+    pub fn load(layout: &dyn StorageLayout) -> Result<Self, PersistenceError> {
+        let base = Checkpoint::open(&layout.base())?;
+        let overlays = layout
+            .existing_overlays()?
+            .iter()
+            .map(|path| OverlayFile::load(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { base, overlays })
+    }
+
+    /// Returns the page with the specified `page_index`.
+    pub fn get_page(&self, page_index: PageIndex) -> &PageBytes {
+        self.overlays
+            .iter()
+            .rev()
+            .find_map(|overlay| overlay.get_page(page_index))
+            .unwrap_or_else(|| self.base.get_page(page_index))
+    }
+
+    /// Returns the largest range within `page_range` that contains
+    /// `page_index` such that all of its pages share the same backing store.
+    /// Pages of overlays are returned as singleton `BackedByPage` regions,
+    /// so that file-backed regions always refer to the base file.
+    pub fn get_memory_region(
+        &self,
+        page_index: PageIndex,
+        mut page_range: Range<PageIndex>,
+    ) -> MemoryRegion {
+        assert!(page_range.contains(&page_index));
+        for overlay in self.overlays.iter().rev() {
+            if let Some(page) = overlay.get_page(page_index) {
+                return MemoryRegion::BackedByPage(page);
+            }
+            let (lower, upper) = overlay.neighbours(page_index);
+            if let Some(lower) = lower {
+                page_range.start = page_range.start.max(PageIndex::new(lower.get() + 1));
+            }
+            if let Some(upper) = upper {
+                page_range.end = page_range.end.min(upper);
+            }
+        }
+        self.base.get_memory_region(page_index, page_range)
+    }
+
+    /// Returns the whole memory region of the base file.
+    pub fn get_base_memory_region(&self) -> MemoryRegion {
+        let start = PageIndex::new(0);
+        let end = PageIndex::new(u64::MAX);
+        self.base.get_memory_region(start, Range { start, end })
+    }
+
+    /// Returns the max number of (possibly) non-zero pages in the storage.
+    pub fn num_logical_pages(&self) -> usize {
+        self.overlays
+            .iter()
+            .map(|overlay| overlay.num_logical_pages())
+            .fold(self.base.num_pages(), usize::max)
+    }
+
+    /// Returns a serialization-friendly representation of the storage.
+    pub fn serialize(&self) -> StorageSerialization {
+        StorageSerialization {
+            base: self.base.serialize(),
+            overlays: self
+                .overlays
+                .iter()
+                .map(|overlay| overlay.serialize())
+                .collect(),
+        }
+    }
+
+    /// Creates the storage from the given serialization-friendly
+    /// representation.
+    pub fn deserialize(serialized: StorageSerialization) -> Result<Self, PersistenceError> {
+        Ok(Self {
+            base: Checkpoint::deserialize(serialized.base)?,
+            overlays: serialized
+                .overlays
+                .into_iter()
+                .map(OverlayFile::deserialize)
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
+/// A suffix of the overlays of a page map that needs to be merged into a
+/// single overlay file to restore the size ratio between overlays.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeCandidate {
+    /// The overlays to merge, ordered from the oldest to the newest one.
+    overlays: Vec<PathBuf>,
+    /// The path of the resulting overlay.
+    dst: PathBuf,
+}
+
+impl MergeCandidate {
+    /// Returns the overlays of the given layout that need to be merged into
+    /// the overlay at `height`, or `None` if the overlays already satisfy the
+    /// size ratio. `height` must not be lower than the height of any existing
+    /// overlay.
+    pub fn new(
+        layout: &dyn StorageLayout,
+        height: Height,
+    ) -> Result<Option<MergeCandidate>, PersistenceError> {
+        let overlays = layout.existing_overlays()?;
+        let sizes = overlays
+            .iter()
+            .map(|path| {
+                path.metadata()
+                    .map(|metadata| metadata.len())
+                    .map_err(|err| PersistenceError::FileSystemError {
+                        path: path.display().to_string(),
+                        context: "Failed to retrieve file metadata".to_string(),
+                        internal_error: err.to_string(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(merge_start(&sizes).map(|start| MergeCandidate {
+            overlays: overlays[start..].to_vec(),
+            dst: layout.overlay(height),
+        }))
+    }
+
+    /// The overlays to merge, ordered from the oldest to the newest one.
+    pub fn overlays(&self) -> &[PathBuf] {
+        &self.overlays
+    }
+
+    /// The path of the resulting overlay.
+    pub fn dst(&self) -> &Path {
+        &self.dst
+    }
+
+    /// Writes the merged overlay and removes the merged ones. The result is
+    /// written to a temporary file first, so that the destination is
+    /// replaced atomically if it is one of the inputs.
+    pub fn apply(&self) -> Result<(), PersistenceError> {
+        let overlays = self
+            .overlays
+            .iter()
+            .map(|path| OverlayFile::load(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Newer overlays are applied last, so their pages win.
+        let mut pages: BTreeMap<PageIndex, &PageBytes> = BTreeMap::new();
+        for overlay in overlays.iter() {
+            pages.extend(overlay.iter());
+        }
+        let pages: Vec<(PageIndex, &PageBytes)> = pages.into_iter().collect();
+
+        let tmp = self.dst.with_extension("tmp");
+        OverlayFile::write(&pages, &tmp)?;
+        std::fs::rename(&tmp, &self.dst).map_err(|err| PersistenceError::FileSystemError {
+            path: tmp.display().to_string(),
+            context: format!("Failed to rename to {}", self.dst.display()),
+            internal_error: err.to_string(),
+        })?;
+        for path in self.overlays.iter().filter(|path| **path != self.dst) {
+            std::fs::remove_file(path).map_err(|err| PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to remove merged overlay".to_string(),
+                internal_error: err.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Given the sizes of the overlays from the oldest to the newest one, returns
+/// the position of the oldest overlay that is smaller than `MERGE_SIZE_RATIO`
+/// times the total size of all newer overlays. Merging all overlays from that
+/// position on restores the size ratio for all remaining overlays.
+fn merge_start(sizes: &[u64]) -> Option<usize> {
+    let mut start = None;
+    let mut newer_total: u64 = 0;
+    for (position, size) in sizes.iter().enumerate().rev() {
+        if *size < MERGE_SIZE_RATIO.saturating_mul(newer_total) {
+            start = Some(position);
+        }
+        newer_total = newer_total.saturating_add(*size);
+    }
+    start
+}
+
+/// Serialization-friendly representation of an `OverlayFile`.
+///
+/// It contains sufficient information to reconstruct the overlay
+/// in another process.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OverlayFileSerialization {
+    pub mapping: MappingSerialization,
+}
+
+/// Serialization-friendly representation of `Storage`.
+///
+/// It contains sufficient information to reconstruct `Storage`
+/// in another process.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageSerialization {
+    pub base: CheckpointSerialization,
+    pub overlays: Vec<OverlayFileSerialization>,
+}
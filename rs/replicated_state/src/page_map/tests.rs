@@ -1,13 +1,15 @@
 use super::{
     checkpoint::{Checkpoint, MappingSerialization},
     page_allocator::PageAllocatorSerialization,
-    Buffer, FileDescriptor, PageAllocator, PageAllocatorRegistry, PageDelta, PageIndex, PageMap,
-    PageMapSerialization,
+    Buffer, FileDescriptor, MergeCandidate, OverlayFileSerialization, PageAllocator,
+    PageAllocatorRegistry, PageDelta, PageIndex, PageMap, PageMapSerialization, PersistenceError,
+    StorageLayout,
 };
 use crate::page_map::{MemoryRegion, TestPageAllocatorFileDescriptorImpl};
 use ic_sys::PAGE_SIZE;
 use ic_types::{Height, MAX_STABLE_MEMORY_IN_BYTES};
 use nix::unistd::dup;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::{fs::OpenOptions, ops::Range};
 
@@ -26,16 +28,25 @@ fn assert_equal_page_maps(page_map1: &PageMap, page_map2: &PageMap) {
 fn duplicate_file_descriptors(
     mut serialized_page_map: PageMapSerialization,
 ) -> PageMapSerialization {
-    serialized_page_map.checkpoint.mapping =
-        serialized_page_map
-            .checkpoint
-            .mapping
-            .map(|mapping| MappingSerialization {
-                file_descriptor: FileDescriptor {
-                    fd: dup(mapping.file_descriptor.fd).unwrap(),
-                },
-                ..mapping
-            });
+    let duplicate_mapping = |mapping: MappingSerialization| MappingSerialization {
+        file_descriptor: FileDescriptor {
+            fd: dup(mapping.file_descriptor.fd).unwrap(),
+        },
+        ..mapping
+    };
+    serialized_page_map.storage.base.mapping = serialized_page_map
+        .storage
+        .base
+        .mapping
+        .map(duplicate_mapping);
+    serialized_page_map.storage.overlays = serialized_page_map
+        .storage
+        .overlays
+        .into_iter()
+        .map(|overlay| OverlayFileSerialization {
+            mapping: duplicate_mapping(overlay.mapping),
+        })
+        .collect();
     serialized_page_map.page_allocator = PageAllocatorSerialization {
         id: serialized_page_map.page_allocator.id,
         fd: FileDescriptor {
@@ -45,6 +56,66 @@ fn duplicate_file_descriptors(
     serialized_page_map
 }
 
+/// A layout that keeps the base file and the overlays in a single directory.
+struct TestStorageLayout {
+    root: PathBuf,
+}
+
+impl StorageLayout for TestStorageLayout {
+    fn base(&self) -> PathBuf {
+        self.root.join("vmemory_0.bin")
+    }
+
+    fn overlay(&self, height: Height) -> PathBuf {
+        self.root.join(format!("{:016}.overlay", height.get()))
+    }
+
+    fn existing_overlays(&self) -> Result<Vec<PathBuf>, PersistenceError> {
+        let mut overlays: Vec<PathBuf> = std::fs::read_dir(&self.root)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "overlay"))
+            .collect();
+        overlays.sort();
+        Ok(overlays)
+    }
+}
+
+/// Writes `pages` as the base file of `layout` and `overlays` as overlays at
+/// heights 1, 2, ... on top of it. Returns a page map with all the pages
+/// applied in the same order.
+fn write_base_and_overlays(
+    layout: &TestStorageLayout,
+    base: &[(PageIndex, &[u8; PAGE_SIZE])],
+    overlays: &[&[(PageIndex, &[u8; PAGE_SIZE])]],
+) -> PageMap {
+    let mut expected = PageMap::new_for_testing();
+    expected.update(base);
+    expected.persist_delta(&layout.base()).unwrap();
+    expected.strip_unflushed_delta();
+    for (i, pages) in overlays.iter().enumerate() {
+        expected.update(pages);
+        expected
+            .persist_unflushed_delta_to_overlay(&layout.overlay(Height::new(i as u64 + 1)))
+            .unwrap();
+        expected.strip_unflushed_delta();
+    }
+    expected
+}
+
+fn open_with_overlays(layout: &TestStorageLayout) -> PageMap {
+    PageMap::open_with_overlays(
+        layout,
+        Height::new(0),
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+    )
+    .unwrap()
+}
+
+fn file_size(path: &Path) -> u64 {
+    path.metadata().unwrap().len()
+}
+
 #[test]
 fn can_debug_display_a_page_map() {
     let page_map = PageMap::new_for_testing();
@@ -159,6 +230,46 @@ fn persisted_map_is_equivalent_to_the_original() {
     assert_eq!(persisted_map, original_map);
 }
 
+#[test]
+fn persist_all_writes_checkpoint_and_delta() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let heap_file = tmp.path().join("heap");
+    let copy_file = tmp.path().join("copy");
+
+    let base_page = [42u8; PAGE_SIZE];
+    let base_pages: Vec<(PageIndex, &[u8; PAGE_SIZE])> =
+        (0..300).map(|i| (PageIndex::new(i), &base_page)).collect();
+    let mut base_map = PageMap::new_for_testing();
+    base_map.update(base_pages.as_slice());
+    base_map.persist_delta(&heap_file).unwrap();
+
+    let mut original_map = PageMap::open(
+        &heap_file,
+        Height::new(0),
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+    )
+    .unwrap();
+    let page_7 = [7u8; PAGE_SIZE];
+    let page_400 = [4u8; PAGE_SIZE];
+    original_map.update(&[
+        (PageIndex::new(7), &page_7),
+        (PageIndex::new(400), &page_400),
+    ]);
+
+    original_map.persist_all(&copy_file).unwrap();
+    let persisted_map = PageMap::open(
+        &copy_file,
+        Height::new(0),
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+    )
+    .unwrap();
+
+    assert_equal_page_maps(&persisted_map, &original_map);
+}
+
 #[test]
 fn can_persist_and_load_an_empty_page_map() {
     let tmp = tempfile::Builder::new()
@@ -492,3 +603,227 @@ fn zeros_region_within_delta() {
         zero_range
     );
 }
+
+#[test]
+fn page_map_with_overlays_is_equivalent_to_the_original() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let base_page = [42u8; PAGE_SIZE];
+    let base_pages: Vec<(PageIndex, &[u8; PAGE_SIZE])> =
+        (0..50).map(|i| (PageIndex::new(i), &base_page)).collect();
+    let expected = write_base_and_overlays(
+        &layout,
+        &base_pages,
+        &[
+            &[
+                (PageIndex::new(1), &[1u8; PAGE_SIZE]),
+                (PageIndex::new(2), &[2u8; PAGE_SIZE]),
+                (PageIndex::new(60), &[60u8; PAGE_SIZE]),
+            ],
+            &[
+                (PageIndex::new(2), &[3u8; PAGE_SIZE]),
+                (PageIndex::new(100), &[100u8; PAGE_SIZE]),
+            ],
+        ],
+    );
+    assert_eq!(layout.existing_overlays().unwrap().len(), 2);
+
+    let persisted_map = open_with_overlays(&layout);
+    assert_eq!(persisted_map.num_host_pages(), 101);
+    assert_eq!(persisted_map.get_page(PageIndex::new(2)), &[3u8; PAGE_SIZE]);
+    assert_eq!(persisted_map, expected);
+}
+
+#[test]
+fn memory_regions_do_not_cover_overlay_pages() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let base_page = [42u8; PAGE_SIZE];
+    let base_pages: Vec<(PageIndex, &[u8; PAGE_SIZE])> =
+        (0..10).map(|i| (PageIndex::new(i), &base_page)).collect();
+    write_base_and_overlays(
+        &layout,
+        &base_pages,
+        &[&[
+            (PageIndex::new(3), &[3u8; PAGE_SIZE]),
+            (PageIndex::new(7), &[7u8; PAGE_SIZE]),
+            (PageIndex::new(20), &[20u8; PAGE_SIZE]),
+        ]],
+    );
+    let page_map = open_with_overlays(&layout);
+
+    assert_eq!(
+        page_map.get_memory_region(PageIndex::new(3)),
+        MemoryRegion::BackedByPage(&[3u8; PAGE_SIZE])
+    );
+    match page_map.get_memory_region(PageIndex::new(5)) {
+        MemoryRegion::BackedByFile(range, _) => assert_eq!(
+            range,
+            Range {
+                start: PageIndex::new(4),
+                end: PageIndex::new(7)
+            }
+        ),
+        region => panic!("Expected a file backed region, got {:?}", region),
+    }
+    assert_eq!(
+        page_map.get_memory_region(PageIndex::new(15)),
+        MemoryRegion::Zeros(Range {
+            start: PageIndex::new(10),
+            end: PageIndex::new(20)
+        })
+    );
+    assert_eq!(
+        page_map.get_memory_region(PageIndex::new(21)),
+        MemoryRegion::Zeros(Range {
+            start: PageIndex::new(21),
+            end: PageIndex::new(u64::MAX)
+        })
+    );
+}
+
+#[test]
+fn serialize_page_map_with_overlays() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+    write_base_and_overlays(
+        &layout,
+        &[(PageIndex::new(0), &[1u8; PAGE_SIZE])],
+        &[&[(PageIndex::new(1), &[2u8; PAGE_SIZE])]],
+    );
+
+    let page_allocator_registry = PageAllocatorRegistry::new();
+    let original_page_map = open_with_overlays(&layout);
+    let serialized_page_map = duplicate_file_descriptors(original_page_map.serialize());
+    let deserialized_page_map =
+        PageMap::deserialize(serialized_page_map, &page_allocator_registry).unwrap();
+    assert_equal_page_maps(&original_page_map, &deserialized_page_map);
+}
+
+#[test]
+fn returns_an_error_if_overlay_is_malformed() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+    write_base_and_overlays(&layout, &[], &[&[(PageIndex::new(1), &[2u8; PAGE_SIZE])]]);
+    let overlay = layout.overlay(Height::new(1));
+    OpenOptions::new()
+        .write(true)
+        .open(&overlay)
+        .unwrap()
+        .set_len(file_size(&overlay) - 1)
+        .unwrap();
+
+    match PageMap::open_with_overlays(
+        &layout,
+        Height::new(0),
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+    ) {
+        Err(PersistenceError::InvalidOverlay { .. }) => {}
+        Err(err) => panic!("Expected an invalid overlay error, got {:?}", err),
+        Ok(_) => panic!("Expected an invalid overlay error, got Ok(_)"),
+    }
+}
+
+#[test]
+fn merge_restores_size_ratio_of_overlays() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+
+    let page = [5u8; PAGE_SIZE];
+    let large: Vec<(PageIndex, &[u8; PAGE_SIZE])> =
+        (0..20).map(|i| (PageIndex::new(i), &page)).collect();
+    let expected = write_base_and_overlays(
+        &layout,
+        &[],
+        &[
+            &large,
+            &[(PageIndex::new(30), &[30u8; PAGE_SIZE])],
+            &[(PageIndex::new(30), &[31u8; PAGE_SIZE])],
+        ],
+    );
+
+    // The large overlay is more than twice as large as the newer ones, so only
+    // the two small overlays need to be merged.
+    let merge = MergeCandidate::new(&layout, Height::new(4))
+        .unwrap()
+        .expect("The two newest overlays must be merged");
+    assert_eq!(
+        merge.overlays(),
+        &[
+            layout.overlay(Height::new(2)),
+            layout.overlay(Height::new(3))
+        ]
+    );
+    assert_eq!(merge.dst(), layout.overlay(Height::new(4)).as_path());
+    merge.apply().unwrap();
+
+    assert_eq!(
+        layout.existing_overlays().unwrap(),
+        vec![
+            layout.overlay(Height::new(1)),
+            layout.overlay(Height::new(4))
+        ]
+    );
+    assert_eq!(open_with_overlays(&layout), expected);
+    assert_eq!(MergeCandidate::new(&layout, Height::new(5)).unwrap(), None);
+}
+
+#[test]
+fn merge_can_replace_the_newest_overlay() {
+    let tmp = tempfile::Builder::new()
+        .prefix("checkpoints")
+        .tempdir()
+        .unwrap();
+    let layout = TestStorageLayout {
+        root: tmp.path().to_path_buf(),
+    };
+    let expected = write_base_and_overlays(
+        &layout,
+        &[(PageIndex::new(0), &[1u8; PAGE_SIZE])],
+        &[
+            &[(PageIndex::new(1), &[2u8; PAGE_SIZE])],
+            &[
+                (PageIndex::new(1), &[3u8; PAGE_SIZE]),
+                (PageIndex::new(2), &[4u8; PAGE_SIZE]),
+            ],
+        ],
+    );
+
+    let merge = MergeCandidate::new(&layout, Height::new(2))
+        .unwrap()
+        .expect("The overlays must be merged");
+    merge.apply().unwrap();
+
+    assert_eq!(
+        layout.existing_overlays().unwrap(),
+        vec![layout.overlay(Height::new(2))]
+    );
+    assert_eq!(open_with_overlays(&layout), expected);
+}
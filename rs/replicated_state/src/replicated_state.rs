@@ -3,6 +3,7 @@ use super::{
     metadata_state::{IngressHistoryState, Stream, Streams, SystemMetadata},
 };
 use crate::{
+    canister_snapshots::CanisterSnapshots,
     canister_state::queues::CanisterQueuesLoopDetector,
     canister_state::system_state::{push_input, CanisterOutputQueuesIterator},
     metadata_state::StreamMap,
@@ -24,7 +25,7 @@ use ic_types::{
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::sync::Arc;
 
 /// Maximum message length of a synthetic reject response produced by message
@@ -373,6 +374,10 @@ pub struct ReplicatedState {
     /// The queue is, therefore, emptied at the end of every round.
     // TODO(EXE-109): Move this queue into `subnet_queues`
     pub consensus_queue: Vec<Response>,
+
+    /// Snapshots of canisters taken via `take_canister_snapshot`, indexed by
+    /// snapshot id.
+    pub canister_snapshots: CanisterSnapshots,
 }
 
 impl ReplicatedState {
@@ -383,6 +388,7 @@ impl ReplicatedState {
             metadata: SystemMetadata::new(own_subnet_id, own_subnet_type),
             subnet_queues: CanisterQueues::default(),
             consensus_queue: Vec::new(),
+            canister_snapshots: CanisterSnapshots::default(),
         }
     }
 
@@ -391,14 +397,17 @@ impl ReplicatedState {
         canister_states: BTreeMap<CanisterId, CanisterState>,
         metadata: SystemMetadata,
         subnet_queues: CanisterQueues,
+        canister_snapshots: CanisterSnapshots,
     ) -> Self {
         let mut res = Self {
             canister_states,
             metadata,
             subnet_queues,
             consensus_queue: Vec::new(),
+            canister_snapshots,
         };
         res.update_stream_responses_size_bytes();
+        res.update_all_snapshots_memory_usage();
         res
     }
 
@@ -551,6 +560,8 @@ impl ReplicatedState {
             mut message_memory_taken,
             wasm_custom_sections_memory_taken,
             canister_history_memory_taken,
+            wasm_chunk_store_memory_taken,
+            snapshots_memory_taken,
         ) = self
             .canisters_iter()
             .map(|canister| {
@@ -562,6 +573,8 @@ impl ReplicatedState {
                     canister.system_state.message_memory_usage(),
                     canister.wasm_custom_sections_memory_usage(),
                     canister.canister_history_memory_usage(),
+                    canister.wasm_chunk_store_memory_usage(),
+                    canister.snapshots_memory_usage(),
                 )
             })
             .reduce(|accum, val| {
@@ -570,6 +583,8 @@ impl ReplicatedState {
                     accum.1 + val.1,
                     accum.2 + val.2,
                     accum.3 + val.3,
+                    accum.4 + val.4,
+                    accum.5 + val.5,
                 )
             })
             .unwrap_or_default();
@@ -577,7 +592,10 @@ impl ReplicatedState {
         message_memory_taken += (self.subnet_queues.memory_usage() as u64).into();
 
         MemoryTaken {
-            execution: raw_memory_taken + canister_history_memory_taken,
+            execution: raw_memory_taken
+                + canister_history_memory_taken
+                + wasm_chunk_store_memory_taken
+                + snapshots_memory_taken,
             messages: message_memory_taken,
             wasm_custom_sections: wasm_custom_sections_memory_taken,
             canister_history: canister_history_memory_taken,
@@ -754,6 +772,24 @@ impl ReplicatedState {
         }
     }
 
+    /// Recomputes the (transient) snapshots memory usage of all canisters.
+    fn update_all_snapshots_memory_usage(&mut self) {
+        for (canister_id, canister_state) in self.canister_states.iter_mut() {
+            canister_state
+                .set_snapshots_memory_usage(self.canister_snapshots.total_size(*canister_id));
+        }
+    }
+
+    /// Recomputes the (transient) snapshots memory usage of the given
+    /// canister. Must be called whenever snapshots of the canister are added
+    /// or removed.
+    pub fn update_snapshots_memory_usage(&mut self, canister_id: &CanisterId) {
+        let size = self.canister_snapshots.total_size(*canister_id);
+        if let Some(canister_state) = self.canister_states.get_mut(canister_id) {
+            canister_state.set_snapshots_memory_usage(size);
+        }
+    }
+
     /// Returns the number of canisters in this `ReplicatedState`.
     pub fn num_canisters(&self) -> usize {
         self.canister_states.len()
@@ -811,6 +847,101 @@ impl ReplicatedState {
         timed_out_requests_count
     }
 
+    /// Expires all best-effort callbacks with deadlines at or before
+    /// `current_time` across all canisters, enqueuing a `SYS_UNKNOWN` reject
+    /// response for each of them (unless a response is already enqueued).
+    /// Returns the number of expired callbacks.
+    ///
+    /// Expiry is deferred for canisters with a paused or aborted execution (which
+    /// may be the execution of the response for an expiring callback) until said
+    /// execution has completed.
+    ///
+    /// See `SystemState::expire_callbacks` for further details.
+    pub fn expire_callbacks(&mut self, current_time: Time) -> u64 {
+        // Same as for `time_out_requests()`, only apply the costly remove-call-replace
+        // to the (usually much fewer) canisters with expired callbacks.
+        let canister_ids_with_expired_callbacks = self
+            .canister_states
+            .iter()
+            .filter(|(_, canister_state)| {
+                canister_state
+                    .system_state
+                    .has_expired_callbacks(current_time)
+                    && !canister_state.has_paused_execution()
+                    && !canister_state.has_aborted_execution()
+            })
+            .map(|(canister_id, _)| *canister_id)
+            .collect::<Vec<_>>();
+
+        let mut expired_callbacks_count = 0;
+        for canister_id in canister_ids_with_expired_callbacks {
+            let mut canister = self.canister_states.remove(&canister_id).unwrap();
+            expired_callbacks_count += canister.system_state.expire_callbacks(
+                current_time,
+                &canister_id,
+                &self.canister_states,
+            );
+            self.canister_states.insert(canister_id, canister);
+        }
+
+        expired_callbacks_count
+    }
+
+    /// Sheds best-effort requests from canister (but not subnet) queues until the
+    /// total byte size of best-effort requests is at most `capacity`. Requests
+    /// are shed one at a time, always the largest best-effort request of the
+    /// canister holding the most best-effort request bytes. Returns the number of
+    /// shed requests.
+    ///
+    /// See `CanisterQueues::shed_largest_best_effort_request` for further details.
+    pub fn shed_best_effort_messages(&mut self, capacity: usize) -> u64 {
+        let mut canisters_by_size: BTreeSet<(usize, CanisterId)> = self
+            .canister_states
+            .iter()
+            .map(|(canister_id, canister)| {
+                (
+                    canister
+                        .system_state
+                        .queues()
+                        .best_effort_requests_size_bytes(),
+                    *canister_id,
+                )
+            })
+            .filter(|(size_bytes, _)| *size_bytes > 0)
+            .collect();
+        let mut total_size_bytes: usize = canisters_by_size
+            .iter()
+            .map(|(size_bytes, _)| size_bytes)
+            .sum();
+
+        let mut shed_requests_count = 0;
+        while total_size_bytes > capacity {
+            let (size_bytes, canister_id) = match canisters_by_size.pop_last() {
+                Some(largest) => largest,
+                None => break,
+            };
+
+            let mut canister = self.canister_states.remove(&canister_id).unwrap();
+            canister
+                .system_state
+                .shed_largest_best_effort_request(&canister_id, &self.canister_states)
+                .expect("Canister with best-effort requests has nothing to shed");
+            let new_size_bytes = canister
+                .system_state
+                .queues()
+                .best_effort_requests_size_bytes();
+            self.canister_states.insert(canister_id, canister);
+
+            total_size_bytes = total_size_bytes - size_bytes + new_size_bytes;
+            if new_size_bytes > 0 {
+                canisters_by_size.insert((new_size_bytes, canister_id));
+            }
+            shed_requests_count += 1;
+        }
+
+        shed_requests_count
+    }
+
     /// Splits the replicated state as part of subnet splitting phase 1, retaining
     /// only the canisters of `new_subnet_id` (as determined by the provided routing
     /// table).
@@ -843,6 +974,7 @@ impl ReplicatedState {
             metadata,
             mut subnet_queues,
             consensus_queue,
+            mut canister_snapshots,
         } = self;
 
         // Consensus queue is always empty at the end of the round.
@@ -854,6 +986,10 @@ impl ReplicatedState {
         canister_states
             .retain(|canister_id, _| routing_table.route(canister_id.get()) == Some(new_subnet_id));
 
+        // Snapshots stay with the canister they were taken of.
+        canister_snapshots
+            .retain_canisters(|canister_id| canister_states.contains_key(canister_id));
+
         // All subnet messages (ingress and canister) only remain on subnet A' because:
         //
         //  * Message Routing would drop a response from subnet B to a request it had
@@ -877,6 +1013,7 @@ impl ReplicatedState {
             metadata,
             subnet_queues,
             consensus_queue,
+            canister_snapshots,
         }
     }
 
@@ -896,6 +1033,7 @@ impl ReplicatedState {
             mut metadata,
             subnet_queues,
             consensus_queue,
+            canister_snapshots,
         } = self;
 
         metadata
@@ -921,6 +1059,7 @@ impl ReplicatedState {
             metadata,
             subnet_queues,
             consensus_queue,
+            canister_snapshots,
         };
         res.update_stream_responses_size_bytes();
         res
@@ -1,6 +1,8 @@
 use ic_base_types::CanisterId;
+use ic_error_types::RejectCode;
+use ic_interfaces::messages::CanisterMessage;
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::{CanisterState, InputQueueType, StateError};
+use ic_replicated_state::{CallOrigin, CanisterState, InputQueueType, StateError};
 use ic_test_utilities::{
     mock_time,
     state::{get_running_canister, get_stopped_canister, get_stopping_canister, register_callback},
@@ -8,10 +10,13 @@ use ic_test_utilities::{
     types::messages::{RequestBuilder, ResponseBuilder},
 };
 use ic_types::{
-    messages::{CallbackId, Request, RequestOrResponse},
+    messages::{CallbackId, Payload, RejectContext, Request, RequestOrResponse, Response},
+    methods::{Callback, WasmClosure},
+    time::CoarseTime,
     xnet::QueueId,
+    Cycles, Time,
 };
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 const CANISTER_ID: CanisterId = CanisterId::from_u64(0);
 const OTHER_CANISTER_ID: CanisterId = CanisterId::from_u64(1);
@@ -82,6 +87,39 @@ impl CanisterFixture {
         );
     }
 
+    fn register_best_effort_callback(&mut self, deadline: CoarseTime) -> CallbackId {
+        let call_context_manager = self
+            .canister_state
+            .system_state
+            .call_context_manager_mut()
+            .unwrap();
+        let call_context_id = call_context_manager.new_call_context(
+            CallOrigin::SystemTask,
+            Cycles::zero(),
+            mock_time(),
+        );
+        call_context_manager.register_callback(Callback::new(
+            call_context_id,
+            Some(CANISTER_ID),
+            Some(OTHER_CANISTER_ID),
+            Cycles::zero(),
+            None,
+            None,
+            WasmClosure::new(0, 2),
+            WasmClosure::new(0, 2),
+            None,
+            deadline,
+        ))
+    }
+
+    fn expire_callbacks(&mut self, current_time: Time) -> u64 {
+        self.canister_state.system_state.expire_callbacks(
+            current_time,
+            &CANISTER_ID,
+            &BTreeMap::new(),
+        )
+    }
+
     fn push_input(
         &mut self,
         msg: RequestOrResponse,
@@ -254,3 +292,126 @@ fn validate_responses_against_callback_details() {
         .push_input(input_response_from(canister_b_id, callback_id_1))
         .unwrap();
 }
+
+fn best_effort_input_response(callback_id: CallbackId, refund: Cycles) -> RequestOrResponse {
+    ResponseBuilder::new()
+        .originator(CANISTER_ID)
+        .respondent(OTHER_CANISTER_ID)
+        .originator_reply_callback(callback_id)
+        .refund(refund)
+        .deadline(DEADLINE)
+        .build()
+        .into()
+}
+
+const DEADLINE: CoarseTime = CoarseTime::from_secs_since_unix_epoch(10);
+
+#[test]
+fn expired_callback_gets_sys_unknown_reject() {
+    let mut fixture = CanisterFixture::running();
+    let callback_id = fixture.register_best_effort_callback(DEADLINE);
+    fixture.with_input_reservation();
+
+    // Nothing expires before the deadline.
+    let before_deadline = Time::from(DEADLINE).saturating_sub_duration(Duration::from_secs(1));
+    assert_eq!(0, fixture.expire_callbacks(before_deadline));
+    assert_eq!(
+        0,
+        fixture
+            .canister_state
+            .system_state
+            .queues()
+            .input_queues_message_count()
+    );
+
+    assert_eq!(1, fixture.expire_callbacks(Time::from(DEADLINE)));
+    assert_eq!(
+        Some(CanisterMessage::Response(Arc::new(Response {
+            originator: CANISTER_ID,
+            respondent: OTHER_CANISTER_ID,
+            originator_reply_callback: callback_id,
+            refund: Cycles::zero(),
+            response_payload: Payload::Reject(RejectContext::new(
+                RejectCode::SysUnknown,
+                "Call deadline has expired.".to_string()
+            )),
+            deadline: DEADLINE,
+        }))),
+        fixture.canister_state.pop_input()
+    );
+
+    // The callback only expires once.
+    assert_eq!(0, fixture.expire_callbacks(Time::from(DEADLINE)));
+}
+
+#[test]
+fn expired_callback_with_enqueued_response_gets_no_reject() {
+    let mut fixture = CanisterFixture::running();
+    let callback_id = fixture.register_best_effort_callback(DEADLINE);
+    fixture.with_input_reservation();
+    fixture
+        .push_input(best_effort_input_response(callback_id, Cycles::zero()))
+        .unwrap();
+
+    assert_eq!(1, fixture.expire_callbacks(Time::from(DEADLINE)));
+
+    // Only the actual response is enqueued.
+    let queues = fixture.canister_state.system_state.queues();
+    assert_eq!(1, queues.input_queues_message_count());
+    assert_eq!(0, queues.input_queues_reservation_count());
+}
+
+#[test]
+fn late_best_effort_response_is_dropped_and_refunded() {
+    let mut fixture = CanisterFixture::running();
+    let callback_id = fixture.register_best_effort_callback(DEADLINE);
+    fixture.with_input_reservation();
+    assert_eq!(1, fixture.expire_callbacks(Time::from(DEADLINE)));
+    let balance_before = fixture.canister_state.system_state.balance();
+
+    // The response arrives after the callback has expired.
+    fixture
+        .push_input(best_effort_input_response(callback_id, Cycles::new(13)))
+        .unwrap();
+
+    // It is dropped (only the `SYS_UNKNOWN` reject is enqueued), but its refund
+    // is credited.
+    assert_eq!(
+        1,
+        fixture
+            .canister_state
+            .system_state
+            .queues()
+            .input_queues_message_count()
+    );
+    assert_eq!(
+        balance_before + Cycles::new(13),
+        fixture.canister_state.system_state.balance()
+    );
+}
+
+#[test]
+fn best_effort_response_for_unknown_callback_is_dropped_and_refunded() {
+    let mut fixture = CanisterFixture::running();
+    let balance_before = fixture.canister_state.system_state.balance();
+
+    fixture
+        .push_input(best_effort_input_response(
+            CallbackId::from(13),
+            Cycles::new(17),
+        ))
+        .unwrap();
+
+    assert_eq!(
+        0,
+        fixture
+            .canister_state
+            .system_state
+            .queues()
+            .input_queues_message_count()
+    );
+    assert_eq!(
+        balance_before + Cycles::new(17),
+        fixture.canister_state.system_state.balance()
+    );
+}
@@ -26,6 +26,7 @@ use ic_test_utilities::types::messages::{RequestBuilder, ResponseBuilder};
 use ic_types::ingress::{IngressState, IngressStatus};
 use ic_types::{
     messages::{Payload, Request, RequestOrResponse, Response, MAX_RESPONSE_COUNT_BYTES},
+    time::CoarseTime,
     CountBytes, Cycles, MemoryAllocation, Time,
 };
 use maplit::btreemap;
@@ -91,7 +92,7 @@ impl ReplicatedStateFixture {
         let mut state = ReplicatedState::new(SUBNET_ID, SubnetType::Application);
         for canister_id in canister_ids {
             let scheduler_state = SchedulerState::default();
-            let system_state = SystemState::new_running(
+            let system_state = SystemState::new_running_for_testing(
                 *canister_id,
                 user_test_id(24).get(),
                 Cycles::new(1 << 36),
@@ -608,6 +609,97 @@ fn time_out_requests_updates_subnet_input_schedules_correctly() {
     );
 }
 
+#[test]
+fn shed_best_effort_messages_respects_capacity() {
+    let mut fixture = ReplicatedStateFixture::with_canisters(&[CANISTER_ID, OTHER_CANISTER_ID]);
+    let remote_canister_id = CanisterId::from_u64(123);
+    let best_effort_request = |sender, payload_size_bytes| {
+        RequestBuilder::default()
+            .sender(sender)
+            .receiver(remote_canister_id)
+            .method_payload(vec![13; payload_size_bytes])
+            .deadline(CoarseTime::from_secs_since_unix_epoch(1000))
+            .build()
+    };
+    let best_effort_requests_size_bytes = |fixture: &ReplicatedStateFixture| {
+        [CANISTER_ID, OTHER_CANISTER_ID]
+            .iter()
+            .map(|canister_id| {
+                fixture
+                    .state
+                    .canister_state(canister_id)
+                    .unwrap()
+                    .system_state
+                    .queues()
+                    .best_effort_requests_size_bytes()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // `CANISTER_ID` enqueues a large and a small request, `OTHER_CANISTER_ID` a
+    // medium sized one.
+    let large_request = best_effort_request(CANISTER_ID, 1000);
+    let small_request = best_effort_request(CANISTER_ID, 100);
+    let medium_request = best_effort_request(OTHER_CANISTER_ID, 500);
+    for request in [&large_request, &small_request, &medium_request] {
+        fixture
+            .state
+            .canister_state_mut(&request.sender)
+            .unwrap()
+            .push_output_request(Arc::new(request.clone()), mock_time())
+            .unwrap();
+    }
+    let total_size_bytes =
+        large_request.count_bytes() + small_request.count_bytes() + medium_request.count_bytes();
+
+    // Nothing is shed while at capacity.
+    assert_eq!(0, fixture.state.shed_best_effort_messages(total_size_bytes));
+
+    // Just above capacity, the largest request of the canister with the most
+    // best-effort bytes is shed.
+    assert_eq!(
+        1,
+        fixture
+            .state
+            .shed_best_effort_messages(total_size_bytes - 1)
+    );
+    assert_eq!(
+        vec![small_request.count_bytes(), medium_request.count_bytes()],
+        best_effort_requests_size_bytes(&fixture)
+    );
+
+    // Shedding down to the size of the small request sheds the medium request,
+    // as `OTHER_CANISTER_ID` now holds the most best-effort bytes.
+    assert_eq!(
+        1,
+        fixture
+            .state
+            .shed_best_effort_messages(small_request.count_bytes())
+    );
+    assert_eq!(
+        vec![small_request.count_bytes(), 0],
+        best_effort_requests_size_bytes(&fixture)
+    );
+
+    // The shed requests were rejected.
+    for canister_id in [CANISTER_ID, OTHER_CANISTER_ID] {
+        assert_eq!(
+            1,
+            fixture
+                .state
+                .canister_state(&canister_id)
+                .unwrap()
+                .system_state
+                .queues()
+                .input_queues_response_count()
+        );
+    }
+
+    // Zero capacity sheds everything.
+    assert_eq!(1, fixture.state.shed_best_effort_messages(0));
+    assert_eq!(vec![0, 0], best_effort_requests_size_bytes(&fixture));
+}
+
 #[test]
 fn split() {
     // We will be splitting subnet A into A' and B. C is a third-party subnet.
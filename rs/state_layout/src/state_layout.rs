@@ -2,6 +2,7 @@ use crate::error::LayoutError;
 use crate::utils::do_copy;
 
 use ic_base_types::{NumBytes, NumSeconds};
+use ic_ic00_types::{LogVisibility, SnapshotId};
 use ic_logger::{error, info, ReplicaLogger};
 use ic_metrics::{buckets::decimal_buckets, MetricsRegistry};
 use ic_protobuf::{
@@ -14,14 +15,19 @@ use ic_protobuf::{
 use ic_replicated_state::{
     canister_state::{
         execution_state::{NextScheduledMethod, WasmMetadata},
-        system_state::{CanisterHistory, CyclesUseCase},
+        system_state::{
+            CanisterHistory, CyclesUseCase, OnLowWasmMemoryHookStatus, TotalQueryStats,
+            WasmChunkStoreMetadata,
+        },
     },
+    page_map::{PersistenceError, StorageLayout},
     CallContextManager, CanisterStatus, ExecutionTask, ExportedFunctions, Global, NumWasmPages,
 };
 use ic_sys::mmap::ScopedMmap;
 use ic_types::{
-    nominal_cycles::NominalCycles, AccumulatedPriority, CanisterId, ComputeAllocation, Cycles,
-    ExecutionRound, Height, MemoryAllocation, NumInstructions, PrincipalId,
+    canister_log::CanisterLog, nominal_cycles::NominalCycles, AccumulatedPriority, CanisterId,
+    ComputeAllocation, Cycles, ExecutionRound, Height, MemoryAllocation, NumInstructions,
+    PrincipalId, Time,
 };
 use ic_utils::fs::sync_path;
 use ic_utils::thread::parallel_map;
@@ -44,10 +50,12 @@ mod tests;
 // State layout directory and file names.
 pub const CHECKPOINTS_DIR: &str = "checkpoints";
 pub const CANISTER_STATES_DIR: &str = "canister_states";
+pub const SNAPSHOTS_DIR: &str = "snapshots";
 pub const QUEUES_FILE: &str = "queues.pbuf";
 pub const CANISTER_FILE: &str = "canister.pbuf";
 pub const INGRESS_HISTORY_FILE: &str = "ingress_history.pbuf";
 pub const SPLIT_MARKER_FILE: &str = "split_from.pbuf";
+pub const SNAPSHOT_FILE: &str = "snapshot.pbuf";
 pub const SUBNET_QUEUES_FILE: &str = "subnet_queues.pbuf";
 pub const SYSTEM_METADATA_FILE: &str = "system_metadata.pbuf";
 
@@ -139,6 +147,7 @@ pub struct CanisterStateBits {
     pub cycles_balance: Cycles,
     pub cycles_debit: Cycles,
     pub reserved_balance: Cycles,
+    pub reserved_balance_limit: Option<Cycles>,
     pub status: CanisterStatus,
     pub scheduled_as_first: u64,
     pub skipped_round_due_to_no_messages: u64,
@@ -155,6 +164,28 @@ pub struct CanisterStateBits {
     pub canister_version: u64,
     pub consumed_cycles_since_replica_started_by_use_cases: BTreeMap<CyclesUseCase, NominalCycles>,
     pub canister_history: CanisterHistory,
+    pub next_snapshot_id: u64,
+    pub log_visibility: LogVisibility,
+    pub canister_log: CanisterLog,
+    pub wasm_chunk_store_metadata: WasmChunkStoreMetadata,
+    pub wasm_memory_limit: Option<NumBytes>,
+    pub wasm_memory_threshold: NumBytes,
+    pub on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus,
+    pub total_query_stats: TotalQueryStats,
+}
+
+/// This struct contains bits of a `CanisterSnapshot` that are not stored in
+/// the snapshot's Wasm and memory files.
+#[derive(Debug)]
+pub struct CanisterSnapshotBits {
+    pub snapshot_id: SnapshotId,
+    pub taken_at_timestamp: Time,
+    pub canister_version: u64,
+    pub certified_data: Vec<u8>,
+    pub exported_globals: Vec<Global>,
+    pub wasm_memory_size: NumWasmPages,
+    pub stable_memory_size: NumWasmPages,
+    pub binary_hash: WasmHash,
 }
 
 #[derive(Clone)]
@@ -210,7 +241,8 @@ struct CheckpointRefData {
 /// │   │       ├── queues.pbuf
 /// │   │       ├── software.wasm
 /// │   │       ├── stable_memory.bin
-/// │   │       └── vmemory_0.bin
+/// │   │       ├── vmemory_0.bin
+/// │   │       └── wasm_chunk_store.bin
 /// │   ├── ingress_history.pbuf
 /// │   ├── split_from.pbuf
 /// │   ├── subnet_queues.pbuf
@@ -224,7 +256,8 @@ struct CheckpointRefData {
 /// │      │       ├── queues.pbuf
 /// │      │       ├── software.wasm
 /// │      │       ├── stable_memory.bin
-/// │      │       └── vmemory_0.bin
+/// │      │       ├── vmemory_0.bin
+/// │      │       └── wasm_chunk_store.bin
 /// │      ├── ingress_history.pbuf
 /// │      ├── split_from.pbuf
 /// │      ├── subnet_queues.pbuf
@@ -375,6 +408,45 @@ impl TipHandler {
         }
         Ok(())
     }
+
+    /// Deletes canister snapshots from tip if they are not in ids.
+    pub fn filter_tip_snapshots(
+        &mut self,
+        height: Height,
+        ids: &BTreeSet<SnapshotId>,
+    ) -> Result<(), LayoutError> {
+        let tip = self.tip(height)?;
+        let snapshots_on_disk = tip.snapshot_ids()?;
+        for id in snapshots_on_disk {
+            if !ids.contains(&id) {
+                let snapshot_path = tip.snapshot(&id)?.raw_path();
+                std::fs::remove_dir_all(&snapshot_path).map_err(|err| LayoutError::IoError {
+                    path: snapshot_path.clone(),
+                    message: "Cannot remove snapshot.".to_string(),
+                    io_err: err,
+                })?;
+                // Also remove the directory of the canister once its last
+                // snapshot is gone.
+                if let Some(canister_path) = snapshot_path.parent() {
+                    if dir_file_names(canister_path)
+                        .map_err(|err| LayoutError::IoError {
+                            path: canister_path.to_path_buf(),
+                            message: "Cannot list snapshots of canister.".to_string(),
+                            io_err: err,
+                        })?
+                        .is_empty()
+                    {
+                        std::fs::remove_dir(canister_path).map_err(|err| LayoutError::IoError {
+                            path: canister_path.to_path_buf(),
+                            message: "Cannot remove snapshots of canister.".to_string(),
+                            io_err: err,
+                        })?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl StateLayout {
@@ -1170,13 +1242,27 @@ fn parse_canister_id(hex: &str) -> Result<CanisterId, String> {
     .map_err(|err| format!("failed to create canister ID: {}", err))
 }
 
+/// Helper for parsing hex representations of local snapshot IDs, used for the
+/// directory names under `snapshots/<canister_id>`.
+fn parse_snapshot_local_id(hex: &str) -> Result<u64, String> {
+    u64::from_str_radix(hex, 16).map_err(|err| {
+        format!(
+            "failed to convert directory name {} into a snapshot ID: {}",
+            hex, err
+        )
+    })
+}
+
 /// Parses the canister ID from a relative path, if it is the path of a canister
-/// state file (e.g. `canister_states/00000000000000010101/queues.pbuf`).
-/// Returns `None` if the path is not under `canister_states`; or if parsing
-/// fails.
+/// state file (e.g. `canister_states/00000000000000010101/queues.pbuf`) or of
+/// a canister snapshot file (e.g.
+/// `snapshots/00000000000000010101/0000000000000000/snapshot.pbuf`).
+/// Returns `None` if the path is not under `canister_states` or `snapshots`;
+/// or if parsing fails.
 pub fn canister_id_from_path(path: &Path) -> Option<CanisterId> {
     let mut path = path.iter();
-    if path.next() == Some(OsStr::new(CANISTER_STATES_DIR)) {
+    let dir = path.next();
+    if dir == Some(OsStr::new(CANISTER_STATES_DIR)) || dir == Some(OsStr::new(SNAPSHOTS_DIR)) {
         if let Some(hex) = path.next() {
             return parse_canister_id(hex.to_str()?).ok();
         }
@@ -1309,6 +1395,34 @@ impl<Permissions: AccessPolicy> CheckpointLayout<Permissions> {
         )
     }
 
+    /// Returns the IDs of all canister snapshots in the checkpoint. Checkpoints
+    /// written before snapshots existed have no snapshots directory.
+    pub fn snapshot_ids(&self) -> Result<Vec<SnapshotId>, LayoutError> {
+        let snapshots_dir = self.root.join(SNAPSHOTS_DIR);
+        let mut snapshot_ids = Vec::new();
+        for canister_id in collect_subdirs(snapshots_dir.as_path(), parse_canister_id)? {
+            let canister_dir = snapshots_dir.join(hex::encode(canister_id.get_ref().as_slice()));
+            snapshot_ids.extend(collect_subdirs(canister_dir.as_path(), |name| {
+                parse_snapshot_local_id(name).map(|local_id| SnapshotId::new(canister_id, local_id))
+            })?);
+        }
+        Ok(snapshot_ids)
+    }
+
+    pub fn snapshot(
+        &self,
+        snapshot_id: &SnapshotId,
+    ) -> Result<SnapshotLayout<Permissions>, LayoutError> {
+        SnapshotLayout::new(
+            self.root
+                .join(SNAPSHOTS_DIR)
+                .join(hex::encode(
+                    snapshot_id.get_canister_id().get_ref().as_slice(),
+                ))
+                .join(format!("{:016x}", snapshot_id.get_local_id())),
+        )
+    }
+
     pub fn height(&self) -> Height {
         self.height
     }
@@ -1357,6 +1471,156 @@ impl<Permissions: AccessPolicy> CanisterLayout<Permissions> {
     pub fn stable_memory_blob(&self) -> PathBuf {
         self.canister_root.join("stable_memory.bin")
     }
+
+    /// The base and overlay files of the Wasm memory.
+    pub fn wasm_memory(&self) -> PageMapLayout<Permissions> {
+        PageMapLayout::new(self.canister_root.clone(), "vmemory_0")
+    }
+
+    /// The base and overlay files of the stable memory.
+    pub fn stable_memory(&self) -> PageMapLayout<Permissions> {
+        PageMapLayout::new(self.canister_root.clone(), "stable_memory")
+    }
+
+    /// The base and overlay files of the Wasm chunk store.
+    pub fn wasm_chunk_store(&self) -> PageMapLayout<Permissions> {
+        PageMapLayout::new(self.canister_root.clone(), "wasm_chunk_store")
+    }
+}
+
+pub struct SnapshotLayout<Permissions: AccessPolicy> {
+    snapshot_root: PathBuf,
+    permissions_tag: PhantomData<Permissions>,
+}
+
+impl<Permissions: AccessPolicy> SnapshotLayout<Permissions> {
+    pub fn new(snapshot_root: PathBuf) -> Result<Self, LayoutError> {
+        Permissions::check_dir(&snapshot_root)?;
+        Ok(Self {
+            snapshot_root,
+            permissions_tag: PhantomData,
+        })
+    }
+
+    pub fn raw_path(&self) -> PathBuf {
+        self.snapshot_root.clone()
+    }
+
+    pub fn snapshot(
+        &self,
+    ) -> ProtoFileWith<pb_canister_state_bits::CanisterSnapshotBits, Permissions> {
+        self.snapshot_root.join(SNAPSHOT_FILE).into()
+    }
+
+    pub fn wasm(&self) -> WasmFile<Permissions> {
+        self.snapshot_root.join("software.wasm").into()
+    }
+
+    pub fn vmemory_0(&self) -> PathBuf {
+        self.snapshot_root.join("vmemory_0.bin")
+    }
+
+    pub fn stable_memory_blob(&self) -> PathBuf {
+        self.snapshot_root.join("stable_memory.bin")
+    }
+
+    /// The base and overlay files of the Wasm memory.
+    pub fn wasm_memory(&self) -> PageMapLayout<Permissions> {
+        PageMapLayout::new(self.snapshot_root.clone(), "vmemory_0")
+    }
+
+    /// The base and overlay files of the stable memory.
+    pub fn stable_memory(&self) -> PageMapLayout<Permissions> {
+        PageMapLayout::new(self.snapshot_root.clone(), "stable_memory")
+    }
+}
+
+/// The files that make up the persisted state of a `PageMap`: the base file
+/// `<name>.bin` and the overlay files `<name>_<height>.overlay`, where
+/// `<height>` is the zero-padded height at which the overlay was written.
+pub struct PageMapLayout<Permissions: AccessPolicy> {
+    root: PathBuf,
+    name: &'static str,
+    permissions_tag: PhantomData<Permissions>,
+}
+
+impl<Permissions: AccessPolicy> PageMapLayout<Permissions> {
+    fn new(root: PathBuf, name: &'static str) -> Self {
+        Self {
+            root,
+            name,
+            permissions_tag: PhantomData,
+        }
+    }
+
+    pub fn base(&self) -> PathBuf {
+        self.root.join(format!("{}.bin", self.name))
+    }
+
+    pub fn overlay(&self, height: Height) -> PathBuf {
+        self.root
+            .join(format!("{}_{:016}.overlay", self.name, height.get()))
+    }
+
+    /// Returns the height of the given overlay file, or `None` if the path
+    /// is not an overlay file of this page map.
+    pub fn overlay_height(&self, path: &Path) -> Option<Height> {
+        if path.parent() != Some(self.root.as_path()) {
+            return None;
+        }
+        path.file_name()?
+            .to_str()?
+            .strip_prefix(self.name)?
+            .strip_prefix('_')?
+            .strip_suffix(".overlay")?
+            .parse::<u64>()
+            .ok()
+            .map(Height::new)
+    }
+
+    /// Returns the existing overlay files, ordered by height.
+    pub fn existing_overlays(&self) -> Result<Vec<PathBuf>, LayoutError> {
+        let names = dir_file_names(&self.root).map_err(|err| LayoutError::IoError {
+            path: self.root.clone(),
+            message: "Failed to list overlay files".to_string(),
+            io_err: err,
+        })?;
+        let mut overlays: Vec<(Height, PathBuf)> = names
+            .into_iter()
+            .map(|name| self.root.join(name))
+            .filter_map(|path| Some((self.overlay_height(&path)?, path)))
+            .collect();
+        overlays.sort();
+        Ok(overlays.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+impl<Permissions: WritePolicy> PageMapLayout<Permissions> {
+    /// Removes all overlay files, leaving only the base file.
+    pub fn delete_overlays(&self) -> Result<(), LayoutError> {
+        for overlay in self.existing_overlays()? {
+            remove_existing_file(&overlay)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Permissions: AccessPolicy> StorageLayout for PageMapLayout<Permissions> {
+    fn base(&self) -> PathBuf {
+        PageMapLayout::base(self)
+    }
+
+    fn overlay(&self, height: Height) -> PathBuf {
+        PageMapLayout::overlay(self, height)
+    }
+
+    fn existing_overlays(&self) -> Result<Vec<PathBuf>, PersistenceError> {
+        PageMapLayout::existing_overlays(self).map_err(|err| PersistenceError::FileSystemError {
+            path: self.root.display().to_string(),
+            context: "Failed to list overlay files".to_string(),
+            internal_error: err.to_string(),
+        })
+    }
 }
 
 fn open_for_write(path: &Path) -> Result<std::fs::File, LayoutError> {
@@ -1598,6 +1862,7 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
             cycles_balance: Some(item.cycles_balance.into()),
             cycles_debit: Some(item.cycles_debit.into()),
             reserved_balance: Some(item.reserved_balance.into()),
+            reserved_balance_limit: item.reserved_balance_limit.map(|limit| limit.into()),
             canister_status: Some((&item.status).into()),
             scheduled_as_first: item.scheduled_as_first,
             skipped_round_due_to_no_messages: item.skipped_round_due_to_no_messages,
@@ -1623,6 +1888,25 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
                 })
                 .collect(),
             canister_history: Some((&item.canister_history).into()),
+            next_snapshot_id: item.next_snapshot_id,
+            log_visibility: pb_canister_state_bits::LogVisibility::from(&item.log_visibility)
+                .into(),
+            canister_log_records: item
+                .canister_log
+                .records()
+                .iter()
+                .map(|record| record.into())
+                .collect(),
+            next_canister_log_record_idx: item.canister_log.next_idx(),
+            wasm_memory_limit: item.wasm_memory_limit.map(|limit| limit.get()),
+            wasm_memory_threshold: item.wasm_memory_threshold.get(),
+            on_low_wasm_memory_hook_status:
+                pb_canister_state_bits::OnLowWasmMemoryHookStatus::from(
+                    &item.on_low_wasm_memory_hook_status,
+                )
+                .into(),
+            total_query_stats: Some((&item.total_query_stats).into()),
+            wasm_chunk_store_metadata: Some((&item.wasm_chunk_store_metadata).into()),
         }
     }
 }
@@ -1668,6 +1952,11 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             .transpose()?
             .unwrap_or_else(Cycles::zero);
 
+        let reserved_balance_limit = value
+            .reserved_balance_limit
+            .map(|c| c.try_into())
+            .transpose()?;
+
         let task_queue = value
             .task_queue
             .into_iter()
@@ -1695,6 +1984,7 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             cycles_balance,
             cycles_debit,
             reserved_balance,
+            reserved_balance_limit,
             status: try_from_option_field(
                 value.canister_status,
                 "CanisterStateBits::canister_status",
@@ -1733,6 +2023,38 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
                 "CanisterStateBits::canister_history",
             )
             .unwrap_or_default(),
+            next_snapshot_id: value.next_snapshot_id,
+            // Checkpoints written before log visibility was introduced have it
+            // unspecified, which defaults to controllers only.
+            log_visibility: pb_canister_state_bits::LogVisibility::from_i32(value.log_visibility)
+                .and_then(|log_visibility| LogVisibility::try_from(log_visibility).ok())
+                .unwrap_or_default(),
+            canister_log: CanisterLog::new(
+                value.next_canister_log_record_idx,
+                value
+                    .canister_log_records
+                    .into_iter()
+                    .map(|record| record.into())
+                    .collect(),
+            ),
+            wasm_memory_limit: value.wasm_memory_limit.map(NumBytes::from),
+            wasm_memory_threshold: NumBytes::from(value.wasm_memory_threshold),
+            on_low_wasm_memory_hook_status:
+                pb_canister_state_bits::OnLowWasmMemoryHookStatus::from_i32(
+                    value.on_low_wasm_memory_hook_status,
+                )
+                .unwrap_or(pb_canister_state_bits::OnLowWasmMemoryHookStatus::Unspecified)
+                .into(),
+            total_query_stats: value
+                .total_query_stats
+                .map(TotalQueryStats::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            wasm_chunk_store_metadata: value
+                .wasm_chunk_store_metadata
+                .map(WasmChunkStoreMetadata::try_from)
+                .transpose()?
+                .unwrap_or_default(),
         })
     }
 }
@@ -1800,6 +2122,58 @@ impl TryFrom<pb_canister_state_bits::ExecutionStateBits> for ExecutionStateBits
     }
 }
 
+impl From<CanisterSnapshotBits> for pb_canister_state_bits::CanisterSnapshotBits {
+    fn from(item: CanisterSnapshotBits) -> Self {
+        Self {
+            canister_id: Some(item.snapshot_id.get_canister_id().into()),
+            local_id: item.snapshot_id.get_local_id(),
+            taken_at_timestamp: item.taken_at_timestamp.as_nanos_since_unix_epoch(),
+            canister_version: item.canister_version,
+            certified_data: item.certified_data,
+            exported_globals: item
+                .exported_globals
+                .iter()
+                .map(|global| global.into())
+                .collect(),
+            wasm_memory_size: item.wasm_memory_size.get() as u64,
+            stable_memory_size: item.stable_memory_size.get() as u64,
+            binary_hash: item.binary_hash.to_vec(),
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterSnapshotBits> for CanisterSnapshotBits {
+    type Error = ProxyDecodeError;
+
+    fn try_from(value: pb_canister_state_bits::CanisterSnapshotBits) -> Result<Self, Self::Error> {
+        let canister_id: CanisterId =
+            try_from_option_field(value.canister_id, "CanisterSnapshotBits::canister_id")?;
+        let mut exported_globals = Vec::with_capacity(value.exported_globals.len());
+        for g in value.exported_globals.into_iter() {
+            exported_globals.push(g.try_into()?);
+        }
+        let binary_hash: [u8; 32] =
+            value
+                .binary_hash
+                .try_into()
+                .map_err(|e| ProxyDecodeError::ValueOutOfRange {
+                    typ: "BinaryHash",
+                    err: format!("Expected a 32-byte long module hash, got {:?}", e),
+                })?;
+
+        Ok(Self {
+            snapshot_id: SnapshotId::new(canister_id, value.local_id),
+            taken_at_timestamp: Time::from_nanos_since_unix_epoch(value.taken_at_timestamp),
+            canister_version: value.canister_version,
+            certified_data: value.certified_data,
+            exported_globals,
+            wasm_memory_size: NumWasmPages::from(value.wasm_memory_size as usize),
+            stable_memory_size: NumWasmPages::from(value.stable_memory_size as usize),
+            binary_hash: binary_hash.into(),
+        })
+    }
+}
+
 fn dir_file_names(p: &Path) -> std::io::Result<Vec<String>> {
     if !p.exists() {
         return Ok(vec![]);
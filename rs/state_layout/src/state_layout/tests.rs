@@ -4,7 +4,7 @@ use ic_ic00_types::{
     CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode, IC_00,
 };
 use ic_interfaces::messages::{CanisterCall, CanisterMessage, CanisterMessageOrTask};
-use ic_replicated_state::canister_state::system_state::CanisterHistory;
+use ic_replicated_state::canister_state::system_state::{CanisterHistory, WasmChunkStore};
 use ic_test_utilities::types::ids::user_test_id;
 use ic_test_utilities::{
     mock_time,
@@ -30,6 +30,7 @@ fn default_canister_state_bits() -> CanisterStateBits {
         cycles_balance: Cycles::zero(),
         cycles_debit: Cycles::zero(),
         reserved_balance: Cycles::zero(),
+        reserved_balance_limit: None,
         status: CanisterStatus::Stopped,
         scheduled_as_first: 0,
         skipped_round_due_to_no_messages: 0,
@@ -46,6 +47,14 @@ fn default_canister_state_bits() -> CanisterStateBits {
         canister_version: 0,
         consumed_cycles_since_replica_started_by_use_cases: BTreeMap::new(),
         canister_history: CanisterHistory::default(),
+        next_snapshot_id: 0,
+        log_visibility: LogVisibility::default(),
+        canister_log: CanisterLog::default(),
+        wasm_chunk_store_metadata: WasmChunkStoreMetadata::default(),
+        wasm_memory_limit: None,
+        wasm_memory_threshold: NumBytes::from(0),
+        on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus::default(),
+        total_query_stats: TotalQueryStats::default(),
     }
 }
 
@@ -186,6 +195,101 @@ fn test_encode_decode_non_empty_history() {
     assert_eq!(canister_state_bits.canister_history, canister_history);
 }
 
+#[test]
+fn test_encode_decode_canister_log() {
+    let mut canister_log = CanisterLog::new_with_next_index(3);
+    canister_log.add_record(10, b"hello");
+    canister_log.add_record(20, b"world");
+
+    let canister_state_bits = CanisterStateBits {
+        log_visibility: LogVisibility::Public,
+        canister_log: canister_log.clone(),
+        ..default_canister_state_bits()
+    };
+
+    let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+    let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+    assert_eq!(canister_state_bits.log_visibility, LogVisibility::Public);
+    assert_eq!(canister_state_bits.canister_log, canister_log);
+}
+
+#[test]
+fn test_decode_unspecified_log_visibility() {
+    let mut pb_bits =
+        pb_canister_state_bits::CanisterStateBits::from(default_canister_state_bits());
+    pb_bits.log_visibility = pb_canister_state_bits::LogVisibility::Unspecified.into();
+    let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+    assert_eq!(
+        canister_state_bits.log_visibility,
+        LogVisibility::Controllers
+    );
+}
+
+#[test]
+fn test_encode_decode_wasm_chunk_store() {
+    let mut wasm_chunk_store = WasmChunkStore::new_for_testing();
+    wasm_chunk_store.insert_chunk(&[1, 2, 3]);
+    wasm_chunk_store.insert_chunk(&[4, 5]);
+    let canister_state_bits = CanisterStateBits {
+        wasm_chunk_store_metadata: wasm_chunk_store.metadata().clone(),
+        ..default_canister_state_bits()
+    };
+
+    let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+    let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+    assert_eq!(
+        &canister_state_bits.wasm_chunk_store_metadata,
+        wasm_chunk_store.metadata()
+    );
+}
+
+#[test]
+fn test_encode_decode_wasm_memory_limit() {
+    let canister_state_bits = CanisterStateBits {
+        wasm_memory_limit: Some(NumBytes::from(1 << 30)),
+        wasm_memory_threshold: NumBytes::from(1 << 20),
+        on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus::Ready,
+        ..default_canister_state_bits()
+    };
+
+    let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+    let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+    assert_eq!(
+        canister_state_bits.wasm_memory_limit,
+        Some(NumBytes::from(1 << 30))
+    );
+    assert_eq!(
+        canister_state_bits.wasm_memory_threshold,
+        NumBytes::from(1 << 20)
+    );
+    assert_eq!(
+        canister_state_bits.on_low_wasm_memory_hook_status,
+        OnLowWasmMemoryHookStatus::Ready
+    );
+}
+
+#[test]
+fn test_encode_decode_reserved_balance() {
+    let canister_state_bits = CanisterStateBits {
+        reserved_balance: Cycles::new(1_000),
+        reserved_balance_limit: Some(Cycles::new(5_000)),
+        ..default_canister_state_bits()
+    };
+
+    let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+    let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+    assert_eq!(canister_state_bits.reserved_balance, Cycles::new(1_000));
+    assert_eq!(
+        canister_state_bits.reserved_balance_limit,
+        Some(Cycles::new(5_000))
+    );
+}
+
 #[test]
 fn test_encode_decode_task_queue() {
     let ingress = Arc::new(IngressBuilder::new().method_name("test_ingress").build());
@@ -343,3 +447,50 @@ fn test_canister_id_from_path() {
         canister_id_from_path(Path::new("canister_states/not-a-canister-ID/queues.pbuf"))
     );
 }
+
+#[test]
+fn test_page_map_layout_lists_overlays_by_height() {
+    let tempdir = tmpdir("page_map_layout");
+    let canister_layout: CanisterLayout<WriteOnly> =
+        CanisterLayout::new(tempdir.path().to_path_buf()).unwrap();
+    let wasm_memory = canister_layout.wasm_memory();
+    let stable_memory = canister_layout.stable_memory();
+    assert_eq!(wasm_memory.base(), canister_layout.vmemory_0());
+    assert_eq!(stable_memory.base(), canister_layout.stable_memory_blob());
+    assert_eq!(
+        canister_layout.wasm_chunk_store().base(),
+        tempdir.path().join("wasm_chunk_store.bin")
+    );
+
+    for height in [100, 9, 1000] {
+        std::fs::write(wasm_memory.overlay(Height::new(height)), b"").unwrap();
+    }
+    std::fs::write(stable_memory.overlay(Height::new(5)), b"").unwrap();
+    std::fs::write(wasm_memory.base(), b"").unwrap();
+
+    assert_eq!(
+        wasm_memory.existing_overlays().unwrap(),
+        vec![
+            wasm_memory.overlay(Height::new(9)),
+            wasm_memory.overlay(Height::new(100)),
+            wasm_memory.overlay(Height::new(1000)),
+        ]
+    );
+    assert_eq!(
+        wasm_memory.overlay_height(&wasm_memory.overlay(Height::new(100))),
+        Some(Height::new(100))
+    );
+    assert_eq!(
+        wasm_memory.overlay_height(&stable_memory.overlay(Height::new(5))),
+        None
+    );
+    assert_eq!(wasm_memory.overlay_height(&wasm_memory.base()), None);
+
+    wasm_memory.delete_overlays().unwrap();
+    assert!(wasm_memory.existing_overlays().unwrap().is_empty());
+    assert!(wasm_memory.base().exists());
+    assert_eq!(
+        stable_memory.existing_overlays().unwrap(),
+        vec![stable_memory.overlay(Height::new(5))]
+    );
+}
@@ -59,7 +59,7 @@ use ic_replicated_state::{
     canister_state::{NumWasmPages, WASM_PAGE_SIZE_IN_BYTES},
     Memory, PageMap, ReplicatedState,
 };
-use ic_state_layout::{CheckpointLayout, RwPolicy};
+use ic_state_layout::{CheckpointLayout, RwPolicy, StateLayout, CHECKPOINTS_DIR};
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities_metrics::{fetch_histogram_stats, fetch_int_counter};
 use ic_test_utilities_registry::{
@@ -74,11 +74,11 @@ use ic_types::crypto::{
     CombinedThresholdSigOf, Signable, Signed,
 };
 use ic_types::malicious_flags::MaliciousFlags;
-use ic_types::messages::{CallbackId, Certificate, Response};
+use ic_types::messages::{CallbackId, Certificate, Response, NO_DEADLINE};
 use ic_types::signature::ThresholdSignature;
 use ic_types::time::GENESIS;
 use ic_types::{
-    batch::{Batch, BatchMessages, XNetPayload},
+    batch::{Batch, BatchMessages, QueryStatsPayload, XNetPayload},
     consensus::certification::Certification,
     messages::{
         Blob, HttpCallContent, HttpCanisterUpdate, HttpRequestEnvelope, Payload as MsgPayload,
@@ -107,9 +107,12 @@ use std::{fmt, io};
 use tempfile::TempDir;
 use tokio::runtime::Runtime;
 
+mod multi_subnet;
 #[cfg(test)]
 mod tests;
 
+pub use multi_subnet::{MultiSubnetStateMachine, MultiSubnetStateMachineBuilder};
+
 struct FakeVerifier;
 
 impl Verifier for FakeVerifier {
@@ -281,6 +284,9 @@ impl StateMachineConfig {
 /// can be used to test this part of the stack in isolation.
 pub struct StateMachine {
     subnet_id: SubnetId,
+    subnet_type: SubnetType,
+    config: Option<StateMachineConfig>,
+    use_cost_scaling_flag: bool,
     public_key: ThresholdSigPublicKey,
     secret_key: SecretKeyBytes,
     ecdsa_secret_key: PrivateKey,
@@ -328,6 +334,7 @@ pub struct StateMachineBuilder {
     use_cost_scaling_flag: bool,
     ecdsa_keys: Vec<EcdsaKeyId>,
     features: SubnetFeatures,
+    registry_data_provider: Option<Arc<ProtoRegistryDataProvider>>,
 }
 
 impl StateMachineBuilder {
@@ -353,6 +360,7 @@ impl StateMachineBuilder {
                 http_requests: true,
                 ..SubnetFeatures::default()
             },
+            registry_data_provider: None,
         }
     }
 
@@ -368,6 +376,18 @@ impl StateMachineBuilder {
         Self { time, ..self }
     }
 
+    /// Uses the given registry instead of constructing one from the subnet
+    /// parameters of the builder.
+    fn with_registry_data_provider(
+        self,
+        registry_data_provider: Arc<ProtoRegistryDataProvider>,
+    ) -> Self {
+        Self {
+            registry_data_provider: Some(registry_data_provider),
+            ..self
+        }
+    }
+
     pub fn with_config(self, config: Option<StateMachineConfig>) -> Self {
         Self { config, ..self }
     }
@@ -472,6 +492,7 @@ impl StateMachineBuilder {
             self.use_cost_scaling_flag,
             self.ecdsa_keys,
             self.features,
+            self.registry_data_provider,
         )
     }
 }
@@ -482,6 +503,95 @@ impl Default for StateMachineBuilder {
     }
 }
 
+/// A saved state of a [StateMachine], see [StateMachine::snapshot].
+///
+/// The snapshot owns a copy of the checkpoint it was taken from, so it stays
+/// valid after the original state machine advances or is dropped.
+pub struct StateMachineSnapshot {
+    checkpoint_dir: TempDir,
+    height: Height,
+    registry: Vec<u8>,
+    nonce: u64,
+    time: Time,
+    checkpoints_enabled: bool,
+    subnet_id: SubnetId,
+    subnet_type: SubnetType,
+    config: Option<StateMachineConfig>,
+    use_cost_scaling_flag: bool,
+    ecdsa_keys: Vec<EcdsaKeyId>,
+}
+
+impl fmt::Debug for StateMachineSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateMachineSnapshot")
+            .field("checkpoint_dir", &self.checkpoint_dir.path().display())
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl StateMachineSnapshot {
+    /// Returns the height of the checkpoint the snapshot was taken from.
+    pub fn height(&self) -> Height {
+        self.height
+    }
+
+    /// Starts a new state machine from this snapshot.
+    ///
+    /// The new state machine has its own state directory, so state machines
+    /// started from the same snapshot do not affect each other.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the checkpoint cannot be copied.
+    pub fn start(&self) -> StateMachine {
+        let state_dir = TempDir::new().expect("failed to create a temporary directory");
+        link_or_copy_recursively(
+            self.checkpoint_dir.path(),
+            &state_dir
+                .path()
+                .join(CHECKPOINTS_DIR)
+                .join(StateLayout::checkpoint_name(self.height)),
+        )
+        .expect("failed to restore checkpoint");
+        let registry_data_provider =
+            Arc::new(ProtoRegistryDataProvider::decode(self.registry.as_slice()));
+
+        StateMachineBuilder {
+            ecdsa_keys: self.ecdsa_keys.clone(),
+            ..StateMachineBuilder::new()
+        }
+        .with_state_dir(state_dir)
+        .with_nonce(self.nonce)
+        .with_time(self.time)
+        .with_config(self.config.clone())
+        .with_checkpoints_enabled(self.checkpoints_enabled)
+        .with_subnet_id(self.subnet_id)
+        .with_subnet_type(self.subnet_type)
+        .with_use_cost_scaling_flag(self.use_cost_scaling_flag)
+        .with_registry_data_provider(registry_data_provider)
+        .build()
+    }
+}
+
+/// Recursively hard-links the files in `src` into `dst`, falling back to
+/// copying if linking fails (e.g. across file systems).
+///
+/// This is only safe for checkpoint files, which are never modified in place.
+fn link_or_copy_recursively(src: &Path, dst: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let dst = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            link_or_copy_recursively(&entry.path(), &dst)?;
+        } else if std::fs::hard_link(entry.path(), &dst).is_err() {
+            std::fs::copy(entry.path(), &dst)?;
+        }
+    }
+    Ok(())
+}
+
 impl StateMachine {
     // TODO: cleanup, replace external calls with `StateMachineBuilder`.
     /// Constructs a new environment that uses a temporary directory for storing
@@ -498,6 +608,7 @@ impl StateMachine {
 
     /// Constructs and initializes a new state machine that uses the specified
     /// directory for storing states.
+    #[allow(clippy::too_many_arguments)]
     fn setup_from_dir(
         state_dir: TempDir,
         nonce: u64,
@@ -512,6 +623,7 @@ impl StateMachine {
         use_cost_scaling_flag: bool,
         ecdsa_keys: Vec<EcdsaKeyId>,
         features: SubnetFeatures,
+        registry_data_provider: Option<Arc<ProtoRegistryDataProvider>>,
     ) -> Self {
         let replica_logger = replica_logger();
 
@@ -522,20 +634,29 @@ impl StateMachine {
         }
         let metrics_registry = MetricsRegistry::new();
 
-        let (subnet_config, mut hypervisor_config) = match config {
+        let (subnet_config, mut hypervisor_config) = match config.clone() {
             Some(config) => (config.subnet_config, config.hypervisor_config),
             None => (SubnetConfig::new(subnet_type), HypervisorConfig::default()),
         };
 
-        let (registry_data_provider, registry_client) = make_nodes_registry(
-            nns_subnet_id,
-            subnet_id,
-            subnet_type,
-            routing_table,
-            &node_ids,
-            &ecdsa_keys,
-            features,
-        );
+        let (registry_data_provider, registry_client) = match registry_data_provider {
+            Some(registry_data_provider) => {
+                let registry_client = Arc::new(FakeRegistryClient::new(Arc::clone(
+                    &registry_data_provider,
+                ) as _));
+                registry_client.update_to_latest_version();
+                (registry_data_provider, registry_client)
+            }
+            None => make_nodes_registry(
+                nns_subnet_id,
+                subnet_id,
+                subnet_type,
+                routing_table,
+                &node_ids,
+                &ecdsa_keys,
+                features,
+            ),
+        };
 
         let sm_config = ic_config::state_manager::Config::new(state_dir.path().to_path_buf());
 
@@ -655,6 +776,9 @@ impl StateMachine {
 
         Self {
             subnet_id,
+            subnet_type,
+            config,
+            use_cost_scaling_flag,
             secret_key: secret_key_bytes.get(0).unwrap().clone(),
             public_key,
             ecdsa_secret_key,
@@ -715,6 +839,51 @@ impl StateMachine {
             .build()
     }
 
+    /// Saves the latest state of this state machine, together with its
+    /// registry, as a [StateMachineSnapshot].
+    ///
+    /// Any number of independent state machines can then be started from the
+    /// snapshot with [StateMachineSnapshot::start], which is much faster than
+    /// re-creating the same state by executing messages. The snapshot is taken
+    /// by writing a checkpoint of the latest state without executing a round,
+    /// so it works regardless of whether checkpoints are enabled.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the checkpoint cannot be written or saved.
+    pub fn snapshot(&self) -> StateMachineSnapshot {
+        let (height, state) = self.state_manager.take_tip();
+        let height = height.increment();
+        self.state_manager
+            .commit_and_certify(state, height, CertificationScope::Full);
+
+        let checkpoint = self
+            .state_manager
+            .state_layout()
+            .checkpoint(height)
+            .expect("failed to obtain checkpoint");
+        let checkpoint_dir = TempDir::new().expect("failed to create a temporary directory");
+        link_or_copy_recursively(checkpoint.raw_path(), checkpoint_dir.path())
+            .expect("failed to save checkpoint");
+
+        let mut registry = vec![];
+        self.registry_data_provider.encode(&mut registry);
+
+        StateMachineSnapshot {
+            checkpoint_dir,
+            height,
+            registry,
+            nonce: self.nonce.get(),
+            time: self.time.get(),
+            checkpoints_enabled: self.checkpoints_enabled.get(),
+            subnet_id: self.subnet_id,
+            subnet_type: self.subnet_type,
+            config: self.config.clone(),
+            use_cost_scaling_flag: self.use_cost_scaling_flag,
+            ecdsa_keys: self.ecdsa_subnet_public_keys.keys().cloned().collect(),
+        }
+    }
+
     /// If the argument is true, the state machine will create an on-disk
     /// checkpoint for each new state it creates.
     ///
@@ -761,7 +930,13 @@ impl StateMachine {
     /// Triggers a single round of execution without any new inputs.  The state
     /// machine will invoke heartbeats and make progress on pending async calls.
     pub fn tick(&self) {
-        let mut payload = PayloadBuilder::default();
+        self.tick_with_xnet_payload(XNetPayload::default())
+    }
+
+    /// Same as [tick], but also inducts the stream slices in the given XNet
+    /// payload.
+    pub fn tick_with_xnet_payload(&self, xnet_payload: XNetPayload) {
+        let mut payload = PayloadBuilder::default().xnet_payload(xnet_payload);
         let state = self.state_manager.get_latest_state().take();
         let sign_with_ecdsa_contexts = state
             .metadata
@@ -788,6 +963,7 @@ impl StateMachine {
                 originator_reply_callback: id,
                 refund: Cycles::zero(),
                 response_payload: MsgPayload::Data(reply.encode()),
+                deadline: NO_DEADLINE,
             });
         }
         self.execute_payload(payload)
@@ -838,6 +1014,7 @@ impl StateMachine {
                 signed_ingress_msgs: payload.ingress_messages,
                 certified_stream_slices: payload.xnet_payload.stream_slices,
                 bitcoin_adapter_responses: vec![],
+                query_stats: payload.query_stats,
             },
             randomness: Randomness::from(seed),
             ecdsa_subnet_public_keys: self.ecdsa_subnet_public_keys.clone(),
@@ -1838,6 +2015,7 @@ pub struct PayloadBuilder {
     ingress_messages: Vec<SignedIngress>,
     xnet_payload: XNetPayload,
     consensus_responses: Vec<Response>,
+    query_stats: Option<QueryStatsPayload>,
 }
 
 impl Default for PayloadBuilder {
@@ -1848,6 +2026,7 @@ impl Default for PayloadBuilder {
             ingress_messages: Default::default(),
             xnet_payload: Default::default(),
             consensus_responses: Default::default(),
+            query_stats: None,
         }
         .with_max_expiry_time_from_now(GENESIS.into())
     }
@@ -1911,6 +2090,11 @@ impl PayloadBuilder {
         self
     }
 
+    pub fn query_stats(mut self, query_stats: QueryStatsPayload) -> Self {
+        self.query_stats = Some(query_stats);
+        self
+    }
+
     pub fn http_response(mut self, id: CallbackId, payload: &CanisterHttpResponsePayload) -> Self {
         self.consensus_responses.push(Response {
             originator: CanisterId::ic_00(),
@@ -1918,6 +2102,7 @@ impl PayloadBuilder {
             originator_reply_callback: id,
             refund: Cycles::zero(),
             response_payload: MsgPayload::Data(payload.encode()),
+            deadline: NO_DEADLINE,
         });
         self
     }
@@ -0,0 +1,353 @@
+use crate::{
+    CanisterId, IngressState, IngressStatus, PrincipalId, StateMachine, StateMachineBuilder,
+    SubnetId, UserError, WasmResult,
+};
+use ic_interfaces_certified_stream_store::EncodeStreamError;
+use ic_registry_routing_table::{
+    routing_table_insert_subnet, CanisterIdRange, CanisterIdRanges, RoutingTable,
+};
+use ic_registry_subnet_type::SubnetType;
+use ic_types::batch::XNetPayload;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime};
+
+/// The maximum number of rounds to wait for the result of an ingress message.
+const MAX_TICKS: usize = 100;
+
+/// Builds a [MultiSubnetStateMachine].
+pub struct MultiSubnetStateMachineBuilder {
+    subnets: Vec<(SubnetId, SubnetType)>,
+}
+
+impl MultiSubnetStateMachineBuilder {
+    pub fn new() -> Self {
+        Self { subnets: vec![] }
+    }
+
+    /// Adds a subnet with the given ID and type. Each subnet is assigned a
+    /// canister ID range in the order in which the subnets are added.
+    ///
+    /// The first system subnet (or the first subnet, if there is no system
+    /// subnet) acts as the NNS subnet.
+    pub fn with_subnet(mut self, subnet_id: SubnetId, subnet_type: SubnetType) -> Self {
+        assert!(
+            self.subnets.iter().all(|(id, _)| *id != subnet_id),
+            "duplicate subnet {}",
+            subnet_id
+        );
+        self.subnets.push((subnet_id, subnet_type));
+        self
+    }
+
+    pub fn build(self) -> MultiSubnetStateMachine {
+        assert!(!self.subnets.is_empty(), "at least one subnet is required");
+
+        let mut routing_table = RoutingTable::new();
+        for (subnet_id, _) in &self.subnets {
+            routing_table_insert_subnet(&mut routing_table, *subnet_id)
+                .expect("failed to update the routing table");
+        }
+        let nns_subnet_id = self
+            .subnets
+            .iter()
+            .find(|(_, subnet_type)| *subnet_type == SubnetType::System)
+            .unwrap_or(&self.subnets[0])
+            .0;
+
+        let subnets = self
+            .subnets
+            .into_iter()
+            .map(|(subnet_id, subnet_type)| {
+                let env = StateMachineBuilder::new()
+                    .with_subnet_id(subnet_id)
+                    .with_subnet_type(subnet_type)
+                    .with_nns_subnet_id(nns_subnet_id)
+                    .with_routing_table(routing_table.clone())
+                    .build();
+                (subnet_id, env)
+            })
+            .collect();
+
+        MultiSubnetStateMachine {
+            subnets,
+            routing_table,
+        }
+    }
+}
+
+impl Default for MultiSubnetStateMachineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A set of [StateMachine] subnets that share one routing table and exchange
+/// XNet streams with each other on every [tick](MultiSubnetStateMachine::tick).
+///
+/// This allows testing cross-subnet canister calls and canister migrations
+/// without constructing XNet payloads by hand. Registry changes (such as
+/// rerouting canister ranges) must go through this struct rather than
+/// through the individual subnets, so that all subnets keep the same view of
+/// the registry.
+pub struct MultiSubnetStateMachine {
+    subnets: BTreeMap<SubnetId, StateMachine>,
+    routing_table: RoutingTable,
+}
+
+impl MultiSubnetStateMachine {
+    /// Returns the subnet with the given ID.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no such subnet.
+    pub fn subnet(&self, subnet_id: SubnetId) -> &StateMachine {
+        self.subnets
+            .get(&subnet_id)
+            .unwrap_or_else(|| panic!("Subnet {} does not exist", subnet_id))
+    }
+
+    /// Returns an iterator over all subnets in ascending order of their IDs.
+    pub fn subnets(&self) -> impl Iterator<Item = &StateMachine> {
+        self.subnets.values()
+    }
+
+    /// Returns the subnet the given canister is routed to, if any.
+    pub fn route(&self, canister_id: CanisterId) -> Option<&StateMachine> {
+        self.routing_table
+            .route(canister_id.get())
+            .and_then(|subnet_id| self.subnets.get(&subnet_id))
+    }
+
+    /// Executes a single round on every subnet.
+    ///
+    /// Before executing the round, each subnet is handed the XNet stream
+    /// slices from all other subnets that it has not inducted yet, as they
+    /// are at the end of the previous round.
+    pub fn tick(&self) {
+        let payloads: Vec<_> = self
+            .subnets
+            .values()
+            .map(|env| (env, self.xnet_payload_for(env)))
+            .collect();
+        for (env, xnet_payload) in payloads {
+            env.tick_with_xnet_payload(xnet_payload);
+        }
+    }
+
+    /// Ticks until there are no more messages in any canister queue or XNet
+    /// stream.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the subnets did not process all messages within
+    /// the `max_ticks` iterations.
+    pub fn run_until_completion(&self, max_ticks: usize) {
+        for _tick in 0..max_ticks {
+            if self.is_idle() {
+                return;
+            }
+            self.tick();
+        }
+        if !self.is_idle() {
+            panic!(
+                "The subnets did not reach completion after {} ticks",
+                max_ticks
+            );
+        }
+    }
+
+    /// Sets the time of all subnets.
+    pub fn set_time(&self, time: SystemTime) {
+        for env in self.subnets.values() {
+            env.set_time(time);
+        }
+    }
+
+    /// Advances the time of all subnets by the given amount.
+    pub fn advance_time(&self, amount: Duration) {
+        for env in self.subnets.values() {
+            env.advance_time(amount);
+        }
+    }
+
+    /// Executes an ingress message on the canister with the specified ID,
+    /// on the subnet the canister is routed to.
+    ///
+    /// This function is synchronous, it ticks all subnets until the result of
+    /// the ingress message is known, so calls to canisters on other subnets
+    /// make progress while waiting.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the canister is not routed to any subnet or if
+    /// the status was not ready in a reasonable amount of time.
+    pub fn execute_ingress_as(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        method: impl ToString,
+        payload: Vec<u8>,
+    ) -> Result<WasmResult, UserError> {
+        let env = self.route_or_panic(canister_id);
+        let msg_id = env.send_ingress(sender, canister_id, method, payload);
+        for _tick in 0..MAX_TICKS {
+            match env.ingress_status(&msg_id) {
+                IngressStatus::Known {
+                    state: IngressState::Completed(result),
+                    ..
+                } => return Ok(result),
+                IngressStatus::Known {
+                    state: IngressState::Failed(error),
+                    ..
+                } => return Err(error),
+                _ => self.tick(),
+            }
+        }
+        panic!(
+            "Did not get answer to ingress {} after {} ticks",
+            msg_id, MAX_TICKS
+        )
+    }
+
+    pub fn execute_ingress(
+        &self,
+        canister_id: CanisterId,
+        method: impl ToString,
+        payload: Vec<u8>,
+    ) -> Result<WasmResult, UserError> {
+        self.execute_ingress_as(PrincipalId::new_anonymous(), canister_id, method, payload)
+    }
+
+    /// Queries the canister with the specified ID on the subnet the canister
+    /// is routed to.
+    pub fn query_as(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        method: impl ToString,
+        payload: Vec<u8>,
+    ) -> Result<WasmResult, UserError> {
+        self.route_or_panic(canister_id)
+            .query_as(sender, canister_id, method, payload)
+    }
+
+    /// Assigns the canister range to the destination subnet in the routing
+    /// table of every subnet.
+    pub fn reroute_canister_range(
+        &mut self,
+        canister_range: std::ops::RangeInclusive<CanisterId>,
+        destination: SubnetId,
+    ) {
+        for env in self.subnets.values() {
+            env.reroute_canister_range(canister_range.clone(), destination);
+        }
+        self.routing_table
+            .assign_ranges(
+                CanisterIdRanges::try_from(vec![CanisterIdRange {
+                    start: *canister_range.start(),
+                    end: *canister_range.end(),
+                }])
+                .unwrap(),
+                destination,
+            )
+            .expect("ranges are not well formed");
+    }
+
+    /// Marks canisters in the specified range as being migrated from the
+    /// source to the destination subnet, on every subnet.
+    pub fn prepare_canister_migrations(
+        &self,
+        canister_range: std::ops::RangeInclusive<CanisterId>,
+        source: SubnetId,
+        destination: SubnetId,
+    ) {
+        for env in self.subnets.values() {
+            env.prepare_canister_migrations(canister_range.clone(), source, destination);
+        }
+    }
+
+    /// Marks canisters in the specified range as successfully migrated, on
+    /// every subnet.
+    pub fn complete_canister_migrations(
+        &self,
+        canister_range: std::ops::RangeInclusive<CanisterId>,
+        migration_trace: Vec<SubnetId>,
+    ) {
+        for env in self.subnets.values() {
+            env.complete_canister_migrations(canister_range.clone(), migration_trace.clone());
+        }
+    }
+
+    /// Migrates the canister to the destination subnet: marks the canister
+    /// as being migrated, reroutes it, moves its state, and completes the
+    /// migration.
+    pub fn migrate_canister(
+        &mut self,
+        canister_id: CanisterId,
+        destination: SubnetId,
+    ) -> Result<(), String> {
+        let source = self
+            .route(canister_id)
+            .ok_or_else(|| format!("Canister {} is not routed to any subnet", canister_id))?
+            .get_subnet_id();
+        if !self.subnets.contains_key(&destination) {
+            return Err(format!("Subnet {} does not exist", destination));
+        }
+
+        self.prepare_canister_migrations(canister_id..=canister_id, source, destination);
+        self.reroute_canister_range(canister_id..=canister_id, destination);
+        self.subnet(source)
+            .move_canister_state_to(self.subnet(destination), canister_id)?;
+        self.complete_canister_migrations(canister_id..=canister_id, vec![source, destination]);
+        Ok(())
+    }
+
+    fn route_or_panic(&self, canister_id: CanisterId) -> &StateMachine {
+        self.route(canister_id)
+            .unwrap_or_else(|| panic!("Canister {} is not routed to any subnet", canister_id))
+    }
+
+    /// Returns the stream slices from all other subnets to `receiver`,
+    /// beginning at the first message `receiver` has not inducted yet.
+    fn xnet_payload_for(&self, receiver: &StateMachine) -> XNetPayload {
+        let receiver_id = receiver.get_subnet_id();
+        let receiver_state = receiver.get_latest_state();
+        let mut stream_slices = BTreeMap::new();
+        for (sender_id, sender) in &self.subnets {
+            if *sender_id == receiver_id {
+                continue;
+            }
+            let begin = receiver_state
+                .get_stream(sender_id)
+                .map(|stream| stream.signals_end());
+            match sender.generate_xnet_payload(receiver_id, begin, begin, None, None) {
+                Ok(payload) => stream_slices.extend(payload.stream_slices),
+                Err(EncodeStreamError::NoStreamForSubnet(_)) => {}
+                Err(err) => panic!(
+                    "Failed to encode stream from {} to {}: {:?}",
+                    sender_id, receiver_id, err
+                ),
+            }
+        }
+        XNetPayload { stream_slices }
+    }
+
+    /// Returns true if no canister has pending messages and all messages in
+    /// XNet streams have been inducted and garbage collected.
+    fn is_idle(&self) -> bool {
+        self.subnets.values().all(|env| {
+            let state = env.get_latest_state();
+            !state
+                .canisters_iter()
+                .any(|canister| canister.has_input() || canister.has_output())
+                && !state.subnet_queues().has_input()
+                && !state.subnet_queues().has_output()
+                && state
+                    .metadata
+                    .streams()
+                    .iter()
+                    .all(|(_, stream)| stream.messages_begin() == stream.messages_end())
+        })
+    }
+}
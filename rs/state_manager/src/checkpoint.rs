@@ -3,14 +3,19 @@ use crossbeam_channel::{unbounded, Sender};
 use ic_base_types::{subnet_id_try_from_protobuf, CanisterId};
 // TODO(MR-412): uncomment
 //use ic_protobuf::proxy::try_from_option_field;
+use ic_ic00_types::SnapshotId;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 use ic_replicated_state::Memory;
 use ic_replicated_state::{
-    canister_state::execution_state::WasmBinary, page_map::PageMap, CanisterMetrics, CanisterState,
-    ExecutionState, ReplicatedState, SchedulerState, SystemState,
+    canister_state::{execution_state::WasmBinary, system_state::WasmChunkStore},
+    page_map::PageMap,
+    CanisterMetrics, CanisterSnapshot, CanisterSnapshots, CanisterState, ExecutionState,
+    ReplicatedState, SchedulerState, SystemState,
+};
+use ic_state_layout::{
+    CanisterLayout, CanisterSnapshotBits, CanisterStateBits, CheckpointLayout, ReadOnly, ReadPolicy,
 };
-use ic_state_layout::{CanisterLayout, CanisterStateBits, CheckpointLayout, ReadOnly, ReadPolicy};
 use ic_types::{CanisterTimer, Height, LongExecutionMode, Time};
 use ic_utils::thread::parallel_map;
 use std::collections::BTreeMap;
@@ -59,6 +64,17 @@ pub(crate) fn make_checkpoint(
         })
         .unwrap();
 
+    tip_channel
+        .send(TipRequest::FilterTipSnapshots {
+            height,
+            ids: state
+                .canister_snapshots
+                .iter()
+                .map(|(snapshot_id, _)| *snapshot_id)
+                .collect(),
+        })
+        .unwrap();
+
     let cp = {
         let _timer = metrics
             .make_checkpoint_step_duration
@@ -213,7 +229,30 @@ pub fn load_checkpoint<P: ReadPolicy + Send + Sync>(
         canister_states
     };
 
-    let state = ReplicatedState::new_from_checkpoint(canister_states, metadata, subnet_queues);
+    let canister_snapshots = {
+        let _timer = metrics
+            .load_checkpoint_step_duration
+            .with_label_values(&["canister_snapshots"])
+            .start_timer();
+
+        let mut snapshots = BTreeMap::new();
+        for snapshot_id in checkpoint_layout.snapshot_ids()? {
+            let snapshot = load_snapshot_from_checkpoint(
+                checkpoint_layout,
+                &snapshot_id,
+                Arc::clone(&fd_factory),
+            )?;
+            snapshots.insert(snapshot_id, Arc::new(snapshot));
+        }
+        CanisterSnapshots::new(snapshots)
+    };
+
+    let state = ReplicatedState::new_from_checkpoint(
+        canister_states,
+        metadata,
+        subnet_queues,
+        canister_snapshots,
+    );
 
     Ok(state)
 }
@@ -265,8 +304,8 @@ pub fn load_canister_state<P: ReadPolicy>(
         Some(execution_state_bits) => {
             let starting_time = Instant::now();
             let wasm_memory = Memory::new(
-                PageMap::open(
-                    &canister_layout.vmemory_0(),
+                PageMap::open_with_overlays(
+                    &canister_layout.wasm_memory(),
                     height,
                     Arc::clone(&fd_factory),
                 )?,
@@ -276,8 +315,8 @@ pub fn load_canister_state<P: ReadPolicy>(
 
             let starting_time = Instant::now();
             let stable_memory = Memory::new(
-                PageMap::open(
-                    &canister_layout.stable_memory_blob(),
+                PageMap::open_with_overlays(
+                    &canister_layout.stable_memory(),
                     height,
                     Arc::clone(&fd_factory),
                 )?,
@@ -324,6 +363,21 @@ pub fn load_canister_state<P: ReadPolicy>(
             })?;
     durations.insert("canister_queues", starting_time.elapsed());
 
+    let starting_time = Instant::now();
+    let wasm_chunk_store_layout = canister_layout.wasm_chunk_store();
+    // Checkpoints written before the chunk store was backed by a page map
+    // don't have a file for it.
+    let wasm_chunk_store_data = if wasm_chunk_store_layout.base().exists() {
+        PageMap::open_with_overlays(&wasm_chunk_store_layout, height, Arc::clone(&fd_factory))?
+    } else {
+        PageMap::new(Arc::clone(&fd_factory))
+    };
+    let wasm_chunk_store = WasmChunkStore::from_checkpoint(
+        wasm_chunk_store_data,
+        canister_state_bits.wasm_chunk_store_metadata,
+    );
+    durations.insert("wasm_chunk_store", starting_time.elapsed());
+
     let canister_metrics = CanisterMetrics::new(
         canister_state_bits.scheduled_as_first,
         canister_state_bits.skipped_round_due_to_no_messages,
@@ -344,10 +398,19 @@ pub fn load_canister_state<P: ReadPolicy>(
         canister_state_bits.cycles_balance,
         canister_state_bits.cycles_debit,
         canister_state_bits.reserved_balance,
+        canister_state_bits.reserved_balance_limit,
         canister_state_bits.task_queue.into_iter().collect(),
         CanisterTimer::from_nanos_since_unix_epoch(canister_state_bits.global_timer_nanos),
         canister_state_bits.canister_version,
         canister_state_bits.canister_history,
+        canister_state_bits.next_snapshot_id,
+        canister_state_bits.log_visibility,
+        canister_state_bits.canister_log,
+        wasm_chunk_store,
+        canister_state_bits.wasm_memory_limit,
+        canister_state_bits.wasm_memory_threshold,
+        canister_state_bits.on_low_wasm_memory_hook_status,
+        canister_state_bits.total_query_stats,
     );
 
     let canister_state = CanisterState {
@@ -387,3 +450,52 @@ fn load_canister_state_from_checkpoint<P: ReadPolicy>(
         Arc::clone(&fd_factory),
     )
 }
+
+/// Loads the canister snapshot with the given id from the checkpoint.
+fn load_snapshot_from_checkpoint<P: ReadPolicy>(
+    checkpoint_layout: &CheckpointLayout<P>,
+    snapshot_id: &SnapshotId,
+    fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
+) -> Result<CanisterSnapshot, CheckpointError> {
+    let snapshot_layout = checkpoint_layout.snapshot(snapshot_id)?;
+    let snapshot_bits: CanisterSnapshotBits = CanisterSnapshotBits::try_from(
+        snapshot_layout.snapshot().deserialize()?,
+    )
+    .map_err(|err| CheckpointError::ProtoError {
+        path: snapshot_layout.raw_path(),
+        field: format!("snapshots[{}]::snapshot_bits", snapshot_id),
+        proto_err: err.to_string(),
+    })?;
+    let height = checkpoint_layout.height();
+
+    let wasm_memory = Memory::new(
+        PageMap::open_with_overlays(
+            &snapshot_layout.wasm_memory(),
+            height,
+            Arc::clone(&fd_factory),
+        )?,
+        snapshot_bits.wasm_memory_size,
+    );
+    let stable_memory = Memory::new(
+        PageMap::open_with_overlays(
+            &snapshot_layout.stable_memory(),
+            height,
+            Arc::clone(&fd_factory),
+        )?,
+        snapshot_bits.stable_memory_size,
+    );
+    let binary = snapshot_layout
+        .wasm()
+        .deserialize(Some(snapshot_bits.binary_hash))?;
+
+    Ok(CanisterSnapshot::new(
+        snapshot_bits.snapshot_id.get_canister_id(),
+        snapshot_bits.taken_at_timestamp,
+        snapshot_bits.canister_version,
+        snapshot_bits.certified_data,
+        binary,
+        snapshot_bits.exported_globals,
+        wasm_memory,
+        stable_memory,
+    ))
+}
@@ -1,6 +1,7 @@
 use super::*;
 use crate::{spawn_tip_thread, StateManagerMetrics, NUMBER_OF_CHECKPOINT_THREADS};
 use ic_base_types::NumSeconds;
+use ic_config::flag_status::FlagStatus;
 use ic_ic00_types::CanisterStatusType;
 use ic_metrics::MetricsRegistry;
 use ic_registry_subnet_type::SubnetType;
@@ -8,7 +9,8 @@ use ic_replicated_state::{
     canister_state::execution_state::{NextScheduledMethod, WasmBinary, WasmMetadata},
     page_map::{Buffer, TestPageAllocatorFileDescriptorImpl},
     testing::ReplicatedStateTesting,
-    CallContextManager, CanisterStatus, ExecutionState, ExportedFunctions, NumWasmPages, PageIndex,
+    CallContextManager, CanisterStatus, ExecutionState, ExportedFunctions, Global, NumWasmPages,
+    PageIndex,
 };
 use ic_state_layout::{
     StateLayout, CANISTER_FILE, CANISTER_STATES_DIR, CHECKPOINTS_DIR, SYSTEM_METADATA_FILE,
@@ -99,6 +101,7 @@ fn can_make_a_checkpoint() {
             log,
             tip_handler,
             layout.clone(),
+            FlagStatus::Disabled,
             state_manager_metrics(),
             MaliciousFlags::default(),
         );
@@ -163,6 +166,7 @@ fn scratchpad_dir_is_deleted_if_checkpointing_failed() {
             log,
             tip_handler,
             layout,
+            FlagStatus::Disabled,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
         );
@@ -213,6 +217,7 @@ fn can_recover_from_a_checkpoint() {
             log,
             tip_handler,
             layout.clone(),
+            FlagStatus::Disabled,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
         );
@@ -303,6 +308,93 @@ fn can_recover_from_a_checkpoint() {
     });
 }
 
+#[test]
+fn can_recover_canister_snapshots() {
+    with_test_replica_logger(|log| {
+        let tmp = tmpdir("checkpoint");
+        let root = tmp.path().to_path_buf();
+        let layout = StateLayout::try_new(log.clone(), root, &MetricsRegistry::new()).unwrap();
+        let tip_handler = layout.capture_tip_handler();
+        let state_manager_metrics = state_manager_metrics();
+        let (_tip_thread, tip_channel) = spawn_tip_thread(
+            log,
+            tip_handler,
+            layout.clone(),
+            FlagStatus::Disabled,
+            state_manager_metrics.clone(),
+            MaliciousFlags::default(),
+        );
+
+        const HEIGHT: Height = Height::new(42);
+        let canister_id: CanisterId = canister_test_id(10);
+
+        let mut canister_state = new_canister_state(
+            canister_id,
+            user_test_id(24).get(),
+            INITIAL_CYCLES,
+            NumSeconds::from(100_000),
+        );
+        canister_state.execution_state = Some(ExecutionState {
+            canister_root: "NOT_USED".into(),
+            session_nonce: None,
+            wasm_binary: WasmBinary::new(empty_wasm()),
+            wasm_memory: one_page_of(1),
+            stable_memory: one_page_of(2),
+            exported_globals: vec![Global::I64(7)],
+            exports: ExportedFunctions::new(BTreeSet::new()),
+            metadata: WasmMetadata::default(),
+            last_executed_round: ExecutionRound::from(0),
+            next_scheduled_method: NextScheduledMethod::default(),
+        });
+        canister_state.system_state.certified_data = b"certified".to_vec();
+
+        let own_subnet_type = SubnetType::Application;
+        let mut state = ReplicatedState::new(subnet_test_id(1), own_subnet_type);
+        let snapshot = CanisterSnapshot::from_canister(&canister_state, state.time()).unwrap();
+        let snapshot_id = SnapshotId::new(canister_id, 0);
+
+        // The canister changes after the snapshot was taken.
+        let execution_state = canister_state.execution_state.as_mut().unwrap();
+        execution_state.wasm_memory = one_page_of(3);
+        execution_state.stable_memory = one_page_of(4);
+        canister_state.system_state.certified_data = b"changed".to_vec();
+
+        state.put_canister_state(canister_state);
+        state
+            .canister_snapshots
+            .push(snapshot_id, Arc::new(snapshot.clone()));
+        state.update_snapshots_memory_usage(&canister_id);
+        let _state = make_checkpoint_and_get_state(&state, HEIGHT, &tip_channel);
+
+        let checkpoint_layout = layout.checkpoint(HEIGHT).unwrap();
+        let fd_factory = Arc::new(TestPageAllocatorFileDescriptorImpl::new());
+        let loaded_snapshot =
+            load_snapshot_from_checkpoint(&checkpoint_layout, &snapshot_id, fd_factory.clone())
+                .unwrap();
+        assert_eq!(loaded_snapshot, snapshot);
+
+        let recovered_state = load_checkpoint(
+            &checkpoint_layout,
+            own_subnet_type,
+            &state_manager_metrics.checkpoint_metrics,
+            Some(&mut thread_pool()),
+            fd_factory,
+        )
+        .unwrap();
+        assert_eq!(
+            recovered_state.canister_snapshots.get(&snapshot_id),
+            Some(&Arc::new(snapshot.clone()))
+        );
+        let canister = recovered_state.canister_state(&canister_id).unwrap();
+        assert_eq!(canister.snapshots_memory_usage(), snapshot.size());
+        assert_eq!(
+            canister.execution_state.as_ref().unwrap().wasm_memory,
+            one_page_of(3)
+        );
+        assert_eq!(canister.system_state.certified_data, b"changed".to_vec());
+    });
+}
+
 #[test]
 fn can_recover_an_empty_state() {
     with_test_replica_logger(|log| {
@@ -315,6 +407,7 @@ fn can_recover_an_empty_state() {
             log,
             tip_handler,
             layout.clone(),
+            FlagStatus::Disabled,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
         );
@@ -399,6 +492,7 @@ fn can_recover_a_stopping_canister() {
             log,
             tip_handler,
             layout.clone(),
+            FlagStatus::Disabled,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
         );
@@ -408,7 +502,7 @@ fn can_recover_a_stopping_canister() {
         let controller = user_test_id(24).get();
 
         let mut canister_state = CanisterState {
-            system_state: SystemState::new_stopping(
+            system_state: SystemState::new_stopping_for_testing(
                 canister_id,
                 controller,
                 INITIAL_CYCLES,
@@ -465,6 +559,7 @@ fn can_recover_a_stopped_canister() {
             log,
             tip_handler,
             layout.clone(),
+            FlagStatus::Disabled,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
         );
@@ -474,7 +569,7 @@ fn can_recover_a_stopped_canister() {
         let controller = user_test_id(24).get();
 
         let canister_state = CanisterState {
-            system_state: SystemState::new_stopped(
+            system_state: SystemState::new_stopped_for_testing(
                 canister_id,
                 controller,
                 INITIAL_CYCLES,
@@ -517,6 +612,7 @@ fn can_recover_a_running_canister() {
             log,
             tip_handler,
             layout.clone(),
+            FlagStatus::Disabled,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
         );
@@ -526,7 +622,7 @@ fn can_recover_a_running_canister() {
         let controller = user_test_id(24).get();
 
         let canister_state = CanisterState {
-            system_state: SystemState::new_running(
+            system_state: SystemState::new_running_for_testing(
                 canister_id,
                 controller,
                 INITIAL_CYCLES,
@@ -569,6 +665,7 @@ fn can_recover_subnet_queues() {
             log,
             tip_handler,
             layout.clone(),
+            FlagStatus::Disabled,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
         );
@@ -619,6 +716,7 @@ fn empty_protobufs_are_loaded_correctly() {
             log,
             tip_handler,
             layout.clone(),
+            FlagStatus::Disabled,
             state_manager_metrics.clone(),
             MaliciousFlags::default(),
         );
@@ -42,7 +42,9 @@ use ic_replicated_state::{
     canister_state::execution_state::SandboxMemory, page_map::PersistenceError, PageIndex, PageMap,
     ReplicatedState,
 };
-use ic_state_layout::{error::LayoutError, AccessPolicy, CheckpointLayout, ReadOnly, StateLayout};
+use ic_state_layout::{
+    error::LayoutError, AccessPolicy, CheckpointLayout, PageMapLayout, ReadOnly, StateLayout,
+};
 use ic_types::{
     consensus::certification::Certification,
     crypto::CryptoHash,
@@ -72,6 +74,7 @@ use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 use std::os::unix::io::RawFd;
 use std::os::unix::prelude::IntoRawFd;
 use tempfile::tempfile;
+use tokio::sync::watch;
 use uuid::Uuid;
 
 /// The number of threads that state manager starts to construct checkpoints.
@@ -720,6 +723,9 @@ pub struct StateManagerImpl {
     // requested quite often and this causes high contention on the lock.
     latest_state_height: AtomicU64,
     latest_certified_height: AtomicU64,
+    // Notifies subscribers (e.g. the HTTP endpoints) whenever the latest
+    // certified height increases, so that they don't need to poll for it.
+    certified_height_sender: watch::Sender<Height>,
     _deallocation_handle: JoinOnDrop<()>,
     persist_metadata_guard: Arc<Mutex<()>>,
     tip_channel: Sender<TipRequest>,
@@ -969,6 +975,7 @@ struct PopulatedMetadata {
 pub enum PageMapType {
     WasmMemory(CanisterId),
     StableMemory(CanisterId),
+    WasmChunkStore(CanisterId),
 }
 
 impl PageMapType {
@@ -980,6 +987,7 @@ impl PageMapType {
                 result.push(Self::WasmMemory(id.to_owned()));
                 result.push(Self::StableMemory(id.to_owned()));
             }
+            result.push(Self::WasmChunkStore(id.to_owned()));
         }
 
         result
@@ -993,6 +1001,23 @@ impl PageMapType {
         match &self {
             PageMapType::WasmMemory(id) => Ok(layout.canister(id)?.vmemory_0()),
             PageMapType::StableMemory(id) => Ok(layout.canister(id)?.stable_memory_blob()),
+            PageMapType::WasmChunkStore(id) => Ok(layout.canister(id)?.wasm_chunk_store().base()),
+        }
+    }
+
+    /// Maps a PageMapType to the layout of its base and overlay files in a
+    /// checkpoint according to `layout`
+    fn layout<Access>(
+        &self,
+        layout: &CheckpointLayout<Access>,
+    ) -> Result<PageMapLayout<Access>, LayoutError>
+    where
+        Access: AccessPolicy,
+    {
+        match &self {
+            PageMapType::WasmMemory(id) => Ok(layout.canister(id)?.wasm_memory()),
+            PageMapType::StableMemory(id) => Ok(layout.canister(id)?.stable_memory()),
+            PageMapType::WasmChunkStore(id) => Ok(layout.canister(id)?.wasm_chunk_store()),
         }
     }
 
@@ -1009,6 +1034,9 @@ impl PageMapType {
                     .as_ref()
                     .map(|ex| &ex.stable_memory.page_map)
             }),
+            PageMapType::WasmChunkStore(id) => state
+                .canister_state(id)
+                .map(|can| can.system_state.wasm_chunk_store.page_map()),
         }
     }
 
@@ -1025,6 +1053,9 @@ impl PageMapType {
                     .as_mut()
                     .map(|ex| &mut ex.stable_memory.page_map)
             }),
+            PageMapType::WasmChunkStore(id) => state
+                .canister_state_mut(id)
+                .map(|can| can.system_state.wasm_chunk_store.page_map_mut()),
         }
     }
 }
@@ -1186,6 +1217,20 @@ fn switch_to_checkpoint(tip: &mut ReplicatedState, src: &ReplicatedState) {
             tip_state.stable_memory.sandbox_memory = SandboxMemory::new();
         }
     }
+
+    // Snapshots are immutable, so the checkpointed ones, which are backed by
+    // files instead of in-memory page deltas, can replace those in `tip`.
+    assert_eq!(
+        tip.canister_snapshots
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>(),
+        src.canister_snapshots
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>(),
+    );
+    tip.canister_snapshots = src.canister_snapshots.clone();
 }
 
 /// Persists metadata after releasing the write lock
@@ -1328,6 +1373,7 @@ impl StateManagerImpl {
             log.clone(),
             state_layout.capture_tip_handler(),
             state_layout.clone(),
+            config.lsmt_storage,
             metrics.clone(),
             malicious_flags.clone(),
         );
@@ -1449,6 +1495,7 @@ impl StateManagerImpl {
 
         let latest_state_height = AtomicU64::new(0);
         let latest_certified_height = AtomicU64::new(0);
+        let (certified_height_sender, _) = watch::channel(Self::INITIAL_STATE_HEIGHT);
 
         let initial_snapshot = Snapshot {
             height: Self::INITIAL_STATE_HEIGHT,
@@ -1544,6 +1591,7 @@ impl StateManagerImpl {
             deallocation_sender,
             latest_state_height,
             latest_certified_height,
+            certified_height_sender,
             _deallocation_handle,
             persist_metadata_guard,
             tip_channel,
@@ -1553,6 +1601,12 @@ impl StateManagerImpl {
             latest_height_update_time: Arc::new(Mutex::new(Instant::now())),
         }
     }
+    /// Returns a receiver that observes the latest certified height. It is
+    /// notified every time a certification for a higher height is delivered.
+    pub fn certified_height_watcher(&self) -> watch::Receiver<Height> {
+        self.certified_height_sender.subscribe()
+    }
+
     /// Returns the Page Allocator file descriptor factory. This will then be
     /// used down the line in hypervisor and state to pass to the page allocators
     /// that are instantiated by the page maps
@@ -1694,6 +1748,21 @@ impl StateManagerImpl {
             })
     }
 
+    /// Returns the manifests of all checkpoints on disk with their checkpoint
+    /// layouts, ordered by height.
+    fn checkpoint_manifests(&self) -> Vec<(Manifest, CheckpointLayout<ReadOnly>)> {
+        let states = self.states.read();
+        self.checkpoint_heights()
+            .iter()
+            .filter_map(|checkpointed_height| {
+                let metadata = states.states_metadata.get(checkpointed_height)?;
+                let manifest = metadata.manifest()?.clone();
+                let checkpoint_layout = metadata.checkpoint_layout.clone()?;
+                Some((manifest, checkpoint_layout))
+            })
+            .collect()
+    }
+
     fn compute_certification_metadata(
         metrics: &StateManagerMetrics,
         log: &ReplicaLogger,
@@ -2334,10 +2403,16 @@ impl StateManagerImpl {
                 states: self.states.clone(),
                 persist_metadata_guard: self.persist_metadata_guard.clone(),
             },
-            tip_requests: vec![TipRequest::DefragTip {
-                height,
-                page_map_types: PageMapType::list_all(state),
-            }],
+            tip_requests: vec![
+                TipRequest::MergeOverlays {
+                    height,
+                    page_map_types: PageMapType::list_all(state),
+                },
+                TipRequest::DefragTip {
+                    height,
+                    page_map_types: PageMapType::list_all(state),
+                },
+            ],
         }
     }
 
@@ -2684,6 +2759,13 @@ impl StateManager for StateManagerImpl {
             self.metrics
                 .latest_certified_height
                 .set(latest_certified as i64);
+            self.certified_height_sender.send_if_modified(|height| {
+                let modified = height.get() < latest_certified;
+                if modified {
+                    *height = Height::new(latest_certified);
+                }
+                modified
+            });
 
             metadata.certification = Some(certification);
 
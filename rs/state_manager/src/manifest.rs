@@ -21,7 +21,7 @@ use ic_crypto_sha::Sha256;
 use ic_logger::{error, fatal, replica_logger::no_op_logger, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_replicated_state::PageIndex;
-use ic_state_layout::{CheckpointLayout, ReadOnly, CANISTER_FILE};
+use ic_state_layout::{CheckpointLayout, PageMapLayout, ReadOnly, CANISTER_FILE};
 use ic_sys::{mmap::ScopedMmap, PAGE_SIZE};
 use ic_types::{
     crypto::CryptoHash,
@@ -765,6 +765,37 @@ fn dirty_chunks_of_file(
     }
 }
 
+/// Returns whether `manifest` contains any overlay file of the page map with
+/// the given layout.
+fn has_overlays_in_manifest(
+    manifest: &Manifest,
+    checkpoint: &CheckpointLayout<ReadOnly>,
+    layout: &PageMapLayout<ReadOnly>,
+) -> bool {
+    let base = layout.base();
+    let dir = match base
+        .strip_prefix(checkpoint.raw_path())
+        .ok()
+        .and_then(Path::parent)
+    {
+        Some(dir) => dir,
+        None => return false,
+    };
+    // The file table is sorted by path, so all files of the directory are
+    // adjacent.
+    let start = manifest
+        .file_table
+        .partition_point(|file_info| file_info.relative_path.as_path() < dir);
+    manifest.file_table[start..]
+        .iter()
+        .take_while(|file_info| file_info.relative_path.starts_with(dir))
+        .any(|file_info| {
+            layout
+                .overlay_height(&checkpoint.raw_path().join(&file_info.relative_path))
+                .is_some()
+        })
+}
+
 /// Computes the bitmap of chunks modified since the base state.
 fn dirty_pages_to_dirty_chunks(
     manifest_delta: &ManifestDelta,
@@ -785,6 +816,12 @@ fn dirty_pages_to_dirty_chunks(
         "chunk size must be a multiple of page size for incremental computation to work correctly"
     );
 
+    let relative_path = |path: &Path| -> PathBuf {
+        path.strip_prefix(checkpoint.raw_path())
+            .expect("failed to strip path prefix")
+            .to_path_buf()
+    };
+
     let mut dirty_chunks: BTreeMap<PathBuf, BitVec> = Default::default();
     for dirty_page in &manifest_delta.dirty_memory_pages {
         if dirty_page.height != manifest_delta.base_height {
@@ -792,7 +829,42 @@ fn dirty_pages_to_dirty_chunks(
         }
 
         let path = match dirty_page.file_type {
-            FileType::PageMap(page_type) => page_type.path(checkpoint),
+            FileType::PageMap(page_type) => {
+                let layout = match page_type.layout(checkpoint) {
+                    Ok(layout) => layout,
+                    Err(_) => continue,
+                };
+                let overlays = layout.existing_overlays()?;
+                if !overlays.is_empty() {
+                    // The page map was persisted as overlays, so its base file
+                    // is unchanged and so are all the overlays that already
+                    // existed in the base checkpoint, as overlays are immutable.
+                    let unchanged_files = std::iter::once(layout.base()).chain(
+                        overlays.into_iter().filter(|overlay| {
+                            layout.overlay_height(overlay) <= Some(manifest_delta.base_height)
+                        }),
+                    );
+                    for path in unchanged_files {
+                        let relative_path = relative_path(&path);
+                        if let Some(chunks_bitmap) = dirty_chunks_of_file(
+                            &relative_path,
+                            &[],
+                            files,
+                            max_chunk_size,
+                            &manifest_delta.base_manifest,
+                        ) {
+                            dirty_chunks.insert(relative_path, chunks_bitmap);
+                        }
+                    }
+                    continue;
+                }
+                if has_overlays_in_manifest(&manifest_delta.base_manifest, checkpoint, &layout) {
+                    // The overlays of the base checkpoint were merged into the
+                    // base file, so the dirty pages do not cover all changes.
+                    continue;
+                }
+                Ok(layout.base())
+            }
             FileType::WasmBinary(canister_id) => {
                 assert!(dirty_page.page_delta_indices.is_empty());
 
@@ -803,18 +875,16 @@ fn dirty_pages_to_dirty_chunks(
         };
 
         if let Ok(path) = path {
-            let relative_path = path
-                .strip_prefix(checkpoint.raw_path())
-                .expect("failed to strip path prefix");
+            let relative_path = relative_path(&path);
 
             if let Some(chunks_bitmap) = dirty_chunks_of_file(
-                relative_path,
+                &relative_path,
                 &dirty_page.page_delta_indices,
                 files,
                 max_chunk_size,
                 &manifest_delta.base_manifest,
             ) {
-                dirty_chunks.insert(relative_path.to_path_buf(), chunks_bitmap);
+                dirty_chunks.insert(relative_path, chunks_bitmap);
             }
         }
     }
@@ -1328,6 +1398,32 @@ pub fn diff_manifest(
     }
 }
 
+/// Finds the chunks among `chunks` of `manifest_new` that have the same hash
+/// as some chunk of `manifest_old` and can thus be copied from the old state.
+/// Keys are indices of the chunk table in the new manifest file, values are
+/// indices of the chunk table in the old manifest file.
+pub(crate) fn find_chunks_by_hash(
+    manifest_old: &Manifest,
+    manifest_new: &Manifest,
+    chunks: &HashSet<NewIndex>,
+) -> HashMap<NewIndex, OldIndex> {
+    let chunk_hash_to_index: HashMap<[u8; 32], OldIndex> = manifest_old
+        .chunk_table
+        .iter()
+        .enumerate()
+        .map(|(chunk_index, chunk_info)| (chunk_info.hash, chunk_index))
+        .collect();
+
+    chunks
+        .iter()
+        .filter_map(|chunk_index| {
+            let chunk_info = manifest_new.chunk_table.get(*chunk_index)?;
+            let old_index = chunk_hash_to_index.get(&chunk_info.hash)?;
+            Some((*chunk_index, *old_index))
+        })
+        .collect()
+}
+
 /// Filters out all-zero chunks in the manifest chunk table and returns the set
 /// of remaining chunks indices.
 pub fn filter_out_zero_chunks(manifest: &Manifest) -> HashSet<usize> {
@@ -1,10 +1,10 @@
 use crate::manifest::validate_manifest_internal_consistency;
 use crate::manifest::{
     build_file_group_chunks, build_meta_manifest, compute_manifest, diff_manifest,
-    file_chunk_range, filter_out_zero_chunks, hash::ManifestHash, manifest_hash, manifest_hash_v1,
-    manifest_hash_v2, meta_manifest_hash, validate_chunk, validate_manifest,
-    validate_meta_manifest, validate_sub_manifest, ChunkValidationError, DiffScript,
-    ManifestMetrics, ManifestValidationError, StateSyncVersion, DEFAULT_CHUNK_SIZE,
+    file_chunk_range, filter_out_zero_chunks, find_chunks_by_hash, hash::ManifestHash,
+    manifest_hash, manifest_hash_v1, manifest_hash_v2, meta_manifest_hash, validate_chunk,
+    validate_manifest, validate_meta_manifest, validate_sub_manifest, ChunkValidationError,
+    DiffScript, ManifestMetrics, ManifestValidationError, StateSyncVersion, DEFAULT_CHUNK_SIZE,
     MAX_FILE_SIZE_TO_GROUP,
 };
 
@@ -676,6 +676,33 @@ fn test_diff_manifest_v1() {
     );
 }
 
+#[test]
+fn test_find_chunks_by_hash() {
+    let (_, manifest_old) = simple_manifest_v1();
+
+    // Chunk 0 now has the same contents as chunk 3, chunk 4 is new.
+    let mut chunk_table = manifest_old.chunk_table.to_owned();
+    chunk_table[0].hash = chunk_table[3].hash;
+    chunk_table[4].hash = hash_concat!(14u8, b"ic-state-chunk", vec![255u8; 26].as_slice());
+    let manifest_new = Manifest::new(
+        manifest_old.version,
+        manifest_old.file_table.to_owned(),
+        chunk_table,
+    );
+
+    assert_eq!(
+        find_chunks_by_hash(&manifest_old, &manifest_new, &maplit::hashset! {0, 3, 4}),
+        maplit::hashmap! {
+            0 => 3,
+            3 => 3,
+        }
+    );
+    assert_eq!(
+        find_chunks_by_hash(&manifest_old, &manifest_new, &HashSet::new()),
+        HashMap::new()
+    );
+}
+
 #[test]
 fn test_diff_manifest() {
     let metrics_registry = MetricsRegistry::new();
@@ -6,7 +6,7 @@ use crate::{
 };
 
 use ic_base_types::CanisterId;
-use ic_config::state_manager::Config;
+use ic_config::{flag_status::FlagStatus, state_manager::Config};
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
 use ic_registry_routing_table::{
@@ -178,6 +178,7 @@ fn write_checkpoint(
         log,
         tip_handler,
         state_layout,
+        FlagStatus::Disabled,
         metrics.clone(),
         MaliciousFlags::default(),
     );
@@ -266,6 +266,7 @@ fn new_state_layout(log: ReplicaLogger) -> TempDir {
         log,
         tip_handler,
         layout.clone(),
+        FlagStatus::Disabled,
         state_manager_metrics.clone(),
         MaliciousFlags::default(),
     );
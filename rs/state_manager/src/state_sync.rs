@@ -55,6 +55,7 @@ impl StateSync {
             id.hash.clone(),
             self.state_manager.state_layout.clone(),
             self.state_manager.latest_manifest(),
+            self.state_manager.checkpoint_manifests(),
             self.state_manager.metrics.clone(),
             self.state_manager.own_subnet_type,
             Arc::new(Mutex::new(scoped_threadpool::Pool::new(
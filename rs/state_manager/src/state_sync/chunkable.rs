@@ -1,5 +1,5 @@
 use crate::{
-    manifest::{build_file_group_chunks, filter_out_zero_chunks, DiffScript},
+    manifest::{build_file_group_chunks, filter_out_zero_chunks, find_chunks_by_hash, DiffScript},
     StateManagerMetrics, StateSyncMetrics, StateSyncRefs,
     CRITICAL_ERROR_STATE_SYNC_CORRUPTED_CHUNKS, LABEL_COPY_CHUNKS, LABEL_COPY_FILES, LABEL_FETCH,
     LABEL_PREALLOCATE, LABEL_STATE_SYNC_MAKE_CHECKPOINT,
@@ -90,6 +90,11 @@ pub struct IncompleteState {
     root_hash: CryptoHashOfState,
     state: DownloadState,
     manifest_with_checkpoint_layout: Option<(Manifest, CheckpointLayout<ReadOnly>)>,
+    /// All local checkpoints with their manifests. Chunks that can not be
+    /// copied from the cache or the latest checkpoint are looked up in these
+    /// by hash before they are fetched from peers. Only used until the state
+    /// on disk is initialized.
+    local_checkpoints: Vec<(Manifest, CheckpointLayout<ReadOnly>)>,
     metrics: StateManagerMetrics,
     started_at: Instant,
     fetch_started_at: Option<Instant>,
@@ -188,6 +193,7 @@ impl IncompleteState {
         root_hash: CryptoHashOfState,
         state_layout: StateLayout,
         manifest_with_checkpoint_layout: Option<(Manifest, CheckpointLayout<ReadOnly>)>,
+        local_checkpoints: Vec<(Manifest, CheckpointLayout<ReadOnly>)>,
         metrics: StateManagerMetrics,
         own_subnet_type: SubnetType,
         thread_pool: Arc<Mutex<scoped_threadpool::Pool>>,
@@ -213,6 +219,7 @@ impl IncompleteState {
             root_hash,
             state: DownloadState::Blank,
             manifest_with_checkpoint_layout,
+            local_checkpoints,
             metrics,
             started_at: Instant::now(),
             fetch_started_at: None,
@@ -907,6 +914,10 @@ impl IncompleteState {
     fn initialize_state_on_disk(&mut self, manifest_new: &Manifest) -> HashSet<usize> {
         Self::preallocate_layout(&self.log, &self.root, manifest_new);
 
+        // The local checkpoints are only needed here, release them so that
+        // they can be removed while the state sync is ongoing.
+        let local_checkpoints = std::mem::take(&mut self.local_checkpoints);
+
         let state_sync_size_fetch = self
             .metrics
             .state_sync_metrics
@@ -1042,11 +1053,16 @@ impl IncompleteState {
                 .map(|i| *i + FILE_CHUNK_ID_OFFSET)
                 .collect();
 
-            let diff_bytes: u64 = diff_script
-                .fetch_chunks
-                .iter()
-                .map(|i| manifest_new.chunk_table[*i].size_bytes as u64)
-                .sum();
+            let mut thread_pool = self.thread_pool.lock().unwrap();
+            self.copy_chunks_from_local_checkpoints(
+                &local_checkpoints,
+                &mut thread_pool,
+                Some(root_old.as_path()),
+                manifest_new,
+                &mut fetch_chunks,
+            );
+
+            let diff_bytes = Self::chunks_size_bytes(manifest_new, &fetch_chunks);
 
             let preallocate_bytes: u64 =
                 (diff_script.zeros_chunks * crate::manifest::DEFAULT_CHUNK_SIZE) as u64;
@@ -1070,7 +1086,6 @@ impl IncompleteState {
                 .remaining
                 .sub(diff_script.zeros_chunks as i64);
 
-            let mut thread_pool = self.thread_pool.lock().unwrap();
             Self::copy_files(
                 &self.log,
                 &self.metrics.state_sync_metrics,
@@ -1105,13 +1120,6 @@ impl IncompleteState {
                 self.height
             );
             let non_zero_chunks = filter_out_zero_chunks(manifest_new);
-            let diff_bytes: u64 = non_zero_chunks
-                .iter()
-                .map(|i| manifest_new.chunk_table[*i].size_bytes as u64)
-                .sum();
-            state_sync_size_fetch.inc_by(diff_bytes);
-            state_sync_size_preallocate.inc_by(total_bytes - diff_bytes);
-
             let zeros_chunks = manifest_new.chunk_table.len() - non_zero_chunks.len();
 
             self.metrics
@@ -1119,10 +1127,96 @@ impl IncompleteState {
                 .remaining
                 .sub(zeros_chunks as i64);
 
-            non_zero_chunks
+            let mut fetch_chunks: HashSet<usize> = non_zero_chunks
                 .iter()
                 .map(|i| *i + FILE_CHUNK_ID_OFFSET)
-                .collect()
+                .collect();
+            let non_zero_bytes = Self::chunks_size_bytes(manifest_new, &fetch_chunks);
+
+            self.copy_chunks_from_local_checkpoints(
+                &local_checkpoints,
+                &mut self.thread_pool.lock().unwrap(),
+                None,
+                manifest_new,
+                &mut fetch_chunks,
+            );
+
+            let diff_bytes = Self::chunks_size_bytes(manifest_new, &fetch_chunks);
+            state_sync_size_fetch.inc_by(diff_bytes);
+            state_sync_size_copy_chunks.inc_by(non_zero_bytes - diff_bytes);
+            state_sync_size_preallocate.inc_by(total_bytes - non_zero_bytes);
+
+            fetch_chunks
+        }
+    }
+
+    /// Returns the total size of the chunks with the given ids.
+    fn chunks_size_bytes(manifest: &Manifest, chunk_ids: &HashSet<usize>) -> u64 {
+        chunk_ids
+            .iter()
+            .map(|id| manifest.chunk_table[*id - FILE_CHUNK_ID_OFFSET].size_bytes as u64)
+            .sum()
+    }
+
+    /// Copies chunks in `fetch_chunks` from any of the `local_checkpoints` that has a
+    /// chunk with the same hash, except the checkpoint at `root_old` that the
+    /// state was already initialized from. Copied chunks are removed from
+    /// `fetch_chunks`.
+    ///
+    /// The copied chunks are always validated against the new manifest, so
+    /// chunks that do not match are fetched from peers instead.
+    fn copy_chunks_from_local_checkpoints(
+        &self,
+        local_checkpoints: &[(Manifest, CheckpointLayout<ReadOnly>)],
+        thread_pool: &mut scoped_threadpool::Pool,
+        root_old: Option<&Path>,
+        manifest_new: &Manifest,
+        fetch_chunks: &mut HashSet<usize>,
+    ) {
+        for (manifest_old, checkpoint_layout) in local_checkpoints {
+            if fetch_chunks.is_empty() {
+                break;
+            }
+            if root_old == Some(checkpoint_layout.raw_path()) {
+                continue;
+            }
+
+            let missing_chunks: HashSet<usize> = fetch_chunks
+                .iter()
+                .map(|id| *id - FILE_CHUNK_ID_OFFSET)
+                .collect();
+            let copy_chunks = find_chunks_by_hash(manifest_old, manifest_new, &missing_chunks);
+            if copy_chunks.is_empty() {
+                continue;
+            }
+            info!(
+                self.log,
+                "state sync: found {} chunks in local checkpoint at height {}",
+                copy_chunks.len(),
+                checkpoint_layout.height()
+            );
+            for new_index in copy_chunks.keys() {
+                fetch_chunks.remove(&(*new_index + FILE_CHUNK_ID_OFFSET));
+            }
+
+            let diff_script = DiffScript {
+                copy_files: Default::default(),
+                copy_chunks,
+                fetch_chunks: Default::default(),
+                zeros_chunks: 0,
+            };
+            Self::copy_chunks(
+                &self.log,
+                &self.metrics.state_sync_metrics,
+                thread_pool,
+                checkpoint_layout.raw_path(),
+                &self.root,
+                manifest_old,
+                manifest_new,
+                &diff_script,
+                true,
+                fetch_chunks,
+            );
         }
     }
 }
@@ -120,6 +120,7 @@ fn incomplete_state_for_tests(
         hash,
         env.state_layout.clone(),
         None,
+        Vec::new(),
         env.metrics.clone(),
         SubnetType::Application,
         Arc::new(Mutex::new(scoped_threadpool::Pool::new(NUM_THREADS))),
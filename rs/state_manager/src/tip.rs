@@ -5,16 +5,18 @@ use crate::{
 };
 use crossbeam_channel::{unbounded, Sender};
 use ic_base_types::subnet_id_into_protobuf;
+use ic_config::flag_status::FlagStatus;
+use ic_ic00_types::SnapshotId;
 use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_protobuf::state::system_metadata::v1::{SplitFrom, SystemMetadata};
 #[allow(unused)]
 use ic_replicated_state::{
-    canister_state::execution_state::SandboxMemory, CanisterState, NumWasmPages, PageMap,
-    ReplicatedState,
+    canister_state::execution_state::SandboxMemory, page_map::MergeCandidate, CanisterSnapshot,
+    CanisterState, NumWasmPages, PageMap, ReplicatedState,
 };
 use ic_state_layout::{
-    error::LayoutError, CanisterStateBits, CheckpointLayout, ExecutionStateBits, ReadOnly,
-    RwPolicy, StateLayout, TipHandler,
+    error::LayoutError, CanisterSnapshotBits, CanisterStateBits, CheckpointLayout,
+    ExecutionStateBits, PageMapLayout, ReadOnly, RwPolicy, StateLayout, TipHandler,
 };
 use ic_types::state_sync::{
     FILE_GROUP_CHUNK_ID_OFFSET, MANIFEST_CHUNK_ID_OFFSET, MAX_SUPPORTED_STATE_SYNC_VERSION,
@@ -64,6 +66,12 @@ pub(crate) enum TipRequest {
         height: Height,
         ids: BTreeSet<CanisterId>,
     },
+    /// Filter canister snapshots in tip. Remove ones not present in the set.
+    /// State: !Empty
+    FilterTipSnapshots {
+        height: Height,
+        ids: BTreeSet<SnapshotId>,
+    },
     /// Truncate PageMaps's path.
     /// State: ReadyForPageDeltas(h) -> ReadyForPageDeltas(height), height >= h
     TruncatePageMapsPath {
@@ -87,6 +95,13 @@ pub(crate) enum TipRequest {
         height: Height,
         replicated_state: Box<ReplicatedState>,
     },
+    /// Merge the overlays of the given PageMaps in the tip once the
+    /// checkpoint @height is created, so that merging doesn't delay it.
+    /// State: ReadyForPageDeltas(h) -> ReadyForPageDeltas(height), height >= h
+    MergeOverlays {
+        height: Height,
+        page_map_types: Vec<PageMapType>,
+    },
     /// Run one round of tip defragmentation.
     /// State: ReadyForPageDeltas(h) -> ReadyForPageDeltas(height), height >= h
     DefragTip {
@@ -114,18 +129,18 @@ fn request_timer(metrics: &StateManagerMetrics, name: &str) -> HistogramTimer {
         .start_timer()
 }
 
-fn page_map_path(
+fn page_map_layout<'a>(
     log: &ReplicaLogger,
-    tip_handler: &mut TipHandler,
+    tip_handler: &'a mut TipHandler,
     height: Height,
     page_map_type: &PageMapType,
-) -> PathBuf {
+) -> PageMapLayout<RwPolicy<'a, TipHandler>> {
     page_map_type
-        .path(&tip_handler.tip(height).unwrap_or_else(|err| {
+        .layout(&tip_handler.tip(height).unwrap_or_else(|err| {
             fatal!(log, "Failed to flush page map: {}", err);
         }))
         .unwrap_or_else(|err| {
-            fatal!(log, "Failed to get path for page map: {}", err);
+            fatal!(log, "Failed to get layout for page map: {}", err);
         })
 }
 
@@ -133,6 +148,7 @@ pub(crate) fn spawn_tip_thread(
     log: ReplicaLogger,
     mut tip_handler: TipHandler,
     state_layout: StateLayout,
+    lsmt_storage: FlagStatus,
     metrics: StateManagerMetrics,
     malicious_flags: MaliciousFlags,
 ) -> (JoinOnDrop<()>, Sender<TipRequest>) {
@@ -163,6 +179,21 @@ pub(crate) fn spawn_tip_thread(
                                     )
                                 });
                         }
+                        TipRequest::FilterTipSnapshots { height, ids } => {
+                            debug_assert_ne!(tip_state, TipState::Empty);
+
+                            let _timer = request_timer(&metrics, "filter_tip_snapshots");
+                            tip_handler
+                                .filter_tip_snapshots(height, &ids)
+                                .unwrap_or_else(|err| {
+                                    fatal!(
+                                        log,
+                                        "Failed to filter tip snapshots for height @{}: {}",
+                                        height,
+                                        err
+                                    )
+                                });
+                        }
                         TipRequest::TipToCheckpoint { height, sender } => {
                             debug_assert_eq!(tip_state, TipState::Serialized(height));
                             debug_assert!(have_latest_manifest);
@@ -225,9 +256,12 @@ pub(crate) fn spawn_tip_thread(
                             }
                             tip_state = TipState::ReadyForPageDeltas(height);
                             let _timer = request_timer(&metrics, "truncate_page_maps_path");
-                            let path =
-                                page_map_path(&log, &mut tip_handler, height, &page_map_type);
-                            truncate_path(&log, &path);
+                            let layout =
+                                page_map_layout(&log, &mut tip_handler, height, &page_map_type);
+                            truncate_path(&log, &layout.base());
+                            layout.delete_overlays().unwrap_or_else(|err| {
+                                fatal!(log, "Failed to delete overlays: {}", err);
+                            });
                         }
 
                         TipRequest::FlushPageMapDelta {
@@ -242,11 +276,10 @@ pub(crate) fn spawn_tip_thread(
                                 _ => panic!("Unexpected tip state: {:?}", tip_state),
                             }
                             tip_state = TipState::ReadyForPageDeltas(height);
-                            let path =
-                                page_map_path(&log, &mut tip_handler, height, &page_map_type);
+                            let layout =
+                                page_map_layout(&log, &mut tip_handler, height, &page_map_type);
                             if !page_map.unflushed_delta_is_empty() {
-                                page_map
-                                    .persist_unflushed_delta(&path)
+                                persist_unflushed_delta(&page_map, &layout, height, lsmt_storage)
                                     .unwrap_or_else(|err| {
                                         fatal!(log, "Failed to persist unflushed delta: {}", err);
                                     });
@@ -274,6 +307,7 @@ pub(crate) fn spawn_tip_thread(
                                         err
                                     );
                                 }),
+                                lsmt_storage,
                                 &mut thread_pool,
                             )
                             .unwrap_or_else(|err| {
@@ -298,6 +332,37 @@ pub(crate) fn spawn_tip_thread(
                                     );
                                 });
                         }
+                        TipRequest::MergeOverlays {
+                            height,
+                            page_map_types,
+                        } => {
+                            debug_assert_ne!(tip_state, TipState::Empty);
+                            tip_state = TipState::ReadyForPageDeltas(height);
+                            if lsmt_storage == FlagStatus::Enabled {
+                                let _timer = request_timer(&metrics, "merge_overlays");
+                                merge_overlays(
+                                    &tip_handler.tip(height).unwrap_or_else(|err| {
+                                        fatal!(
+                                            log,
+                                            "Failed to get tip @{} to merge overlays: {}",
+                                            height,
+                                            err
+                                        );
+                                    }),
+                                    &page_map_types,
+                                    height,
+                                    &mut thread_pool,
+                                )
+                                .unwrap_or_else(|err| {
+                                    fatal!(
+                                        log,
+                                        "Failed to merge overlays in tip @{}: {}",
+                                        height,
+                                        err
+                                    );
+                                });
+                            }
+                        }
                         TipRequest::DefragTip {
                             height,
                             page_map_types,
@@ -351,10 +416,99 @@ pub(crate) fn spawn_tip_thread(
     (tip_handle, tip_sender)
 }
 
+/// Persists the unflushed delta of `page_map` to the files in `layout`.
+///
+/// With LSMT storage enabled, the delta is written as a new overlay file.
+/// Otherwise it is written to the base file, after merging any overlays left
+/// over from a time when LSMT storage was enabled into it.
+fn persist_unflushed_delta(
+    page_map: &PageMap,
+    layout: &PageMapLayout<RwPolicy<TipHandler>>,
+    height: Height,
+    lsmt_storage: FlagStatus,
+) -> Result<(), CheckpointError> {
+    if lsmt_storage == FlagStatus::Enabled {
+        page_map.persist_unflushed_delta_to_overlay(&layout.overlay(height))?;
+    } else if layout.existing_overlays()?.is_empty() {
+        page_map.persist_unflushed_delta(&layout.base())?;
+    } else {
+        page_map.persist_all(&layout.base())?;
+        layout.delete_overlays()?;
+    }
+    Ok(())
+}
+
+/// Persists all changes of `page_map` since the last checkpoint to the files
+/// in `layout`.
+///
+/// With LSMT storage enabled, the changes are written as an overlay file
+/// (unless they were already flushed as overlays). The overlays are merged
+/// later by a `MergeOverlays` request. Otherwise the changes are written to
+/// the base file.
+fn persist_page_map(
+    page_map: &PageMap,
+    layout: &PageMapLayout<RwPolicy<TipHandler>>,
+    height: Height,
+    lsmt_storage: FlagStatus,
+) -> Result<(), CheckpointError> {
+    if lsmt_storage == FlagStatus::Enabled {
+        if !page_map.unflushed_delta_is_empty() {
+            page_map.persist_unflushed_delta_to_overlay(&layout.overlay(height))?;
+        }
+        let base = layout.base();
+        if !base.exists() {
+            // Page maps without a base file are written as overlays only, but
+            // loading them requires the base file to exist.
+            open_for_write(&base)?;
+        }
+    } else if layout.existing_overlays()?.is_empty() {
+        page_map.persist_delta(&layout.base())?;
+    } else {
+        page_map.persist_all(&layout.base())?;
+        layout.delete_overlays()?;
+    }
+    Ok(())
+}
+
+/// Merges the overlays of the given page maps in `tip` where needed to keep
+/// their number logarithmic in the size of the page map.
+fn merge_overlays(
+    tip: &CheckpointLayout<RwPolicy<TipHandler>>,
+    page_map_types: &[PageMapType],
+    height: Height,
+    thread_pool: &mut scoped_threadpool::Pool,
+) -> Result<(), CheckpointError> {
+    let results = parallel_map(thread_pool, page_map_types.iter(), |page_map_type| {
+        let layout = page_map_type.layout(tip)?;
+        if let Some(merge) = MergeCandidate::new(&layout, height)? {
+            merge.apply()?;
+        }
+        Ok::<(), CheckpointError>(())
+    });
+
+    for result in results.into_iter() {
+        result?;
+    }
+    Ok(())
+}
+
+fn open_for_write(path: &Path) -> Result<std::fs::File, CheckpointError> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .open(path)
+        .map_err(|err| CheckpointError::IoError {
+            path: path.to_path_buf(),
+            message: "failed to create base file".to_string(),
+            io_err: err.to_string(),
+        })
+}
+
 fn serialize_to_tip(
     log: &ReplicaLogger,
     state: &ReplicatedState,
     tip: &CheckpointLayout<RwPolicy<TipHandler>>,
+    lsmt_storage: FlagStatus,
     thread_pool: &mut scoped_threadpool::Pool,
 ) -> Result<(), CheckpointError> {
     // Serialize ingress history separately. The `SystemMetadata` proto does not
@@ -387,13 +541,63 @@ fn serialize_to_tip(
         .serialize((state.subnet_queues()).into())?;
 
     let results = parallel_map(thread_pool, state.canisters_iter(), |canister_state| {
-        serialize_canister_to_tip(log, canister_state, tip)
+        serialize_canister_to_tip(log, canister_state, tip, lsmt_storage)
     });
 
     for result in results.into_iter() {
         result?;
     }
 
+    let results = parallel_map(
+        thread_pool,
+        state.canister_snapshots.iter(),
+        |(snapshot_id, snapshot)| serialize_snapshot_to_tip(snapshot_id, snapshot, tip),
+    );
+
+    for result in results.into_iter() {
+        result?;
+    }
+
+    Ok(())
+}
+
+/// Snapshots are immutable, so their Wasm and memory files only need to be
+/// written at the first checkpoint after the snapshot was taken. Afterwards,
+/// they are carried over from the previous checkpoint like any other file.
+fn serialize_snapshot_to_tip(
+    snapshot_id: &SnapshotId,
+    snapshot: &CanisterSnapshot,
+    tip: &CheckpointLayout<RwPolicy<TipHandler>>,
+) -> Result<(), CheckpointError> {
+    let snapshot_layout = tip.snapshot(snapshot_id)?;
+    let wasm = snapshot_layout.wasm();
+    if !wasm.raw_path().exists() {
+        snapshot
+            .wasm_memory()
+            .page_map
+            .persist_all(&snapshot_layout.vmemory_0())?;
+        snapshot
+            .stable_memory()
+            .page_map
+            .persist_all(&snapshot_layout.stable_memory_blob())?;
+        // The Wasm file is written last, as its presence marks the snapshot
+        // as fully persisted.
+        wasm.serialize(snapshot.binary())?;
+    }
+
+    snapshot_layout.snapshot().serialize(
+        CanisterSnapshotBits {
+            snapshot_id: *snapshot_id,
+            taken_at_timestamp: snapshot.taken_at_timestamp(),
+            canister_version: snapshot.canister_version(),
+            certified_data: snapshot.certified_data().clone(),
+            exported_globals: snapshot.exported_globals().clone(),
+            wasm_memory_size: snapshot.wasm_memory().size,
+            stable_memory_size: snapshot.stable_memory().size,
+            binary_hash: snapshot.binary().module_hash().into(),
+        }
+        .into(),
+    )?;
     Ok(())
 }
 
@@ -401,6 +605,7 @@ fn serialize_canister_to_tip(
     log: &ReplicaLogger,
     canister_state: &CanisterState,
     tip: &CheckpointLayout<RwPolicy<TipHandler>>,
+    lsmt_storage: FlagStatus,
 ) -> Result<(), CheckpointError> {
     let canister_layout = tip.canister(&canister_state.canister_id())?;
     canister_layout
@@ -432,14 +637,18 @@ fn serialize_canister_to_tip(
                         .serialize(&execution_state.wasm_binary.binary)?;
                 }
             }
-            execution_state
-                .wasm_memory
-                .page_map
-                .persist_delta(&canister_layout.vmemory_0())?;
-            execution_state
-                .stable_memory
-                .page_map
-                .persist_delta(&canister_layout.stable_memory_blob())?;
+            persist_page_map(
+                &execution_state.wasm_memory.page_map,
+                &canister_layout.wasm_memory(),
+                tip.height(),
+                lsmt_storage,
+            )?;
+            persist_page_map(
+                &execution_state.stable_memory.page_map,
+                &canister_layout.stable_memory(),
+                tip.height(),
+                lsmt_storage,
+            )?;
 
             Some(ExecutionStateBits {
                 exported_globals: execution_state.exported_globals.clone(),
@@ -454,10 +663,18 @@ fn serialize_canister_to_tip(
         None => {
             truncate_path(log, &canister_layout.vmemory_0());
             truncate_path(log, &canister_layout.stable_memory_blob());
+            canister_layout.wasm_memory().delete_overlays()?;
+            canister_layout.stable_memory().delete_overlays()?;
             canister_layout.wasm().try_delete_file()?;
             None
         }
     };
+    persist_page_map(
+        canister_state.system_state.wasm_chunk_store.page_map(),
+        &canister_layout.wasm_chunk_store(),
+        tip.height(),
+        lsmt_storage,
+    )?;
     // Priority credit must be zero at this point
     assert_eq!(canister_state.scheduler_state.priority_credit.get(), 0);
     canister_layout.canister().serialize(
@@ -472,6 +689,7 @@ fn serialize_canister_to_tip(
             cycles_balance: canister_state.system_state.balance(),
             cycles_debit: canister_state.system_state.ingress_induction_cycles_debit(),
             reserved_balance: canister_state.system_state.reserved_balance(),
+            reserved_balance_limit: canister_state.system_state.reserved_balance_limit(),
             execution_state_bits,
             status: canister_state.system_state.status.clone(),
             scheduled_as_first: canister_state
@@ -520,6 +738,20 @@ fn serialize_canister_to_tip(
                 .get_consumed_cycles_since_replica_started_by_use_cases()
                 .clone(),
             canister_history: canister_state.system_state.get_canister_history().clone(),
+            next_snapshot_id: canister_state.system_state.next_snapshot_id(),
+            log_visibility: canister_state.system_state.log_visibility,
+            canister_log: canister_state.system_state.canister_log.clone(),
+            wasm_chunk_store_metadata: canister_state
+                .system_state
+                .wasm_chunk_store
+                .metadata()
+                .clone(),
+            wasm_memory_limit: canister_state.system_state.wasm_memory_limit,
+            wasm_memory_threshold: canister_state.system_state.wasm_memory_threshold,
+            on_low_wasm_memory_hook_status: canister_state
+                .system_state
+                .on_low_wasm_memory_hook_status(),
+            total_query_stats: canister_state.system_state.total_query_stats.clone(),
         }
         .into(),
     )?;
@@ -777,8 +1009,14 @@ mod test {
             let metrics_registry = ic_metrics::MetricsRegistry::new();
             let metrics = StateManagerMetrics::new(&metrics_registry);
             let tip_handler = layout.capture_tip_handler();
-            let (_h, _s) =
-                spawn_tip_thread(log, tip_handler, layout, metrics, MaliciousFlags::default());
+            let (_h, _s) = spawn_tip_thread(
+                log,
+                tip_handler,
+                layout,
+                FlagStatus::Disabled,
+                metrics,
+                MaliciousFlags::default(),
+            );
         });
     }
 
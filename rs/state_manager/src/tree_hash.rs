@@ -285,6 +285,8 @@ mod tests {
             "D963A967586652BBBAFBD630A1DB53442F01548A5AC42E5A33D1BFEF61BFD9A0",
             "1213C1D177E064FB70CB9B62BFE20DB823A109B71B4DAC7E41AEAE07DEFDA6FC",
             "C3F332850C080533635500BE033EF6383321032644914CF3356EFC9733A3E55D",
+            "814E1B403A9DB6749981B3F8B0511C04B4739E8F63693F45384295A1223BF05B",
+            "814E1B403A9DB6749981B3F8B0511C04B4739E8F63693F45384295A1223BF05B",
         ];
         for certification_version in CertificationVersion::iter() {
             assert_partial_state_hash_matches(
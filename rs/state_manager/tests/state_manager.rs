@@ -1,5 +1,5 @@
 use ic_base_types::NumBytes;
-use ic_config::state_manager::Config;
+use ic_config::{flag_status::FlagStatus, state_manager::Config};
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, MixedHashTree};
 use ic_ic00_types::{CanisterChangeDetails, CanisterChangeOrigin};
 use ic_interfaces::artifact_manager::{ArtifactClient, ArtifactProcessor};
@@ -819,6 +819,120 @@ fn checkpoints_outlive_state_manager() {
     });
 }
 
+#[test]
+fn checkpoints_do_not_wait_for_overlay_merges() {
+    let tmp = tmpdir("sm");
+    let config = Config {
+        lsmt_storage: FlagStatus::Enabled,
+        ..Config::new(tmp.path().into())
+    };
+
+    with_test_replica_logger(|log| {
+        let canister_id: CanisterId = canister_test_id(100);
+        let metrics_registry = MetricsRegistry::new();
+        let state_manager = StateManagerImpl::new(
+            Arc::new(FakeVerifier::new()),
+            subnet_test_id(42),
+            SubnetType::Application,
+            log,
+            &metrics_registry,
+            &config,
+            None,
+            ic_types::malicious_flags::MaliciousFlags::default(),
+        );
+
+        let write_page = |state: &mut ReplicatedState, page: u64, byte: u8| {
+            let canister_state = state.canister_state_mut(&canister_id).unwrap();
+            let execution_state = canister_state.execution_state.as_mut().unwrap();
+            execution_state
+                .wasm_memory
+                .page_map
+                .update(&[(PageIndex::new(page), &[byte; PAGE_SIZE])]);
+        };
+        let overlays = |h: Height| {
+            state_manager
+                .state_layout()
+                .checkpoint(h)
+                .unwrap()
+                .canister(&canister_id)
+                .unwrap()
+                .wasm_memory()
+                .existing_overlays()
+                .unwrap()
+                .len()
+        };
+
+        let (_height, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_id);
+        write_page(&mut state, 1, 1);
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+
+        // Two overlays of the same size need to be merged, but the merge only
+        // happens in the tip after the checkpoint @2 is created.
+        let (_height, mut state) = state_manager.take_tip();
+        write_page(&mut state, 2, 2);
+        state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+        assert_eq!(overlays(height(1)), 1);
+        assert_eq!(overlays(height(2)), 2);
+
+        // The next checkpoint is created from the merged tip.
+        let (_height, state) = state_manager.take_tip();
+        state_manager.commit_and_certify(state, height(3), CertificationScope::Full);
+        assert_eq!(overlays(height(3)), 1);
+
+        let checkpointed_state = state_manager.get_latest_state();
+        assert_eq!(checkpointed_state.height(), height(3));
+        let page_map = &checkpointed_state
+            .get_ref()
+            .canister_state(&canister_id)
+            .unwrap()
+            .execution_state
+            .as_ref()
+            .unwrap()
+            .wasm_memory
+            .page_map;
+        assert_eq!(page_map.get_page(PageIndex::new(1)), &[1; PAGE_SIZE]);
+        assert_eq!(page_map.get_page(PageIndex::new(2)), &[2; PAGE_SIZE]);
+    });
+}
+
+#[test]
+fn wasm_chunk_store_is_persisted_in_checkpoints() {
+    state_manager_restart_test(|state_manager, restart_fn| {
+        let canister_id: CanisterId = canister_test_id(100);
+        let (_height, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_id);
+        let hash = state
+            .canister_state_mut(&canister_id)
+            .unwrap()
+            .system_state
+            .wasm_chunk_store
+            .insert_chunk(&[1, 2, 3]);
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+
+        assert!(state_manager
+            .state_layout()
+            .checkpoint(height(1))
+            .unwrap()
+            .canister(&canister_id)
+            .unwrap()
+            .wasm_chunk_store()
+            .base()
+            .exists());
+
+        let state_manager = restart_fn(state_manager, None);
+
+        let (_height, recovered_tip) = state_manager.take_tip();
+        let wasm_chunk_store = &recovered_tip
+            .canister_state(&canister_id)
+            .unwrap()
+            .system_state
+            .wasm_chunk_store;
+        assert_eq!(wasm_chunk_store.len(), 1);
+        assert_eq!(wasm_chunk_store.get_chunk(&hash), Some(vec![1, 2, 3]));
+    });
+}
+
 #[test]
 fn certifications_are_not_persisted() {
     let tmp = tmpdir("sm");
@@ -2662,6 +2776,105 @@ fn can_state_sync_based_on_old_checkpoint() {
     });
 }
 
+#[test]
+fn can_state_sync_chunks_from_older_local_checkpoint() {
+    fn write_page(state: &mut ReplicatedState, byte: u8) {
+        let canister_state = state.canister_state_mut(&canister_test_id(100)).unwrap();
+        let execution_state = canister_state.execution_state.as_mut().unwrap();
+        execution_state
+            .wasm_memory
+            .page_map
+            .update(&[(PageIndex::new(0), &[byte; PAGE_SIZE])]);
+    }
+
+    state_manager_test_with_state_sync(|src_metrics, src_state_manager, src_state_sync| {
+        for h in 1..3 {
+            let (_height, state) = src_state_manager.take_tip();
+            src_state_manager.commit_and_certify(state, height(h), CertificationScope::Metadata);
+        }
+        let (_height, mut state) = src_state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+        write_page(&mut state, 1);
+        src_state_manager.commit_and_certify(state, height(3), CertificationScope::Full);
+        let time_source = ic_test_utilities::FastForwardTimeSource::new();
+
+        let hash = wait_for_checkpoint(&*src_state_manager, height(3));
+        let id = StateSyncArtifactId {
+            height: height(3),
+            hash,
+        };
+        let msg = src_state_sync
+            .get_validated_by_identifier(&id)
+            .expect("failed to get state sync message");
+
+        assert_error_counters(src_metrics);
+
+        let memory_file_index = msg
+            .manifest
+            .file_table
+            .iter()
+            .position(|file_info| file_info.relative_path.ends_with("vmemory_0.bin"))
+            .unwrap();
+        let memory_chunk_index = msg
+            .manifest
+            .chunk_table
+            .iter()
+            .position(|chunk_info| chunk_info.file_index as usize == memory_file_index)
+            .unwrap();
+        let memory_chunk_id = ChunkId::new(memory_chunk_index as u32 + 1);
+
+        state_manager_test_with_state_sync(|dst_metrics, dst_state_manager, dst_state_sync| {
+            // The memory chunk of the synced state only exists in checkpoint @1,
+            // the latest checkpoint @2 contains different memory.
+            let (_height, mut state) = dst_state_manager.take_tip();
+            insert_dummy_canister(&mut state, canister_test_id(100));
+            write_page(&mut state, 1);
+            dst_state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+            wait_for_checkpoint(&*dst_state_manager, height(1));
+
+            let (_height, mut state) = dst_state_manager.take_tip();
+            write_page(&mut state, 2);
+            dst_state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+            wait_for_checkpoint(&*dst_state_manager, height(2));
+
+            let mut chunkable = dst_state_sync.create_chunkable_state(&id);
+
+            let result = pipe_meta_manifest(&msg, &mut *chunkable, false);
+            assert!(matches!(result, Err(StateSyncErrorCode::ChunksMoreNeeded)));
+            let result = pipe_manifest(&msg, &mut *chunkable, false);
+            assert!(matches!(result, Err(StateSyncErrorCode::ChunksMoreNeeded)));
+
+            // The memory chunk is copied from checkpoint @1 instead of being fetched.
+            assert!(!chunkable
+                .chunks_to_download()
+                .any(|chunk_id| chunk_id == memory_chunk_id));
+
+            let dst_msg = pipe_partial_state_sync(
+                &msg,
+                &mut *chunkable,
+                &maplit::hashset! {memory_chunk_id},
+                false,
+            )
+            .expect("State sync not completed.");
+            dst_state_sync.process_changes(
+                time_source.as_ref(),
+                vec![UnvalidatedArtifact {
+                    message: dst_msg,
+                    peer_id: node_test_id(0),
+                    timestamp: mock_time(),
+                }],
+            );
+
+            let expected_state = src_state_manager.get_latest_state();
+
+            assert_eq!(dst_state_manager.get_latest_state(), expected_state);
+
+            assert_no_remaining_chunks(dst_metrics);
+            assert_error_counters(dst_metrics);
+        })
+    });
+}
+
 #[test]
 fn can_recover_from_corruption_on_state_sync() {
     use ic_state_layout::{CheckpointLayout, RwPolicy};
@@ -3066,6 +3279,21 @@ fn can_get_dirty_pages() {
                 file_type: FileType::PageMap(PageMapType::StableMemory(canister_test_id(100))),
                 page_delta_indices: vec![],
             },
+            DirtyPageMap {
+                height: height(1),
+                file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(80))),
+                page_delta_indices: vec![],
+            },
+            DirtyPageMap {
+                height: height(1),
+                file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(90))),
+                page_delta_indices: vec![],
+            },
+            DirtyPageMap {
+                height: height(1),
+                file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(100))),
+                page_delta_indices: vec![],
+            },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::WasmBinary(canister_test_id(80)),
@@ -3127,6 +3355,21 @@ fn can_get_dirty_pages() {
                 file_type: FileType::PageMap(PageMapType::StableMemory(canister_test_id(100))),
                 page_delta_indices: vec![PageIndex::new(1), PageIndex::new(300)],
             },
+            DirtyPageMap {
+                height: height(2),
+                file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(80))),
+                page_delta_indices: vec![],
+            },
+            DirtyPageMap {
+                height: height(2),
+                file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(90))),
+                page_delta_indices: vec![],
+            },
+            DirtyPageMap {
+                height: height(2),
+                file_type: FileType::PageMap(PageMapType::WasmChunkStore(canister_test_id(100))),
+                page_delta_indices: vec![],
+            },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::WasmBinary(canister_test_id(80)),
@@ -7,7 +7,9 @@ pub mod system_api_empty;
 
 use ic_base_types::PrincipalIdBlobParseError;
 use ic_config::flag_status::FlagStatus;
+use ic_cycles_account_manager::ResourceSaturation;
 use ic_error_types::RejectCode;
+use ic_ic00_types::{EcdsaCurve, EcdsaKeyId};
 use ic_interfaces::execution_environment::{
     ExecutionComplexity, ExecutionMode,
     HypervisorError::{self, *},
@@ -23,13 +25,16 @@ use ic_replicated_state::{
 };
 use ic_sys::PageBytes;
 use ic_types::{
+    canister_log::CanisterLog,
     ingress::WasmResult,
     messages::{CallContextId, RejectContext, Request, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES},
     methods::{SystemMethod, WasmClosure},
+    time::CoarseTime,
     CanisterId, CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumBytes,
     NumInstructions, NumPages, PrincipalId, SubnetId, Time, MAX_STABLE_MEMORY_IN_BYTES,
 };
 use ic_utils::deterministic_operations::deterministic_copy_from_slice;
+pub use request_in_prep::MAX_CALL_TIMEOUT_SECONDS;
 use request_in_prep::{into_request, RequestInPrep};
 use sandbox_safe_system_state::{CanisterStatusView, SandboxSafeSystemState, SystemStateChanges};
 use serde::{Deserialize, Serialize};
@@ -41,7 +46,7 @@ use std::{
 
 const MULTIPLIER_MAX_SIZE_LOCAL_SUBNET: u64 = 5;
 const MAX_NON_REPLICATED_QUERY_REPLY_SIZE: NumBytes = NumBytes::new(3 << 20);
-const CERTIFIED_DATA_MAX_LENGTH: u32 = 32;
+const CERTIFIED_DATA_MAX_LENGTH: usize = 32;
 
 // Enables tracing of system calls for local debugging.
 const TRACE_SYSCALLS: bool = false;
@@ -78,10 +83,10 @@ macro_rules! trace_syscall {
 
 // This helper is used in system calls for displaying a summary hash of a heap region.
 #[inline]
-fn summarize(heap: &[u8], start: u32, size: u32) -> u64 {
+fn summarize(heap: &[u8], start: usize, size: usize) -> u64 {
     if TRACE_SYSCALLS {
-        let start = (start as usize).min(heap.len());
-        let end = (start + (size as usize)).min(heap.len());
+        let start = start.min(heap.len());
+        let end = start.saturating_add(size).min(heap.len());
         // The actual hash function doesn't matter much as long as it is
         // cheap to compute and maps the input to u64 reasonably well.
         let mut sum = 0;
@@ -227,6 +232,9 @@ pub enum ApiType {
         incoming_cycles: Cycles,
         caller: PrincipalId,
         call_context_id: CallContextId,
+        /// The deadline of the incoming call, `NO_DEADLINE` for ingress
+        /// messages and guaranteed response calls.
+        deadline: CoarseTime,
         /// Begins as empty and used to accumulate data for sending replies.
         #[serde(with = "serde_bytes")]
         response_data: Vec<u8>,
@@ -272,6 +280,8 @@ pub enum ApiType {
         incoming_payload: Vec<u8>,
         incoming_cycles: Cycles,
         call_context_id: CallContextId,
+        /// The deadline of the call this is a response to.
+        deadline: CoarseTime,
         // Begins as empty and used to accumulate data for sending replies.
         #[serde(with = "serde_bytes")]
         response_data: Vec<u8>,
@@ -289,6 +299,8 @@ pub enum ApiType {
         reject_context: RejectContext,
         incoming_cycles: Cycles,
         call_context_id: CallContextId,
+        /// The deadline of the call this is a response to.
+        deadline: CoarseTime,
         // Begins as empty and used to accumulate data for sending replies.
         #[serde(with = "serde_bytes")]
         response_data: Vec<u8>,
@@ -317,10 +329,12 @@ pub enum ApiType {
         message_accepted: bool,
     },
 
-    // For executing the `canister_heartbeat` or `canister_global_timer` methods
+    // For executing the `canister_heartbeat`, `canister_global_timer` or
+    // `canister_on_low_wasm_memory` methods
     SystemTask {
         /// System task to execute.
-        /// Only `canister_heartbeat` and `canister_global_timer` are allowed.
+        /// Only `canister_heartbeat`, `canister_global_timer` and
+        /// `canister_on_low_wasm_memory` are allowed.
         system_task: SystemMethod,
         time: Time,
         call_context_id: CallContextId,
@@ -373,6 +387,7 @@ impl ApiType {
         incoming_cycles: Cycles,
         caller: PrincipalId,
         call_context_id: CallContextId,
+        deadline: CoarseTime,
     ) -> Self {
         Self::Update {
             time,
@@ -380,6 +395,7 @@ impl ApiType {
             incoming_cycles,
             caller,
             call_context_id,
+            deadline,
             response_data: vec![],
             response_status: ResponseStatus::NotRepliedYet,
             outgoing_request: None,
@@ -432,6 +448,7 @@ impl ApiType {
         incoming_payload: Vec<u8>,
         incoming_cycles: Cycles,
         call_context_id: CallContextId,
+        deadline: CoarseTime,
         replied: bool,
         execution_mode: ExecutionMode,
     ) -> Self {
@@ -440,6 +457,7 @@ impl ApiType {
             incoming_payload,
             incoming_cycles,
             call_context_id,
+            deadline,
             response_data: vec![],
             response_status: if replied {
                 ResponseStatus::AlreadyReplied
@@ -458,6 +476,7 @@ impl ApiType {
         reject_context: RejectContext,
         incoming_cycles: Cycles,
         call_context_id: CallContextId,
+        deadline: CoarseTime,
         replied: bool,
         execution_mode: ExecutionMode,
     ) -> Self {
@@ -466,6 +485,7 @@ impl ApiType {
             reject_context,
             incoming_cycles,
             call_context_id,
+            deadline,
             response_data: vec![],
             response_status: if replied {
                 ResponseStatus::AlreadyReplied
@@ -531,7 +551,11 @@ impl ApiType {
             ApiType::SystemTask { system_task, .. } => match system_task {
                 SystemMethod::CanisterHeartbeat => "heartbeat",
                 SystemMethod::CanisterGlobalTimer => "global timer",
-                _ => panic!("Only `canister_heartbeat` and `canister_global_timer` are allowed."),
+                SystemMethod::CanisterOnLowWasmMemory => "on low wasm memory",
+                _ => panic!(
+                    "Only `canister_heartbeat`, `canister_global_timer` and \
+                     `canister_on_low_wasm_memory` are allowed."
+                ),
             },
             ApiType::Update { .. } => "update",
             ApiType::ReplicatedQuery { .. } => "replicated query",
@@ -581,9 +605,17 @@ struct MemoryUsage {
 
     /// The memory allocation of the canister.
     memory_allocation: MemoryAllocation,
+
+    /// The subnet memory usage above which newly allocated memory reserves
+    /// cycles.
+    subnet_memory_threshold: NumBytes,
+
+    /// The total execution memory of the subnet.
+    subnet_memory_capacity: NumBytes,
 }
 
 impl MemoryUsage {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         log: ReplicaLogger,
         canister_id: CanisterId,
@@ -591,6 +623,8 @@ impl MemoryUsage {
         current_usage: NumBytes,
         subnet_available_memory: SubnetAvailableMemory,
         memory_allocation: MemoryAllocation,
+        subnet_memory_threshold: NumBytes,
+        subnet_memory_capacity: NumBytes,
     ) -> Self {
         // A canister's current usage should never exceed its limit. This is
         // most probably a bug. Panicking here due to this inconsistency has the
@@ -612,6 +646,8 @@ impl MemoryUsage {
             allocated_execution_memory: NumBytes::from(0),
             allocated_message_memory: NumBytes::from(0),
             memory_allocation,
+            subnet_memory_threshold,
+            subnet_memory_capacity,
         }
     }
 
@@ -628,7 +664,7 @@ impl MemoryUsage {
         &mut self,
         pages: usize,
         api_type: &ApiType,
-        sandbox_safe_system_state: &SandboxSafeSystemState,
+        sandbox_safe_system_state: &mut SandboxSafeSystemState,
     ) -> HypervisorResult<()> {
         let bytes = ic_replicated_state::num_bytes_try_from(NumWasmPages::from(pages))
             .map_err(|_| HypervisorError::OutOfMemory)?;
@@ -664,13 +700,18 @@ impl MemoryUsage {
     ///
     /// Returns `Err(HypervisorError::InsufficientCyclesInMemoryGrow)` and
     /// leaves `self` unchanged if freezing threshold check is needed for the
-    /// given API type and canister would be frozen after the allocation.
+    /// given API type and canister would be frozen after the allocation, or
+    /// if the canister cannot afford the storage reservation.
+    ///
+    /// Returns `Err(HypervisorError::ReservedCyclesLimitExceededInMemoryGrow)`
+    /// and leaves `self` unchanged if the storage reservation would exceed the
+    /// reserved cycles limit of the canister.
     fn allocate_memory(
         &mut self,
         execution_bytes: NumBytes,
         message_bytes: NumBytes,
         api_type: &ApiType,
-        sandbox_safe_system_state: &SandboxSafeSystemState,
+        sandbox_safe_system_state: &mut SandboxSafeSystemState,
     ) -> HypervisorResult<()> {
         let (new_usage, overflow) = self
             .current_usage
@@ -691,12 +732,29 @@ impl MemoryUsage {
         // at the time of reservation.
         match self.memory_allocation {
             MemoryAllocation::BestEffort => {
+                let subnet_memory_saturation = ResourceSaturation::new_from_available(
+                    self.subnet_available_memory.get_execution_memory().max(0) as u64,
+                    self.subnet_memory_threshold.get(),
+                    self.subnet_memory_capacity.get(),
+                );
                 match self.subnet_available_memory.try_decrement(
                     execution_bytes,
                     message_bytes,
                     NumBytes::from(0),
                 ) {
                     Ok(()) => {
+                        if let Err(err) = sandbox_safe_system_state.reserve_storage_cycles(
+                            execution_bytes,
+                            &subnet_memory_saturation,
+                            api_type,
+                        ) {
+                            self.subnet_available_memory.increment(
+                                execution_bytes,
+                                message_bytes,
+                                NumBytes::from(0),
+                            );
+                            return Err(err);
+                        }
                         self.current_usage = NumBytes::from(new_usage);
                         self.allocated_execution_memory += execution_bytes;
                         self.allocated_message_memory += message_bytes;
@@ -795,6 +853,10 @@ pub struct SystemApiImpl {
 
     /// Tracks the complexity accumulated during the message execution.
     execution_complexity: ExecutionComplexity,
+
+    /// Log records produced during the message execution. They are kept even
+    /// if the execution fails, so that traps show up in the canister log.
+    canister_log: CanisterLog,
 }
 
 impl SystemApiImpl {
@@ -817,6 +879,8 @@ impl SystemApiImpl {
             canister_current_memory_usage,
             subnet_available_memory,
             execution_parameters.memory_allocation,
+            execution_parameters.subnet_memory_threshold,
+            execution_parameters.subnet_memory_capacity,
         );
         let stable_memory = StableMemory::new(stable_memory);
         let slice_limit = execution_parameters.instruction_limits.slice().get();
@@ -833,6 +897,7 @@ impl SystemApiImpl {
             current_slice_instruction_limit: i64::try_from(slice_limit).unwrap_or(i64::MAX),
             instructions_executed_before_current_slice: 0,
             execution_complexity: ExecutionComplexity::default(),
+            canister_log: CanisterLog::default(),
         }
     }
 
@@ -874,6 +939,15 @@ impl SystemApiImpl {
             .cloned()
             .or_else(|| self.execution_error.take())
         {
+            match &err {
+                HypervisorError::CalledTrap(msg) => {
+                    self.add_log_record(format!("[TRAP]: {}", msg).as_bytes())
+                }
+                HypervisorError::Trapped(code) => {
+                    self.add_log_record(format!("[TRAP]: {}", code).as_bytes())
+                }
+                _ => (),
+            }
             // Return allocated memory in case of failed message execution.
             self.memory_usage.deallocate_memory(
                 self.memory_usage.allocated_execution_memory,
@@ -917,6 +991,40 @@ impl SystemApiImpl {
         }
     }
 
+    /// Returns the log records produced during the execution, leaving an
+    /// empty log behind.
+    pub fn take_canister_log(&mut self) -> CanisterLog {
+        std::mem::take(&mut self.canister_log)
+    }
+
+    /// Appends a record to the canister log. Non-replicated executions do not
+    /// record anything since their state changes are discarded anyway.
+    fn add_log_record(&mut self, content: &[u8]) {
+        let time = match &self.api_type {
+            ApiType::NonReplicatedQuery { .. }
+            | ApiType::InspectMessage { .. }
+            | ApiType::ReplyCallback {
+                execution_mode: ExecutionMode::NonReplicated,
+                ..
+            }
+            | ApiType::RejectCallback {
+                execution_mode: ExecutionMode::NonReplicated,
+                ..
+            } => return,
+            ApiType::Start { time }
+            | ApiType::Init { time, .. }
+            | ApiType::SystemTask { time, .. }
+            | ApiType::Update { time, .. }
+            | ApiType::Cleanup { time, .. }
+            | ApiType::ReplicatedQuery { time, .. }
+            | ApiType::PreUpgrade { time, .. }
+            | ApiType::ReplyCallback { time, .. }
+            | ApiType::RejectCallback { time, .. } => *time,
+        };
+        self.canister_log
+            .add_record(time.as_nanos_since_unix_epoch(), content);
+    }
+
     /// Note that this function is made public only for the tests
     #[doc(hidden)]
     pub fn get_current_memory_usage(&self) -> NumBytes {
@@ -1246,7 +1354,7 @@ impl SystemApiImpl {
                 NumBytes::from(0),
                 reservation_bytes,
                 &self.api_type,
-                &self.sandbox_safe_system_state,
+                &mut self.sandbox_safe_system_state,
             )
             .is_err()
         {
@@ -1268,6 +1376,47 @@ impl SystemApiImpl {
         }
     }
 
+    /// Checks that growing the Wasm memory from `current_pages` by
+    /// `additional_pages` stays within the `wasm_memory_limit` of the canister.
+    /// The limit applies only to update calls, system tasks and replicated
+    /// callbacks, so that installing and upgrading a canister keeps working.
+    fn check_wasm_memory_limit(
+        &self,
+        current_pages: u64,
+        additional_pages: u64,
+    ) -> HypervisorResult<()> {
+        let limit = match self.sandbox_safe_system_state.wasm_memory_limit() {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        let is_limit_enforced = match &self.api_type {
+            ApiType::Update { .. } | ApiType::SystemTask { .. } => true,
+            ApiType::ReplyCallback { .. } | ApiType::RejectCallback { .. } => {
+                self.execution_parameters.execution_mode == ExecutionMode::Replicated
+            }
+            ApiType::Start { .. }
+            | ApiType::Init { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::InspectMessage { .. } => false,
+        };
+        if !is_limit_enforced {
+            return Ok(());
+        }
+        let bytes = current_pages
+            .saturating_add(additional_pages)
+            .saturating_mul(WASM_PAGE_SIZE_IN_BYTES as u64);
+        if bytes > limit.get() {
+            return Err(HypervisorError::WasmMemoryLimitExceeded {
+                bytes: NumBytes::new(bytes),
+                limit,
+            });
+        }
+        Ok(())
+    }
+
     fn stable_memory(&self) -> &StableMemory {
         if self.wasm_native_stable_memory == FlagStatus::Disabled {
             &self.stable_memory
@@ -1350,19 +1499,19 @@ impl SystemApi for SystemApiImpl {
         NumInstructions::from(result)
     }
 
-    fn ic0_msg_caller_size(&self) -> HypervisorResult<u32> {
+    fn ic0_msg_caller_size(&self) -> HypervisorResult<usize> {
         let result = self
             .get_msg_caller_id("ic0_msg_caller_size")
-            .map(|caller_id| caller_id.as_slice().len() as u32);
+            .map(|caller_id| caller_id.as_slice().len());
         trace_syscall!(self, ic0_msg_caller_size, result);
         result
     }
 
     fn ic0_msg_caller_copy(
         &self,
-        dst: u32,
-        offset: u32,
-        size: u32,
+        dst: usize,
+        offset: usize,
+        size: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
         let result = match self.get_msg_caller_id("ic0_msg_caller_copy") {
@@ -1370,7 +1519,6 @@ impl SystemApi for SystemApiImpl {
                 let id_bytes = caller_id.as_slice();
                 valid_subslice("ic0.msg_caller_copy heap", dst, size, heap)?;
                 let slice = valid_subslice("ic0.msg_caller_copy id", offset, size, id_bytes)?;
-                let (dst, size) = (dst as usize, size as usize);
                 deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
                 Ok(())
             }
@@ -1388,7 +1536,7 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_msg_arg_data_size(&self) -> HypervisorResult<u32> {
+    fn ic0_msg_arg_data_size(&self) -> HypervisorResult<usize> {
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Cleanup { .. }
@@ -1412,7 +1560,7 @@ impl SystemApi for SystemApiImpl {
             }
             | ApiType::NonReplicatedQuery {
                 incoming_payload, ..
-            } => Ok(incoming_payload.len() as u32),
+            } => Ok(incoming_payload.len()),
         };
         trace_syscall!(self, ic0_msg_arg_data_size, result);
         result
@@ -1420,9 +1568,9 @@ impl SystemApi for SystemApiImpl {
 
     fn ic0_msg_arg_data_copy(
         &self,
-        dst: u32,
-        offset: u32,
-        size: u32,
+        dst: usize,
+        offset: usize,
+        size: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
         let result = match &self.api_type {
@@ -1456,7 +1604,6 @@ impl SystemApi for SystemApiImpl {
                     size,
                     incoming_payload,
                 )?;
-                let (dst, size) = (dst as usize, size as usize);
                 deterministic_copy_from_slice(&mut heap[dst..dst + size], payload_subslice);
                 Ok(())
             }
@@ -1473,7 +1620,7 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_msg_method_name_size(&self) -> HypervisorResult<u32> {
+    fn ic0_msg_method_name_size(&self) -> HypervisorResult<usize> {
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::RejectCallback { .. }
@@ -1485,7 +1632,7 @@ impl SystemApi for SystemApiImpl {
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. } => Err(self.error_for("ic0_msg_method_name_size")),
-            ApiType::InspectMessage { method_name, .. } => Ok(method_name.len() as u32),
+            ApiType::InspectMessage { method_name, .. } => Ok(method_name.len()),
         };
         trace_syscall!(self, ic0_msg_method_name_size, result);
         result
@@ -1493,9 +1640,9 @@ impl SystemApi for SystemApiImpl {
 
     fn ic0_msg_method_name_copy(
         &self,
-        dst: u32,
-        offset: u32,
-        size: u32,
+        dst: usize,
+        offset: usize,
+        size: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
         let result = match &self.api_type {
@@ -1517,7 +1664,6 @@ impl SystemApi for SystemApiImpl {
                     size,
                     method_name.as_bytes(),
                 )?;
-                let (dst, size) = (dst as usize, size as usize);
                 deterministic_copy_from_slice(&mut heap[dst..dst + size], payload_subslice);
                 Ok(())
             }
@@ -1584,15 +1730,15 @@ impl SystemApi for SystemApiImpl {
 
     fn ic0_msg_reply_data_append(
         &mut self,
-        src: u32,
-        size: u32,
+        src: usize,
+        size: usize,
         heap: &[u8],
     ) -> HypervisorResult<()> {
         let result = match self.get_response_info() {
             None => Err(self.error_for("ic0_msg_reply_data_append")),
             Some((data, max_reply_size, response_status)) => match response_status {
                 ResponseStatus::NotRepliedYet => {
-                    let payload_size = (data.len() + size as usize) as u64;
+                    let payload_size = data.len().saturating_add(size) as u64;
                     if payload_size > max_reply_size.get() {
                         let string = format!(
                             "ic0.msg_reply_data_append: application payload size ({}) cannot be larger than {}",
@@ -1622,7 +1768,7 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_msg_reject(&mut self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()> {
+    fn ic0_msg_reject(&mut self, src: usize, size: usize, heap: &[u8]) -> HypervisorResult<()> {
         let result = match self.get_response_info() {
             None => Err(self.error_for("ic0_msg_reject")),
             Some((_, max_reply_size, response_status)) => match response_status {
@@ -1668,20 +1814,20 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_msg_reject_msg_size(&self) -> HypervisorResult<u32> {
+    fn ic0_msg_reject_msg_size(&self) -> HypervisorResult<usize> {
         let reject_context = self
             .get_reject_context()
             .ok_or_else(|| self.error_for("ic0_msg_reject_msg_size"))?;
-        let result = Ok(reject_context.message().len() as u32);
+        let result = Ok(reject_context.message().len());
         trace_syscall!(self, ic0_msg_reject_msg_size, result);
         result
     }
 
     fn ic0_msg_reject_msg_copy(
         &self,
-        dst: u32,
-        offset: u32,
-        size: u32,
+        dst: usize,
+        offset: usize,
+        size: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
         let result = {
@@ -1691,10 +1837,8 @@ impl SystemApi for SystemApiImpl {
             valid_subslice("ic0.msg_reject_msg_copy heap", dst, size, heap)?;
 
             let msg = reject_context.message();
-            let dst = dst as usize;
             let msg_bytes =
                 valid_subslice("ic0.msg_reject_msg_copy msg", offset, size, msg.as_bytes())?;
-            let size = size as usize;
             deterministic_copy_from_slice(&mut heap[dst..dst + size], msg_bytes);
             Ok(())
         };
@@ -1710,6 +1854,25 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
+    fn ic0_msg_deadline(&self) -> HypervisorResult<u64> {
+        let result = match &self.api_type {
+            ApiType::Start { .. }
+            | ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => Err(self.error_for("ic0_msg_deadline")),
+            ApiType::ReplicatedQuery { .. } | ApiType::NonReplicatedQuery { .. } => Ok(0),
+            ApiType::Update { deadline, .. }
+            | ApiType::ReplyCallback { deadline, .. }
+            | ApiType::RejectCallback { deadline, .. } => {
+                Ok(Time::from(*deadline).as_nanos_since_unix_epoch())
+            }
+        };
+        trace_syscall!(self, ic0_msg_deadline, result);
+        result
+    }
+
     fn ic0_canister_self_size(&self) -> HypervisorResult<usize> {
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_canister_self_size")),
@@ -1735,9 +1898,9 @@ impl SystemApi for SystemApiImpl {
 
     fn ic0_canister_self_copy(
         &mut self,
-        dst: u32,
-        offset: u32,
-        size: u32,
+        dst: usize,
+        offset: usize,
+        size: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
         let result = match &self.api_type {
@@ -1756,7 +1919,6 @@ impl SystemApi for SystemApiImpl {
                 let canister_id = self.sandbox_safe_system_state.canister_id;
                 let id_bytes = canister_id.get_ref().as_slice();
                 let slice = valid_subslice("ic0.canister_self_copy id", offset, size, id_bytes)?;
-                let (dst, size) = (dst as usize, size as usize);
                 deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
                 Ok(())
             }
@@ -1775,14 +1937,14 @@ impl SystemApi for SystemApiImpl {
 
     fn ic0_call_new(
         &mut self,
-        callee_src: u32,
-        callee_size: u32,
-        name_src: u32,
-        name_len: u32,
+        callee_src: usize,
+        callee_size: usize,
+        name_src: usize,
+        name_len: usize,
         reply_fun: u32,
-        reply_env: u32,
+        reply_env: u64,
         reject_fun: u32,
-        reject_env: u32,
+        reject_env: u64,
         heap: &[u8],
     ) -> HypervisorResult<()> {
         let result = match &mut self.api_type {
@@ -1853,7 +2015,12 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_call_data_append(&mut self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()> {
+    fn ic0_call_data_append(
+        &mut self,
+        src: usize,
+        size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<()> {
         let result = match &mut self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
@@ -1900,7 +2067,7 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_call_on_cleanup(&mut self, fun: u32, env: u32) -> HypervisorResult<()> {
+    fn ic0_call_on_cleanup(&mut self, fun: u32, env: u64) -> HypervisorResult<()> {
         let result = match &mut self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
@@ -1941,6 +2108,55 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
+    fn ic0_call_with_best_effort_response(&mut self, timeout_seconds: u32) -> HypervisorResult<()> {
+        let result = match &mut self.api_type {
+            ApiType::Start { .. }
+            | ApiType::Init { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery {
+                query_kind: NonReplicatedQueryKind::Pure,
+                ..
+            }
+            | ApiType::Cleanup { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                Err(self.error_for("ic0_call_with_best_effort_response"))
+            }
+            ApiType::Update {
+                outgoing_request, ..
+            }
+            | ApiType::NonReplicatedQuery {
+                query_kind:
+                    NonReplicatedQueryKind::Stateful {
+                        outgoing_request, ..
+                    },
+                ..
+            }
+            | ApiType::SystemTask {
+                outgoing_request, ..
+            }
+            | ApiType::ReplyCallback {
+                outgoing_request, ..
+            }
+            | ApiType::RejectCallback {
+                outgoing_request, ..
+            } => match outgoing_request {
+                None => Err(HypervisorError::ContractViolation(
+                    "ic0.call_with_best_effort_response called when no call is under construction."
+                        .to_string(),
+                )),
+                Some(request) => request.set_timeout(timeout_seconds),
+            },
+        };
+        trace_syscall!(
+            self,
+            ic0_call_with_best_effort_response,
+            result,
+            timeout_seconds
+        );
+        result
+    }
+
     fn ic0_call_cycles_add(&mut self, amount: u64) -> HypervisorResult<()> {
         let result = self.ic0_call_cycles_add_helper("ic0_call_cycles_add", Cycles::from(amount));
         trace_syscall!(self, ic0_call_cycles_add, result, amount);
@@ -1975,26 +2191,31 @@ impl SystemApi for SystemApiImpl {
             | ApiType::PreUpgrade { .. }
             | ApiType::InspectMessage { .. } => Err(self.error_for("ic0_call_perform")),
             ApiType::Update {
+                time,
                 call_context_id,
                 outgoing_request,
                 ..
             }
             | ApiType::SystemTask {
+                time,
                 call_context_id,
                 outgoing_request,
                 ..
             }
             | ApiType::ReplyCallback {
+                time,
                 call_context_id,
                 outgoing_request,
                 ..
             }
             | ApiType::RejectCallback {
+                time,
                 call_context_id,
                 outgoing_request,
                 ..
             }
             | ApiType::NonReplicatedQuery {
+                time,
                 query_kind:
                     NonReplicatedQueryKind::Stateful {
                         call_context_id,
@@ -2011,6 +2232,7 @@ impl SystemApi for SystemApiImpl {
                 let req = into_request(
                     req_in_prep,
                     *call_context_id,
+                    *time,
                     &mut self.sandbox_safe_system_state,
                     &self.log,
                 )?;
@@ -2048,7 +2270,7 @@ impl SystemApi for SystemApiImpl {
         let result = match self.memory_usage.allocate_pages(
             additional_pages as usize,
             &self.api_type,
-            &self.sandbox_safe_system_state,
+            &mut self.sandbox_safe_system_state,
         ) {
             Ok(()) => {
                 let res = self.stable_memory_mut().stable_grow(additional_pages);
@@ -2060,7 +2282,10 @@ impl SystemApi for SystemApiImpl {
                 }
                 res
             }
-            Err(err @ HypervisorError::InsufficientCyclesInMemoryGrow { .. }) => {
+            Err(
+                err @ (HypervisorError::InsufficientCyclesInMemoryGrow { .. }
+                | HypervisorError::ReservedCyclesLimitExceededInMemoryGrow { .. }),
+            ) => {
                 // Trap instead of returning -1 in order to give the developer
                 // more actionable error message. Otherwise, they cannot
                 // distinguish between out-of-memory and out-of-cycles.
@@ -2087,7 +2312,7 @@ impl SystemApi for SystemApiImpl {
             dst,
             offset,
             size,
-            summarize(heap, dst, size)
+            summarize(heap, dst as usize, size as usize)
         );
         result
     }
@@ -2109,7 +2334,7 @@ impl SystemApi for SystemApiImpl {
             offset,
             src,
             size,
-            summarize(heap, src, size)
+            summarize(heap, src as usize, size as usize)
         );
         result
     }
@@ -2124,7 +2349,7 @@ impl SystemApi for SystemApiImpl {
         let result = match self.memory_usage.allocate_pages(
             additional_pages as usize,
             &self.api_type,
-            &self.sandbox_safe_system_state,
+            &mut self.sandbox_safe_system_state,
         ) {
             Ok(()) => {
                 let res = self.stable_memory_mut().stable64_grow(additional_pages);
@@ -2136,7 +2361,10 @@ impl SystemApi for SystemApiImpl {
                 }
                 res
             }
-            Err(err @ HypervisorError::InsufficientCyclesInMemoryGrow { .. }) => {
+            Err(
+                err @ (HypervisorError::InsufficientCyclesInMemoryGrow { .. }
+                | HypervisorError::ReservedCyclesLimitExceededInMemoryGrow { .. }),
+            ) => {
                 // Trap instead of returning -1 in order to give the developer
                 // more actionable error message. Otherwise, they cannot
                 // distinguish between out-of-memory and out-of-cycles.
@@ -2163,7 +2391,7 @@ impl SystemApi for SystemApiImpl {
             dst,
             offset,
             size,
-            summarize(heap, dst as u32, size as u32)
+            summarize(heap, dst as usize, size as usize)
         );
         result
     }
@@ -2196,7 +2424,7 @@ impl SystemApi for SystemApiImpl {
             offset,
             src,
             size,
-            summarize(heap, src as u32, size as u32)
+            summarize(heap, src as usize, size as usize)
         );
         result
     }
@@ -2326,14 +2554,22 @@ impl SystemApi for SystemApiImpl {
                 .map(NumBytes::new)
                 .ok_or(HypervisorError::OutOfMemory)?;
 
+            // `element_size` distinguishes `memory.grow` from `table.grow`.
+            if element_size == WASM_PAGE_SIZE_IN_BYTES as u64 {
+                self.check_wasm_memory_limit(native_memory_grow_res as u64, additional_elements)?;
+            }
+
             match self.memory_usage.allocate_memory(
                 bytes,
                 NumBytes::new(0),
                 &self.api_type,
-                &self.sandbox_safe_system_state,
+                &mut self.sandbox_safe_system_state,
             ) {
                 Ok(()) => Ok(()),
-                Err(err @ HypervisorError::InsufficientCyclesInMemoryGrow { .. }) => {
+                Err(
+                    err @ (HypervisorError::InsufficientCyclesInMemoryGrow { .. }
+                    | HypervisorError::ReservedCyclesLimitExceededInMemoryGrow { .. }),
+                ) => {
                     // Return an out-of-cycles error instead of out-of-memory.
                     Err(err)
                 }
@@ -2374,10 +2610,13 @@ impl SystemApi for SystemApiImpl {
         match self.memory_usage.allocate_pages(
             additional_pages as usize,
             &self.api_type,
-            &self.sandbox_safe_system_state,
+            &mut self.sandbox_safe_system_state,
         ) {
             Ok(()) => Ok(StableGrowOutcome::Success),
-            Err(err @ HypervisorError::InsufficientCyclesInMemoryGrow { .. }) => {
+            Err(
+                err @ (HypervisorError::InsufficientCyclesInMemoryGrow { .. }
+                | HypervisorError::ReservedCyclesLimitExceededInMemoryGrow { .. }),
+            ) => {
                 // Trap instead of returning -1 in order to give the developer
                 // more actionable error message. Otherwise, they cannot
                 // distinguish between out-of-memory and out-of-cycles.
@@ -2401,7 +2640,7 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_canister_cycle_balance128(&self, dst: u32, heap: &mut [u8]) -> HypervisorResult<()> {
+    fn ic0_canister_cycle_balance128(&self, dst: usize, heap: &mut [u8]) -> HypervisorResult<()> {
         let result = {
             let method_name = "ic0_canister_cycle_balance128";
             let cycles = self.ic0_canister_cycle_balance_helper(method_name)?;
@@ -2431,7 +2670,7 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_msg_cycles_available128(&self, dst: u32, heap: &mut [u8]) -> HypervisorResult<()> {
+    fn ic0_msg_cycles_available128(&self, dst: usize, heap: &mut [u8]) -> HypervisorResult<()> {
         let result = {
             let method_name = "ic0_msg_cycles_available128";
             let cycles = self.ic0_msg_cycles_available_helper(method_name)?;
@@ -2456,7 +2695,7 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_msg_cycles_refunded128(&self, dst: u32, heap: &mut [u8]) -> HypervisorResult<()> {
+    fn ic0_msg_cycles_refunded128(&self, dst: usize, heap: &mut [u8]) -> HypervisorResult<()> {
         let result = {
             let method_name = "ic0_msg_cycles_refunded128";
             let cycles = self.ic0_msg_cycles_refunded_helper(method_name)?;
@@ -2495,7 +2734,7 @@ impl SystemApi for SystemApiImpl {
     fn ic0_msg_cycles_accept128(
         &mut self,
         max_amount: Cycles,
-        dst: u32,
+        dst: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
         let result = {
@@ -2560,9 +2799,9 @@ impl SystemApi for SystemApiImpl {
 
     fn ic0_data_certificate_copy(
         &self,
-        dst: u32,
-        offset: u32,
-        size: u32,
+        dst: usize,
+        offset: usize,
+        size: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
         let result = match &self.api_type {
@@ -2582,8 +2821,6 @@ impl SystemApi for SystemApiImpl {
                 data_certificate, ..
             } => match data_certificate {
                 Some(data_certificate) => {
-                    let (dst, offset, size) = (dst as usize, offset as usize, size as usize);
-
                     let (upper_bound, overflow) = offset.overflowing_add(size);
                     if overflow || upper_bound > data_certificate.len() {
                         return Err(ContractViolation(format!(
@@ -2629,7 +2866,12 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_certified_data_set(&mut self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()> {
+    fn ic0_certified_data_set(
+        &mut self,
+        src: usize,
+        size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<()> {
         let result = match &mut self.api_type {
             ApiType::Start { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2656,7 +2898,6 @@ impl SystemApi for SystemApiImpl {
                     )));
                 }
 
-                let (src, size) = (src as usize, size as usize);
                 let (upper_bound, overflow) = src.overflowing_add(size);
                 if overflow || upper_bound > heap.len() {
                     return Err(ContractViolation(format!(
@@ -2731,8 +2972,8 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
-    fn ic0_debug_print(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()> {
-        const MAX_DEBUG_MESSAGE_SIZE: u32 = 32 * 1024;
+    fn ic0_debug_print(&self, src: usize, size: usize, heap: &[u8]) -> HypervisorResult<()> {
+        const MAX_DEBUG_MESSAGE_SIZE: usize = 32 * 1024;
         let size = size.min(MAX_DEBUG_MESSAGE_SIZE);
         let msg = match valid_subslice("ic0.debug_print", src, size, heap) {
             Ok(bytes) => String::from_utf8_lossy(bytes).to_string(),
@@ -2763,8 +3004,8 @@ impl SystemApi for SystemApiImpl {
         Ok(())
     }
 
-    fn ic0_trap(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()> {
-        const MAX_ERROR_MESSAGE_SIZE: u32 = 16 * 1024;
+    fn ic0_trap(&self, src: usize, size: usize, heap: &[u8]) -> HypervisorResult<()> {
+        const MAX_ERROR_MESSAGE_SIZE: usize = 16 * 1024;
         let size = size.min(MAX_ERROR_MESSAGE_SIZE);
         let result = {
             let msg = valid_subslice("trap", src, size, heap)
@@ -2776,7 +3017,17 @@ impl SystemApi for SystemApiImpl {
         Err(result)
     }
 
-    fn ic0_is_controller(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<u32> {
+    fn save_log_message(&mut self, src: usize, size: usize, heap: &[u8]) {
+        const MAX_LOG_MESSAGE_SIZE: usize = 32 * 1024;
+        let size = size.min(MAX_LOG_MESSAGE_SIZE);
+        match valid_subslice("save_log_message", src, size, heap) {
+            Ok(bytes) => self.add_log_record(bytes),
+            // Like `ic0.debug_print`, logging never fails.
+            Err(_) => self.add_log_record(b"(debug message out of memory bounds)"),
+        }
+    }
+
+    fn ic0_is_controller(&self, src: usize, size: usize, heap: &[u8]) -> HypervisorResult<u32> {
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
@@ -2812,6 +3063,249 @@ impl SystemApi for SystemApiImpl {
         );
         result
     }
+
+    fn ic0_in_replicated_execution(&self) -> HypervisorResult<i32> {
+        let execution_mode = match &self.api_type {
+            ApiType::Start { .. }
+            | ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. } => ExecutionMode::Replicated,
+            ApiType::NonReplicatedQuery { .. } | ApiType::InspectMessage { .. } => {
+                ExecutionMode::NonReplicated
+            }
+            ApiType::ReplyCallback { execution_mode, .. }
+            | ApiType::RejectCallback { execution_mode, .. } => execution_mode.clone(),
+            // The cleanup callback runs in the mode of the callback it
+            // cleans up after.
+            ApiType::Cleanup { .. } => self.execution_parameters.execution_mode.clone(),
+        };
+        let result = match execution_mode {
+            ExecutionMode::Replicated => Ok(1),
+            ExecutionMode::NonReplicated => Ok(0),
+        };
+        trace_syscall!(self, ic0_in_replicated_execution, result);
+        result
+    }
+
+    fn ic0_subnet_self_size(&self) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_subnet_self_size")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => Ok(self
+                .sandbox_safe_system_state
+                .own_subnet_id()
+                .get_ref()
+                .as_slice()
+                .len()),
+        };
+        trace_syscall!(self, ic0_subnet_self_size, result);
+        result
+    }
+
+    fn ic0_subnet_self_copy(
+        &self,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_subnet_self_copy")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                valid_subslice("ic0.subnet_self_copy heap", dst, size, heap)?;
+                let subnet_id = self.sandbox_safe_system_state.own_subnet_id();
+                let id_bytes = subnet_id.get_ref().as_slice();
+                let slice = valid_subslice("ic0.subnet_self_copy id", offset, size, id_bytes)?;
+                deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
+                Ok(())
+            }
+        };
+        trace_syscall!(
+            self,
+            ic0_subnet_self_copy,
+            result,
+            dst,
+            offset,
+            size,
+            summarize(heap, dst, size)
+        );
+        result
+    }
+
+    fn ic0_root_key_size(&self) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_root_key_size")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => Ok(self.sandbox_safe_system_state.root_key().len()),
+        };
+        trace_syscall!(self, ic0_root_key_size, result);
+        result
+    }
+
+    fn ic0_root_key_copy(
+        &self,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_root_key_copy")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                valid_subslice("ic0.root_key_copy heap", dst, size, heap)?;
+                let root_key = self.sandbox_safe_system_state.root_key();
+                let slice = valid_subslice("ic0.root_key_copy key", offset, size, root_key)?;
+                deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
+                Ok(())
+            }
+        };
+        trace_syscall!(
+            self,
+            ic0_root_key_copy,
+            result,
+            dst,
+            offset,
+            size,
+            summarize(heap, dst, size)
+        );
+        result
+    }
+
+    fn ic0_cost_call(
+        &self,
+        method_name_size: u64,
+        payload_size: u64,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let cost = self.sandbox_safe_system_state.call_cost(
+            NumBytes::from(method_name_size),
+            NumBytes::from(payload_size),
+        );
+        let result = copy_cycles_to_heap(cost, dst, heap, "ic0_cost_call");
+        trace_syscall!(
+            self,
+            ic0_cost_call,
+            method_name_size,
+            payload_size,
+            dst,
+            summarize(heap, dst, 16)
+        );
+        result
+    }
+
+    fn ic0_cost_create_canister(&self, dst: usize, heap: &mut [u8]) -> HypervisorResult<()> {
+        let cost = self.sandbox_safe_system_state.create_canister_cost();
+        let result = copy_cycles_to_heap(cost, dst, heap, "ic0_cost_create_canister");
+        trace_syscall!(
+            self,
+            ic0_cost_create_canister,
+            dst,
+            summarize(heap, dst, 16)
+        );
+        result
+    }
+
+    fn ic0_cost_http_request(
+        &self,
+        request_size: u64,
+        max_res_bytes: u64,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let cost = self
+            .sandbox_safe_system_state
+            .http_request_cost(NumBytes::from(request_size), NumBytes::from(max_res_bytes));
+        let result = copy_cycles_to_heap(cost, dst, heap, "ic0_cost_http_request");
+        trace_syscall!(
+            self,
+            ic0_cost_http_request,
+            request_size,
+            max_res_bytes,
+            dst,
+            summarize(heap, dst, 16)
+        );
+        result
+    }
+
+    fn ic0_cost_sign_with_ecdsa(
+        &self,
+        src: usize,
+        size: usize,
+        ecdsa_curve: u32,
+        dst: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u32> {
+        let result = {
+            let name = valid_subslice("ic0.cost_sign_with_ecdsa", src, size, heap)?;
+            let key_id = match (ecdsa_curve, std::str::from_utf8(name)) {
+                (0, Ok(name)) => Some(EcdsaKeyId {
+                    curve: EcdsaCurve::Secp256k1,
+                    name: name.to_string(),
+                }),
+                _ => None,
+            };
+            match key_id {
+                None => Ok(1),
+                Some(key_id) => {
+                    match self.sandbox_safe_system_state.ecdsa_signature_cost(&key_id) {
+                        None => Ok(2),
+                        Some(cost) => {
+                            copy_cycles_to_heap(cost, dst, heap, "ic0_cost_sign_with_ecdsa")?;
+                            Ok(0)
+                        }
+                    }
+                }
+            }
+        };
+        trace_syscall!(
+            self,
+            ic0_cost_sign_with_ecdsa,
+            src,
+            size,
+            summarize(heap, src, size),
+            ecdsa_curve,
+            dst,
+            result
+        );
+        result
+    }
 }
 
 /// The default implementation of the `OutOfInstructionHandler` trait.
@@ -2830,7 +3324,7 @@ impl OutOfInstructionsHandler for DefaultOutOfInstructionsHandler {
 
 pub(crate) fn copy_cycles_to_heap(
     cycles: Cycles,
-    dst: u32,
+    dst: usize,
     heap: &mut [u8],
     method_name: &str,
 ) -> HypervisorResult<()> {
@@ -2839,7 +3333,6 @@ pub(crate) fn copy_cycles_to_heap(
     let size = bytes.len();
     assert_eq!(size, 16);
 
-    let dst = dst as usize;
     let (upper_bound, overflow) = dst.overflowing_add(size);
     if overflow || upper_bound > heap.len() {
         return Err(ContractViolation(format!(
@@ -2857,13 +3350,11 @@ pub(crate) fn copy_cycles_to_heap(
 
 pub(crate) fn valid_subslice<'a>(
     ctx: &str,
-    src: u32,
-    len: u32,
+    src: usize,
+    len: usize,
     slice: &'a [u8],
 ) -> HypervisorResult<&'a [u8]> {
-    let len = len as usize;
-    let src = src as usize;
-    if slice.len() < src + len {
+    if src.checked_add(len).map_or(true, |end| end > slice.len()) {
         return Err(ContractViolation(format!(
             "{}: src={} + length={} exceeds the slice size={}",
             ctx,
@@ -2,13 +2,18 @@ use crate::{sandbox_safe_system_state::SandboxSafeSystemState, valid_subslice};
 use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
 use ic_logger::ReplicaLogger;
 use ic_types::{
-    messages::{CallContextId, Request},
+    messages::{CallContextId, Request, NO_DEADLINE},
     methods::{Callback, WasmClosure},
-    CanisterId, Cycles, NumBytes, PrincipalId,
+    time::CoarseTime,
+    CanisterId, Cycles, NumBytes, PrincipalId, Time,
 };
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+/// The maximum timeout of a best-effort call, in seconds. Larger timeouts
+/// passed to `ic0.call_with_best_effort_response` are silently capped.
+pub const MAX_CALL_TIMEOUT_SECONDS: u32 = 300;
+
 /// Represents an under construction `Request`.
 ///
 /// The main differences from a `Request` are:
@@ -47,16 +52,20 @@ pub struct RequestInPrep {
     /// them up creating tricky bugs. Storing this an integer means that the two
     /// limits are stored as different types and are more difficult to mix up.
     multiplier_max_size_local_subnet: u64,
+    /// The timeout of a best-effort call, set by
+    /// `ic0.call_with_best_effort_response`. `None` for guaranteed response
+    /// calls.
+    timeout_seconds: Option<u32>,
 }
 
 impl RequestInPrep {
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         sender: CanisterId,
-        callee_src: u32,
-        callee_size: u32,
-        method_name_src: u32,
-        method_name_len: u32,
+        callee_src: usize,
+        callee_size: usize,
+        method_name_src: usize,
+        method_name_len: usize,
         heap: &[u8],
         on_reply: WasmClosure,
         on_reject: WasmClosure,
@@ -96,6 +105,7 @@ impl RequestInPrep {
             method_payload: Vec::new(),
             max_size_remote_subnet,
             multiplier_max_size_local_subnet,
+            timeout_seconds: None,
         })
     }
 
@@ -111,14 +121,26 @@ impl RequestInPrep {
         }
     }
 
+    pub(crate) fn set_timeout(&mut self, timeout_seconds: u32) -> HypervisorResult<()> {
+        if self.timeout_seconds.is_some() {
+            Err(HypervisorError::ContractViolation(
+                "ic0.call_with_best_effort_response can be called at most once between `ic0.call_new` and `ic0.call_perform`"
+                    .to_string(),
+            ))
+        } else {
+            self.timeout_seconds = Some(timeout_seconds.min(MAX_CALL_TIMEOUT_SECONDS));
+            Ok(())
+        }
+    }
+
     pub(crate) fn take_cycles(self) -> Cycles {
         self.cycles
     }
 
     pub(crate) fn extend_method_payload(
         &mut self,
-        src: u32,
-        size: u32,
+        src: usize,
+        size: usize,
         heap: &[u8],
     ) -> HypervisorResult<()> {
         let current_size = self.method_name.len() + self.method_payload.len();
@@ -161,8 +183,10 @@ pub(crate) fn into_request(
         method_payload,
         max_size_remote_subnet,
         multiplier_max_size_local_subnet,
+        timeout_seconds,
     }: RequestInPrep,
     call_context_id: CallContextId,
+    time: Time,
     sandbox_safe_system_state: &mut SandboxSafeSystemState,
     _logger: &ReplicaLogger,
 ) -> HypervisorResult<RequestWithPrepayment> {
@@ -185,6 +209,15 @@ pub(crate) fn into_request(
     let prepayment_for_response_transmission =
         sandbox_safe_system_state.prepayment_for_response_transmission();
 
+    let deadline = match timeout_seconds {
+        Some(timeout_seconds) => CoarseTime::from_secs_since_unix_epoch(
+            CoarseTime::floor(time)
+                .as_secs_since_unix_epoch()
+                .saturating_add(timeout_seconds),
+        ),
+        None => NO_DEADLINE,
+    };
+
     let callback_id = sandbox_safe_system_state.register_callback(Callback::new(
         call_context_id,
         Some(sender),
@@ -195,6 +228,7 @@ pub(crate) fn into_request(
         on_reply,
         on_reject,
         on_cleanup,
+        deadline,
     ))?;
 
     let req = Request {
@@ -204,6 +238,7 @@ pub(crate) fn into_request(
         method_payload,
         sender_reply_callback: callback_id,
         payment: cycles,
+        deadline,
     };
     // We cannot call `Request::payload_size_bytes()` before constructing the
     // request, so ensure our separate calculation matches the actual size.
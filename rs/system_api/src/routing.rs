@@ -3,13 +3,15 @@ use std::{collections::BTreeSet, fmt::Write};
 
 use ic_base_types::{CanisterId, PrincipalId, SubnetId};
 use ic_btc_interface::NetworkInRequest as BitcoinNetwork;
-use ic_error_types::UserError;
+use ic_error_types::{ErrorCode, UserError};
 use ic_ic00_types::{
     BitcoinGetBalanceArgs, BitcoinGetCurrentFeePercentilesArgs, BitcoinGetUtxosArgs,
-    BitcoinSendTransactionArgs, CanisterIdRecord, CanisterInfoRequest,
-    ComputeInitialEcdsaDealingsArgs, ECDSAPublicKeyArgs, EcdsaKeyId, InstallCodeArgs,
+    BitcoinSendTransactionArgs, CanisterIdRecord, CanisterInfoRequest, ClearChunkStoreArgs,
+    ComputeInitialEcdsaDealingsArgs, DeleteCanisterSnapshotArgs, ECDSAPublicKeyArgs, EcdsaKeyId,
+    InstallChunkedCodeArgs, InstallCodeArgs, ListCanisterSnapshotArgs, LoadCanisterSnapshotArgs,
     Method as Ic00Method, Payload, ProvisionalTopUpCanisterArgs, SetControllerArgs,
-    SignWithECDSAArgs, UninstallCodeArgs, UpdateSettingsArgs,
+    SignWithECDSAArgs, StoredChunksArgs, TakeCanisterSnapshotArgs, UninstallCodeArgs,
+    UpdateSettingsArgs, UploadChunkArgs,
 };
 use ic_replicated_state::NetworkTopology;
 
@@ -77,6 +79,57 @@ pub(super) fn resolve_destination(
                     ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::InstallCode)
                 })
         }
+        Ok(Ic00Method::InstallChunkedCode) => {
+            // Find the destination canister from the payload.
+            let args = InstallChunkedCodeArgs::decode(payload)?;
+            let canister_id = args.target_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(
+                        canister_id,
+                        Ic00Method::InstallChunkedCode,
+                    )
+                })
+        }
+        Ok(Ic00Method::UploadChunk) => {
+            let args = UploadChunkArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::UploadChunk)
+                })
+        }
+        Ok(Ic00Method::ClearChunkStore) => {
+            let args = ClearChunkStoreArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(
+                        canister_id,
+                        Ic00Method::ClearChunkStore,
+                    )
+                })
+        }
+        Ok(Ic00Method::StoredChunks) => {
+            let args = StoredChunksArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::StoredChunks)
+                })
+        }
         Ok(Ic00Method::SetController) => {
             let args = SetControllerArgs::decode(payload)?;
             let canister_id = args.get_canister_id();
@@ -125,6 +178,50 @@ pub(super) fn resolve_destination(
                     ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
                 })
         }
+        Ok(Ic00Method::TakeCanisterSnapshot) => {
+            let args = TakeCanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or_else(|| {
+                    ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
+                })
+        }
+        Ok(Ic00Method::LoadCanisterSnapshot) => {
+            let args = LoadCanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or_else(|| {
+                    ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
+                })
+        }
+        Ok(Ic00Method::ListCanisterSnapshots) => {
+            let args = ListCanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or_else(|| {
+                    ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
+                })
+        }
+        Ok(Ic00Method::DeleteCanisterSnapshot) => {
+            let args = DeleteCanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .map(|subnet_id| subnet_id.get())
+                .ok_or_else(|| {
+                    ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
+                })
+        }
         Ok(Ic00Method::ProvisionalTopUpCanister) => {
             let args = ProvisionalTopUpCanisterArgs::decode(payload)?;
             let canister_id = args.get_canister_id();
@@ -199,6 +296,15 @@ pub(super) fn resolve_destination(
                 EcdsaSubnetKind::OnlyHoldsKey,
             )
         }
+        Ok(Ic00Method::FetchCanisterLogs) => {
+            Err(ResolveDestinationError::UserError(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!(
+                    "{} API is only accessible in non-replicated mode",
+                    Ic00Method::FetchCanisterLogs
+                ),
+            )))
+        }
         Err(_) => Err(ResolveDestinationError::MethodNotFound(
             method_name.to_string(),
         )),
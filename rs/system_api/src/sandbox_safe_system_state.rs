@@ -3,10 +3,13 @@ use std::collections::{BTreeMap, BTreeSet};
 use crate::{routing::ResolveDestinationError, ApiType};
 use ic_base_types::{CanisterId, NumBytes, NumSeconds, PrincipalId, SubnetId};
 use ic_constants::{LOG_CANISTER_OPERATION_CYCLES_THRESHOLD, SMALL_APP_SUBNET_MAX_SIZE};
-use ic_cycles_account_manager::{CyclesAccountManager, CyclesAccountManagerError};
+use ic_cycles_account_manager::{
+    CyclesAccountManager, CyclesAccountManagerError, ResourceSaturation,
+};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CreateCanisterArgs, InstallCodeArgs, Method as Ic00Method, Payload,
+    CreateCanisterArgs, EcdsaKeyId, InstallChunkedCodeArgs, InstallCodeArgs,
+    LoadCanisterSnapshotArgs, Method as Ic00Method, Payload,
     ProvisionalCreateCanisterWithCyclesArgs, SetControllerArgs, UninstallCodeArgs,
     UpdateSettingsArgs, IC_00,
 };
@@ -64,6 +67,9 @@ pub struct SystemStateChanges {
     request_slots_used: BTreeMap<CanisterId, usize>,
     requests: Vec<Request>,
     pub(super) new_global_timer: Option<CanisterTimer>,
+    /// Cycles moved from the main balance to the reserved balance for newly
+    /// allocated memory. Not included in `cycles_balance_change`.
+    reserved_cycles: Cycles,
 }
 
 impl Default for SystemStateChanges {
@@ -77,6 +83,7 @@ impl Default for SystemStateChanges {
             request_slots_used: BTreeMap::new(),
             requests: vec![],
             new_global_timer: None,
+            reserved_cycles: Cycles::zero(),
         }
     }
 }
@@ -207,6 +214,10 @@ impl SystemStateChanges {
                 .map(|record| record.get_sender_canister_version()),
             Ok(Ic00Method::UninstallCode) => UninstallCodeArgs::decode(payload)
                 .map(|record| record.get_sender_canister_version()),
+            Ok(Ic00Method::LoadCanisterSnapshot) => LoadCanisterSnapshotArgs::decode(payload)
+                .map(|record| record.get_sender_canister_version()),
+            Ok(Ic00Method::InstallChunkedCode) => InstallChunkedCodeArgs::decode(payload)
+                .map(|record| record.get_sender_canister_version()),
             Ok(Ic00Method::ProvisionalCreateCanisterWithCycles) => {
                 ProvisionalCreateCanisterWithCyclesArgs::decode(payload)
                     .map(|record| record.get_sender_canister_version())
@@ -224,6 +235,13 @@ impl SystemStateChanges {
             | Ok(Ic00Method::ECDSAPublicKey)
             | Ok(Ic00Method::ComputeInitialEcdsaDealings)
             | Ok(Ic00Method::ProvisionalTopUpCanister)
+            | Ok(Ic00Method::TakeCanisterSnapshot)
+            | Ok(Ic00Method::ListCanisterSnapshots)
+            | Ok(Ic00Method::DeleteCanisterSnapshot)
+            | Ok(Ic00Method::FetchCanisterLogs)
+            | Ok(Ic00Method::UploadChunk)
+            | Ok(Ic00Method::ClearChunkStore)
+            | Ok(Ic00Method::StoredChunks)
             | Ok(Ic00Method::BitcoinSendTransactionInternal)
             | Ok(Ic00Method::BitcoinGetSuccessors)
             | Ok(Ic00Method::BitcoinGetBalance)
@@ -273,6 +291,11 @@ impl SystemStateChanges {
         // Observe consumed cycles.
         system_state.observe_consumed_cycles(consumed_cycles);
 
+        // Move the cycles reserved for allocated memory.
+        system_state
+            .reserve_cycles(self.reserved_cycles)
+            .map_err(|err| Self::error(format!("Failed to reserve cycles: {:?}", err)))?;
+
         // Verify we don't accept more cycles than are available from each call
         // context and update each call context balance
         if !self.call_context_balance_taken.is_empty() {
@@ -291,7 +314,7 @@ impl SystemStateChanges {
                 })?;
                 if (*amount_taken).get() > LOG_CANISTER_OPERATION_CYCLES_THRESHOLD {
                     match call_context.call_origin() {
-                        CallOrigin::CanisterUpdate(origin_canister_id, _)
+                        CallOrigin::CanisterUpdate(origin_canister_id, _, _)
                         | CallOrigin::CanisterQuery(origin_canister_id, _) => info!(
                             logger,
                             "Canister {} accepted {} cycles from canister {}.",
@@ -515,6 +538,14 @@ pub struct SandboxSafeSystemState {
     global_timer: CanisterTimer,
     canister_version: u64,
     controllers: BTreeSet<PrincipalId>,
+    wasm_memory_limit: Option<NumBytes>,
+    initial_reserved_balance: Cycles,
+    reserved_balance_limit: Option<Cycles>,
+    /// The sizes of the subnets that sign with the available ECDSA keys.
+    ecdsa_signing_subnet_sizes: BTreeMap<EcdsaKeyId, usize>,
+    /// The DER-encoded public key of the IC root, i.e. of the NNS subnet.
+    #[serde(with = "serde_bytes")]
+    root_key: Vec<u8>,
 }
 
 impl SandboxSafeSystemState {
@@ -539,6 +570,11 @@ impl SandboxSafeSystemState {
         global_timer: CanisterTimer,
         canister_version: u64,
         controllers: BTreeSet<PrincipalId>,
+        wasm_memory_limit: Option<NumBytes>,
+        initial_reserved_balance: Cycles,
+        reserved_balance_limit: Option<Cycles>,
+        ecdsa_signing_subnet_sizes: BTreeMap<EcdsaKeyId, usize>,
+        root_key: Vec<u8>,
     ) -> Self {
         Self {
             canister_id,
@@ -560,6 +596,11 @@ impl SandboxSafeSystemState {
             global_timer,
             canister_version,
             controllers,
+            wasm_memory_limit,
+            initial_reserved_balance,
+            reserved_balance_limit,
+            ecdsa_signing_subnet_sizes,
+            root_key,
         }
     }
 
@@ -601,6 +642,19 @@ impl SandboxSafeSystemState {
         let subnet_size = network_topology
             .get_subnet_size(&cycles_account_manager.get_subnet_id())
             .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
+        let ecdsa_signing_subnet_sizes = network_topology
+            .ecdsa_signing_subnets
+            .iter()
+            .filter_map(|(key_id, subnets)| {
+                let subnet_size = network_topology.get_subnet_size(subnets.first()?)?;
+                Some((key_id.clone(), subnet_size))
+            })
+            .collect();
+        let root_key = network_topology
+            .subnets
+            .get(&network_topology.nns_subnet_id)
+            .map(|subnet_topology| subnet_topology.public_key.clone())
+            .unwrap_or_default();
 
         Self::new_internal(
             system_state.canister_id,
@@ -622,6 +676,11 @@ impl SandboxSafeSystemState {
             system_state.global_timer,
             system_state.canister_version,
             system_state.controllers.clone(),
+            system_state.wasm_memory_limit,
+            system_state.reserved_balance(),
+            system_state.reserved_balance_limit(),
+            ecdsa_signing_subnet_sizes,
+            root_key,
         )
     }
 
@@ -629,6 +688,16 @@ impl SandboxSafeSystemState {
         self.canister_id
     }
 
+    /// Returns the id of the subnet the canister is running on.
+    pub fn own_subnet_id(&self) -> SubnetId {
+        self.cycles_account_manager.get_subnet_id()
+    }
+
+    /// Returns the DER-encoded public key of the IC root.
+    pub fn root_key(&self) -> &[u8] {
+        &self.root_key
+    }
+
     pub fn global_timer(&self) -> CanisterTimer {
         self.global_timer
     }
@@ -637,6 +706,11 @@ impl SandboxSafeSystemState {
         self.canister_version
     }
 
+    /// Returns the limit on the Wasm memory of the canister, if any.
+    pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
+        self.wasm_memory_limit
+    }
+
     pub fn set_global_timer(&mut self, timer: CanisterTimer) {
         // Update both sandbox global timer and the changes.
         self.system_state_changes.new_global_timer = Some(timer);
@@ -679,7 +753,13 @@ impl SandboxSafeSystemState {
 
     pub(super) fn cycles_balance(&self) -> Cycles {
         let cycles_change = self.system_state_changes.cycles_balance_change;
-        cycles_change.apply(self.initial_cycles_balance)
+        cycles_change.apply(self.initial_cycles_balance) - self.system_state_changes.reserved_cycles
+    }
+
+    /// Returns the reserved balance including the cycles reserved so far
+    /// during this execution.
+    pub fn reserved_balance(&self) -> Cycles {
+        self.initial_reserved_balance + self.system_state_changes.reserved_cycles
     }
 
     pub(super) fn msg_cycles_available(&self, call_context_id: CallContextId) -> Cycles {
@@ -696,8 +776,12 @@ impl SandboxSafeSystemState {
     }
 
     fn update_balance_change(&mut self, new_balance: Cycles) {
-        self.system_state_changes.cycles_balance_change =
-            CyclesBalanceChange::new(self.initial_cycles_balance, new_balance);
+        // Reserved cycles are tracked separately and are added back here
+        // because `cycles_balance()` excludes them.
+        self.system_state_changes.cycles_balance_change = CyclesBalanceChange::new(
+            self.initial_cycles_balance,
+            new_balance + self.system_state_changes.reserved_cycles,
+        );
     }
 
     /// Same as [`update_balance_change`], but asserts the balance has decreased
@@ -787,6 +871,46 @@ impl SandboxSafeSystemState {
             .prepayment_for_response_transmission(self.subnet_size)
     }
 
+    /// Returns the cycles that are withdrawn when sending a request with the
+    /// given method name and payload sizes. This is the same amount that
+    /// `push_output_request` charges.
+    pub fn call_cost(&self, method_name_size: NumBytes, payload_size: NumBytes) -> Cycles {
+        self.cycles_account_manager
+            .xnet_call_performed_fee(self.subnet_size)
+            + self
+                .cycles_account_manager
+                .xnet_call_bytes_transmitted_fee(method_name_size + payload_size, self.subnet_size)
+            + self.prepayment_for_response_transmission()
+            + self.prepayment_for_response_execution()
+    }
+
+    /// Returns the fee for creating a canister on this subnet.
+    pub fn create_canister_cost(&self) -> Cycles {
+        self.cycles_account_manager
+            .canister_creation_fee(self.subnet_size)
+    }
+
+    /// Returns the fee for an HTTPS outcall with the given request size and
+    /// maximum response size.
+    pub fn http_request_cost(&self, request_size: NumBytes, max_response_size: NumBytes) -> Cycles {
+        self.cycles_account_manager.http_request_fee(
+            request_size,
+            Some(max_response_size),
+            self.subnet_size,
+        )
+    }
+
+    /// Returns the fee for a signature with the given ECDSA key, or `None` if
+    /// no subnet signs with the key.
+    pub fn ecdsa_signature_cost(&self, key_id: &EcdsaKeyId) -> Option<Cycles> {
+        self.ecdsa_signing_subnet_sizes
+            .get(key_id)
+            .map(|subnet_size| {
+                self.cycles_account_manager
+                    .ecdsa_signature_fee(*subnet_size)
+            })
+    }
+
     pub(super) fn withdraw_cycles_for_transfer(
         &mut self,
         canister_current_memory_usage: NumBytes,
@@ -924,6 +1048,72 @@ impl SandboxSafeSystemState {
         }
     }
 
+    /// Moves cycles from the main balance to the reserved balance to pay for
+    /// storing `allocated_bytes` of newly allocated memory in the future. The
+    /// amount depends on how close the subnet memory usage is to its capacity.
+    ///
+    /// Returns `Err(HypervisorError::ReservedCyclesLimitExceededInMemoryGrow)`
+    /// if the reserved balance would exceed its limit and
+    /// `Err(HypervisorError::InsufficientCyclesInMemoryGrow)` if the main
+    /// balance is too low.
+    pub(super) fn reserve_storage_cycles(
+        &mut self,
+        allocated_bytes: NumBytes,
+        subnet_memory_saturation: &ResourceSaturation,
+        api_type: &ApiType,
+    ) -> HypervisorResult<()> {
+        match api_type {
+            ApiType::Update { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::Cleanup { .. } => {}
+
+            ApiType::Start { .. } | ApiType::Init { .. } | ApiType::PreUpgrade { .. } => {
+                // Individual endpoints of install_code do not reserve cycles.
+                // Instead, cycles are reserved at the end of install_code.
+                return Ok(());
+            }
+
+            ApiType::InspectMessage { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. } => {
+                // Queries do not reserve cycles because the state changes are
+                // discarded anyways.
+                return Ok(());
+            }
+        }
+
+        let cycles_to_reserve = self.cycles_account_manager.storage_reservation_cycles(
+            allocated_bytes,
+            subnet_memory_saturation,
+            self.subnet_size,
+        );
+        if cycles_to_reserve == Cycles::zero() {
+            return Ok(());
+        }
+        if let Some(limit) = self.reserved_balance_limit {
+            let requested = self.reserved_balance() + cycles_to_reserve;
+            if requested > limit {
+                return Err(HypervisorError::ReservedCyclesLimitExceededInMemoryGrow {
+                    bytes: allocated_bytes,
+                    requested,
+                    limit,
+                });
+            }
+        }
+        let available = self.cycles_balance();
+        if cycles_to_reserve > available {
+            return Err(HypervisorError::InsufficientCyclesInMemoryGrow {
+                bytes: allocated_bytes,
+                available,
+                threshold: cycles_to_reserve,
+            });
+        }
+        self.system_state_changes.reserved_cycles += cycles_to_reserve;
+        Ok(())
+    }
+
     // Returns `true` if the freezing threshold needs to be checked for the given
     // API type when growing memory.
     fn should_check_freezing_threshold_for_memory_grow(&self, api_type: &ApiType) -> bool {
@@ -971,7 +1161,7 @@ mod tests {
 
     #[test]
     fn test_apply_balance_changes() {
-        let mut system_state = SystemState::new_running(
+        let mut system_state = SystemState::new_running_for_testing(
             canister_test_id(0),
             user_test_id(1).get(),
             Cycles::new(1_000_000_000),
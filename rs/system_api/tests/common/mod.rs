@@ -21,7 +21,7 @@ use ic_test_utilities::{
     types::ids::{call_context_test_id, canister_test_id, subnet_test_id, user_test_id},
 };
 use ic_types::{
-    messages::{CallContextId, CallbackId, RejectContext},
+    messages::{CallContextId, CallbackId, RejectContext, NO_DEADLINE},
     methods::SystemMethod,
     ComputeAllocation, Cycles, MemoryAllocation, NumInstructions, Time,
 };
@@ -83,6 +83,7 @@ impl ApiTypeBuilder {
             Cycles::zero(),
             user_test_id(1).get(),
             CallContextId::from(1),
+            NO_DEADLINE,
         )
     }
 
@@ -100,6 +101,7 @@ impl ApiTypeBuilder {
             vec![],
             incoming_cycles,
             CallContextId::new(1),
+            NO_DEADLINE,
             false,
             ExecutionMode::Replicated,
         )
@@ -111,6 +113,7 @@ impl ApiTypeBuilder {
             reject_context,
             Cycles::zero(),
             call_context_test_id(1),
+            NO_DEADLINE,
             false,
             ExecutionMode::Replicated,
         )
@@ -154,7 +157,7 @@ pub fn get_system_state() -> SystemState {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(50),
             Time::from_nanos_since_unix_epoch(0),
         );
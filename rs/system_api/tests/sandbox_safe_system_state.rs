@@ -11,7 +11,7 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::canister_state::system_state::CyclesUseCase;
 use ic_replicated_state::testing::SystemStateTesting;
 use ic_replicated_state::{NetworkTopology, SystemState};
-use ic_system_api::sandbox_safe_system_state::SandboxSafeSystemState;
+use ic_system_api::{sandbox_safe_system_state::SandboxSafeSystemState, MAX_CALL_TIMEOUT_SECONDS};
 use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
     mock_time,
@@ -23,7 +23,9 @@ use ic_test_utilities::{
 };
 use ic_types::nominal_cycles::NominalCycles;
 use ic_types::{
-    messages::MAX_INTER_CANISTER_PAYLOAD_IN_BYTES, ComputeAllocation, Cycles, NumInstructions,
+    messages::{RequestOrResponse, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES},
+    time::CoarseTime,
+    ComputeAllocation, Cycles, NumInstructions,
 };
 use prometheus::IntCounter;
 use std::collections::BTreeSet;
@@ -51,7 +53,7 @@ fn push_output_request_fails_not_enough_cycles_for_request() {
 
     // Set cycles balance low enough that not even the cost for transferring
     // the request is covered.
-    let system_state = SystemState::new_running(
+    let system_state = SystemState::new_running_for_testing(
         canister_test_id(0),
         user_test_id(1).get(),
         request_payload_cost - Cycles::new(10),
@@ -101,7 +103,7 @@ fn push_output_request_fails_not_enough_cycles_for_response() {
 
     // Set cycles balance to a number that is enough to cover for the request
     // transfer but not to cover the cost of processing the expected response.
-    let system_state = SystemState::new_running(
+    let system_state = SystemState::new_running_for_testing(
         canister_test_id(0),
         user_test_id(1).get(),
         total_cost - Cycles::new(10),
@@ -133,7 +135,7 @@ fn push_output_request_succeeds_with_enough_cycles() {
         .with_max_num_instructions(MAX_NUM_INSTRUCTIONS)
         .build();
 
-    let system_state = SystemState::new_running(
+    let system_state = SystemState::new_running_for_testing(
         canister_test_id(0),
         user_test_id(1).get(),
         INITIAL_CYCLES,
@@ -173,7 +175,7 @@ fn correct_charging_source_canister_for_a_request() {
         .with_max_num_instructions(MAX_NUM_INSTRUCTIONS)
         .with_subnet_type(subnet_type)
         .build();
-    let mut system_state = SystemState::new_running(
+    let mut system_state = SystemState::new_running_for_testing(
         canister_test_id(0),
         user_test_id(1).get(),
         INITIAL_CYCLES,
@@ -347,6 +349,50 @@ fn is_controller_test() {
     }
 }
 
+#[test]
+fn best_effort_call_has_capped_deadline() {
+    let mut system_state = SystemStateBuilder::default().build();
+    let own_canister_id = system_state.canister_id;
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let mut api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &system_state,
+        cycles_account_manager,
+    );
+
+    api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]).unwrap();
+    api.ic0_call_with_best_effort_response(MAX_CALL_TIMEOUT_SECONDS + 100)
+        .unwrap();
+    // The timeout can only be set once per call.
+    api.ic0_call_with_best_effort_response(10).unwrap_err();
+    api.ic0_call_perform().unwrap();
+
+    api.into_system_state_changes()
+        .apply_changes(
+            mock_time(),
+            &mut system_state,
+            &default_network_topology(),
+            subnet_test_id(1),
+            &no_op_logger(),
+        )
+        .unwrap();
+
+    let expected_deadline = CoarseTime::from_secs_since_unix_epoch(
+        CoarseTime::floor(mock_time()).as_secs_since_unix_epoch() + MAX_CALL_TIMEOUT_SECONDS,
+    );
+    let request = match system_state.output_into_iter(own_canister_id).next() {
+        Some((_, RequestOrResponse::Request(request))) => request,
+        msg => panic!("Expected a request, got {:?}", msg),
+    };
+    assert_eq!(request.deadline, expected_deadline);
+    let callback = system_state
+        .call_context_manager()
+        .unwrap()
+        .callback(&request.sender_reply_callback)
+        .unwrap();
+    assert_eq!(callback.deadline, expected_deadline);
+}
+
 #[test]
 fn call_increases_cycles_consumed_metric() {
     let mut system_state = SystemStateBuilder::default().build();
@@ -406,7 +452,7 @@ fn test_inter_canister_call(
         .with_subnet_type(subnet_type)
         .build();
     let sender_controller = user_test_id(1).get();
-    let mut system_state = SystemState::new_running(
+    let mut system_state = SystemState::new_running_for_testing(
         sender,
         sender_controller,
         INITIAL_CYCLES,
@@ -3,15 +3,18 @@ use ic_config::{
     embedders::Config as EmbeddersConfig, flag_status::FlagStatus, subnet_config::SchedulerConfig,
 };
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
+use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::RejectCode;
+use ic_ic00_types::{EcdsaCurve, EcdsaKeyId};
 use ic_interfaces::execution_environment::{
-    CanisterOutOfCyclesError, HypervisorError, HypervisorResult, PerformanceCounterType,
-    SubnetAvailableMemory, SystemApi, TrapCode,
+    CanisterOutOfCyclesError, ExecutionMode, HypervisorError, HypervisorResult,
+    PerformanceCounterType, SubnetAvailableMemory, SystemApi, TrapCode,
 };
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    testing::CanisterQueuesTesting, CallOrigin, Memory, NetworkTopology, SystemState,
+    testing::CanisterQueuesTesting, CallOrigin, Memory, NetworkTopology, SubnetTopology,
+    SystemState,
 };
 use ic_system_api::{
     sandbox_safe_system_state::SandboxSafeSystemState, ApiType, DefaultOutOfInstructionsHandler,
@@ -22,15 +25,17 @@ use ic_test_utilities::{
     mock_time,
     state::SystemStateBuilder,
     types::{
-        ids::{call_context_test_id, canister_test_id, subnet_test_id, user_test_id},
+        ids::{call_context_test_id, canister_test_id, node_test_id, subnet_test_id, user_test_id},
         messages::RequestBuilder,
     },
 };
 use ic_types::{
-    messages::{CallContextId, CallbackId, RejectContext, MAX_RESPONSE_COUNT_BYTES},
+    messages::{CallContextId, CallbackId, RejectContext, MAX_RESPONSE_COUNT_BYTES, NO_DEADLINE},
     methods::{Callback, WasmClosure},
-    time, CanisterTimer, CountBytes, Cycles, NumInstructions, Time,
+    time::{self, CoarseTime},
+    CanisterTimer, CountBytes, Cycles, NumBytes, NumInstructions, Time,
 };
+use maplit::btreemap;
 use std::{
     collections::BTreeSet,
     convert::{From, TryInto},
@@ -44,7 +49,7 @@ use common::*;
 const INITIAL_CYCLES: Cycles = Cycles::new(1 << 40);
 
 fn get_system_state_with_cycles(cycles_amount: Cycles) -> SystemState {
-    SystemState::new_running(
+    SystemState::new_running_for_testing(
         canister_test_id(42),
         user_test_id(24).get(),
         cycles_amount,
@@ -149,6 +154,7 @@ fn test_canister_init_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -156,6 +162,7 @@ fn test_canister_init_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -205,6 +212,7 @@ fn test_canister_update_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -212,6 +220,7 @@ fn test_canister_update_support() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -261,6 +270,7 @@ fn test_canister_replicated_query_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -268,6 +278,7 @@ fn test_canister_replicated_query_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -317,6 +328,7 @@ fn test_canister_pure_query_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -324,6 +336,7 @@ fn test_canister_pure_query_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -383,6 +396,7 @@ fn test_canister_stateful_query_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -390,6 +404,7 @@ fn test_canister_stateful_query_support() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -439,6 +454,7 @@ fn test_reply_api_support_on_nns() {
     assert_api_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -446,6 +462,7 @@ fn test_reply_api_support_on_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -495,6 +512,7 @@ fn test_reply_api_support_non_nns() {
     assert_api_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -502,6 +520,7 @@ fn test_reply_api_support_non_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -554,6 +573,7 @@ fn test_reject_api_support_on_nns() {
     assert_api_supported(api.ic0_msg_reject_code());
     assert_api_supported(api.ic0_msg_reject_msg_size());
     assert_api_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -561,6 +581,7 @@ fn test_reject_api_support_on_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -613,6 +634,7 @@ fn test_reject_api_support_non_nns() {
     assert_api_supported(api.ic0_msg_reject_code());
     assert_api_supported(api.ic0_msg_reject_msg_size());
     assert_api_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -620,6 +642,7 @@ fn test_reject_api_support_non_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -669,6 +692,7 @@ fn test_pre_upgrade_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -676,6 +700,7 @@ fn test_pre_upgrade_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -725,6 +750,7 @@ fn test_start_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_deadline());
     assert_api_not_supported(api.ic0_canister_self_size());
     assert_api_not_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -732,6 +758,7 @@ fn test_start_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -781,6 +808,7 @@ fn test_cleanup_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -788,6 +816,7 @@ fn test_cleanup_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -842,6 +871,7 @@ fn test_inspect_message_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -849,6 +879,7 @@ fn test_inspect_message_support() {
     assert_api_not_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_not_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_not_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_not_supported(api.ic0_call_cycles_add(0));
     assert_api_not_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_not_supported(api.ic0_call_perform());
@@ -899,6 +930,7 @@ fn test_canister_heartbeat_support() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -906,6 +938,7 @@ fn test_canister_heartbeat_support() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -955,6 +988,7 @@ fn test_canister_heartbeat_support_nns() {
     assert_api_not_supported(api.ic0_msg_reject_code());
     assert_api_not_supported(api.ic0_msg_reject_msg_size());
     assert_api_not_supported(api.ic0_msg_reject_msg_copy(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_msg_deadline());
     assert_api_supported(api.ic0_canister_self_size());
     assert_api_supported(api.ic0_canister_self_copy(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_debug_print(0, 0, &[]));
@@ -962,6 +996,7 @@ fn test_canister_heartbeat_support_nns() {
     assert_api_supported(api.ic0_call_new(0, 0, 0, 0, 0, 0, 0, 0, &[]));
     assert_api_supported(api.ic0_call_data_append(0, 0, &[]));
     assert_api_supported(api.ic0_call_on_cleanup(0, 0));
+    assert_api_supported(api.ic0_call_with_best_effort_response(0));
     assert_api_supported(api.ic0_call_cycles_add(0));
     assert_api_supported(api.ic0_call_cycles_add128(Cycles::new(0)));
     assert_api_supported(api.ic0_call_perform());
@@ -989,6 +1024,31 @@ fn test_canister_heartbeat_support_nns() {
     check_stable_apis_support(api);
 }
 
+#[test]
+fn msg_deadline_returns_deadline_of_call() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let system_state = SystemStateBuilder::default().build();
+
+    let api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &system_state,
+        cycles_account_manager,
+    );
+    assert_eq!(api.ic0_msg_deadline().unwrap(), 0);
+
+    let api_type = ApiType::reply_callback(
+        mock_time(),
+        vec![],
+        Cycles::zero(),
+        CallContextId::new(1),
+        CoarseTime::from_secs_since_unix_epoch(42),
+        false,
+        ExecutionMode::Replicated,
+    );
+    let api = get_system_api(api_type, &system_state, cycles_account_manager);
+    assert_eq!(api.ic0_msg_deadline().unwrap(), 42_000_000_000);
+}
+
 #[test]
 fn test_discard_cycles_charge_by_new_call() {
     let cycles_amount = Cycles::from(1_000_000_000_000u128);
@@ -1136,7 +1196,7 @@ fn test_canister_balance() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(50),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1164,7 +1224,7 @@ fn test_canister_cycle_balance() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(50),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1198,7 +1258,7 @@ fn test_msg_cycles_available_traps() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             available_cycles,
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1322,7 +1382,7 @@ fn canister_status() {
     );
     assert_eq!(api.ic0_canister_status(), Ok(1));
 
-    let stopping_system_state = SystemState::new_stopping(
+    let stopping_system_state = SystemState::new_stopping_for_testing(
         canister_test_id(42),
         user_test_id(24).get(),
         INITIAL_CYCLES,
@@ -1335,7 +1395,7 @@ fn canister_status() {
     );
     assert_eq!(api.ic0_canister_status(), Ok(2));
 
-    let stopped_system_state = SystemState::new_stopped(
+    let stopped_system_state = SystemState::new_stopped_for_testing(
         canister_test_id(42),
         user_test_id(24).get(),
         INITIAL_CYCLES,
@@ -1359,7 +1419,7 @@ fn msg_cycles_accept_all_cycles_in_call_context() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::from(amount),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1382,7 +1442,7 @@ fn msg_cycles_accept_all_cycles_in_call_context_when_more_asked() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(40),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1413,7 +1473,7 @@ fn call_perform_not_enough_cycles_resets_state() {
         .call_context_manager_mut()
         .unwrap()
         .new_call_context(
-            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5)),
+            CallOrigin::CanisterUpdate(canister_test_id(33), CallbackId::from(5), NO_DEADLINE),
             Cycles::new(40),
             Time::from_nanos_since_unix_epoch(0),
         );
@@ -1517,6 +1577,7 @@ fn take_execution_result_properly_frees_memory() {
             WasmClosure::new(0, 0),
             WasmClosure::new(0, 0),
             None,
+            NO_DEADLINE,
         ))
         .unwrap();
     let mut api = SystemApiImpl::new(
@@ -1596,6 +1657,7 @@ fn push_output_request_respects_memory_limits() {
             WasmClosure::new(0, 0),
             WasmClosure::new(0, 0),
             None,
+            NO_DEADLINE,
         ))
         .unwrap();
     let mut api = SystemApiImpl::new(
@@ -1699,6 +1761,7 @@ fn push_output_request_oversized_request_memory_limits() {
             WasmClosure::new(0, 0),
             WasmClosure::new(0, 0),
             None,
+            NO_DEADLINE,
         ))
         .unwrap();
     let mut api = SystemApiImpl::new(
@@ -1826,7 +1889,7 @@ fn ic0_is_controller_test() {
     for i in 1..5 {
         let controller = user_test_id(i).get();
         assert_eq!(
-            api.ic0_is_controller(0, controller.as_slice().len() as u32, controller.as_slice())
+            api.ic0_is_controller(0, controller.as_slice().len(), controller.as_slice())
                 .unwrap(),
             (i <= 2) as u32
         );
@@ -1842,9 +1905,199 @@ fn ic0_is_controller_invalid_principal_id() {
     );
     let controller = [0u8; 70];
     assert!(matches!(
-        api.ic0_is_controller(0, controller.len() as u32, &controller),
+        api.ic0_is_controller(0, controller.len(), &controller),
         Err(HypervisorError::InvalidPrincipalId(
             PrincipalIdBlobParseError(..)
         ))
     ));
 }
+
+#[test]
+fn ic0_cost_apis_match_cycles_account_manager() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &SystemStateBuilder::default().build(),
+        cycles_account_manager,
+    );
+    let subnet_size = SMALL_APP_SUBNET_MAX_SIZE;
+    let mut heap = vec![0; 16];
+
+    api.ic0_cost_call(10, 1000, 0, &mut heap).unwrap();
+    let expected = cycles_account_manager.xnet_call_performed_fee(subnet_size)
+        + cycles_account_manager.xnet_call_bytes_transmitted_fee(NumBytes::from(1010), subnet_size)
+        + cycles_account_manager.prepayment_for_response_transmission(subnet_size)
+        + cycles_account_manager.prepayment_for_response_execution(subnet_size);
+    assert_eq!(heap, expected.get().to_le_bytes());
+
+    api.ic0_cost_create_canister(0, &mut heap).unwrap();
+    assert_eq!(
+        heap,
+        cycles_account_manager
+            .canister_creation_fee(subnet_size)
+            .get()
+            .to_le_bytes()
+    );
+
+    api.ic0_cost_http_request(100, 2000, 0, &mut heap).unwrap();
+    let expected = cycles_account_manager.http_request_fee(
+        NumBytes::from(100),
+        Some(NumBytes::from(2000)),
+        subnet_size,
+    );
+    assert_eq!(heap, expected.get().to_le_bytes());
+
+    // The destination must be within the heap.
+    assert!(api.ic0_cost_create_canister(1, &mut heap).is_err());
+}
+
+fn get_system_api_with_network_topology(
+    api_type: ApiType,
+    network_topology: &NetworkTopology,
+    cycles_account_manager: CyclesAccountManager,
+) -> SystemApiImpl {
+    let sandbox_safe_system_state = SandboxSafeSystemState::new(
+        &SystemStateBuilder::default().build(),
+        cycles_account_manager,
+        network_topology,
+        SchedulerConfig::application_subnet().dirty_page_overhead,
+        execution_parameters().compute_allocation,
+    );
+    SystemApiImpl::new(
+        api_type,
+        sandbox_safe_system_state,
+        CANISTER_CURRENT_MEMORY_USAGE,
+        execution_parameters(),
+        SubnetAvailableMemory::new(0, 0, 0),
+        EmbeddersConfig::default()
+            .feature_flags
+            .wasm_native_stable_memory,
+        Memory::new_for_testing(),
+        Arc::new(DefaultOutOfInstructionsHandler {}),
+        no_op_logger(),
+    )
+}
+
+#[test]
+fn ic0_cost_sign_with_ecdsa_uses_size_of_signing_subnet() {
+    let key_id = EcdsaKeyId {
+        curve: EcdsaCurve::Secp256k1,
+        name: "key".to_string(),
+    };
+    let signing_subnet_size = 34;
+    let network_topology = NetworkTopology {
+        subnets: btreemap! {
+            subnet_test_id(1) => SubnetTopology {
+                nodes: (0..signing_subnet_size).map(node_test_id).collect(),
+                ..SubnetTopology::default()
+            },
+        },
+        ecdsa_signing_subnets: btreemap! {
+            key_id => vec![subnet_test_id(1)],
+        },
+        ..NetworkTopology::default()
+    };
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let api = get_system_api_with_network_topology(
+        ApiTypeBuilder::build_update_api(),
+        &network_topology,
+        cycles_account_manager,
+    );
+
+    // The key name is stored at the beginning of the heap and the cost is
+    // copied right after it.
+    let mut heap = vec![0; 32];
+    heap[..3].copy_from_slice(b"key");
+    assert_eq!(api.ic0_cost_sign_with_ecdsa(0, 3, 0, 16, &mut heap), Ok(0));
+    assert_eq!(
+        heap[16..],
+        cycles_account_manager
+            .ecdsa_signature_fee(signing_subnet_size as usize)
+            .get()
+            .to_le_bytes()
+    );
+
+    // Unknown curve.
+    assert_eq!(api.ic0_cost_sign_with_ecdsa(0, 3, 1, 16, &mut heap), Ok(1));
+    // Unknown key.
+    assert_eq!(api.ic0_cost_sign_with_ecdsa(0, 2, 0, 16, &mut heap), Ok(2));
+}
+
+#[test]
+fn ic0_in_replicated_execution_test() {
+    let in_replicated_execution = |api_type| {
+        get_system_api(
+            api_type,
+            &SystemStateBuilder::default().build(),
+            CyclesAccountManagerBuilder::new().build(),
+        )
+        .ic0_in_replicated_execution()
+        .unwrap()
+    };
+    assert_eq!(
+        in_replicated_execution(ApiTypeBuilder::build_update_api()),
+        1
+    );
+    assert_eq!(
+        in_replicated_execution(ApiType::replicated_query(
+            mock_time(),
+            vec![],
+            user_test_id(1).get(),
+            None
+        )),
+        1
+    );
+    assert_eq!(
+        in_replicated_execution(ApiType::inspect_message(
+            user_test_id(1).get(),
+            "hello".to_string(),
+            vec![],
+            mock_time(),
+        )),
+        0
+    );
+}
+
+#[test]
+fn ic0_subnet_self_and_root_key_test() {
+    let root_key = vec![1, 2, 3, 4];
+    let network_topology = NetworkTopology {
+        subnets: btreemap! {
+            subnet_test_id(2) => SubnetTopology {
+                public_key: root_key.clone(),
+                ..SubnetTopology::default()
+            },
+        },
+        nns_subnet_id: subnet_test_id(2),
+        ..NetworkTopology::default()
+    };
+    let cycles_account_manager = CyclesAccountManagerBuilder::new()
+        .with_subnet_id(subnet_test_id(1))
+        .build();
+    let api = get_system_api_with_network_topology(
+        ApiTypeBuilder::build_update_api(),
+        &network_topology,
+        cycles_account_manager,
+    );
+
+    let subnet_id = subnet_test_id(1).get();
+    let size = api.ic0_subnet_self_size().unwrap();
+    assert_eq!(size, subnet_id.as_slice().len());
+    let mut heap = vec![0; size];
+    api.ic0_subnet_self_copy(0, 0, size, &mut heap).unwrap();
+    assert_eq!(heap, subnet_id.as_slice());
+
+    assert_eq!(api.ic0_root_key_size().unwrap(), root_key.len());
+    let mut heap = vec![0; 2];
+    api.ic0_root_key_copy(0, 1, 2, &mut heap).unwrap();
+    assert_eq!(heap, root_key[1..3]);
+    assert!(api.ic0_root_key_copy(0, 3, 2, &mut heap).is_err());
+
+    let api = get_system_api_with_network_topology(
+        ApiType::start(mock_time()),
+        &network_topology,
+        cycles_account_manager,
+    );
+    assert_api_not_supported(api.ic0_subnet_self_size());
+    assert_api_not_supported(api.ic0_root_key_size());
+}
@@ -36,6 +36,7 @@ use ic_interfaces::{
 };
 use ic_logger::{replica_logger::no_op_logger, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
+use ic_query_stats::init_query_stats;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::{
     CanisterIdRange, RoutingTable, WellFormedError, CANISTER_IDS_PER_SUBNET,
@@ -838,6 +839,12 @@ impl ExecutionTest {
                     .task_queue
                     .push_front(ExecutionTask::GlobalTimer);
             }
+            CanisterTask::OnLowWasmMemory => {
+                canister
+                    .system_state
+                    .task_queue
+                    .push_front(ExecutionTask::OnLowWasmMemory);
+            }
         }
         let result = execute_canister(
             &self.exec_env,
@@ -1420,6 +1427,7 @@ pub struct ExecutionTestBuilder {
     subnet_message_memory: i64,
     subnet_wasm_custom_sections_memory: i64,
     subnet_memory_reservation: i64,
+    subnet_memory_threshold: i64,
     registry_settings: RegistryExecutionSettings,
     manual_execution: bool,
     rate_limiting_of_instructions: bool,
@@ -1428,6 +1436,7 @@ pub struct ExecutionTestBuilder {
     composite_queries: bool,
     query_caching: bool,
     query_cache_capacity: u64,
+    wasm64: bool,
     allocatable_compute_capacity_in_percent: usize,
     subnet_features: String,
     bitcoin_privileged_access: Vec<CanisterId>,
@@ -1451,6 +1460,9 @@ impl Default for ExecutionTestBuilder {
         let subnet_wasm_custom_sections_memory = ic_config::execution_environment::Config::default()
             .subnet_wasm_custom_sections_memory_capacity
             .get() as i64;
+        let subnet_memory_threshold = ic_config::execution_environment::Config::default()
+            .subnet_memory_threshold
+            .get() as i64;
         let subnet_memory_reservation = ic_config::execution_environment::Config::default()
             .subnet_memory_reservation
             .get() as i64;
@@ -1477,6 +1489,7 @@ impl Default for ExecutionTestBuilder {
             subnet_message_memory,
             subnet_wasm_custom_sections_memory,
             subnet_memory_reservation,
+            subnet_memory_threshold,
             registry_settings: test_registry_settings(),
             manual_execution: false,
             rate_limiting_of_instructions: false,
@@ -1485,6 +1498,7 @@ impl Default for ExecutionTestBuilder {
             composite_queries: false,
             query_caching: false,
             query_cache_capacity: 100_000_000, // 100MB
+            wasm64: false,
             allocatable_compute_capacity_in_percent: 100,
             subnet_features: String::default(),
             bitcoin_privileged_access: Vec::default(),
@@ -1620,6 +1634,13 @@ impl ExecutionTestBuilder {
         }
     }
 
+    pub fn with_subnet_memory_threshold(self, subnet_memory_threshold: i64) -> Self {
+        Self {
+            subnet_memory_threshold,
+            ..self
+        }
+    }
+
     pub fn with_subnet_message_memory(self, subnet_message_memory: i64) -> Self {
         Self {
             subnet_message_memory,
@@ -1703,6 +1724,13 @@ impl ExecutionTestBuilder {
         }
     }
 
+    pub fn with_wasm64(self) -> Self {
+        Self {
+            wasm64: true,
+            ..self
+        }
+    }
+
     pub fn with_allocatable_compute_capacity_in_percent(
         self,
         allocatable_compute_capacity_in_percent: usize,
@@ -1865,6 +1893,11 @@ impl ExecutionTestBuilder {
         } else {
             FlagStatus::Disabled
         };
+        let wasm64 = if self.wasm64 {
+            FlagStatus::Enabled
+        } else {
+            FlagStatus::Disabled
+        };
         let config = Config {
             rate_limiting_of_instructions,
             deterministic_time_slicing,
@@ -1876,6 +1909,7 @@ impl ExecutionTestBuilder {
             subnet_memory_capacity: NumBytes::from(self.subnet_execution_memory as u64),
             subnet_message_memory_capacity: NumBytes::from(self.subnet_message_memory as u64),
             subnet_memory_reservation: NumBytes::from(self.subnet_memory_reservation as u64),
+            subnet_memory_threshold: NumBytes::from(self.subnet_memory_threshold as u64),
             bitcoin: BitcoinConfig {
                 privileged_access: self.bitcoin_privileged_access,
                 ..Default::default()
@@ -1883,6 +1917,7 @@ impl ExecutionTestBuilder {
             cost_to_compile_wasm_instruction: self.cost_to_compile_wasm_instruction.into(),
             max_query_call_graph_instructions: self.max_query_call_graph_instructions,
             stable_memory_dirty_page_limit: self.stable_memory_dirty_page_limit,
+            wasm64,
             ..Config::default()
         };
 
@@ -1922,6 +1957,7 @@ impl ExecutionTestBuilder {
             config.clone(),
             Arc::clone(&cycles_account_manager),
         );
+        let (query_stats_collector, _) = init_query_stats(self.log.clone(), &config);
         let query_handler = InternalHttpQueryHandler::new(
             self.log.clone(),
             hypervisor,
@@ -1930,6 +1966,7 @@ impl ExecutionTestBuilder {
             &metrics_registry,
             self.instruction_limit_without_dts,
             Arc::clone(&cycles_account_manager),
+            Arc::new(query_stats_collector),
         );
         ExecutionTest {
             state: Some(state),
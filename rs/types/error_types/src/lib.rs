@@ -22,6 +22,7 @@ pub enum RejectCode {
     DestinationInvalid = 3,
     CanisterReject = 4,
     CanisterError = 5,
+    SysUnknown = 6,
 }
 
 impl ToString for RejectCode {
@@ -38,6 +39,7 @@ impl RejectCode {
             RejectCode::DestinationInvalid => "DESTINATION_INVALID",
             RejectCode::CanisterReject => "CANISTER_REJECT",
             RejectCode::CanisterError => "CANISTER_ERROR",
+            RejectCode::SysUnknown => "SYS_UNKNOWN",
         }
     }
 }
@@ -51,6 +53,7 @@ impl TryFrom<u64> for RejectCode {
             3 => Ok(RejectCode::DestinationInvalid),
             4 => Ok(RejectCode::CanisterReject),
             5 => Ok(RejectCode::CanisterError),
+            6 => Ok(RejectCode::SysUnknown),
             _ => Err(TryFromError::ValueOutOfRange(code)),
         }
     }
@@ -107,6 +110,11 @@ impl From<ErrorCode> for RejectCode {
             InsufficientCyclesInComputeAllocation => CanisterError,
             InsufficientCyclesInMemoryAllocation => CanisterError,
             InsufficientCyclesInMemoryGrow => CanisterError,
+            CanisterSnapshotNotFound => DestinationInvalid,
+            CanisterSnapshotLimitExceeded => CanisterError,
+            CanisterWasmMemoryLimitExceeded => CanisterError,
+            ReservedCyclesLimitExceededInMemoryGrow => CanisterError,
+            ReservedCyclesLimitIsTooLow => CanisterError,
         }
     }
 }
@@ -129,6 +137,7 @@ pub enum ErrorCode {
     CanisterMethodNotFound = 302,
     CanisterAlreadyInstalled = 303,
     CanisterWasmModuleNotFound = 304,
+    CanisterSnapshotNotFound = 305,
     InsufficientMemoryAllocation = 402,
     InsufficientCyclesForCreateCanister = 403,
     SubnetNotFound = 404,
@@ -165,6 +174,10 @@ pub enum ErrorCode {
     InsufficientCyclesInComputeAllocation = 530,
     InsufficientCyclesInMemoryAllocation = 531,
     InsufficientCyclesInMemoryGrow = 532,
+    CanisterSnapshotLimitExceeded = 533,
+    CanisterWasmMemoryLimitExceeded = 534,
+    ReservedCyclesLimitExceededInMemoryGrow = 535,
+    ReservedCyclesLimitIsTooLow = 536,
 }
 
 impl TryFrom<u64> for ErrorCode {
@@ -181,6 +194,7 @@ impl TryFrom<u64> for ErrorCode {
             302 => Ok(ErrorCode::CanisterMethodNotFound),
             303 => Ok(ErrorCode::CanisterAlreadyInstalled),
             304 => Ok(ErrorCode::CanisterWasmModuleNotFound),
+            305 => Ok(ErrorCode::CanisterSnapshotNotFound),
             402 => Ok(ErrorCode::InsufficientMemoryAllocation),
             403 => Ok(ErrorCode::InsufficientCyclesForCreateCanister),
             404 => Ok(ErrorCode::SubnetNotFound),
@@ -217,6 +231,10 @@ impl TryFrom<u64> for ErrorCode {
             530 => Ok(ErrorCode::InsufficientCyclesInComputeAllocation),
             531 => Ok(ErrorCode::InsufficientCyclesInMemoryAllocation),
             532 => Ok(ErrorCode::InsufficientCyclesInMemoryGrow),
+            533 => Ok(ErrorCode::CanisterSnapshotLimitExceeded),
+            534 => Ok(ErrorCode::CanisterWasmMemoryLimitExceeded),
+            535 => Ok(ErrorCode::ReservedCyclesLimitExceededInMemoryGrow),
+            536 => Ok(ErrorCode::ReservedCyclesLimitIsTooLow),
             _ => Err(TryFromError::ValueOutOfRange(err)),
         }
     }
@@ -311,7 +329,12 @@ impl UserError {
             | ErrorCode::QueryTimeLimitExceeded
             | ErrorCode::InsufficientCyclesInComputeAllocation
             | ErrorCode::InsufficientCyclesInMemoryAllocation
-            | ErrorCode::InsufficientCyclesInMemoryGrow => false,
+            | ErrorCode::InsufficientCyclesInMemoryGrow
+            | ErrorCode::CanisterSnapshotNotFound
+            | ErrorCode::CanisterSnapshotLimitExceeded
+            | ErrorCode::CanisterWasmMemoryLimitExceeded
+            | ErrorCode::ReservedCyclesLimitExceededInMemoryGrow
+            | ErrorCode::ReservedCyclesLimitIsTooLow => false,
         }
     }
 
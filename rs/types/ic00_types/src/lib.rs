@@ -59,6 +59,19 @@ pub enum Method {
     UninstallCode,
     UpdateSettings,
     ComputeInitialEcdsaDealings,
+    FetchCanisterLogs,
+
+    // Canister snapshots.
+    TakeCanisterSnapshot,
+    LoadCanisterSnapshot,
+    ListCanisterSnapshots,
+    DeleteCanisterSnapshot,
+
+    // Chunked Wasm upload.
+    UploadChunk,
+    ClearChunkStore,
+    StoredChunks,
+    InstallChunkedCode,
 
     // Bitcoin Interface.
     BitcoinGetBalance,
@@ -623,11 +636,253 @@ impl UninstallCodeArgs {
 
 impl Payload<'_> for UninstallCodeArgs {}
 
+/// Identifies a snapshot of a canister's state. The id is unique across the
+/// IC: it consists of the id of the canister the snapshot belongs to and a
+/// number local to that canister.
+///
+/// Snapshot ids are exposed to users as opaque `blob`s.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotId {
+    canister_id: CanisterId,
+    local_id: u64,
+}
+
+impl SnapshotId {
+    pub fn new(canister_id: CanisterId, local_id: u64) -> Self {
+        Self {
+            canister_id,
+            local_id,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        self.canister_id
+    }
+
+    pub fn get_local_id(&self) -> u64 {
+        self.local_id
+    }
+
+    /// Encodes the id as the local id in big-endian order followed by the
+    /// bytes of the canister id.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = self.local_id.to_be_bytes().to_vec();
+        bytes.extend_from_slice(self.canister_id.get_ref().as_slice());
+        bytes
+    }
+}
+
+impl fmt::Display for SnapshotId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.canister_id, self.local_id)
+    }
+}
+
+impl TryFrom<&[u8]> for SnapshotId {
+    type Error = UserError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let invalid = || {
+            UserError::new(
+                ErrorCode::InvalidManagementPayload,
+                format!("Invalid snapshot id: {:?}", bytes),
+            )
+        };
+        if bytes.len() <= size_of::<u64>() {
+            return Err(invalid());
+        }
+        let (local_id, canister_id) = bytes.split_at(size_of::<u64>());
+        let local_id = u64::from_be_bytes(local_id.try_into().map_err(|_| invalid())?);
+        let canister_id = CanisterId::try_from(canister_id).map_err(|_| invalid())?;
+        Ok(Self::new(canister_id, local_id))
+    }
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id: principal;
+///     replace_snapshot: opt blob;
+/// })`
+#[derive(CandidType, Serialize, Deserialize, Debug)]
+pub struct TakeCanisterSnapshotArgs {
+    canister_id: PrincipalId,
+    replace_snapshot: Option<serde_bytes::ByteBuf>,
+}
+
+impl TakeCanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId, replace_snapshot: Option<SnapshotId>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            replace_snapshot: replace_snapshot.map(|id| serde_bytes::ByteBuf::from(id.to_vec())),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+
+    pub fn replace_snapshot(&self) -> Result<Option<SnapshotId>, UserError> {
+        self.replace_snapshot
+            .as_ref()
+            .map(|id| SnapshotId::try_from(id.as_slice()))
+            .transpose()
+    }
+}
+
+impl Payload<'_> for TakeCanisterSnapshotArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     id: blob;
+///     taken_at_timestamp: nat64;
+///     total_size: nat64;
+/// })`
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterSnapshotResponse {
+    #[serde(with = "serde_bytes")]
+    pub id: Vec<u8>,
+    pub taken_at_timestamp: u64,
+    pub total_size: u64,
+}
+
+impl CanisterSnapshotResponse {
+    pub fn new(snapshot_id: &SnapshotId, taken_at_timestamp: u64, total_size: NumBytes) -> Self {
+        Self {
+            id: snapshot_id.to_vec(),
+            taken_at_timestamp,
+            total_size: total_size.get(),
+        }
+    }
+
+    pub fn snapshot_id(&self) -> Result<SnapshotId, UserError> {
+        SnapshotId::try_from(self.id.as_slice())
+    }
+}
+
+impl Payload<'_> for CanisterSnapshotResponse {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id: principal;
+///     snapshot_id: blob;
+///     sender_canister_version: opt nat64;
+/// })`
+#[derive(CandidType, Serialize, Deserialize, Debug)]
+pub struct LoadCanisterSnapshotArgs {
+    canister_id: PrincipalId,
+    #[serde(with = "serde_bytes")]
+    snapshot_id: Vec<u8>,
+    sender_canister_version: Option<u64>,
+}
+
+impl LoadCanisterSnapshotArgs {
+    pub fn new(
+        canister_id: CanisterId,
+        snapshot_id: SnapshotId,
+        sender_canister_version: Option<u64>,
+    ) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            snapshot_id: snapshot_id.to_vec(),
+            sender_canister_version,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+
+    pub fn snapshot_id(&self) -> Result<SnapshotId, UserError> {
+        SnapshotId::try_from(self.snapshot_id.as_slice())
+    }
+
+    pub fn get_sender_canister_version(&self) -> Option<u64> {
+        self.sender_canister_version
+    }
+}
+
+impl Payload<'_> for LoadCanisterSnapshotArgs {}
+
+/// Struct used for encoding/decoding `(record {canister_id: principal})`.
+#[derive(CandidType, Serialize, Deserialize, Debug)]
+pub struct ListCanisterSnapshotArgs {
+    canister_id: PrincipalId,
+}
+
+impl ListCanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for ListCanisterSnapshotArgs {}
+
+/// Struct used for encoding/decoding the response of
+/// `list_canister_snapshots`: `(vec canister_snapshot)`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ListCanisterSnapshotsResponse(pub Vec<CanisterSnapshotResponse>);
+
+impl Payload<'_> for ListCanisterSnapshotsResponse {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id: principal;
+///     snapshot_id: blob;
+/// })`
+#[derive(CandidType, Serialize, Deserialize, Debug)]
+pub struct DeleteCanisterSnapshotArgs {
+    canister_id: PrincipalId,
+    #[serde(with = "serde_bytes")]
+    snapshot_id: Vec<u8>,
+}
+
+impl DeleteCanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId, snapshot_id: SnapshotId) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            snapshot_id: snapshot_id.to_vec(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+
+    pub fn snapshot_id(&self) -> Result<SnapshotId, UserError> {
+        SnapshotId::try_from(self.snapshot_id.as_slice())
+    }
+}
+
+impl Payload<'_> for DeleteCanisterSnapshotArgs {}
+
+#[test]
+fn snapshot_id_round_trip() {
+    let snapshot_id = SnapshotId::new(CanisterId::from_u64(42), 7);
+    assert_eq!(
+        SnapshotId::try_from(snapshot_id.to_vec().as_slice()).unwrap(),
+        snapshot_id
+    );
+    assert!(SnapshotId::try_from(&[0_u8; 8][..]).is_err());
+}
+
 /// Struct used for encoding/decoding
 /// `(record {
 ///     controller : principal;
 ///     compute_allocation: nat;
 ///     memory_allocation: opt nat;
+///     wasm_memory_limit: nat;
+///     wasm_memory_threshold: nat;
+///     reserved_cycles_limit: opt nat;
 /// })`
 #[derive(CandidType, Deserialize, Debug, Eq, PartialEq)]
 pub struct DefiniteCanisterSettingsArgs {
@@ -636,32 +891,58 @@ pub struct DefiniteCanisterSettingsArgs {
     compute_allocation: candid::Nat,
     memory_allocation: candid::Nat,
     freezing_threshold: candid::Nat,
+    wasm_memory_limit: candid::Nat,
+    wasm_memory_threshold: candid::Nat,
+    reserved_cycles_limit: Option<candid::Nat>,
 }
 
 impl DefiniteCanisterSettingsArgs {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         controller: PrincipalId,
         controllers: Vec<PrincipalId>,
         compute_allocation: u64,
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
+        wasm_memory_limit: Option<u64>,
+        wasm_memory_threshold: u64,
+        reserved_cycles_limit: Option<u128>,
     ) -> Self {
         let memory_allocation = match memory_allocation {
             None => candid::Nat::from(0),
             Some(memory) => candid::Nat::from(memory),
         };
+        // A Wasm memory limit of zero means that there is no limit.
+        let wasm_memory_limit = candid::Nat::from(wasm_memory_limit.unwrap_or(0));
         Self {
             controller,
             controllers,
             compute_allocation: candid::Nat::from(compute_allocation),
             memory_allocation,
             freezing_threshold: candid::Nat::from(freezing_threshold),
+            wasm_memory_limit,
+            wasm_memory_threshold: candid::Nat::from(wasm_memory_threshold),
+            reserved_cycles_limit: reserved_cycles_limit.map(candid::Nat::from),
         }
     }
 
     pub fn controllers(&self) -> Vec<PrincipalId> {
         self.controllers.clone()
     }
+
+    pub fn wasm_memory_limit(&self) -> u64 {
+        self.wasm_memory_limit.0.to_u64().unwrap()
+    }
+
+    pub fn wasm_memory_threshold(&self) -> u64 {
+        self.wasm_memory_threshold.0.to_u64().unwrap()
+    }
+
+    pub fn reserved_cycles_limit(&self) -> Option<u128> {
+        self.reserved_cycles_limit
+            .as_ref()
+            .map(|limit| limit.0.to_u128().unwrap())
+    }
 }
 
 impl Payload<'_> for DefiniteCanisterSettingsArgs {}
@@ -730,7 +1011,9 @@ impl Payload<'_> for CanisterStatusResult {}
 ///     controller: principal;
 ///     memory_size: nat;
 ///     cycles: nat;
+///     reserved_cycles: nat;
 ///     idle_cycles_burned_per_day: nat;
+///     query_stats: query_stats;
 /// })`
 #[derive(CandidType, Debug, Deserialize, Eq, PartialEq)]
 pub struct CanisterStatusResultV2 {
@@ -744,6 +1027,55 @@ pub struct CanisterStatusResultV2 {
     balance: Vec<(Vec<u8>, candid::Nat)>,
     freezing_threshold: candid::Nat,
     idle_cycles_burned_per_day: candid::Nat,
+    reserved_cycles: candid::Nat,
+    query_stats: QueryStatsResponse,
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     num_calls_total: nat;
+///     num_instructions_total: nat;
+///     request_payload_bytes_total: nat;
+///     response_payload_bytes_total: nat;
+/// })`
+#[derive(CandidType, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct QueryStatsResponse {
+    num_calls_total: candid::Nat,
+    num_instructions_total: candid::Nat,
+    request_payload_bytes_total: candid::Nat,
+    response_payload_bytes_total: candid::Nat,
+}
+
+impl QueryStatsResponse {
+    pub fn new(
+        num_calls_total: u128,
+        num_instructions_total: u128,
+        request_payload_bytes_total: u128,
+        response_payload_bytes_total: u128,
+    ) -> Self {
+        Self {
+            num_calls_total: candid::Nat::from(num_calls_total),
+            num_instructions_total: candid::Nat::from(num_instructions_total),
+            request_payload_bytes_total: candid::Nat::from(request_payload_bytes_total),
+            response_payload_bytes_total: candid::Nat::from(response_payload_bytes_total),
+        }
+    }
+
+    pub fn num_calls_total(&self) -> u128 {
+        self.num_calls_total.0.to_u128().unwrap()
+    }
+
+    pub fn num_instructions_total(&self) -> u128 {
+        self.num_instructions_total.0.to_u128().unwrap()
+    }
+
+    pub fn request_payload_bytes_total(&self) -> u128 {
+        self.request_payload_bytes_total.0.to_u128().unwrap()
+    }
+
+    pub fn response_payload_bytes_total(&self) -> u128 {
+        self.response_payload_bytes_total.0.to_u128().unwrap()
+    }
 }
 
 impl CanisterStatusResultV2 {
@@ -758,7 +1090,12 @@ impl CanisterStatusResultV2 {
         compute_allocation: u64,
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
+        wasm_memory_limit: Option<u64>,
+        wasm_memory_threshold: u64,
         idle_cycles_burned_per_day: u128,
+        reserved_cycles: u128,
+        reserved_cycles_limit: Option<u128>,
+        query_stats: QueryStatsResponse,
     ) -> Self {
         Self {
             status,
@@ -775,9 +1112,14 @@ impl CanisterStatusResultV2 {
                 compute_allocation,
                 memory_allocation,
                 freezing_threshold,
+                wasm_memory_limit,
+                wasm_memory_threshold,
+                reserved_cycles_limit,
             ),
             freezing_threshold: candid::Nat::from(freezing_threshold),
             idle_cycles_burned_per_day: candid::Nat::from(idle_cycles_burned_per_day),
+            reserved_cycles: candid::Nat::from(reserved_cycles),
+            query_stats,
         }
     }
 
@@ -809,9 +1151,34 @@ impl CanisterStatusResultV2 {
         self.freezing_threshold.0.to_u64().unwrap()
     }
 
+    /// Returns the Wasm memory limit in bytes, where zero means no limit.
+    pub fn wasm_memory_limit(&self) -> u64 {
+        self.settings.wasm_memory_limit()
+    }
+
+    pub fn wasm_memory_threshold(&self) -> u64 {
+        self.settings.wasm_memory_threshold()
+    }
+
     pub fn idle_cycles_burned_per_day(&self) -> u128 {
         self.idle_cycles_burned_per_day.0.to_u128().unwrap()
     }
+
+    /// Returns the cycles reserved for future storage fees.
+    pub fn reserved_cycles(&self) -> u128 {
+        self.reserved_cycles.0.to_u128().unwrap()
+    }
+
+    /// Returns the limit on the reserved cycles, if any.
+    pub fn reserved_cycles_limit(&self) -> Option<u128> {
+        self.settings.reserved_cycles_limit()
+    }
+
+    /// Returns the aggregated statistics of the queries executed on the
+    /// canister.
+    pub fn query_stats(&self) -> &QueryStatsResponse {
+        &self.query_stats
+    }
 }
 
 /// Indicates whether the canister is running, stopping, or stopped.
@@ -1098,6 +1465,178 @@ impl UpdateSettingsArgs {
     }
 }
 
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id: principal;
+///     chunk: blob;
+/// })`
+#[derive(CandidType, Serialize, Deserialize, Debug)]
+pub struct UploadChunkArgs {
+    pub canister_id: PrincipalId,
+    #[serde(with = "serde_bytes")]
+    pub chunk: Vec<u8>,
+}
+
+impl UploadChunkArgs {
+    pub fn new(canister_id: CanisterId, chunk: Vec<u8>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            chunk,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for UploadChunkArgs {}
+
+/// Struct used for encoding/decoding `(record { hash: blob })`, the hash of a
+/// chunk in a Wasm chunk store.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChunkHash {
+    #[serde(with = "serde_bytes")]
+    pub hash: Vec<u8>,
+}
+
+impl Payload<'_> for ChunkHash {}
+
+/// The reply of `upload_chunk`.
+pub type UploadChunkReply = ChunkHash;
+
+/// Struct used for encoding/decoding `(record { canister_id: principal })`.
+#[derive(CandidType, Serialize, Deserialize, Debug)]
+pub struct ClearChunkStoreArgs {
+    pub canister_id: PrincipalId,
+}
+
+impl ClearChunkStoreArgs {
+    pub fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for ClearChunkStoreArgs {}
+
+/// Struct used for encoding/decoding `(record { canister_id: principal })`.
+#[derive(CandidType, Serialize, Deserialize, Debug)]
+pub struct StoredChunksArgs {
+    pub canister_id: PrincipalId,
+}
+
+impl StoredChunksArgs {
+    pub fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for StoredChunksArgs {}
+
+/// Struct used for encoding/decoding `(vec record { hash: blob })`.
+#[derive(CandidType, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct StoredChunksReply(pub Vec<ChunkHash>);
+
+impl Payload<'_> for StoredChunksReply {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     mode : variant { install; reinstall; upgrade };
+///     target_canister: principal;
+///     store_canister: opt principal;
+///     chunk_hashes_list: vec record { hash: blob };
+///     wasm_module_hash: blob;
+///     arg: blob;
+///     sender_canister_version : opt nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug)]
+pub struct InstallChunkedCodeArgs {
+    pub mode: CanisterInstallMode,
+    pub target_canister: PrincipalId,
+    pub store_canister: Option<PrincipalId>,
+    pub chunk_hashes_list: Vec<ChunkHash>,
+    #[serde(with = "serde_bytes")]
+    pub wasm_module_hash: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub arg: Vec<u8>,
+    pub sender_canister_version: Option<u64>,
+}
+
+impl std::fmt::Display for InstallChunkedCodeArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "InstallChunkedCodeArgs {{")?;
+        writeln!(f, "  mode: {:?}", &self.mode)?;
+        writeln!(f, "  target_canister: {:?}", &self.target_canister)?;
+        writeln!(f, "  store_canister: {:?}", &self.store_canister)?;
+        writeln!(
+            f,
+            "  chunk_hashes_list: <{:?} chunks>",
+            self.chunk_hashes_list.len()
+        )?;
+        writeln!(f, "  wasm_module_hash: {:?}", &self.wasm_module_hash)?;
+        writeln!(f, "  arg: <{:?} bytes>", self.arg.len())?;
+        writeln!(f, "}}")
+    }
+}
+
+impl Payload<'_> for InstallChunkedCodeArgs {}
+
+impl InstallChunkedCodeArgs {
+    pub fn new(
+        mode: CanisterInstallMode,
+        target_canister: CanisterId,
+        store_canister: Option<CanisterId>,
+        chunk_hashes_list: Vec<Vec<u8>>,
+        wasm_module_hash: Vec<u8>,
+        arg: Vec<u8>,
+    ) -> Self {
+        Self {
+            mode,
+            target_canister: target_canister.into(),
+            store_canister: store_canister.map(|canister_id| canister_id.into()),
+            chunk_hashes_list: chunk_hashes_list
+                .into_iter()
+                .map(|hash| ChunkHash { hash })
+                .collect(),
+            wasm_module_hash,
+            arg,
+            sender_canister_version: None,
+        }
+    }
+
+    pub fn target_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.target_canister).unwrap()
+    }
+
+    /// Returns the canister holding the chunks, which defaults to the target
+    /// canister.
+    pub fn store_canister_id(&self) -> CanisterId {
+        self.store_canister
+            .map(|canister_id| CanisterId::new(canister_id).unwrap())
+            .unwrap_or_else(|| self.target_canister_id())
+    }
+
+    pub fn get_sender_canister_version(&self) -> Option<u64> {
+        self.sender_canister_version
+    }
+}
+
 #[cfg(feature = "fuzzing_code")]
 impl<'a> Arbitrary<'a> for UpdateSettingsArgs {
     fn arbitrary(u: &mut Unstructured<'a>) -> ArbitraryResult<Self> {
@@ -1115,12 +1654,134 @@ impl<'a> Arbitrary<'a> for UpdateSettingsArgs {
 
 impl Payload<'_> for UpdateSettingsArgs {}
 
+/// Who is allowed to fetch the logs of a canister via `fetch_canister_logs`.
+///
+/// `(variant { controllers; public })`
+#[derive(Copy, Clone, CandidType, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum LogVisibility {
+    /// Only the controllers of the canister can fetch its logs.
+    #[serde(rename = "controllers")]
+    Controllers,
+    /// Anyone can fetch the logs of the canister.
+    #[serde(rename = "public")]
+    Public,
+}
+
+impl Default for LogVisibility {
+    fn default() -> Self {
+        LogVisibility::Controllers
+    }
+}
+
+impl From<&LogVisibility> for pb_canister_state_bits::LogVisibility {
+    fn from(item: &LogVisibility) -> Self {
+        match item {
+            LogVisibility::Controllers => pb_canister_state_bits::LogVisibility::Controllers,
+            LogVisibility::Public => pb_canister_state_bits::LogVisibility::Public,
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::LogVisibility> for LogVisibility {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb_canister_state_bits::LogVisibility) -> Result<Self, Self::Error> {
+        match item {
+            pb_canister_state_bits::LogVisibility::Controllers => Ok(Self::Controllers),
+            pb_canister_state_bits::LogVisibility::Public => Ok(Self::Public),
+            pb_canister_state_bits::LogVisibility::Unspecified => {
+                Err(ProxyDecodeError::ValueOutOfRange {
+                    typ: "LogVisibility",
+                    err: format!("Unknown value for log visibility {:?}", item),
+                })
+            }
+        }
+    }
+}
+
+/// A record in the log of a canister, as returned by `fetch_canister_logs`.
+///
+/// `(record {
+///     idx: nat64;
+///     timestamp_nanos: nat64;
+///     content: blob;
+/// })`
+#[derive(Clone, CandidType, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterLogRecord {
+    pub idx: u64,
+    pub timestamp_nanos: u64,
+    #[serde(with = "serde_bytes")]
+    pub content: Vec<u8>,
+}
+
+impl CanisterLogRecord {
+    /// Returns the number of bytes the record takes up in a canister log.
+    pub fn data_size(&self) -> usize {
+        size_of::<u64>() * 2 + self.content.len()
+    }
+}
+
+impl From<&CanisterLogRecord> for pb_canister_state_bits::CanisterLogRecord {
+    fn from(item: &CanisterLogRecord) -> Self {
+        Self {
+            idx: item.idx,
+            timestamp_nanos: item.timestamp_nanos,
+            content: item.content.clone(),
+        }
+    }
+}
+
+impl From<pb_canister_state_bits::CanisterLogRecord> for CanisterLogRecord {
+    fn from(item: pb_canister_state_bits::CanisterLogRecord) -> Self {
+        Self {
+            idx: item.idx,
+            timestamp_nanos: item.timestamp_nanos,
+            content: item.content,
+        }
+    }
+}
+
+/// Struct used for encoding/decoding `(record { canister_id: principal })`
+/// for `fetch_canister_logs`.
+#[derive(CandidType, Serialize, Deserialize, Debug)]
+pub struct FetchCanisterLogsRequest {
+    canister_id: PrincipalId,
+}
+
+impl FetchCanisterLogsRequest {
+    pub fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+}
+
+impl Payload<'_> for FetchCanisterLogsRequest {}
+
+/// Struct used for encoding/decoding
+/// `(record { canister_log_records: vec canister_log_record })`.
+#[derive(CandidType, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FetchCanisterLogsResponse {
+    pub canister_log_records: Vec<CanisterLogRecord>,
+}
+
+impl Payload<'_> for FetchCanisterLogsResponse {}
+
 /// Struct used for encoding/decoding
 /// `(record {
 ///     controller: opt principal;
 ///     controllers: opt vec principal;
 ///     compute_allocation: opt nat;
 ///     memory_allocation: opt nat;
+///     log_visibility: opt log_visibility;
+///     wasm_memory_limit: opt nat;
+///     wasm_memory_threshold: opt nat;
+///     reserved_cycles_limit: opt nat;
 /// })`
 #[derive(Default, Clone, CandidType, Deserialize, Debug)]
 pub struct CanisterSettingsArgs {
@@ -1130,6 +1791,10 @@ pub struct CanisterSettingsArgs {
     pub compute_allocation: Option<candid::Nat>,
     pub memory_allocation: Option<candid::Nat>,
     pub freezing_threshold: Option<candid::Nat>,
+    pub log_visibility: Option<LogVisibility>,
+    pub wasm_memory_limit: Option<candid::Nat>,
+    pub wasm_memory_threshold: Option<candid::Nat>,
+    pub reserved_cycles_limit: Option<candid::Nat>,
 }
 
 impl Payload<'_> for CanisterSettingsArgs {}
@@ -1147,6 +1812,10 @@ impl CanisterSettingsArgs {
             compute_allocation: compute_allocation.map(candid::Nat::from),
             memory_allocation: memory_allocation.map(candid::Nat::from),
             freezing_threshold: freezing_threshold.map(candid::Nat::from),
+            log_visibility: None,
+            wasm_memory_limit: None,
+            wasm_memory_threshold: None,
+            reserved_cycles_limit: None,
         }
     }
 
@@ -1162,6 +1831,10 @@ pub struct CanisterSettingsArgsBuilder {
     compute_allocation: Option<candid::Nat>,
     memory_allocation: Option<candid::Nat>,
     freezing_threshold: Option<candid::Nat>,
+    log_visibility: Option<LogVisibility>,
+    wasm_memory_limit: Option<candid::Nat>,
+    wasm_memory_threshold: Option<candid::Nat>,
+    reserved_cycles_limit: Option<candid::Nat>,
 }
 
 #[allow(dead_code)]
@@ -1177,6 +1850,10 @@ impl CanisterSettingsArgsBuilder {
             compute_allocation: self.compute_allocation,
             memory_allocation: self.memory_allocation,
             freezing_threshold: self.freezing_threshold,
+            log_visibility: self.log_visibility,
+            wasm_memory_limit: self.wasm_memory_limit,
+            wasm_memory_threshold: self.wasm_memory_threshold,
+            reserved_cycles_limit: self.reserved_cycles_limit,
         }
     }
 
@@ -1220,6 +1897,42 @@ impl CanisterSettingsArgsBuilder {
             ..self
         }
     }
+
+    /// Sets who is allowed to fetch the logs of the canister.
+    pub fn with_log_visibility(self, log_visibility: LogVisibility) -> Self {
+        Self {
+            log_visibility: Some(log_visibility),
+            ..self
+        }
+    }
+
+    /// Sets the Wasm memory limit in bytes, where zero means no limit. For
+    /// more details see the description of this field in the IC specification.
+    pub fn with_wasm_memory_limit(self, wasm_memory_limit: u64) -> Self {
+        Self {
+            wasm_memory_limit: Some(candid::Nat::from(wasm_memory_limit)),
+            ..self
+        }
+    }
+
+    /// Sets the Wasm memory threshold in bytes. For more details see the
+    /// description of this field in the IC specification.
+    pub fn with_wasm_memory_threshold(self, wasm_memory_threshold: u64) -> Self {
+        Self {
+            wasm_memory_threshold: Some(candid::Nat::from(wasm_memory_threshold)),
+            ..self
+        }
+    }
+
+    /// Sets the upper bound on the cycles that the canister can reserve for
+    /// future storage fees. For more details see the description of this
+    /// field in the IC specification.
+    pub fn with_reserved_cycles_limit(self, reserved_cycles_limit: u128) -> Self {
+        Self {
+            reserved_cycles_limit: Some(candid::Nat::from(reserved_cycles_limit)),
+            ..self
+        }
+    }
 }
 
 /// Struct used for encoding/decoding
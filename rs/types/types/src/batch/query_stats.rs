@@ -0,0 +1,210 @@
+use crate::{node_id_into_protobuf, node_id_try_from_option, CanisterId, Height, NodeId, NumBytes};
+use ic_protobuf::{
+    proxy::{try_from_option_field, ProxyDecodeError},
+    types::v1 as pb,
+};
+use phantom_newtype::AmountOf;
+use prost::{bytes::BufMut, Message};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+pub struct QueryStatsEpochTag;
+/// The epoch in which query statistics are collected. Every replica reports
+/// its statistics for a given epoch at most once, after the epoch is over.
+pub type QueryStatsEpoch = AmountOf<QueryStatsEpochTag, u64>;
+
+/// Returns the epoch that the block at `height` belongs to.
+pub fn epoch_from_height(height: Height, epoch_length: u64) -> QueryStatsEpoch {
+    QueryStatsEpoch::from(height.get() / epoch_length.max(1))
+}
+
+/// Statistics about the queries that a single replica executed on a canister.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QueryStats {
+    /// The number of query calls executed.
+    pub num_calls: u32,
+    /// The number of instructions used by the query calls.
+    pub num_instructions: u64,
+    /// The total size of the payloads of the query calls.
+    pub ingress_payload_size: u64,
+    /// The total size of the replies and rejects of the query calls.
+    pub egress_payload_size: u64,
+}
+
+impl QueryStats {
+    /// Adds `other` to `self`, saturating at the maximum value of each field.
+    pub fn saturating_accumulate(&mut self, other: &QueryStats) {
+        self.num_calls = self.num_calls.saturating_add(other.num_calls);
+        self.num_instructions = self.num_instructions.saturating_add(other.num_instructions);
+        self.ingress_payload_size = self
+            .ingress_payload_size
+            .saturating_add(other.ingress_payload_size);
+        self.egress_payload_size = self
+            .egress_payload_size
+            .saturating_add(other.egress_payload_size);
+    }
+}
+
+/// The query statistics of a canister, as reported by a single replica.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterQueryStats {
+    pub canister_id: CanisterId,
+    pub stats: QueryStats,
+}
+
+/// The query statistics collected by `proposer` during `epoch`, included in a
+/// block by that replica.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QueryStatsPayload {
+    pub epoch: QueryStatsEpoch,
+    pub proposer: NodeId,
+    pub stats: Vec<CanisterQueryStats>,
+}
+
+impl QueryStatsPayload {
+    /// Serializes the payload, leaving out the statistics of the canisters
+    /// that do not fit into `max_size`. Returns an empty vector if the payload
+    /// has statistics but not even those of a single canister fit.
+    pub fn serialize_with_limit(&self, max_size: NumBytes) -> Vec<u8> {
+        let mut payload = pb::QueryStatsPayload {
+            proposer: Some(node_id_into_protobuf(self.proposer)),
+            epoch: self.epoch.get(),
+            canister_stats: vec![],
+        };
+        let mut size = payload.encoded_len();
+        if size > max_size.get() as usize {
+            return vec![];
+        }
+        for stats in &self.stats {
+            let stats = pb::CanisterQueryStats::from(stats);
+            let stats_size = prost::encoding::message::encoded_len(3, &stats);
+            if size + stats_size > max_size.get() as usize {
+                break;
+            }
+            size += stats_size;
+            payload.canister_stats.push(stats);
+        }
+
+        if payload.canister_stats.is_empty() && !self.stats.is_empty() {
+            return vec![];
+        }
+        let mut buffer = vec![].limit(max_size.get() as usize);
+        payload
+            .encode(&mut buffer)
+            .expect("The payload size was checked above");
+        buffer.into_inner()
+    }
+
+    /// Deserializes a payload. An empty slice corresponds to no payload.
+    pub fn deserialize(data: &[u8]) -> Result<Option<Self>, ProxyDecodeError> {
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let payload = pb::QueryStatsPayload::decode(data).map_err(ProxyDecodeError::DecodeError)?;
+        Ok(Some(Self {
+            epoch: QueryStatsEpoch::from(payload.epoch),
+            proposer: node_id_try_from_option(payload.proposer)?,
+            stats: payload
+                .canister_stats
+                .into_iter()
+                .map(CanisterQueryStats::try_from)
+                .collect::<Result<_, _>>()?,
+        }))
+    }
+}
+
+impl From<&CanisterQueryStats> for pb::CanisterQueryStats {
+    fn from(item: &CanisterQueryStats) -> Self {
+        Self {
+            canister_id: Some(pb::CanisterId::from(item.canister_id)),
+            num_calls: item.stats.num_calls,
+            num_instructions: item.stats.num_instructions,
+            ingress_payload_size: item.stats.ingress_payload_size,
+            egress_payload_size: item.stats.egress_payload_size,
+        }
+    }
+}
+
+impl TryFrom<pb::CanisterQueryStats> for CanisterQueryStats {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb::CanisterQueryStats) -> Result<Self, Self::Error> {
+        Ok(Self {
+            canister_id: try_from_option_field(
+                item.canister_id,
+                "CanisterQueryStats::canister_id",
+            )?,
+            stats: QueryStats {
+                num_calls: item.num_calls,
+                num_instructions: item.num_instructions,
+                ingress_payload_size: item.ingress_payload_size,
+                egress_payload_size: item.egress_payload_size,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::PrincipalId;
+
+    fn test_payload(num_canisters: u64) -> QueryStatsPayload {
+        QueryStatsPayload {
+            epoch: QueryStatsEpoch::from(7),
+            proposer: NodeId::from(PrincipalId::new_node_test_id(1)),
+            stats: (0..num_canisters)
+                .map(|i| CanisterQueryStats {
+                    canister_id: CanisterId::from_u64(i),
+                    stats: QueryStats {
+                        num_calls: 1,
+                        num_instructions: 1_000 * i,
+                        ingress_payload_size: 10,
+                        egress_payload_size: 20,
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn payload_roundtrip() {
+        let payload = test_payload(10);
+        let data = payload.serialize_with_limit(NumBytes::from(1_000_000));
+        assert_eq!(
+            QueryStatsPayload::deserialize(&data).unwrap(),
+            Some(payload)
+        );
+        assert_eq!(QueryStatsPayload::deserialize(&[]).unwrap(), None);
+
+        // A report without statistics is still serialized.
+        let payload = test_payload(0);
+        let data = payload.serialize_with_limit(NumBytes::from(1_000_000));
+        assert_eq!(
+            QueryStatsPayload::deserialize(&data).unwrap(),
+            Some(payload)
+        );
+    }
+
+    #[test]
+    fn serialization_respects_size_limit() {
+        let payload = test_payload(1_000);
+        let max_size = NumBytes::from(1_000);
+        let data = payload.serialize_with_limit(max_size);
+        assert!(!data.is_empty());
+        assert!(data.len() as u64 <= max_size.get());
+
+        let truncated = QueryStatsPayload::deserialize(&data).unwrap().unwrap();
+        assert!(truncated.stats.len() < payload.stats.len());
+        assert_eq!(truncated.stats[..], payload.stats[..truncated.stats.len()]);
+
+        assert!(payload.serialize_with_limit(NumBytes::from(10)).is_empty());
+    }
+
+    #[test]
+    fn epoch_from_height_divides_by_epoch_length() {
+        assert_eq!(epoch_from_height(Height::from(0), 100).get(), 0);
+        assert_eq!(epoch_from_height(Height::from(199), 100).get(), 1);
+        assert_eq!(epoch_from_height(Height::from(200), 100).get(), 2);
+    }
+}
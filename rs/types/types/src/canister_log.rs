@@ -0,0 +1,148 @@
+//! The log buffer of a canister, filled by `ic0.debug_print` and by trap
+//! messages during replicated execution and returned by the management
+//! canister's `fetch_canister_logs` method.
+
+use ic_ic00_types::CanisterLogRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// The maximum total size of the records kept in a canister log. When a new
+/// record does not fit, the oldest records are dropped.
+pub const MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE: usize = 4 * 1024;
+
+/// A bounded FIFO buffer of log records. Every record gets a unique,
+/// monotonically increasing index.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanisterLog {
+    /// The index to assign to the next record.
+    next_idx: u64,
+    /// The records, oldest first.
+    records: VecDeque<CanisterLogRecord>,
+    /// The total `data_size()` of `records`.
+    size: usize,
+}
+
+impl CanisterLog {
+    pub fn new(next_idx: u64, records: Vec<CanisterLogRecord>) -> Self {
+        let size = records.iter().map(|r| r.data_size()).sum();
+        Self {
+            next_idx,
+            records: records.into(),
+            size,
+        }
+    }
+
+    /// Creates an empty log whose records are indexed starting at `next_idx`.
+    pub fn new_with_next_index(next_idx: u64) -> Self {
+        Self {
+            next_idx,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the index that will be assigned to the next record.
+    pub fn next_idx(&self) -> u64 {
+        self.next_idx
+    }
+
+    /// Returns the records in the log, oldest first.
+    pub fn records(&self) -> &VecDeque<CanisterLogRecord> {
+        &self.records
+    }
+
+    /// Returns the total size of the records in the log.
+    pub fn used_space(&self) -> usize {
+        self.size
+    }
+
+    /// Appends a new record, dropping the oldest records if the buffer is
+    /// full. Records larger than the whole buffer are truncated.
+    pub fn add_record(&mut self, timestamp_nanos: u64, content: &[u8]) {
+        let mut record = CanisterLogRecord {
+            idx: self.next_idx,
+            timestamp_nanos,
+            content: vec![],
+        };
+        let max_content_size = MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE - record.data_size();
+        record.content = content[..content.len().min(max_content_size)].to_vec();
+        self.next_idx += 1;
+
+        let record_size = record.data_size();
+        while self.size + record_size > MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE {
+            // Cannot be empty: the record fits into an empty buffer.
+            let oldest = self.records.pop_front().unwrap();
+            self.size -= oldest.data_size();
+        }
+        self.size += record_size;
+        self.records.push_back(record);
+    }
+
+    /// Moves all records of `other` to the end of this log, assigning them
+    /// new indices. `other` is left empty.
+    pub fn append(&mut self, other: &mut CanisterLog) {
+        for record in other.records.drain(..) {
+            self.add_record(record.timestamp_nanos, &record.content);
+        }
+        other.size = 0;
+    }
+
+    /// Removes all records from the log. Indices keep increasing.
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.size = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_are_indexed_sequentially() {
+        let mut log = CanisterLog::new_with_next_index(5);
+        log.add_record(100, b"first");
+        log.add_record(200, b"second");
+        let records: Vec<_> = log.records().iter().cloned().collect();
+        assert_eq!(records[0].idx, 5);
+        assert_eq!(records[0].content, b"first");
+        assert_eq!(records[1].idx, 6);
+        assert_eq!(records[1].timestamp_nanos, 200);
+        assert_eq!(log.next_idx(), 7);
+    }
+
+    #[test]
+    fn oldest_records_are_dropped_when_full() {
+        let mut log = CanisterLog::default();
+        let content = vec![b'x'; 1000];
+        for i in 0..10 {
+            log.add_record(i, &content);
+        }
+        assert!(log.used_space() <= MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE);
+        assert_eq!(log.records().len(), 4);
+        assert_eq!(log.records().front().unwrap().idx, 6);
+        assert_eq!(log.records().back().unwrap().idx, 9);
+    }
+
+    #[test]
+    fn oversized_record_is_truncated() {
+        let mut log = CanisterLog::default();
+        log.add_record(0, b"small");
+        log.add_record(1, &vec![b'x'; 2 * MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE]);
+        assert_eq!(log.records().len(), 1);
+        assert_eq!(log.used_space(), MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn append_reindexes_records() {
+        let mut log = CanisterLog::new_with_next_index(10);
+        log.add_record(0, b"a");
+        let mut delta = CanisterLog::default();
+        delta.add_record(1, b"b");
+        delta.add_record(2, b"c");
+        log.append(&mut delta);
+        let idxs: Vec<_> = log.records().iter().map(|r| r.idx).collect();
+        assert_eq!(idxs, vec![10, 11, 12]);
+        assert!(delta.records().is_empty());
+        assert_eq!(delta.used_space(), 0);
+    }
+}
@@ -124,8 +124,12 @@ impl From<ArtifactChunk> for pb::StateSyncChunkResponse {
         match chunk.artifact_chunk_data {
             ArtifactChunkData::UnitChunkData(artifact) => Self {
                 data: serialize(&artifact).unwrap(),
+                compression: pb::StateSyncChunkCompression::Unspecified as i32,
+            },
+            ArtifactChunkData::SemiStructuredChunkData(chunk_data) => Self {
+                data: chunk_data,
+                compression: pb::StateSyncChunkCompression::Unspecified as i32,
             },
-            ArtifactChunkData::SemiStructuredChunkData(chunk_data) => Self { data: chunk_data },
         }
     }
 }
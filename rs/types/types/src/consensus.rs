@@ -1320,6 +1320,7 @@ impl From<&Block> for pb::Block {
             ingress_payload,
             self_validating_payload,
             canister_http_payload_bytes,
+            query_stats_payload_bytes,
             ecdsa_payload,
         ) = if payload.is_summary() {
             (
@@ -1328,6 +1329,7 @@ impl From<&Block> for pb::Block {
                 None,
                 None,
                 vec![],
+                vec![],
                 payload
                     .as_summary()
                     .ecdsa
@@ -1342,6 +1344,7 @@ impl From<&Block> for pb::Block {
                 Some(pb::IngressPayload::from(&batch.ingress)),
                 Some(pb::SelfValidatingPayload::from(&batch.self_validating)),
                 batch.canister_http.clone(),
+                batch.query_stats.clone(),
                 payload.as_data().ecdsa.as_ref().map(|ecdsa| ecdsa.into()),
             )
         };
@@ -1359,6 +1362,7 @@ impl From<&Block> for pb::Block {
             self_validating_payload,
             canister_http_payload: None,
             canister_http_payload_bytes,
+            query_stats_payload_bytes,
             ecdsa_payload,
             payload_hash: block.payload.get_hash().clone().get().0,
         }
@@ -1390,6 +1394,7 @@ impl TryFrom<pb::Block> for Block {
                 .transpose()?
                 .unwrap_or_default(),
             canister_http: block.canister_http_payload_bytes,
+            query_stats: block.query_stats_payload_bytes,
         };
         let payload = match dkg_payload {
             dkg::Payload::Summary(summary) => {
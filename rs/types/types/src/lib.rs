@@ -67,6 +67,7 @@ pub mod artifact;
 pub mod artifact_kind;
 pub mod batch;
 pub mod canister_http;
+pub mod canister_log;
 pub mod chunkable;
 pub mod consensus;
 pub mod crypto;
@@ -532,6 +533,27 @@ impl MemoryAllocation {
             MemoryAllocation::BestEffort => memory_usage,
         }
     }
+
+    /// Converts `bytes` into a memory allocation that reserves at most
+    /// `max_memory_allocation` bytes.
+    pub fn try_from_bytes(
+        bytes: NumBytes,
+        max_memory_allocation: NumBytes,
+    ) -> Result<Self, InvalidMemoryAllocationError> {
+        if bytes > max_memory_allocation {
+            return Err(InvalidMemoryAllocationError::new(
+                candid::Nat::from(bytes.get()),
+                max_memory_allocation,
+            ));
+        }
+        // A memory allocation of 0 means that the canister's memory growth will be
+        // best-effort.
+        if bytes.get() == 0 {
+            Ok(MemoryAllocation::BestEffort)
+        } else {
+            Ok(MemoryAllocation::Reserved(bytes))
+        }
+    }
 }
 
 impl fmt::Display for MemoryAllocation {
@@ -591,36 +613,40 @@ pub const MAX_STABLE_MEMORY_IN_BYTES: u64 = 64 * GB;
 /// it is public and `u64` (`NumBytes` cannot be used in const expressions).
 pub const MAX_WASM_MEMORY_IN_BYTES: u64 = 4 * GB;
 
+/// The upper limit on the Wasm memory size of canisters using 64-bit memory.
+/// This constant is used by other crates to define other constants, that's why
+/// it is public and `u64` (`NumBytes` cannot be used in const expressions).
+pub const MAX_WASM64_MEMORY_IN_BYTES: u64 = 16 * GB;
+
 const MIN_MEMORY_ALLOCATION: NumBytes = NumBytes::new(0);
+
+/// The upper limit on the memory allocation of a canister.
 pub const MAX_MEMORY_ALLOCATION: NumBytes =
     NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES + MAX_WASM_MEMORY_IN_BYTES);
 
+/// The upper limit on the memory allocation of a canister if canisters may
+/// use a 64-bit Wasm memory.
+pub const MAX_WASM64_MEMORY_ALLOCATION: NumBytes =
+    NumBytes::new(MAX_STABLE_MEMORY_IN_BYTES + MAX_WASM64_MEMORY_IN_BYTES);
+
 impl InvalidMemoryAllocationError {
-    pub fn new(given: candid::Nat) -> Self {
+    pub fn new(given: candid::Nat, max_memory_allocation: NumBytes) -> Self {
         Self {
             min: candid::Nat::from(MIN_MEMORY_ALLOCATION.get()),
-            max: candid::Nat::from(MAX_MEMORY_ALLOCATION.get()),
+            max: candid::Nat::from(max_memory_allocation.get()),
             given,
         }
     }
 }
 
+/// Accepts memory allocations up to [`MAX_WASM64_MEMORY_ALLOCATION`], which is
+/// the upper limit for every configuration. Use
+/// [`MemoryAllocation::try_from_bytes`] to enforce the limit of a subnet.
 impl TryFrom<NumBytes> for MemoryAllocation {
     type Error = InvalidMemoryAllocationError;
 
     fn try_from(bytes: NumBytes) -> Result<Self, Self::Error> {
-        if bytes > MAX_MEMORY_ALLOCATION {
-            return Err(InvalidMemoryAllocationError::new(candid::Nat::from(
-                bytes.get(),
-            )));
-        }
-        // A memory allocation of 0 means that the canister's memory growth will be
-        // best-effort.
-        if bytes.get() == 0 {
-            Ok(MemoryAllocation::BestEffort)
-        } else {
-            Ok(MemoryAllocation::Reserved(bytes))
-        }
+        Self::try_from_bytes(bytes, MAX_WASM64_MEMORY_ALLOCATION)
     }
 }
 
@@ -10,10 +10,10 @@ mod webauthn;
 
 pub use self::http::{
     Authentication, Certificate, CertificateDelegation, Delegation, HasCanisterId, HttpCallContent,
-    HttpCanisterUpdate, HttpQueryContent, HttpQueryResponse, HttpQueryResponseReply, HttpReadState,
-    HttpReadStateContent, HttpReadStateResponse, HttpReply, HttpRequest, HttpRequestContent,
-    HttpRequestEnvelope, HttpRequestError, HttpStatusResponse, HttpUserQuery, RawHttpRequestVal,
-    ReplicaHealthStatus, SignedDelegation,
+    HttpCallV3Response, HttpCanisterUpdate, HttpQueryContent, HttpQueryResponse,
+    HttpQueryResponseReply, HttpReadState, HttpReadStateContent, HttpReadStateResponse, HttpReply,
+    HttpRequest, HttpRequestContent, HttpRequestEnvelope, HttpRequestError, HttpStatusResponse,
+    HttpUserQuery, RawHttpRequestVal, ReplicaHealthStatus, SignedDelegation,
 };
 use crate::{user_id_into_protobuf, user_id_try_from_protobuf, Cycles, Funds, NumBytes, UserId};
 pub use blob::Blob;
@@ -27,6 +27,7 @@ pub use ingress_messages::{
 };
 pub use inter_canister::{
     CallContextId, CallbackId, Payload, RejectContext, Request, RequestOrResponse, Response,
+    NO_DEADLINE,
 };
 pub use message_id::{MessageId, MessageIdError, EXPECTED_MESSAGE_ID_LENGTH};
 pub use query::{AnonymousQuery, AnonymousQueryResponse, AnonymousQueryResponseReply, UserQuery};
@@ -619,6 +619,20 @@ pub struct HttpReadStateResponse {
     pub certificate: Blob,
 }
 
+/// The response to a synchronous `call` request (`/api/v3/canister/<id>/call`)
+/// once the message has been executed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "status")]
+pub enum HttpCallV3Response {
+    /// The message was executed and `certificate` contains its certified
+    /// `request_status`, which may be `replied` or `rejected`.
+    Replied {
+        /// The CBOR-encoded `Certificate`.
+        certificate: Blob,
+    },
+}
+
 /// A `Certificate` as defined in `<https://internetcomputer.org/docs/current/references/ic-interface-spec#certificate>`
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Certificate {
@@ -555,8 +555,8 @@ mod cbor_serialization {
 
     use crate::messages::http::btreemap;
     use crate::messages::{
-        Blob, Delegation, HttpQueryResponse, HttpQueryResponseReply, HttpStatusResponse,
-        ReplicaHealthStatus, SignedDelegation,
+        Blob, Delegation, HttpCallV3Response, HttpQueryResponse, HttpQueryResponseReply,
+        HttpStatusResponse, ReplicaHealthStatus, SignedDelegation,
     };
     use crate::{time::UNIX_EPOCH, AmountOf};
     use pretty_assertions::assert_eq;
@@ -619,6 +619,19 @@ mod cbor_serialization {
         );
     }
 
+    #[test]
+    fn encoding_call_v3_response() {
+        assert_cbor_ser_equal(
+            &HttpCallV3Response::Replied {
+                certificate: Blob(b"some_certificate".to_vec()),
+            },
+            Value::Map(btreemap! {
+                text("status") => text("replied"),
+                text("certificate") => bytes(b"some_certificate"),
+            }),
+        );
+    }
+
     #[test]
     fn encoding_status_without_root_key() {
         assert_cbor_ser_equal(
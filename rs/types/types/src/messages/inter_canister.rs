@@ -1,8 +1,12 @@
-use crate::{ingress::WasmResult, CanisterId, CountBytes, Cycles, Funds, NumBytes};
+use crate::{
+    ingress::WasmResult, time::CoarseTime, CanisterId, CountBytes, Cycles, Funds, NumBytes, Time,
+};
 use ic_error_types::{RejectCode, TryFromError, UserError};
 use ic_ic00_types::{
-    CanisterIdRecord, CanisterInfoRequest, InstallCodeArgs, Method, Payload as _,
-    ProvisionalTopUpCanisterArgs, SetControllerArgs, UpdateSettingsArgs,
+    CanisterIdRecord, CanisterInfoRequest, ClearChunkStoreArgs, DeleteCanisterSnapshotArgs,
+    InstallChunkedCodeArgs, InstallCodeArgs, ListCanisterSnapshotArgs, LoadCanisterSnapshotArgs,
+    Method, Payload as _, ProvisionalTopUpCanisterArgs, SetControllerArgs, StoredChunksArgs,
+    TakeCanisterSnapshotArgs, UpdateSettingsArgs, UploadChunkArgs,
 };
 use ic_protobuf::{
     proxy::{try_from_option_field, ProxyDecodeError},
@@ -34,6 +38,10 @@ pub enum CallContextIdTag {}
 /// Identifies an incoming call.
 pub type CallContextId = Id<CallContextIdTag, u64>;
 
+/// The deadline of guaranteed response messages, i.e. of messages without a
+/// deadline.
+pub const NO_DEADLINE: CoarseTime = CoarseTime::from_secs_since_unix_epoch(0);
+
 /// Canister-to-canister request message.
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Request {
@@ -44,6 +52,11 @@ pub struct Request {
     pub method_name: String,
     #[serde(with = "serde_bytes")]
     pub method_payload: Vec<u8>,
+    /// The deadline of a best-effort call, after which the request may be
+    /// dropped and the caller given a `SYS_UNKNOWN` reject response.
+    /// `NO_DEADLINE` for guaranteed response calls.
+    #[serde(default)]
+    pub deadline: CoarseTime,
 }
 
 impl Request {
@@ -62,6 +75,17 @@ impl Request {
         &self.method_payload
     }
 
+    /// Returns `true` if this is the request of a best-effort call.
+    pub fn is_best_effort(&self) -> bool {
+        self.deadline != NO_DEADLINE
+    }
+
+    /// Returns `true` if this is a best-effort request whose deadline is
+    /// before `current_time`.
+    pub fn has_expired_deadline(&self, current_time: Time) -> bool {
+        self.is_best_effort() && Time::from(self.deadline) < current_time
+    }
+
     /// Returns the size of the user-controlled part of this `Request`,
     /// in bytes.
     pub fn payload_size_bytes(&self) -> NumBytes {
@@ -104,6 +128,50 @@ impl Request {
                     Err(_) => None,
                 }
             }
+            Ok(Method::TakeCanisterSnapshot) => {
+                match TakeCanisterSnapshotArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.get_canister_id()),
+                    Err(_) => None,
+                }
+            }
+            Ok(Method::LoadCanisterSnapshot) => {
+                match LoadCanisterSnapshotArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.get_canister_id()),
+                    Err(_) => None,
+                }
+            }
+            Ok(Method::ListCanisterSnapshots) => {
+                match ListCanisterSnapshotArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.get_canister_id()),
+                    Err(_) => None,
+                }
+            }
+            Ok(Method::DeleteCanisterSnapshot) => {
+                match DeleteCanisterSnapshotArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.get_canister_id()),
+                    Err(_) => None,
+                }
+            }
+            Ok(Method::UploadChunk) => match UploadChunkArgs::decode(&self.method_payload) {
+                Ok(record) => Some(record.get_canister_id()),
+                Err(_) => None,
+            },
+            Ok(Method::ClearChunkStore) => {
+                match ClearChunkStoreArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.get_canister_id()),
+                    Err(_) => None,
+                }
+            }
+            Ok(Method::StoredChunks) => match StoredChunksArgs::decode(&self.method_payload) {
+                Ok(record) => Some(record.get_canister_id()),
+                Err(_) => None,
+            },
+            Ok(Method::InstallChunkedCode) => {
+                match InstallChunkedCodeArgs::decode(&self.method_payload) {
+                    Ok(record) => Some(record.target_canister_id()),
+                    Err(_) => None,
+                }
+            }
             Ok(Method::CreateCanister)
             | Ok(Method::SetupInitialDKG)
             | Ok(Method::HttpRequest)
@@ -111,6 +179,7 @@ impl Request {
             | Ok(Method::ECDSAPublicKey)
             | Ok(Method::SignWithECDSA)
             | Ok(Method::ComputeInitialEcdsaDealings)
+            | Ok(Method::FetchCanisterLogs)
             | Ok(Method::BitcoinGetBalance)
             | Ok(Method::BitcoinGetUtxos)
             | Ok(Method::BitcoinSendTransaction)
@@ -274,9 +343,18 @@ pub struct Response {
     pub originator_reply_callback: CallbackId,
     pub refund: Cycles,
     pub response_payload: Payload,
+    /// The deadline of the request this is a response to. `NO_DEADLINE` for
+    /// guaranteed responses.
+    #[serde(default)]
+    pub deadline: CoarseTime,
 }
 
 impl Response {
+    /// Returns `true` if this is the response to a best-effort call.
+    pub fn is_best_effort(&self) -> bool {
+        self.deadline != NO_DEADLINE
+    }
+
     /// Returns the size in bytes of this `Response`'s payload.
     pub fn payload_size_bytes(&self) -> NumBytes {
         self.response_payload.size_bytes()
@@ -385,6 +463,7 @@ impl From<&Request> for pb_queues::Request {
             method_name: req.method_name.clone(),
             method_payload: req.method_payload.clone(),
             cycles_payment: Some((req.payment).into()),
+            deadline_seconds: req.deadline.as_secs_since_unix_epoch(),
         }
     }
 }
@@ -408,6 +487,7 @@ impl TryFrom<pb_queues::Request> for Request {
             payment,
             method_name: req.method_name,
             method_payload: req.method_payload,
+            deadline: CoarseTime::from_secs_since_unix_epoch(req.deadline_seconds),
         })
     }
 }
@@ -450,6 +530,7 @@ impl From<&Response> for pb_queues::Response {
             refund: Some((&Funds::new(rep.refund)).into()),
             response_payload: Some(p),
             cycles_refund: Some((rep.refund).into()),
+            deadline_seconds: rep.deadline.as_secs_since_unix_epoch(),
         }
     }
 }
@@ -480,6 +561,7 @@ impl TryFrom<pb_queues::Response> for Response {
             originator_reply_callback: rep.originator_reply_callback.into(),
             refund,
             response_payload,
+            deadline: CoarseTime::from_secs_since_unix_epoch(rep.deadline_seconds),
         })
     }
 }
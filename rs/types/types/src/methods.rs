@@ -1,7 +1,7 @@
 //! This module contains a collection of types and structs that define the
 //! various types of methods in the IC.
 
-use crate::{messages::CallContextId, Cycles};
+use crate::{messages::CallContextId, time::CoarseTime, Cycles};
 use ic_base_types::CanisterId;
 use ic_protobuf::proxy::{try_from_option_field, ProxyDecodeError};
 use ic_protobuf::state::{canister_state_bits::v1 as pb, queues::v1::Cycles as PbCycles};
@@ -107,6 +107,9 @@ impl From<&WasmMethod> for pb::WasmMethod {
                     SystemMethod::CanisterHeartbeat => PbSystemMethod::CanisterHeartbeat,
                     SystemMethod::Empty => PbSystemMethod::Empty,
                     SystemMethod::CanisterGlobalTimer => PbSystemMethod::CanisterGlobalTimer,
+                    SystemMethod::CanisterOnLowWasmMemory => {
+                        PbSystemMethod::CanisterOnLowWasmMemory
+                    }
                 } as i32)),
             },
         }
@@ -142,6 +145,9 @@ impl TryFrom<pb::WasmMethod> for WasmMethod {
                     PbSystemMethod::CanisterHeartbeat => SystemMethod::CanisterHeartbeat,
                     PbSystemMethod::Empty => SystemMethod::Empty,
                     PbSystemMethod::CanisterGlobalTimer => SystemMethod::CanisterGlobalTimer,
+                    PbSystemMethod::CanisterOnLowWasmMemory => {
+                        SystemMethod::CanisterOnLowWasmMemory
+                    }
                 }))
             }
         }
@@ -166,6 +172,9 @@ pub enum SystemMethod {
     CanisterHeartbeat,
     /// A system method that is run after a specified time.
     CanisterGlobalTimer,
+    /// A system method that is run when the canister's Wasm memory gets close
+    /// to its Wasm memory limit.
+    CanisterOnLowWasmMemory,
     /// This is introduced as temporary scaffolding to aid in construction of
     /// the initial ExecutionState. This isn't used to execute any actual wasm
     /// but as a way to get to the wasm embedder from execution. Eventually, we
@@ -186,6 +195,7 @@ impl TryFrom<&str> for SystemMethod {
             "canister_inspect_message" => Ok(SystemMethod::CanisterInspectMessage),
             "canister_heartbeat" => Ok(SystemMethod::CanisterHeartbeat),
             "canister_global_timer" => Ok(SystemMethod::CanisterGlobalTimer),
+            "canister_on_low_wasm_memory" => Ok(SystemMethod::CanisterOnLowWasmMemory),
             "empty" => Ok(SystemMethod::Empty),
             _ => Err(format!("Cannot convert {} to SystemMethod.", value)),
         }
@@ -203,6 +213,7 @@ impl fmt::Display for SystemMethod {
             Self::CanisterHeartbeat => write!(f, "canister_heartbeat"),
             Self::Empty => write!(f, "empty"),
             Self::CanisterGlobalTimer => write!(f, "canister_global_timer"),
+            Self::CanisterOnLowWasmMemory => write!(f, "canister_on_low_wasm_memory"),
         }
     }
 }
@@ -211,11 +222,11 @@ impl fmt::Display for SystemMethod {
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WasmClosure {
     pub func_idx: u32,
-    pub env: u32,
+    pub env: u64,
 }
 
 impl WasmClosure {
-    pub fn new(func_idx: u32, env: u32) -> Self {
+    pub fn new(func_idx: u32, env: u64) -> Self {
         Self { func_idx, env }
     }
 }
@@ -248,6 +259,9 @@ pub struct Callback {
     /// An optional closure to be executed if the execution of `on_reply` or
     /// `on_reject` traps.
     pub on_cleanup: Option<WasmClosure>,
+    /// The deadline of the call, `NO_DEADLINE` for guaranteed response calls.
+    #[serde(default)]
+    pub deadline: CoarseTime,
 }
 
 impl Callback {
@@ -261,6 +275,7 @@ impl Callback {
         on_reply: WasmClosure,
         on_reject: WasmClosure,
         on_cleanup: Option<WasmClosure>,
+        deadline: CoarseTime,
     ) -> Self {
         Self {
             call_context_id,
@@ -272,6 +287,7 @@ impl Callback {
             on_reply,
             on_reject,
             on_cleanup,
+            deadline,
         }
     }
 }
@@ -307,6 +323,7 @@ impl From<&Callback> for pb::Callback {
                 func_idx: on_cleanup.func_idx,
                 env: on_cleanup.env,
             }),
+            deadline_seconds: item.deadline.as_secs_since_unix_epoch(),
         }
     }
 }
@@ -351,6 +368,7 @@ impl TryFrom<pb::Callback> for Callback {
                 func_idx: on_cleanup.func_idx,
                 env: on_cleanup.env,
             }),
+            deadline: CoarseTime::from_secs_since_unix_epoch(value.deadline_seconds),
         })
     }
 }
@@ -303,3 +303,41 @@ pub fn expiry_time_from_now() -> Time {
 
     UNIX_EPOCH + (since_epoch + MAX_INGRESS_TTL - PERMITTED_DRIFT)
 }
+
+/// Time since UNIX_EPOCH, in seconds. A more compact representation of
+/// [`Time`] for places where second granularity is enough, such as message
+/// deadlines.
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct CoarseTime(u32);
+
+impl CoarseTime {
+    pub const fn from_secs_since_unix_epoch(secs: u32) -> Self {
+        Self(secs)
+    }
+
+    pub fn as_secs_since_unix_epoch(self) -> u32 {
+        self.0
+    }
+
+    /// Rounds `time` down to the previous full second, saturating at
+    /// `u32::MAX` seconds.
+    pub fn floor(time: Time) -> Self {
+        Self(time.as_secs_since_unix_epoch().min(u32::MAX as u64) as u32)
+    }
+
+    /// Rounds `time` up to the next full second, saturating at `u32::MAX`
+    /// seconds.
+    pub fn ceil(time: Time) -> Self {
+        let secs = (time.as_nanos_since_unix_epoch() + NANOS_PER_SEC - 1) / NANOS_PER_SEC;
+        Self(secs.min(u32::MAX as u64) as u32)
+    }
+}
+
+impl From<CoarseTime> for Time {
+    fn from(coarse: CoarseTime) -> Self {
+        Time::from_nanos_since_unix_epoch(coarse.0 as u64 * NANOS_PER_SEC)
+    }
+}
@@ -122,3 +122,26 @@ fn should_convert_from_system_time_and_back() {
     let back: SystemTime = time.into();
     assert_eq!(system_time, back);
 }
+
+mod coarse_time {
+    use crate::time::{CoarseTime, NANOS_PER_SEC};
+    use crate::Time;
+
+    #[test]
+    fn should_round_down_and_up() {
+        let time = Time::from_nanos_since_unix_epoch(7 * NANOS_PER_SEC + 1);
+        assert_eq!(CoarseTime::floor(time).as_secs_since_unix_epoch(), 7);
+        assert_eq!(CoarseTime::ceil(time).as_secs_since_unix_epoch(), 8);
+
+        let exact = Time::from_nanos_since_unix_epoch(7 * NANOS_PER_SEC);
+        assert_eq!(CoarseTime::floor(exact), CoarseTime::ceil(exact));
+        assert_eq!(Time::from(CoarseTime::floor(exact)), exact);
+    }
+
+    #[test]
+    fn should_saturate() {
+        let time = Time::from_nanos_since_unix_epoch(u64::MAX);
+        assert_eq!(CoarseTime::floor(time).as_secs_since_unix_epoch(), u32::MAX);
+        assert_eq!(CoarseTime::ceil(time).as_secs_since_unix_epoch(), u32::MAX);
+    }
+}